@@ -5,6 +5,9 @@ use {
         makepad_platform::audio::*,
         makepad_platform::midi::*,
         audio_traits::*,
+        offline_render::*,
+        parameter::*,
+        preset::*,
     },
     std::any::TypeId,
     std::sync::{Arc, Mutex},
@@ -18,6 +21,7 @@ live_design!{
 pub enum FromUI {
     AllNotesOff,
     MidiData(MidiData),
+    Param(ParamChange),
     NewRoot(Box<dyn AudioGraphNode + Send>),
     DisplayAudio(AudioBuffer),
 }
@@ -36,6 +40,9 @@ pub struct AudioGraph {
     #[live] root: AudioComponentRef,
     #[rust] from_ui: FromUISender<FromUI>,
     #[rust] to_ui: ToUIReceiver<ToUIDisplayMsg>,
+    #[rust] cc_mappings: Vec<CcMapping>,
+    // the parameter that gets bound to the next controller that moves
+    #[rust] learn_cc: Option<ParamInfo>,
 }
 
 impl LiveHook for AudioGraph {
@@ -66,14 +73,119 @@ impl AudioGraph {
         None
     }
     
-    pub fn send_midi_data(&self, data: MidiData) {
+    // controllers mapped to a parameter set it instead of reaching the graph as midi
+    pub fn send_midi_data(&mut self, data: MidiData) {
+        if let MidiEvent::ControlChange(cc) = data.decode() {
+            if let Some(param) = self.learn_cc.take() {
+                self.map_cc(cc.channel, cc.param, param);
+            }
+            if let Some(mapping) = self.cc_mappings.iter().find( | m | m.channel == cc.channel && m.controller == cc.param) {
+                let value = mapping.param.denormalize(cc.value as f64 / 127.0);
+                self.set_param(mapping.param.id, value);
+                return
+            }
+        }
         let _ = self.from_ui.send(FromUI::MidiData(data));
     }
     
+    // passes everything waiting on a midi input to the graph, call it from handle_event
+    pub fn forward_midi_input(&mut self, input: &mut MidiInput) {
+        while let Some((_port, data)) = input.receive() {
+            self.send_midi_data(data);
+        }
+    }
+    
     
     pub fn all_notes_off(&self) {
         let _ =  self.from_ui.send(FromUI::AllNotesOff);
     }
+    
+    pub fn params(&self) -> Vec<ParamInfo> {
+        self.root.params()
+    }
+    
+    pub fn set_param(&self, id: LiveId, value: f64) {
+        let _ = self.from_ui.send(FromUI::Param(ParamChange::new(id, value)));
+    }
+    
+    // binds the next controller that moves to the parameter
+    pub fn learn_cc(&mut self, id: LiveId) {
+        self.learn_cc = self.params().into_iter().find( | info | info.id == id);
+    }
+    
+    pub fn is_learning_cc(&self) -> bool {
+        self.learn_cc.is_some()
+    }
+    
+    pub fn cancel_learn_cc(&mut self) {
+        self.learn_cc = None;
+    }
+    
+    pub fn map_cc(&mut self, channel: u8, controller: u8, param: ParamInfo) {
+        self.cc_mappings.retain( | m | m.param.id != param.id && (m.channel, m.controller) != (channel, controller));
+        self.cc_mappings.push(CcMapping {channel, controller, param});
+    }
+    
+    pub fn unmap_cc(&mut self, id: LiveId) {
+        self.cc_mappings.retain( | m | m.param.id != id);
+    }
+    
+    pub fn cc_mappings(&self) -> &[CcMapping] {
+        &self.cc_mappings
+    }
+    
+    // the live state of the whole graph
+    pub fn read_preset(&self, name: &str) -> Preset {
+        let mut nodes = Vec::new();
+        self.root.preset_read_to(live_id!(root), &mut nodes);
+        Preset::new(name, nodes)
+    }
+    
+    pub fn apply_preset(&mut self, cx: &mut Cx, preset: &Preset) {
+        if !preset.nodes.is_empty() {
+            self.root.preset_apply(cx, &preset.nodes);
+        }
+    }
+    
+    // A/B blend of two presets read from this graph, see Preset::morph
+    pub fn morph_presets(&mut self, cx: &mut Cx, a: &Preset, b: &Preset, amount: f64) {
+        self.apply_preset(cx, &Preset::morph(a, b, amount));
+    }
+    
+    // the live state of the first component of a type, like a single synth in a larger graph
+    pub fn read_preset_of<T: 'static + AudioComponent>(&mut self, name: &str) -> Option<Preset> {
+        let child = self.root.audio_query(&AudioQuery::TypeId(TypeId::of::<T>()), &mut None).into_found()?;
+        let mut nodes = Vec::new();
+        child.preset_read_to(live_id!(root), &mut nodes);
+        Some(Preset::new(name, nodes))
+    }
+    
+    pub fn apply_preset_to<T: 'static + AudioComponent>(&mut self, cx: &mut Cx, preset: &Preset) -> bool {
+        if let Some(child) = self.root.audio_query(&AudioQuery::TypeId(TypeId::of::<T>()), &mut None).into_found() {
+            if !preset.nodes.is_empty() {
+                child.preset_apply(cx, &preset.nodes);
+            }
+            return true
+        }
+        false
+    }
+    
+    // bounces the current root to a buffer without touching the audio device. the offline node
+    // gets channels of its own, so the live graph keeps playing and listening to the UI
+    pub fn render_offline(&mut self, cx: &mut Cx, render: &OfflineRender, events: &[TimedMidiData], duration: f64) -> Option<AudioBuffer> {
+        let root = self.root.as_mut()?;
+        let mut graph_node = root.get_offline_graph_node(cx);
+        Some(render.render(&mut *graph_node, events, duration))
+    }
+    
+    pub fn render_offline_to_wav(&mut self, cx: &mut Cx, render: &OfflineRender, events: &[TimedMidiData], duration: f64, format: WavFormat, path: &std::path::Path) -> std::io::Result<()> {
+        if let Some(buffer) = self.render_offline(cx, render, events, duration) {
+            write_wav(path, &buffer, render.sample_rate as u32, format)
+        }
+        else {
+            Err(std::io::Error::new(std::io::ErrorKind::NotFound, "AudioGraph has no root component"))
+        }
+    }
      
     fn render_to_output_buffer(node: &mut Node, to_ui: &ToUISender<ToUIDisplayMsg>, info: AudioInfo, output: &mut AudioBuffer) {
         
@@ -93,6 +205,11 @@ impl AudioGraph {
                     }
                     // }
                 }
+                FromUI::Param(change) => {
+                    if let Some(root) = node.root.as_mut() {
+                        root.set_param(change);
+                    }
+                }
                 FromUI::AllNotesOff=>{
                     if let Some(root) = node.root.as_mut() {
                         root.all_notes_off();
@@ -5,6 +5,7 @@ use {
         makepad_platform::audio::*,
         makepad_platform::midi::*,
         makepad_platform::thread::*,
+        parameter::*,
     }
 };
 
@@ -20,7 +21,21 @@ pub trait AudioComponent: LiveApply {
     fn type_id(&self) -> LiveType where Self: 'static {LiveType::of::<Self>()}
     fn handle_event_with(&mut self, _cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction));
     fn get_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send>;
+    // a node for offline rendering, it leaves the live node's channels and shared state alone
+    fn get_offline_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send>;
     fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult;
+    // named ports as seen by a Router, in the order of the buffers passed to render_to_audio_buffer
+    fn input_ports(&self) -> Vec<LiveId> {vec![live_id!(main)]}
+    fn output_ports(&self) -> Vec<LiveId> {vec![live_id!(main)]}
+    // the automatable parameters, containers list those of their children
+    fn params(&self) -> Vec<ParamInfo> {Vec::new()}
+    // the state a preset keeps, pushed as one node named id. components push their settings,
+    // containers their children, the rest push nothing
+    fn preset_read_to(&self, _id: LiveId, _out: &mut Vec<LiveNode>) {}
+    // applies what preset_read_to pushed, nodes start at that node
+    fn preset_apply(&mut self, cx: &mut Cx, nodes: &[LiveNode]) {
+        self.apply_over(cx, nodes);
+    }
 }
 
 pub trait AudioGraphNode {
@@ -33,6 +48,8 @@ pub trait AudioGraphNode {
         inputs: &[&AudioBuffer],
         display: &mut DisplayAudioGraph
     );
+    // parameters reach every node like midi does, nodes ignore the ones they don't declare
+    fn set_param(&mut self, _change: ParamChange) {}
 }
 
 generate_ref_cast_api!(AudioComponent);
@@ -127,6 +144,22 @@ impl AudioComponentRef {
             AudioResult::not_found()
         }
     }
+    
+    pub fn params(&self) -> Vec<ParamInfo> {
+        self.0.as_ref().map_or(Vec::new(), | inner | inner.params())
+    }
+    
+    pub fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        if let Some(inner) = &self.0 {
+            inner.preset_read_to(id, out)
+        }
+    }
+    
+    pub fn preset_apply(&mut self, cx: &mut Cx, nodes: &[LiveNode]) {
+        if let Some(inner) = &mut self.0 {
+            inner.preset_apply(cx, nodes)
+        }
+    }
 }
 
 impl LiveHook for AudioComponentRef {}
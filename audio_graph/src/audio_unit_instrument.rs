@@ -123,6 +123,13 @@ impl AudioComponent for AudioUnitInstrument {
         })
     }
     
+    fn get_offline_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        Box::new(Node {
+            from_ui: self.from_ui.detached_receiver(),
+            audio_unit: if let Some(audio_unit) = &self.audio_unit {Some(audio_unit.clone())}else {None}
+        })
+    }
+    
     fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
         // ui EVENT
         while let Ok(to_ui) = self.to_ui.try_recv() {
@@ -0,0 +1,117 @@
+pub mod wav;
+pub mod flac;
+pub mod ogg;
+pub mod vorbis;
+
+use {
+    std::io::{Read, Seek, SeekFrom, Cursor},
+    std::path::Path,
+    crate::makepad_platform::audio::*,
+    self::{
+        wav::WavDecoder,
+        flac::FlacDecoder,
+        vorbis::VorbisDecoder,
+    }
+};
+
+// Pure rust decoders for WAV, FLAC and Ogg Vorbis. They all decode block by block
+// so files can be streamed from disk, decode_audio is the load-everything shortcut.
+
+#[derive(Debug)]
+pub enum DecodeError {
+    UnknownFormat,
+    Unsupported(String),
+    Corrupt(String),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for DecodeError {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == std::io::ErrorKind::UnexpectedEof {
+            Self::Corrupt("unexpected end of stream".to_string())
+        }
+        else {
+            Self::Io(err)
+        }
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::UnknownFormat => write!(f, "unknown audio format"),
+            Self::Unsupported(what) => write!(f, "unsupported: {}", what),
+            Self::Corrupt(what) => write!(f, "corrupt stream: {}", what),
+            Self::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+pub trait ReadSeek: Read + Seek + Send {}
+impl<T> ReadSeek for T where T: Read + Seek + Send {}
+
+pub trait AudioDecoder: Send {
+    fn sample_rate(&self) -> f64;
+    fn channel_count(&self) -> usize;
+    // total length in frames if the container tells us
+    fn frame_count(&self) -> Option<usize>;
+    // the next block of decoded audio, None at the end of the stream
+    fn decode_block(&mut self) -> Result<Option<AudioBuffer>, DecodeError>;
+    fn rewind(&mut self) -> Result<(), DecodeError>;
+}
+
+pub struct DecodedAudio {
+    pub buffer: AudioBuffer,
+    pub sample_rate: f64,
+}
+
+pub fn open_decoder<R: ReadSeek + 'static>(mut reader: R) -> Result<Box<dyn AudioDecoder>, DecodeError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    reader.seek(SeekFrom::Start(0))?;
+    match &magic {
+        b"RIFF" => Ok(Box::new(WavDecoder::new(reader)?)),
+        b"fLaC" => Ok(Box::new(FlacDecoder::new(reader)?)),
+        b"OggS" => Ok(Box::new(VorbisDecoder::new(reader)?)),
+        _ => Err(DecodeError::UnknownFormat)
+    }
+}
+
+pub fn open_decoder_file(path: &Path) -> Result<Box<dyn AudioDecoder>, DecodeError> {
+    let file = std::fs::File::open(path)?;
+    open_decoder(std::io::BufReader::new(file))
+}
+
+pub fn decode_all(decoder: &mut dyn AudioDecoder) -> Result<DecodedAudio, DecodeError> {
+    let channel_count = decoder.channel_count();
+    let mut channels = vec![Vec::new(); channel_count];
+    if let Some(frame_count) = decoder.frame_count() {
+        for channel in &mut channels {
+            channel.reserve(frame_count);
+        }
+    }
+    while let Some(block) = decoder.decode_block()? {
+        for (c, channel) in channels.iter_mut().enumerate() {
+            channel.extend_from_slice(block.channel(c));
+        }
+    }
+    let frame_count = channels.first().map( | c | c.len()).unwrap_or(0);
+    let mut data = Vec::with_capacity(frame_count * channel_count);
+    for channel in channels {
+        data.extend_from_slice(&channel);
+    }
+    Ok(DecodedAudio {
+        buffer: AudioBuffer::from_data(data, channel_count.max(1)),
+        sample_rate: decoder.sample_rate()
+    })
+}
+
+pub fn decode_audio(data: Vec<u8>) -> Result<DecodedAudio, DecodeError> {
+    let mut decoder = open_decoder(Cursor::new(data))?;
+    decode_all(&mut *decoder)
+}
+
+pub fn decode_audio_file(path: &Path) -> Result<DecodedAudio, DecodeError> {
+    let mut decoder = open_decoder_file(path)?;
+    decode_all(&mut *decoder)
+}
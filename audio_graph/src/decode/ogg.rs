@@ -0,0 +1,112 @@
+use {
+    std::io::SeekFrom,
+    super::{DecodeError, ReadSeek},
+};
+
+// Reassembles the packets of the first logical bitstream in an Ogg file.
+
+pub struct OggPacket {
+    pub data: Vec<u8>,
+    // granule position of the page this packet ended on, if it is the last packet ending there
+    pub granule: Option<u64>,
+    pub is_last: bool,
+}
+
+pub struct OggReader<R> {
+    reader: R,
+    serial: Option<u32>,
+    segments: Vec<u8>,
+    segment_index: usize,
+    page_data: Vec<u8>,
+    page_offset: usize,
+    page_granule: u64,
+    page_is_last: bool,
+    partial: Vec<u8>,
+}
+
+impl<R: ReadSeek> OggReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            serial: None,
+            segments: Vec::new(),
+            segment_index: 0,
+            page_data: Vec::new(),
+            page_offset: 0,
+            page_granule: 0,
+            page_is_last: false,
+            partial: Vec::new(),
+        }
+    }
+
+    pub fn rewind(&mut self) -> Result<(), DecodeError> {
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.segments.clear();
+        self.segment_index = 0;
+        self.page_data.clear();
+        self.page_offset = 0;
+        self.page_is_last = false;
+        self.partial.clear();
+        Ok(())
+    }
+
+    // returns false at the end of the file
+    fn read_page(&mut self) -> Result<bool, DecodeError> {
+        loop {
+            let mut header = [0u8; 27];
+            match self.reader.read_exact(&mut header) {
+                Ok(_) => (),
+                Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+                Err(err) => return Err(err.into())
+            }
+            if &header[0..4] != b"OggS" || header[4] != 0 {
+                return Err(DecodeError::Corrupt("bad ogg page header".to_string()))
+            }
+            let header_type = header[5];
+            let granule = u64::from_le_bytes(header[6..14].try_into().unwrap());
+            let serial = u32::from_le_bytes(header[14..18].try_into().unwrap());
+            let segment_count = header[26] as usize;
+            let mut segments = vec![0u8; segment_count];
+            self.reader.read_exact(&mut segments)?;
+            let page_size: usize = segments.iter().map( | s | *s as usize).sum();
+            if *self.serial.get_or_insert(serial) != serial {
+                // another logical stream multiplexed in, skip its pages
+                self.reader.seek(SeekFrom::Current(page_size as i64))?;
+                continue;
+            }
+            let mut data = std::mem::take(&mut self.page_data);
+            data.resize(page_size, 0);
+            self.reader.read_exact(&mut data)?;
+            self.page_data = data;
+            self.page_offset = 0;
+            self.segments = segments;
+            self.segment_index = 0;
+            self.page_granule = granule;
+            self.page_is_last = header_type & 4 != 0;
+            return Ok(true)
+        }
+    }
+
+    pub fn next_packet(&mut self) -> Result<Option<OggPacket>, DecodeError> {
+        loop {
+            while self.segment_index < self.segments.len() {
+                let size = self.segments[self.segment_index] as usize;
+                self.partial.extend_from_slice(&self.page_data[self.page_offset..self.page_offset + size]);
+                self.page_offset += size;
+                self.segment_index += 1;
+                // a segment shorter than 255 ends the packet
+                if size < 255 {
+                    let ends_page = !self.segments[self.segment_index..].iter().any( | s | *s < 255);
+                    return Ok(Some(OggPacket {
+                        data: std::mem::take(&mut self.partial),
+                        granule: if ends_page {Some(self.page_granule)} else {None},
+                        is_last: ends_page && self.page_is_last,
+                    }))
+                }
+            }
+            if self.page_is_last || !self.read_page()? {
+                return Ok(None)
+            }
+        }
+    }
+}
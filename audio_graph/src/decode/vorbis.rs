@@ -0,0 +1,1247 @@
+use {
+    std::f32::consts::PI,
+    crate::makepad_platform::audio::*,
+    super::{
+        AudioDecoder,
+        DecodeError,
+        ReadSeek,
+        ogg::OggReader,
+    },
+};
+
+// Ogg Vorbis I decoder, follows the structure of the specification:
+// headers, then per packet floors, residues, inverse coupling, imdct and overlap-add.
+
+// Vorbis reads its bits least significant first, None means end of packet
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {data, pos: 0}
+    }
+
+    fn read(&mut self, count: u32) -> Option<u32> {
+        if count == 0 {
+            return Some(0)
+        }
+        if self.pos + count as usize > self.data.len() * 8 {
+            self.pos = self.data.len() * 8;
+            return None
+        }
+        let mut value = 0u64;
+        let mut done = 0;
+        while done < count {
+            let byte = self.data[self.pos >> 3] as u64;
+            let shift = (self.pos & 7) as u32;
+            let take = (8 - shift).min(count - done);
+            value |= ((byte >> shift) & ((1 << take) - 1)) << done;
+            done += take;
+            self.pos += take as usize;
+        }
+        Some(value as u32)
+    }
+
+    fn read_flag(&mut self) -> Option<bool> {
+        self.read(1).map( | v | v == 1)
+    }
+}
+
+fn corrupt(what: &str) -> DecodeError {
+    DecodeError::Corrupt(format!("vorbis {}", what))
+}
+
+fn ilog(value: u32) -> u32 {
+    32 - value.leading_zeros()
+}
+
+fn float32_unpack(value: u32) -> f32 {
+    let mantissa = (value & 0x1fffff) as f32;
+    let exponent = ((value & 0x7fe00000) >> 21) as i32;
+    let mantissa = if value & 0x80000000 != 0 {-mantissa} else {mantissa};
+    mantissa * (2.0f32).powi(exponent - 788)
+}
+
+fn lookup1_values(entries: u32, dimensions: u32) -> u32 {
+    let mut r = (entries as f64).powf(1.0 / dimensions as f64).floor() as u32;
+    // fix up float rounding in either direction
+    while (r + 1).checked_pow(dimensions).map_or(false, | p | p <= entries) {
+        r += 1;
+    }
+    while r > 0 && r.checked_pow(dimensions).map_or(true, | p | p > entries) {
+        r -= 1;
+    }
+    r
+}
+
+struct Codebook {
+    dimensions: usize,
+    // huffman tree, a child > 0 is a node index, < 0 is -(entry + 1), 0 is missing
+    tree: Vec<[i32; 2]>,
+    vq: Option<Vec<f32>>,
+}
+
+impl Codebook {
+    fn read(bits: &mut BitReader) -> Result<Self, DecodeError> {
+        let eop = || corrupt("codebook truncated");
+        if bits.read(24).ok_or_else(eop)? != 0x564342 {
+            return Err(corrupt("codebook sync"))
+        }
+        let dimensions = bits.read(16).ok_or_else(eop)?;
+        let entries = bits.read(24).ok_or_else(eop)?;
+        let mut lengths = vec![0u8; entries as usize];
+        if !bits.read_flag().ok_or_else(eop)? {
+            let sparse = bits.read_flag().ok_or_else(eop)?;
+            for length in &mut lengths {
+                if !sparse || bits.read_flag().ok_or_else(eop)? {
+                    *length = bits.read(5).ok_or_else(eop)? as u8 + 1;
+                }
+            }
+        }
+        else {
+            let mut current = 0;
+            let mut length = bits.read(5).ok_or_else(eop)? + 1;
+            while current < entries {
+                let number = bits.read(ilog(entries - current)).ok_or_else(eop)?;
+                if current + number > entries || length > 32 {
+                    return Err(corrupt("codebook lengths"))
+                }
+                for l in &mut lengths[current as usize..(current + number) as usize] {
+                    *l = length as u8;
+                }
+                current += number;
+                length += 1;
+            }
+        }
+        let tree = Self::build_tree(&lengths)?;
+        let vq = match bits.read(4).ok_or_else(eop)? {
+            0 => None,
+            lookup_type @ (1 | 2) => {
+                let minimum = float32_unpack(bits.read(32).ok_or_else(eop)?);
+                let delta = float32_unpack(bits.read(32).ok_or_else(eop)?);
+                let value_bits = bits.read(4).ok_or_else(eop)? + 1;
+                let sequence_p = bits.read_flag().ok_or_else(eop)?;
+                let lookup_values = if lookup_type == 1 {
+                    lookup1_values(entries, dimensions)
+                }
+                else {
+                    entries * dimensions
+                };
+                let mut multiplicands = Vec::with_capacity(lookup_values as usize);
+                for _ in 0..lookup_values {
+                    multiplicands.push(bits.read(value_bits).ok_or_else(eop)?);
+                }
+                if lookup_values == 0 {
+                    return Err(corrupt("codebook without lookup values"))
+                }
+                // unpack every entry up front, codebooks are small
+                let mut vq = Vec::with_capacity((entries * dimensions) as usize);
+                for entry in 0..entries as usize {
+                    let mut last = 0.0;
+                    let mut index_divisor = 1usize;
+                    for i in 0..dimensions as usize {
+                        let offset = if lookup_type == 1 {
+                            (entry / index_divisor) % lookup_values as usize
+                        }
+                        else {
+                            entry * dimensions as usize + i
+                        };
+                        let value = multiplicands[offset] as f32 * delta + minimum + last;
+                        if sequence_p {
+                            last = value;
+                        }
+                        vq.push(value);
+                        index_divisor = index_divisor.saturating_mul(lookup_values as usize);
+                    }
+                }
+                Some(vq)
+            }
+            _ => return Err(corrupt("codebook lookup type"))
+        };
+        Ok(Self {
+            dimensions: dimensions as usize,
+            tree,
+            vq
+        })
+    }
+
+    fn build_tree(lengths: &[u8]) -> Result<Vec<[i32; 2]>, DecodeError> {
+        let mut tree = vec![[0i32; 2]];
+        let used: Vec<usize> = (0..lengths.len()).filter( | i | lengths[*i] > 0).collect();
+        if used.len() == 1 {
+            // a single entry codebook reads one bit either way
+            let leaf = -(used[0] as i32 + 1);
+            tree[0] = [leaf, leaf];
+            return Ok(tree)
+        }
+        // available[l] holds the next free codeword of length l, left aligned
+        let mut available = [0u32; 33];
+        for (n, entry) in used.iter().enumerate() {
+            let length = lengths[*entry] as usize;
+            let codeword = if n == 0 {
+                for (l, slot) in available.iter_mut().enumerate().take(length + 1).skip(1) {
+                    *slot = 1u32 << (32 - l);
+                }
+                0
+            }
+            else {
+                let mut z = length;
+                while z > 0 && available[z] == 0 {
+                    z -= 1;
+                }
+                if z == 0 {
+                    return Err(corrupt("overspecified huffman tree"))
+                }
+                let codeword = available[z];
+                available[z] = 0;
+                for (l, slot) in available.iter_mut().enumerate().take(length + 1).skip(z + 1) {
+                    *slot = codeword + (1u32 << (32 - l));
+                }
+                codeword
+            };
+            let mut node = 0;
+            for b in 0..length {
+                let bit = ((codeword >> (31 - b)) & 1) as usize;
+                if b == length - 1 {
+                    tree[node][bit] = -(*entry as i32 + 1);
+                }
+                else {
+                    let child = tree[node][bit];
+                    if child < 0 {
+                        return Err(corrupt("huffman prefix collision"))
+                    }
+                    node = if child == 0 {
+                        tree.push([0, 0]);
+                        let index = tree.len() - 1;
+                        tree[node][bit] = index as i32;
+                        index
+                    }
+                    else {
+                        child as usize
+                    };
+                }
+            }
+        }
+        Ok(tree)
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Option<usize> {
+        let mut node = 0;
+        loop {
+            let child = self.tree[node][bits.read(1)? as usize];
+            if child < 0 {
+                return Some((-child - 1) as usize)
+            }
+            if child == 0 {
+                return None
+            }
+            node = child as usize;
+        }
+    }
+
+    fn decode_vq(&self, bits: &mut BitReader) -> Option<&[f32]> {
+        let entry = self.decode(bits)?;
+        let vq = self.vq.as_ref()?;
+        Some(&vq[entry * self.dimensions..(entry + 1) * self.dimensions])
+    }
+}
+
+struct Floor0 {
+    order: usize,
+    rate: u32,
+    bark_map_size: u32,
+    amplitude_bits: u32,
+    amplitude_offset: u32,
+    books: Vec<usize>,
+}
+
+struct Floor1Class {
+    dimensions: usize,
+    subclasses: u32,
+    masterbook: Option<usize>,
+    subclass_books: Vec<Option<usize>>,
+}
+
+struct Floor1 {
+    partition_classes: Vec<usize>,
+    classes: Vec<Floor1Class>,
+    multiplier: u32,
+    xs: Vec<u32>,
+    // indices into xs sorted by x, and the neighbours used for prediction
+    sorted: Vec<usize>,
+    neighbours: Vec<(usize, usize)>,
+}
+
+enum Floor {
+    Zero(Floor0),
+    One(Floor1),
+}
+
+enum DecodedFloor {
+    Unused,
+    Zero {amplitude: u32, coefficients: Vec<f32>},
+    One(Vec<i32>),
+}
+
+struct Residue {
+    kind: u32,
+    begin: usize,
+    end: usize,
+    partition_size: usize,
+    classifications: usize,
+    classbook: usize,
+    books: Vec<[Option<usize>; 8]>,
+}
+
+struct Mapping {
+    coupling: Vec<(usize, usize)>,
+    mux: Vec<usize>,
+    submap_floors: Vec<usize>,
+    submap_residues: Vec<usize>,
+}
+
+struct Mode {
+    blockflag: bool,
+    mapping: usize,
+}
+
+// per blocksize tables for the window and the imdct
+struct BlockTables {
+    size: usize,
+    window_slope: Vec<f32>,
+    pre_twiddle: Vec<(f32, f32)>,
+    post_twiddle: Vec<(f32, f32)>,
+    fft_twiddle: Vec<(f32, f32)>,
+}
+
+impl BlockTables {
+    fn new(size: usize) -> Self {
+        let half = size / 2;
+        let quarter = size / 4;
+        let window_slope = (0..half).map( | i | {
+            let s = ((i as f32 + 0.5) / half as f32 * PI * 0.5).sin();
+            (PI * 0.5 * s * s).sin()
+        }).collect();
+        let pre_twiddle = (0..quarter).map( | n | {
+            let t = -PI * (4 * n + 1) as f32 / (4 * half) as f32;
+            (t.cos(), t.sin())
+        }).collect();
+        let post_twiddle = (0..quarter).map( | k | {
+            let t = -PI * k as f32 / half as f32;
+            (t.cos(), t.sin())
+        }).collect();
+        let fft_twiddle = (0..quarter / 2).map( | k | {
+            let t = -2.0 * PI * k as f32 / quarter as f32;
+            (t.cos(), t.sin())
+        }).collect();
+        Self {size, window_slope, pre_twiddle, post_twiddle, fft_twiddle}
+    }
+
+    fn fft(&self, re: &mut [f32], im: &mut [f32]) {
+        let n = re.len();
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+            if i < j {
+                re.swap(i, j);
+                im.swap(i, j);
+            }
+        }
+        let mut len = 2;
+        while len <= n {
+            let stride = n / len;
+            for start in (0..n).step_by(len) {
+                for k in 0..len / 2 {
+                    let (wr, wi) = self.fft_twiddle[k * stride];
+                    let a = start + k;
+                    let b = a + len / 2;
+                    let tr = re[b] * wr - im[b] * wi;
+                    let ti = re[b] * wi + im[b] * wr;
+                    re[b] = re[a] - tr;
+                    im[b] = im[a] - ti;
+                    re[a] += tr;
+                    im[a] += ti;
+                }
+            }
+            len <<= 1;
+        }
+    }
+
+    // unscaled inverse mdct of size/2 coefficients into size samples, computed as a
+    // dct-iv through a size/4 point complex fft and unfolded with its symmetries
+    fn imdct(&self, spectrum: &[f32], output: &mut [f32]) {
+        let half = self.size / 2;
+        let quarter = self.size / 4;
+        let mut re = vec![0.0; quarter];
+        let mut im = vec![0.0; quarter];
+        for n in 0..quarter {
+            let a = spectrum[2 * n];
+            let b = spectrum[half - 1 - 2 * n];
+            let (c, s) = self.pre_twiddle[n];
+            re[n] = a * c - b * s;
+            im[n] = a * s + b * c;
+        }
+        self.fft(&mut re, &mut im);
+        let mut dct = vec![0.0; half];
+        for k in 0..quarter {
+            let (c, s) = self.post_twiddle[k];
+            dct[2 * k] = re[k] * c - im[k] * s;
+            dct[half - 1 - 2 * k] = -(re[k] * s + im[k] * c);
+        }
+        for (i, out) in output.iter_mut().enumerate().take(self.size) {
+            let index = i + half / 2;
+            *out = if index < half {
+                dct[index]
+            }
+            else if index < 2 * half {
+                -dct[2 * half - 1 - index]
+            }
+            else {
+                -dct[index - 2 * half]
+            };
+        }
+    }
+}
+
+pub struct VorbisDecoder<R> {
+    ogg: OggReader<R>,
+    sample_rate: f64,
+    channel_count: usize,
+    blocksizes: [usize; 2],
+    tables: [BlockTables; 2],
+    codebooks: Vec<Codebook>,
+    floors: Vec<Floor>,
+    residues: Vec<Residue>,
+    mappings: Vec<Mapping>,
+    modes: Vec<Mode>,
+    // raw right half of the previous window per channel, None before the first packet
+    previous: Option<Vec<Vec<f32>>>,
+    frames_decoded: u64,
+    finished: bool,
+}
+
+impl<R: ReadSeek> VorbisDecoder<R> {
+    pub fn new(reader: R) -> Result<Self, DecodeError> {
+        let mut ogg = OggReader::new(reader);
+        let mut next_header = | kind: u8 | -> Result<Vec<u8>, DecodeError> {
+            let packet = ogg.next_packet()?.ok_or_else( || corrupt("missing header"))?;
+            if packet.data.len() < 7 || packet.data[0] != kind || &packet.data[1..7] != b"vorbis" {
+                return Err(DecodeError::UnknownFormat)
+            }
+            Ok(packet.data)
+        };
+        let ident = next_header(1)?;
+        let _comments = next_header(3)?;
+        let setup = next_header(5)?;
+        if ident.len() < 30 || u32::from_le_bytes(ident[7..11].try_into().unwrap()) != 0 {
+            return Err(DecodeError::Unsupported("vorbis version".to_string()))
+        }
+        let channel_count = ident[11] as usize;
+        let sample_rate = u32::from_le_bytes(ident[12..16].try_into().unwrap()) as f64;
+        let blocksizes = [1usize << (ident[28] & 15), 1usize << (ident[28] >> 4)];
+        if channel_count == 0 || sample_rate == 0.0 || blocksizes[0] < 64 || blocksizes[1] > 8192 || blocksizes[0] > blocksizes[1] {
+            return Err(corrupt("identification header"))
+        }
+        let mut decoder = Self {
+            ogg,
+            sample_rate,
+            channel_count,
+            blocksizes,
+            tables: [BlockTables::new(blocksizes[0]), BlockTables::new(blocksizes[1])],
+            codebooks: Vec::new(),
+            floors: Vec::new(),
+            residues: Vec::new(),
+            mappings: Vec::new(),
+            modes: Vec::new(),
+            previous: None,
+            frames_decoded: 0,
+            finished: false,
+        };
+        decoder.read_setup(&setup[7..])?;
+        Ok(decoder)
+    }
+
+    fn read_setup(&mut self, data: &[u8]) -> Result<(), DecodeError> {
+        let mut bits = BitReader::new(data);
+        let bits = &mut bits;
+        let eop = || corrupt("setup header truncated");
+        let book = | bits: &mut BitReader, count: usize | -> Result<usize, DecodeError> {
+            let book = bits.read(8).ok_or_else(eop)? as usize;
+            if book >= count {
+                return Err(corrupt("codebook index"))
+            }
+            Ok(book)
+        };
+
+        for _ in 0..bits.read(8).ok_or_else(eop)? + 1 {
+            self.codebooks.push(Codebook::read(bits)?);
+        }
+        let books = self.codebooks.len();
+
+        // time domain transforms are placeholders in vorbis I
+        for _ in 0..bits.read(6).ok_or_else(eop)? + 1 {
+            if bits.read(16).ok_or_else(eop)? != 0 {
+                return Err(corrupt("time domain transform"))
+            }
+        }
+
+        for _ in 0..bits.read(6).ok_or_else(eop)? + 1 {
+            match bits.read(16).ok_or_else(eop)? {
+                0 => {
+                    let order = bits.read(8).ok_or_else(eop)? as usize;
+                    let rate = bits.read(16).ok_or_else(eop)?;
+                    let bark_map_size = bits.read(16).ok_or_else(eop)?;
+                    let amplitude_bits = bits.read(6).ok_or_else(eop)?;
+                    let amplitude_offset = bits.read(8).ok_or_else(eop)?;
+                    let mut floor_books = Vec::new();
+                    for _ in 0..bits.read(4).ok_or_else(eop)? + 1 {
+                        floor_books.push(book(bits, books)?);
+                    }
+                    self.floors.push(Floor::Zero(Floor0 {
+                        order,
+                        rate,
+                        bark_map_size,
+                        amplitude_bits,
+                        amplitude_offset,
+                        books: floor_books
+                    }));
+                }
+                1 => {
+                    let partitions = bits.read(5).ok_or_else(eop)?;
+                    let mut partition_classes = Vec::new();
+                    for _ in 0..partitions {
+                        partition_classes.push(bits.read(4).ok_or_else(eop)? as usize);
+                    }
+                    let class_count = partition_classes.iter().max().map_or(0, | m | m + 1);
+                    let mut classes = Vec::new();
+                    for _ in 0..class_count {
+                        let dimensions = bits.read(3).ok_or_else(eop)? as usize + 1;
+                        let subclasses = bits.read(2).ok_or_else(eop)?;
+                        let masterbook = if subclasses > 0 {Some(book(bits, books)?)} else {None};
+                        let mut subclass_books = Vec::new();
+                        for _ in 0..1 << subclasses {
+                            let book = bits.read(8).ok_or_else(eop)? as usize;
+                            if book > books {
+                                return Err(corrupt("codebook index"))
+                            }
+                            subclass_books.push(book.checked_sub(1));
+                        }
+                        classes.push(Floor1Class {dimensions, subclasses, masterbook, subclass_books});
+                    }
+                    let multiplier = bits.read(2).ok_or_else(eop)? + 1;
+                    let range_bits = bits.read(4).ok_or_else(eop)?;
+                    let mut xs = vec![0, 1 << range_bits];
+                    for class in &partition_classes {
+                        for _ in 0..classes[*class].dimensions {
+                            xs.push(bits.read(range_bits).ok_or_else(eop)?);
+                        }
+                    }
+                    if xs.len() > 65 {
+                        return Err(corrupt("floor1 has too many values"))
+                    }
+                    let mut sorted: Vec<usize> = (0..xs.len()).collect();
+                    sorted.sort_by_key( | i | xs[*i]);
+                    let mut neighbours = vec![(0, 0); xs.len()];
+                    for i in 2..xs.len() {
+                        let mut low = 0;
+                        let mut high = 1;
+                        for j in 0..i {
+                            if xs[j] < xs[i] && xs[j] > xs[low] {
+                                low = j;
+                            }
+                            if xs[j] > xs[i] && xs[j] < xs[high] {
+                                high = j;
+                            }
+                        }
+                        neighbours[i] = (low, high);
+                    }
+                    self.floors.push(Floor::One(Floor1 {
+                        partition_classes,
+                        classes,
+                        multiplier,
+                        xs,
+                        sorted,
+                        neighbours
+                    }));
+                }
+                _ => return Err(corrupt("floor type"))
+            }
+        }
+
+        for _ in 0..bits.read(6).ok_or_else(eop)? + 1 {
+            let kind = bits.read(16).ok_or_else(eop)?;
+            if kind > 2 {
+                return Err(corrupt("residue type"))
+            }
+            let begin = bits.read(24).ok_or_else(eop)? as usize;
+            let end = bits.read(24).ok_or_else(eop)? as usize;
+            let partition_size = bits.read(24).ok_or_else(eop)? as usize + 1;
+            let classifications = bits.read(6).ok_or_else(eop)? as usize + 1;
+            let classbook = book(bits, books)?;
+            let mut cascades = Vec::new();
+            for _ in 0..classifications {
+                let low = bits.read(3).ok_or_else(eop)?;
+                let high = if bits.read_flag().ok_or_else(eop)? {bits.read(5).ok_or_else(eop)?} else {0};
+                cascades.push(high * 8 + low);
+            }
+            let mut residue_books = Vec::new();
+            for cascade in cascades {
+                let mut pass_books = [None; 8];
+                for (pass, slot) in pass_books.iter_mut().enumerate() {
+                    if cascade & (1 << pass) != 0 {
+                        let book = book(bits, books)?;
+                        if self.codebooks[book].vq.is_none() {
+                            return Err(corrupt("residue book without lookup"))
+                        }
+                        *slot = Some(book);
+                    }
+                }
+                residue_books.push(pass_books);
+            }
+            if self.codebooks[classbook].dimensions == 0 {
+                return Err(corrupt("residue classbook"))
+            }
+            self.residues.push(Residue {
+                kind,
+                begin,
+                end,
+                partition_size,
+                classifications,
+                classbook,
+                books: residue_books
+            });
+        }
+
+        let channel_bits = ilog(self.channel_count as u32 - 1);
+        for _ in 0..bits.read(6).ok_or_else(eop)? + 1 {
+            if bits.read(16).ok_or_else(eop)? != 0 {
+                return Err(corrupt("mapping type"))
+            }
+            let submaps = if bits.read_flag().ok_or_else(eop)? {bits.read(4).ok_or_else(eop)? as usize + 1} else {1};
+            let mut coupling = Vec::new();
+            if bits.read_flag().ok_or_else(eop)? {
+                for _ in 0..bits.read(8).ok_or_else(eop)? + 1 {
+                    let magnitude = bits.read(channel_bits).ok_or_else(eop)? as usize;
+                    let angle = bits.read(channel_bits).ok_or_else(eop)? as usize;
+                    if magnitude == angle || magnitude >= self.channel_count || angle >= self.channel_count {
+                        return Err(corrupt("channel coupling"))
+                    }
+                    coupling.push((magnitude, angle));
+                }
+            }
+            if bits.read(2).ok_or_else(eop)? != 0 {
+                return Err(corrupt("mapping reserved bits"))
+            }
+            let mut mux = vec![0; self.channel_count];
+            if submaps > 1 {
+                for m in &mut mux {
+                    *m = bits.read(4).ok_or_else(eop)? as usize;
+                    if *m >= submaps {
+                        return Err(corrupt("mapping mux"))
+                    }
+                }
+            }
+            let mut submap_floors = Vec::new();
+            let mut submap_residues = Vec::new();
+            for _ in 0..submaps {
+                bits.read(8).ok_or_else(eop)?;
+                let floor = bits.read(8).ok_or_else(eop)? as usize;
+                let residue = bits.read(8).ok_or_else(eop)? as usize;
+                if floor >= self.floors.len() || residue >= self.residues.len() {
+                    return Err(corrupt("mapping submap"))
+                }
+                submap_floors.push(floor);
+                submap_residues.push(residue);
+            }
+            self.mappings.push(Mapping {coupling, mux, submap_floors, submap_residues});
+        }
+
+        for _ in 0..bits.read(6).ok_or_else(eop)? + 1 {
+            let blockflag = bits.read_flag().ok_or_else(eop)?;
+            let window_type = bits.read(16).ok_or_else(eop)?;
+            let transform_type = bits.read(16).ok_or_else(eop)?;
+            let mapping = bits.read(8).ok_or_else(eop)? as usize;
+            if window_type != 0 || transform_type != 0 || mapping >= self.mappings.len() {
+                return Err(corrupt("mode"))
+            }
+            self.modes.push(Mode {blockflag, mapping});
+        }
+        if !bits.read_flag().ok_or_else(eop)? {
+            return Err(corrupt("setup framing bit"))
+        }
+        Ok(())
+    }
+
+    fn decode_floor(&self, floor: &Floor, bits: &mut BitReader) -> Option<DecodedFloor> {
+        match floor {
+            Floor::Zero(floor) => {
+                let amplitude = bits.read(floor.amplitude_bits)?;
+                if amplitude == 0 {
+                    return Some(DecodedFloor::Unused)
+                }
+                let book = bits.read(ilog(floor.books.len() as u32))? as usize;
+                let codebook = &self.codebooks[*floor.books.get(book)?];
+                let mut coefficients = Vec::with_capacity(floor.order + codebook.dimensions);
+                let mut last = 0.0;
+                while coefficients.len() < floor.order {
+                    let values = codebook.decode_vq(bits)?;
+                    for value in values {
+                        coefficients.push(value + last);
+                    }
+                    last = *coefficients.last()?;
+                }
+                coefficients.truncate(floor.order);
+                Some(DecodedFloor::Zero {amplitude, coefficients})
+            }
+            Floor::One(floor) => {
+                if !bits.read_flag()? {
+                    return Some(DecodedFloor::Unused)
+                }
+                let range = [256, 128, 86, 64][floor.multiplier as usize - 1];
+                let range_bits = ilog(range - 1);
+                let mut ys = Vec::with_capacity(floor.xs.len());
+                ys.push(bits.read(range_bits)? as i32);
+                ys.push(bits.read(range_bits)? as i32);
+                for class in &floor.partition_classes {
+                    let class = &floor.classes[*class];
+                    let mut value = match class.masterbook {
+                        Some(book) => self.codebooks[book].decode(bits)?,
+                        None => 0
+                    };
+                    let mask = (1 << class.subclasses) - 1;
+                    for _ in 0..class.dimensions {
+                        let book = class.subclass_books[value & mask];
+                        value >>= class.subclasses;
+                        ys.push(match book {
+                            Some(book) => self.codebooks[book].decode(bits)? as i32,
+                            None => 0
+                        });
+                    }
+                }
+                Some(DecodedFloor::One(ys))
+            }
+        }
+    }
+
+    fn floor0_curve(floor: &Floor0, amplitude: u32, coefficients: &[f32], n: usize) -> Vec<f32> {
+        let bark = | x: f32 | 13.1 * (0.00074 * x).atan() + 2.24 * (0.0000000185 * x * x).atan() + 0.0001 * x;
+        let bark_scale = floor.bark_map_size as f32 / bark(0.5 * floor.rate as f32);
+        let cos_coefficients: Vec<f32> = coefficients.iter().map( | c | c.cos()).collect();
+        let amplitude = amplitude as f32;
+        let max_amplitude = ((1u64 << floor.amplitude_bits) - 1) as f32;
+        let mut curve = vec![0.0; n];
+        let mut i = 0;
+        while i < n {
+            let map = ((bark(floor.rate as f32 * i as f32 / (2.0 * n as f32)) * bark_scale).floor() as u32)
+                .min(floor.bark_map_size - 1);
+            let omega = (PI * map as f32 / floor.bark_map_size as f32).cos();
+            let (mut p, mut q) = if floor.order & 1 == 1 {
+                (1.0 - omega * omega, 0.25)
+            }
+            else {
+                ((1.0 - omega) * 0.5, (1.0 + omega) * 0.5)
+            };
+            for (j, c) in cos_coefficients.iter().enumerate() {
+                let d = 4.0 * (c - omega) * (c - omega);
+                if j & 1 == 1 {p *= d} else {q *= d}
+            }
+            let value = (0.11512925 * (amplitude * floor.amplitude_offset as f32 / (max_amplitude * (p + q).sqrt()) - floor.amplitude_offset as f32)).exp();
+            // neighbouring bins mapping to the same bark value share the value
+            loop {
+                curve[i] = value;
+                i += 1;
+                if i >= n {
+                    break
+                }
+                let next = ((bark(floor.rate as f32 * i as f32 / (2.0 * n as f32)) * bark_scale).floor() as u32)
+                    .min(floor.bark_map_size - 1);
+                if next != map {
+                    break
+                }
+            }
+        }
+        curve
+    }
+
+    fn floor1_curve(floor: &Floor1, ys: &[i32], n: usize) -> Vec<f32> {
+        let range = [256, 128, 86, 64][floor.multiplier as usize - 1];
+        let mut step2 = vec![false; ys.len()];
+        let mut final_y = vec![0i32; ys.len()];
+        step2[0] = true;
+        step2[1] = true;
+        final_y[0] = ys[0];
+        final_y[1] = ys[1];
+        for i in 2..ys.len() {
+            let (low, high) = floor.neighbours[i];
+            let predicted = render_point(
+                floor.xs[low] as i32,
+                final_y[low],
+                floor.xs[high] as i32,
+                final_y[high],
+                floor.xs[i] as i32
+            );
+            let value = ys[i];
+            let high_room = range - predicted;
+            let low_room = predicted;
+            let room = if high_room < low_room {high_room * 2} else {low_room * 2};
+            if value != 0 {
+                step2[low] = true;
+                step2[high] = true;
+                step2[i] = true;
+                final_y[i] = if value >= room {
+                    if high_room > low_room {value - low_room + predicted} else {predicted - value + high_room - 1}
+                }
+                else if value & 1 == 1 {
+                    predicted - (value + 1) / 2
+                }
+                else {
+                    predicted + value / 2
+                };
+            }
+            else {
+                final_y[i] = predicted;
+            }
+            final_y[i] = final_y[i].clamp(0, range - 1);
+        }
+
+        let multiplier = floor.multiplier as i32;
+        let mut curve = vec![0.0; n];
+        let mut lx = 0;
+        let mut ly = final_y[floor.sorted[0]] * multiplier;
+        for &i in &floor.sorted[1..] {
+            if step2[i] {
+                let hx = floor.xs[i] as i32;
+                let hy = final_y[i] * multiplier;
+                render_line(lx, ly, hx, hy, &mut curve);
+                lx = hx;
+                ly = hy;
+            }
+        }
+        if (lx as usize) < n {
+            render_line(lx, ly, n as i32, ly, &mut curve);
+        }
+        curve
+    }
+
+    fn decode_residue(&self, residue: &Residue, bits: &mut BitReader, n: usize, skip: &[bool]) -> Vec<Vec<f32>> {
+        let channels = skip.len();
+        if residue.kind == 2 {
+            let mut output = vec![vec![0.0; n]; channels];
+            if skip.iter().all( | s | *s) {
+                return output
+            }
+            let mut interleaved = vec![vec![0.0; n * channels]];
+            self.decode_partitions(residue, bits, &mut interleaved, &[false]);
+            for (i, value) in interleaved[0].iter().enumerate() {
+                output[i % channels][i / channels] = *value;
+            }
+            output
+        }
+        else {
+            let mut output = vec![vec![0.0; n]; channels];
+            self.decode_partitions(residue, bits, &mut output, skip);
+            output
+        }
+    }
+
+    fn decode_partitions(&self, residue: &Residue, bits: &mut BitReader, vectors: &mut [Vec<f32>], skip: &[bool]) -> Option<()> {
+        let size = vectors[0].len();
+        let begin = residue.begin.min(size);
+        let end = residue.end.min(size);
+        let partitions = (end - begin) / residue.partition_size;
+        let classbook = &self.codebooks[residue.classbook];
+        let classwords = classbook.dimensions;
+        let mut classifications = vec![vec![0usize; partitions + classwords]; vectors.len()];
+        for pass in 0..8 {
+            let mut partition = 0;
+            while partition < partitions {
+                if pass == 0 {
+                    for (ch, classes) in classifications.iter_mut().enumerate() {
+                        if skip[ch] {
+                            continue
+                        }
+                        let mut temp = classbook.decode(bits)?;
+                        for i in (0..classwords).rev() {
+                            classes[i + partition] = temp % residue.classifications;
+                            temp /= residue.classifications;
+                        }
+                    }
+                }
+                for _ in 0..classwords {
+                    if partition >= partitions {
+                        break
+                    }
+                    for (ch, vector) in vectors.iter_mut().enumerate() {
+                        if skip[ch] {
+                            continue
+                        }
+                        let class = classifications[ch][partition];
+                        if let Some(Some(book)) = residue.books.get(class).map( | b | b[pass]) {
+                            let book = &self.codebooks[book];
+                            let offset = begin + partition * residue.partition_size;
+                            let target = &mut vector[offset..offset + residue.partition_size];
+                            if residue.kind == 0 {
+                                let step = residue.partition_size / book.dimensions;
+                                for j in 0..step {
+                                    for (i, value) in book.decode_vq(bits)?.iter().enumerate() {
+                                        target[j + i * step] += value;
+                                    }
+                                }
+                            }
+                            else {
+                                let mut i = 0;
+                                while i < residue.partition_size {
+                                    for value in book.decode_vq(bits)? {
+                                        if i < residue.partition_size {
+                                            target[i] += value;
+                                        }
+                                        i += 1;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    partition += 1;
+                }
+            }
+        }
+        Some(())
+    }
+
+    // decodes one audio packet, returns the finished samples per channel
+    fn decode_packet(&mut self, data: &[u8]) -> Result<Vec<Vec<f32>>, DecodeError> {
+        let mut bits = BitReader::new(data);
+        let bits = &mut bits;
+        let eop = || corrupt("audio packet truncated");
+        if bits.read_flag().ok_or_else(eop)? {
+            // a stray header packet, nothing to output
+            return Ok(vec![Vec::new(); self.channel_count])
+        }
+        let mode = bits.read(ilog(self.modes.len() as u32 - 1)).ok_or_else(eop)? as usize;
+        let mode = self.modes.get(mode).ok_or_else( || corrupt("mode index"))?;
+        let blockflag = mode.blockflag;
+        let mapping = &self.mappings[mode.mapping];
+        let n = self.blocksizes[blockflag as usize];
+        let (previous_long, next_long) = if blockflag {
+            (bits.read_flag().ok_or_else(eop)?, bits.read_flag().ok_or_else(eop)?)
+        }
+        else {
+            (false, false)
+        };
+
+        // an end of packet inside the floors leaves the remaining channels unused
+        let mut floors = Vec::with_capacity(self.channel_count);
+        for ch in 0..self.channel_count {
+            let floor = &self.floors[mapping.submap_floors[mapping.mux[ch]]];
+            floors.push(self.decode_floor(floor, bits).unwrap_or(DecodedFloor::Unused));
+        }
+
+        let mut no_residue: Vec<bool> = floors.iter().map( | f | matches!(f, DecodedFloor::Unused)).collect();
+        for (magnitude, angle) in &mapping.coupling {
+            if !no_residue[*magnitude] || !no_residue[*angle] {
+                no_residue[*magnitude] = false;
+                no_residue[*angle] = false;
+            }
+        }
+
+        let half = n / 2;
+        let mut residues = vec![Vec::new(); self.channel_count];
+        for (submap, residue) in mapping.submap_residues.iter().enumerate() {
+            let channels: Vec<usize> = (0..self.channel_count).filter( | ch | mapping.mux[*ch] == submap).collect();
+            let skip: Vec<bool> = channels.iter().map( | ch | no_residue[*ch]).collect();
+            let vectors = self.decode_residue(&self.residues[*residue], bits, half, &skip);
+            for (ch, vector) in channels.into_iter().zip(vectors) {
+                residues[ch] = vector;
+            }
+        }
+
+        for (magnitude, angle) in mapping.coupling.iter().rev() {
+            let (m, a) = if magnitude < angle {
+                let (left, right) = residues.split_at_mut(*angle);
+                (&mut left[*magnitude], &mut right[0])
+            }
+            else {
+                let (left, right) = residues.split_at_mut(*magnitude);
+                (&mut right[0], &mut left[*angle])
+            };
+            for (m, a) in m.iter_mut().zip(a.iter_mut()) {
+                let (new_m, new_a) = if *m > 0.0 {
+                    if *a > 0.0 {(*m, *m - *a)} else {(*m + *a, *m)}
+                }
+                else if *a > 0.0 {
+                    (*m, *m + *a)
+                }
+                else {
+                    (*m - *a, *m)
+                };
+                *m = new_m;
+                *a = new_a;
+            }
+        }
+
+        let tables = &self.tables[blockflag as usize];
+        let mut blocks = Vec::with_capacity(self.channel_count);
+        for (floor, residue) in floors.iter().zip(residues.iter()) {
+            let mut spectrum = match floor {
+                DecodedFloor::Unused => vec![0.0; half],
+                DecodedFloor::Zero {amplitude, coefficients} => {
+                    let floor = &self.floors[mapping.submap_floors[mapping.mux[blocks.len()]]];
+                    if let Floor::Zero(floor) = floor {Self::floor0_curve(floor, *amplitude, coefficients, half)} else {vec![0.0; half]}
+                }
+                DecodedFloor::One(ys) => {
+                    let floor = &self.floors[mapping.submap_floors[mapping.mux[blocks.len()]]];
+                    if let Floor::One(floor) = floor {Self::floor1_curve(floor, ys, half)} else {vec![0.0; half]}
+                }
+            };
+            for (s, r) in spectrum.iter_mut().zip(residue.iter()) {
+                *s *= r;
+            }
+            let mut block = vec![0.0; n];
+            tables.imdct(&spectrum, &mut block);
+            blocks.push(block);
+        }
+
+        // window shapes depend on the neighbouring block sizes
+        let short = self.blocksizes[0];
+        let center = n / 2;
+        let (left_start, left_long) = if !blockflag || previous_long {
+            (0, blockflag)
+        }
+        else {
+            ((n - short) / 4, false)
+        };
+        let (right_start, right_end) = if !blockflag || next_long {
+            (center, n)
+        }
+        else {
+            ((n * 3 - short) / 4, (n * 3 + short) / 4)
+        };
+        let slope = &self.tables[left_long as usize].window_slope;
+
+        let mut output = Vec::with_capacity(self.channel_count);
+        let mut next_previous = Vec::with_capacity(self.channel_count);
+        let previous = self.previous.take();
+        for (ch, mut block) in blocks.into_iter().enumerate() {
+            next_previous.push(block[right_start..right_end].to_vec());
+            if let Some(previous) = &previous {
+                let prev = &previous[ch];
+                if prev.len() > slope.len() || left_start + prev.len() > n {
+                    return Err(corrupt("window overlap"))
+                }
+                let slope = &slope[..prev.len()];
+                for (i, p) in prev.iter().enumerate() {
+                    let v = &mut block[left_start + i];
+                    *v = *v * slope[i] + p * slope[prev.len() - 1 - i];
+                }
+                output.push(block[left_start..right_start].to_vec());
+            }
+            else {
+                // the first packet only primes the overlap
+                output.push(Vec::new());
+            }
+        }
+        self.previous = Some(next_previous);
+        Ok(output)
+    }
+}
+
+static FLOOR1_INVERSE_DB_TABLE: [f32; 256] = [
+    1.0649863e-07, 1.1341951e-07, 1.2079015e-07, 1.2863978e-07, 1.3699951e-07, 1.4590251e-07,
+    1.5538408e-07, 1.6548181e-07, 1.7623575e-07, 1.8768855e-07, 1.9988561e-07, 2.1287530e-07,
+    2.2670913e-07, 2.4144197e-07, 2.5713223e-07, 2.7384213e-07, 2.9163793e-07, 3.1059021e-07,
+    3.3077411e-07, 3.5226968e-07, 3.7516214e-07, 3.9954229e-07, 4.2550680e-07, 4.5315863e-07,
+    4.8260743e-07, 5.1396998e-07, 5.4737065e-07, 5.8294187e-07, 6.2082472e-07, 6.6116941e-07,
+    7.0413592e-07, 7.4989464e-07, 7.9862701e-07, 8.5052630e-07, 9.0579828e-07, 9.6466216e-07,
+    1.0273513e-06, 1.0941144e-06, 1.1652161e-06, 1.2409384e-06, 1.3215816e-06, 1.4074654e-06,
+    1.4989305e-06, 1.5963394e-06, 1.7000785e-06, 1.8105592e-06, 1.9282195e-06, 2.0535261e-06,
+    2.1869758e-06, 2.3290978e-06, 2.4804557e-06, 2.6416497e-06, 2.8133190e-06, 2.9961443e-06,
+    3.1908506e-06, 3.3982101e-06, 3.6190449e-06, 3.8542308e-06, 4.1047004e-06, 4.3714470e-06,
+    4.6555282e-06, 4.9580707e-06, 5.2802740e-06, 5.6234160e-06, 5.9888572e-06, 6.3780469e-06,
+    6.7925283e-06, 7.2339451e-06, 7.7040476e-06, 8.2047000e-06, 8.7378876e-06, 9.3057248e-06,
+    9.9104632e-06, 1.0554501e-05, 1.1240392e-05, 1.1970856e-05, 1.2748789e-05, 1.3577278e-05,
+    1.4459606e-05, 1.5399272e-05, 1.6400004e-05, 1.7465768e-05, 1.8600792e-05, 1.9809576e-05,
+    2.1096914e-05, 2.2467911e-05, 2.3928002e-05, 2.5482978e-05, 2.7139006e-05, 2.8902651e-05,
+    3.0780908e-05, 3.2781225e-05, 3.4911534e-05, 3.7180282e-05, 3.9596466e-05, 4.2169667e-05,
+    4.4910090e-05, 4.7828601e-05, 5.0936773e-05, 5.4246931e-05, 5.7772202e-05, 6.1526565e-05,
+    6.5524908e-05, 6.9783085e-05, 7.4317983e-05, 7.9147585e-05, 8.4291040e-05, 8.9768747e-05,
+    9.5602426e-05, 0.00010181521, 0.00010843174, 0.00011547824, 0.00012298267, 0.00013097477,
+    0.00013948625, 0.00014855085, 0.00015820453, 0.00016848555, 0.00017943469, 0.00019109536,
+    0.00020351382, 0.00021673929, 0.00023082423, 0.00024582449, 0.00026179955, 0.00027881276,
+    0.00029693158, 0.00031622787, 0.00033677814, 0.00035866388, 0.00038197188, 0.00040679456,
+    0.00043323036, 0.00046138411, 0.00049136745, 0.00052329927, 0.00055730621, 0.00059352311,
+    0.00063209358, 0.00067317058, 0.00071691700, 0.00076350630, 0.00081312324, 0.00086596457,
+    0.00092223983, 0.00098217216, 0.0010459992, 0.0011139742, 0.0011863665, 0.0012634633,
+    0.0013455702, 0.0014330129, 0.0015261382, 0.0016253153, 0.0017309374, 0.0018434235,
+    0.0019632195, 0.0020908006, 0.0022266726, 0.0023713743, 0.0025254795, 0.0026895994,
+    0.0028643847, 0.0030505286, 0.0032487691, 0.0034598925, 0.0036847358, 0.0039241906,
+    0.0041792066, 0.0044507950, 0.0047400328, 0.0050480668, 0.0053761186, 0.0057254891,
+    0.0060975636, 0.0064938176, 0.0069158225, 0.0073652516, 0.0078438871, 0.0083536271,
+    0.0088964928, 0.009474637, 0.010090352, 0.010746080, 0.011444421, 0.012188144, 0.012980198,
+    0.013823725, 0.014722068, 0.015678791, 0.016697687, 0.017782797, 0.018938423, 0.020169149,
+    0.021479854, 0.022875735, 0.024362330, 0.025945531, 0.027631618, 0.029427276, 0.031339626,
+    0.033376252, 0.035545228, 0.037855157, 0.040315199, 0.042935108, 0.045725273, 0.048696758,
+    0.051861348, 0.055231591, 0.058820850, 0.062643361, 0.066714279, 0.071049749, 0.075666962,
+    0.080584227, 0.085821044, 0.091398179, 0.097337747, 0.10366330, 0.11039993, 0.11757434,
+    0.12521498, 0.13335215, 0.14201813, 0.15124727, 0.16107617, 0.17154380, 0.18269168, 0.19456402,
+    0.20720788, 0.22067342, 0.23501402, 0.25028656, 0.26655159, 0.28387361, 0.30232132, 0.32196786,
+    0.34289114, 0.36517414, 0.38890521, 0.41417847, 0.44109412, 0.46975890, 0.50028648, 0.53279791,
+    0.56742212, 0.60429640, 0.64356699, 0.68538959, 0.72993007, 0.77736504, 0.82788260, 0.88168307,
+    0.9389798, 1.0,
+];
+
+fn render_point(x0: i32, y0: i32, x1: i32, y1: i32, x: i32) -> i32 {
+    let dy = y1 - y0;
+    let adx = x1 - x0;
+    if adx == 0 {
+        return y0
+    }
+    let offset = dy.abs() * (x - x0) / adx;
+    if dy < 0 {y0 - offset} else {y0 + offset}
+}
+
+fn render_line(x0: i32, y0: i32, x1: i32, y1: i32, curve: &mut [f32]) {
+    let dy = y1 - y0;
+    let adx = x1 - x0;
+    if adx <= 0 {
+        return
+    }
+    let base = dy / adx;
+    let sy = if dy < 0 {base - 1} else {base + 1};
+    let ady = dy.abs() - base.abs() * adx;
+    let mut y = y0;
+    let mut err = 0;
+    let lookup = | y: i32 | FLOOR1_INVERSE_DB_TABLE[y.clamp(0, 255) as usize];
+    if let Some(v) = curve.get_mut(x0 as usize) {
+        *v = lookup(y);
+    }
+    for x in x0 + 1..x1 {
+        err += ady;
+        if err >= adx {
+            err -= adx;
+            y += sy;
+        }
+        else {
+            y += base;
+        }
+        if let Some(v) = curve.get_mut(x as usize) {
+            *v = lookup(y);
+        }
+    }
+}
+
+impl<R: ReadSeek> AudioDecoder for VorbisDecoder<R> {
+    fn sample_rate(&self) -> f64 {self.sample_rate}
+
+    fn channel_count(&self) -> usize {self.channel_count}
+
+    fn frame_count(&self) -> Option<usize> {None}
+
+    fn decode_block(&mut self) -> Result<Option<AudioBuffer>, DecodeError> {
+        loop {
+            if self.finished {
+                return Ok(None)
+            }
+            let packet = if let Some(packet) = self.ogg.next_packet()? {packet} else {
+                self.finished = true;
+                return Ok(None)
+            };
+            let mut channels = self.decode_packet(&packet.data)?;
+            let mut frames = channels[0].len();
+            // the granule of the last page trims the padding of the final block
+            if packet.is_last {
+                self.finished = true;
+                if let Some(granule) = packet.granule {
+                    frames = frames.min(granule.saturating_sub(self.frames_decoded) as usize);
+                }
+            }
+            if frames == 0 {
+                continue
+            }
+            self.frames_decoded += frames as u64;
+            let mut buffer = AudioBuffer::new_with_size(frames, self.channel_count);
+            for (c, channel) in channels.iter_mut().enumerate() {
+                buffer.channel_mut(c).copy_from_slice(&channel[..frames]);
+            }
+            return Ok(Some(buffer))
+        }
+    }
+
+    fn rewind(&mut self) -> Result<(), DecodeError> {
+        self.ogg.rewind()?;
+        // skip the three header packets again
+        for _ in 0..3 {
+            self.ogg.next_packet()?;
+        }
+        self.previous = None;
+        self.frames_decoded = 0;
+        self.finished = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::decode::decode_audio,
+    };
+    
+    // a hand built mono stream at 8khz: short blocks only, a flat floor 1 and residue 1
+    // carrying three fixed bins, the last page trims the length to 1892 frames
+    const TONE: &[u8] = include_bytes!("../../tests/data/tone.ogg");
+    
+    #[test]
+    fn decodes_against_reference() {
+        let decoded = decode_audio(TONE.to_vec()).unwrap();
+        assert_eq!(decoded.sample_rate, 8000.0);
+        assert_eq!(decoded.buffer.channel_count(), 1);
+        assert_eq!(decoded.buffer.frame_count(), 1892);
+        // picked from the same stream decoded with lewton
+        let reference = [
+            (17, -3.1374185e-3),
+            (250, 4.8629657e-4),
+            (483, -1.1116537e-2),
+            (716, 1.5367635e-2),
+            (949, 2.0578641e-2),
+            (1182, -4.442241e-3),
+            (1415, 2.3400206e-3),
+            (1648, 1.8231823e-3),
+        ];
+        let channel = decoded.buffer.channel(0);
+        for (frame, value) in reference {
+            assert!((channel[frame] - value).abs() < 1e-6, "frame {}: {} vs {}", frame, channel[frame], value);
+        }
+        let rms = (channel.iter().map( | v | (*v as f64) * (*v as f64)).sum::<f64>() / channel.len() as f64).sqrt();
+        assert!((rms - 1.625460066382134e-2).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn rewinds_to_the_same_audio() {
+        let mut decoder = VorbisDecoder::new(std::io::Cursor::new(TONE.to_vec())).unwrap();
+        let first = crate::decode::decode_all(&mut decoder).unwrap();
+        decoder.rewind().unwrap();
+        let second = crate::decode::decode_all(&mut decoder).unwrap();
+        assert_eq!(first.buffer.channel(0), second.buffer.channel(0));
+    }
+    
+    #[test]
+    fn broken_streams_are_errors() {
+        // cut inside the setup header
+        assert!(decode_audio(TONE[0..150].to_vec()).is_err());
+        // the first codebook loses its sync pattern
+        let mut data = TONE.to_vec();
+        data[150] ^= 0xff;
+        assert!(decode_audio(data).is_err());
+        // cut between audio pages, what came before still plays
+        if let Ok(decoded) = decode_audio(TONE[0..390].to_vec()) {
+            assert!(decoded.buffer.frame_count() < 1892);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use {
+    crate::makepad_platform::audio::*,
+    super::{AudioDecoder, DecodeError, ReadSeek},
+};
+
+const BLOCK_FRAMES: usize = 4096;
+
+impl From<WavError> for DecodeError {
+    fn from(err: WavError) -> Self {
+        match err {
+            WavError::NotWav => Self::UnknownFormat,
+            WavError::Unsupported(what) => Self::Unsupported(format!("wav {}", what)),
+            WavError::Corrupt(what) => Self::Corrupt(what),
+            WavError::Io(err) => Self::Io(err),
+        }
+    }
+}
+
+// the platform wav reader behind the decoder interface
+pub struct WavDecoder<R> {
+    reader: WavReader<R>,
+}
+
+impl<R: ReadSeek> WavDecoder<R> {
+    pub fn new(reader: R) -> Result<Self, DecodeError> {
+        Ok(Self {reader: WavReader::new(reader)?})
+    }
+}
+
+impl<R: ReadSeek> AudioDecoder for WavDecoder<R> {
+    fn sample_rate(&self) -> f64 {self.reader.sample_rate()}
+
+    fn channel_count(&self) -> usize {self.reader.channel_count()}
+
+    fn frame_count(&self) -> Option<usize> {Some(self.reader.frame_count())}
+
+    fn decode_block(&mut self) -> Result<Option<AudioBuffer>, DecodeError> {
+        Ok(self.reader.read_block(BLOCK_FRAMES)?)
+    }
+
+    fn rewind(&mut self) -> Result<(), DecodeError> {
+        Ok(self.reader.rewind()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::decode::decode_audio,
+    };
+    
+    fn chunk(id: &[u8], size: u32, data: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+    
+    fn fmt(tag: u16, channel_count: u16, bits_per_sample: u16) -> Vec<u8> {
+        let block_align = channel_count * bits_per_sample / 8;
+        let mut data = Vec::new();
+        data.extend_from_slice(&tag.to_le_bytes());
+        data.extend_from_slice(&channel_count.to_le_bytes());
+        data.extend_from_slice(&48000u32.to_le_bytes());
+        data.extend_from_slice(&(48000 * block_align as u32).to_le_bytes());
+        data.extend_from_slice(&block_align.to_le_bytes());
+        data.extend_from_slice(&bits_per_sample.to_le_bytes());
+        data
+    }
+    
+    fn riff(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut out = b"RIFF".to_vec();
+        out.extend_from_slice(&(4 + body.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+        out.extend_from_slice(&body);
+        out
+    }
+    
+    fn pcm16(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map( | s | s.to_le_bytes()).collect()
+    }
+    
+    #[test]
+    fn decodes_pcm_and_float() {
+        let samples = [0i16, 16384, -16384, 32767, -32768, 8192];
+        let data = pcm16(&samples);
+        let fmt = fmt(1, 2, 16);
+        let file = riff(&[chunk(b"fmt ", fmt.len() as u32, &fmt), chunk(b"data", data.len() as u32, &data)]);
+        let decoded = decode_audio(file).unwrap();
+        assert_eq!(decoded.sample_rate, 48000.0);
+        assert_eq!(decoded.buffer.frame_count(), 3);
+        assert_eq!(decoded.buffer.channel(0), &[0.0, -0.5, -1.0]);
+        assert_eq!(decoded.buffer.channel(1), &[0.5, 32767.0 / 32768.0, 0.25]);
+        
+        let floats = [0.25f32, -0.75, 1.5];
+        let data: Vec<u8> = floats.iter().flat_map( | s | s.to_le_bytes()).collect();
+        let fmt = self::fmt(3, 1, 32);
+        let file = riff(&[chunk(b"fmt ", fmt.len() as u32, &fmt), chunk(b"data", data.len() as u32, &data)]);
+        assert_eq!(decode_audio(file).unwrap().buffer.channel(0), &floats);
+    }
+    
+    #[test]
+    fn skips_padded_chunks_and_reads_extensible() {
+        // extensible fmt with the pcm subformat, after an odd sized chunk and its pad byte
+        let mut fmt = fmt(0xfffe, 1, 24);
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&24u16.to_le_bytes());
+        fmt.extend_from_slice(&4u32.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&[0; 14]);
+        let data = [0x00, 0x00, 0x40, 0x00, 0x00, 0xc0];
+        let file = riff(&[
+            chunk(b"LIST", 3, &[1, 2, 3, 0]),
+            chunk(b"fmt ", fmt.len() as u32, &fmt),
+            chunk(b"data", data.len() as u32, &data)
+        ]);
+        assert_eq!(decode_audio(file).unwrap().buffer.channel(0), &[0.5, -0.5]);
+    }
+    
+    #[test]
+    fn streamed_and_truncated_data_end_cleanly() {
+        let fmt = fmt(1, 2, 16);
+        // a streamed file never got its data size filled in
+        let data = pcm16(&[1000; 20]);
+        let file = riff(&[chunk(b"fmt ", fmt.len() as u32, &fmt), chunk(b"data", 0xffffffff, &data)]);
+        let mut decoder = WavDecoder::new(std::io::Cursor::new(file)).unwrap();
+        assert_eq!(decoder.frame_count(), Some(10));
+        assert_eq!(decoder.decode_block().unwrap().unwrap().frame_count(), 10);
+        assert!(decoder.decode_block().unwrap().is_none());
+        
+        // the header promises 100 frames, seven and a half made it to disk
+        let mut data = pcm16(&[1000; 15]);
+        data.push(0);
+        let file = riff(&[chunk(b"fmt ", fmt.len() as u32, &fmt), chunk(b"data", 400, &data)]);
+        assert_eq!(decode_audio(file).unwrap().buffer.frame_count(), 7);
+    }
+    
+    #[test]
+    fn rejects_chunk_sizes_past_the_end() {
+        let fmt = fmt(1, 2, 16);
+        let file = riff(&[chunk(b"fmt ", 0x7fffffff, &fmt)]);
+        assert!(matches!(WavDecoder::new(std::io::Cursor::new(file)), Err(DecodeError::Corrupt(_))));
+        // a header cut off in the middle of a chunk
+        let file = riff(&[chunk(b"fmt ", fmt.len() as u32, &fmt[0..6])]);
+        assert!(WavDecoder::new(std::io::Cursor::new(file)).is_err());
+        assert!(WavDecoder::new(std::io::Cursor::new(b"RIFF\0\0\0\0WAVE".to_vec())).is_err());
+    }
+}
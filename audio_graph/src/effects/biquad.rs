@@ -0,0 +1,330 @@
+use {
+    std::f64::consts::PI,
+    crate::{
+        register_audio_component,
+        audio_traits::*,
+        parameter::*,
+        effects::*,
+    },
+};
+
+// Second order filters after the RBJ audio EQ cookbook, and a parametric
+// equalizer built from a chain of them.
+//
+// eq = <Equalizer> {
+//     bands: [
+//         {kind: HighPass, freq: 40.0}
+//         {kind: Peak, freq: 2500.0, gain: -3.0, q: 1.4}
+//         {kind: HighShelf, freq: 8000.0, gain: 2.0}
+//     ]
+// }
+
+live_design!{
+    Equalizer = {{Equalizer}} {
+    }
+}
+
+// coefficients are recalculated at most this often while a band glides
+const COEFFICIENT_INTERVAL: usize = 16;
+
+#[derive(Live, LiveHook, LiveRead, Clone, Copy, Debug, PartialEq)]
+pub enum BiquadKind {
+    #[pick] Peak,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+    AllPass,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiquadCoefficients {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Default for BiquadCoefficients {
+    fn default() -> Self {
+        Self {b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0}
+    }
+}
+
+impl BiquadCoefficients {
+    pub fn new(kind: BiquadKind, freq: f64, q: f64, gain_db: f64, sample_rate: f64) -> Self {
+        let freq = freq.clamp(1.0, sample_rate * 0.49);
+        let q = q.max(0.01);
+        let a = 10.0f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * PI * freq / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let (b0, b1, b2, a0, a1, a2) = match kind {
+            BiquadKind::Peak => (1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a, 1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a),
+            BiquadKind::LowShelf => {
+                let sq = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos + sq),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - sq),
+                    (a + 1.0) + (a - 1.0) * cos + sq,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - sq,
+                )
+            }
+            BiquadKind::HighShelf => {
+                let sq = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos + sq),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - sq),
+                    (a + 1.0) - (a - 1.0) * cos + sq,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - sq,
+                )
+            }
+            BiquadKind::LowPass => ((1.0 - cos) * 0.5, 1.0 - cos, (1.0 - cos) * 0.5, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadKind::HighPass => ((1.0 + cos) * 0.5, -(1.0 + cos), (1.0 + cos) * 0.5, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadKind::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadKind::Notch => (1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadKind::AllPass => (1.0 - alpha, -2.0 * cos, 1.0 + alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+        };
+        Self {b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: a1 / a0, a2: a2 / a0}
+    }
+
+    // the gain of the filter at a frequency, for drawing response curves
+    pub fn magnitude_at(&self, freq: f64, sample_rate: f64) -> f64 {
+        let w = 2.0 * PI * freq / sample_rate;
+        let (sin1, cos1) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+        let num_re = self.b0 + self.b1 * cos1 + self.b2 * cos2;
+        let num_im = -self.b1 * sin1 - self.b2 * sin2;
+        let den_re = 1.0 + self.a1 * cos1 + self.a2 * cos2;
+        let den_im = -self.a1 * sin1 - self.a2 * sin2;
+        ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).sqrt()
+    }
+}
+
+// transposed direct form II, one per channel
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Biquad {
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    pub fn process(&mut self, c: &BiquadCoefficients, input: f32) -> f32 {
+        let x = input as f64;
+        let y = c.b0 * x + self.z1;
+        self.z1 = c.b1 * x - c.a1 * y + self.z2;
+        self.z2 = c.b2 * x - c.a2 * y;
+        y as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+#[derive(Clone, Debug, Live, LiveHook, LiveRead)]
+pub struct EqBand {
+    #[live] kind: BiquadKind,
+    #[live(1000.0)] freq: f64,
+    // in decibels, used by peak and shelf bands
+    #[live(0.0)] gain: f64,
+    #[live(0.707)] q: f64,
+}
+
+impl EqBand {
+    pub fn new(kind: BiquadKind, freq: f64, gain: f64, q: f64) -> Self {
+        Self {kind, freq, gain, q}
+    }
+}
+
+struct BandState {
+    kind: BiquadKind,
+    coefficients: BiquadCoefficients,
+    // freq, gain and q the coefficients were made from
+    made_from: [f32; 3],
+    filters: Vec<Biquad>,
+}
+
+// Each band reads three parameters, band n has n_freq, n_gain and n_q.
+pub struct EqualizerState {
+    bands: Vec<BandState>,
+    sample_rate: f64,
+}
+
+impl EqualizerState {
+    pub fn new(kinds: &[BiquadKind]) -> Self {
+        Self {
+            bands: kinds.iter().map( | kind | BandState {
+                kind: *kind,
+                coefficients: BiquadCoefficients::default(),
+                made_from: [f32::NAN; 3],
+                filters: Vec::new(),
+            }).collect(),
+            sample_rate: 0.0,
+        }
+    }
+
+    // the combined gain of all bands at a frequency
+    pub fn magnitude_at(&self, freq: f64) -> f64 {
+        self.bands.iter().map( | band | band.coefficients.magnitude_at(freq, self.sample_rate)).product()
+    }
+
+    fn update_coefficients(&mut self, params: &ParamSet, sample_rate: f64) {
+        let rate_changed = self.sample_rate != sample_rate;
+        self.sample_rate = sample_rate;
+        for (index, band) in self.bands.iter_mut().enumerate() {
+            let values = [params.value(index * 3), params.value(index * 3 + 1), params.value(index * 3 + 2)];
+            if rate_changed || values != band.made_from {
+                band.coefficients = BiquadCoefficients::new(band.kind, values[0] as f64, values[2] as f64, values[1] as f64, sample_rate);
+                band.made_from = values;
+            }
+        }
+    }
+}
+
+impl AudioEffect for EqualizerState {
+    fn params(&self) -> Vec<ParamInfo> {
+        let mut params = Vec::new();
+        for index in 0..self.bands.len() {
+            let n = index + 1;
+            params.push(ParamInfo::new(local_param_id(&format!("{}_freq", n)), 20.0, 20000.0, 1000.0)
+                .with_unit(ParamUnit::Hertz).with_curve(ParamCurve::Exponential).with_smoothing(0.05));
+            params.push(ParamInfo::new(local_param_id(&format!("{}_gain", n)), -24.0, 24.0, 0.0)
+                .with_unit(ParamUnit::Decibels).with_smoothing(0.05));
+            params.push(ParamInfo::new(local_param_id(&format!("{}_q", n)), 0.1, 18.0, 0.707)
+                .with_curve(ParamCurve::Exponential).with_smoothing(0.05));
+        }
+        params
+    }
+
+    fn process(&mut self, params: &mut ParamSet, buffer: &mut AudioBuffer, _sidechain: Option<&AudioBuffer>, sample_rate: f64) {
+        let channel_count = buffer.channel_count();
+        for band in &mut self.bands {
+            band.filters.resize(channel_count, Biquad::default());
+        }
+        for i in 0..buffer.frame_count() {
+            params.tick();
+            if i % COEFFICIENT_INTERVAL == 0 {
+                self.update_coefficients(params, sample_rate);
+            }
+            for c in 0..channel_count {
+                let sample = &mut buffer.channel_mut(c)[i];
+                for band in &mut self.bands {
+                    *sample = band.filters[c].process(&band.coefficients, *sample);
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        for band in &mut self.bands {
+            band.filters.iter_mut().for_each( | f | f.reset());
+        }
+    }
+}
+
+#[derive(Live, LiveRead)]
+pub struct Equalizer {
+    #[live] bands: Vec<EqBand>,
+    #[rust] effect: EffectComponent,
+}
+
+impl LiveHook for Equalizer {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, Equalizer)
+    }
+
+    fn after_apply(&mut self, _cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) {
+        let values = self.bands.iter().flat_map( | band | [band.freq, band.gain, band.q]).collect();
+        self.effect.apply(from, nodes[index].id, self.state().params(), values);
+    }
+}
+
+impl Equalizer {
+    fn state(&self) -> EqualizerState {
+        EqualizerState::new(&self.bands.iter().map( | band | band.kind).collect::<Vec<_ >> ())
+    }
+}
+
+impl AudioComponent for Equalizer {
+    fn get_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        let state = self.state();
+        self.effect.graph_node(state, false)
+    }
+
+    fn get_offline_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        let state = self.state();
+        self.effect.graph_node(state, true)
+    }
+
+    fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+    }
+
+    fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        AudioResult::not_found()
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        self.effect.params()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        self.live_read_to(id, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn rms(samples: &[f32]) -> f64 {
+        (samples.iter().map( | s | (*s as f64) * (*s as f64)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    // the level a sine at freq comes out with, measured after the filter settled
+    fn sine_gain(state: &mut EqualizerState, params: &mut ParamSet, freq: f64) -> f64 {
+        state.reset();
+        let frames = 9600;
+        let mut buffer = AudioBuffer::new_with_size(frames, 1);
+        for (i, sample) in buffer.channel_mut(0).iter_mut().enumerate() {
+            *sample = (2.0 * PI * freq * i as f64 / SAMPLE_RATE).sin() as f32;
+        }
+        let input = rms(&buffer.channel(0)[frames / 2..]);
+        state.process_buffer(params, &mut buffer, None, SAMPLE_RATE);
+        rms(&buffer.channel(0)[frames / 2..]) / input
+    }
+
+    #[test]
+    fn peak_gain_at_centre_frequency() {
+        let mut state = EqualizerState::new(&[BiquadKind::Peak]);
+        let mut params = ParamSet::new(state.params());
+        params.jump_index(0, 1000.0);
+        params.jump_index(1, 6.0);
+        params.jump_index(2, 1.0);
+        let gain = sine_gain(&mut state, &mut params, 1000.0);
+        assert!((gain - db_to_gain(6.0) as f64).abs() < 0.01, "gain {}", gain);
+        assert!((state.magnitude_at(1000.0) - db_to_gain(6.0) as f64).abs() < 1e-6);
+        // far from the centre the band leaves the signal alone
+        let gain = sine_gain(&mut state, &mut params, 50.0);
+        assert!((gain - 1.0).abs() < 0.01, "gain {}", gain);
+    }
+
+    #[test]
+    fn notch_removes_centre_frequency() {
+        let mut state = EqualizerState::new(&[BiquadKind::Notch]);
+        let mut params = ParamSet::new(state.params());
+        params.jump_index(0, 2000.0);
+        params.jump_index(2, 2.0);
+        assert!(sine_gain(&mut state, &mut params, 2000.0) < 0.01);
+    }
+}
@@ -0,0 +1,269 @@
+use {
+    std::f64::consts::PI,
+    crate::{
+        register_audio_component,
+        audio_traits::*,
+        parameter::*,
+        effects::*,
+    },
+};
+
+// A feedback delay with a damped feedback path and optional ping pong. With sync on
+// the time is set in quarter notes and follows the bpm parameter a Sequencer sends,
+// or the bpm field when nothing does.
+//
+// echo = <Delay> {sync: true, beats: 0.75, feedback: 0.45, ping_pong: true}
+
+live_design!{
+    Delay = {{Delay}} {
+    }
+}
+
+const TIME: usize = 0;
+const SYNC: usize = 1;
+const BEATS: usize = 2;
+const FEEDBACK: usize = 3;
+const MIX: usize = 4;
+const PING_PONG: usize = 5;
+const DAMPING: usize = 6;
+
+const MAX_DELAY_SECONDS: f64 = 4.0;
+
+pub struct DelayState {
+    bpm: f64,
+    lines: Vec<Vec<f32>>,
+    filters: Vec<f32>,
+    write: usize,
+    // in frames, glides so time changes bend the pitch instead of clicking
+    delay: SmoothedParam,
+    sample_rate: f64,
+}
+
+impl Default for DelayState {
+    fn default() -> Self {
+        Self::new(120.0)
+    }
+}
+
+impl DelayState {
+    pub fn new(bpm: f64) -> Self {
+        Self {
+            bpm,
+            lines: Vec::new(),
+            filters: Vec::new(),
+            write: 0,
+            delay: SmoothedParam::new(0.0),
+            sample_rate: 0.0,
+        }
+    }
+
+    fn delay_frames(&self, params: &ParamSet) -> f32 {
+        let seconds = if params.value(SYNC) >= 0.5 {
+            params.value(BEATS) as f64 * 60.0 / self.bpm.max(1.0)
+        }
+        else {
+            params.value(TIME) as f64
+        };
+        (seconds.min(MAX_DELAY_SECONDS) * self.sample_rate).max(1.0) as f32
+    }
+
+    fn read(line: &[f32], write: usize, delay: f32) -> f32 {
+        let len = line.len();
+        let position = write as f32 + len as f32 - delay;
+        let index = position.floor() as usize;
+        let frac = position - position.floor();
+        let a = line[index % len];
+        let b = line[(index + 1) % len];
+        a + (b - a) * frac
+    }
+}
+
+impl AudioEffect for DelayState {
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo::new(local_param_id("time"), 0.001, 2.0, 0.375).with_unit(ParamUnit::Seconds).with_curve(ParamCurve::Exponential),
+            ParamInfo::new(local_param_id("sync"), 0.0, 1.0, 1.0).with_curve(ParamCurve::Stepped).with_smoothing(0.0),
+            ParamInfo::new(local_param_id("beats"), 0.0625, 4.0, 0.75),
+            ParamInfo::new(local_param_id("feedback"), 0.0, 0.98, 0.4).with_unit(ParamUnit::Percent),
+            ParamInfo::new(local_param_id("mix"), 0.0, 1.0, 0.3).with_unit(ParamUnit::Percent),
+            ParamInfo::new(local_param_id("ping_pong"), 0.0, 1.0, 0.0).with_curve(ParamCurve::Stepped).with_smoothing(0.0),
+            ParamInfo::new(local_param_id("damping"), 500.0, 20000.0, 8000.0).with_unit(ParamUnit::Hertz).with_curve(ParamCurve::Exponential),
+        ]
+    }
+
+    fn process(&mut self, params: &mut ParamSet, buffer: &mut AudioBuffer, _sidechain: Option<&AudioBuffer>, sample_rate: f64) {
+        let channel_count = buffer.channel_count();
+        if self.sample_rate != sample_rate || self.lines.len() != channel_count {
+            self.sample_rate = sample_rate;
+            let len = (MAX_DELAY_SECONDS * sample_rate) as usize + 2;
+            self.lines = vec![vec![0.0; len]; channel_count];
+            self.filters = vec![0.0; channel_count];
+            self.write = 0;
+            self.delay.set_time(0.1, sample_rate);
+            self.delay.jump(self.delay_frames(params));
+        }
+        let len = self.lines.first().map_or(1, | line | line.len());
+        let mut delayed = [0.0f32; 2];
+        for i in 0..buffer.frame_count() {
+            params.tick();
+            self.delay.set(self.delay_frames(params));
+            let delay = self.delay.next();
+            let feedback = params.value(FEEDBACK);
+            let mix = params.value(MIX);
+            let damping = (-2.0 * PI * params.value(DAMPING) as f64 / sample_rate).exp() as f32;
+            if channel_count == 2 && params.value(PING_PONG) >= 0.5 {
+                for c in 0..2 {
+                    let tap = Self::read(&self.lines[c], self.write, delay);
+                    self.filters[c] = tap * (1.0 - damping) + self.filters[c] * damping;
+                    delayed[c] = self.filters[c];
+                }
+                let (left, right) = buffer.stereo_mut();
+                // the input enters on the left and every repeat swaps sides
+                self.lines[0][self.write] = (left[i] + right[i]) * 0.5 + delayed[1] * feedback;
+                self.lines[1][self.write] = delayed[0] * feedback;
+                left[i] = left[i] * (1.0 - mix) + delayed[0] * mix;
+                right[i] = right[i] * (1.0 - mix) + delayed[1] * mix;
+            }
+            else {
+                for c in 0..channel_count {
+                    let tap = Self::read(&self.lines[c], self.write, delay);
+                    self.filters[c] = tap * (1.0 - damping) + self.filters[c] * damping;
+                    let sample = &mut buffer.channel_mut(c)[i];
+                    self.lines[c][self.write] = *sample + self.filters[c] * feedback;
+                    *sample = *sample * (1.0 - mix) + self.filters[c] * mix;
+                }
+            }
+            self.write = (self.write + 1) % len;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.lines.clear();
+        self.sample_rate = 0.0;
+    }
+
+    fn set_tempo(&mut self, bpm: f64) {
+        self.bpm = bpm;
+    }
+}
+
+#[derive(Live, LiveRead)]
+pub struct Delay {
+    #[live(0.375)] time: f64,
+    #[live(true)] sync: bool,
+    #[live(0.75)] beats: f64,
+    #[live(0.4)] feedback: f64,
+    #[live(0.3)] mix: f64,
+    #[live(false)] ping_pong: bool,
+    #[live(8000.0)] damping: f64,
+    // the tempo until a sequencer sets one
+    #[live(120.0)] bpm: f64,
+    #[rust] effect: EffectComponent,
+}
+
+impl LiveHook for Delay {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, Delay)
+    }
+
+    fn after_apply(&mut self, _cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) {
+        let values = vec![
+            self.time,
+            if self.sync {1.0} else {0.0},
+            self.beats,
+            self.feedback,
+            self.mix,
+            if self.ping_pong {1.0} else {0.0},
+            self.damping
+        ];
+        self.effect.apply(from, nodes[index].id, DelayState::default().params(), values);
+    }
+}
+
+impl AudioComponent for Delay {
+    fn get_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(DelayState::new(self.bpm), false)
+    }
+
+    fn get_offline_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(DelayState::new(self.bpm), true)
+    }
+
+    fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+    }
+
+    fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        AudioResult::not_found()
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        self.effect.params()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        self.live_read_to(id, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn impulse_response(state: &mut DelayState, params: &mut ParamSet, frames: usize) -> Vec<f32> {
+        let mut buffer = AudioBuffer::new_with_size(frames, 1);
+        buffer.channel_mut(0)[0] = 1.0;
+        state.process_buffer(params, &mut buffer, None, SAMPLE_RATE);
+        buffer.channel(0).to_vec()
+    }
+
+    fn loudest(samples: &[f32]) -> usize {
+        (0..samples.len()).max_by( | a, b | samples[*a].abs().total_cmp(&samples[*b].abs())).unwrap()
+    }
+
+    // fully wet, no feedback and the damping filter as open as it goes
+    fn params(state: &DelayState, sync: bool) -> ParamSet {
+        let mut params = ParamSet::new(state.params());
+        params.jump_index(SYNC, if sync {1.0} else {0.0});
+        params.jump_index(BEATS, 0.5);
+        params.jump_index(TIME, 0.1);
+        params.jump_index(FEEDBACK, 0.0);
+        params.jump_index(MIX, 1.0);
+        params.jump_index(DAMPING, 20000.0);
+        params
+    }
+
+    #[test]
+    fn synced_tap_follows_tempo() {
+        // half a beat is 0.25s at 120 bpm and a third of a second at 90
+        for (bpm, frames) in [(120.0, 12000), (90.0, 16000)] {
+            let mut state = DelayState::new(120.0);
+            state.set_tempo(bpm);
+            let mut params = params(&state, true);
+            let response = impulse_response(&mut state, &mut params, 20000);
+            assert_eq!(loudest(&response), frames, "at {} bpm", bpm);
+            assert!(response[..frames].iter().all( | s | *s == 0.0));
+        }
+    }
+
+    #[test]
+    fn free_tap_uses_time() {
+        let mut state = DelayState::default();
+        let mut params = params(&state, false);
+        let response = impulse_response(&mut state, &mut params, 20000);
+        assert_eq!(loudest(&response), 4800);
+    }
+
+    #[test]
+    fn feedback_repeats_at_the_tap() {
+        let mut state = DelayState::new(120.0);
+        let mut params = params(&state, true);
+        params.jump_index(FEEDBACK, 0.5);
+        let response = impulse_response(&mut state, &mut params, 30000);
+        let first = loudest(&response[..18000]);
+        let second = 18000 + loudest(&response[18000..]);
+        assert_eq!((first, second), (12000, 24000));
+        assert!((response[second] / response[first] - 0.5).abs() < 0.1);
+    }
+}
@@ -0,0 +1,391 @@
+use {
+    std::collections::VecDeque,
+    crate::{
+        register_audio_component,
+        audio_traits::*,
+        parameter::*,
+        effects::*,
+    },
+};
+
+// A feed forward compressor and a lookahead brickwall limiter. The compressor
+// listens to its sidechain port when something is routed there.
+//
+// duck = <Compressor> {threshold: -30.0, ratio: 8.0, release: 0.25}
+// wires: [{from: kick, to: duck, to_port: sidechain}, {from: pad, to: duck}]
+
+live_design!{
+    Compressor = {{Compressor}} {
+    }
+    Limiter = {{Limiter}} {
+    }
+}
+
+const THRESHOLD: usize = 0;
+const RATIO: usize = 1;
+const KNEE: usize = 2;
+const ATTACK: usize = 3;
+const RELEASE: usize = 4;
+const MAKEUP: usize = 5;
+
+fn time_coefficient(seconds: f32, sample_rate: f64) -> f32 {
+    if seconds <= 0.0 {
+        return 0.0
+    }
+    (-1.0 / (seconds as f64 * sample_rate)).exp() as f32
+}
+
+#[derive(Default)]
+pub struct CompressorState {
+    // smoothed gain reduction in decibels, zero or below
+    envelope: f32,
+}
+
+impl CompressorState {
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.envelope
+    }
+
+    // the static curve with a soft knee, returns the reduction in decibels
+    pub fn gain_computer(level_db: f32, threshold: f32, ratio: f32, knee: f32) -> f32 {
+        let over = level_db - threshold;
+        let slope = 1.0 / ratio.max(1.0) - 1.0;
+        if knee > 0.0 && over.abs() * 2.0 <= knee {
+            let x = over + knee * 0.5;
+            slope * x * x / (2.0 * knee)
+        }
+        else if over > 0.0 {
+            slope * over
+        }
+        else {
+            0.0
+        }
+    }
+}
+
+impl AudioEffect for CompressorState {
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo::new(local_param_id("threshold"), -60.0, 0.0, -18.0).with_unit(ParamUnit::Decibels),
+            ParamInfo::new(local_param_id("ratio"), 1.0, 20.0, 4.0).with_unit(ParamUnit::Ratio).with_curve(ParamCurve::Exponential),
+            ParamInfo::new(local_param_id("knee"), 0.0, 24.0, 6.0).with_unit(ParamUnit::Decibels),
+            ParamInfo::new(local_param_id("attack"), 0.0001, 0.5, 0.01).with_unit(ParamUnit::Seconds).with_curve(ParamCurve::Exponential),
+            ParamInfo::new(local_param_id("release"), 0.01, 2.0, 0.1).with_unit(ParamUnit::Seconds).with_curve(ParamCurve::Exponential),
+            ParamInfo::new(local_param_id("makeup"), 0.0, 24.0, 0.0).with_unit(ParamUnit::Decibels),
+        ]
+    }
+
+    fn process(&mut self, params: &mut ParamSet, buffer: &mut AudioBuffer, sidechain: Option<&AudioBuffer>, sample_rate: f64) {
+        let sidechain = sidechain.filter( | s | s.channel_count() > 0 && s.frame_count() == buffer.frame_count());
+        let channel_count = buffer.channel_count();
+        for i in 0..buffer.frame_count() {
+            params.tick();
+            let mut peak = 0.0f32;
+            match sidechain {
+                Some(key) => for c in 0..key.channel_count() {
+                    peak = peak.max(key.channel(c)[i].abs());
+                }
+                None => for c in 0..channel_count {
+                    peak = peak.max(buffer.channel(c)[i].abs());
+                }
+            }
+            let target = Self::gain_computer(gain_to_db(peak), params.value(THRESHOLD), params.value(RATIO), params.value(KNEE));
+            // more reduction follows the attack, less follows the release
+            let coefficient = if target < self.envelope {
+                time_coefficient(params.value(ATTACK), sample_rate)
+            }
+            else {
+                time_coefficient(params.value(RELEASE), sample_rate)
+            };
+            self.envelope = target + (self.envelope - target) * coefficient;
+            let gain = db_to_gain(self.envelope + params.value(MAKEUP));
+            for c in 0..channel_count {
+                buffer.channel_mut(c)[i] *= gain;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+}
+
+const CEILING: usize = 0;
+const LIMITER_RELEASE: usize = 1;
+
+// Keeps every sample under the ceiling. The signal is delayed by the lookahead,
+// the gain is the minimum needed over that window averaged over the same window,
+// so it is already down when a peak comes through and never steps.
+pub struct LimiterState {
+    lookahead: f64,
+    window: usize,
+    // required gains with their frame, increasing, for the sliding minimum
+    minimum: VecDeque<(u64, f32)>,
+    // the last window of minimums for the moving average
+    averages: Vec<f32>,
+    average_sum: f64,
+    delay: Vec<Vec<f32>>,
+    position: u64,
+    gain: f32,
+    sample_rate: f64,
+}
+
+impl Default for LimiterState {
+    fn default() -> Self {
+        Self::new(0.005)
+    }
+}
+
+impl LimiterState {
+    pub fn new(lookahead: f64) -> Self {
+        Self {
+            lookahead,
+            window: 0,
+            minimum: VecDeque::new(),
+            averages: Vec::new(),
+            average_sum: 0.0,
+            delay: Vec::new(),
+            position: 0,
+            gain: 1.0,
+            sample_rate: 0.0,
+        }
+    }
+
+    // frames the output lags behind the input
+    pub fn latency(&self, sample_rate: f64) -> usize {
+        ((self.lookahead * sample_rate).round() as usize).max(1) - 1
+    }
+
+    pub fn gain_reduction_db(&self) -> f32 {
+        gain_to_db(self.gain)
+    }
+
+    fn prepare(&mut self, channel_count: usize, sample_rate: f64) {
+        if self.sample_rate != sample_rate || self.delay.len() != channel_count {
+            self.sample_rate = sample_rate;
+            self.window = ((self.lookahead * sample_rate).round() as usize).max(1);
+            self.minimum = VecDeque::with_capacity(self.window + 1);
+            self.averages = vec![1.0; self.window];
+            self.average_sum = self.window as f64;
+            self.delay = vec![vec![0.0; self.window]; channel_count];
+            self.position = 0;
+            self.gain = 1.0;
+        }
+    }
+}
+
+impl AudioEffect for LimiterState {
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo::new(local_param_id("ceiling"), -24.0, 0.0, -0.3).with_unit(ParamUnit::Decibels),
+            ParamInfo::new(local_param_id("release"), 0.001, 1.0, 0.05).with_unit(ParamUnit::Seconds).with_curve(ParamCurve::Exponential),
+        ]
+    }
+
+    fn process(&mut self, params: &mut ParamSet, buffer: &mut AudioBuffer, _sidechain: Option<&AudioBuffer>, sample_rate: f64) {
+        let channel_count = buffer.channel_count();
+        self.prepare(channel_count, sample_rate);
+        let window = self.window;
+        for i in 0..buffer.frame_count() {
+            params.tick();
+            let ceiling = db_to_gain(params.value(CEILING));
+            let mut peak = 0.0f32;
+            for c in 0..channel_count {
+                peak = peak.max(buffer.channel(c)[i].abs());
+            }
+            let required = if peak > ceiling {ceiling / peak} else {1.0};
+            // sliding minimum over the window
+            while self.minimum.back().map_or(false, | (_, g) | *g >= required) {
+                self.minimum.pop_back();
+            }
+            self.minimum.push_back((self.position, required));
+            while self.minimum.front().map_or(false, | (frame, _) | *frame + window as u64 <= self.position) {
+                self.minimum.pop_front();
+            }
+            let minimum = self.minimum.front().map_or(1.0, | (_, g) | *g);
+            // moving average of the minimum over the same window
+            let slot = (self.position % window as u64) as usize;
+            self.average_sum += (minimum - self.averages[slot]) as f64;
+            self.averages[slot] = minimum;
+            let smoothed = (self.average_sum / window as f64) as f32;
+            let release = time_coefficient(params.value(LIMITER_RELEASE), sample_rate);
+            self.gain = smoothed.min(1.0 - (1.0 - self.gain) * release);
+            // delay the signal so the gain lines up with it
+            let read = ((self.position + 1) % window as u64) as usize;
+            for c in 0..channel_count {
+                let line = &mut self.delay[c];
+                let input = buffer.channel(c)[i];
+                line[slot] = input;
+                let delayed = if window > 1 {line[read]} else {input};
+                buffer.channel_mut(c)[i] = delayed * self.gain;
+            }
+            self.position += 1;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.sample_rate = 0.0;
+        self.delay.clear();
+    }
+}
+
+#[derive(Live, LiveRead)]
+pub struct Compressor {
+    #[live(-18.0)] threshold: f64,
+    #[live(4.0)] ratio: f64,
+    #[live(6.0)] knee: f64,
+    #[live(0.01)] attack: f64,
+    #[live(0.1)] release: f64,
+    #[live(0.0)] makeup: f64,
+    #[rust] effect: EffectComponent,
+}
+
+impl LiveHook for Compressor {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, Compressor)
+    }
+
+    fn after_apply(&mut self, _cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) {
+        let values = vec![self.threshold, self.ratio, self.knee, self.attack, self.release, self.makeup];
+        self.effect.apply(from, nodes[index].id, CompressorState::default().params(), values);
+    }
+}
+
+impl AudioComponent for Compressor {
+    fn get_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(CompressorState::default(), false)
+    }
+
+    fn get_offline_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(CompressorState::default(), true)
+    }
+
+    fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+    }
+
+    fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        AudioResult::not_found()
+    }
+
+    fn input_ports(&self) -> Vec<LiveId> {
+        vec![live_id!(main), live_id!(sidechain)]
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        self.effect.params()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        self.live_read_to(id, out);
+    }
+}
+
+#[derive(Live, LiveRead)]
+pub struct Limiter {
+    #[live(-0.3)] ceiling: f64,
+    #[live(0.05)] release: f64,
+    // seconds the output is delayed so peaks are caught before they pass
+    #[live(0.005)] lookahead: f64,
+    #[rust] effect: EffectComponent,
+}
+
+impl LiveHook for Limiter {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, Limiter)
+    }
+
+    fn after_apply(&mut self, _cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) {
+        let values = vec![self.ceiling, self.release];
+        self.effect.apply(from, nodes[index].id, LimiterState::default().params(), values);
+    }
+}
+
+impl AudioComponent for Limiter {
+    fn get_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(LimiterState::new(self.lookahead.clamp(0.0, 0.1)), false)
+    }
+
+    fn get_offline_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(LimiterState::new(self.lookahead.clamp(0.0, 0.1)), true)
+    }
+
+    fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+    }
+
+    fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        AudioResult::not_found()
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        self.effect.params()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        self.live_read_to(id, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+
+    fn constant(value: f32, frames: usize) -> AudioBuffer {
+        AudioBuffer::from_data(vec![value; frames], 1)
+    }
+
+    // threshold -20 dB, 4:1, hard knee and an attack fast enough to settle within the buffer
+    fn compressor() -> (CompressorState, ParamSet) {
+        let state = CompressorState::default();
+        let mut params = ParamSet::new(state.params());
+        params.jump_index(THRESHOLD, -20.0);
+        params.jump_index(RATIO, 4.0);
+        params.jump_index(KNEE, 0.0);
+        params.jump_index(ATTACK, 0.0001);
+        params.jump_index(MAKEUP, 0.0);
+        (state, params)
+    }
+
+    #[test]
+    fn gain_computer_curve() {
+        assert_eq!(CompressorState::gain_computer(-30.0, -20.0, 4.0, 0.0), 0.0);
+        assert!((CompressorState::gain_computer(-8.0, -20.0, 4.0, 0.0) + 9.0).abs() < 1e-5);
+        // the knee is halfway at the threshold
+        assert!((CompressorState::gain_computer(-20.0, -20.0, 4.0, 6.0) + 0.5625).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reduces_gain_above_threshold() {
+        let (mut state, mut params) = compressor();
+        // 0.5 is about 14 dB over, a quarter of that is left
+        let mut buffer = constant(0.5, 4800);
+        state.process_buffer(&mut params, &mut buffer, None, SAMPLE_RATE);
+        let expected_db = -(1.0 - 1.0 / 4.0) * (gain_to_db(0.5) + 20.0);
+        assert!((state.gain_reduction_db() - expected_db).abs() < 1e-3, "reduction {}", state.gain_reduction_db());
+        let last = *buffer.channel(0).last().unwrap();
+        assert!((last - 0.5 * db_to_gain(expected_db)).abs() < 1e-4, "output {}", last);
+        // below the threshold nothing changes
+        let (mut state, mut params) = compressor();
+        let mut buffer = constant(0.05, 4800);
+        state.process_buffer(&mut params, &mut buffer, None, SAMPLE_RATE);
+        assert!(buffer.channel(0).iter().all( | s | (*s - 0.05).abs() < 1e-6));
+    }
+
+    #[test]
+    fn sidechain_drives_the_reduction() {
+        let (mut state, mut params) = compressor();
+        let mut buffer = constant(0.05, 4800);
+        let key = constant(0.5, 4800);
+        state.process_buffer(&mut params, &mut buffer, Some(&key), SAMPLE_RATE);
+        let expected_db = -(1.0 - 1.0 / 4.0) * (gain_to_db(0.5) + 20.0);
+        let last = *buffer.channel(0).last().unwrap();
+        assert!((last - 0.05 * db_to_gain(expected_db)).abs() < 1e-5, "output {}", last);
+        // a sidechain of the wrong length is ignored
+        let (mut state, mut params) = compressor();
+        let mut buffer = constant(0.05, 4800);
+        let key = constant(0.5, 100);
+        state.process_buffer(&mut params, &mut buffer, Some(&key), SAMPLE_RATE);
+        assert!((buffer.channel(0)[4799] - 0.05).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,130 @@
+use {
+    crate::{
+        register_audio_component,
+        audio_traits::*,
+        parameter::*,
+        effects::*,
+    },
+};
+
+live_design!{
+    GainPan = {{GainPan}} {
+    }
+}
+
+const GAIN: usize = 0;
+const PAN: usize = 1;
+
+// Level in decibels and stereo balance, mono buffers only get the gain.
+#[derive(Default)]
+pub struct GainPanState {
+}
+
+impl AudioEffect for GainPanState {
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo::new(local_param_id("gain"), -60.0, 24.0, 0.0).with_unit(ParamUnit::Decibels),
+            ParamInfo::new(local_param_id("pan"), -1.0, 1.0, 0.0).with_unit(ParamUnit::Pan),
+        ]
+    }
+
+    fn process(&mut self, params: &mut ParamSet, buffer: &mut AudioBuffer, _sidechain: Option<&AudioBuffer>, _sample_rate: f64) {
+        let channel_count = buffer.channel_count();
+        for i in 0..buffer.frame_count() {
+            params.tick();
+            let gain = db_to_gain(params.value(GAIN));
+            let pan = params.value(PAN);
+            if channel_count == 2 {
+                let (left, right) = buffer.stereo_mut();
+                left[i] *= gain * (1.0 - pan).min(1.0);
+                right[i] *= gain * (1.0 + pan).min(1.0);
+            }
+            else {
+                for c in 0..channel_count {
+                    buffer.channel_mut(c)[i] *= gain;
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+    }
+}
+
+#[derive(Live, LiveRead)]
+pub struct GainPan {
+    #[live(0.0)] gain: f64,
+    #[live(0.0)] pan: f64,
+    #[rust] effect: EffectComponent,
+}
+
+impl LiveHook for GainPan {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, GainPan)
+    }
+
+    fn after_apply(&mut self, _cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) {
+        let values = vec![self.gain, self.pan];
+        self.effect.apply(from, nodes[index].id, GainPanState::default().params(), values);
+    }
+}
+
+impl AudioComponent for GainPan {
+    fn get_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(GainPanState::default(), false)
+    }
+
+    fn get_offline_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(GainPanState::default(), true)
+    }
+
+    fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+    }
+
+    fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        AudioResult::not_found()
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        self.effect.params()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        self.live_read_to(id, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(gain: f64, pan: f64, channel_count: usize) -> AudioBuffer {
+        let mut state = GainPanState::default();
+        let mut params = ParamSet::new(state.params());
+        params.jump_index(GAIN, gain);
+        params.jump_index(PAN, pan);
+        let mut buffer = AudioBuffer::from_data(vec![1.0; 16 * channel_count], channel_count);
+        state.process_buffer(&mut params, &mut buffer, None, 48000.0);
+        buffer
+    }
+
+    #[test]
+    fn gain_and_balance() {
+        let g = db_to_gain(-6.0);
+        let centre = process(-6.0, 0.0, 2);
+        assert!(centre.channel(0).iter().chain(centre.channel(1)).all( | s | (*s - g).abs() < 1e-6));
+        // panning turns the other side down and leaves this side at the gain
+        let right = process(-6.0, 0.5, 2);
+        assert!(right.channel(0).iter().all( | s | (*s - g * 0.5).abs() < 1e-6));
+        assert!(right.channel(1).iter().all( | s | (*s - g).abs() < 1e-6));
+        let left = process(0.0, -1.0, 2);
+        assert!(left.channel(0).iter().all( | s | (*s - 1.0).abs() < 1e-6));
+        assert!(left.channel(1).iter().all( | s | *s == 0.0));
+    }
+
+    #[test]
+    fn mono_only_gets_gain() {
+        let mono = process(6.0, 1.0, 1);
+        assert!(mono.channel(0).iter().all( | s | (*s - db_to_gain(6.0)).abs() < 1e-6));
+    }
+}
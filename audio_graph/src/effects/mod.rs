@@ -0,0 +1,181 @@
+use {
+    crate::{
+        makepad_platform::*,
+        makepad_platform::thread::*,
+        makepad_platform::audio::*,
+        makepad_platform::midi::*,
+        audio_traits::*,
+        parameter::*,
+    },
+};
+
+// Effects that process the buffer on their main input. The DSP lives in plain state
+// structs implementing AudioEffect, so they run on any AudioBuffer outside of a graph:
+//
+// let mut reverb = ReverbState::default();
+// let mut params = ParamSet::new(reverb.params());
+// params.jump(live_id!(mix), 0.5);
+// reverb.process_buffer(&mut params, &mut buffer, None, 48000.0);
+//
+// As graph components their parameters are named after the instance, a
+// `comp = <Compressor> {}` has comp_threshold, comp_ratio and so on.
+
+pub mod biquad;
+pub mod dynamics;
+pub mod reverb;
+pub mod delay;
+pub mod gain_pan;
+
+pub use self::{
+    biquad::*,
+    dynamics::*,
+    reverb::*,
+    delay::*,
+    gain_pan::*,
+};
+
+pub fn live_design(cx: &mut Cx) {
+    self::biquad::live_design(cx);
+    self::dynamics::live_design(cx);
+    self::reverb::live_design(cx);
+    self::delay::live_design(cx);
+    self::gain_pan::live_design(cx);
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10.0f32.powf(db * 0.05)
+}
+
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.max(1e-9).log10()
+}
+
+pub trait AudioEffect: Send {
+    // the parameters in the order process reads them from the ParamSet
+    fn params(&self) -> Vec<ParamInfo>;
+    // processes the buffer in place, ticking the params once per frame
+    fn process(&mut self, params: &mut ParamSet, buffer: &mut AudioBuffer, sidechain: Option<&AudioBuffer>, sample_rate: f64);
+    fn reset(&mut self);
+    // the song tempo, sent through the graph as the bpm parameter
+    fn set_tempo(&mut self, _bpm: f64) {}
+
+    fn process_buffer(&mut self, params: &mut ParamSet, buffer: &mut AudioBuffer, sidechain: Option<&AudioBuffer>, sample_rate: f64) {
+        params.set_sample_rate(sample_rate);
+        self.process(params, buffer, sidechain, sample_rate);
+        params.end_block();
+    }
+}
+
+// parameter ids are interned so components can prefix them with their instance name
+pub(crate) fn local_param_id(name: &str) -> LiveId {
+    LiveId::from_str_with_lut(name).unwrap_or(LiveId::from_str(name))
+}
+
+pub fn param_id(instance: LiveId, param: LiveId) -> LiveId {
+    let instance_name = instance.as_string( | s | s.map( | s | s.to_string()));
+    let param_name = param.as_string( | s | s.map( | s | s.to_string()));
+    match (instance_name, param_name) {
+        (Some(instance_name), Some(param_name)) => local_param_id(&format!("{}_{}", instance_name, param_name)),
+        _ => instance.id_append(param)
+    }
+}
+
+pub(crate) enum EffectFromUI {
+    Values(Vec<f64>),
+}
+
+// The UI side of an effect component, it keeps the prefixed parameters and sends
+// edited live values to the running node.
+#[derive(Default)]
+pub(crate) struct EffectComponent {
+    params: Vec<ParamInfo>,
+    values: Vec<f64>,
+    from_ui: FromUISender<EffectFromUI>,
+}
+
+impl EffectComponent {
+    pub fn apply(&mut self, from: ApplyFrom, instance: LiveId, params: Vec<ParamInfo>, values: Vec<f64>) {
+        // only the document knows the instance name, presets apply over it
+        if from.is_from_doc() {
+            self.params = params.into_iter().map( | info | ParamInfo {id: param_id(instance, info.id), ..info}).collect();
+        }
+        if self.values != values {
+            let _ = self.from_ui.send(EffectFromUI::Values(values.clone()));
+            self.values = values;
+        }
+    }
+
+    pub fn params(&self) -> Vec<ParamInfo> {
+        self.params.clone()
+    }
+
+    pub fn graph_node<E: AudioEffect + 'static>(&mut self, effect: E, offline: bool) -> Box<dyn AudioGraphNode + Send> {
+        let from_ui = if offline {
+            self.from_ui.detached_receiver()
+        }
+        else {
+            self.from_ui.new_channel();
+            self.from_ui.receiver()
+        };
+        let mut params = ParamSet::new(self.params.clone());
+        for (index, value) in self.values.iter().enumerate().take(self.params.len()) {
+            params.jump_index(index, *value);
+        }
+        Box::new(EffectNode {
+            from_ui,
+            effect,
+            params,
+            ids: self.params.iter().map( | info | info.id).collect(),
+        })
+    }
+}
+
+struct EffectNode<E: AudioEffect> {
+    from_ui: FromUIReceiver<EffectFromUI>,
+    effect: E,
+    params: ParamSet,
+    ids: Vec<LiveId>,
+}
+
+impl<E: AudioEffect> AudioGraphNode for EffectNode<E> {
+    fn handle_midi_data(&mut self, _data: MidiData) {
+    }
+
+    fn all_notes_off(&mut self) {
+    }
+
+    fn set_param(&mut self, change: ParamChange) {
+        if change.id == live_id!(bpm) {
+            self.effect.set_tempo(change.value);
+        }
+        else {
+            self.params.push(change);
+        }
+    }
+
+    fn render_to_audio_buffer(
+        &mut self,
+        info: AudioInfo,
+        outputs: &mut [&mut AudioBuffer],
+        inputs: &[&AudioBuffer],
+        _display: &mut DisplayAudioGraph
+    ) {
+        while let Ok(msg) = self.from_ui.try_recv() {
+            match msg {
+                EffectFromUI::Values(values) => for (id, value) in self.ids.iter().zip(values) {
+                    self.params.push(ParamChange::new(*id, value));
+                }
+            }
+        }
+        let output = &mut outputs[0];
+        match inputs.first() {
+            Some(input) if input.channel_count() > 0 && input.frame_count() == output.frame_count() => {
+                for c in 0..output.channel_count() {
+                    output.channel_mut(c).copy_from_slice(input.channel(c % input.channel_count()));
+                }
+            }
+            _ => output.zero()
+        }
+        self.effect.process_buffer(&mut self.params, output, inputs.get(1).copied(), info.sample_rate);
+    }
+}
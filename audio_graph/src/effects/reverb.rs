@@ -0,0 +1,238 @@
+use {
+    crate::{
+        register_audio_component,
+        audio_traits::*,
+        parameter::*,
+        effects::*,
+    },
+};
+
+// Schroeder/Moorer reverb in the freeverb layout: eight damped combs in parallel
+// into four allpasses in series per channel, the right channel slightly detuned.
+
+live_design!{
+    Reverb = {{Reverb}} {
+    }
+}
+
+const ROOM_SIZE: usize = 0;
+const DAMPING: usize = 1;
+const WIDTH: usize = 2;
+const MIX: usize = 3;
+
+// delay lengths in frames at 44.1kHz
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+const STEREO_SPREAD: usize = 23;
+const INPUT_GAIN: f32 = 0.015;
+const ROOM_SCALE: f32 = 0.28;
+const ROOM_OFFSET: f32 = 0.7;
+const DAMPING_SCALE: f32 = 0.4;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+struct Comb {
+    line: Vec<f32>,
+    index: usize,
+    filter: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Self {line: vec![0.0; len.max(1)], index: 0, filter: 0.0}
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.line[self.index];
+        self.filter = output * (1.0 - damping) + self.filter * damping;
+        self.line[self.index] = input + self.filter * feedback;
+        self.index = (self.index + 1) % self.line.len();
+        output
+    }
+}
+
+struct Allpass {
+    line: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(len: usize) -> Self {
+        Self {line: vec![0.0; len.max(1)], index: 0}
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.line[self.index];
+        self.line[self.index] = input + delayed * ALLPASS_FEEDBACK;
+        self.index = (self.index + 1) % self.line.len();
+        delayed - input
+    }
+}
+
+struct ReverbChannel {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+impl ReverbChannel {
+    fn new(sample_rate: f64, spread: usize) -> Self {
+        let scale = sample_rate / 44100.0;
+        let len = | tuning: usize | ((tuning + spread) as f64 * scale).round() as usize;
+        Self {
+            combs: COMB_TUNING.iter().map( | t | Comb::new(len(*t))).collect(),
+            allpasses: ALLPASS_TUNING.iter().map( | t | Allpass::new(len(*t))).collect(),
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let mut output = 0.0;
+        for comb in &mut self.combs {
+            output += comb.process(input, feedback, damping);
+        }
+        for allpass in &mut self.allpasses {
+            output = allpass.process(output);
+        }
+        output
+    }
+}
+
+#[derive(Default)]
+pub struct ReverbState {
+    channels: Vec<ReverbChannel>,
+    sample_rate: f64,
+}
+
+impl AudioEffect for ReverbState {
+    fn params(&self) -> Vec<ParamInfo> {
+        vec![
+            ParamInfo::new(local_param_id("room_size"), 0.0, 1.0, 0.5).with_unit(ParamUnit::Percent),
+            ParamInfo::new(local_param_id("damping"), 0.0, 1.0, 0.5).with_unit(ParamUnit::Percent),
+            ParamInfo::new(local_param_id("width"), 0.0, 1.0, 1.0).with_unit(ParamUnit::Percent),
+            ParamInfo::new(local_param_id("mix"), 0.0, 1.0, 0.25).with_unit(ParamUnit::Percent),
+        ]
+    }
+
+    fn process(&mut self, params: &mut ParamSet, buffer: &mut AudioBuffer, _sidechain: Option<&AudioBuffer>, sample_rate: f64) {
+        let channel_count = buffer.channel_count();
+        if self.sample_rate != sample_rate || self.channels.len() != channel_count {
+            self.sample_rate = sample_rate;
+            self.channels = (0..channel_count).map( | c | ReverbChannel::new(sample_rate, if c % 2 == 1 {STEREO_SPREAD} else {0})).collect();
+        }
+        let mut wet = [0.0f32; 2];
+        for i in 0..buffer.frame_count() {
+            params.tick();
+            let feedback = params.value(ROOM_SIZE) * ROOM_SCALE + ROOM_OFFSET;
+            let damping = params.value(DAMPING) * DAMPING_SCALE;
+            let width = params.value(WIDTH);
+            let mix = params.value(MIX);
+            if channel_count == 2 {
+                let (left, right) = buffer.stereo_mut();
+                let input = (left[i] + right[i]) * INPUT_GAIN;
+                for (c, channel) in self.channels.iter_mut().enumerate() {
+                    wet[c] = channel.process(input, feedback, damping);
+                }
+                // width crossfeeds the two tails, 0 is mono
+                let same = 0.5 + width * 0.5;
+                let cross = 0.5 - width * 0.5;
+                left[i] = left[i] * (1.0 - mix) + (wet[0] * same + wet[1] * cross) * mix;
+                right[i] = right[i] * (1.0 - mix) + (wet[1] * same + wet[0] * cross) * mix;
+            }
+            else {
+                for (c, channel) in self.channels.iter_mut().enumerate() {
+                    let sample = &mut buffer.channel_mut(c)[i];
+                    let tail = channel.process(*sample * INPUT_GAIN * 2.0, feedback, damping);
+                    *sample = *sample * (1.0 - mix) + tail * mix;
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.channels.clear();
+        self.sample_rate = 0.0;
+    }
+}
+
+#[derive(Live, LiveRead)]
+pub struct Reverb {
+    #[live(0.5)] room_size: f64,
+    #[live(0.5)] damping: f64,
+    #[live(1.0)] width: f64,
+    #[live(0.25)] mix: f64,
+    #[rust] effect: EffectComponent,
+}
+
+impl LiveHook for Reverb {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, Reverb)
+    }
+
+    fn after_apply(&mut self, _cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) {
+        let values = vec![self.room_size, self.damping, self.width, self.mix];
+        self.effect.apply(from, nodes[index].id, ReverbState::default().params(), values);
+    }
+}
+
+impl AudioComponent for Reverb {
+    fn get_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(ReverbState::default(), false)
+    }
+
+    fn get_offline_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.effect.graph_node(ReverbState::default(), true)
+    }
+
+    fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+    }
+
+    fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        AudioResult::not_found()
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        self.effect.params()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        self.live_read_to(id, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+    const WINDOW: usize = 4800;
+
+    // the rms of the wet impulse response in 0.1 second windows
+    fn tail(room_size: f64) -> Vec<f64> {
+        let mut state = ReverbState::default();
+        let mut params = ParamSet::new(state.params());
+        params.jump_index(ROOM_SIZE, room_size);
+        params.jump_index(MIX, 1.0);
+        let mut buffer = AudioBuffer::new_with_size(WINDOW * 20, 2);
+        buffer.channel_mut(0)[0] = 1.0;
+        buffer.channel_mut(1)[0] = 1.0;
+        state.process_buffer(&mut params, &mut buffer, None, SAMPLE_RATE);
+        buffer.channel(0).chunks(WINDOW).map( | window | {
+            (window.iter().map( | s | (*s as f64) * (*s as f64)).sum::<f64>() / WINDOW as f64).sqrt()
+        }).collect()
+    }
+
+    #[test]
+    fn tail_decays() {
+        let windows = tail(0.5);
+        assert!(windows[0] > 0.0);
+        for pair in windows[2..].windows(2) {
+            assert!(pair[1] < pair[0], "{:?}", windows);
+        }
+        assert!(windows[15] < windows[2] * 0.01, "{:?}", windows);
+    }
+
+    #[test]
+    fn larger_room_rings_longer() {
+        let small = tail(0.0);
+        let large = tail(1.0);
+        assert!(large[10] / large[2] > small[10] / small[2] * 10.0);
+    }
+}
@@ -5,7 +5,9 @@ use {
         makepad_platform::audio::*,
         makepad_platform::midi::*,
         register_audio_component,
-        audio_traits::*
+        audio_traits::*,
+        parameter::*,
+        preset::*,
     },
 };
 
@@ -48,6 +50,12 @@ impl AudioGraphNode for Node {
         }
     }
     
+    fn set_param(&mut self, change: ParamChange) {
+        for step in &mut self.steps {
+            step.graph_node.set_param(change);
+        }
+    }
+    
     fn render_to_audio_buffer(&mut self, info: AudioInfo, outputs: &mut [&mut AudioBuffer], inputs: &[&AudioBuffer], display:&mut DisplayAudioGraph) {
         // reverse over the steps chaining the audio nodes
         let steps = &mut self.steps;
@@ -99,23 +107,39 @@ impl LiveHook for Instrument {
     }
 }
 
-impl AudioComponent for Instrument {
-    fn get_graph_node(&mut self, cx:&mut Cx) -> Box<dyn AudioGraphNode + Send> {
-        self.from_ui.new_channel();
+impl Instrument {
+    fn graph_node(&mut self, cx:&mut Cx, offline: bool) -> Box<dyn AudioGraphNode + Send> {
+        let from_ui = if offline {
+            self.from_ui.detached_receiver()
+        }
+        else {
+            self.from_ui.new_channel();
+            self.from_ui.receiver()
+        };
         let mut steps = Vec::new();
         for step_id in &self.step_order {
             if let Some(input) = self.steps.get_mut(step_id).unwrap().as_mut() {
                 steps.push(Step {
-                    graph_node: input.get_graph_node(cx),
+                    graph_node: if offline {input.get_offline_graph_node(cx)} else {input.get_graph_node(cx)},
                     input_buffer: AudioBuffer::default()
                 });
             }
         }
         Box::new(Node {
             steps,
-            _from_ui: self.from_ui.receiver()
+            _from_ui: from_ui
         })
     }
+}
+
+impl AudioComponent for Instrument {
+    fn get_graph_node(&mut self, cx:&mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.graph_node(cx, false)
+    }
+    
+    fn get_offline_graph_node(&mut self, cx:&mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.graph_node(cx, true)
+    }
     
     fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
         for step in self.steps.values_mut(){
@@ -131,4 +155,16 @@ impl AudioComponent for Instrument {
         }
         AudioResult::not_found()
     }
+    
+    fn params(&self) -> Vec<ParamInfo> {
+        self.step_order.iter().filter_map( | id | self.steps.get(id)).flat_map( | step | step.params()).collect()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        preset_read_children(id, self.step_order.iter().filter_map( | id | self.steps.get(id).map( | step | (*id, step))), out);
+    }
+    
+    fn preset_apply(&mut self, cx: &mut Cx, nodes: &[LiveNode]) {
+        preset_apply_children(cx, nodes, &mut self.steps);
+    }
 }
@@ -6,8 +6,18 @@ pub mod audio_unit_effect;
 pub mod audio_unit_instrument;
 
 pub mod mixer;
+pub mod router;
 pub mod instrument;
 pub mod audio_stream;
+pub mod offline_render;
+pub mod decode;
+pub mod resampler;
+pub mod sample_player;
+pub mod midi_file;
+pub mod parameter;
+pub mod sequencer;
+pub mod effects;
+pub mod preset;
 
 use makepad_platform::Cx;
 pub use makepad_platform;
@@ -15,9 +25,18 @@ pub use makepad_platform::makepad_error_log;
 pub use makepad_platform::makepad_math;
 pub use crate::audio_graph::*;
 pub use crate::audio_traits::*;
+pub use crate::offline_render::*;
+pub use crate::midi_file::*;
+pub use crate::parameter::*;
+pub use crate::effects::*;
+pub use crate::preset::*;
 
 pub fn live_design(cx:&mut Cx){
     self::audio_graph::live_design(cx);
     self::mixer::live_design(cx);
+    self::router::live_design(cx);
     self::instrument::live_design(cx);
+    self::sample_player::live_design(cx);
+    self::sequencer::live_design(cx);
+    self::effects::live_design(cx);
 }
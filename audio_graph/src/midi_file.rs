@@ -0,0 +1,610 @@
+use {
+    std::path::Path,
+    crate::makepad_platform::midi::*,
+    crate::offline_render::TimedMidiData,
+};
+
+// Standard MIDI File (type 0 and 1) reading and writing. Events are kept with absolute
+// tick times per track, the TempoMap converts between ticks and seconds.
+
+#[derive(Debug)]
+pub enum MidiFileError {
+    NotMidi,
+    Unsupported(String),
+    Corrupt(String),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for MidiFileError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl std::fmt::Display for MidiFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotMidi => write!(f, "not a midi file"),
+            Self::Unsupported(what) => write!(f, "unsupported: {}", what),
+            Self::Corrupt(what) => write!(f, "corrupt midi file: {}", what),
+            Self::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MidiFileTiming {
+    TicksPerQuarter(u16),
+    // frames per second (29 means 29.97 drop frame) and ticks per frame
+    Smpte {fps: u8, ticks_per_frame: u8},
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MidiFileEvent {
+    Midi(MidiData),
+    // microseconds per quarter note
+    Tempo(u32),
+    TimeSignature {numerator: u8, denominator: u8, clocks_per_click: u8, thirty_seconds_per_quarter: u8},
+    KeySignature {sharps: i8, minor: bool},
+    TrackName(String),
+    // any other meta event, by type
+    Meta {kind: u8, data: Vec<u8>},
+    // the bytes following the F0 or F7 status
+    SysEx {status: u8, data: Vec<u8>},
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiFileTrackEvent {
+    pub tick: u64,
+    pub event: MidiFileEvent,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MidiFileTrack {
+    pub events: Vec<MidiFileTrackEvent>,
+}
+
+impl MidiFileTrack {
+    pub fn name(&self) -> Option<&str> {
+        self.events.iter().find_map( | e | if let MidiFileEvent::TrackName(name) = &e.event {Some(name.as_str())} else {None})
+    }
+
+    pub fn push(&mut self, tick: u64, event: MidiFileEvent) {
+        self.events.push(MidiFileTrackEvent {tick, event});
+    }
+
+    // keeps events at the same tick in insertion order
+    pub fn sort(&mut self) {
+        self.events.sort_by_key( | e | e.tick);
+    }
+
+    pub fn end_tick(&self) -> u64 {
+        self.events.iter().map( | e | e.tick).max().unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiFile {
+    pub timing: MidiFileTiming,
+    pub tracks: Vec<MidiFileTrack>,
+}
+
+impl Default for MidiFile {
+    fn default() -> Self {
+        Self::new(480)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, MidiFileError> {
+        let byte = *self.data.get(self.pos).ok_or_else( || MidiFileError::Corrupt("unexpected end of track".to_string()))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], MidiFileError> {
+        if self.pos + len > self.data.len() {
+            return Err(MidiFileError::Corrupt("unexpected end of track".to_string()))
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, MidiFileError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, MidiFileError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn vlq(&mut self) -> Result<u32, MidiFileError> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value)
+            }
+        }
+        Err(MidiFileError::Corrupt("variable length number too long".to_string()))
+    }
+}
+
+// the largest number a variable length quantity holds in its four bytes
+const VLQ_MAX: u32 = 0x0fffffff;
+
+fn write_vlq(out: &mut Vec<u8>, value: u32) {
+    debug_assert!(value <= VLQ_MAX);
+    let mut bytes = [0u8; 4];
+    let mut count = 0;
+    let mut value = value.min(VLQ_MAX);
+    loop {
+        bytes[count] = (value & 0x7f) as u8;
+        count += 1;
+        value >>= 7;
+        if value == 0 {
+            break
+        }
+    }
+    for i in (0..count).rev() {
+        out.push(bytes[i] | if i > 0 {0x80} else {0});
+    }
+}
+
+// data bytes following a channel status
+fn channel_data_len(status: u8) -> usize {
+    match status >> 4 {
+        0xC | 0xD => 1,
+        _ => 2
+    }
+}
+
+impl MidiFile {
+    pub fn new(ticks_per_quarter: u16) -> Self {
+        Self {
+            timing: MidiFileTiming::TicksPerQuarter(ticks_per_quarter),
+            tracks: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, MidiFileError> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), MidiFileError> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, MidiFileError> {
+        let mut reader = Reader {data, pos: 0};
+        if data.len() < 14 || &data[0..4] != b"MThd" {
+            return Err(MidiFileError::NotMidi)
+        }
+        reader.pos = 4;
+        let header_len = reader.u32()? as usize;
+        let format = reader.u16()?;
+        let track_count = reader.u16()?;
+        let division = reader.u16()?;
+        reader.pos = 8 + header_len;
+        if format > 1 {
+            return Err(MidiFileError::Unsupported(format!("midi file format {}", format)))
+        }
+        let timing = if division & 0x8000 != 0 {
+            MidiFileTiming::Smpte {
+                fps: (-((division >> 8) as u8 as i8)) as u8,
+                ticks_per_frame: (division & 0xff) as u8
+            }
+        }
+        else {
+            MidiFileTiming::TicksPerQuarter(division.max(1))
+        };
+        let mut tracks = Vec::new();
+        while tracks.len() < track_count as usize && reader.pos + 8 <= data.len() {
+            let id = reader.bytes(4)?;
+            let len = reader.u32()? as usize;
+            if reader.pos + len > data.len() {
+                return Err(MidiFileError::Corrupt("chunk runs past the end of the file".to_string()))
+            }
+            let end = reader.pos + len;
+            // unknown chunks are skipped as the spec asks
+            if id == b"MTrk" {
+                tracks.push(Self::parse_track(&data[reader.pos..end])?);
+            }
+            reader.pos = end;
+        }
+        if tracks.len() < track_count as usize {
+            return Err(MidiFileError::Corrupt(format!("{} of {} tracks found", tracks.len(), track_count)))
+        }
+        Ok(Self {timing, tracks})
+    }
+
+    fn parse_track(data: &[u8]) -> Result<MidiFileTrack, MidiFileError> {
+        let mut reader = Reader {data, pos: 0};
+        let mut track = MidiFileTrack::default();
+        let mut tick = 0u64;
+        let mut running_status = 0u8;
+        while reader.pos < data.len() {
+            tick += reader.vlq()? as u64;
+            let mut status = reader.byte()?;
+            match status {
+                0xff => {
+                    let kind = reader.byte()?;
+                    let len = reader.vlq()? as usize;
+                    let bytes = reader.bytes(len)?;
+                    let event = match (kind, len) {
+                        (0x2f, _) => break,
+                        (0x51, 3) => MidiFileEvent::Tempo(((bytes[0] as u32) << 16) | ((bytes[1] as u32) << 8) | bytes[2] as u32),
+                        (0x58, 4) => MidiFileEvent::TimeSignature {
+                            numerator: bytes[0],
+                            denominator: bytes[1],
+                            clocks_per_click: bytes[2],
+                            thirty_seconds_per_quarter: bytes[3]
+                        },
+                        (0x59, 2) => MidiFileEvent::KeySignature {sharps: bytes[0] as i8, minor: bytes[1] != 0},
+                        (0x03, _) => MidiFileEvent::TrackName(String::from_utf8_lossy(bytes).to_string()),
+                        _ => MidiFileEvent::Meta {kind, data: bytes.to_vec()}
+                    };
+                    track.push(tick, event);
+                }
+                0xf0 | 0xf7 => {
+                    let len = reader.vlq()? as usize;
+                    let bytes = reader.bytes(len)?;
+                    track.push(tick, MidiFileEvent::SysEx {status, data: bytes.to_vec()});
+                    // sysex cancels running status
+                    running_status = 0;
+                }
+                // system common and realtime messages have no place in a file
+                0xf1..=0xfe => {
+                    return Err(MidiFileError::Corrupt(format!("system message {:02x} in track", status)))
+                }
+                _ => {
+                    let first = if status & 0x80 == 0 {
+                        if running_status == 0 {
+                            return Err(MidiFileError::Corrupt("data byte without running status".to_string()))
+                        }
+                        let first = status;
+                        status = running_status;
+                        first
+                    }
+                    else {
+                        reader.byte()?
+                    };
+                    running_status = status;
+                    let second = if channel_data_len(status) == 2 {reader.byte()?} else {0};
+                    track.push(tick, MidiFileEvent::Midi(MidiData {data: [status, first, second]}));
+                }
+            }
+        }
+        Ok(track)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"MThd");
+        out.extend_from_slice(&6u32.to_be_bytes());
+        let format: u16 = if self.tracks.len() == 1 {0} else {1};
+        out.extend_from_slice(&format.to_be_bytes());
+        out.extend_from_slice(&(self.tracks.len() as u16).to_be_bytes());
+        let division = match self.timing {
+            MidiFileTiming::TicksPerQuarter(ticks) => ticks & 0x7fff,
+            MidiFileTiming::Smpte {fps, ticks_per_frame} => (((-(fps as i8)) as u8 as u16) << 8) | ticks_per_frame as u16
+        };
+        out.extend_from_slice(&division.to_be_bytes());
+        for track in &self.tracks {
+            let mut events: Vec<&MidiFileTrackEvent> = track.events.iter().collect();
+            events.sort_by_key( | e | e.tick);
+            let mut data = Vec::new();
+            let mut tick = 0;
+            for event in events {
+                // a delta too long for one number is bridged with empty text events
+                let mut delta = event.tick - tick;
+                while delta > VLQ_MAX as u64 {
+                    write_vlq(&mut data, VLQ_MAX);
+                    data.extend_from_slice(&[0xff, 0x01, 0]);
+                    delta -= VLQ_MAX as u64;
+                }
+                write_vlq(&mut data, delta as u32);
+                tick = event.tick;
+                match &event.event {
+                    MidiFileEvent::Midi(midi) => {
+                        let len = channel_data_len(midi.data[0]);
+                        data.extend_from_slice(&midi.data[0..1 + len]);
+                    }
+                    MidiFileEvent::Tempo(tempo) => {
+                        data.extend_from_slice(&[0xff, 0x51, 3]);
+                        data.extend_from_slice(&tempo.to_be_bytes()[1..4]);
+                    }
+                    MidiFileEvent::TimeSignature {numerator, denominator, clocks_per_click, thirty_seconds_per_quarter} => {
+                        data.extend_from_slice(&[0xff, 0x58, 4, *numerator, *denominator, *clocks_per_click, *thirty_seconds_per_quarter]);
+                    }
+                    MidiFileEvent::KeySignature {sharps, minor} => {
+                        data.extend_from_slice(&[0xff, 0x59, 2, *sharps as u8, *minor as u8]);
+                    }
+                    MidiFileEvent::TrackName(name) => {
+                        data.extend_from_slice(&[0xff, 0x03]);
+                        write_vlq(&mut data, name.len() as u32);
+                        data.extend_from_slice(name.as_bytes());
+                    }
+                    MidiFileEvent::Meta {kind, data: bytes} => {
+                        data.extend_from_slice(&[0xff, *kind]);
+                        write_vlq(&mut data, bytes.len() as u32);
+                        data.extend_from_slice(bytes);
+                    }
+                    MidiFileEvent::SysEx {status, data: bytes} => {
+                        data.push(*status);
+                        write_vlq(&mut data, bytes.len() as u32);
+                        data.extend_from_slice(bytes);
+                    }
+                }
+            }
+            data.extend_from_slice(&[0, 0xff, 0x2f, 0]);
+            out.extend_from_slice(b"MTrk");
+            out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            out.extend_from_slice(&data);
+        }
+        out
+    }
+
+    pub fn end_tick(&self) -> u64 {
+        self.tracks.iter().map( | t | t.end_tick()).max().unwrap_or(0)
+    }
+
+    // the midi events of all tracks in seconds, for rendering a song offline
+    pub fn timed_midi_data(&self, default_bpm: f64) -> Vec<TimedMidiData> {
+        let tempo = self.tempo_map(default_bpm);
+        let mut events: Vec<TimedMidiData> = self.tracks.iter()
+            .flat_map( | t | t.events.iter())
+            .filter_map( | e | if let MidiFileEvent::Midi(data) = e.event {
+                Some(TimedMidiData::new(tempo.tick_to_seconds(e.tick as f64), data))
+            } else {None})
+            .collect();
+        events.sort_by( | a, b | a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+        events
+    }
+
+    pub fn tempo_map(&self, default_bpm: f64) -> TempoMap {
+        let mut map = TempoMap::new(self.timing, default_bpm);
+        if let MidiFileTiming::TicksPerQuarter(_) = self.timing {
+            let mut tempos: Vec<(u64, u32)> = self.tracks.iter()
+                .flat_map( | t | t.events.iter())
+                .filter_map( | e | if let MidiFileEvent::Tempo(tempo) = e.event {Some((e.tick, tempo))} else {None})
+                .collect();
+            tempos.sort_by_key( | t | t.0);
+            for (tick, tempo) in tempos {
+                map.push_tempo(tick, tempo as f64 / 1_000_000.0);
+            }
+        }
+        map
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TempoSegment {
+    tick: u64,
+    seconds: f64,
+    seconds_per_tick: f64,
+}
+
+// Piecewise linear mapping between ticks and seconds built from the tempo events.
+#[derive(Clone, Debug)]
+pub struct TempoMap {
+    ticks_per_quarter: f64,
+    segments: Vec<TempoSegment>,
+}
+
+impl TempoMap {
+    pub fn new(timing: MidiFileTiming, bpm: f64) -> Self {
+        let bpm = if bpm > 0.0 {bpm} else {120.0};
+        let (ticks_per_quarter, seconds_per_tick) = match timing {
+            MidiFileTiming::TicksPerQuarter(ticks) => {
+                (ticks as f64, 60.0 / bpm / ticks as f64)
+            }
+            MidiFileTiming::Smpte {fps, ticks_per_frame} => {
+                let fps = if fps == 29 {29.97} else {fps as f64};
+                let seconds_per_tick = 1.0 / (fps * ticks_per_frame.max(1) as f64);
+                // smpte files have no quarter notes, beats follow the given tempo
+                (60.0 / bpm / seconds_per_tick, seconds_per_tick)
+            }
+        };
+        Self {
+            ticks_per_quarter,
+            segments: vec![TempoSegment {tick: 0, seconds: 0.0, seconds_per_tick}],
+        }
+    }
+
+    // sets the tempo from tick onwards, in seconds per quarter note
+    pub fn push_tempo(&mut self, tick: u64, seconds_per_quarter: f64) {
+        let seconds_per_tick = seconds_per_quarter / self.ticks_per_quarter;
+        let last = *self.segments.last().unwrap();
+        if tick <= last.tick {
+            // a tempo at the same tick replaces the previous one
+            let seconds = last.seconds;
+            *self.segments.last_mut().unwrap() = TempoSegment {tick: last.tick, seconds, seconds_per_tick};
+        }
+        else {
+            let seconds = last.seconds + (tick - last.tick) as f64 * last.seconds_per_tick;
+            self.segments.push(TempoSegment {tick, seconds, seconds_per_tick});
+        }
+    }
+
+    pub fn ticks_per_quarter(&self) -> f64 {self.ticks_per_quarter}
+
+    pub fn tick_to_seconds(&self, tick: f64) -> f64 {
+        let index = self.segments.partition_point( | s | s.tick as f64 <= tick).max(1) - 1;
+        let segment = &self.segments[index];
+        segment.seconds + (tick - segment.tick as f64) * segment.seconds_per_tick
+    }
+
+    pub fn seconds_to_tick(&self, seconds: f64) -> f64 {
+        let index = self.segments.partition_point( | s | s.seconds <= seconds).max(1) - 1;
+        let segment = &self.segments[index];
+        segment.tick as f64 + (seconds - segment.seconds) / segment.seconds_per_tick
+    }
+
+    pub fn beats_to_ticks(&self, beats: f64) -> f64 {
+        beats * self.ticks_per_quarter
+    }
+
+    pub fn ticks_to_beats(&self, ticks: f64) -> f64 {
+        ticks / self.ticks_per_quarter
+    }
+
+    pub fn bpm_at(&self, tick: f64) -> f64 {
+        let index = self.segments.partition_point( | s | s.tick as f64 <= tick).max(1) - 1;
+        60.0 / (self.segments[index].seconds_per_tick * self.ticks_per_quarter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn midi(status: u8, first: u8, second: u8) -> MidiFileEvent {
+        MidiFileEvent::Midi(MidiData {data: [status, first, second]})
+    }
+    
+    // a file with one track holding data, for hand written event bytes
+    fn single_track(track: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"MThd");
+        out.extend_from_slice(&[0, 0, 0, 6, 0, 0, 0, 1, 0x01, 0xe0]);
+        out.extend_from_slice(b"MTrk");
+        out.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        out.extend_from_slice(track);
+        out
+    }
+    
+    #[test]
+    fn write_read_round_trip() {
+        let mut file = MidiFile::new(96);
+        let mut conductor = MidiFileTrack::default();
+        conductor.push(0, MidiFileEvent::TrackName("conductor".to_string()));
+        conductor.push(0, MidiFileEvent::Tempo(500_000));
+        conductor.push(0, MidiFileEvent::TimeSignature {numerator: 6, denominator: 3, clocks_per_click: 24, thirty_seconds_per_quarter: 8});
+        conductor.push(0, MidiFileEvent::KeySignature {sharps: -3, minor: true});
+        conductor.push(384, MidiFileEvent::Tempo(400_000));
+        let mut notes = MidiFileTrack::default();
+        notes.push(0, midi(0x90, 60, 100));
+        notes.push(0, midi(0xc0, 5, 0));
+        notes.push(48, midi(0x80, 60, 0));
+        notes.push(48, MidiFileEvent::SysEx {status: 0xf0, data: vec![0x7e, 0x7f, 0x09, 0x01, 0xf7]});
+        notes.push(200, MidiFileEvent::Meta {kind: 0x06, data: b"marker".to_vec()});
+        notes.push(300_000_000, midi(0xb0, 7, 90));
+        file.tracks = vec![conductor, notes];
+        
+        let parsed = MidiFile::parse(&file.to_bytes()).unwrap();
+        assert_eq!(parsed.timing, file.timing);
+        assert_eq!(parsed.tracks[0], file.tracks[0]);
+        // the long delta comes back with the empty text event that bridges it
+        let events: Vec<&MidiFileTrackEvent> = parsed.tracks[1].events.iter()
+            .filter( | e | e.event != MidiFileEvent::Meta {kind: 0x01, data: Vec::new()})
+            .collect();
+        assert_eq!(events, file.tracks[1].events.iter().collect::<Vec<_>>());
+        assert_eq!(parsed.tracks[0].name(), Some("conductor"));
+    }
+    
+    #[test]
+    fn smpte_timing_round_trip() {
+        let file = MidiFile {
+            timing: MidiFileTiming::Smpte {fps: 25, ticks_per_frame: 40},
+            tracks: vec![MidiFileTrack::default()],
+        };
+        assert_eq!(MidiFile::parse(&file.to_bytes()).unwrap().timing, file.timing);
+    }
+    
+    #[test]
+    fn running_status() {
+        let file = MidiFile::parse(&single_track(&[
+            0x00, 0x90, 60, 100,
+            0x10, 62, 100,
+            0x10, 60, 0,
+            0x00, 0xc1, 3,
+            0x00, 4,
+            0x00, 0xff, 0x2f, 0x00,
+        ])).unwrap();
+        let events: Vec<(u64, MidiFileEvent)> = file.tracks[0].events.iter().map( | e | (e.tick, e.event.clone())).collect();
+        assert_eq!(events, vec![
+            (0, midi(0x90, 60, 100)),
+            (16, midi(0x90, 62, 100)),
+            (32, midi(0x90, 60, 0)),
+            (32, midi(0xc1, 3, 0)),
+            (32, midi(0xc1, 4, 0)),
+        ]);
+    }
+    
+    #[test]
+    fn sysex_cancels_running_status() {
+        let result = MidiFile::parse(&single_track(&[
+            0x00, 0x90, 60, 100,
+            0x00, 0xf0, 0x02, 0x01, 0xf7,
+            0x00, 62, 100,
+        ]));
+        assert!(matches!(result, Err(MidiFileError::Corrupt(_))));
+    }
+    
+    #[test]
+    fn system_messages_are_rejected() {
+        for status in [0xf1, 0xf2, 0xf8, 0xfe] {
+            let result = MidiFile::parse(&single_track(&[0x00, status, 0x00, 0x00]));
+            assert!(matches!(result, Err(MidiFileError::Corrupt(_))), "{:02x}", status);
+        }
+    }
+    
+    #[test]
+    fn truncated_input() {
+        let mut file = MidiFile::new(480);
+        let mut track = MidiFileTrack::default();
+        track.push(0, MidiFileEvent::TrackName("a name".to_string()));
+        track.push(10, midi(0x90, 60, 100));
+        track.push(20, midi(0x80, 60, 0));
+        file.tracks.push(track);
+        let bytes = file.to_bytes();
+        assert!(matches!(MidiFile::parse(&bytes[..10]), Err(MidiFileError::NotMidi)));
+        assert!(matches!(MidiFile::parse(b"RIFF0000000000"), Err(MidiFileError::NotMidi)));
+        // cut anywhere the track runs past the end of the data
+        for len in 14..bytes.len() {
+            assert!(matches!(MidiFile::parse(&bytes[..len]), Err(MidiFileError::Corrupt(_))), "{}", len);
+        }
+        // a number that doesn't end within four bytes
+        let result = MidiFile::parse(&single_track(&[0xff, 0xff, 0xff, 0xff, 0x00]));
+        assert!(matches!(result, Err(MidiFileError::Corrupt(_))));
+    }
+    
+    #[test]
+    fn tick_to_seconds_across_tempo_changes() {
+        let mut file = MidiFile::new(480);
+        let mut track = MidiFileTrack::default();
+        // 120 bpm, 60 bpm after two beats, 240 bpm after four
+        track.push(0, MidiFileEvent::Tempo(500_000));
+        track.push(960, MidiFileEvent::Tempo(1_000_000));
+        track.push(1920, MidiFileEvent::Tempo(250_000));
+        file.tracks.push(track);
+        let map = file.tempo_map(100.0);
+        let close = | a: f64, b: f64 | (a - b).abs() < 1e-9;
+        assert!(close(map.tick_to_seconds(480.0), 0.5));
+        assert!(close(map.tick_to_seconds(960.0), 1.0));
+        assert!(close(map.tick_to_seconds(1440.0), 2.0));
+        assert!(close(map.tick_to_seconds(1920.0), 3.0));
+        assert!(close(map.tick_to_seconds(2400.0), 3.25));
+        for seconds in [0.25, 1.5, 3.0, 4.0] {
+            assert!(close(map.tick_to_seconds(map.seconds_to_tick(seconds)), seconds));
+        }
+        assert!(close(map.bpm_at(100.0), 120.0));
+        assert!(close(map.bpm_at(1000.0), 60.0));
+        assert!(close(map.bpm_at(5000.0), 240.0));
+        
+        // without tempo events the default tempo holds
+        let map = MidiFile::new(480).tempo_map(90.0);
+        assert!(close(map.tick_to_seconds(480.0), 60.0 / 90.0));
+    }
+}
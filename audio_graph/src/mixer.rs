@@ -5,7 +5,9 @@ use {
         makepad_platform::audio::*,
         makepad_platform::midi::*,
         register_audio_component,
-        audio_traits::*
+        audio_traits::*,
+        parameter::*,
+        preset::*,
     },
 };
 
@@ -63,6 +65,12 @@ impl AudioGraphNode for Node {
         }
     }
     
+    fn set_param(&mut self, change: ParamChange) {
+        for input in &mut self.inputs {
+            input.set_param(change);
+        }
+    }
+    
     fn render_to_audio_buffer(
         &mut self,
         info: AudioInfo,
@@ -88,22 +96,37 @@ impl AudioGraphNode for Node {
 }
 
 
-impl AudioComponent for Mixer {
-    fn get_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
-        
-        self.from_ui.new_channel();
+impl Mixer {
+    fn graph_node(&mut self, cx: &mut Cx, offline: bool) -> Box<dyn AudioGraphNode + Send> {
+        let from_ui = if offline {
+            self.from_ui.detached_receiver()
+        }
+        else {
+            self.from_ui.new_channel();
+            self.from_ui.receiver()
+        };
         let mut inputs = Vec::new();
         for input in self.inputs.values_mut() {
             if let Some(input) = input.as_mut() {
-                inputs.push(input.get_graph_node(cx));
+                inputs.push(if offline {input.get_offline_graph_node(cx)} else {input.get_graph_node(cx)});
             }
         }
         Box::new(Node {
             inputs,
             buffer: AudioBuffer::default(),
-            _from_ui: self.from_ui.receiver()
+            _from_ui: from_ui
         })
     }
+}
+
+impl AudioComponent for Mixer {
+    fn get_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.graph_node(cx, false)
+    }
+    
+    fn get_offline_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.graph_node(cx, true)
+    }
     
     fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
         for input in self.inputs.values_mut() {
@@ -120,5 +143,18 @@ impl AudioComponent for Mixer {
         AudioResult::not_found()
     }
     
+    fn params(&self) -> Vec<ParamInfo> {
+        self.inputs.values().flat_map( | input | input.params()).collect()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        let mut ids: Vec<LiveId> = self.inputs.keys().copied().collect();
+        ids.sort();
+        preset_read_children(id, ids.into_iter().filter_map( | id | self.inputs.get(&id).map( | input | (id, input))), out);
+    }
+    
+    fn preset_apply(&mut self, cx: &mut Cx, nodes: &[LiveNode]) {
+        preset_apply_children(cx, nodes, &mut self.inputs);
+    }
 }
 
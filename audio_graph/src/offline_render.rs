@@ -0,0 +1,270 @@
+use {
+    crate::{
+        makepad_platform::audio::*,
+        makepad_platform::midi::*,
+        makepad_platform::thread::*,
+        audio_traits::*,
+    },
+    std::path::Path,
+};
+
+// Renders an AudioGraphNode tree without an audio device, as fast as the cpu allows.
+// Midi events are applied sample accurately by splitting the render blocks on event boundaries.
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimedMidiData {
+    pub time: f64,
+    pub data: MidiData,
+}
+
+impl TimedMidiData {
+    pub fn new(time: f64, data: MidiData) -> Self {
+        Self {time, data}
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OfflineRender {
+    pub sample_rate: f64,
+    pub channel_count: usize,
+    pub block_size: usize,
+}
+
+impl Default for OfflineRender {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100.0,
+            channel_count: 2,
+            block_size: 512,
+        }
+    }
+}
+
+impl OfflineRender {
+
+    pub fn render(&self, root: &mut dyn AudioGraphNode, events: &[TimedMidiData], duration: f64) -> AudioBuffer {
+        let total_frames = (duration * self.sample_rate).round().max(0.0) as usize;
+        let block_size = self.block_size.max(1);
+
+        let mut events = events.to_vec();
+        events.sort_by( | a, b | a.time.partial_cmp(&b.time).unwrap_or(std::cmp::Ordering::Equal));
+        let mut next_event = 0;
+
+        // nobody is listening to display buffers, but nodes still want somewhere to send them
+        let to_ui = ToUIReceiver::<ToUIDisplayMsg>::default();
+        let to_ui_sender = to_ui.sender();
+        let mut display_buffers = Vec::new();
+
+        let mut output = AudioBuffer::new_with_size(total_frames, self.channel_count);
+        let mut block = AudioBuffer::default();
+        let mut frame = 0;
+        while frame < total_frames {
+            // deliver everything that is due at this frame
+            while next_event < events.len() && self.time_to_frame(events[next_event].time) <= frame {
+                root.handle_midi_data(events[next_event].data);
+                next_event += 1;
+            }
+            let mut end = (frame + block_size).min(total_frames);
+            if next_event < events.len() {
+                end = end.min(self.time_to_frame(events[next_event].time).max(frame + 1));
+            }
+
+            block.resize(end - frame, self.channel_count);
+            block.zero();
+            let info = AudioInfo {
+                device_id: AudioDeviceId::default(),
+                sample_rate: self.sample_rate,
+                time: Some(AudioTime {
+                    sample_time: frame as f64,
+                    host_time: 0,
+                    rate_scalar: 1.0
+                })
+            };
+            let mut display = DisplayAudioGraph {
+                to_ui: &to_ui_sender,
+                buffers: &mut display_buffers
+            };
+            root.render_to_audio_buffer(info, &mut [&mut block], &[], &mut display);
+
+            // hand display buffers straight back so nodes don't run out
+            while let Ok(msg) = to_ui.try_recv() {
+                if let ToUIDisplayMsg::DisplayAudio {buffer, ..} = msg {
+                    display_buffers.push(buffer);
+                }
+            }
+
+            for c in 0..self.channel_count {
+                output.channel_mut(c)[frame..end].copy_from_slice(block.channel(c));
+            }
+            frame = end;
+        }
+        output
+    }
+
+    pub fn render_to_wav(&self, root: &mut dyn AudioGraphNode, events: &[TimedMidiData], duration: f64, format: WavFormat, path: &Path) -> std::io::Result<()> {
+        let buffer = self.render(root, events, duration);
+        write_wav(path, &buffer, self.sample_rate as u32, format)
+    }
+
+    fn time_to_frame(&self, time: f64) -> usize {
+        (time * self.sample_rate).round().max(0.0) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            makepad_platform::*,
+            decode::decode_audio,
+            effects::*,
+        },
+    };
+    
+    const SAMPLE_RATE: f64 = 22050.0;
+    
+    // sine voices that start at zero phase, so a render only depends on the event timing
+    #[derive(Default)]
+    struct SineVoices {
+        voices: Vec<(u8, f64)>,
+        frame: usize,
+    }
+    
+    impl AudioGraphNode for SineVoices {
+        fn handle_midi_data(&mut self, data: MidiData) {
+            if let MidiEvent::Note(note) = data.decode() {
+                self.voices.retain( | (number, _) | *number != note.note_number);
+                if note.is_on {
+                    self.voices.push((note.note_number, 0.0));
+                }
+            }
+        }
+        
+        fn all_notes_off(&mut self) {
+            self.voices.clear();
+        }
+        
+        fn render_to_audio_buffer(&mut self, info: AudioInfo, outputs: &mut [&mut AudioBuffer], _inputs: &[&AudioBuffer], _display: &mut DisplayAudioGraph) {
+            let output = &mut outputs[0];
+            // the renderer splits blocks on events, the block start has to match where the last one ended
+            assert_eq!(info.time.unwrap().sample_time as usize, self.frame);
+            output.zero();
+            for i in 0..output.frame_count() {
+                let mut sample = 0.0;
+                for (number, phase) in &mut self.voices {
+                    let freq = 440.0 * 2.0f64.powf((*number as f64 - 69.0) / 12.0);
+                    sample += (*phase * std::f64::consts::TAU).sin() * 0.25;
+                    *phase = (*phase + freq / info.sample_rate).fract();
+                }
+                for c in 0..output.channel_count() {
+                    output.channel_mut(c)[i] = sample as f32;
+                }
+            }
+            self.frame += output.frame_count();
+        }
+    }
+    
+    // the voices through an effect, like a two node router
+    struct Chain {
+        voices: SineVoices,
+        effect: Box<dyn AudioGraphNode + Send>,
+        buffer: AudioBuffer,
+    }
+    
+    impl AudioGraphNode for Chain {
+        fn handle_midi_data(&mut self, data: MidiData) {
+            self.voices.handle_midi_data(data);
+        }
+        
+        fn all_notes_off(&mut self) {
+            self.voices.all_notes_off();
+        }
+        
+        fn render_to_audio_buffer(&mut self, info: AudioInfo, outputs: &mut [&mut AudioBuffer], _inputs: &[&AudioBuffer], display: &mut DisplayAudioGraph) {
+            self.buffer.resize_like(outputs[0]);
+            self.voices.render_to_audio_buffer(info, &mut [&mut self.buffer], &[], display);
+            self.effect.render_to_audio_buffer(info, outputs, &[&self.buffer], display);
+        }
+    }
+    
+    fn note(time: f64, is_on: bool, note_number: u8) -> TimedMidiData {
+        TimedMidiData::new(time, MidiNote {is_on, channel: 0, note_number, velocity: 100}.into())
+    }
+    
+    // events deliberately land inside render blocks
+    fn script() -> Vec<TimedMidiData> {
+        vec![
+            note(0.0, true, 60),
+            note(0.0503, true, 64),
+            note(0.1207, true, 67),
+            note(0.2011, false, 60),
+            note(0.2519, false, 64),
+            note(0.3023, false, 67),
+        ]
+    }
+    
+    fn render_reference() -> AudioBuffer {
+        let mut effect = EffectComponent::default();
+        effect.apply(ApplyFrom::NewFromDoc {file_id: LiveFileId::new(0)}, live_id!(reverb), ReverbState::default().params(), vec![0.7, 0.3, 1.0, 0.4]);
+        let mut chain = Chain {
+            voices: SineVoices::default(),
+            effect: effect.graph_node(ReverbState::default(), true),
+            buffer: AudioBuffer::default(),
+        };
+        let render = OfflineRender {sample_rate: SAMPLE_RATE, channel_count: 2, block_size: 256};
+        render.render(&mut chain, &script(), 0.5)
+    }
+    
+    // compares against tests/data/offline_render.wav, UPDATE_REFERENCE=1 rewrites it after an intended change
+    #[test]
+    fn render_matches_reference() {
+        let buffer = render_reference();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/offline_render.wav");
+        if std::env::var_os("UPDATE_REFERENCE").is_some() {
+            write_wav(&path, &buffer, SAMPLE_RATE as u32, WavFormat::Pcm16).unwrap();
+        }
+        let reference = decode_audio(std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(reference.sample_rate, SAMPLE_RATE);
+        assert_eq!(reference.buffer.channel_count(), buffer.channel_count());
+        assert_eq!(reference.buffer.frame_count(), buffer.frame_count());
+        for c in 0..buffer.channel_count() {
+            for (i, (a, b)) in buffer.channel(c).iter().zip(reference.buffer.channel(c)).enumerate() {
+                // the reference is 16 bit, allow for rounding on either side
+                assert!((a - b).abs() < 2.0 / 32767.0, "channel {} frame {}: {} vs {}", c, i, a, b);
+            }
+        }
+    }
+    
+    #[test]
+    fn render_is_block_size_independent() {
+        let mut a = SineVoices::default();
+        let mut b = SineVoices::default();
+        let a = OfflineRender {sample_rate: SAMPLE_RATE, channel_count: 1, block_size: 64}.render(&mut a, &script(), 0.4);
+        let b = OfflineRender {sample_rate: SAMPLE_RATE, channel_count: 1, block_size: 1000}.render(&mut b, &script(), 0.4);
+        assert_eq!(a.channel(0), b.channel(0));
+    }
+    
+    #[test]
+    fn offline_node_leaves_live_channel() {
+        let mut effect = EffectComponent::default();
+        let params = GainPanState::default().params();
+        effect.apply(ApplyFrom::NewFromDoc {file_id: LiveFileId::new(0)}, live_id!(gain_pan), params.clone(), vec![0.0, 0.0]);
+        let mut live = effect.graph_node(GainPanState::default(), false);
+        let mut offline = effect.graph_node(GainPanState::default(), true);
+        // an edit after the offline node was built still has to reach the live one
+        effect.apply(ApplyFrom::ApplyOver, live_id!(gain_pan), params, vec![-60.0, 0.0]);
+        
+        let input = AudioBuffer::from_data(vec![1.0; 4096], 1);
+        let render_block = | node: &mut Box<dyn AudioGraphNode + Send> | {
+            let to_ui = ToUIReceiver::<ToUIDisplayMsg>::default();
+            let mut display = DisplayAudioGraph {to_ui: &to_ui.sender(), buffers: &mut Vec::new()};
+            let info = AudioInfo {device_id: AudioDeviceId::default(), sample_rate: SAMPLE_RATE, time: None};
+            let mut output = AudioBuffer::new_with_size(4096, 1);
+            node.render_to_audio_buffer(info, &mut [&mut output], &[&input], &mut display);
+            output.channel(0)[4095]
+        };
+        assert!(render_block(&mut live) < 0.01);
+        assert!((render_block(&mut offline) - 1.0).abs() < 1e-6);
+    }
+}
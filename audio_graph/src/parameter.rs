@@ -0,0 +1,539 @@
+use {
+    crate::{
+        makepad_platform::*,
+    },
+};
+
+// Automatable parameters. Components declare them with a range and unit through
+// AudioComponent::params, the graph sets them with ParamChange messages that carry
+// a frame offset into the next rendered block, and nodes read them per sample through
+// a ParamSet which smooths every change so nothing zippers.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamUnit {
+    None,
+    Decibels,
+    Hertz,
+    Seconds,
+    Milliseconds,
+    Percent,
+    Semitones,
+    // -1 is hard left, 1 is hard right
+    Pan,
+    Ratio,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamCurve {
+    Linear,
+    // equal steps per octave over the range, min has to be above zero
+    Exponential,
+    // rounded to whole numbers
+    Stepped,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamInfo {
+    pub id: LiveId,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub unit: ParamUnit,
+    pub curve: ParamCurve,
+    // time in seconds a change takes to settle
+    pub smoothing: f64,
+}
+
+impl ParamInfo {
+    pub fn new(id: LiveId, min: f64, max: f64, default: f64) -> Self {
+        Self {
+            id,
+            min,
+            max,
+            default,
+            unit: ParamUnit::None,
+            curve: ParamCurve::Linear,
+            smoothing: 0.02,
+        }
+    }
+
+    pub fn with_unit(mut self, unit: ParamUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    pub fn with_curve(mut self, curve: ParamCurve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    pub fn clamp(&self, value: f64) -> f64 {
+        let value = value.clamp(self.min.min(self.max), self.max.max(self.min));
+        if self.curve == ParamCurve::Stepped {value.round()} else {value}
+    }
+
+    // maps the value to 0..1 along the curve, for knobs and midi controllers
+    pub fn normalize(&self, value: f64) -> f64 {
+        if self.max == self.min {
+            return 0.0
+        }
+        let value = self.clamp(value);
+        match self.curve {
+            ParamCurve::Exponential if self.min > 0.0 => (value / self.min).ln() / (self.max / self.min).ln(),
+            _ => (value - self.min) / (self.max - self.min)
+        }
+    }
+
+    pub fn denormalize(&self, normalized: f64) -> f64 {
+        let normalized = normalized.clamp(0.0, 1.0);
+        let value = match self.curve {
+            ParamCurve::Exponential if self.min > 0.0 => self.min * (self.max / self.min).powf(normalized),
+            _ => self.min + (self.max - self.min) * normalized
+        };
+        self.clamp(value)
+    }
+
+    pub fn format(&self, value: f64) -> String {
+        match self.unit {
+            ParamUnit::None => format!("{:.2}", value),
+            ParamUnit::Decibels => if value <= -96.0 {"-inf dB".to_string()} else {format!("{:.1} dB", value)},
+            ParamUnit::Hertz => if value >= 1000.0 {format!("{:.2} kHz", value / 1000.0)} else {format!("{:.1} Hz", value)},
+            ParamUnit::Seconds => if value < 1.0 {format!("{:.0} ms", value * 1000.0)} else {format!("{:.2} s", value)},
+            ParamUnit::Milliseconds => format!("{:.1} ms", value),
+            ParamUnit::Percent => format!("{:.0}%", value * 100.0),
+            ParamUnit::Semitones => format!("{:+.1} st", value),
+            ParamUnit::Pan => {
+                let pan = (value * 100.0).round() as i32;
+                if pan < 0 {format!("L{}", -pan)} else if pan > 0 {format!("R{}", pan)} else {"C".to_string()}
+            }
+            ParamUnit::Ratio => format!("{:.1}:1", value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamChange {
+    pub id: LiveId,
+    pub value: f64,
+    // frame offset into the next rendered block
+    pub frame: usize,
+    // frames to glide linearly to the value, 0 uses the smoothing of the parameter
+    pub ramp: usize,
+}
+
+impl ParamChange {
+    pub fn new(id: LiveId, value: f64) -> Self {
+        Self {id, value, frame: 0, ramp: 0}
+    }
+
+    pub fn at_frame(mut self, frame: usize) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    pub fn with_ramp(mut self, ramp: usize) -> Self {
+        self.ramp = ramp;
+        self
+    }
+}
+
+// A value that follows its target per sample, either along a one pole curve
+// with a time constant or along a linear ramp of a given length.
+#[derive(Clone, Copy, Debug)]
+pub struct SmoothedParam {
+    current: f32,
+    target: f32,
+    coefficient: f32,
+    step: f32,
+    ramp_left: usize,
+}
+
+impl Default for SmoothedParam {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+impl SmoothedParam {
+    pub fn new(value: f32) -> Self {
+        Self {
+            current: value,
+            target: value,
+            coefficient: 1.0,
+            step: 0.0,
+            ramp_left: 0,
+        }
+    }
+
+    pub fn with_time(value: f32, seconds: f64, sample_rate: f64) -> Self {
+        let mut param = Self::new(value);
+        param.set_time(seconds, sample_rate);
+        param
+    }
+
+    // reaches about 63% of a change after the given time
+    pub fn set_time(&mut self, seconds: f64, sample_rate: f64) {
+        self.coefficient = if seconds <= 0.0 || sample_rate <= 0.0 {
+            1.0
+        }
+        else {
+            (1.0 - (-1.0 / (seconds * sample_rate)).exp()) as f32
+        };
+    }
+
+    pub fn set(&mut self, target: f32) {
+        self.target = target;
+        self.ramp_left = 0;
+    }
+
+    pub fn ramp(&mut self, target: f32, frames: usize) {
+        if frames == 0 {
+            return self.jump(target)
+        }
+        self.target = target;
+        self.step = (target - self.current) / frames as f32;
+        self.ramp_left = frames;
+    }
+
+    pub fn jump(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.ramp_left = 0;
+    }
+
+    pub fn value(&self) -> f32 {self.current}
+
+    pub fn target(&self) -> f32 {self.target}
+
+    pub fn is_settled(&self) -> bool {
+        self.current == self.target
+    }
+
+    pub fn next(&mut self) -> f32 {
+        if self.ramp_left > 0 {
+            self.ramp_left -= 1;
+            self.current = if self.ramp_left == 0 {self.target} else {self.current + self.step};
+        }
+        else if self.current != self.target {
+            self.current += (self.target - self.current) * self.coefficient;
+            if (self.target - self.current).abs() <= 1e-6 * self.target.abs().max(1e-3) {
+                self.current = self.target;
+            }
+        }
+        self.current
+    }
+
+    // advances a whole block at once, for values used at control rate
+    pub fn skip(&mut self, frames: usize) -> f32 {
+        if self.ramp_left > 0 {
+            let frames = frames.min(self.ramp_left);
+            self.ramp_left -= frames;
+            self.current = if self.ramp_left == 0 {self.target} else {self.current + self.step * frames as f32};
+        }
+        else if self.current != self.target {
+            let remain = (1.0 - self.coefficient).powi(frames as i32);
+            self.current = self.target + (self.current - self.target) * remain;
+            if (self.target - self.current).abs() <= 1e-6 * self.target.abs().max(1e-3) {
+                self.current = self.target;
+            }
+        }
+        self.current
+    }
+}
+
+// The parameters of a graph node. Changes are queued with their frame offset and
+// applied when rendering reaches that frame.
+//
+// for i in 0..frames {
+//     self.params.tick();
+//     let gain = self.params.value(GAIN);
+//     ...
+// }
+// self.params.end_block();
+pub struct ParamSet {
+    infos: Vec<ParamInfo>,
+    values: Vec<SmoothedParam>,
+    // sorted by frame, read from next on so the audio thread never shifts or grows it
+    pending: Vec<ParamChange>,
+    next: usize,
+    frame: usize,
+    sample_rate: f64,
+}
+
+impl ParamSet {
+    const PENDING_CAPACITY: usize = 256;
+
+    pub fn new(infos: Vec<ParamInfo>) -> Self {
+        let values = infos.iter().map( | info | SmoothedParam::new(info.default as f32)).collect();
+        Self {
+            infos,
+            values,
+            pending: Vec::with_capacity(Self::PENDING_CAPACITY),
+            next: 0,
+            frame: 0,
+            sample_rate: 0.0,
+        }
+    }
+
+    pub fn infos(&self) -> &[ParamInfo] {
+        &self.infos
+    }
+
+    pub fn index(&self, id: LiveId) -> Option<usize> {
+        self.infos.iter().position( | info | info.id == id)
+    }
+
+    // call at the start of every block, smoothing times depend on the rate
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            for (info, value) in self.infos.iter().zip(self.values.iter_mut()) {
+                value.set_time(info.smoothing, sample_rate);
+            }
+        }
+    }
+
+    // returns false when the parameter isn't part of this set
+    pub fn push(&mut self, change: ParamChange) -> bool {
+        if self.index(change.id).is_none() {
+            return false
+        }
+        if self.pending.len() == self.pending.capacity() {
+            self.pending.drain(..self.next);
+            self.next = 0;
+            // still full, rather apply it early than allocate
+            if self.pending.len() == self.pending.capacity() {
+                self.apply(change);
+                return true
+            }
+        }
+        self.pending.push(change);
+        let mut at = self.pending.len() - 1;
+        while at > self.next && self.pending[at - 1].frame > change.frame {
+            self.pending.swap(at - 1, at);
+            at -= 1;
+        }
+        true
+    }
+
+    fn pop_due(&mut self, frame: usize) -> Option<ParamChange> {
+        let change = *self.pending.get(self.next)?;
+        if change.frame > frame {
+            return None
+        }
+        self.next += 1;
+        Some(change)
+    }
+
+    fn apply(&mut self, change: ParamChange) {
+        if let Some(index) = self.index(change.id) {
+            let value = self.infos[index].clamp(change.value) as f32;
+            // unsmoothed parameters take the value at their frame
+            if change.ramp == 0 && self.infos[index].smoothing <= 0.0 {
+                self.values[index].jump(value);
+            }
+            else if change.ramp > 0 {
+                self.values[index].ramp(value, change.ramp);
+            }
+            else {
+                self.values[index].set(value);
+            }
+        }
+    }
+
+    // applies the changes due at the current frame without advancing
+    pub fn apply_due(&mut self) {
+        while let Some(change) = self.pop_due(self.frame) {
+            self.apply(change);
+        }
+    }
+
+    // the frame of the next queued change, for nodes that render in sub-blocks
+    pub fn next_change(&self) -> Option<usize> {
+        self.pending.get(self.next).map( | p | p.frame)
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.values.iter().all( | value | value.is_settled())
+    }
+
+    // applies the changes due at the current frame and advances every value by one sample
+    pub fn tick(&mut self) {
+        self.apply_due();
+        for value in &mut self.values {
+            value.next();
+        }
+        self.frame += 1;
+    }
+
+    // applies the changes due within the next frames and advances by that many samples
+    pub fn skip(&mut self, frames: usize) {
+        let end = self.frame + frames;
+        while self.frame < end {
+            if let Some(change) = self.pop_due(self.frame) {
+                self.apply(change);
+                continue
+            }
+            let next = self.pending.get(self.next).map_or(end, | p | p.frame.min(end));
+            for value in &mut self.values {
+                value.skip(next - self.frame);
+            }
+            self.frame = next;
+        }
+    }
+
+    // sets a value right away without smoothing
+    pub fn jump(&mut self, id: LiveId, value: f64) -> bool {
+        if let Some(index) = self.index(id) {
+            self.jump_index(index, value);
+            return true
+        }
+        false
+    }
+
+    pub fn jump_index(&mut self, index: usize, value: f64) {
+        let value = self.infos[index].clamp(value) as f32;
+        self.values[index].jump(value);
+    }
+
+    pub fn value(&self, index: usize) -> f32 {
+        self.values[index].value()
+    }
+
+    pub fn value_of(&self, id: LiveId) -> Option<f32> {
+        self.index(id).map( | index | self.values[index].value())
+    }
+
+    // changes past the end of the block apply right away, the next block starts at frame 0
+    pub fn end_block(&mut self) {
+        while let Some(change) = self.pop_due(usize::MAX) {
+            self.apply(change);
+        }
+        self.pending.clear();
+        self.next = 0;
+        self.frame = 0;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutomationPoint {
+    pub beat: f64,
+    pub value: f64,
+}
+
+// A breakpoint curve in quarter notes, linear between points and holding its
+// first and last value outside of them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AutomationCurve {
+    pub points: Vec<AutomationPoint>,
+}
+
+impl AutomationCurve {
+    pub fn value_at(&self, beat: f64) -> Option<f64> {
+        let first = self.points.first()?;
+        if beat <= first.beat {
+            return Some(first.value)
+        }
+        let index = self.points.partition_point( | p | p.beat <= beat);
+        if index >= self.points.len() {
+            return self.points.last().map( | p | p.value)
+        }
+        let a = self.points[index - 1];
+        let b = self.points[index];
+        let t = if b.beat > a.beat {(beat - a.beat) / (b.beat - a.beat)} else {1.0};
+        Some(a.value + (b.value - a.value) * t)
+    }
+
+    // a point at the same beat gets replaced
+    pub fn insert(&mut self, beat: f64, value: f64) {
+        let index = self.points.partition_point( | p | p.beat < beat);
+        if let Some(point) = self.points.get_mut(index) {
+            if point.beat == beat {
+                point.value = value;
+                return
+            }
+        }
+        self.points.insert(index, AutomationPoint {beat, value});
+    }
+
+    pub fn remove_range(&mut self, start: f64, end: f64) {
+        self.points.retain( | p | p.beat < start || p.beat > end);
+    }
+
+    // drops points that lie within tolerance of the line through their neighbours
+    pub fn simplify(&mut self, tolerance: f64) {
+        if self.points.len() < 3 {
+            return
+        }
+        let mut kept = vec![self.points[0]];
+        for i in 1..self.points.len() - 1 {
+            let a = *kept.last().unwrap();
+            let p = self.points[i];
+            let b = self.points[i + 1];
+            let t = if b.beat > a.beat {(p.beat - a.beat) / (b.beat - a.beat)} else {0.0};
+            if (a.value + (b.value - a.value) * t - p.value).abs() > tolerance {
+                kept.push(p);
+            }
+        }
+        kept.push(*self.points.last().unwrap());
+        self.points = kept;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutomationLane {
+    pub param: LiveId,
+    pub curve: AutomationCurve,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CcMapping {
+    pub channel: u8,
+    pub controller: u8,
+    pub param: ParamInfo,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set() -> ParamSet {
+        ParamSet::new(vec![ParamInfo::new(live_id!(gain), 0.0, 1.0, 0.0).with_smoothing(0.0)])
+    }
+
+    #[test]
+    fn changes_apply_in_frame_order() {
+        let mut params = set();
+        params.push(ParamChange::new(live_id!(gain), 0.75).at_frame(6));
+        params.push(ParamChange::new(live_id!(gain), 0.25).at_frame(2));
+        params.push(ParamChange::new(live_id!(gain), 0.5).at_frame(4));
+        let mut seen = Vec::new();
+        for _ in 0..8 {
+            params.tick();
+            seen.push(params.value(0));
+        }
+        assert_eq!(seen, vec![0.0, 0.0, 0.25, 0.25, 0.5, 0.5, 0.75, 0.75]);
+        params.end_block();
+        assert_eq!(params.next_change(), None);
+    }
+
+    #[test]
+    fn full_queue_does_not_grow() {
+        let mut params = set();
+        let capacity = params.pending.capacity();
+        for i in 0..capacity * 2 {
+            params.push(ParamChange::new(live_id!(gain), 1.0).at_frame(i));
+            if i % 3 == 0 {
+                params.skip(1);
+            }
+        }
+        assert_eq!(params.pending.capacity(), capacity);
+        params.end_block();
+        assert_eq!(params.value(0), 1.0);
+    }
+}
@@ -0,0 +1,381 @@
+use {
+    std::collections::HashMap,
+    std::path::{Path, PathBuf},
+    crate::{
+        makepad_platform::*,
+        audio_traits::*,
+    }
+};
+
+// A preset is the live state of a component tree as read by AudioComponent::preset_read_to,
+// so anything that derives LiveRead for its settings can be saved, loaded and morphed.
+// Files are a small header followed by the nodes as cbor.
+//
+// let a = audio_graph.read_preset("warm pad");
+// bank.save(&a)?;
+// let b = bank.load("bright pad")?;
+// audio_graph.morph_presets(cx, &a, &b, 0.25);
+
+const PRESET_MAGIC: &[u8; 4] = b"MPRS";
+const PRESET_VERSION: u8 = 1;
+const PRESET_EXTENSION: &str = "preset";
+
+#[derive(Debug)]
+pub enum PresetError {
+    NotPreset,
+    Corrupt(String),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for PresetError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl std::fmt::Display for PresetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotPreset => write!(f, "not a preset file"),
+            Self::Corrupt(what) => write!(f, "corrupt preset: {}", what),
+            Self::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Preset {
+    pub name: String,
+    pub nodes: Vec<LiveNode>,
+}
+
+impl Preset {
+    pub fn new(name: &str, nodes: Vec<LiveNode>) -> Self {
+        Self {name: name.to_string(), nodes}
+    }
+
+    pub fn load(path: &Path) -> Result<Self, PresetError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), PresetError> {
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PresetError> {
+        let data = self.nodes.to_cbor(0).map_err(PresetError::Corrupt)?;
+        let name = self.name.as_bytes();
+        let mut out = Vec::with_capacity(7 + name.len() + data.len());
+        out.extend_from_slice(PRESET_MAGIC);
+        out.push(PRESET_VERSION);
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(name);
+        out.extend_from_slice(&data);
+        Ok(out)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, PresetError> {
+        if data.len() < 7 || &data[0..4] != PRESET_MAGIC {
+            return Err(PresetError::NotPreset)
+        }
+        if data[4] != PRESET_VERSION {
+            return Err(PresetError::Corrupt(format!("unknown version {}", data[4])))
+        }
+        let name_len = u16::from_le_bytes([data[5], data[6]]) as usize;
+        if data.len() < 7 + name_len {
+            return Err(PresetError::Corrupt("name runs past the end".to_string()))
+        }
+        let name = String::from_utf8_lossy(&data[7..7 + name_len]).to_string();
+        let mut nodes = Vec::new();
+        nodes.from_cbor(&data[7 + name_len..]).map_err( | e | PresetError::Corrupt(format!("{:?}", e)))?;
+        Ok(Self {name, nodes})
+    }
+
+    // Blends two presets of the same components, 0 is a and 1 is b. Numbers are
+    // interpolated, everything else like enums and bools switches halfway. The result
+    // has the layout of a, values only b has are left out. Values are matched by their
+    // path below the root, a preset read back from a file has lost the id of its root.
+    pub fn morph(a: &Preset, b: &Preset, amount: f64) -> Preset {
+        let amount = amount.clamp(0.0, 1.0);
+        let mut b_values = HashMap::new();
+        let mut path = Vec::new();
+        for node in &b.nodes {
+            if node.value.is_close() {
+                path.pop();
+                continue;
+            }
+            path.push(if path.is_empty() {LiveId(0)} else {node.id});
+            if !node.value.is_open() {
+                b_values.insert(path.clone(), &node.value);
+                path.pop();
+            }
+        }
+        let mut nodes = a.nodes.clone();
+        path.clear();
+        for node in &mut nodes {
+            if node.value.is_close() {
+                path.pop();
+                continue;
+            }
+            path.push(if path.is_empty() {LiveId(0)} else {node.id});
+            if !node.value.is_open() {
+                if let Some(b_value) = b_values.get(&path) {
+                    node.value = morph_value(&node.value, b_value, amount);
+                }
+                path.pop();
+            }
+        }
+        Preset {
+            name: if amount < 0.5 {a.name.clone()} else {b.name.clone()},
+            nodes
+        }
+    }
+}
+
+fn morph_value(a: &LiveValue, b: &LiveValue, amount: f64) -> LiveValue {
+    fn number(value: &LiveValue) -> Option<f64> {
+        match value {
+            LiveValue::Float64(v) => Some(*v),
+            LiveValue::Float32(v) => Some(*v as f64),
+            LiveValue::Int64(v) => Some(*v as f64),
+            _ => None
+        }
+    }
+    if let (Some(from), Some(to)) = (number(a), number(b)) {
+        let v = from + (to - from) * amount;
+        // files store whole floats as ints, so only two ints blend as an int
+        return match (a, b) {
+            (LiveValue::Float32(_), _) => LiveValue::Float32(v as f32),
+            (LiveValue::Int64(_), LiveValue::Int64(_)) => LiveValue::Int64(v.round() as i64),
+            _ => LiveValue::Float64(v),
+        }
+    }
+    if amount < 0.5 {a.clone()} else {b.clone()}
+}
+
+// A directory of preset files, named after the preset.
+pub struct PresetBank {
+    dir: PathBuf,
+}
+
+impl PresetBank {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {dir: dir.as_ref().to_path_buf()}
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // the names of the presets in the directory, sorted
+    pub fn list(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if let Ok(entries) = std::fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().map_or(false, | ext | ext == PRESET_EXTENSION) {
+                    if let Some(stem) = path.file_stem() {
+                        names.push(stem.to_string_lossy().to_string());
+                    }
+                }
+            }
+        }
+        names.sort();
+        names
+    }
+
+    pub fn load(&self, name: &str) -> Result<Preset, PresetError> {
+        let mut preset = Preset::load(&self.path(name))?;
+        preset.name = name.to_string();
+        Ok(preset)
+    }
+
+    pub fn save(&self, preset: &Preset) -> Result<(), PresetError> {
+        std::fs::create_dir_all(&self.dir)?;
+        preset.save(&self.path(&preset.name))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), PresetError> {
+        std::fs::remove_file(self.path(name))?;
+        Ok(())
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        // keep names from escaping the directory
+        let name: String = name.chars().map( | c | if c == '/' || c == '\\' {'_'} else {c}).collect();
+        self.dir.join(format!("{}.{}", name, PRESET_EXTENSION))
+    }
+}
+
+// for containers, their preset is an object with one node per child
+pub fn preset_read_children<'a>(id: LiveId, children: impl Iterator<Item = (LiveId, &'a AudioComponentRef)>, out: &mut Vec<LiveNode>) {
+    out.open_object(id);
+    for (child_id, child) in children {
+        child.preset_read_to(child_id, out);
+    }
+    out.close();
+}
+
+pub fn preset_apply_children(cx: &mut Cx, nodes: &[LiveNode], children: &mut ComponentMap<LiveId, AudioComponentRef>) {
+    if nodes.is_empty() || !nodes[0].value.is_open() {
+        return
+    }
+    let mut index = 1;
+    while index < nodes.len() && !nodes[index].value.is_close() {
+        let next = nodes.skip_node(index);
+        if let Some(child) = children.get_mut(&nodes[index].id) {
+            child.preset_apply(cx, &nodes[index..next]);
+        }
+        index = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn value(id: LiveId, value: LiveValue) -> LiveNode {
+        LiveNode::from_id_value(id, value)
+    }
+    
+    // a synth with two oscillators that share their field names
+    fn synth(level1: f64, level2: f32, voices: i64, mono: bool, wave: LiveId) -> Vec<LiveNode> {
+        let mut nodes = Vec::new();
+        nodes.open_object(live_id!(synth));
+        nodes.open_object(live_id!(osc1));
+        nodes.push(value(live_id!(level), LiveValue::Float64(level1)));
+        nodes.push(value(live_id!(wave), LiveValue::BareEnum(wave)));
+        nodes.close();
+        nodes.open_object(live_id!(osc2));
+        nodes.push(value(live_id!(level), LiveValue::Float32(level2)));
+        nodes.close();
+        nodes.push(value(live_id!(voices), LiveValue::Int64(voices)));
+        nodes.push(value(live_id!(mono), LiveValue::Bool(mono)));
+        nodes.close();
+        nodes
+    }
+    
+    fn find(nodes: &[LiveNode], path: &[LiveId]) -> Option<LiveValue> {
+        let mut stack = Vec::new();
+        for node in nodes {
+            if node.value.is_close() {
+                stack.pop();
+                continue;
+            }
+            stack.push(node.id);
+            if !node.value.is_open() {
+                if stack == path {
+                    return Some(node.value.clone())
+                }
+                stack.pop();
+            }
+        }
+        None
+    }
+    
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("makepad_preset_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+    
+    #[test]
+    fn bank_round_trip() {
+        let dir = temp_dir("bank");
+        let bank = PresetBank::new(&dir);
+        let warm = Preset::new("warm pad", synth(0.25, 0.5, 8, false, live_id!(Saw)));
+        let bright = Preset::new("bright/lead", synth(1.0, 0.0, 1, true, live_id!(Square)));
+        bank.save(&warm).unwrap();
+        bank.save(&bright).unwrap();
+        // a file with another extension isn't a preset of the bank
+        std::fs::write(dir.join("notes.txt"), "hello").unwrap();
+        
+        assert_eq!(bank.list(), vec!["bright_lead".to_string(), "warm pad".to_string()]);
+        let loaded = bank.load("warm pad").unwrap();
+        assert_eq!(loaded.name, "warm pad");
+        // the root loses its id in the file and whole floats come back as ints, the rest as saved
+        let values = | nodes: &[LiveNode] | nodes.iter().skip(1).map( | node | (node.id, match node.value {
+            LiveValue::Float64(v) if v.fract() == 0.0 => LiveValue::Int64(v as i64),
+            LiveValue::Float32(v) if v.fract() == 0.0 => LiveValue::Int64(v as i64),
+            _ => node.value.clone()
+        })).collect::<Vec<_ >> ();
+        assert_eq!(values(&loaded.nodes), values(&warm.nodes));
+        let loaded_bright = bank.load("bright_lead").unwrap();
+        assert_eq!(values(&loaded_bright.nodes), values(&bright.nodes));
+        // so a loaded preset still morphs with one read from the components
+        let half = Preset::morph(&warm, &loaded_bright, 0.5);
+        assert_eq!(find(&half.nodes, &[live_id!(synth), live_id!(osc1), live_id!(level)]), Some(LiveValue::Float64(0.625)));
+        
+        
+        bank.delete("warm pad").unwrap();
+        assert_eq!(bank.list(), vec!["bright_lead".to_string()]);
+        assert!(matches!(bank.load("warm pad"), Err(PresetError::Io(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn broken_files() {
+        let bytes = Preset::new("name", synth(0.25, 0.5, 8, false, live_id!(Saw))).to_bytes().unwrap();
+        assert!(matches!(Preset::from_bytes(b"RIFF"), Err(PresetError::NotPreset)));
+        assert!(matches!(Preset::from_bytes(&bytes[..9]), Err(PresetError::Corrupt(_))));
+        let mut version = bytes.clone();
+        version[4] = 99;
+        assert!(matches!(Preset::from_bytes(&version), Err(PresetError::Corrupt(_))));
+    }
+    
+    #[test]
+    fn morph_between_presets() {
+        let a = Preset::new("a", synth(0.0, 1.0, 2, false, live_id!(Saw)));
+        let b = Preset::new("b", synth(1.0, 0.0, 7, true, live_id!(Square)));
+        let level1 = [live_id!(synth), live_id!(osc1), live_id!(level)];
+        let level2 = [live_id!(synth), live_id!(osc2), live_id!(level)];
+        let wave = [live_id!(synth), live_id!(osc1), live_id!(wave)];
+        let voices = [live_id!(synth), live_id!(voices)];
+        let mono = [live_id!(synth), live_id!(mono)];
+        
+        let start = Preset::morph(&a, &b, 0.0);
+        assert_eq!(start.name, "a");
+        assert_eq!(start.nodes, a.nodes);
+        
+        let half = Preset::morph(&a, &b, 0.5);
+        assert_eq!(half.name, "b");
+        assert_eq!(find(&half.nodes, &level1), Some(LiveValue::Float64(0.5)));
+        assert_eq!(find(&half.nodes, &level2), Some(LiveValue::Float32(0.5)));
+        assert_eq!(find(&half.nodes, &voices), Some(LiveValue::Int64(5)));
+        // values that can't be blended switch halfway
+        assert_eq!(find(&half.nodes, &mono), Some(LiveValue::Bool(true)));
+        assert_eq!(find(&half.nodes, &wave), Some(LiveValue::BareEnum(live_id!(Square))));
+        assert_eq!(find(&Preset::morph(&a, &b, 0.49).nodes, &mono), Some(LiveValue::Bool(false)));
+        
+        let end = Preset::morph(&a, &b, 1.0);
+        assert_eq!(end.nodes, b.nodes);
+        // out of range amounts are clamped
+        assert_eq!(Preset::morph(&a, &b, 3.0).nodes, b.nodes);
+    }
+    
+    #[test]
+    fn morph_with_params_in_one_preset() {
+        let a = Preset::new("a", synth(0.0, 1.0, 2, false, live_id!(Saw)));
+        // b has no osc2 but a cutoff a doesn't know
+        let mut nodes = Vec::new();
+        nodes.open_object(live_id!(synth));
+        nodes.open_object(live_id!(osc1));
+        nodes.push(value(live_id!(level), LiveValue::Float64(1.0)));
+        nodes.close();
+        nodes.push(value(live_id!(cutoff), LiveValue::Float64(800.0)));
+        nodes.close();
+        let b = Preset::new("b", nodes);
+        
+        let half = Preset::morph(&a, &b, 0.5);
+        assert_eq!(find(&half.nodes, &[live_id!(synth), live_id!(osc1), live_id!(level)]), Some(LiveValue::Float64(0.5)));
+        // only in a, it keeps its value all the way
+        let osc2 = [live_id!(synth), live_id!(osc2), live_id!(level)];
+        assert_eq!(find(&half.nodes, &osc2), Some(LiveValue::Float32(1.0)));
+        assert_eq!(find(&Preset::morph(&a, &b, 1.0).nodes, &osc2), Some(LiveValue::Float32(1.0)));
+        assert_eq!(find(&half.nodes, &[live_id!(synth), live_id!(voices)]), Some(LiveValue::Int64(2)));
+        // only in b, the result has the layout of a so it is left out
+        assert_eq!(find(&half.nodes, &[live_id!(synth), live_id!(cutoff)]), None);
+        assert_eq!(half.nodes.len(), a.nodes.len());
+    }
+}
@@ -0,0 +1,237 @@
+use {
+    std::sync::Arc,
+    crate::makepad_platform::audio::*,
+};
+
+// Band limited resampling with a kaiser windowed sinc kernel.
+// The kernel is tabulated once and read with linear interpolation, so the ratio
+// can change at any time (pitch bends, voices at different notes) without rebuilding tables.
+
+// zero crossings on either side of the kernel center
+const HALF_WIDTH: usize = 16;
+// table entries per zero crossing
+const RESOLUTION: usize = 256;
+// when downsampling the kernel is stretched, beyond this ratio we accept aliasing
+const MAX_STRETCH: f64 = 4.0;
+// power of two ring size that holds the widest stretched kernel
+const HISTORY: usize = 256;
+// keeps the passband a little under nyquist so the transition band doesn't alias
+const CUTOFF: f64 = 0.92;
+const KAISER_BETA: f64 = 8.6;
+
+pub struct SincKernel {
+    table: Vec<f32>,
+}
+
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half = x * 0.5;
+    for k in 1..32 {
+        term *= half / k as f64;
+        sum += term * term;
+    }
+    sum
+}
+
+impl SincKernel {
+    pub fn new() -> Self {
+        let len = HALF_WIDTH * RESOLUTION + 2;
+        let norm = bessel_i0(KAISER_BETA);
+        let table = (0..len).map( | i | {
+            let x = i as f64 / RESOLUTION as f64;
+            if x >= HALF_WIDTH as f64 {
+                return 0.0
+            }
+            let t = x / HALF_WIDTH as f64;
+            let window = bessel_i0(KAISER_BETA * (1.0 - t * t).sqrt()) / norm;
+            let sx = std::f64::consts::PI * x * CUTOFF;
+            let sinc = if x == 0.0 {1.0} else {sx.sin() / sx};
+            (CUTOFF * sinc * window) as f32
+        }).collect();
+        Self {table}
+    }
+
+    // kernel value at a distance in zero crossings
+    fn value(&self, x: f64) -> f32 {
+        let pos = x.abs() * RESOLUTION as f64;
+        let index = pos as usize;
+        if index + 1 >= self.table.len() {
+            return 0.0
+        }
+        let frac = (pos - index as f64) as f32;
+        self.table[index] + (self.table[index + 1] - self.table[index]) * frac
+    }
+}
+
+impl Default for SincKernel {
+    fn default() -> Self {Self::new()}
+}
+
+// A streaming resampler. It pulls source frames on demand and writes output frames,
+// step is the amount of source frames advanced per output frame.
+pub struct Resampler {
+    kernel: Arc<SincKernel>,
+    channel_count: usize,
+    step: f64,
+    // frame interleaved ring of the most recent source frames
+    history: Vec<f32>,
+    frames_in: u64,
+    // source position of the next output frame
+    position: f64,
+    // total source length once the source ran dry
+    end: Option<u64>,
+    frame: Vec<f32>,
+    sum: Vec<f32>,
+}
+
+impl Resampler {
+    pub fn new(channel_count: usize, source_rate: f64, target_rate: f64) -> Self {
+        Self::with_kernel(Arc::new(SincKernel::new()), channel_count, source_rate / target_rate)
+    }
+
+    // voices share one kernel table
+    pub fn with_kernel(kernel: Arc<SincKernel>, channel_count: usize, step: f64) -> Self {
+        Self {
+            kernel,
+            channel_count,
+            step,
+            history: vec![0.0; HISTORY * channel_count],
+            frames_in: 0,
+            position: 0.0,
+            end: None,
+            frame: vec![0.0; channel_count],
+            sum: vec![0.0; channel_count],
+        }
+    }
+
+    pub fn set_step(&mut self, step: f64) {
+        self.step = step.max(0.0);
+    }
+
+    pub fn set_rates(&mut self, source_rate: f64, target_rate: f64) {
+        self.set_step(source_rate / target_rate);
+    }
+
+    pub fn step(&self) -> f64 {self.step}
+
+    // current read position in source frames
+    pub fn position(&self) -> f64 {self.position}
+
+    pub fn reset(&mut self) {
+        self.history.iter_mut().for_each( | v | *v = 0.0);
+        self.frames_in = 0;
+        self.position = 0.0;
+        self.end = None;
+    }
+
+    // true once the source ran dry and all of it has been played out
+    pub fn is_finished(&self) -> bool {
+        self.end.map_or(false, | end | self.position >= end as f64)
+    }
+
+    // Renders into output starting at frame offset, pulling source frames through `source`
+    // which fills one frame and returns false at the end. Returns the frames written.
+    pub fn process<F: FnMut(&mut [f32]) -> bool>(&mut self, output: &mut AudioBuffer, offset: usize, frames: usize, mut source: F) -> usize {
+        let channel_count = self.channel_count;
+        let out_channels = output.channel_count();
+        let stretch = self.step.clamp(1.0, MAX_STRETCH);
+        let scale = 1.0 / stretch;
+        let reach = (HALF_WIDTH as f64 * stretch).ceil() as i64;
+        for i in 0..frames {
+            if self.is_finished() {
+                return i
+            }
+            let center = self.position.floor() as i64;
+            // make sure every frame the kernel touches has been pulled in
+            while (self.frames_in as i64) <= center + reach {
+                self.frame.iter_mut().for_each( | v | *v = 0.0);
+                if self.end.is_none() && !source(&mut self.frame) {
+                    self.end = Some(self.frames_in);
+                    self.frame.iter_mut().for_each( | v | *v = 0.0);
+                }
+                let slot = (self.frames_in as usize & (HISTORY - 1)) * channel_count;
+                self.history[slot..slot + channel_count].copy_from_slice(&self.frame);
+                self.frames_in += 1;
+            }
+            self.sum.iter_mut().for_each( | v | *v = 0.0);
+            for k in (center - reach + 1).max(0)..=center + reach {
+                let weight = self.kernel.value((self.position - k as f64) * scale) * scale as f32;
+                if weight == 0.0 {
+                    continue
+                }
+                let slot = (k as usize & (HISTORY - 1)) * channel_count;
+                for (s, h) in self.sum.iter_mut().zip(&self.history[slot..slot + channel_count]) {
+                    *s += h * weight;
+                }
+            }
+            for c in 0..out_channels {
+                // a mono source feeds every output channel
+                output.channel_mut(c)[offset + i] = self.sum[c.min(channel_count - 1)];
+            }
+            self.position += self.step;
+        }
+        frames
+    }
+
+    // converts a whole buffer in one go
+    pub fn resample_buffer(buffer: &AudioBuffer, source_rate: f64, target_rate: f64) -> AudioBuffer {
+        let channel_count = buffer.channel_count();
+        if source_rate == target_rate || buffer.frame_count() == 0 {
+            return buffer.clone()
+        }
+        let mut resampler = Self::new(channel_count, source_rate, target_rate);
+        let frames = (buffer.frame_count() as f64 * target_rate / source_rate).ceil() as usize;
+        let mut output = AudioBuffer::new_with_size(frames, channel_count);
+        let mut read = 0;
+        resampler.process(&mut output, 0, frames, | frame | {
+            if read >= buffer.frame_count() {
+                return false
+            }
+            for (c, v) in frame.iter_mut().enumerate() {
+                *v = buffer.channel(c)[read];
+            }
+            read += 1;
+            true
+        });
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn sine(freq: f64, rate: f64, frames: usize) -> AudioBuffer {
+        let data = (0..frames).map( | i | (std::f64::consts::TAU * freq * i as f64 / rate).sin() as f32).collect();
+        AudioBuffer::from_data(data, 1)
+    }
+    
+    // the largest difference to the ideal sine, away from the edges the kernel runs off
+    fn max_error(buffer: &AudioBuffer, freq: f64, rate: f64) -> f32 {
+        let expected = sine(freq, rate, buffer.frame_count());
+        let edge = HALF_WIDTH * 4;
+        buffer.channel(0)[edge..buffer.frame_count() - edge].iter().zip(&expected.channel(0)[edge..]).fold(0.0, | max, (a, b) | max.max((a - b).abs()))
+    }
+    
+    #[test]
+    fn resamples_a_sine_up_and_down() {
+        let source = sine(1000.0, 44100.0, 4410);
+        let up = Resampler::resample_buffer(&source, 44100.0, 48000.0);
+        assert_eq!(up.frame_count(), 4800);
+        assert!(max_error(&up, 1000.0, 48000.0) < 1e-3);
+        let down = Resampler::resample_buffer(&source, 44100.0, 22050.0);
+        assert_eq!(down.frame_count(), 2205);
+        assert!(max_error(&down, 1000.0, 22050.0) < 1e-3);
+    }
+    
+    #[test]
+    fn filters_above_the_target_nyquist() {
+        // 15khz folds back to 7.05khz at 22.05khz unless the kernel removes it
+        let source = sine(15000.0, 44100.0, 4410);
+        let down = Resampler::resample_buffer(&source, 44100.0, 22050.0);
+        let edge = HALF_WIDTH * 4;
+        let peak = down.channel(0)[edge..down.frame_count() - edge].iter().fold(0.0f32, | max, v | max.max(v.abs()));
+        assert!(peak < 0.01, "alias at {}", peak);
+    }
+}
@@ -0,0 +1,704 @@
+use {
+    crate::{
+        makepad_platform::*,
+        makepad_platform::thread::*,
+        makepad_platform::audio::*,
+        makepad_platform::midi::*,
+        register_audio_component,
+        audio_traits::*,
+        parameter::*,
+        preset::*,
+    },
+};
+
+// A Router wires its child components into an arbitrary acyclic graph.
+// Ports are referred to by name, an empty port name means the first (main) port.
+// The router itself is addressed as `input` when reading its inputs and `output` when writing its outputs.
+//
+// Router {
+//     synth = <IronFish> {}
+//     kick = <Drums> {}
+//     reverb = <Reverb> {}
+//     comp = <Compressor> {}
+//     wires: [
+//         {from: synth, to: comp}
+//         {from: kick, to: comp, to_port: sidechain}
+//         {from: comp, to: reverb, gain: 0.3} // send
+//         {from: reverb, to: output} // return
+//         {from: comp, to: output}
+//     ]
+// }
+
+live_design!{
+    Router = {{Router}} {
+    }
+}
+
+#[derive(Clone, Debug, Live, LiveHook)]
+pub struct Wire {
+    #[live] from: LiveId,
+    #[live] from_port: LiveId,
+    #[live] to: LiveId,
+    #[live] to_port: LiveId,
+    #[live(1.0)] gain: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Endpoint {
+    External,
+    Node(usize)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Route {
+    from: Endpoint,
+    from_port: usize,
+    to: Endpoint,
+    to_port: usize,
+    gain: f32,
+}
+
+// nodes take their ports as slices of references, these are built on the stack so a block doesn't allocate
+const MAX_PORTS: usize = 8;
+
+// the wires resolved against the node list, in the order the nodes render
+struct Wiring {
+    order: Vec<LiveId>,
+    input_counts: Vec<usize>,
+    output_counts: Vec<usize>,
+    routes: Vec<Route>,
+}
+
+// a plan is built on the UI thread down to the buffers, the audio thread only moves
+// the nodes that keep running into it and sends the old plan back to be dropped
+struct Plan {
+    channels: usize,
+    slots: Vec<Slot>,
+    // (running slot, new slot) for nodes that keep running, their new slot holds a Running placeholder
+    keep: Vec<(usize, usize)>,
+    output_routes: Vec<Route>,
+}
+
+enum FromUI {
+    UpdatePlan(Plan)
+}
+
+enum ToUI {
+    Retired(Plan),
+    Rejected(Plan),
+}
+
+#[derive(Live)]
+pub struct Router {
+    #[live] wires: Vec<Wire>,
+    #[live] input_ports: Vec<LiveId>,
+    #[live] output_ports: Vec<LiveId>,
+    // channel count of the internal buses, 0 follows the output
+    #[live] channels: usize,
+
+    #[rust] node_order: Vec<LiveId>,
+    #[rust] nodes: ComponentMap<LiveId, AudioComponentRef>,
+    // what the audio thread runs, in slot order
+    #[rust] running: Vec<(LiveId, LiveType)>,
+    #[rust] has_graph_node: bool,
+    #[rust] from_ui: FromUISender<FromUI>,
+    #[rust] to_ui: ToUIReceiver<ToUI>,
+}
+
+impl LiveHook for Router {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, Router)
+    }
+
+    fn before_apply(&mut self, _cx: &mut Cx, from: ApplyFrom, _index: usize, _nodes: &[LiveNode]) {
+        if from.is_from_doc() {
+            self.node_order.clear();
+        }
+    }
+
+    fn apply_value_instance(&mut self, cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) -> usize {
+        if from.is_from_doc() {
+            self.node_order.push(nodes[index].id);
+        }
+        self.nodes.get_or_insert(cx, nodes[index].id, | cx | {AudioComponentRef::new(cx)})
+            .apply(cx, from, index, nodes)
+    }
+
+    fn after_apply(&mut self, cx: &mut Cx, from: ApplyFrom, _index: usize, _nodes: &[LiveNode]) {
+        if from.is_from_doc() {
+            self.nodes.retain_visible();
+            // live reload: hand the running graph a new plan with only the nodes it doesn't have yet
+            if self.has_graph_node {
+                let plan = self.build_plan(cx, true, false);
+                let _ = self.from_ui.send(FromUI::UpdatePlan(plan));
+            }
+        }
+    }
+}
+
+impl Router {
+    fn port_index(ports: &[LiveId], port: LiveId) -> Option<usize> {
+        if port.is_empty() {
+            return Some(0)
+        }
+        ports.iter().position( | p | *p == port)
+    }
+
+    fn node_ports(&mut self, id: LiveId) -> Option<(Vec<LiveId>, Vec<LiveId>)> {
+        if let Some(node) = self.nodes.get_mut(&id).and_then( | node | node.as_mut()) {
+            let mut inputs = node.input_ports();
+            let mut outputs = node.output_ports();
+            if inputs.len() > MAX_PORTS || outputs.len() > MAX_PORTS {
+                error!("Router node {} has more than {} ports on a side, the rest stay unconnected", id, MAX_PORTS);
+                inputs.truncate(MAX_PORTS);
+                outputs.truncate(MAX_PORTS);
+            }
+            return Some((inputs, outputs))
+        }
+        None
+    }
+
+    // resolves the wiring into slots. with reuse, nodes the audio thread runs already are kept
+    // and only the components it doesn't have yet, or whose type changed, build a graph node
+    fn build_plan(&mut self, cx: &mut Cx, reuse: bool, offline: bool) -> Plan {
+        let mut ids = Vec::new();
+        for id in &self.node_order {
+            if self.nodes.get_mut(id).and_then( | node | node.as_mut()).is_some() {
+                ids.push(*id);
+            }
+        }
+        let wiring = self.build_wiring(&ids);
+        let mut slots = Vec::new();
+        let mut keep = Vec::new();
+        let mut running = Vec::new();
+        for (index, id) in wiring.order.iter().enumerate() {
+            let node = self.nodes.get_mut(id).and_then( | node | node.as_mut()).unwrap();
+            let live_type = node.type_id();
+            let position = self.running.iter().position( | r | *r == (*id, live_type)).filter( | _ | reuse);
+            let graph_node: Box<dyn AudioGraphNode + Send> = if let Some(position) = position {
+                keep.push((position, index));
+                Box::new(Running)
+            }
+            else if offline {
+                node.get_offline_graph_node(cx)
+            }
+            else {
+                node.get_graph_node(cx)
+            };
+            running.push((*id, live_type));
+            let mut inputs = Vec::new();
+            inputs.resize_with(wiring.input_counts[index], AudioBuffer::default);
+            let mut outputs = Vec::new();
+            outputs.resize_with(wiring.output_counts[index], AudioBuffer::default);
+            slots.push(Slot {
+                id: *id,
+                graph_node,
+                inputs,
+                outputs,
+                routes: wiring.routes.iter().filter( | route | route.to == Endpoint::Node(index)).cloned().collect()
+            });
+        }
+        if !offline {
+            self.running = running;
+        }
+        Plan {
+            channels: self.channels,
+            slots,
+            keep,
+            output_routes: wiring.routes.iter().filter( | route | route.to == Endpoint::External).cloned().collect()
+        }
+    }
+
+    fn build_wiring(&mut self, ids: &[LiveId]) -> Wiring {
+        let mut ports = Vec::new();
+        for id in ids {
+            ports.push(self.node_ports(*id).unwrap_or_default());
+        }
+        let router_inputs = if self.input_ports.len() > 0 {self.input_ports.clone()} else {vec![live_id!(main)]};
+        let router_outputs = if self.output_ports.len() > 0 {self.output_ports.clone()} else {vec![live_id!(main)]};
+        Self::resolve_wiring(&self.wires, ids, &ports, &router_inputs, &router_outputs)
+    }
+    
+    // true if node can reach itself over the edges between the nodes not sorted yet
+    fn on_cycle(node: usize, edges: &[Route], done: &[bool]) -> bool {
+        let mut stack = vec![node];
+        let mut seen = vec![false; done.len()];
+        while let Some(from) = stack.pop() {
+            for edge in edges {
+                if let (Endpoint::Node(edge_from), Endpoint::Node(to)) = (edge.from, edge.to) {
+                    if edge_from == from && !done[to] {
+                        if to == node {
+                            return true
+                        }
+                        if !seen[to] {
+                            seen[to] = true;
+                            stack.push(to);
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+    
+    fn resolve_wiring(wires: &[Wire], ids: &[LiveId], ports: &[(Vec<LiveId>, Vec<LiveId>)], router_inputs: &[LiveId], router_outputs: &[LiveId]) -> Wiring {
+        let input_counts: Vec<usize> = ports.iter().map( | (inputs, _) | inputs.len().max(1)).collect();
+        let output_counts: Vec<usize> = ports.iter().map( | (_, outputs) | outputs.len().max(1)).collect();
+
+        // resolve the wires against the node list
+        let mut edges = Vec::new();
+        for wire in wires {
+            let from = if wire.from == live_id!(input) {
+                Self::port_index(router_inputs, wire.from_port).map( | port | (Endpoint::External, port))
+            }
+            else if let Some(index) = ids.iter().position( | id | *id == wire.from) {
+                Self::port_index(&ports[index].1, wire.from_port).map( | port | (Endpoint::Node(index), port))
+            }
+            else {
+                error!("Router wire from unknown node {}", wire.from);
+                continue;
+            };
+            let to = if wire.to == live_id!(output) {
+                Self::port_index(router_outputs, wire.to_port).map( | port | (Endpoint::External, port))
+            }
+            else if let Some(index) = ids.iter().position( | id | *id == wire.to) {
+                Self::port_index(&ports[index].0, wire.to_port).map( | port | (Endpoint::Node(index), port))
+            }
+            else {
+                error!("Router wire to unknown node {}", wire.to);
+                continue;
+            };
+            if let (Some((from, from_port)), Some((to, to_port))) = (from, to) {
+                edges.push(Route {from, from_port, to, to_port, gain: wire.gain as f32});
+            }
+            else {
+                error!("Router wire {}.{} -> {}.{} uses an unknown port", wire.from, wire.from_port, wire.to, wire.to_port);
+            }
+        }
+
+        // topological sort, keeping declaration order among independent nodes
+        let mut in_degree = vec![0usize; ids.len()];
+        for edge in &edges {
+            if let (Endpoint::Node(_), Endpoint::Node(to)) = (edge.from, edge.to) {
+                in_degree[to] += 1;
+            }
+        }
+        let mut sorted = Vec::new();
+        let mut done = vec![false; ids.len()];
+        while sorted.len() < ids.len() {
+            // stuck on a cycle, it is broken at its first declared node so the nodes after the
+            // cycle still come after it
+            let next = (0..ids.len()).find( | i | !done[*i] && in_degree[*i] == 0)
+                .or_else( || (0..ids.len()).find( | i | !done[*i] && Self::on_cycle(*i, &edges, &done)))
+                .unwrap();
+            done[next] = true;
+            sorted.push(next);
+            for edge in &edges {
+                if let (Endpoint::Node(from), Endpoint::Node(to)) = (edge.from, edge.to) {
+                    if from == next && !done[to] {
+                        in_degree[to] -= 1;
+                    }
+                }
+            }
+        }
+
+        // remap to sorted slots, anything not running forward would be feedback
+        let mut slot_of = vec![0; ids.len()];
+        for (slot, index) in sorted.iter().enumerate() {
+            slot_of[*index] = slot;
+        }
+        let sorted_id = | slot: usize | ids[sorted[slot]];
+        let remap = | endpoint: Endpoint | match endpoint {
+            Endpoint::Node(index) => Endpoint::Node(slot_of[index]),
+            Endpoint::External => Endpoint::External
+        };
+        let routes = edges.iter().map( | edge | Route {from: remap(edge.from), to: remap(edge.to), ..*edge}).filter( | route | {
+            match (route.from, route.to) {
+                (Endpoint::Node(from), Endpoint::Node(to)) if from >= to => {
+                    error!("Router has a cycle, the feedback wire from {} to {} is ignored", sorted_id(from), sorted_id(to));
+                    false
+                }
+                _ => true
+            }
+        }).collect();
+
+        Wiring {
+            order: sorted.iter().map( | index | ids[*index]).collect(),
+            input_counts: sorted.iter().map( | index | input_counts[*index]).collect(),
+            output_counts: sorted.iter().map( | index | output_counts[*index]).collect(),
+            routes
+        }
+    }
+}
+
+struct Slot {
+    id: LiveId,
+    graph_node: Box<dyn AudioGraphNode + Send>,
+    inputs: Vec<AudioBuffer>,
+    outputs: Vec<AudioBuffer>,
+    routes: Vec<Route>,
+}
+
+struct Node {
+    from_ui: FromUIReceiver<FromUI>,
+    to_ui: ToUISender<ToUI>,
+    channels: usize,
+    slots: Vec<Slot>,
+    output_routes: Vec<Route>,
+    // stand-ins past a slot's outputs when filling the port array
+    spare_outputs: [AudioBuffer; MAX_PORTS],
+}
+
+// holds the slot of a node that keeps running until the audio thread moves it in
+struct Running;
+
+impl AudioGraphNode for Running {
+    fn handle_midi_data(&mut self, _data: MidiData) {}
+    fn all_notes_off(&mut self) {}
+    fn render_to_audio_buffer(&mut self, _info: AudioInfo, _outputs: &mut [&mut AudioBuffer], _inputs: &[&AudioBuffer], _display: &mut DisplayAudioGraph) {}
+}
+
+impl Node {
+    fn new(from_ui: FromUIReceiver<FromUI>, to_ui: ToUISender<ToUI>, plan: Plan) -> Self {
+        Self {
+            from_ui,
+            to_ui,
+            channels: plan.channels,
+            slots: plan.slots,
+            output_routes: plan.output_routes,
+            spare_outputs: Default::default(),
+        }
+    }
+
+    fn update_plan(&mut self, mut plan: Plan) {
+        // a plan expecting a node we don't run would leave slots and routes out of step, keep the current one
+        let matches = plan.keep.iter().all( | (running, slot) | {
+            self.slots.get(*running).map( | s | s.id) == plan.slots.get(*slot).map( | s | s.id)
+        });
+        if !matches {
+            let _ = self.to_ui.send(ToUI::Rejected(plan));
+            return
+        }
+        // existing nodes keep running with their state, new ones slot in
+        for (running, slot) in &plan.keep {
+            std::mem::swap(&mut self.slots[*running].graph_node, &mut plan.slots[*slot].graph_node);
+        }
+        std::mem::swap(&mut self.slots, &mut plan.slots);
+        std::mem::swap(&mut self.output_routes, &mut plan.output_routes);
+        self.channels = plan.channels;
+        // the replaced nodes and buffers are dropped on the UI thread
+        let _ = self.to_ui.send(ToUI::Retired(plan));
+    }
+
+    fn mix_into(output: &mut AudioBuffer, input: &AudioBuffer, gain: f32) {
+        if input.channel_count() == 0 || input.frame_count() != output.frame_count() {
+            return
+        }
+        // buses with a different width wrap around their channels, so mono spreads over stereo
+        for c in 0..output.channel_count() {
+            let in_channel = input.channel(c % input.channel_count());
+            let out_channel = output.channel_mut(c);
+            for i in 0..out_channel.len() {
+                out_channel[i] += in_channel[i] * gain;
+            }
+        }
+    }
+}
+
+impl AudioGraphNode for Node {
+    fn all_notes_off(&mut self) {
+        for slot in &mut self.slots {
+            slot.graph_node.all_notes_off();
+        }
+    }
+
+    fn handle_midi_data(&mut self, data: MidiData) {
+        for slot in &mut self.slots {
+            slot.graph_node.handle_midi_data(data);
+        }
+    }
+
+    fn set_param(&mut self, change: ParamChange) {
+        for slot in &mut self.slots {
+            slot.graph_node.set_param(change);
+        }
+    }
+
+    fn render_to_audio_buffer(
+        &mut self,
+        info: AudioInfo,
+        outputs: &mut [&mut AudioBuffer],
+        inputs: &[&AudioBuffer],
+        display: &mut DisplayAudioGraph
+    ) {
+        while let Ok(msg) = self.from_ui.try_recv() {
+            match msg {
+                FromUI::UpdatePlan(plan) => self.update_plan(plan)
+            }
+        }
+
+        let frame_count = outputs[0].frame_count();
+        let channels = if self.channels > 0 {self.channels} else {outputs[0].channel_count()};
+
+        for index in 0..self.slots.len() {
+            let (done, rest) = self.slots.split_at_mut(index);
+            let slot = &mut rest[0];
+            for input in &mut slot.inputs {
+                input.resize(frame_count, channels);
+                input.zero();
+            }
+            for route in &slot.routes {
+                let source = match route.from {
+                    Endpoint::External => inputs.get(route.from_port).copied(),
+                    Endpoint::Node(from) => done[from].outputs.get(route.from_port)
+                };
+                if let Some(source) = source {
+                    Self::mix_into(&mut slot.inputs[route.to_port], source, route.gain);
+                }
+            }
+            for output in &mut slot.outputs {
+                output.resize(frame_count, channels);
+                output.zero();
+            }
+            let input_count = slot.inputs.len();
+            let output_count = slot.outputs.len();
+            let slot_inputs: [&AudioBuffer; MAX_PORTS] = std::array::from_fn( | i | slot.inputs.get(i).unwrap_or(&slot.inputs[0]));
+            let mut outputs_iter = slot.outputs.iter_mut();
+            let mut spare_iter = self.spare_outputs.iter_mut();
+            let mut slot_outputs: [&mut AudioBuffer; MAX_PORTS] = std::array::from_fn( | _ | outputs_iter.next().or_else( || spare_iter.next()).unwrap());
+            slot.graph_node.render_to_audio_buffer(info, &mut slot_outputs[0..output_count], &slot_inputs[0..input_count], display);
+        }
+
+        for output in outputs.iter_mut() {
+            output.zero();
+        }
+        for route in &self.output_routes {
+            let source = match route.from {
+                Endpoint::External => inputs.get(route.from_port).copied(),
+                Endpoint::Node(from) => self.slots[from].outputs.get(route.from_port)
+            };
+            if let (Some(source), Some(output)) = (source, outputs.get_mut(route.to_port)) {
+                Self::mix_into(output, source, route.gain);
+            }
+        }
+    }
+}
+
+impl AudioComponent for Router {
+    fn get_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.from_ui.new_channel();
+        self.has_graph_node = true;
+        let plan = self.build_plan(cx, false, false);
+        Box::new(Node::new(self.from_ui.receiver(), self.to_ui.sender(), plan))
+    }
+
+    fn get_offline_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        // builds every node without touching what the live graph was handed
+        let plan = self.build_plan(cx, false, true);
+        Box::new(Node::new(self.from_ui.detached_receiver(), ToUIReceiver::default().sender(), plan))
+    }
+
+    fn input_ports(&self) -> Vec<LiveId> {
+        if self.input_ports.len() > 0 {self.input_ports.clone()} else {vec![live_id!(main)]}
+    }
+
+    fn output_ports(&self) -> Vec<LiveId> {
+        if self.output_ports.len() > 0 {self.output_ports.clone()} else {vec![live_id!(main)]}
+    }
+
+    fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+        while let Ok(msg) = self.to_ui.try_recv() {
+            match msg {
+                ToUI::Retired(plan) => drop(plan),
+                ToUI::Rejected(plan) => {
+                    drop(plan);
+                    error!("Router plan did not match the running graph, rebuilding all nodes");
+                    let plan = self.build_plan(cx, false, false);
+                    let _ = self.from_ui.send(FromUI::UpdatePlan(plan));
+                }
+            }
+        }
+        for node in self.nodes.values_mut() {
+            if let Some(node) = node.as_mut() {
+                node.handle_event_with(cx, event, dispatch_action)
+            }
+        }
+    }
+
+    fn audio_query(&mut self, query: &AudioQuery, callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        for node in self.nodes.values_mut() {
+            node.audio_query(query, callback) ?;
+        }
+        AudioResult::not_found()
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        self.node_order.iter().filter_map( | id | self.nodes.get(id)).flat_map( | node | node.params()).collect()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        preset_read_children(id, self.node_order.iter().filter_map( | id | self.nodes.get(id).map( | node | (*id, node))), out);
+    }
+    
+    fn preset_apply(&mut self, cx: &mut Cx, nodes: &[LiveNode]) {
+        preset_apply_children(cx, nodes, &mut self.nodes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // a node that only remembers which one it is
+    struct Tagged(usize);
+    
+    impl AudioGraphNode for Tagged {
+        fn handle_midi_data(&mut self, _data: MidiData) {}
+        fn all_notes_off(&mut self) {}
+        fn render_to_audio_buffer(&mut self, _info: AudioInfo, outputs: &mut [&mut AudioBuffer], _inputs: &[&AudioBuffer], _display: &mut DisplayAudioGraph) {
+            outputs[0].channel_mut(0).fill(self.0 as f32);
+        }
+    }
+    
+    fn slot(id: LiveId, graph_node: Box<dyn AudioGraphNode + Send>) -> Slot {
+        Slot {id, graph_node, inputs: vec![AudioBuffer::default()], outputs: vec![AudioBuffer::default()], routes: Vec::new()}
+    }
+    
+    fn plan(slots: Vec<Slot>, keep: Vec<(usize, usize)>) -> Plan {
+        let output_routes = (0..slots.len()).map( | i | Route {from: Endpoint::Node(i), from_port: 0, to: Endpoint::External, to_port: 0, gain: 1.0}).collect();
+        Plan {channels: 1, slots, keep, output_routes}
+    }
+    
+    fn render(node: &mut Node) -> f32 {
+        let to_ui = ToUIReceiver::<ToUIDisplayMsg>::default();
+        let mut display = DisplayAudioGraph {to_ui: &to_ui.sender(), buffers: &mut Vec::new()};
+        let info = AudioInfo {device_id: AudioDeviceId::default(), sample_rate: 48000.0, time: None};
+        let mut output = AudioBuffer::new_with_size(16, 1);
+        node.render_to_audio_buffer(info, &mut [&mut output], &[], &mut display);
+        output.channel(0)[0]
+    }
+    
+    fn wire(from: LiveId, to: LiveId, to_port: LiveId) -> Wire {
+        Wire {from, from_port: LiveId::empty(), to, to_port, gain: 1.0}
+    }
+    
+    // nodes with a main input and output, and a sidechain input on comp
+    fn resolve(ids: &[LiveId], wires: &[Wire]) -> Wiring {
+        let ports: Vec<(Vec<LiveId>, Vec<LiveId>)> = ids.iter().map( | id | {
+            let inputs = if *id == live_id!(comp) {vec![live_id!(main), live_id!(sidechain)]} else {vec![live_id!(main)]};
+            (inputs, vec![live_id!(main)])
+        }).collect();
+        Router::resolve_wiring(wires, ids, &ports, &[live_id!(main)], &[live_id!(main)])
+    }
+    
+    fn node_routes(wiring: &Wiring) -> Vec<(LiveId, LiveId, usize)> {
+        wiring.routes.iter().filter_map( | route | match (route.from, route.to) {
+            (Endpoint::Node(from), Endpoint::Node(to)) => Some((wiring.order[from], wiring.order[to], route.to_port)),
+            _ => None
+        }).collect()
+    }
+    
+    #[test]
+    fn wiring_sorts_sends_and_sidechains() {
+        let empty = LiveId::empty();
+        // declared with the effects first, they have to render after what feeds them
+        let ids = [live_id!(reverb), live_id!(comp), live_id!(synth), live_id!(kick), live_id!(pad)];
+        let wiring = resolve(&ids, &[
+            wire(live_id!(synth), live_id!(comp), empty),
+            wire(live_id!(kick), live_id!(comp), live_id!(sidechain)),
+            wire(live_id!(comp), live_id!(reverb), empty),
+            wire(live_id!(reverb), live_id!(output), empty),
+            wire(live_id!(comp), live_id!(output), empty),
+            wire(live_id!(input), live_id!(synth), empty),
+        ]);
+        assert_eq!(wiring.order, vec![live_id!(synth), live_id!(kick), live_id!(comp), live_id!(reverb), live_id!(pad)]);
+        assert_eq!(node_routes(&wiring), vec![
+            (live_id!(synth), live_id!(comp), 0),
+            (live_id!(kick), live_id!(comp), 1),
+            (live_id!(comp), live_id!(reverb), 0),
+        ]);
+        assert_eq!(wiring.routes.len(), 6);
+        assert_eq!(wiring.input_counts, vec![1, 1, 2, 1, 1]);
+        for route in &wiring.routes {
+            if let (Endpoint::Node(from), Endpoint::Node(to)) = (route.from, route.to) {
+                assert!(from < to);
+            }
+        }
+    }
+    
+    #[test]
+    fn wiring_drops_only_the_feedback_wire_of_a_cycle() {
+        let empty = LiveId::empty();
+        // synth -> a -> b -> a is a loop, c hangs off the loop and is declared before it
+        let ids = [live_id!(c), live_id!(a), live_id!(b), live_id!(synth)];
+        let wiring = resolve(&ids, &[
+            wire(live_id!(synth), live_id!(a), empty),
+            wire(live_id!(a), live_id!(b), empty),
+            wire(live_id!(b), live_id!(a), empty),
+            wire(live_id!(b), live_id!(c), empty),
+            wire(live_id!(c), live_id!(output), empty),
+        ]);
+        assert_eq!(wiring.order, vec![live_id!(synth), live_id!(a), live_id!(b), live_id!(c)]);
+        assert_eq!(node_routes(&wiring), vec![
+            (live_id!(synth), live_id!(a), 0),
+            (live_id!(a), live_id!(b), 0),
+            (live_id!(b), live_id!(c), 0),
+        ]);
+        assert_eq!(wiring.routes.len(), 4);
+        
+        // a node wired to itself keeps its other wires
+        let wiring = resolve(&[live_id!(a), live_id!(b)], &[
+            wire(live_id!(a), live_id!(a), empty),
+            wire(live_id!(a), live_id!(b), empty),
+        ]);
+        assert_eq!(wiring.order, vec![live_id!(a), live_id!(b)]);
+        assert_eq!(node_routes(&wiring), vec![(live_id!(a), live_id!(b), 0)]);
+    }
+    
+    #[test]
+    fn wiring_skips_unknown_nodes_and_ports() {
+        let wiring = resolve(&[live_id!(a), live_id!(comp)], &[
+            wire(live_id!(a), live_id!(comp), live_id!(nope)),
+            wire(live_id!(missing), live_id!(comp), LiveId::empty()),
+            wire(live_id!(a), live_id!(comp), live_id!(sidechain)),
+        ]);
+        assert_eq!(node_routes(&wiring), vec![(live_id!(a), live_id!(comp), 1)]);
+    }
+    
+    #[test]
+    fn update_plan_keeps_running_nodes() {
+        let to_ui = ToUIReceiver::default();
+        let mut node = Node::new(FromUISender::default().detached_receiver(), to_ui.sender(), plan(vec![
+            slot(live_id!(a), Box::new(Tagged(1))),
+            slot(live_id!(b), Box::new(Tagged(2))),
+        ], Vec::new()));
+        assert_eq!(render(&mut node), 3.0);
+        
+        // b keeps running in the first slot, a is replaced
+        node.update_plan(plan(vec![
+            slot(live_id!(b), Box::new(Running)),
+            slot(live_id!(a), Box::new(Tagged(10))),
+        ], vec![(1, 0)]));
+        assert_eq!(render(&mut node), 12.0);
+        match to_ui.try_recv() {
+            Ok(ToUI::Retired(old)) => assert_eq!(old.slots.iter().map( | s | s.id).collect::<Vec<_ >> (), vec![live_id!(a), live_id!(b)]),
+            _ => panic!("the old plan was not sent back")
+        }
+    }
+    
+    #[test]
+    fn update_plan_rejects_missing_nodes() {
+        let to_ui = ToUIReceiver::default();
+        let mut node = Node::new(FromUISender::default().detached_receiver(), to_ui.sender(), plan(vec![
+            slot(live_id!(a), Box::new(Tagged(1))),
+        ], Vec::new()));
+        // the plan expects a running b that isn't there
+        node.update_plan(plan(vec![
+            slot(live_id!(a), Box::new(Running)),
+            slot(live_id!(b), Box::new(Running)),
+        ], vec![(0, 0), (1, 1)]));
+        assert!(matches!(to_ui.try_recv(), Ok(ToUI::Rejected(_))));
+        assert_eq!(node.slots.len(), 1);
+        assert_eq!(render(&mut node), 1.0);
+    }
+}
@@ -0,0 +1,715 @@
+use {
+    std::path::Path,
+    std::sync::{Arc, mpsc},
+    crate::{
+        makepad_platform::*,
+        makepad_platform::thread::*,
+        makepad_platform::audio::*,
+        makepad_platform::midi::*,
+        register_audio_component,
+        audio_traits::*,
+        decode::*,
+        resampler::*,
+    },
+};
+
+// Plays an audio file on MIDI notes. The sample comes either from a live dependency
+// (decoded into memory) or from a path on disk, which can be streamed instead of loaded.
+// Playback is resampled to the device rate and follows the note pitch around root_note.
+//
+// kick = <SamplePlayer> {source: dep("crate://self/samples/kick.wav"), key_low: 36, key_high: 36}
+// pad = <SamplePlayer> {path: "/sounds/pad.ogg", stream: true, one_shot: false, looping: true, loop_start: 44100}
+
+live_design!{
+    SamplePlayer = {{SamplePlayer}} {
+    }
+}
+
+// decoded blocks buffered between the disk thread and each voice
+const STREAM_QUEUE_BLOCKS: usize = 8;
+
+pub struct SampleData {
+    // the whole sample, or its first frames when streaming
+    pub head: AudioBuffer,
+    pub sample_rate: f64,
+    // total length, None while streaming a file whose length the container doesn't state
+    pub frame_count: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Settings {
+    root_note: u32,
+    key_low: u32,
+    key_high: u32,
+    pitch_track: bool,
+    one_shot: bool,
+    looping: bool,
+    loop_start: usize,
+    loop_end: usize,
+    gain: f32,
+    release: f64,
+}
+
+#[derive(Clone, PartialEq)]
+struct LoadKey {
+    source: String,
+    path: String,
+    stream: bool,
+    preload_frames: usize,
+    polyphony: usize,
+}
+
+enum StreamCommand {
+    Start {voice: usize, generation: u64, from: usize, looping: Option<(usize, usize)>},
+    Stop {voice: usize},
+}
+
+// an empty buffer marks the end of the stream
+struct StreamBlock {
+    generation: u64,
+    buffer: AudioBuffer,
+}
+
+struct StreamLink {
+    commands: mpsc::Sender<StreamCommand>,
+    blocks: Vec<mpsc::Receiver<StreamBlock>>,
+}
+
+enum FromUI {
+    Load {
+        sample: Option<Arc<SampleData>>,
+        streams: Option<StreamLink>,
+        settings: Settings,
+        polyphony: usize,
+    },
+    Settings(Settings),
+}
+
+#[derive(Live)]
+pub struct SamplePlayer {
+    // decoded into memory, for samples shipped with the application
+    #[live] source: LiveDependency,
+    // a file on disk, decoded into memory unless stream is set
+    #[live] path: String,
+    #[live] stream: bool,
+    // frames decoded up front when streaming, so notes start without waiting on the disk
+    #[live(65536usize)] preload_frames: usize,
+    // the note that plays the sample at its recorded pitch
+    #[live(60u32)] root_note: u32,
+    #[live(0u32)] key_low: u32,
+    #[live(127u32)] key_high: u32,
+    #[live(true)] pitch_track: bool,
+    // one-shots ignore note off and play to the end
+    #[live(true)] one_shot: bool,
+    #[live] looping: bool,
+    // loop points in frames of the source file, a loop_end of 0 means the end of the file
+    #[live] loop_start: usize,
+    #[live] loop_end: usize,
+    #[live(1.0)] gain: f64,
+    // fade out in seconds on note off and when a voice gets stolen
+    #[live(0.01)] release: f64,
+    #[live(8usize)] polyphony: usize,
+
+    #[rust] sample: Option<Arc<SampleData>>,
+    #[rust] loaded: Option<LoadKey>,
+    #[rust] has_graph_node: bool,
+    #[rust] from_ui: FromUISender<FromUI>,
+}
+
+impl LiveHook for SamplePlayer {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, SamplePlayer)
+    }
+
+    fn after_apply(&mut self, cx: &mut Cx, from: ApplyFrom, _index: usize, _nodes: &[LiveNode]) {
+        if !from.is_from_doc() {
+            return
+        }
+        let key = self.load_key();
+        if self.loaded.as_ref() != Some(&key) {
+            self.load_sample(cx);
+            self.loaded = Some(key);
+            if self.has_graph_node {
+                let _ = self.from_ui.send(FromUI::Load {
+                    sample: self.sample.clone(),
+                    streams: self.start_streams(),
+                    settings: self.settings(),
+                    polyphony: self.polyphony.max(1),
+                });
+            }
+        }
+        else if self.has_graph_node {
+            let _ = self.from_ui.send(FromUI::Settings(self.settings()));
+        }
+    }
+}
+
+impl SamplePlayer {
+    fn load_key(&self) -> LoadKey {
+        LoadKey {
+            source: self.source.as_str().to_string(),
+            path: self.path.clone(),
+            stream: self.stream,
+            preload_frames: self.preload_frames,
+            polyphony: self.polyphony,
+        }
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            root_note: self.root_note,
+            key_low: self.key_low,
+            key_high: self.key_high,
+            pitch_track: self.pitch_track,
+            one_shot: self.one_shot,
+            looping: self.looping,
+            loop_start: self.loop_start,
+            loop_end: self.loop_end,
+            gain: self.gain as f32,
+            release: self.release,
+        }
+    }
+
+    fn is_streaming(&self) -> bool {
+        self.stream && self.path.len() > 0
+    }
+
+    fn load_sample(&mut self, cx: &mut Cx) {
+        self.sample = None;
+        let result = if self.source.as_str().len() > 0 {
+            match cx.get_dependency(self.source.as_str()) {
+                Ok(data) => decode_audio(data.to_vec()).map( | decoded | SampleData {
+                    frame_count: Some(decoded.buffer.frame_count()),
+                    head: decoded.buffer,
+                    sample_rate: decoded.sample_rate,
+                }),
+                Err(err) => {
+                    error!("SamplePlayer cannot load {}: {}", self.source.as_str(), err);
+                    return
+                }
+            }
+        }
+        else if self.path.len() > 0 {
+            if self.stream {
+                Self::preload_file(Path::new(&self.path), self.preload_frames)
+            }
+            else {
+                decode_audio_file(Path::new(&self.path)).map( | decoded | SampleData {
+                    frame_count: Some(decoded.buffer.frame_count()),
+                    head: decoded.buffer,
+                    sample_rate: decoded.sample_rate,
+                })
+            }
+        }
+        else {
+            return
+        };
+        match result {
+            Ok(sample) => self.sample = Some(Arc::new(sample)),
+            Err(err) => error!("SamplePlayer cannot decode {}{}: {}", self.source.as_str(), self.path, err)
+        }
+    }
+
+    fn preload_file(path: &Path, preload_frames: usize) -> Result<SampleData, DecodeError> {
+        let mut decoder = open_decoder_file(path)?;
+        let channel_count = decoder.channel_count();
+        let mut blocks = Vec::new();
+        let mut frames = 0;
+        let mut at_end = false;
+        while frames < preload_frames {
+            if let Some(block) = decoder.decode_block()? {
+                frames += block.frame_count();
+                blocks.push(block);
+            }
+            else {
+                at_end = true;
+                break
+            }
+        }
+        let mut head = AudioBuffer::new_with_size(frames, channel_count);
+        let mut offset = 0;
+        for block in blocks {
+            for c in 0..channel_count {
+                head.channel_mut(c)[offset..offset + block.frame_count()].copy_from_slice(block.channel(c));
+            }
+            offset += block.frame_count();
+        }
+        Ok(SampleData {
+            head,
+            sample_rate: decoder.sample_rate(),
+            frame_count: if at_end {Some(frames)} else {decoder.frame_count()},
+        })
+    }
+
+    // every graph node gets its own disk thread, it exits when the node drops the link
+    fn start_streams(&self) -> Option<StreamLink> {
+        if !self.is_streaming() || self.sample.is_none() {
+            return None
+        }
+        let (commands, command_receiver) = mpsc::channel();
+        let mut senders = Vec::new();
+        let mut blocks = Vec::new();
+        for _ in 0..self.polyphony.max(1) {
+            let (sender, receiver) = mpsc::sync_channel(STREAM_QUEUE_BLOCKS);
+            senders.push(sender);
+            blocks.push(receiver);
+        }
+        let path = self.path.clone();
+        std::thread::spawn(move || stream_thread(path, command_receiver, senders));
+        Some(StreamLink {commands, blocks})
+    }
+
+    fn graph_node(&self, from_ui: FromUIReceiver<FromUI>) -> Box<dyn AudioGraphNode + Send> {
+        let mut node = Node {
+            from_ui,
+            sample: None,
+            streams: None,
+            settings: self.settings(),
+            kernel: Arc::new(SincKernel::new()),
+            voices: Vec::new(),
+            buffer: AudioBuffer::default(),
+            note_counter: 0,
+            generation: 0,
+        };
+        node.load(self.sample.clone(), self.start_streams(), self.settings(), self.polyphony.max(1));
+        Box::new(node)
+    }
+}
+
+struct StreamVoice {
+    decoder: Box<dyn AudioDecoder>,
+    generation: u64,
+    // source frame of the next decoded frame
+    position: usize,
+    looping: Option<(usize, usize)>,
+    pending: Option<StreamBlock>,
+    done: bool,
+}
+
+fn copy_frames(buffer: &AudioBuffer, start: usize, end: usize) -> AudioBuffer {
+    let mut out = AudioBuffer::new_with_size(end - start, buffer.channel_count());
+    for c in 0..buffer.channel_count() {
+        out.channel_mut(c).copy_from_slice(&buffer.channel(c)[start..end]);
+    }
+    out
+}
+
+impl StreamVoice {
+    // decodes forward until the given frame, the remainder of the straddling block is returned
+    fn seek(&mut self, frame: usize) -> Result<Option<AudioBuffer>, DecodeError> {
+        if frame < self.position {
+            self.decoder.rewind()?;
+            self.position = 0;
+        }
+        while let Some(block) = self.decoder.decode_block()? {
+            let start = self.position;
+            self.position += block.frame_count();
+            if self.position > frame {
+                return Ok(Some(copy_frames(&block, frame - start, block.frame_count())))
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_block(&mut self) -> Result<Option<AudioBuffer>, DecodeError> {
+        let block = match self.decoder.decode_block()? {
+            Some(block) => {
+                self.position += block.frame_count();
+                Some(block)
+            }
+            None => None
+        };
+        if let Some((loop_start, loop_end)) = self.looping {
+            let loop_end = if loop_end == 0 {usize::MAX} else {loop_end};
+            match block {
+                Some(block) if self.position < loop_end => Ok(Some(block)),
+                Some(block) => {
+                    // cut at the loop end and continue from the loop start
+                    let start = self.position - block.frame_count();
+                    let keep = loop_end.saturating_sub(start);
+                    let head = copy_frames(&block, 0, keep.min(block.frame_count()));
+                    let tail = self.seek(loop_start)?;
+                    self.pending_tail(tail);
+                    Ok(Some(head))
+                }
+                None => {
+                    let tail = self.seek(loop_start)?;
+                    Ok(tail)
+                }
+            }
+        }
+        else {
+            Ok(block)
+        }
+    }
+
+    fn pending_tail(&mut self, tail: Option<AudioBuffer>) {
+        if let Some(buffer) = tail {
+            self.pending = Some(StreamBlock {generation: self.generation, buffer});
+        }
+    }
+}
+
+fn stream_thread(path: String, commands: mpsc::Receiver<StreamCommand>, outputs: Vec<mpsc::SyncSender<StreamBlock >>) {
+    let mut voices: Vec<Option<StreamVoice >> = outputs.iter().map( | _ | None).collect();
+    loop {
+        // block on commands while idle, otherwise just poll them
+        let idle = voices.iter().all( | v | v.is_none());
+        let mut next = if idle {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return
+            }
+        }
+        else {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(mpsc::TryRecvError::Empty) => None,
+                Err(mpsc::TryRecvError::Disconnected) => return
+            }
+        };
+        while let Some(command) = next.take() {
+            match command {
+                StreamCommand::Start {voice, generation, from, looping} => {
+                    voices[voice] = match open_decoder_file(Path::new(&path)) {
+                        Ok(decoder) => {
+                            let mut stream = StreamVoice {decoder, generation, position: 0, looping, pending: None, done: false};
+                            match stream.seek(from) {
+                                Ok(first) => stream.pending_tail(first),
+                                Err(err) => error!("SamplePlayer stream {}: {}", path, err)
+                            }
+                            Some(stream)
+                        }
+                        Err(err) => {
+                            error!("SamplePlayer cannot open {}: {}", path, err);
+                            None
+                        }
+                    };
+                }
+                StreamCommand::Stop {voice} => {
+                    voices[voice] = None;
+                }
+            }
+            next = commands.try_recv().ok();
+        }
+
+        let mut progressed = false;
+        for (index, slot) in voices.iter_mut().enumerate() {
+            let stream = if let Some(stream) = slot {stream} else {continue};
+            if stream.pending.is_none() && !stream.done {
+                let buffer = match stream.next_block() {
+                    Ok(Some(buffer)) => buffer,
+                    Ok(None) => {
+                        stream.done = true;
+                        AudioBuffer::default()
+                    }
+                    Err(err) => {
+                        error!("SamplePlayer stream {}: {}", path, err);
+                        stream.done = true;
+                        AudioBuffer::default()
+                    }
+                };
+                stream.pending = Some(StreamBlock {generation: stream.generation, buffer});
+            }
+            if let Some(block) = stream.pending.take() {
+                match outputs[index].try_send(block) {
+                    Ok(()) => progressed = true,
+                    Err(mpsc::TrySendError::Full(block)) => stream.pending = Some(block),
+                    Err(mpsc::TrySendError::Disconnected(_)) => return
+                }
+            }
+            if stream.done && stream.pending.is_none() {
+                *slot = None;
+            }
+        }
+        if !progressed {
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+    }
+}
+
+// where a voice reads its frames from
+struct VoiceSource {
+    // next frame to read from the head
+    cursor: usize,
+    // past the head, frames come from the disk thread
+    streaming: bool,
+    block: AudioBuffer,
+    block_pos: usize,
+    generation: u64,
+}
+
+impl VoiceSource {
+    fn next_frame(&mut self, sample: &SampleData, settings: &Settings, stream: Option<&mpsc::Receiver<StreamBlock >>, frame: &mut [f32]) -> bool {
+        let head = &sample.head;
+        let channel_count = head.channel_count();
+        if !self.streaming {
+            let length = sample.frame_count.unwrap_or(usize::MAX);
+            let region_end = if settings.looping && settings.loop_end > 0 {settings.loop_end.min(length)} else {length};
+            if self.cursor >= region_end {
+                if settings.looping && settings.loop_start < region_end {
+                    self.cursor = settings.loop_start;
+                }
+                else {
+                    return false
+                }
+            }
+            if self.cursor >= head.frame_count() {
+                if stream.is_none() {
+                    return false
+                }
+                self.streaming = true;
+            }
+            else {
+                for (c, v) in frame.iter_mut().enumerate() {
+                    *v = head.channel(c.min(channel_count - 1))[self.cursor];
+                }
+                self.cursor += 1;
+                return true
+            }
+        }
+        let stream = if let Some(stream) = stream {stream} else {return false};
+        while self.block_pos >= self.block.frame_count() {
+            match stream.try_recv() {
+                Ok(block) => {
+                    if block.generation != self.generation {
+                        continue
+                    }
+                    if block.buffer.frame_count() == 0 {
+                        return false
+                    }
+                    self.block = block.buffer;
+                    self.block_pos = 0;
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    // the disk didn't keep up, play silence rather than stopping the voice
+                    frame.iter_mut().for_each( | v | *v = 0.0);
+                    return true
+                }
+                Err(mpsc::TryRecvError::Disconnected) => return false
+            }
+        }
+        let block_channels = self.block.channel_count();
+        for (c, v) in frame.iter_mut().enumerate() {
+            *v = self.block.channel(c.min(block_channels - 1))[self.block_pos];
+        }
+        self.block_pos += 1;
+        true
+    }
+}
+
+struct Voice {
+    active: bool,
+    note: u32,
+    started: u64,
+    velocity: f32,
+    // 1.0 while held, fades to 0.0 once released
+    level: f32,
+    released: bool,
+    resampler: Resampler,
+    source: VoiceSource,
+}
+
+struct Node {
+    from_ui: FromUIReceiver<FromUI>,
+    sample: Option<Arc<SampleData>>,
+    streams: Option<StreamLink>,
+    settings: Settings,
+    kernel: Arc<SincKernel>,
+    voices: Vec<Voice>,
+    buffer: AudioBuffer,
+    note_counter: u64,
+    generation: u64,
+}
+
+impl Node {
+    fn load(&mut self, sample: Option<Arc<SampleData>>, streams: Option<StreamLink>, settings: Settings, polyphony: usize) {
+        self.sample = sample;
+        self.streams = streams;
+        self.settings = settings;
+        let channel_count = self.sample.as_ref().map_or(1, | s | s.head.channel_count().max(1));
+        self.voices = (0..polyphony).map( | _ | Voice {
+            active: false,
+            note: 0,
+            started: 0,
+            velocity: 0.0,
+            level: 0.0,
+            released: false,
+            resampler: Resampler::with_kernel(self.kernel.clone(), channel_count, 1.0),
+            source: VoiceSource {
+                cursor: 0,
+                streaming: false,
+                block: AudioBuffer::default(),
+                block_pos: 0,
+                generation: 0,
+            }
+        }).collect();
+    }
+
+    fn needs_stream(&self, sample: &SampleData) -> bool {
+        let length = sample.frame_count.unwrap_or(usize::MAX);
+        let region_end = if self.settings.looping && self.settings.loop_end > 0 {self.settings.loop_end.min(length)} else {length};
+        self.streams.is_some() && region_end > sample.head.frame_count()
+    }
+
+    fn note_on(&mut self, note: u32, velocity: u32) {
+        let sample = if let Some(sample) = self.sample.clone() {sample} else {return};
+        if note < self.settings.key_low || note > self.settings.key_high || self.voices.is_empty() {
+            return
+        }
+        // a free voice, otherwise steal the oldest one
+        let index = self.voices.iter().position( | v | !v.active).unwrap_or_else( || {
+            self.voices.iter().enumerate().min_by_key( | (_, v) | v.started).map( | (i, _) | i).unwrap_or(0)
+        });
+        self.generation += 1;
+        self.note_counter += 1;
+        let needs_stream = self.needs_stream(&sample);
+        let voice = &mut self.voices[index];
+        voice.active = true;
+        voice.note = note;
+        voice.started = self.note_counter;
+        voice.velocity = velocity as f32 / 127.0;
+        voice.level = 1.0;
+        voice.released = false;
+        voice.resampler.reset();
+        voice.source.cursor = 0;
+        voice.source.streaming = false;
+        voice.source.block_pos = voice.source.block.frame_count();
+        voice.source.generation = self.generation;
+        if let Some(streams) = &self.streams {
+            if needs_stream {
+                let looping = if self.settings.looping {Some((self.settings.loop_start, self.settings.loop_end))} else {None};
+                let _ = streams.commands.send(StreamCommand::Start {
+                    voice: index,
+                    generation: self.generation,
+                    from: sample.head.frame_count(),
+                    looping
+                });
+            }
+        }
+    }
+
+    fn note_off(&mut self, note: u32) {
+        if self.settings.one_shot {
+            return
+        }
+        for voice in &mut self.voices {
+            if voice.active && voice.note == note {
+                voice.released = true;
+            }
+        }
+    }
+
+    fn stop_voice(streams: &Option<StreamLink>, voice: &mut Voice, index: usize) {
+        voice.active = false;
+        if let Some(streams) = streams {
+            let _ = streams.commands.send(StreamCommand::Stop {voice: index});
+        }
+    }
+}
+
+impl AudioGraphNode for Node {
+    fn all_notes_off(&mut self) {
+        for (index, voice) in self.voices.iter_mut().enumerate() {
+            if voice.active {
+                Self::stop_voice(&self.streams, voice, index);
+            }
+        }
+    }
+
+    fn handle_midi_data(&mut self, data: MidiData) {
+        match data.decode() {
+            MidiEvent::Note(note) if note.is_on && note.velocity > 0 => {
+                self.note_on(note.note_number as u32, note.velocity as u32);
+            }
+            MidiEvent::Note(note) => {
+                self.note_off(note.note_number as u32);
+            }
+            _ => ()
+        }
+    }
+
+    fn render_to_audio_buffer(
+        &mut self,
+        info: AudioInfo,
+        outputs: &mut [&mut AudioBuffer],
+        _inputs: &[&AudioBuffer],
+        _display: &mut DisplayAudioGraph
+    ) {
+        while let Ok(msg) = self.from_ui.try_recv() {
+            match msg {
+                FromUI::Load {sample, streams, settings, polyphony} => {
+                    self.load(sample, streams, settings, polyphony);
+                }
+                FromUI::Settings(settings) => {
+                    self.settings = settings;
+                }
+            }
+        }
+        let output = &mut outputs[0];
+        output.zero();
+        let sample = if let Some(sample) = &self.sample {sample.clone()} else {return};
+        let frame_count = output.frame_count();
+        let channel_count = output.channel_count();
+        self.buffer.resize(frame_count, channel_count);
+        let settings = self.settings;
+        let rate_step = sample.sample_rate / info.sample_rate.max(1.0);
+        let release_step = if settings.release > 0.0 {(1.0 / (settings.release * info.sample_rate)) as f32} else {1.0};
+        for (index, voice) in self.voices.iter_mut().enumerate() {
+            if !voice.active {
+                continue
+            }
+            let pitch = if settings.pitch_track {
+                2.0f64.powf((voice.note as f64 - settings.root_note as f64) / 12.0)
+            }
+            else {
+                1.0
+            };
+            voice.resampler.set_step(rate_step * pitch);
+            let stream = self.streams.as_ref().and_then( | s | s.blocks.get(index));
+            let source = &mut voice.source;
+            let rendered = voice.resampler.process(&mut self.buffer, 0, frame_count, | frame | {
+                source.next_frame(&sample, &settings, stream, frame)
+            });
+            let gain = settings.gain * voice.velocity;
+            for c in 0..channel_count {
+                let mut level = voice.level;
+                let input = self.buffer.channel(c);
+                let out = output.channel_mut(c);
+                for i in 0..rendered {
+                    if voice.released {
+                        level = (level - release_step).max(0.0);
+                    }
+                    out[i] += input[i] * gain * level;
+                }
+                if c == channel_count - 1 {
+                    voice.level = level;
+                }
+            }
+            if rendered < frame_count || (voice.released && voice.level <= 0.0) {
+                Self::stop_voice(&self.streams, voice, index);
+            }
+        }
+    }
+}
+
+impl AudioComponent for SamplePlayer {
+    fn get_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.from_ui.new_channel();
+        self.has_graph_node = true;
+        let from_ui = self.from_ui.receiver();
+        self.graph_node(from_ui)
+    }
+
+    fn get_offline_graph_node(&mut self, _cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        let from_ui = self.from_ui.detached_receiver();
+        self.graph_node(from_ui)
+    }
+
+    fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+    }
+
+    fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        AudioResult::not_found()
+    }
+}
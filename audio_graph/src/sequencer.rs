@@ -0,0 +1,680 @@
+use {
+    std::path::Path,
+    std::sync::{Arc, atomic::{AtomicU64, Ordering}},
+    crate::{
+        makepad_platform::*,
+        makepad_platform::thread::*,
+        makepad_platform::audio::*,
+        makepad_platform::midi::*,
+        register_audio_component,
+        audio_traits::*,
+        midi_file::*,
+        parameter::*,
+        preset::*,
+    },
+};
+
+// Plays the tracks of a MIDI file into its child components. Events are dispatched at
+// the exact frame they fall on by splitting the render of the children around them.
+// MIDI arriving at the sequencer is passed through to the children, and while recording
+// it is stamped with the song position and collected on the UI side.
+// Automation lanes play parameter curves into the children, parameters set through
+// the sequencer while recording are written into their lane.
+//
+// seq = <Sequencer> {
+//     source: dep("crate://self/songs/song.mid")
+//     looping: true, loop_start: 0.0, loop_end: 16.0
+//     routes: [{track: 1, to: bass}, {track: 2, to: drums}]
+//     bass = <Instrument> {...}
+//     drums = <SamplePlayer> {...}
+// }
+
+live_design!{
+    Sequencer = {{Sequencer}} {
+    }
+}
+
+// sends the midi events of a track to one child, tracks without a route go to every child
+#[derive(Clone, Debug, Live, LiveHook)]
+pub struct TrackRoute {
+    #[live] track: usize,
+    #[live] to: LiveId,
+}
+
+#[derive(Clone, Copy)]
+struct SequencerEvent {
+    seconds: f64,
+    // index into the children, None for all of them
+    target: Option<usize>,
+    data: MidiData,
+}
+
+struct ScheduledLane {
+    param: LiveId,
+    // breakpoints in seconds
+    points: Vec<(f64, f64)>,
+}
+
+impl ScheduledLane {
+    fn value_at(&self, seconds: f64) -> Option<f64> {
+        let first = self.points.first()?;
+        if seconds <= first.0 {
+            return Some(first.1)
+        }
+        let index = self.points.partition_point( | p | p.0 <= seconds);
+        if index >= self.points.len() {
+            return self.points.last().map( | p | p.1)
+        }
+        let (a, b) = (self.points[index - 1], self.points[index]);
+        Some(a.1 + (b.1 - a.1) * ((seconds - a.0) / (b.0 - a.0).max(f64::EPSILON)))
+    }
+
+    fn next_point(&self, seconds: f64) -> Option<f64> {
+        self.points.get(self.points.partition_point( | p | p.0 <= seconds)).map( | p | p.0)
+    }
+}
+
+struct Schedule {
+    tempo: TempoMap,
+    events: Vec<SequencerEvent>,
+    lanes: Vec<ScheduledLane>,
+    // loop range in seconds
+    looping: Option<(f64, f64)>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            tempo: TempoMap::new(MidiFileTiming::TicksPerQuarter(480), 120.0),
+            events: Vec::new(),
+            lanes: Vec::new(),
+            looping: None,
+        }
+    }
+}
+
+enum FromUI {
+    Schedule(Box<Schedule>),
+    Play,
+    Stop,
+    Seek(f64),
+    Record(bool),
+    // a touched parameter overrides its lane until recording stops
+    Param {change: ParamChange, touch: bool},
+}
+
+enum ToUI {
+    Recorded {tick: u64, data: MidiData},
+}
+
+#[derive(Live)]
+pub struct Sequencer {
+    #[live] source: LiveDependency,
+    #[live] path: String,
+    // the tempo until the song sets one
+    #[live(120.0)] bpm: f64,
+    #[live] routes: Vec<TrackRoute>,
+    #[live] looping: bool,
+    // loop range in quarter notes, a loop_end of 0 means the end of the song
+    #[live] loop_start: f64,
+    #[live] loop_end: f64,
+    #[live] autoplay: bool,
+
+    #[rust] song: MidiFile,
+    #[rust] loaded: Option<(String, String)>,
+    #[rust] recording: MidiFileTrack,
+    #[rust] lanes: Vec<AutomationLane>,
+    // last point written per parameter while recording
+    #[rust] recorded_params: Vec<(LiveId, AutomationPoint)>,
+    #[rust] is_recording: bool,
+    #[rust] is_playing: bool,
+    // song position in ticks as f64 bits, written by the audio thread
+    #[rust] position: Arc<AtomicU64>,
+    #[rust] child_order: Vec<LiveId>,
+    #[rust] children: ComponentMap<LiveId, AudioComponentRef>,
+    #[rust] has_graph_node: bool,
+    #[rust] from_ui: FromUISender<FromUI>,
+    #[rust] to_ui: ToUIReceiver<ToUI>,
+}
+
+impl LiveHook for Sequencer {
+    fn before_live_design(cx: &mut Cx) {
+        register_audio_component!(cx, Sequencer)
+    }
+
+    fn before_apply(&mut self, _cx: &mut Cx, from: ApplyFrom, _index: usize, _nodes: &[LiveNode]) {
+        if from.is_from_doc() {
+            self.child_order.clear();
+        }
+    }
+
+    fn apply_value_instance(&mut self, cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) -> usize {
+        if from.is_from_doc() {
+            self.child_order.push(nodes[index].id);
+        }
+        self.children.get_or_insert(cx, nodes[index].id, | cx | {AudioComponentRef::new(cx)})
+            .apply(cx, from, index, nodes)
+    }
+
+    fn after_apply(&mut self, cx: &mut Cx, from: ApplyFrom, _index: usize, _nodes: &[LiveNode]) {
+        if !from.is_from_doc() {
+            return
+        }
+        self.children.retain_visible();
+        let key = (self.source.as_str().to_string(), self.path.clone());
+        if self.loaded.as_ref() != Some(&key) {
+            self.load_song(cx);
+            self.loaded = Some(key);
+        }
+        if self.has_graph_node {
+            self.send_schedule();
+        }
+    }
+}
+
+impl Sequencer {
+    fn load_song(&mut self, cx: &mut Cx) {
+        let result = if self.source.as_str().len() > 0 {
+            match cx.get_dependency(self.source.as_str()) {
+                Ok(data) => MidiFile::parse(&data),
+                Err(err) => {
+                    error!("Sequencer cannot load {}: {}", self.source.as_str(), err);
+                    return
+                }
+            }
+        }
+        else if self.path.len() > 0 {
+            MidiFile::load(Path::new(&self.path))
+        }
+        else {
+            return
+        };
+        match result {
+            Ok(song) => self.song = song,
+            Err(err) => error!("Sequencer cannot parse {}{}: {}", self.source.as_str(), self.path, err)
+        }
+    }
+
+    fn build_schedule(&self) -> Schedule {
+        let tempo = self.song.tempo_map(self.bpm);
+        let mut events = Vec::new();
+        for (index, track) in self.song.tracks.iter().enumerate() {
+            let mut targets: Vec<Option<usize>> = self.routes.iter()
+                .filter( | route | route.track == index)
+                .filter_map( | route | self.child_order.iter().position( | id | *id == route.to))
+                .map(Some)
+                .collect();
+            if targets.is_empty() {
+                if self.routes.iter().any( | route | route.track == index) {
+                    // routed to children that don't exist
+                    continue
+                }
+                targets.push(None);
+            }
+            for event in &track.events {
+                if let MidiFileEvent::Midi(data) = event.event {
+                    let seconds = tempo.tick_to_seconds(event.tick as f64);
+                    for target in &targets {
+                        events.push(SequencerEvent {seconds, target: *target, data});
+                    }
+                }
+            }
+        }
+        events.sort_by( | a, b | a.seconds.partial_cmp(&b.seconds).unwrap_or(std::cmp::Ordering::Equal));
+        let looping = if self.looping {
+            let end_tick = if self.loop_end > 0.0 {
+                tempo.beats_to_ticks(self.loop_end)
+            }
+            else {
+                self.song.end_tick() as f64
+            };
+            let start = tempo.tick_to_seconds(tempo.beats_to_ticks(self.loop_start.max(0.0)));
+            let end = tempo.tick_to_seconds(end_tick);
+            if end > start {Some((start, end))} else {None}
+        }
+        else {
+            None
+        };
+        let lanes = self.lanes.iter().map( | lane | ScheduledLane {
+            param: lane.param,
+            points: lane.curve.points.iter().map( | p | (tempo.tick_to_seconds(tempo.beats_to_ticks(p.beat)), p.value)).collect()
+        }).collect();
+        Schedule {tempo, events, lanes, looping}
+    }
+
+    fn send_schedule(&mut self) {
+        let _ = self.from_ui.send(FromUI::Schedule(Box::new(self.build_schedule())));
+    }
+
+    pub fn song(&self) -> &MidiFile {
+        &self.song
+    }
+
+    pub fn set_song(&mut self, song: MidiFile) {
+        self.song = song;
+        if self.has_graph_node {
+            self.send_schedule();
+        }
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<(), MidiFileError> {
+        self.set_song(MidiFile::load(path)?);
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), MidiFileError> {
+        self.song.save(path)
+    }
+
+    pub fn play(&mut self) {
+        self.is_playing = true;
+        let _ = self.from_ui.send(FromUI::Play);
+    }
+
+    pub fn stop(&mut self) {
+        self.is_playing = false;
+        let _ = self.from_ui.send(FromUI::Stop);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    pub fn seek(&mut self, beats: f64) {
+        let ticks = self.song.tempo_map(self.bpm).beats_to_ticks(beats.max(0.0));
+        let _ = self.from_ui.send(FromUI::Seek(ticks));
+    }
+
+    // the song position in quarter notes
+    pub fn position(&self) -> f64 {
+        let ticks = f64::from_bits(self.position.load(Ordering::Relaxed));
+        self.song.tempo_map(self.bpm).ticks_to_beats(ticks)
+    }
+
+    // starts collecting the incoming midi, it is stamped with the song position while playing
+    pub fn record(&mut self, record: bool) {
+        self.is_recording = record;
+        let _ = self.from_ui.send(FromUI::Record(record));
+        if !record && self.recorded_params.len() > 0 {
+            let params = self.params();
+            for (id, _) in std::mem::take(&mut self.recorded_params) {
+                let range = params.iter().find( | info | info.id == id).map_or(1.0, | info | (info.max - info.min).abs());
+                if let Some(lane) = self.lanes.iter_mut().find( | lane | lane.param == id) {
+                    lane.curve.simplify(range * 0.001);
+                }
+            }
+            if self.has_graph_node {
+                self.send_schedule();
+            }
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.is_recording
+    }
+
+    pub fn take_recording(&mut self) -> MidiFileTrack {
+        std::mem::take(&mut self.recording)
+    }
+
+    pub fn lanes(&self) -> &[AutomationLane] {
+        &self.lanes
+    }
+
+    pub fn set_lanes(&mut self, lanes: Vec<AutomationLane>) {
+        self.lanes = lanes;
+        if self.has_graph_node {
+            self.send_schedule();
+        }
+    }
+
+    // sets a parameter of the children, while recording and playing it is written into its lane
+    pub fn set_param(&mut self, param: LiveId, value: f64) {
+        let touch = self.is_recording && self.is_playing;
+        let _ = self.from_ui.send(FromUI::Param {change: ParamChange::new(param, value), touch});
+        if !touch {
+            return
+        }
+        let beat = self.position();
+        let index = match self.lanes.iter().position( | lane | lane.param == param) {
+            Some(index) => index,
+            None => {
+                self.lanes.push(AutomationLane {param, curve: AutomationCurve::default()});
+                self.lanes.len() - 1
+            }
+        };
+        let curve = &mut self.lanes[index].curve;
+        // what the lane had between the previous point and now is overwritten
+        let previous = self.recorded_params.iter().position( | (id, _) | *id == param);
+        if let Some(previous) = previous {
+            let last = self.recorded_params[previous].1;
+            if beat >= last.beat {
+                curve.remove_range(last.beat, beat);
+                curve.insert(last.beat, last.value);
+            }
+            self.recorded_params[previous].1 = AutomationPoint {beat, value};
+        }
+        else {
+            self.recorded_params.push((param, AutomationPoint {beat, value}));
+        }
+        curve.insert(beat, value);
+    }
+
+    // appends what was recorded so far as a new track of the song
+    pub fn commit_recording(&mut self) -> Option<usize> {
+        let track = self.take_recording();
+        if track.events.is_empty() {
+            return None
+        }
+        self.song.tracks.push(track);
+        if self.has_graph_node {
+            self.send_schedule();
+        }
+        Some(self.song.tracks.len() - 1)
+    }
+}
+
+struct Node {
+    from_ui: FromUIReceiver<FromUI>,
+    to_ui: ToUISender<ToUI>,
+    children: Vec<Box<dyn AudioGraphNode + Send >>,
+    buffer: AudioBuffer,
+    schedule: Box<Schedule>,
+    playing: bool,
+    recording: bool,
+    // song position in seconds
+    time: f64,
+    // next event to dispatch
+    cursor: usize,
+    // notes started by the sequencer that haven't seen their note off yet
+    held: Vec<(Option<usize>, MidiNote)>,
+    // parameter changes for the current block, the children render it in pieces
+    pending_params: Vec<ParamChange>,
+    touched: Vec<LiveId>,
+    position: Arc<AtomicU64>,
+}
+
+impl Node {
+    fn send_to(&mut self, target: Option<usize>, data: MidiData) {
+        match target {
+            Some(index) => if let Some(child) = self.children.get_mut(index) {
+                child.handle_midi_data(data);
+            }
+            None => for child in &mut self.children {
+                child.handle_midi_data(data);
+            }
+        }
+    }
+
+    fn dispatch(&mut self, event: SequencerEvent) {
+        if let MidiEvent::Note(note) = event.data.decode() {
+            let key = (event.target, note.channel, note.note_number);
+            self.held.retain( | (target, held) | (*target, held.channel, held.note_number) != key);
+            if note.is_on && note.velocity > 0 {
+                self.held.push((event.target, note));
+            }
+        }
+        self.send_to(event.target, event.data);
+    }
+
+    fn release_held(&mut self) {
+        while let Some((target, note)) = self.held.pop() {
+            self.send_to(target, MidiNote {is_on: false, velocity: 0, ..note}.into());
+        }
+    }
+
+    fn seek(&mut self, seconds: f64) {
+        self.release_held();
+        self.time = seconds.max(0.0);
+        self.cursor = self.schedule.events.partition_point( | e | e.seconds < self.time);
+    }
+
+    fn send_params(&mut self, sample_rate: f64, start: usize, frames: usize, is_last: bool) {
+        let mut i = 0;
+        while i < self.pending_params.len() {
+            let change = self.pending_params[i];
+            if is_last || change.frame < start + frames {
+                self.pending_params.remove(i);
+                let change = ParamChange {frame: change.frame.saturating_sub(start), ..change};
+                for child in &mut self.children {
+                    child.set_param(change);
+                }
+            }
+            else {
+                i += 1;
+            }
+        }
+        if !self.playing {
+            return
+        }
+        // tempo synced effects follow the song
+        let bpm = self.schedule.tempo.bpm_at(self.schedule.tempo.seconds_to_tick(self.time));
+        for child in &mut self.children {
+            child.set_param(ParamChange::new(live_id!(bpm), bpm));
+        }
+        // automation glides to the value at the end of the piece, pieces end on breakpoints
+        let end = self.time + frames as f64 / sample_rate;
+        for lane in &self.schedule.lanes {
+            if self.touched.contains(&lane.param) {
+                continue
+            }
+            if let Some(value) = lane.value_at(end) {
+                let change = ParamChange::new(lane.param, value).with_ramp(frames);
+                for child in &mut self.children {
+                    child.set_param(change);
+                }
+            }
+        }
+    }
+
+    fn render_children(&mut self, info: AudioInfo, output: &mut AudioBuffer, start: usize, frames: usize, display: &mut DisplayAudioGraph) {
+        self.send_params(info.sample_rate, start, frames, start + frames >= output.frame_count());
+        self.buffer.resize(frames, output.channel_count());
+        for child in &mut self.children {
+            child.render_to_audio_buffer(info, &mut [&mut self.buffer], &[], display);
+            for c in 0..output.channel_count() {
+                let out_channel = &mut output.channel_mut(c)[start..start + frames];
+                for (o, i) in out_channel.iter_mut().zip(self.buffer.channel(c)) {
+                    *o += i;
+                }
+            }
+        }
+    }
+}
+
+impl AudioGraphNode for Node {
+    fn all_notes_off(&mut self) {
+        self.held.clear();
+        for child in &mut self.children {
+            child.all_notes_off();
+        }
+    }
+
+    fn set_param(&mut self, change: ParamChange) {
+        self.pending_params.push(change);
+    }
+
+    fn handle_midi_data(&mut self, data: MidiData) {
+        if self.recording {
+            // midi arrives ahead of the block it plays in, so it lands on the block start
+            let tick = self.schedule.tempo.seconds_to_tick(self.time).max(0.0).round() as u64;
+            let _ = self.to_ui.send(ToUI::Recorded {tick, data});
+        }
+        for child in &mut self.children {
+            child.handle_midi_data(data);
+        }
+    }
+
+    fn render_to_audio_buffer(
+        &mut self,
+        info: AudioInfo,
+        outputs: &mut [&mut AudioBuffer],
+        _inputs: &[&AudioBuffer],
+        display: &mut DisplayAudioGraph
+    ) {
+        while let Ok(msg) = self.from_ui.try_recv() {
+            match msg {
+                FromUI::Schedule(schedule) => {
+                    // keep the musical position when the tempo map changes
+                    let tick = self.schedule.tempo.seconds_to_tick(self.time);
+                    self.schedule = schedule;
+                    let seconds = self.schedule.tempo.tick_to_seconds(tick);
+                    self.seek(seconds);
+                }
+                FromUI::Play => self.playing = true,
+                FromUI::Stop => {
+                    self.playing = false;
+                    self.release_held();
+                }
+                FromUI::Seek(tick) => {
+                    let seconds = self.schedule.tempo.tick_to_seconds(tick);
+                    self.seek(seconds);
+                }
+                FromUI::Record(recording) => {
+                    self.recording = recording;
+                    if !recording {
+                        self.touched.clear();
+                    }
+                }
+                FromUI::Param {change, touch} => {
+                    if touch && !self.touched.contains(&change.id) {
+                        self.touched.push(change.id);
+                    }
+                    self.pending_params.push(change);
+                }
+            }
+        }
+
+        let output = &mut outputs[0];
+        output.zero();
+        let frame_count = output.frame_count();
+        let sample_rate = info.sample_rate;
+        let mut frame = 0;
+        while frame < frame_count {
+            let mut run = frame_count - frame;
+            if self.playing {
+                // everything that rounds to the current frame goes out before it renders
+                loop {
+                    if let Some((loop_start, loop_end)) = self.schedule.looping {
+                        let to_end = (loop_end - self.time) * sample_rate;
+                        if to_end <= 0.5 && to_end > -1.0 {
+                            self.seek(loop_start);
+                            continue
+                        }
+                    }
+                    if let Some(event) = self.schedule.events.get(self.cursor).copied() {
+                        if (event.seconds - self.time) * sample_rate <= 0.5 {
+                            self.cursor += 1;
+                            self.dispatch(event);
+                            continue
+                        }
+                    }
+                    break
+                }
+                let mut next = self.schedule.events.get(self.cursor).map_or(f64::MAX, | e | e.seconds);
+                if let Some((_, loop_end)) = self.schedule.looping {
+                    if loop_end > self.time {
+                        next = next.min(loop_end);
+                    }
+                }
+                for lane in &self.schedule.lanes {
+                    if let Some(point) = lane.next_point(self.time) {
+                        next = next.min(point);
+                    }
+                }
+                let until = ((next - self.time) * sample_rate).round().max(1.0);
+                if until < run as f64 {
+                    run = until as usize;
+                }
+            }
+            self.render_children(info, output, frame, run, display);
+            if self.playing {
+                self.time += run as f64 / sample_rate;
+            }
+            frame += run;
+        }
+        let tick = self.schedule.tempo.seconds_to_tick(self.time);
+        self.position.store(tick.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Sequencer {
+    fn graph_node(&mut self, cx: &mut Cx, offline: bool) -> Box<dyn AudioGraphNode + Send> {
+        let mut children = Vec::new();
+        for id in &self.child_order {
+            if let Some(child) = self.children.get_mut(id).and_then( | child | child.as_mut()) {
+                children.push(if offline {child.get_offline_graph_node(cx)} else {child.get_graph_node(cx)});
+            }
+        }
+        // an offline node doesn't record, and keeps its position and messages to itself
+        let (from_ui, to_ui, position) = if offline {
+            (self.from_ui.detached_receiver(), ToUIReceiver::default().sender(), Arc::new(AtomicU64::new(0)))
+        }
+        else {
+            self.from_ui.new_channel();
+            self.has_graph_node = true;
+            (self.from_ui.receiver(), self.to_ui.sender(), self.position.clone())
+        };
+        Box::new(Node {
+            from_ui,
+            to_ui,
+            children,
+            buffer: AudioBuffer::default(),
+            schedule: Box::new(self.build_schedule()),
+            playing: self.is_playing || self.autoplay,
+            recording: self.is_recording && !offline,
+            time: 0.0,
+            cursor: 0,
+            held: Vec::new(),
+            pending_params: Vec::new(),
+            touched: Vec::new(),
+            position,
+        })
+    }
+}
+
+impl AudioComponent for Sequencer {
+    fn get_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        let node = self.graph_node(cx, false);
+        self.is_playing = self.is_playing || self.autoplay;
+        node
+    }
+
+    fn get_offline_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.graph_node(cx, true)
+    }
+
+    fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
+        while let Ok(msg) = self.to_ui.try_recv() {
+            match msg {
+                ToUI::Recorded {tick, data} => {
+                    self.recording.push(tick, MidiFileEvent::Midi(data));
+                }
+            }
+        }
+        for child in self.children.values_mut() {
+            if let Some(child) = child.as_mut() {
+                child.handle_event_with(cx, event, dispatch_action)
+            }
+        }
+    }
+
+    fn audio_query(&mut self, query: &AudioQuery, callback: &mut Option<AudioQueryCb>) -> AudioResult {
+        for child in self.children.values_mut() {
+            child.audio_query(query, callback) ?;
+        }
+        AudioResult::not_found()
+    }
+
+    fn params(&self) -> Vec<ParamInfo> {
+        self.child_order.iter().filter_map( | id | self.children.get(id)).flat_map( | child | child.params()).collect()
+    }
+
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        preset_read_children(id, self.child_order.iter().filter_map( | id | self.children.get(id).map( | child | (*id, child))), out);
+    }
+    
+    fn preset_apply(&mut self, cx: &mut Cx, nodes: &[LiveNode]) {
+        preset_apply_children(cx, nodes, &mut self.children);
+    }
+}
@@ -78,6 +78,10 @@ impl AudioComponent for BasicSynth {
         Box::new(Node::default())
     }
     
+    fn get_offline_graph_node(&mut self, _cx:&mut Cx) -> Box<dyn AudioGraphNode + Send>{
+        Box::new(Node::default())
+    }
+    
     fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)){
     }
     // we dont have inputs
@@ -0,0 +1,442 @@
+use {
+    std::fmt::Write,
+    crate::{
+        cx_2d::Cx2d,
+        nav::{CxNavTree, CxNavTreeRc, NavItem, NavRole},
+        makepad_platform::*,
+    }
+};
+
+// The accessibility tree is derived from the nav tree each frame. Widgets describe themselves
+// by attaching an AccessInfo to their nav stop, the structure (draw lists, scroll areas) comes for free.
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessState {
+    pub focused: bool,
+    pub checked: Option<bool>,
+    pub selected: bool,
+    pub expanded: Option<bool>,
+    pub disabled: bool,
+    pub read_only: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessInfo {
+    pub name: String,
+    pub value: Option<String>,
+    pub state: AccessState,
+}
+
+impl AccessInfo {
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_value(mut self, value: &str) -> Self {
+        self.value = Some(value.to_string());
+        self
+    }
+
+    pub fn with_checked(mut self, checked: bool) -> Self {
+        self.state.checked = Some(checked);
+        self
+    }
+
+    pub fn with_selected(mut self, selected: bool) -> Self {
+        self.state.selected = selected;
+        self
+    }
+
+    pub fn with_expanded(mut self, expanded: bool) -> Self {
+        self.state.expanded = Some(expanded);
+        self
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.state.read_only = read_only;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AccessRole {
+    Window,
+    Group,
+    ScrollArea,
+    Widget(NavRole),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AccessNodeId(pub usize);
+
+#[derive(Clone, Debug)]
+pub struct AccessNode {
+    pub role: AccessRole,
+    pub info: AccessInfo,
+    pub rect: Rect,
+    pub area: Area,
+    pub parent: Option<AccessNodeId>,
+    pub children: Vec<AccessNodeId>,
+}
+
+// Actions an assistive technology can send back into the app.
+// They are delivered as key focus changes and triggers on the area of the nav stop,
+// widgets pick up the triggers with access_action_hit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AccessAction {
+    Focus,
+    Activate,
+    Increment,
+    Decrement,
+}
+
+impl AccessAction {
+    pub fn trigger_id(&self) -> LiveId {
+        match self {
+            Self::Focus => live_id!(access_focus),
+            Self::Activate => live_id!(access_activate),
+            Self::Increment => live_id!(access_increment),
+            Self::Decrement => live_id!(access_decrement),
+        }
+    }
+
+    pub fn from_trigger_id(id: LiveId) -> Option<Self> {
+        [Self::Focus, Self::Activate, Self::Increment, Self::Decrement].into_iter().find( | action | action.trigger_id() == id)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AccessTree {
+    pub nodes: Vec<AccessNode>,
+}
+
+impl AccessTree {
+    pub fn root(&self) -> Option<&AccessNode> {
+        self.nodes.first()
+    }
+
+    pub fn node(&self, id: AccessNodeId) -> &AccessNode {
+        &self.nodes[id.0]
+    }
+
+    pub fn find<F>(&self, mut filter: F) -> Option<AccessNodeId> where F: FnMut(&AccessNode) -> bool {
+        self.nodes.iter().position( | node | filter(node)).map( | index | AccessNodeId(index))
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<AccessNodeId> {
+        self.find( | node | node.info.name == name)
+    }
+
+    pub fn focused(&self) -> Option<AccessNodeId> {
+        self.find( | node | node.info.state.focused)
+    }
+
+    fn push_node(&mut self, parent: Option<AccessNodeId>, role: AccessRole, info: AccessInfo, area: Area, rect: Rect) -> AccessNodeId {
+        let id = AccessNodeId(self.nodes.len());
+        self.nodes.push(AccessNode {
+            role,
+            info,
+            rect,
+            area,
+            parent,
+            children: Vec::new()
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+        id
+    }
+
+    // removes groups without any widgets in them, they are just drawlists nobody cares about
+    fn prune_empty_groups(&mut self) {
+        fn has_widgets(tree: &AccessTree, id: AccessNodeId) -> bool {
+            let node = tree.node(id);
+            if let AccessRole::Widget(_) = node.role {
+                return true
+            }
+            node.children.iter().any( | child | has_widgets(tree, *child))
+        }
+        let keep: Vec<bool> = (0..self.nodes.len()).map( | i | {
+            i == 0 || has_widgets(self, AccessNodeId(i))
+        }).collect();
+        let mut remap = Vec::with_capacity(self.nodes.len());
+        let mut new_index = 0;
+        for k in &keep {
+            remap.push(new_index);
+            if *k {new_index += 1}
+        }
+        let old_nodes = std::mem::take(&mut self.nodes);
+        for (i, mut node) in old_nodes.into_iter().enumerate() {
+            if !keep[i] {
+                continue
+            }
+            node.parent = node.parent.map( | p | AccessNodeId(remap[p.0]));
+            node.children = node.children.iter().filter( | c | keep[c.0]).map( | c | AccessNodeId(remap[c.0])).collect();
+            self.nodes.push(node);
+        }
+    }
+
+    // a stable textual form of the tree, handy to assert on in tests
+    pub fn dump(&self) -> String {
+        fn dump_node(tree: &AccessTree, id: AccessNodeId, depth: usize, out: &mut String) {
+            let node = tree.node(id);
+            let _ = write!(out, "{}{:?}", "  ".repeat(depth), node.role);
+            if !node.info.name.is_empty() {
+                let _ = write!(out, " {:?}", node.info.name);
+            }
+            if let Some(value) = &node.info.value {
+                let _ = write!(out, " value={:?}", value);
+            }
+            let state = &node.info.state;
+            if state.focused {out.push_str(" focused")}
+            if let Some(checked) = state.checked {let _ = write!(out, " checked={}", checked);}
+            if state.selected {out.push_str(" selected")}
+            if let Some(expanded) = state.expanded {let _ = write!(out, " expanded={}", expanded);}
+            if state.disabled {out.push_str(" disabled")}
+            if state.read_only {out.push_str(" read_only")}
+            out.push('\n');
+            for child in &node.children {
+                dump_node(tree, *child, depth + 1, out);
+            }
+        }
+        let mut out = String::new();
+        if self.nodes.len()>0 {
+            dump_node(self, AccessNodeId(0), 0, &mut out);
+        }
+        out
+    }
+}
+
+impl<'a> Cx2d<'a> {
+
+    pub fn build_access_tree(cx: &mut Cx, root: DrawListId, name: &str) -> AccessTree {
+        let mut tree = AccessTree::default();
+        if !cx.has_global::<CxNavTreeRc>() {
+            return tree
+        }
+        let nav_tree_rc = cx.get_global::<CxNavTreeRc>().clone();
+        let nav_tree = &*nav_tree_rc.0.borrow();
+
+        fn build(cx: &Cx, tree: &mut AccessTree, nav_tree: &CxNavTree, draw_list_id: DrawListId, parent: AccessNodeId) {
+            if draw_list_id.index() >= nav_tree.len() {
+                return
+            }
+            let mut scroll_stack = vec![parent];
+            for nav_item in &nav_tree[draw_list_id].nav_list {
+                let parent = *scroll_stack.last().unwrap();
+                match nav_item {
+                    NavItem::Child(draw_list_id) => {
+                        let group = tree.push_node(Some(parent), AccessRole::Group, AccessInfo::default(), Area::Empty, Rect::default());
+                        build(cx, tree, nav_tree, *draw_list_id, group);
+                    }
+                    NavItem::Stop(stop) => {
+                        let mut info = stop.access.clone();
+                        info.state.focused = cx.has_key_focus(stop.area);
+                        tree.push_node(Some(parent), AccessRole::Widget(stop.role.clone()), info, stop.area, stop.area.get_rect(cx));
+                    }
+                    NavItem::BeginScroll(area) => {
+                        let scroll = tree.push_node(Some(parent), AccessRole::ScrollArea, AccessInfo::default(), *area, area.get_rect(cx));
+                        scroll_stack.push(scroll);
+                    }
+                    NavItem::EndScroll(_) => {
+                        if scroll_stack.len() > 1 {
+                            scroll_stack.pop();
+                        }
+                    }
+                }
+            }
+        }
+        let root_id = tree.push_node(None, AccessRole::Window, AccessInfo::named(name), Area::Empty, Rect::default());
+        build(cx, &mut tree, nav_tree, root, root_id);
+        tree.prune_empty_groups();
+
+        // groups get the bounds of their content
+        for i in (0..tree.nodes.len()).rev() {
+            if tree.nodes[i].area.is_empty() && tree.nodes[i].children.len() > 0 {
+                let mut min = dvec2(f64::INFINITY, f64::INFINITY);
+                let mut max = dvec2(f64::NEG_INFINITY, f64::NEG_INFINITY);
+                for child in &tree.nodes[i].children {
+                    let rect = tree.nodes[child.0].rect;
+                    min.x = min.x.min(rect.pos.x);
+                    min.y = min.y.min(rect.pos.y);
+                    max.x = max.x.max(rect.pos.x + rect.size.x);
+                    max.y = max.y.max(rect.pos.y + rect.size.y);
+                }
+                tree.nodes[i].rect = Rect {pos: min, size: max - min};
+            }
+        }
+        tree
+    }
+
+    pub fn dispatch_access_action(cx: &mut Cx, tree: &AccessTree, id: AccessNodeId, action: AccessAction) {
+        let node = tree.node(id);
+        if node.area.is_empty() {
+            return
+        }
+        // make sure scrollviews bring the target into view, same as tab navigation
+        let mut scroll_parent = node.parent;
+        let mut prev_area = node.area;
+        while let Some(parent) = scroll_parent {
+            let parent = tree.node(parent);
+            if parent.role == AccessRole::ScrollArea {
+                cx.send_trigger(parent.area, Trigger {
+                    id: live_id!(scroll_focus_nav),
+                    from: prev_area
+                });
+                prev_area = parent.area;
+            }
+            scroll_parent = parent.parent;
+        }
+        if let AccessAction::Focus = action {
+            cx.set_key_focus(node.area);
+        }
+        cx.send_trigger(node.area, Trigger {
+            id: action.trigger_id(),
+            from: Area::Empty
+        });
+    }
+
+    pub fn access_action_hit(event: &Event, area: Area) -> Option<AccessAction> {
+        if let Event::Trigger(te) = event {
+            if let Some(triggers) = te.triggers.get(&area) {
+                return triggers.iter().find_map( | t | AccessAction::from_trigger_id(t.id))
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{rc::Rc, cell::RefCell},
+        super::*,
+        crate::nav::{NavOrder, NavStop},
+    };
+    
+    fn rect_area(cx: &mut Cx, draw_list_id: DrawListId, rect: Rect) -> Area {
+        let draw_list = &mut cx.draw_lists[draw_list_id];
+        draw_list.rect_areas.push(CxRectArea {rect, draw_clip: Default::default()});
+        Area::Rect(RectArea {
+            draw_list_id,
+            rect_id: draw_list.rect_areas.len() - 1,
+            redraw_id: 0
+        })
+    }
+    
+    fn stop(area: Area, role: NavRole, access: AccessInfo) -> NavItem {
+        NavItem::Stop(NavStop {role, order: NavOrder::Default, margin: Margin::default(), area, access})
+    }
+    
+    struct TestUi {
+        _draw_lists: Vec<DrawList>,
+        root: DrawListId,
+        button: Area,
+        scroll: Area,
+        check_box: Area,
+    }
+    
+    // a window with a button, a scroll area with a checkbox in a child draw list,
+    // and a child draw list without any nav stops
+    fn build_test_ui(cx: &mut Cx) -> TestUi {
+        let draw_lists = vec![cx.draw_lists.alloc(), cx.draw_lists.alloc(), cx.draw_lists.alloc()];
+        let (root, child, empty) = (draw_lists[0].id(), draw_lists[1].id(), draw_lists[2].id());
+        let button = rect_area(cx, root, Rect {pos: dvec2(10.0, 10.0), size: dvec2(80.0, 20.0)});
+        let scroll = rect_area(cx, root, Rect {pos: dvec2(0.0, 40.0), size: dvec2(200.0, 100.0)});
+        let check_box = rect_area(cx, child, Rect {pos: dvec2(10.0, 50.0), size: dvec2(20.0, 20.0)});
+        
+        Cx2d::lazy_construct_nav_tree(cx);
+        let nav_tree_rc = cx.get_global::<CxNavTreeRc>().clone();
+        let mut nav_tree = nav_tree_rc.0.borrow_mut();
+        for draw_list_id in [root, child, empty] {
+            nav_tree.clear_list(draw_list_id);
+        }
+        nav_tree.push_item(root, stop(button, NavRole::Button, AccessInfo::named("Ok")));
+        nav_tree.push_item(root, NavItem::BeginScroll(scroll));
+        nav_tree.push_item(root, NavItem::Child(child));
+        nav_tree.push_item(child, stop(check_box, NavRole::CheckBox, AccessInfo::named("Wrap").with_checked(true)));
+        nav_tree.push_item(root, NavItem::EndScroll(scroll));
+        nav_tree.push_item(root, NavItem::Child(empty));
+        
+        TestUi {_draw_lists: draw_lists, root, button, scroll, check_box}
+    }
+    
+    #[test]
+    fn builds_tree_from_nav_stops() {
+        let mut cx = Cx::new(Box::new( | _, _ | {}));
+        let ui = build_test_ui(&mut cx);
+        
+        // key focus shows up in the tree once it has moved
+        let tree = Cx2d::build_access_tree(&mut cx, ui.root, "Test");
+        assert_eq!(tree.focused(), None);
+        Cx2d::dispatch_access_action(&mut cx, &tree, tree.find_by_name("Ok").unwrap(), AccessAction::Focus);
+        cx.handle_triggers();
+        
+        let tree = Cx2d::build_access_tree(&mut cx, ui.root, "Test");
+        assert_eq!(tree.dump(), concat!(
+            "Window \"Test\"\n",
+            "  Widget(Button) \"Ok\" focused\n",
+            "  ScrollArea\n",
+            "    Group\n",
+            "      Widget(CheckBox) \"Wrap\" checked=true\n",
+        ));
+        assert_eq!(tree.focused(), tree.find_by_name("Ok"));
+        
+        // groups get the bounds of what is in them
+        let check_box = tree.find_by_name("Wrap").unwrap();
+        let group = tree.node(check_box).parent.unwrap();
+        assert_eq!(tree.node(group).rect, Rect {pos: dvec2(10.0, 50.0), size: dvec2(20.0, 20.0)});
+    }
+    
+    #[test]
+    fn dispatches_actions_to_widgets() {
+        // every trigger that arrives, and the access actions widgets would see in them
+        let triggers = Rc::new(RefCell::new(Vec::new()));
+        let actions = Rc::new(RefCell::new(Vec::new()));
+        let mut cx = Cx::new(Box::new({
+            let (triggers, actions) = (triggers.clone(), actions.clone());
+            move | _, event | if let Event::Trigger(te) = event {
+                for (area, area_triggers) in &te.triggers {
+                    triggers.borrow_mut().extend(area_triggers.iter().map( | t | (*area, t.id)));
+                    if let Some(action) = Cx2d::access_action_hit(event, *area) {
+                        actions.borrow_mut().push((*area, action));
+                    }
+                }
+            }
+        }));
+        let ui = build_test_ui(&mut cx);
+        let tree = Cx2d::build_access_tree(&mut cx, ui.root, "Test");
+        
+        // focusing the checkbox moves key focus and asks the scroll area to bring it into view
+        Cx2d::dispatch_access_action(&mut cx, &tree, tree.find_by_name("Wrap").unwrap(), AccessAction::Focus);
+        cx.handle_triggers();
+        assert!(cx.has_key_focus(ui.check_box));
+        assert_eq!(*actions.borrow(), vec![(ui.check_box, AccessAction::Focus)]);
+        assert!(triggers.borrow().contains(&(ui.scroll, live_id!(scroll_focus_nav))));
+        
+        actions.borrow_mut().clear();
+        triggers.borrow_mut().clear();
+        Cx2d::dispatch_access_action(&mut cx, &tree, tree.find_by_name("Ok").unwrap(), AccessAction::Activate);
+        cx.handle_triggers();
+        assert_eq!(*actions.borrow(), vec![(ui.button, AccessAction::Activate)]);
+        assert_eq!(*triggers.borrow(), vec![(ui.button, live_id!(access_activate))]);
+        assert!(cx.has_key_focus(ui.check_box));
+        
+        // nodes without an area of their own, like the window, ignore actions
+        actions.borrow_mut().clear();
+        Cx2d::dispatch_access_action(&mut cx, &tree, AccessNodeId(0), AccessAction::Activate);
+        cx.handle_triggers();
+        assert!(actions.borrow().is_empty());
+    }
+    
+    #[test]
+    fn access_actions_round_trip_trigger_ids() {
+        for action in [AccessAction::Focus, AccessAction::Activate, AccessAction::Increment, AccessAction::Decrement] {
+            assert_eq!(AccessAction::from_trigger_id(action.trigger_id()), Some(action));
+        }
+        assert_eq!(AccessAction::from_trigger_id(live_id!(scroll_focus_nav)), None);
+    }
+}
@@ -0,0 +1,345 @@
+// Colour glyphs such as emoji, rasterized on the cpu into their own BGRA atlas. Layers come
+// from COLR and CPAL, bitmaps from CBDT and sbix.
+
+use {
+    std::collections::HashMap,
+    crate::{
+        makepad_platform::*,
+        font_atlas::GlyphImage,
+        owned_font_face::OwnedFace,
+        makepad_vector::font::TTFFont,
+        makepad_vector::geometry::{AffineTransformation, Point, Rectangle, Transform, Trapezoid, Vector},
+        makepad_vector::internal_iter::ExtendFromInternalIterator,
+        makepad_vector::path::PathIterator,
+        makepad_vector::trapezoidator::Trapezoidator,
+    },
+    makepad_zune_png::PngDecoder,
+    rustybuzz::ttf_parser::{Face, GlyphId, RasterImageFormat, Tag},
+};
+
+// glyphs are never rasterized larger than this, bigger text scales the atlas image up
+const MAX_COLOR_GLYPH_SIZE: f64 = 256.0;
+
+// layers in the text colour are cached with the glyph, so they are drawn in this colour
+const FOREGROUND_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+pub struct ColorLayer {
+    pub glyph_id: usize,
+    // straight alpha rgba, None for the text colour
+    pub color: Option<[f32; 4]>,
+}
+
+pub struct CxColorFont {
+    pub layers: HashMap<usize, Vec<ColorLayer>>,
+    pub has_bitmaps: bool,
+}
+
+impl CxColorFont {
+    pub fn parse(face: &Face<'_>) -> Option<Self> {
+        let colr = face.raw_face().table(Tag::from_bytes(b"COLR"));
+        let cpal = face.raw_face().table(Tag::from_bytes(b"CPAL"));
+        let layers = match (colr, cpal) {
+            (Some(colr), Some(cpal)) => parse_colr(colr, cpal).unwrap_or_default(),
+            _ => HashMap::new()
+        };
+        let has_bitmaps = face.tables().cbdt.is_some() || face.tables().sbix.is_some();
+        if layers.is_empty() && !has_bitmaps {
+            return None
+        }
+        Some(Self {
+            layers,
+            has_bitmaps
+        })
+    }
+    
+    pub fn rasterize(
+        &self,
+        ttf_font: &mut TTFFont,
+        owned_font_face: &OwnedFace,
+        trapezoidator: &mut Trapezoidator,
+        glyph_id: usize,
+        pixels_per_em: f64
+    ) -> Option<GlyphImage> {
+        if let Some(layers) = self.layers.get(&glyph_id) {
+            return rasterize_layers(layers, ttf_font, owned_font_face, trapezoidator, pixels_per_em / ttf_font.units_per_em)
+        }
+        if self.has_bitmaps {
+            return owned_font_face.with_ref( | face | rasterize_bitmap(face, glyph_id, pixels_per_em))
+        }
+        None
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+// reads the version 0 base glyph list, which version 1 tables keep for older renderers, with
+// the colours of the first palette
+fn parse_colr(colr: &[u8], cpal: &[u8]) -> Option<HashMap<usize, Vec<ColorLayer>>> {
+    let num_base_glyphs = read_u16(colr, 2)? as usize;
+    let base_glyphs_offset = read_u32(colr, 4)? as usize;
+    let layers_offset = read_u32(colr, 8)? as usize;
+    let num_layers = read_u16(colr, 12)? as usize;
+    
+    let num_palette_entries = read_u16(cpal, 2)? as usize;
+    let color_records_offset = read_u32(cpal, 8)? as usize;
+    let first_color_index = read_u16(cpal, 12)? as usize;
+    
+    let mut out = HashMap::new();
+    for i in 0..num_base_glyphs {
+        let record = base_glyphs_offset + i * 6;
+        let glyph_id = read_u16(colr, record)? as usize;
+        let first_layer = read_u16(colr, record + 2)? as usize;
+        let layer_count = read_u16(colr, record + 4)? as usize;
+        let mut layers = Vec::with_capacity(layer_count);
+        for layer in first_layer..(first_layer + layer_count).min(num_layers) {
+            let record = layers_offset + layer * 4;
+            let palette_index = read_u16(colr, record + 2)?;
+            let color = if palette_index == 0xffff || palette_index as usize >= num_palette_entries {
+                None
+            }
+            else {
+                let offset = color_records_offset + (first_color_index + palette_index as usize) * 4;
+                let bgra = cpal.get(offset..offset + 4)?;
+                Some([bgra[2] as f32 / 255.0, bgra[1] as f32 / 255.0, bgra[0] as f32 / 255.0, bgra[3] as f32 / 255.0])
+            };
+            layers.push(ColorLayer {
+                glyph_id: read_u16(colr, record)? as usize,
+                color
+            });
+        }
+        out.insert(glyph_id, layers);
+    }
+    Some(out)
+}
+
+fn rasterize_layers(
+    layers: &[ColorLayer],
+    ttf_font: &mut TTFFont,
+    owned_font_face: &OwnedFace,
+    trapezoidator: &mut Trapezoidator,
+    scale: f64
+) -> Option<GlyphImage> {
+    let mut bounds: Option<Rectangle> = None;
+    for layer in layers {
+        let glyph = owned_font_face.with_ref( | face | ttf_font.get_glyph_by_id(face, layer.glyph_id)).ok() ?;
+        if glyph.outline.is_empty() {
+            continue;
+        }
+        bounds = Some(match bounds {
+            Some(bounds) => Rectangle::new(
+                Point::new(bounds.p_min.x.min(glyph.bounds.p_min.x), bounds.p_min.y.min(glyph.bounds.p_min.y)),
+                Point::new(bounds.p_max.x.max(glyph.bounds.p_max.x), bounds.p_max.y.max(glyph.bounds.p_max.y)),
+            ),
+            None => glyph.bounds
+        });
+    }
+    let bounds = bounds?;
+    let x0 = (bounds.p_min.x * scale).floor();
+    let y0 = (bounds.p_min.y * scale).floor();
+    let x1 = (bounds.p_max.x * scale).ceil().min(x0 + MAX_COLOR_GLYPH_SIZE);
+    let y1 = (bounds.p_max.y * scale).ceil().min(y0 + MAX_COLOR_GLYPH_SIZE);
+    let width = (x1 - x0) as usize;
+    let height = (y1 - y0) as usize;
+    if width == 0 || height == 0 {
+        return None
+    }
+    // font units to pixels, flipped so the top row comes first
+    let transform = AffineTransformation::scaling(Vector::new(scale, -scale)).translate(Vector::new(-x0, y1));
+    
+    let mut premultiplied = vec![[0.0f32; 4]; width * height];
+    let mut coverage = vec![0.0f32; width * height];
+    let mut trapezoids = Vec::new();
+    for layer in layers {
+        let glyph = owned_font_face.with_ref( | face | ttf_font.get_glyph_by_id(face, layer.glyph_id)).ok() ?;
+        coverage.fill(0.0);
+        trapezoids.clear();
+        if let Some(trapezoidate) = trapezoidator.trapezoidate(
+            glyph.outline.iter().map( | command | command.transform(&transform)).linearize(0.25)
+        ) {
+            trapezoids.extend_from_internal_iter(trapezoidate);
+        }
+        for trapezoid in &trapezoids {
+            add_trapezoid_coverage(trapezoid, &mut coverage, width, height);
+        }
+        let color = layer.color.unwrap_or(FOREGROUND_COLOR);
+        for (pixel, coverage) in premultiplied.iter_mut().zip(coverage.iter()) {
+            let alpha = color[3] * coverage.min(1.0);
+            for c in 0..3 {
+                pixel[c] = color[c] * alpha + pixel[c] * (1.0 - alpha);
+            }
+            pixel[3] = alpha + pixel[3] * (1.0 - alpha);
+        }
+    }
+    let data = premultiplied.iter().map( | pixel | {
+        if pixel[3] <= 0.0 {
+            return 0
+        }
+        let channel = | value: f32 | ((value / pixel[3]).min(1.0) * 255.0 + 0.5) as u32;
+        ((pixel[3] * 255.0 + 0.5) as u32) << 24 | channel(pixel[0]) << 16 | channel(pixel[1]) << 8 | channel(pixel[2])
+    }).collect();
+    Some(GlyphImage {
+        width,
+        height,
+        x: x0,
+        y: y0,
+        data
+    })
+}
+
+// the area of the part of a pixel below the line from p0 to p1, the cpu version of the
+// trapezoid shader in draw_trapezoid.rs
+fn clamped_right_trapezoid_area(mut p0: Vec2, mut p1: Vec2, p_min: Vec2, p_max: Vec2) -> f32 {
+    fn intersect_vertical(p0: Vec2, p1: Vec2, x: f32) -> Vec2 {
+        vec2(x, p0.y + (p1.y - p0.y) * (x - p0.x) / (p1.x - p0.x))
+    }
+    fn intersect_horizontal(p0: Vec2, p1: Vec2, y: f32) -> Vec2 {
+        vec2(p0.x + (p1.x - p0.x) * (y - p0.y) / (p1.y - p0.y), y)
+    }
+    fn clamp(p: Vec2, p_min: Vec2, p_max: Vec2) -> Vec2 {
+        vec2(p.x.max(p_min.x).min(p_max.x), p.y.max(p_min.y).min(p_max.y))
+    }
+    let x0 = p0.x.max(p_min.x).min(p_max.x);
+    let x1 = p1.x.max(p_min.x).min(p_max.x);
+    if p0.x < p_min.x && p_min.x < p1.x {
+        p0 = intersect_vertical(p0, p1, p_min.x);
+    }
+    if p0.x < p_max.x && p_max.x < p1.x {
+        p1 = intersect_vertical(p0, p1, p_max.x);
+    }
+    if p0.y < p_min.y && p_min.y < p1.y {
+        p0 = intersect_horizontal(p0, p1, p_min.y);
+    }
+    if p1.y < p_min.y && p_min.y < p0.y {
+        p1 = intersect_horizontal(p1, p0, p_min.y);
+    }
+    if p0.y < p_max.y && p_max.y < p1.y {
+        p1 = intersect_horizontal(p0, p1, p_max.y);
+    }
+    if p1.y < p_max.y && p_max.y < p0.y {
+        p0 = intersect_horizontal(p1, p0, p_max.y);
+    }
+    p0 = clamp(p0, p_min, p_max);
+    p1 = clamp(p1, p_min, p_max);
+    let h0 = p_max.y - p0.y;
+    let h1 = p_max.y - p1.y;
+    (p0.x - x0) * h0 + (p1.x - p0.x) * (h0 + h1) * 0.5 + (x1 - p1.x) * h1
+}
+
+fn add_trapezoid_coverage(trapezoid: &Trapezoid, coverage: &mut [f32], width: usize, height: usize) {
+    let p0 = vec2(trapezoid.xs[0], trapezoid.ys[0]);
+    let p1 = vec2(trapezoid.xs[1], trapezoid.ys[1]);
+    let p2 = vec2(trapezoid.xs[0], trapezoid.ys[2]);
+    let p3 = vec2(trapezoid.xs[1], trapezoid.ys[3]);
+    let x_start = trapezoid.xs[0].floor().max(0.0) as usize;
+    let x_end = (trapezoid.xs[1].ceil().max(0.0) as usize).min(width);
+    let y_start = trapezoid.ys[0].min(trapezoid.ys[1]).floor().max(0.0) as usize;
+    let y_end = (trapezoid.ys[2].max(trapezoid.ys[3]).ceil().max(0.0) as usize).min(height);
+    for y in y_start..y_end {
+        for x in x_start..x_end {
+            let p_min = vec2(x as f32, y as f32);
+            let p_max = vec2(x as f32 + 1.0, y as f32 + 1.0);
+            let area = clamped_right_trapezoid_area(p0, p1, p_min, p_max) - clamped_right_trapezoid_area(p2, p3, p_min, p_max);
+            coverage[y * width + x] += area;
+        }
+    }
+}
+
+fn rasterize_bitmap(face: &Face<'_>, glyph_id: usize, pixels_per_em: f64) -> Option<GlyphImage> {
+    let strike_size = pixels_per_em.round().max(1.0).min(u16::MAX as f64) as u16;
+    let image = face.glyph_raster_image(GlyphId(glyph_id as u16), strike_size) ?;
+    let (src_width, src_height, src) = match image.format {
+        RasterImageFormat::PNG => decode_png(image.data) ?,
+        RasterImageFormat::BitmapPremulBgra32 => {
+            let (width, height) = (image.width as usize, image.height as usize);
+            let mut data = Vec::with_capacity(width * height);
+            for bgra in image.data.chunks_exact(4).take(width * height) {
+                let alpha = bgra[3] as f32;
+                let channel = | value: u8 | if alpha > 0.0 {(value as f32 * 255.0 / alpha).min(255.0) as u32} else {0};
+                data.push((bgra[3] as u32) << 24 | channel(bgra[2]) << 16 | channel(bgra[1]) << 8 | channel(bgra[0]));
+            }
+            if data.len() != width * height {
+                return None
+            }
+            (width, height, data)
+        }
+        // monochrome and grayscale bitmaps are an outline font's job
+        _ => return None
+    };
+    let scale = (pixels_per_em / image.pixels_per_em as f64).min(MAX_COLOR_GLYPH_SIZE / src_width.max(src_height) as f64);
+    let width = ((src_width as f64 * scale).ceil() as usize).max(1);
+    let height = ((src_height as f64 * scale).ceil() as usize).max(1);
+    Some(GlyphImage {
+        width,
+        height,
+        x: image.x as f64 * scale,
+        y: image.y as f64 * scale,
+        data: resample(&src, src_width, src_height, width, height)
+    })
+}
+
+fn decode_png(data: &[u8]) -> Option<(usize, usize, Vec<u32>)> {
+    let mut decoder = PngDecoder::new(data);
+    let image = decoder.decode().ok() ?;
+    let bytes = image.u8() ?;
+    let (width, height) = decoder.get_dimensions() ?;
+    let pixels = width * height;
+    if pixels == 0 {
+        return None
+    }
+    let channels = bytes.len() / pixels;
+    let mut out = Vec::with_capacity(pixels);
+    for pixel in bytes.chunks_exact(channels).take(pixels) {
+        let (r, g, b, a) = match channels {
+            1 => (pixel[0], pixel[0], pixel[0], 255),
+            2 => (pixel[0], pixel[0], pixel[0], pixel[1]),
+            3 => (pixel[0], pixel[1], pixel[2], 255),
+            4 => (pixel[0], pixel[1], pixel[2], pixel[3]),
+            _ => return None
+        };
+        out.push((a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32);
+    }
+    Some((width, height, out))
+}
+
+// box filters straight alpha BGRA pixels to a new size, averaging in premultiplied space
+fn resample(src: &[u32], src_width: usize, src_height: usize, width: usize, height: usize) -> Vec<u32> {
+    if src_width == width && src_height == height {
+        return src.to_vec()
+    }
+    let sx = src_width as f64 / width as f64;
+    let sy = src_height as f64 / height as f64;
+    let mut out = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let y_start = (y as f64 * sy) as usize;
+        let y_end = (((y + 1) as f64 * sy).ceil() as usize).clamp(y_start + 1, src_height);
+        for x in 0..width {
+            let x_start = (x as f64 * sx) as usize;
+            let x_end = (((x + 1) as f64 * sx).ceil() as usize).clamp(x_start + 1, src_width);
+            let mut sum = [0.0f64; 4];
+            for pixel in (y_start..y_end).flat_map( | y | src[y * src_width + x_start..y * src_width + x_end].iter()) {
+                let alpha = (pixel >> 24) as f64;
+                sum[0] += ((pixel >> 16) & 0xff) as f64 * alpha;
+                sum[1] += ((pixel >> 8) & 0xff) as f64 * alpha;
+                sum[2] += (pixel & 0xff) as f64 * alpha;
+                sum[3] += alpha;
+            }
+            let count = ((y_end - y_start) * (x_end - x_start)) as f64;
+            if sum[3] <= 0.0 {
+                out.push(0);
+                continue;
+            }
+            let channel = | value: f64 | (value / sum[3]).round().min(255.0) as u32;
+            let alpha = (sum[3] / count).round().min(255.0) as u32;
+            out.push(alpha << 24 | channel(sum[0]) << 16 | channel(sum[1]) << 8 | channel(sum[2]));
+        }
+    }
+    out
+}
+
+// the cpu side of the colour glyph atlas, uploaded as a whole when glyphs were added
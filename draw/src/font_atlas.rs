@@ -21,6 +21,9 @@ pub use {
         makepad_vector::geometry::{AffineTransformation, Transform, Vector},
         makepad_vector::internal_iter::ExtendFromInternalIterator,
         makepad_vector::path::PathIterator,
+        color_font::CxColorFont,
+        sdf_glyph::SDF_ATLAS_SIZE,
+        system_fonts::{CxSystemFonts, SystemFontsLoad},
     },
     rustybuzz::{Direction, GlyphInfo, UnicodeBuffer},
 };
@@ -30,7 +33,41 @@ pub struct CxFontsAtlas {
     pub path_to_font_id: HashMap<String, usize>,
     pub texture_id: TextureId,
     pub clear_buffer: bool,
-    pub alloc: CxFontsAtlasAlloc
+    pub alloc: CxFontsAtlasAlloc,
+    // glyphs rasterized on the cpu: colour glyphs, and distance fields for the TextStyle glyph_atlas modes
+    pub color_atlas: CxImageAtlas,
+    pub sdf_atlas: CxImageAtlas,
+    pub trapezoidator: Trapezoidator,
+    pub system_fonts: CxSystemFonts,
+    pub glyph_budget: CxGlyphBudget,
+}
+
+// colour and distance field glyphs are rasterized on the cpu while text is drawn, so a frame
+// only does this many and the rest are drawn in the frames after it
+const CPU_GLYPHS_PER_FRAME: usize = 16;
+
+#[derive(Default)]
+pub struct CxGlyphBudget {
+    rasterized: usize,
+    deferred: bool,
+}
+
+impl CxGlyphBudget {
+    // whether another glyph can be rasterized this frame, if not the frame remembers one waits
+    pub fn spend(&mut self) -> bool {
+        if self.rasterized < CPU_GLYPHS_PER_FRAME {
+            self.rasterized += 1;
+            return true
+        }
+        self.deferred = true;
+        false
+    }
+    
+    // starts over for the next frame, returns whether glyphs are waiting for it
+    pub fn next_frame(&mut self) -> bool {
+        self.rasterized = 0;
+        std::mem::take(&mut self.deferred)
+    }
 }
 
 #[derive(Default)]
@@ -43,12 +80,17 @@ pub struct CxFontsAtlasAlloc {
 }
 
 impl CxFontsAtlas {
-    pub fn new(texture_id: TextureId) -> Self {
+    pub fn new(texture_id: TextureId, color_texture_id: TextureId, sdf_texture_id: TextureId) -> Self {
         Self {
             fonts: Vec::new(),
             path_to_font_id: HashMap::new(),
             texture_id,
             clear_buffer: false,
+            color_atlas: CxImageAtlas::new(color_texture_id, DVec2 {x: 1024.0, y: 1024.0}),
+            sdf_atlas: CxImageAtlas::new(sdf_texture_id, DVec2 {x: SDF_ATLAS_SIZE, y: SDF_ATLAS_SIZE}),
+            trapezoidator: Trapezoidator::default(),
+            system_fonts: CxSystemFonts::default(),
+            glyph_budget: CxGlyphBudget::default(),
             alloc: CxFontsAtlasAlloc {
                 texture_size: DVec2 {x: 2048.0, y: 4096.0},
                 xpos: 0.0,
@@ -85,10 +127,104 @@ impl CxFontsAtlasAlloc {
     }
 }
 
+// A glyph rasterized on the cpu, for the atlases that are uploaded as an image instead of being
+// drawn on the gpu.
+pub struct GlyphImage {
+    pub width: usize,
+    pub height: usize,
+    // offset of the bottom left corner from the glyph origin, in pixels with y up
+    pub x: f64,
+    pub y: f64,
+    // BGRA, top row first
+    pub data: Vec<u32>,
+}
+
+// The atlas image lives in the texture, glyphs wait here with their position until the next
+// upload writes them into it.
+pub struct CxImageAtlas {
+    pub texture_id: TextureId,
+    pub alloc: CxFontsAtlasAlloc,
+    pub pending: Vec<(usize, usize, GlyphImage)>,
+    // the texture holds a full size image, and whether that has to be wiped first
+    pub uploaded: bool,
+    pub cleared: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct CxImageAtlasGlyph {
+    pub tc: CxFontAtlasGlyph,
+    // offset of the bottom left corner from the glyph origin, and the size, in pixels
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl CxImageAtlas {
+    pub fn new(texture_id: TextureId, texture_size: DVec2) -> Self {
+        Self {
+            texture_id,
+            alloc: CxFontsAtlasAlloc {
+                texture_size,
+                ..Default::default()
+            },
+            pending: Vec::new(),
+            uploaded: false,
+            cleared: false,
+        }
+    }
+    
+    pub fn add_glyph(&mut self, glyph: GlyphImage) -> CxImageAtlasGlyph {
+        let tc = self.alloc.alloc_atlas_glyph(glyph.width as f64, glyph.height as f64);
+        let left = (tc.t1.x as f64 * self.alloc.texture_size.x).round() as usize;
+        let top = (tc.t1.y as f64 * self.alloc.texture_size.y).round() as usize;
+        let atlas_glyph = CxImageAtlasGlyph {
+            tc,
+            x: glyph.x,
+            y: glyph.y,
+            width: glyph.width as f64,
+            height: glyph.height as f64,
+        };
+        self.pending.push((left, top, glyph));
+        atlas_glyph
+    }
+    
+    pub fn reset(&mut self) {
+        self.alloc.xpos = 0.;
+        self.alloc.ypos = 0.;
+        self.alloc.hmax = 0.;
+        self.pending.clear();
+        self.cleared = true;
+    }
+    
+    // copies the pending glyphs into the atlas image, clipped to it, and returns the rect they cover
+    fn write_pending(&mut self, image: &mut [u32]) -> TextureRect {
+        let texture_width = self.alloc.texture_size.x as usize;
+        let texture_height = self.alloc.texture_size.y as usize;
+        let mut rect: Option<TextureRect> = None;
+        for (left, top, glyph) in self.pending.drain(..) {
+            let columns = glyph.width.min(texture_width.saturating_sub(left));
+            let rows = glyph.height.min(texture_height.saturating_sub(top));
+            for y in 0..rows {
+                let row = (top + y) * texture_width + left;
+                image[row..row + columns].copy_from_slice(&glyph.data[y * glyph.width..y * glyph.width + columns]);
+            }
+            let glyph_rect = TextureRect {x: left, y: top, width: columns, height: rows};
+            rect = Some(rect.map_or(glyph_rect, | rect | rect.union(glyph_rect)));
+        }
+        rect.unwrap_or_default()
+    }
+}
+
+// A font with an ordered list of fallbacks. Characters the font has no glyph for are drawn
+// with the first fallback that has one, and then with a system font if system_fallback is set.
 #[derive(Clone, Live)]
 pub struct Font {
     #[rust] pub font_id: Option<usize>,
-    #[live] pub path: LiveDependency
+    #[rust] pub fallback_ids: Vec<usize>,
+    #[live] pub path: LiveDependency,
+    #[live] pub fallbacks: Vec<LiveDependency>,
+    #[live] pub system_fallback: bool,
 }
 
 #[derive(Clone)]
@@ -98,7 +234,15 @@ impl LiveHook for Font {
     fn after_apply(&mut self, cx: &mut Cx, _apply_from: ApplyFrom, _index: usize, _nodes: &[LiveNode]) {
         Cx2d::lazy_construct_font_atlas(cx);
         let atlas = cx.get_global::<CxFontsAtlasRc>().clone();
-        self.font_id = Some(atlas.0.borrow_mut().get_font_by_path(cx, self.path.as_str()));
+        let mut atlas = atlas.0.borrow_mut();
+        self.font_id = Some(atlas.get_font_by_path(cx, self.path.as_str()));
+        self.fallback_ids = self.fallbacks.iter()
+            .filter( | path | path.as_str().len() != 0)
+            .map( | path | atlas.get_font_by_path(cx, path.as_str()))
+            .collect();
+        if self.system_fallback {
+            atlas.system_fonts.start_scan();
+        }
     }
 }
 
@@ -132,16 +276,108 @@ impl CxFontsAtlas {
         font_id
     }
     
+    // adds a font read from a file outside of the live dependencies, such as a system font
+    pub fn get_font_by_file_data(&mut self, path: &std::path::Path, data: Vec<u8>) -> Option<usize> {
+        let key = path.to_string_lossy().to_string();
+        if let Some(item) = self.path_to_font_id.get(&key) {
+            return Some(*item);
+        }
+        match CxFont::load_from_ttf_bytes(Rc::new(data)) {
+            Ok(cxfont) => {
+                let font_id = self.fonts.len();
+                self.fonts.push(Some(cxfont));
+                self.path_to_font_id.insert(key, font_id);
+                Some(font_id)
+            }
+            Err(_) => {
+                error!("Error loading font {} ", key);
+                None
+            }
+        }
+    }
+    
+    fn has_glyph(&self, font_id: usize, c: char) -> bool {
+        if let Some(Some(cxfont)) = self.fonts.get(font_id) {
+            cxfont.owned_font_face.with_ref( | face | face.glyph_index(c).is_some())
+        }
+        else {
+            false
+        }
+    }
+    
+    // Returns the font of the fallback chain of font that draws c. If none of them has a glyph
+    // for it, the primary font draws it as its missing glyph box.
+    pub fn font_for_char(&mut self, font: &Font, c: char) -> usize {
+        let font_id = font.font_id.unwrap_or(0);
+        if self.has_glyph(font_id, c) || c.is_control() {
+            return font_id
+        }
+        if let Some(fallback_id) = font.fallback_ids.iter().find( | fallback_id | self.has_glyph(**fallback_id, c)) {
+            return *fallback_id
+        }
+        if font.system_fallback {
+            if let Some(index) = self.system_fonts.find_font_for_char(c) {
+                if let Some(font_id) = self.system_fonts.fonts.as_ref().unwrap()[index].font_id {
+                    return font_id
+                }
+                self.system_fonts.request_load(index);
+            }
+        }
+        font_id
+    }
+    
+    // takes in what the system font thread has scanned or loaded, returns whether anything
+    // changed so text has to be drawn again
+    pub fn poll_system_fonts(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(load) = self.system_fonts.loads.try_recv() {
+            changed = true;
+            match load {
+                SystemFontsLoad::Scanned(fonts) => {
+                    self.system_fonts.fonts = Some(fonts);
+                }
+                SystemFontsLoad::Loaded(index, data) => {
+                    let path = self.system_fonts.fonts.as_ref().unwrap()[index].path.clone();
+                    if let Some(font_id) = self.get_font_by_file_data(&path, data) {
+                        self.system_fonts.fonts.as_mut().unwrap()[index].font_id = Some(font_id);
+                    }
+                    else {
+                        self.system_fonts.set_failed(index);
+                    }
+                }
+                SystemFontsLoad::Failed(index) => {
+                    let path = &self.system_fonts.fonts.as_ref().unwrap()[index].path;
+                    error!("Error reading font {}", path.display());
+                    self.system_fonts.set_failed(index);
+                }
+            }
+        }
+        changed
+    }
+    
+    // the advance of c in ems, from the font of the fallback chain that draws it
+    pub fn get_char_advance(&mut self, font: &Font, c: char) -> Option<f64> {
+        let font_id = self.font_for_char(font, c);
+        let cxfont = self.fonts.get_mut(font_id)?.as_mut()?;
+        let glyph_id = cxfont.owned_font_face.with_ref( | face | face.glyph_index(c))?.0 as usize;
+        let units_per_em = cxfont.ttf_font.units_per_em;
+        let glyph = cxfont.get_glyph_by_id(glyph_id).ok()?;
+        Some(glyph.horizontal_metrics.advance_width / units_per_em)
+    }
+    
     pub fn reset_fonts_atlas(&mut self) {
         for cxfont in &mut self.fonts {
             if let Some(cxfont) = cxfont {
                 cxfont.atlas_pages.clear();
+                cxfont.sdf_glyphs.clear();
             }
         }
         self.alloc.xpos = 0.;
         self.alloc.ypos = 0.;
         self.alloc.hmax = 0.;
         self.clear_buffer = true;
+        self.color_atlas.reset();
+        self.sdf_atlas.reset();
     }
     
     pub fn get_internal_font_atlas_texture_id(&self) -> TextureId {
@@ -225,6 +461,8 @@ pub struct CxDrawFontsAtlas {
     pub atlas_pass: Pass,
     pub atlas_draw_list: DrawList2d,
     pub atlas_texture: Texture,
+    pub color_texture: Texture,
+    pub sdf_texture: Texture,
     pub counter: usize
 }
 
@@ -235,6 +473,25 @@ impl CxDrawFontsAtlas {
         
         //cx.fonts_atlas.texture_id = Some(atlas_texture.texture_id());
         
+        // the colour glyph atlas starts out as a single empty pixel, until the first colour glyph
+        let color_texture = Texture::new(cx);
+        color_texture.set_desc(cx, TextureDesc {
+            format: TextureFormat::ImageBGRA,
+            width: Some(1),
+            height: Some(1),
+            ..Default::default()
+        });
+        color_texture.swap_image_u32(cx, &mut vec![0]);
+        
+        let sdf_texture = Texture::new(cx);
+        sdf_texture.set_desc(cx, TextureDesc {
+            format: TextureFormat::ImageBGRA,
+            width: Some(1),
+            height: Some(1),
+            ..Default::default()
+        });
+        sdf_texture.swap_image_u32(cx, &mut vec![0]);
+        
         let draw_trapezoid = DrawTrapezoidVector::new_local(cx);
         // ok we need to initialize drawtrapezoidtext from a live pointer.
         Self {
@@ -242,7 +499,9 @@ impl CxDrawFontsAtlas {
             draw_trapezoid,
             atlas_pass: Pass::new(cx),
             atlas_draw_list: DrawList2d::new(cx),
-            atlas_texture: atlas_texture
+            atlas_texture: atlas_texture,
+            color_texture,
+            sdf_texture,
         }
     }
 }
@@ -254,9 +513,11 @@ impl<'a> Cx2d<'a> {
             
             let draw_fonts_atlas = CxDrawFontsAtlas::new(cx);
             let texture_id = draw_fonts_atlas.atlas_texture.texture_id();
+            let color_texture_id = draw_fonts_atlas.color_texture.texture_id();
+            let sdf_texture_id = draw_fonts_atlas.sdf_texture.texture_id();
             cx.set_global(CxDrawFontsAtlasRc(Rc::new(RefCell::new(draw_fonts_atlas))));
             
-            let fonts_atlas = CxFontsAtlas::new(texture_id);
+            let fonts_atlas = CxFontsAtlas::new(texture_id, color_texture_id, sdf_texture_id);
             cx.set_global(CxFontsAtlasRc(Rc::new(RefCell::new(fonts_atlas))));
         }
     }
@@ -268,6 +529,46 @@ impl<'a> Cx2d<'a> {
         }
     }
         
+    pub fn poll_system_fonts(cx: &mut Cx) {
+        if cx.has_global::<CxFontsAtlasRc>() {
+            let fonts_atlas_rc = cx.get_global::<CxFontsAtlasRc>().clone();
+            if fonts_atlas_rc.0.borrow_mut().poll_system_fonts() {
+                cx.redraw_all();
+            }
+        }
+    }
+        
+    // the first upload and one after a reset send the whole image, after that only the rect
+    // of the new glyphs goes up
+    fn upload_image_atlas(cx: &mut Cx, atlas: &mut CxImageAtlas, texture: &Texture, sampler: Option<TextureSampler>) {
+        if atlas.pending.is_empty() && !(atlas.cleared && atlas.uploaded) {
+            return
+        }
+        if !atlas.uploaded || atlas.cleared {
+            atlas.uploaded = true;
+            atlas.cleared = false;
+            let texture_size = atlas.alloc.texture_size;
+            let mut desc = TextureDesc {
+                format: TextureFormat::ImageBGRA,
+                width: Some(texture_size.x as usize),
+                height: Some(texture_size.y as usize),
+                sampler,
+                ..Default::default()
+            };
+            // platforms without texture samplers filter the atlas the way their shaders do
+            if desc.check_supported().is_err() {
+                desc.sampler = None;
+            }
+            texture.set_desc(cx, desc);
+            let mut image = vec![0; texture_size.x as usize * texture_size.y as usize];
+            atlas.write_pending(&mut image);
+            texture.swap_image_u32(cx, &mut image);
+        }
+        else {
+            texture.update_image_u32_rect(cx, | image | atlas.write_pending(image));
+        }
+    }
+    
     pub fn draw_font_atlas(&mut self) {
         let draw_fonts_atlas_rc = self.cx.get_global::<CxDrawFontsAtlasRc>().clone();
         let mut draw_fonts_atlas = draw_fonts_atlas_rc.0.borrow_mut();
@@ -275,6 +576,16 @@ impl<'a> Cx2d<'a> {
         let mut fonts_atlas = fonts_atlas_rc.0.borrow_mut();
         let fonts_atlas = &mut*fonts_atlas;
         //let start = Cx::profile_time_ns();
+        // glyphs rasterized on the cpu only need their atlas uploaded
+        if fonts_atlas.glyph_budget.next_frame() {
+            self.cx.redraw_all();
+        }
+        Self::upload_image_atlas(self.cx, &mut fonts_atlas.color_atlas, &draw_fonts_atlas.color_texture, None);
+        // distance fields are sampled between texels, so they need linear filtering
+        Self::upload_image_atlas(self.cx, &mut fonts_atlas.sdf_atlas, &draw_fonts_atlas.sdf_texture, Some(TextureSampler {
+            mag_filter: TextureFilter::Linear,
+            ..Default::default()
+        }));
         // we need to start a pass that just uses the texture
         if fonts_atlas.alloc.todo.len()>0 {
             self.begin_pass(&draw_fonts_atlas.atlas_pass, None);
@@ -319,11 +630,21 @@ pub struct CxFont {
     pub owned_font_face: crate::owned_font_face::OwnedFace,
     pub atlas_pages: Vec<CxFontAtlasPage>,
     pub shape_cache: ShapeCache,
+    pub color_font: Option<CxColorFont>,
+    // distance fields don't depend on the size they are drawn at, so they are cached per font
+    pub sdf_glyphs: HashMap<usize, Option<CxImageAtlasGlyph >>,
+}
+
+#[derive(Clone, Copy)]
+pub struct ShapedGlyph {
+    pub id: usize,
+    // the byte offset in the shaped string of the first char the glyph was shaped from
+    pub cluster: usize,
 }
 
 pub struct ShapeCache {
     pub keys: VecDeque<(Direction, Rc<str>)>,
-    pub glyph_ids: HashMap<(Direction, Rc<str>), Vec<usize>>,
+    pub glyph_ids: HashMap<(Direction, Rc<str>), Vec<ShapedGlyph >>,
 }
 
 impl ShapeCache {
@@ -338,8 +659,8 @@ impl ShapeCache {
     }
 
     // If there is an entry for the given key in the cache, returns the corresponding list of
-    // glyph indices for that key. Otherwise, uses the given UnicodeBuffer and OwnedFace to
-    // compute the list of glyph indices for the key, inserts that in the cache and then returns
+    // glyphs for that key. Otherwise, uses the given UnicodeBuffer and OwnedFace to
+    // compute the list of glyphs for the key, inserts that in the cache and then returns
     // the corresponding list.
     //
     // This method takes a UnicodeBuffer by value, and then returns the same buffer by value. This
@@ -355,7 +676,7 @@ impl ShapeCache {
         key: (Direction, &str),
         mut rustybuzz_buffer: UnicodeBuffer,
         owned_font_face: &crate::owned_font_face::OwnedFace
-    ) -> (&[usize], UnicodeBuffer) {
+    ) -> (&[ShapedGlyph], UnicodeBuffer) {
         if !self.glyph_ids.contains_key(&key as &dyn ShapeCacheKey) {
             if self.keys.len() == Self::MAX_SIZE {
                 for run in self.keys.drain(..Self::MAX_SIZE / 2) {
@@ -367,7 +688,10 @@ impl ShapeCache {
             rustybuzz_buffer.set_direction(direction);
             rustybuzz_buffer.push_str(string);
             let glyph_buffer = owned_font_face.with_ref( | face | rustybuzz::shape(face, &[], rustybuzz_buffer));
-            let glyph_ids: Vec<_> = glyph_buffer.glyph_infos().iter().map( | glyph | glyph.glyph_id as usize).collect();
+            let glyph_ids: Vec<_> = glyph_buffer.glyph_infos().iter().map( | glyph | ShapedGlyph {
+                id: glyph.glyph_id as usize,
+                cluster: glyph.cluster as usize
+            }).collect();
             rustybuzz_buffer = glyph_buffer.clear();
 
             let owned_string: Rc<str> = string.into();
@@ -452,7 +776,9 @@ pub const ATLAS_SUBPIXEL_SLOTS: usize = 64;
 pub struct CxFontAtlasPage {
     pub dpi_factor: f64,
     pub font_size: f64,
-    pub atlas_glyphs: Vec<[Option<CxFontAtlasGlyph>; ATLAS_SUBPIXEL_SLOTS]>
+    pub atlas_glyphs: Vec<[Option<CxFontAtlasGlyph>; ATLAS_SUBPIXEL_SLOTS]>,
+    // None for glyphs of a colour font that turned out to be plain outlines
+    pub color_glyphs: HashMap<usize, Option<CxImageAtlasGlyph >>,
 }
 
 #[derive(Clone, Copy)]
@@ -475,11 +801,14 @@ impl CxFont {
     pub fn load_from_ttf_bytes(bytes: Rc<Vec<u8>>) -> Result<Self, crate::owned_font_face::FaceParsingError> {
         let owned_font_face = crate::owned_font_face::OwnedFace::parse(bytes, 0)?;
         let ttf_font = owned_font_face.with_ref(|face| makepad_vector::ttf_parser::from_ttf_parser_face(face));
+        let color_font = owned_font_face.with_ref(|face| CxColorFont::parse(face));
         Ok(Self {
             ttf_font,
             owned_font_face,
             atlas_pages: Vec::new(),
             shape_cache: ShapeCache::new(),
+            color_font,
+            sdf_glyphs: HashMap::new(),
         })
     }
     
@@ -497,7 +826,8 @@ impl CxFont {
                 let mut v = Vec::new();
                 v.resize(self.owned_font_face.with_ref(|face| face.number_of_glyphs() as usize), [None; ATLAS_SUBPIXEL_SLOTS]);
                 v
-            }
+            },
+            color_glyphs: HashMap::new(),
         });
         self.atlas_pages.len() - 1
     }
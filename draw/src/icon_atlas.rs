@@ -16,7 +16,9 @@ pub use {
         makepad_vector::trapezoidator::Trapezoidator,
         makepad_vector::geometry::{AffineTransformation, Transform, Vector, Point},
         makepad_vector::internal_iter::*,
-        makepad_vector::path::{PathIterator, PathCommand},
+        makepad_vector::path::{PathIterator, PathCommand, LinePath, LinePathCommand},
+        makepad_vector::stroker::Stroker,
+        makepad_vector::svg::{Document, parse_path_data},
     }
 };
 
@@ -121,39 +123,9 @@ impl CxIconAtlas {
     }
     
     pub fn parse_and_cache_path(&mut self, path_hash: CxIconPathHash, path: &[u8]) -> Option<(CxIconPathHash, Rect)> {
-        match parse_svg_path(path) {
-            Ok(path) => {
-                let mut min = dvec2(f64::INFINITY, f64::INFINITY);
-                let mut max = dvec2(-f64::INFINITY, -f64::INFINITY);
-                fn bound(p: &Point, min: &mut DVec2, max: &mut DVec2) {
-                    if p.x < min.x {min.x = p.x}
-                    if p.y < min.y {min.y = p.y}
-                    if p.x > max.x {max.x = p.x}
-                    if p.y > max.y {max.y = p.y}
-                }
-                for cmd in &path {
-                    match cmd {
-                        PathCommand::MoveTo(p) => {bound(p, &mut min, &mut max)},
-                        PathCommand::LineTo(p) => {bound(p, &mut min, &mut max)},
-                        PathCommand::QuadraticTo(p1, p) => {
-                            bound(p1, &mut min, &mut max);
-                            bound(p, &mut min, &mut max);
-                        },
-                        PathCommand::CubicTo(p1, p2, p) => {
-                            bound(p1, &mut min, &mut max);
-                            bound(p2, &mut min, &mut max);
-                            bound(p, &mut min, &mut max);
-                        },
-                        PathCommand::Close => ()
-                    }
-                }
-                let bounds = Rect {pos: min, size: max - min};
-                self.paths.insert(path_hash, CxIconPathCommands {
-                    bounds,
-                    path
-                });
-                return Some((path_hash, bounds));
-            }
+        let parsed = std::str::from_utf8(path).map_err(|e| e.to_string()).and_then(parse_path_data);
+        match parsed {
+            Ok(path) => Some(self.cache_path(path_hash, path)),
             Err(e) => {
                 log!("Error in SVG Path {}", e);
                 return None
@@ -161,6 +133,39 @@ impl CxIconAtlas {
         }
     }
     
+    fn cache_path(&mut self, path_hash: CxIconPathHash, path: Vec<PathCommand>) -> (CxIconPathHash, Rect) {
+        let mut min = dvec2(f64::INFINITY, f64::INFINITY);
+        let mut max = dvec2(-f64::INFINITY, -f64::INFINITY);
+        fn bound(p: &Point, min: &mut DVec2, max: &mut DVec2) {
+            if p.x < min.x {min.x = p.x}
+            if p.y < min.y {min.y = p.y}
+            if p.x > max.x {max.x = p.x}
+            if p.y > max.y {max.y = p.y}
+        }
+        for cmd in &path {
+            match cmd {
+                PathCommand::MoveTo(p) => {bound(p, &mut min, &mut max)},
+                PathCommand::LineTo(p) => {bound(p, &mut min, &mut max)},
+                PathCommand::QuadraticTo(p1, p) => {
+                    bound(p1, &mut min, &mut max);
+                    bound(p, &mut min, &mut max);
+                },
+                PathCommand::CubicTo(p1, p2, p) => {
+                    bound(p1, &mut min, &mut max);
+                    bound(p2, &mut min, &mut max);
+                    bound(p, &mut min, &mut max);
+                },
+                PathCommand::Close => ()
+            }
+        }
+        let bounds = Rect {pos: min, size: max - min};
+        self.paths.insert(path_hash, CxIconPathCommands {
+            bounds,
+            path
+        });
+        (path_hash, bounds)
+    }
+    
     pub fn get_icon_bounds(&mut self, cx: &Cx, path_str: &Rc<String>, svg_dep: &Rc<String>) -> Option<(CxIconPathHash, Rect)> {
         if svg_dep.len() != 0 {
             // alright so. lets see if we have a path hash
@@ -172,31 +177,41 @@ impl CxIconAtlas {
             }
             let path_hash = CxIconPathHash(LiveId(self.svg_deps.len() as u64));
             self.svg_deps.insert(svg_dep.as_str().to_string(), path_hash);
-            // lets parse the path range out of the svg file
+            // icons are drawn in a single color, so all the shapes of the document are merged
+            // into one path in document coordinates, strokes as the outline they cover
             match cx.get_dependency(svg_dep.as_str()) {
                 Ok(data)=>{
-                    fn find_path_str(data:&[u8])->Option<&[u8]>{
-                        let pat = "path d=\"".as_bytes();
-                        'outer:for i in 0..data.len(){
-                            for j in 0..pat.len(){
-                                if data[i+j] != pat[j]{
-                                    continue 'outer;
+                    let document = std::str::from_utf8(&data).map_err(|e| e.to_string()).and_then(Document::parse);
+                    match document {
+                        Ok(document) => {
+                            let mut path = Vec::new();
+                            let mut stroker = Stroker::new();
+                            let mut outline = LinePath::new();
+                            // the outline is flattened before the icon size is known, a thousandth
+                            // of the view box stays under a pixel at any sensible icon size
+                            let view_box_size = document.view_box.p_max - document.view_box.p_min;
+                            let epsilon = view_box_size.x.max(view_box_size.y) * 0.001;
+                            for shape in &document.shapes {
+                                if shape.fill.is_some() {
+                                    path.extend(Iterator::map(shape.path.commands(), |cmd| cmd.transform(&shape.transform)));
                                 }
-                            }
-                            for k in i+pat.len()..data.len(){
-                                if data[k] == '\"' as u8{
-                                    return Some(&data[i+pat.len()..k])
+                                if shape.stroke.is_some() {
+                                    outline.clear();
+                                    stroker.stroke(shape.path.commands().linearize(epsilon), &shape.stroke_style, epsilon, &mut outline);
+                                    path.extend(Iterator::map(outline.commands(), |cmd| match cmd.transform(&shape.transform) {
+                                        LinePathCommand::MoveTo(p) => PathCommand::MoveTo(p),
+                                        LinePathCommand::LineTo(p) => PathCommand::LineTo(p),
+                                        LinePathCommand::Close => PathCommand::Close,
+                                    }));
                                 }
                             }
+                            return Some(self.cache_path(path_hash, path))
+                        }
+                        Err(e) => {
+                            log!("Error in SVG {} {}", svg_dep, e);
                             return None
                         }
-                        None
-                    }
-                    if let Some(data) = find_path_str(&data){
-                        return self.parse_and_cache_path(path_hash, data)
                     }
-                    return None
-                    
                 }
                 Err(_err)=>{
                     return None
@@ -415,234 +430,3 @@ impl<'a> Cx2d<'a> {
     
     
 }
-
-fn parse_svg_path(path: &[u8]) -> Result<Vec<PathCommand>, String> {
-    #[derive(Debug)]
-    enum Cmd {
-        Unknown,
-        Move(bool),
-        Hor(bool),
-        Vert(bool),
-        Line(bool),
-        Cubic(bool),
-        Quadratic(bool),
-        Close
-    }
-    impl Default for Cmd {fn default() -> Self {Self::Unknown}}
-    
-    #[derive(Default)]
-    struct ParseState {
-        cmd: Cmd,
-        expect_nums: usize,
-        chain: bool,
-        nums: [f64; 6],
-        num_count: usize,
-        last_pt: Point,
-        out: Vec<PathCommand>,
-        num_state: Option<NumState>
-    }
-    
-    struct NumState {
-        num: f64,
-        mul: f64,
-        has_dot: bool,
-    }
-    
-    impl NumState {
-        fn new_pos(v: f64) -> Self {Self {num: v, mul: 1.0, has_dot: false}}
-        fn new_min() -> Self {Self {num: 0.0, mul: -1.0, has_dot: false}}
-        fn finalize(self) -> f64 {self.num * self.mul}
-        fn add_digit(&mut self, digit: f64) {
-            self.num *= 10.0;
-            self.num += digit;
-            if self.has_dot {
-                self.mul *= 0.1;
-            }
-        }
-    }
-    
-    impl ParseState {
-        fn next_cmd(&mut self, cmd: Cmd) -> Result<(), String> {
-            self.finalize_cmd() ?;
-            self.chain = false;
-            self.expect_nums = match cmd {
-                Cmd::Unknown => panic!(),
-                Cmd::Move(_) => 2,
-                Cmd::Hor(_) => 1,
-                Cmd::Vert(_) => 1,
-                Cmd::Line(_) => 2,
-                Cmd::Cubic(_) => 6,
-                Cmd::Quadratic(_) => 4,
-                Cmd::Close => 0
-            };
-            self.cmd = cmd;
-            Ok(())
-        }
-        
-        fn add_min(&mut self) -> Result<(), String> {
-            if self.expect_nums == self.num_count {
-                self.finalize_cmd() ?;
-            }
-            if self.expect_nums == 0 {
-                return Err(format!("Unexpected minus"));
-            }
-            self.num_state = Some(NumState::new_min());
-            Ok(())
-        }
-        
-        fn add_digit(&mut self, digit: f64) -> Result<(), String> {
-            if let Some(num_state) = &mut self.num_state {
-                num_state.add_digit(digit);
-            }
-            else {
-                if self.expect_nums == self.num_count {
-                    self.finalize_cmd() ?;
-                }
-                if self.expect_nums == 0 {
-                    return Err(format!("Unexpected digit"));
-                }
-                self.num_state = Some(NumState::new_pos(digit))
-            }
-            Ok(())
-        }
-        
-        fn add_dot(&mut self) -> Result<(), String> {
-            if let Some(num_state) = &mut self.num_state {
-                if num_state.has_dot {
-                    return Err(format!("Unexpected ."));
-                }
-                num_state.has_dot = true;
-            }
-            else {
-                return Err(format!("Unexpected ."));
-            }
-            Ok(())
-        }
-        
-        fn finalize_num(&mut self) {
-            if let Some(num_state) = self.num_state.take() {
-                self.nums[self.num_count] = num_state.finalize();
-                self.num_count += 1;
-            }
-        }
-        
-        fn whitespace(&mut self) -> Result<(), String> {
-            self.finalize_num();
-            if self.expect_nums == self.num_count {
-                self.finalize_cmd() ?;
-            }
-            Ok(())
-        }
-        
-        fn finalize_cmd(&mut self) -> Result<(), String> {
-            self.finalize_num();
-            if self.chain && self.num_count == 0 {
-                return Ok(())
-            }
-            if self.expect_nums != self.num_count {
-                return Err(format!("SVG Path command {:?} expected {} points, got {}", self.cmd, self.expect_nums, self.num_count));
-            }
-            match self.cmd {
-                Cmd::Unknown => (),
-                Cmd::Move(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.nums[0], y: self.nums[1]};
-                    }
-                    else {
-                        self.last_pt += Vector {x: self.nums[0], y: self.nums[1]};
-                    }
-                    self.out.push(PathCommand::MoveTo(self.last_pt));
-                },
-                Cmd::Hor(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.nums[0], y: self.last_pt.y};
-                    }
-                    else {
-                        self.last_pt += Vector {x: self.nums[0], y: 0.0};
-                    }
-                    self.out.push(PathCommand::LineTo(self.last_pt));
-                }
-                Cmd::Vert(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.last_pt.x, y: self.nums[0]};
-                    }
-                    else {
-                        self.last_pt += Vector {x: 0.0, y: self.nums[0]};
-                    }
-                    self.out.push(PathCommand::LineTo(self.last_pt));
-                }
-                Cmd::Line(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.nums[0], y: self.nums[1]};
-                    }
-                    else {
-                        self.last_pt += Vector {x: self.nums[0], y: self.nums[1]};
-                    }
-                    self.out.push(PathCommand::LineTo(self.last_pt));
-                },
-                Cmd::Cubic(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.nums[4], y: self.nums[5]};
-                    }
-                    else {
-                        self.last_pt += Vector {x: self.nums[4], y: self.nums[5]};
-                    }
-                    self.out.push(PathCommand::CubicTo(
-                        Point {x: self.nums[0], y: self.nums[1]},
-                        Point {x: self.nums[2], y: self.nums[3]},
-                        
-                        self.last_pt,
-                    ))
-                },
-                Cmd::Quadratic(abs) => {
-                    if abs {
-                        self.last_pt = Point {x: self.nums[2], y: self.nums[3]};
-                    }
-                    else {
-                        self.last_pt += Vector {x: self.nums[2], y: self.nums[3]};
-                    }
-                    self.out.push(PathCommand::QuadraticTo(
-                        Point {x: self.nums[0], y: self.nums[1]},
-                        self.last_pt
-                    ));
-                }
-                Cmd::Close => {
-                    self.out.push(PathCommand::Close);
-                }
-            }
-            self.num_count = 0;
-            self.chain = true;
-            Ok(())
-        }
-    }
-    
-    let mut state = ParseState::default();
-    for i in 0..path.len() {
-        match path[i] {
-            b'M' => state.next_cmd(Cmd::Move(true)) ?,
-            b'm' => state.next_cmd(Cmd::Move(false)) ?,
-            b'Q' => state.next_cmd(Cmd::Quadratic(true)) ?,
-            b'q' => state.next_cmd(Cmd::Quadratic(false)) ?,
-            b'C' => state.next_cmd(Cmd::Cubic(true)) ?,
-            b'c' => state.next_cmd(Cmd::Cubic(false)) ?,
-            b'H' => state.next_cmd(Cmd::Hor(true)) ?,
-            b'h' => state.next_cmd(Cmd::Hor(false)) ?,
-            b'V' => state.next_cmd(Cmd::Vert(true)) ?,
-            b'v' => state.next_cmd(Cmd::Vert(false)) ?,
-            b'L' => state.next_cmd(Cmd::Line(true)) ?,
-            b'l' => state.next_cmd(Cmd::Line(false)) ?,
-            b'Z' | b'z' => state.next_cmd(Cmd::Close) ?,
-            b'-' => state.add_min() ?,
-            b'0'..=b'9' => state.add_digit((path[i] - b'0') as f64) ?,
-            b'.' => state.add_dot() ?,
-            b',' | b' ' | b'\r' | b'\n' | b'\t' => state.whitespace() ?,
-            x => {
-                return Err(format!("Unexpected character {} - {}", x, x as char))
-            }
-        }
-    }
-    state.finalize_cmd() ?;
-    
-    Ok(state.out)
-}
-
@@ -11,8 +11,12 @@ pub mod turtle;
 pub mod font_atlas;
 pub mod geometry;
 pub mod nav;
+pub mod accessibility;
 pub mod icon_atlas;
 mod owned_font_face;
+mod color_font;
+mod system_fonts;
+mod sdf_glyph;
  
 pub use crate::{
     font_atlas::Font,
@@ -37,6 +41,15 @@ pub use crate::{
         NavItem,
         NavScrollIndex
     },
+    accessibility::{
+        AccessTree,
+        AccessNode,
+        AccessNodeId,
+        AccessRole,
+        AccessInfo,
+        AccessState,
+        AccessAction,
+    },
     draw_list_2d::{
         DrawList2d,
         ManyInstances,
@@ -50,8 +63,9 @@ pub use crate::{
         //draw_shape::{DrawShape, Shape, Fill},
         draw_icon::DrawIcon,
         draw_quad::DrawQuad,
-        draw_text::DrawText,
+        draw_text::{DrawText, CharBoxes, GlyphAtlas, TextWrap},
         draw_color::DrawColor,
+        draw_path::DrawPath,
     },
     geometry::{
         GeometryGen,
@@ -67,4 +81,5 @@ pub fn live_design(cx: &mut Cx) {
     crate::geometry::geometry_gen::live_design(cx);
     crate::shader::std::live_design(cx);
     crate::shader::draw_trapezoid::live_design(cx);
+    crate::shader::draw_path::live_design(cx);
 }
@@ -4,10 +4,8 @@ use {
     std::cell::RefCell,
     crate::{
         cx_2d::Cx2d,
-        makepad_platform::Area,
-        makepad_platform::DrawListId,
-        makepad_platform::Margin,
-        makepad_platform::Cx,
+        makepad_platform::*,
+        accessibility::AccessInfo,
     }
 };
 
@@ -16,6 +14,23 @@ pub struct CxNavTree {
     nav_lists: Vec<CxNavList>
 }
 
+impl CxNavTree {
+    pub fn len(&self) -> usize {
+        self.nav_lists.len()
+    }
+    
+    pub fn clear_list(&mut self, draw_list_id: DrawListId) {
+        if draw_list_id.index() >= self.nav_lists.len() {
+            self.nav_lists.resize(draw_list_id.index() + 1, Default::default());
+        }
+        self[draw_list_id].nav_list.clear();
+    }
+    
+    pub fn push_item(&mut self, draw_list_id: DrawListId, item: NavItem) {
+        self[draw_list_id].nav_list.push(item);
+    }
+}
+
 #[derive(Clone)]
 pub struct CxNavTreeRc(pub Rc<RefCell<CxNavTree >>);
 
@@ -37,12 +52,26 @@ impl std::ops::IndexMut<DrawListId> for CxNavTree {
     }
 }
 
-#[derive(Debug, Clone)]
+// Tab order of a nav stop. Default stops keep their draw order,
+// Top stops come before all of them and Bottom stops after, Middle stops sort in between the default ones
+#[derive(Copy, Clone, Debug, Live, LiveHook)]
+#[live_ignore]
 pub enum NavOrder {
-    Default,
-    Top(u64),
-    Middle(u64),
-    Bottom(u64),
+    #[pick] Default,
+    #[live(0)] Top(usize),
+    #[live(0)] Middle(usize),
+    #[live(0)] Bottom(usize),
+}
+
+impl NavOrder {
+    fn sort_key(&self) -> (usize, usize) {
+        match self {
+            Self::Top(n) => (0, *n),
+            Self::Default => (1, 0),
+            Self::Middle(n) => (1, *n),
+            Self::Bottom(n) => (2, *n),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -50,7 +79,8 @@ pub struct NavStop {
     pub role: NavRole,
     pub order: NavOrder,
     pub margin: Margin,
-    pub area: Area
+    pub area: Area,
+    pub access: AccessInfo,
 }
 
 #[derive(Debug, Clone)]
@@ -61,11 +91,17 @@ pub enum NavItem {
     EndScroll(Area)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum NavRole {
     TextInput,
     DropDown,
     Slider,
+    Button,
+    CheckBox,
+    RadioButton,
+    Tab,
+    ListItem,
+    TreeItem,
 }
 
 impl<'a> Cx2d<'a> {
@@ -114,26 +150,60 @@ impl<'a> Cx2d<'a> {
         }
     }
     
-    pub fn nav_list_clear(&mut self, draw_list_id: DrawListId) {
-        let mut nav_tree = self.nav_tree_rc.0.borrow_mut();
-        if draw_list_id.index() >= nav_tree.nav_lists.len() {
-            nav_tree.nav_lists.resize(draw_list_id.index() + 1, Default::default());
+    // all nav stops under root in tab order, each with the scroll areas it sits in
+    // followed by the area of the stop itself (the form send_trigger_to_scroll_stack wants)
+    pub fn collect_nav_stops(cx: &mut Cx, root: DrawListId) -> Vec<(NavStop, Vec<Area>)> {
+        let mut stops = Vec::new();
+        if !cx.has_global::<CxNavTreeRc>() {
+            return stops
+        }
+        let nav_tree_rc = cx.get_global::<CxNavTreeRc>().clone();
+        let nav_tree = &*nav_tree_rc.0.borrow();
+        fn collect(nav_tree: &CxNavTree, draw_list_id: DrawListId, scroll_stack: &mut Vec<Area>, stops: &mut Vec<(NavStop, Vec<Area>)>) {
+            if draw_list_id.index() >= nav_tree.len() {
+                return
+            }
+            for nav_item in &nav_tree[draw_list_id].nav_list {
+                match nav_item {
+                    NavItem::Child(draw_list_id) => collect(nav_tree, *draw_list_id, scroll_stack, stops),
+                    NavItem::Stop(stop) => {
+                        let mut stack = scroll_stack.clone();
+                        stack.push(stop.area);
+                        stops.push((stop.clone(), stack));
+                    }
+                    NavItem::BeginScroll(area) => scroll_stack.push(*area),
+                    NavItem::EndScroll(_) => {
+                        scroll_stack.pop();
+                    }
+                }
+            }
         }
-        nav_tree[draw_list_id].nav_list.clear();
+        collect(nav_tree, root, &mut Vec::new(), &mut stops);
+        stops.retain( | (stop, _) | !stop.area.is_empty());
+        stops.sort_by_key( | (stop, _) | stop.order.sort_key());
+        stops
+    }
+    
+    pub fn nav_list_clear(&mut self, draw_list_id: DrawListId) {
+        self.nav_tree_rc.0.borrow_mut().clear_list(draw_list_id);
     }
     
     pub fn nav_list_item_push(&mut self, draw_list_id: DrawListId, item: NavItem){
-        let mut nav_tree = self.nav_tree_rc.0.borrow_mut();
-        nav_tree[draw_list_id].nav_list.push(item);
+        self.nav_tree_rc.0.borrow_mut().push_item(draw_list_id, item);
     }
     
     pub fn add_nav_stop(&mut self, area: Area, role: NavRole, margin: Margin) {
+        self.add_nav_stop_with_access(area, role, NavOrder::Default, margin, AccessInfo::default())
+    }
+    
+    pub fn add_nav_stop_with_access(&mut self, area: Area, role: NavRole, order: NavOrder, margin: Margin, access: AccessInfo) {
         let draw_list_id = *self.draw_list_stack.last().unwrap();
         self.nav_list_item_push(draw_list_id, NavItem::Stop(NavStop {
             role,
             area,
-            order: NavOrder::Default,
-            margin
+            order,
+            margin,
+            access
         }));
     }
     
@@ -0,0 +1,456 @@
+// Distance field glyphs for the Sdf and Msdf text styles, rasterized once at SDF_PIXELS_PER_EM.
+// Alpha holds the true signed distance, rgb a multi channel field as in msdfgen whose median
+// keeps the corners sharp.
+
+use {
+    crate::{
+        makepad_platform::*,
+        font_atlas::GlyphImage,
+        makepad_vector::font::Glyph,
+        makepad_vector::path::PathCommand,
+    },
+};
+
+// the size of the em square the distance fields are rasterized at
+pub const SDF_PIXELS_PER_EM: f64 = 32.0;
+// the distance in pixels that the encoded range of [0, 1] spans on either side of the outline,
+// which is also the padding around the glyph
+pub const SDF_RANGE: f64 = 4.0;
+pub const SDF_ATLAS_SIZE: f64 = 2048.0;
+
+// two outline directions meeting at more than about 8 degrees make a corner, as in msdfgen
+const CORNER_CROSS_THRESHOLD: f64 = 0.1411;
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const YELLOW: u8 = RED | GREEN;
+const MAGENTA: u8 = RED | BLUE;
+const CYAN: u8 = GREEN | BLUE;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+// A piece of the outline between two corners, or one path command, flattened into a polyline.
+struct Edge {
+    points: Vec<DVec2>,
+    color: u8,
+}
+
+impl Edge {
+    fn start_direction(&self) -> DVec2 {
+        normalize(self.points[1] - self.points[0])
+    }
+    
+    fn end_direction(&self) -> DVec2 {
+        let n = self.points.len();
+        normalize(self.points[n - 1] - self.points[n - 2])
+    }
+    
+    // splits the edge into three at a third and two thirds of its length
+    fn split_in_thirds(self) -> [Edge; 3] {
+        let lengths: Vec<f64> = self.points.windows(2).map( | w | (w[1] - w[0]).length()).collect();
+        let total: f64 = lengths.iter().sum();
+        let mut parts = [Vec::new(), Vec::new(), Vec::new()];
+        let mut part = 0;
+        let mut travelled = 0.0;
+        parts[0].push(self.points[0]);
+        for (i, length) in lengths.iter().enumerate() {
+            let (a, b) = (self.points[i], self.points[i + 1]);
+            while part < 2 && travelled + length >= total * (part + 1) as f64 / 3.0 {
+                let t = (total * (part + 1) as f64 / 3.0 - travelled) / length.max(f64::EPSILON);
+                let split = a + (b - a) * t;
+                parts[part].push(split);
+                part += 1;
+                parts[part].push(split);
+            }
+            travelled += length;
+            parts[part].push(b);
+        }
+        parts.map( | points | Edge {points, color: self.color})
+    }
+}
+
+// The distance from a point to an edge. Two edges at the same distance meet at the point
+// closest to it, and then the one we are more perpendicular to is the one we are next to.
+#[derive(Clone, Copy)]
+struct EdgeDistance {
+    distance: f64,
+    orthogonality: f64,
+    // the distance to the line extending the edge where the closest point is an end point,
+    // positive on the inside of the edge
+    pseudo_distance: f64,
+}
+
+impl EdgeDistance {
+    const FAR: EdgeDistance = EdgeDistance {distance: f64::MAX, orthogonality: 0.0, pseudo_distance: f64::MAX};
+    
+    fn is_closer_than(&self, other: &EdgeDistance) -> bool {
+        self.distance < other.distance || (self.distance == other.distance && self.orthogonality > other.orthogonality)
+    }
+}
+
+fn dot(a: DVec2, b: DVec2) -> f64 {
+    a.x * b.x + a.y * b.y
+}
+
+fn cross(a: DVec2, b: DVec2) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+fn normalize(v: DVec2) -> DVec2 {
+    let length = v.length();
+    if length == 0.0 {dvec2(0.0, 0.0)} else {v / length}
+}
+
+// orientation is 1.0 when the inside is on the left of the edges, and -1.0 when on the right
+fn edge_distance(edge: &Edge, p: DVec2, orientation: f64) -> EdgeDistance {
+    let mut best = EdgeDistance::FAR;
+    let last = edge.points.len() - 2;
+    for (i, segment) in edge.points.windows(2).enumerate() {
+        let (a, b) = (segment[0], segment[1]);
+        let ab = b - a;
+        let length_squared = dot(ab, ab);
+        if length_squared == 0.0 {
+            continue;
+        }
+        let t = dot(p - a, ab) / length_squared;
+        let closest = a + ab * t.clamp(0.0, 1.0);
+        let to_p = p - closest;
+        let distance = to_p.length();
+        let direction = ab / length_squared.sqrt();
+        let perpendicular = cross(direction, p - a) * orientation;
+        let side = if perpendicular >= 0.0 {1.0} else {-1.0};
+        let candidate = EdgeDistance {
+            distance,
+            orthogonality: if distance == 0.0 {1.0} else {cross(direction, to_p).abs() / distance},
+            pseudo_distance: if (t < 0.0 && i == 0) || (t > 1.0 && i == last) {
+                side * perpendicular.abs().min(distance)
+            }
+            else {
+                side * distance
+            }
+        };
+        if candidate.is_closer_than(&best) {
+            best = candidate;
+        }
+    }
+    best
+}
+
+// the signed area of the closed polylines, positive when they run counter clockwise with y up
+fn signed_area(contours: &[Vec<Edge >]) -> f64 {
+    let mut area = 0.0;
+    for contour in contours {
+        for edge in contour {
+            for segment in edge.points.windows(2) {
+                area += cross(segment[0], segment[1]);
+            }
+        }
+    }
+    area * 0.5
+}
+
+// the nonzero winding number of the outline around p
+fn winding(contours: &[Vec<Edge >], p: DVec2) -> i32 {
+    let mut winding = 0;
+    for contour in contours {
+        for edge in contour {
+            for segment in edge.points.windows(2) {
+                let (a, b) = (segment[0], segment[1]);
+                if a.y <= p.y {
+                    if b.y > p.y && cross(b - a, p - a) > 0.0 {
+                        winding += 1;
+                    }
+                }
+                else if b.y <= p.y && cross(b - a, p - a) < 0.0 {
+                    winding -= 1;
+                }
+            }
+        }
+    }
+    winding
+}
+
+fn flatten_quadratic(points: &mut Vec<DVec2>, p0: DVec2, p1: DVec2, p2: DVec2) {
+    let steps = (((p1 - p0).length() + (p2 - p1).length()) * 0.5).ceil().clamp(2.0, 32.0) as usize;
+    for step in 1..=steps {
+        let t = step as f64 / steps as f64;
+        let u = 1.0 - t;
+        points.push(p0 * (u * u) + p1 * (2.0 * u * t) + p2 * (t * t));
+    }
+}
+
+fn flatten_cubic(points: &mut Vec<DVec2>, p0: DVec2, p1: DVec2, p2: DVec2, p3: DVec2) {
+    let steps = (((p1 - p0).length() + (p2 - p1).length() + (p3 - p2).length()) * 0.5).ceil().clamp(2.0, 32.0) as usize;
+    for step in 1..=steps {
+        let t = step as f64 / steps as f64;
+        let u = 1.0 - t;
+        points.push(p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t));
+    }
+}
+
+// every path command becomes an edge of its contour, and contours are closed with a line
+fn outline_contours(outline: &[PathCommand], scale: f64) -> Vec<Vec<Edge >> {
+    let mut contours = Vec::new();
+    let mut contour: Vec<Edge> = Vec::new();
+    let mut start = dvec2(0.0, 0.0);
+    let mut last = dvec2(0.0, 0.0);
+    let close = | contour: &mut Vec<Edge>, contours: &mut Vec<Vec<Edge >>, start: DVec2, last: DVec2 | {
+        if !contour.is_empty() && last != start {
+            contour.push(Edge {points: vec![last, start], color: WHITE});
+        }
+        if !contour.is_empty() {
+            contours.push(std::mem::take(contour));
+        }
+    };
+    for command in outline {
+        let point = | p: crate::makepad_vector::geometry::Point | dvec2(p.x * scale, p.y * scale);
+        match *command {
+            PathCommand::MoveTo(p) => {
+                close(&mut contour, &mut contours, start, last);
+                start = point(p);
+                last = start;
+                continue;
+            }
+            PathCommand::Close => {
+                close(&mut contour, &mut contours, start, last);
+                last = start;
+                continue;
+            }
+            _ => ()
+        }
+        let mut points = vec![last];
+        match *command {
+            PathCommand::LineTo(p) => points.push(point(p)),
+            PathCommand::QuadraticTo(p1, p) => flatten_quadratic(&mut points, last, point(p1), point(p)),
+            PathCommand::CubicTo(p1, p2, p) => flatten_cubic(&mut points, last, point(p1), point(p2), point(p)),
+            _ => unreachable!()
+        }
+        last = *points.last().unwrap();
+        points.dedup();
+        if points.len() >= 2 {
+            contour.push(Edge {points, color: WHITE});
+        }
+    }
+    close(&mut contour, &mut contours, start, last);
+    contours
+}
+
+// msdfgen's simple edge colouring, with a fixed seed
+fn switch_color(color: u8, banned: u8) -> u8 {
+    let combined = color & banned;
+    if combined == RED || combined == GREEN || combined == BLUE {
+        return combined ^ WHITE
+    }
+    let shifted = color << 1;
+    (shifted | shifted >> 3) & WHITE
+}
+
+fn color_edges(contour: &mut Vec<Edge>) {
+    let corners: Vec<usize> = (0..contour.len()).filter( | &i | {
+        let previous = contour[(i + contour.len() - 1) % contour.len()].end_direction();
+        let next = contour[i].start_direction();
+        dot(previous, next) <= 0.0 || cross(previous, next).abs() > CORNER_CROSS_THRESHOLD
+    }).collect();
+    match corners.len() {
+        0 => (),
+        1 => {
+            // a teardrop, which needs at least three edges to get three colours
+            let corner = corners[0];
+            contour.rotate_left(corner);
+            if contour.len() < 3 {
+                let edges = std::mem::take(contour);
+                for edge in edges {
+                    contour.extend(edge.split_in_thirds());
+                }
+            }
+            let n = contour.len();
+            let colors = [MAGENTA, WHITE, YELLOW];
+            for (i, edge) in contour.iter_mut().enumerate() {
+                let third = (3.0 + 2.875 * i as f64 / (n - 1) as f64 - 1.4375 + 0.5) as i32 - 3;
+                edge.color = colors[(third + 1) as usize];
+            }
+        }
+        _ => {
+            let n = contour.len();
+            let mut color = CYAN;
+            let initial_color = color;
+            let mut spline = 0;
+            for i in 0..n {
+                let index = (corners[0] + i) % n;
+                if spline + 1 < corners.len() && corners[spline + 1] == index {
+                    spline += 1;
+                    color = switch_color(color, if spline == corners.len() - 1 {initial_color} else {0});
+                }
+                contour[index].color = color;
+            }
+        }
+    }
+}
+
+fn encode(distance: f64) -> u32 {
+    ((0.5 + distance / (2.0 * SDF_RANGE)).clamp(0.0, 1.0) * 255.0 + 0.5) as u32
+}
+
+// Rasterizes the distance field of a glyph, with glyph units_per_em font units to the em. Returns
+// None for glyphs without an outline.
+pub fn rasterize_sdf_glyph(glyph: &Glyph, units_per_em: f64) -> Option<GlyphImage> {
+    let scale = SDF_PIXELS_PER_EM / units_per_em;
+    let mut contours = outline_contours(&glyph.outline, scale);
+    if contours.is_empty() {
+        return None
+    }
+    for contour in &mut contours {
+        color_edges(contour);
+    }
+    // the side of an edge the inside is on follows from the direction the outer contours run in
+    let orientation = if signed_area(&contours) < 0.0 {-1.0} else {1.0};
+    
+    let x0 = (glyph.bounds.p_min.x * scale).floor() - SDF_RANGE;
+    let y0 = (glyph.bounds.p_min.y * scale).floor() - SDF_RANGE;
+    let width = ((glyph.bounds.p_max.x * scale).ceil() + SDF_RANGE - x0) as usize;
+    let height = ((glyph.bounds.p_max.y * scale).ceil() + SDF_RANGE - y0) as usize;
+    
+    let mut data = Vec::with_capacity(width * height);
+    for row in 0..height {
+        for column in 0..width {
+            let p = dvec2(x0 + column as f64 + 0.5, y0 + (height - row) as f64 - 0.5);
+            let inside = winding(&contours, p) != 0;
+            let sign = if inside {1.0} else {-1.0};
+            
+            let mut nearest = EdgeDistance::FAR;
+            let mut channels = [EdgeDistance::FAR; 3];
+            for edge in contours.iter().flatten() {
+                let distance = edge_distance(edge, p, orientation);
+                if distance.is_closer_than(&nearest) {
+                    nearest = distance;
+                }
+                for (channel, best) in channels.iter_mut().enumerate() {
+                    if edge.color & (1 << channel) != 0 && distance.is_closer_than(best) {
+                        *best = distance;
+                    }
+                }
+            }
+            let true_distance = sign * nearest.distance;
+            
+            // every channel has the pseudo distance to the nearest edge of its colour, so that
+            // where two edges meet at a corner the channels disagree and the median keeps it sharp
+            let mut values = channels.map( | best | {
+                if best.distance == f64::MAX {true_distance} else {best.pseudo_distance}
+            });
+            // where the median ends up on the wrong side of the outline the colouring couldn't
+            // separate the edges, fall back to the true distance there
+            let median = values[0].min(values[1]).max(values[0].max(values[1]).min(values[2]));
+            if (median > 0.0) != inside || nearest.distance == f64::MAX {
+                values = [true_distance; 3];
+            }
+            data.push(encode(true_distance) << 24 | encode(values[0]) << 16 | encode(values[1]) << 8 | encode(values[2]));
+        }
+    }
+    Some(GlyphImage {
+        width,
+        height,
+        x: x0,
+        y: y0,
+        data
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::makepad_vector::{
+            font::HorizontalMetrics,
+            geometry::{Point, Rectangle},
+        },
+    };
+    
+    // a glyph in an em of SDF_PIXELS_PER_EM units, so glyph units are pixels
+    fn glyph(outline: Vec<PathCommand>, min: f64, max: f64) -> GlyphImage {
+        let glyph = Glyph {
+            horizontal_metrics: HorizontalMetrics {advance_width: max, left_side_bearing: min},
+            bounds: Rectangle::new(Point::new(min, min), Point::new(max, max)),
+            outline,
+        };
+        rasterize_sdf_glyph(&glyph, SDF_PIXELS_PER_EM).unwrap()
+    }
+    
+    fn decode(value: u32) -> f64 {
+        (value as f64 / 255.0 - 0.5) * 2.0 * SDF_RANGE
+    }
+    
+    // the true distance and the median of the channels at the pixel centered on (x, y)
+    fn sample(image: &GlyphImage, x: f64, y: f64) -> (f64, f64) {
+        let column = (x - image.x - 0.5).round() as usize;
+        let row = (image.y + image.height as f64 - 0.5 - y).round() as usize;
+        let pixel = image.data[row * image.width + column];
+        let [a, r, g, b] = [pixel >> 24, (pixel >> 16) & 0xff, (pixel >> 8) & 0xff, pixel & 0xff].map(decode);
+        (a, r.min(g).max(r.max(g).min(b)))
+    }
+    
+    fn assert_near(value: f64, expected: f64, what: &str) {
+        assert!((value - expected).abs() < 0.05, "{} is {}, expected {}", what, value, expected);
+    }
+    
+    fn square(clockwise: bool) -> Vec<PathCommand> {
+        let mut corners = [(0.0, 0.0), (16.0, 0.0), (16.0, 16.0), (0.0, 16.0)];
+        if clockwise {
+            corners.reverse();
+        }
+        let mut outline = vec![PathCommand::MoveTo(Point::new(corners[0].0, corners[0].1))];
+        outline.extend(corners[1..].iter().map( | (x, y) | PathCommand::LineTo(Point::new(*x, *y))));
+        outline.push(PathCommand::Close);
+        outline
+    }
+    
+    #[test]
+    fn square_distances() {
+        for clockwise in [false, true] {
+            let image = glyph(square(clockwise), 0.0, 16.0);
+            assert_eq!((image.x, image.y, image.width, image.height), (-SDF_RANGE, -SDF_RANGE, 24, 24));
+            // positive inside, clamped to the range far from the outline
+            let (true_distance, median) = sample(&image, 8.5, 8.5);
+            assert_near(true_distance, SDF_RANGE, "center");
+            assert_near(median, SDF_RANGE, "center median");
+            // half a pixel either side of the left edge
+            for (x, expected) in [(0.5, 0.5), (-0.5, -0.5), (1.5, 1.5), (-2.5, -2.5)] {
+                let (true_distance, median) = sample(&image, x, 8.5);
+                assert_near(true_distance, expected, "edge distance");
+                assert_near(median, expected, "edge median");
+            }
+            // off a corner the true distance rounds it, the median keeps it square
+            let (true_distance, median) = sample(&image, -1.5, -1.5);
+            assert_near(true_distance, -(2.0f64).sqrt() * 1.5, "corner distance");
+            assert_near(median, -1.5, "corner median");
+            let (true_distance, median) = sample(&image, 15.5, 15.5);
+            assert_near(true_distance, 0.5, "inner corner distance");
+            assert!(median > 0.0, "inner corner median {}", median);
+        }
+    }
+    
+    #[test]
+    fn circle_distances() {
+        // four cubics around (16, 16) with radius 10
+        let (c, r, k) = (16.0, 10.0, 10.0 * 0.552_284_749_831);
+        let outline = vec![
+            PathCommand::MoveTo(Point::new(c + r, c)),
+            PathCommand::CubicTo(Point::new(c + r, c + k), Point::new(c + k, c + r), Point::new(c, c + r)),
+            PathCommand::CubicTo(Point::new(c - k, c + r), Point::new(c - r, c + k), Point::new(c - r, c)),
+            PathCommand::CubicTo(Point::new(c - r, c - k), Point::new(c - k, c - r), Point::new(c, c - r)),
+            PathCommand::CubicTo(Point::new(c + k, c - r), Point::new(c + r, c - k), Point::new(c + r, c)),
+            PathCommand::Close,
+        ];
+        let image = glyph(outline, 6.0, 26.0);
+        for row in 0..image.height {
+            for column in 0..image.width {
+                let x = image.x + column as f64 + 0.5;
+                let y = image.y + (image.height - row) as f64 - 0.5;
+                let expected = (r - dvec2(x - c, y - c).length()).clamp(-SDF_RANGE, SDF_RANGE);
+                let (true_distance, median) = sample(&image, x, y);
+                assert!((true_distance - expected).abs() < 0.1, "distance at ({}, {}) is {}, expected {}", x, y, true_distance, expected);
+                // a circle has no corners, so the median is the distance as well
+                assert!((median - expected).abs() < 0.1, "median at ({}, {}) is {}, expected {}", x, y, median, expected);
+            }
+        }
+    }
+}
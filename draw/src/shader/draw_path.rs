@@ -0,0 +1,436 @@
+use {
+    crate::{
+        makepad_platform::*,
+        cx_2d::Cx2d,
+        geometry::GeometryQuad2D,
+        makepad_vector::geometry::{AffineTransformation, LinearTransformation, Point, Transform, Transformation, Trapezoid, Vector},
+        makepad_vector::internal_iter::*,
+        makepad_vector::path::{LinePath, Path, PathIterator},
+        makepad_vector::stroker::{Stroker, StrokeStyle},
+        makepad_vector::svg::{Document, Paint},
+        makepad_vector::trapezoidator::{FillRule, Trapezoidator},
+    },
+};
+
+live_design!{
+    DrawPath = {{DrawPath}} {
+        
+        varying v_p0: vec2;
+        varying v_p1: vec2;
+        varying v_p2: vec2;
+        varying v_p3: vec2;
+        varying v_pixel: vec2;
+        varying v_world: vec2;
+        
+        fn intersect_line_segment_with_vertical_line(p0: vec2, p1: vec2, x: float) -> vec2 {
+            return vec2(
+                x,
+                mix(p0.y, p1.y, (x - p0.x) / (p1.x - p0.x))
+            );
+        }
+        
+        fn intersect_line_segment_with_horizontal_line(p0: vec2, p1: vec2, y: float) -> vec2 {
+            return vec2(
+                mix(p0.x, p1.x, (y - p0.y) / (p1.y - p0.y)),
+                y
+            );
+        }
+        
+        fn compute_clamped_right_trapezoid_area(p0: vec2, p1: vec2, p_min: vec2, p_max: vec2) -> float {
+            let x0 = clamp(p0.x, p_min.x, p_max.x);
+            let x1 = clamp(p1.x, p_min.x, p_max.x);
+            if (p0.x < p_min.x && p_min.x < p1.x) {
+                p0 = intersect_line_segment_with_vertical_line(p0, p1, p_min.x);
+            }
+            if (p0.x < p_max.x && p_max.x < p1.x) {
+                p1 = intersect_line_segment_with_vertical_line(p0, p1, p_max.x);
+            }
+            if (p0.y < p_min.y && p_min.y < p1.y) {
+                p0 = intersect_line_segment_with_horizontal_line(p0, p1, p_min.y);
+            }
+            if (p1.y < p_min.y && p_min.y < p0.y) {
+                p1 = intersect_line_segment_with_horizontal_line(p1, p0, p_min.y);
+            }
+            if (p0.y < p_max.y && p_max.y < p1.y) {
+                p1 = intersect_line_segment_with_horizontal_line(p0, p1, p_max.y);
+            }
+            if (p1.y < p_max.y && p_max.y < p0.y) {
+                p0 = intersect_line_segment_with_horizontal_line(p1, p0, p_max.y);
+            }
+            p0 = clamp(p0, p_min, p_max);
+            p1 = clamp(p1, p_min, p_max);
+            let h0 = p_max.y - p0.y;
+            let h1 = p_max.y - p1.y;
+            let a0 = (p0.x - x0) * h0;
+            let a1 = (p1.x - p0.x) * (h0 + h1) * 0.5;
+            let a2 = (x1 - p1.x) * h1;
+            return a0 + a1 + a2;
+        }
+        
+        fn compute_clamped_trapezoid_area(self, p_min: vec2, p_max: vec2) -> float {
+            let a0 = compute_clamped_right_trapezoid_area(self.v_p0, self.v_p1, p_min, p_max);
+            let a1 = compute_clamped_right_trapezoid_area(self.v_p2, self.v_p3, p_min, p_max);
+            return a0 - a1;
+        }
+        
+        fn get_color(self) -> vec4 {
+            if self.gradient_kind < 0.5 {
+                return self.color;
+            }
+            let delta = self.gradient_end - self.gradient_start;
+            let t = 0.0;
+            if self.gradient_kind < 1.5 {
+                t = dot(self.v_world - self.gradient_start, delta) / max(dot(delta, delta), 0.0001);
+            }
+            else {
+                t = length(self.v_world - self.gradient_start) / max(length(delta), 0.0001);
+            }
+            return mix(self.color, self.color2, clamp(t, 0.0, 1.0));
+        }
+        
+        fn pixel(self) -> vec4 {
+            let p_min = self.v_pixel.xy - 0.5;
+            let p_max = self.v_pixel.xy + 0.5;
+            let coverage = clamp(abs(self.compute_clamped_trapezoid_area(p_min, p_max)), 0.0, 1.0);
+            let color = self.get_color();
+            return vec4(color.rgb * color.a, color.a) * coverage;
+        }
+        
+        fn vertex(self) -> vec4 {
+            // the trapezoids are in device pixels relative to rect_pos
+            let pos_min = vec2(self.a_xs.x, min(self.a_ys.x, self.a_ys.y));
+            let pos_max = vec2(self.a_xs.y, max(self.a_ys.z, self.a_ys.w));
+            let pos = mix(pos_min - 1.0, pos_max + 1.0, self.geom_pos);
+            let world = clamp(
+                pos / self.dpi_factor + self.rect_pos,
+                self.draw_clip.xy,
+                self.draw_clip.zw
+            );
+            
+            // set the varyings
+            self.v_p0 = vec2(self.a_xs.x, self.a_ys.x);
+            self.v_p1 = vec2(self.a_xs.y, self.a_ys.y);
+            self.v_p2 = vec2(self.a_xs.x, self.a_ys.z);
+            self.v_p3 = vec2(self.a_xs.y, self.a_ys.w);
+            self.v_pixel = (world - self.rect_pos) * self.dpi_factor;
+            self.v_world = world;
+            return self.camera_projection * (self.camera_view * (self.view_transform * vec4(
+                world.x,
+                world.y,
+                self.draw_depth + self.draw_zbias,
+                1.
+            )));
+        }
+    }
+}
+
+/// Immediate mode drawing of filled and stroked vector paths.
+///
+/// A path is built with `begin_path`, `move_to`, `line_to`, `quadratic_to`, `cubic_to` and `close`
+/// in absolute coordinates, which are mapped through the current transform. Calling `fill` or
+/// `stroke` turns the path into antialiased trapezoids; the path is kept, so it can be filled and
+/// then stroked.
+#[derive(Live)]
+#[repr(C)]
+pub struct DrawPath {
+    #[rust] pub trapezoidator: Trapezoidator,
+    #[rust] pub stroker: Stroker,
+    #[rust] pub path: Path,
+    #[rust] line_path: LinePath,
+    #[rust(AffineTransformation::identity())] transform: AffineTransformation,
+    #[rust] transform_stack: Vec<AffineTransformation>,
+    #[live] pub geometry: GeometryQuad2D,
+    #[deref] pub draw_vars: DrawVars,
+    #[calc] pub rect_pos: Vec2,
+    #[calc] pub draw_clip: Vec4,
+    #[calc] pub a_xs: Vec2,
+    #[calc] pub a_ys: Vec4,
+    #[calc] pub dpi_factor: f32,
+    #[calc] pub gradient_kind: f32,
+    #[calc] pub gradient_start: Vec2,
+    #[calc] pub gradient_end: Vec2,
+    #[live] pub color: Vec4,
+    #[live] pub color2: Vec4,
+    #[live(1.0)] pub draw_depth: f32,
+}
+
+impl LiveHook for DrawPath{
+    fn before_apply(&mut self, cx: &mut Cx, apply_from: ApplyFrom, index: usize, nodes: &[LiveNode]){
+        self.draw_vars.before_apply_init_shader(cx, apply_from, index, nodes, &self.geometry);
+    }
+    fn after_apply(&mut self, cx: &mut Cx, apply_from: ApplyFrom, index: usize, nodes: &[LiveNode]) {
+        self.draw_vars.after_apply_update_self(cx, apply_from, index, nodes, &self.geometry);
+    }
+}
+
+// the gradient_kind instance values
+const GRADIENT_NONE: f32 = 0.0;
+const GRADIENT_LINEAR: f32 = 1.0;
+const GRADIENT_RADIAL: f32 = 2.0;
+
+impl DrawPath {
+    pub fn begin_path(&mut self) {
+        self.path.clear();
+    }
+    
+    pub fn move_to(&mut self, p: DVec2) {
+        self.path.move_to(to_point(p));
+    }
+    
+    pub fn line_to(&mut self, p: DVec2) {
+        self.path.line_to(to_point(p));
+    }
+    
+    pub fn quadratic_to(&mut self, p1: DVec2, p: DVec2) {
+        self.path.quadratic_to(to_point(p1), to_point(p));
+    }
+    
+    pub fn cubic_to(&mut self, p1: DVec2, p2: DVec2, p: DVec2) {
+        self.path.cubic_to(to_point(p1), to_point(p2), to_point(p));
+    }
+    
+    pub fn close(&mut self) {
+        self.path.close();
+    }
+    
+    pub fn rect(&mut self, rect: Rect) {
+        self.move_to(rect.pos);
+        self.line_to(dvec2(rect.pos.x + rect.size.x, rect.pos.y));
+        self.line_to(rect.pos + rect.size);
+        self.line_to(dvec2(rect.pos.x, rect.pos.y + rect.size.y));
+        self.close();
+    }
+    
+    pub fn circle(&mut self, center: DVec2, radius: f64) {
+        self.ellipse(center, dvec2(radius, radius));
+    }
+    
+    pub fn ellipse(&mut self, center: DVec2, radius: DVec2) {
+        // cubic approximation of a quarter circle
+        const K: f64 = 0.552_284_749_831;
+        let k = radius * K;
+        self.move_to(dvec2(center.x + radius.x, center.y));
+        self.cubic_to(
+            dvec2(center.x + radius.x, center.y + k.y),
+            dvec2(center.x + k.x, center.y + radius.y),
+            dvec2(center.x, center.y + radius.y)
+        );
+        self.cubic_to(
+            dvec2(center.x - k.x, center.y + radius.y),
+            dvec2(center.x - radius.x, center.y + k.y),
+            dvec2(center.x - radius.x, center.y)
+        );
+        self.cubic_to(
+            dvec2(center.x - radius.x, center.y - k.y),
+            dvec2(center.x - k.x, center.y - radius.y),
+            dvec2(center.x, center.y - radius.y)
+        );
+        self.cubic_to(
+            dvec2(center.x + k.x, center.y - radius.y),
+            dvec2(center.x + radius.x, center.y - k.y),
+            dvec2(center.x + radius.x, center.y)
+        );
+        self.close();
+    }
+    
+    pub fn set_color(&mut self, color: Vec4) {
+        self.color = color;
+        self.gradient_kind = GRADIENT_NONE;
+    }
+    
+    /// Fills with a gradient from `color` at `start` to `color2` at `end`. The points are in path
+    /// coordinates and are mapped through the transform that is current at the time of drawing.
+    pub fn set_linear_gradient(&mut self, start: DVec2, color: Vec4, end: DVec2, color2: Vec4) {
+        self.set_gradient(GRADIENT_LINEAR, start, color, end, color2);
+    }
+    
+    /// Fills with a gradient from `color` at `center` to `color2` at `radius` away from it.
+    pub fn set_radial_gradient(&mut self, center: DVec2, color: Vec4, radius: f64, color2: Vec4) {
+        self.set_gradient(GRADIENT_RADIAL, center, color, center + dvec2(radius, 0.0), color2);
+    }
+    
+    fn set_gradient(&mut self, kind: f32, start: DVec2, color: Vec4, end: DVec2, color2: Vec4) {
+        self.gradient_kind = kind;
+        self.gradient_start = start.into();
+        self.gradient_end = end.into();
+        self.color = color;
+        self.color2 = color2;
+    }
+    
+    pub fn reset_transform(&mut self) {
+        self.transform = AffineTransformation::identity();
+    }
+    
+    pub fn set_transform(&mut self, transform: AffineTransformation) {
+        self.transform = transform;
+    }
+    
+    pub fn transform(&self) -> AffineTransformation {
+        self.transform
+    }
+    
+    pub fn push_transform(&mut self) {
+        self.transform_stack.push(self.transform);
+    }
+    
+    pub fn pop_transform(&mut self) {
+        if let Some(transform) = self.transform_stack.pop() {
+            self.transform = transform;
+        }
+    }
+    
+    pub fn translate(&mut self, offset: DVec2) {
+        self.apply_transform(AffineTransformation::translation(Vector::new(offset.x, offset.y)));
+    }
+    
+    pub fn scale(&mut self, scale: DVec2) {
+        self.apply_transform(AffineTransformation::scaling(Vector::new(scale.x, scale.y)));
+    }
+    
+    /// Rotates subsequent drawing clockwise by `angle` radians (y points down).
+    pub fn rotate(&mut self, angle: f64) {
+        let (sin, cos) = angle.sin_cos();
+        self.apply_transform(AffineTransformation::new(
+            LinearTransformation::new(Vector::new(cos, sin), Vector::new(-sin, cos)),
+            Vector::zero()
+        ));
+    }
+    
+    fn apply_transform(&mut self, transform: AffineTransformation) {
+        self.transform = self.transform.compose(transform);
+    }
+    
+    /// Fills the current path with the given fill rule.
+    pub fn fill(&mut self, cx: &mut Cx2d, fill_rule: FillRule) {
+        let dpi_factor = cx.current_dpi_factor();
+        let transform = self.device_transform(dpi_factor);
+        self.trapezoidator.set_fill_rule(fill_rule);
+        self.trapezoidator.set_split_intersections(true);
+        let mut trapezoids = Vec::new();
+        if let Some(trapezoidate) = self.trapezoidator.trapezoidate(
+            Iterator::map(self.path.commands(), |command| command.transform(&transform)).linearize(0.1)
+        ) {
+            trapezoids.extend_from_internal_iter(trapezoidate);
+        }
+        self.draw_trapezoids(cx, dpi_factor, &trapezoids);
+    }
+    
+    /// Strokes the current path. The stroke width and dash lengths are in path coordinates, so they
+    /// scale with the transform.
+    pub fn stroke(&mut self, cx: &mut Cx2d, style: &StrokeStyle) {
+        let dpi_factor = cx.current_dpi_factor();
+        let transform = self.device_transform(dpi_factor);
+        // stroke in path space with a tolerance of a tenth of a device pixel
+        let scale = (transform.xy.x.cross(transform.xy.y)).abs().sqrt().max(1e-6);
+        let epsilon = 0.1 / scale;
+        self.line_path.clear();
+        self.stroker.stroke(self.path.commands().linearize(epsilon), style, epsilon, &mut self.line_path);
+        self.trapezoidator.set_fill_rule(FillRule::NonZero);
+        // the outline of a stroke overlaps itself at joins and tight curves
+        self.trapezoidator.set_split_intersections(true);
+        let mut trapezoids = Vec::new();
+        if let Some(trapezoidate) = self.trapezoidator.trapezoidate(
+            Iterator::map(self.line_path.commands(), |command| command.transform(&transform))
+        ) {
+            trapezoids.extend_from_internal_iter(trapezoidate);
+        }
+        self.draw_trapezoids(cx, dpi_factor, &trapezoids);
+    }
+    
+    /// Draws a parsed SVG document, fitting its view box into `rect` while keeping the aspect
+    /// ratio. The current transform applies on top, and the path and color are reset afterwards.
+    pub fn draw_svg(&mut self, cx: &mut Cx2d, document: &Document, rect: Rect) {
+        let saved_transform = self.transform;
+        let base = self.transform
+            .compose(AffineTransformation::translation(Vector::new(rect.pos.x, rect.pos.y)))
+            .compose(document.view_box_transform(Vector::new(rect.size.x, rect.size.y)));
+        for shape in &document.shapes {
+            self.transform = base.compose(shape.transform);
+            self.path.clone_from(&shape.path);
+            if let Some(paint) = &shape.fill {
+                self.set_paint(paint);
+                self.fill(cx, shape.fill_rule);
+            }
+            if let Some(paint) = &shape.stroke {
+                self.set_paint(paint);
+                self.stroke(cx, &shape.stroke_style);
+            }
+        }
+        self.transform = saved_transform;
+        self.path.clear();
+        self.gradient_kind = GRADIENT_NONE;
+    }
+    
+    // the shader interpolates between two colors, so multi stop gradients use their outer stops
+    fn set_paint(&mut self, paint: &Paint) {
+        fn to_vec4(color: makepad_vector::svg::Color) -> Vec4 {
+            vec4(color.r, color.g, color.b, color.a)
+        }
+        match paint {
+            Paint::Color(color) => self.set_color(to_vec4(*color)),
+            Paint::LinearGradient {start, end, stops} => {
+                let (first, last) = (stops[0], stops[stops.len() - 1]);
+                let delta = *end - *start;
+                let p0 = *start + delta * first.offset;
+                let p1 = *start + delta * last.offset;
+                self.set_linear_gradient(
+                    dvec2(p0.x, p0.y),
+                    to_vec4(first.color),
+                    dvec2(p1.x, p1.y),
+                    to_vec4(last.color)
+                );
+            }
+            Paint::RadialGradient {center, radius, stops} => {
+                let (first, last) = (stops[0], stops[stops.len() - 1]);
+                self.set_radial_gradient(
+                    dvec2(center.x, center.y),
+                    to_vec4(first.color),
+                    radius * last.offset,
+                    to_vec4(last.color)
+                );
+            }
+        }
+    }
+    
+    fn device_transform(&self, dpi_factor: f64) -> AffineTransformation {
+        AffineTransformation::uniform_scaling(dpi_factor).compose(self.transform)
+    }
+    
+    fn draw_trapezoids(&mut self, cx: &mut Cx2d, dpi_factor: f64, trapezoids: &[Trapezoid]) {
+        if trapezoids.is_empty() {
+            return
+        }
+        let gradient = (self.gradient_start, self.gradient_end);
+        if self.gradient_kind != GRADIENT_NONE {
+            let start = self.transform.transform_point(Point::new(gradient.0.x as f64, gradient.0.y as f64));
+            let end = if self.gradient_kind == GRADIENT_RADIAL {
+                // keep the radius as the transformed length along x
+                let radius = self.transform.transform_vector(Vector::new((gradient.1.x - gradient.0.x) as f64, 0.0)).length();
+                start + Vector::new(radius, 0.0)
+            }
+            else {
+                self.transform.transform_point(Point::new(gradient.1.x as f64, gradient.1.y as f64))
+            };
+            self.gradient_start = vec2(start.x as f32, start.y as f32);
+            self.gradient_end = vec2(end.x as f32, end.y as f32);
+        }
+        self.rect_pos = vec2(0.0, 0.0);
+        self.dpi_factor = dpi_factor as f32;
+        if let Some(mut mi) = cx.begin_many_aligned_instances(&self.draw_vars) {
+            for trapezoid in trapezoids {
+                self.a_xs = Vec2 {x: trapezoid.xs[0], y: trapezoid.xs[1]};
+                self.a_ys = Vec4 {x: trapezoid.ys[0], y: trapezoid.ys[1], z: trapezoid.ys[2], w: trapezoid.ys[3]};
+                mi.instances.extend_from_slice(self.draw_vars.as_slice());
+            }
+            let new_area = cx.end_many_instances(mi);
+            self.draw_vars.area = cx.update_area_refs(self.draw_vars.area, new_area);
+        }
+        self.gradient_start = gradient.0;
+        self.gradient_end = gradient.1;
+    }
+}
+
+fn to_point(p: DVec2) -> Point {
+    Point::new(p.x, p.y)
+}
+
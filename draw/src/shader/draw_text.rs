@@ -2,7 +2,8 @@ use {
     crate::{
         makepad_platform::*,
         turtle::{Walk, Size, Align},
-        font_atlas::{CxFontsAtlasTodo, CxFont, CxFontsAtlas, Font},
+        font_atlas::{CxFontsAtlasTodo, CxFontsAtlas, Font},
+        sdf_glyph::{rasterize_sdf_glyph, SDF_ATLAS_SIZE, SDF_PIXELS_PER_EM, SDF_RANGE},
         draw_list_2d::ManyInstances,
         geometry::GeometryQuad2D,
         cx_2d::Cx2d
@@ -18,8 +19,12 @@ live_design!{
         
         uniform brightness: float
         uniform curve: float
+        uniform glyph_atlas: float
+        uniform sdf_range: float
         
         texture tex: texture2d
+        texture color_tex: texture2d
+        texture sdf_tex: texture2d
         
         varying tex_coord1: vec2
         varying tex_coord2: vec2
@@ -70,6 +75,27 @@ live_design!{
         }
         
         fn pixel(self) -> vec4 {
+            // colour glyphs such as emoji keep their own colours, only taking the text alpha
+            if self.color_glyph > 0.5 {
+                let c = sample2d(self.color_tex, self.tex_coord1.xy);
+                let a = c.a * self.get_color().a;
+                return vec4(c.rgb * a, a);
+            }
+            
+            // distance fields hold the distance to the outline, mapped so 0.5 is on it and
+            // sdf_range is the range of the whole field in texture coordinates
+            if self.glyph_atlas > 0.5 {
+                let d = sample2d(self.sdf_tex, self.tex_coord1.xy);
+                let dist = d.a;
+                if self.glyph_atlas > 1.5 {
+                    dist = max(min(d.r, d.g), min(max(d.r, d.g), d.b));
+                }
+                let texels_per_pixel = abs(dFdx(self.tex_coord1.xy)) + abs(dFdy(self.tex_coord1.xy));
+                let screen_range = max(self.sdf_range / max(texels_per_pixel.x, texels_per_pixel.y), 1.0);
+                let coverage = clamp((dist - 0.5) * screen_range + 0.5, 0.0, 1.0);
+                let text_color = self.get_color();
+                return vec4(coverage * text_color.rgb * self.brightness * text_color.a, coverage * text_color.a);
+            }
             
             let dx = dFdx(vec2(self.tex_coord1.x * 2048.0, 0.)).x;
             let dp = 1.0 / 2048.0;
@@ -116,6 +142,19 @@ pub struct TextStyle {
     #[live(1.4)] pub line_spacing: f64,
     #[live(1.1)] pub top_drop: f64,
     #[live(1.3)] pub height_factor: f64,
+    #[live] pub glyph_atlas: GlyphAtlas,
+}
+
+// How the glyphs of a text style are rasterized. Coverage glyphs are rasterized for every font
+// size, dpi factor and subpixel offset they are drawn at, and are the sharpest for small static
+// text. Distance field glyphs are rasterized once per glyph and stay crisp at any scale, for
+// zooming, scaled and 3D text. Msdf keeps the corners sharp where Sdf rounds them off.
+#[derive(Clone, Copy, Live, LiveHook, PartialEq)]
+#[live_ignore]
+pub enum GlyphAtlas {
+    #[pick] Coverage,
+    Sdf,
+    Msdf
 }
 
 #[derive(Clone, Live, LiveHook)]
@@ -157,7 +196,7 @@ impl<'a> WordIterator<'a> {
             font_size_total
         }
     }
-    fn next_word(&mut self, font: &mut CxFont) -> Option<WordIteratorItem> {
+    fn next_word(&mut self, fonts_atlas: &mut CxFontsAtlas, font: &Font) -> Option<WordIteratorItem> {
         if let Some(char_iter) = &mut self.char_iter {
             while let Some((i, c)) = char_iter.next() {
                 self.last_index = i;
@@ -169,18 +208,19 @@ impl<'a> WordIterator<'a> {
                     with_newline: false
                 };
                 
-                let adv = if let Some(glyph) = font.get_glyph(c) {
-                    glyph.horizontal_metrics.advance_width * self.font_size_total
+                let adv = if let Some(advance) = fonts_atlas.get_char_advance(font, c) {
+                    advance * self.font_size_total
                 }else {0.0};
                 
                 if c == '\r' {
                     continue;
                 }
                 if c == '\n' {
+                    // the newline stays with the line it ends, so every char keeps a glyph on the line it belongs to
                     self.last_is_whitespace = false;
-                    self.word_start = i;
+                    self.word_start = i + 1;
                     self.word_width = 0.0;
-                    return Some(WordIteratorItem {with_newline: true, end: i, ..ret})
+                    return Some(WordIteratorItem {with_newline: true, end: i + 1, ..ret})
                 }
                 else if c.is_whitespace() { // we only return words where whitespace turns to word
                     self.last_is_whitespace = true;
@@ -216,6 +256,23 @@ impl<'a> WordIterator<'a> {
         }
     }
 }
+// Characters that belong to the grapheme of the character before them: combining marks, the
+// zero width joiner, variation selectors, skin tone modifiers and emoji tag sequences.
+fn is_cluster_continuation(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}'
+        | '\u{E0100}'..='\u{E01EF}'
+    )
+}
+
 /*
 #[derive(Debug, Clone, Copy, Live, LiveHook)]
 pub enum Overflow {
@@ -224,6 +281,216 @@ pub enum Overflow {
     #[live] None
 }*/
 
+// The box of a char of the text as drawn, from its advance and the y of its line.
+#[derive(Clone, Copy)]
+struct CharBox {
+    left: f64,
+    right: f64,
+    y: f64,
+    rtl: bool,
+}
+
+impl CharBox {
+    // the edge the text flows in from, which is where the cursor goes for the char
+    fn leading_edge(&self) -> f64 {
+        if self.rtl {self.right} else {self.left}
+    }
+    
+    fn trailing_edge(&self) -> f64 {
+        if self.rtl {self.left} else {self.right}
+    }
+}
+
+// The char boxes of a drawn text in logical order, with the line spacing it was drawn with.
+pub struct CharBoxes {
+    boxes: Vec<CharBox>,
+    line_spacing: f64,
+    // an empty text that was drawn has no boxes but still a place for the cursor
+    drawn: bool,
+}
+
+impl CharBoxes {
+    // the logical range of the chars on the line of the char at index, char boxes are in logical
+    // order and a line is a run of them at the same y
+    fn line_of(&self, index: usize) -> std::ops::Range<usize> {
+        let boxes = &self.boxes;
+        let index = index.min(boxes.len() - 1);
+        let same_line = | other: &CharBox | (other.y - boxes[index].y).abs() < self.line_spacing * 0.5;
+        let start = boxes[..index].iter().rposition( | other | !same_line(other)).map_or(0, | i | i + 1);
+        let end = boxes[index..].iter().position( | other | !same_line(other)).map_or(boxes.len(), | i | index + i);
+        start..end
+    }
+    
+    // the places the cursor can go on a line, with the char index they put it at. The end of a line
+    // that wraps is the start of the next one, so it isn't a place on this line.
+    fn cursor_stops(&self, line: std::ops::Range<usize>) -> Vec<(f64, usize)> {
+        let mut stops = Vec::new();
+        for index in line.clone() {
+            stops.push((self.boxes[index].leading_edge(), index));
+            if index + 1 < line.end || line.end == self.boxes.len() {
+                stops.push((self.boxes[index].trailing_edge(), index + 1));
+            }
+        }
+        stops
+    }
+    
+    // The x of the cursor at index. Inside a run it is the edge of the char at index the text
+    // flows from, at the end of the text the trailing edge of the last char.
+    fn cursor_x(&self, index: usize) -> f64 {
+        if index < self.boxes.len() {
+            self.boxes[index].leading_edge()
+        }
+        else {
+            self.boxes[self.boxes.len() - 1].trailing_edge()
+        }
+    }
+    
+    // The stop nearest to x that passes filter. Where the chars of two runs of different
+    // direction meet, two stops have the same x and we prefer the one the cursor is drawn at.
+    fn nearest_stop(&self, stops: &[(f64, usize)], x: f64, filter: impl Fn(f64) -> bool) -> Option<usize> {
+        let nearest = stops.iter()
+            .filter( | (stop_x, _) | filter(*stop_x))
+            .map( | (stop_x, _) | (stop_x - x).abs())
+            .fold(None, | nearest: Option<f64>, distance | Some(nearest.map_or(distance, | nearest | nearest.min(distance))))?;
+        let candidates: Vec<&(f64, usize)> = stops.iter()
+            .filter( | (stop_x, _) | filter(*stop_x) && (stop_x - x).abs() - nearest < 0.5)
+            .collect();
+        candidates.iter()
+            .find( | (stop_x, index) | (self.cursor_x(*index) - stop_x).abs() < 0.5)
+            .or(candidates.first())
+            .map( | (_, index) | *index)
+    }
+    
+    pub fn closest_offset(&self, pos: DVec2) -> Option<usize> {
+        if self.boxes.is_empty() {
+            return if self.drawn {Some(0)} else {None}
+        }
+        
+        let mut index = 0;
+        loop {
+            let line = self.line_of(index);
+            if pos.y < self.boxes[line.start].y + self.line_spacing {
+                let stops = self.cursor_stops(line);
+                return self.nearest_stop(&stops, pos.x, | _ | true)
+            }
+            if line.end == self.boxes.len() {
+                return Some(self.boxes.len())
+            }
+            index = line.end;
+        }
+    }
+    
+    pub fn selection_rects(&self, start: usize, end: usize, shift: DVec2, pad: DVec2) -> Vec<Rect> {
+        let boxes = &self.boxes;
+        let mut out = Vec::new();
+        
+        // the selected chars of a line are contiguous in logical order, but in bidi text not on
+        // screen, so every line gets a rect for each visual stretch of selected chars
+        let mut index = start;
+        while index < end.min(boxes.len()) {
+            let line = self.line_of(index);
+            let mut spans: Vec<(f64, f64)> = boxes[index..line.end.min(end)].iter().map( | b | (b.left, b.right)).collect();
+            spans.sort_by( | a, b | a.0.total_cmp(&b.0));
+            let mut merged: Vec<(f64, f64)> = Vec::new();
+            for (left, right) in spans {
+                match merged.last_mut() {
+                    Some(last) if left - last.1 < 0.5 => last.1 = last.1.max(right),
+                    _ => merged.push((left, right))
+                }
+            }
+            for (left, right) in merged {
+                out.push(Rect {
+                    pos: dvec2(left, boxes[index].y) + shift,
+                    size: dvec2(right - left, self.line_spacing) + pad
+                });
+            }
+            index = line.end;
+        }
+        out
+    }
+    
+    pub fn cursor_pos(&self, pos: f32, index: usize) -> Option<DVec2> {
+        let boxes = &self.boxes;
+        if boxes.is_empty() {
+            return None
+        }
+        if index >= boxes.len() {
+            let last = &boxes[boxes.len() - 1];
+            Some(dvec2(last.trailing_edge(), last.y))
+        }
+        else {
+            // pos goes through the char in the direction of its run
+            let char_box = &boxes[index];
+            let offset = (char_box.right - char_box.left) * pos as f64;
+            let x = if char_box.rtl {char_box.right - offset} else {char_box.left + offset};
+            Some(dvec2(x, char_box.y))
+        }
+    }
+    
+    // Where the runs of the chars before and after index have a different direction, the
+    // trailing edge of the char before it is somewhere else than the cursor. This returns that
+    // second position, for drawing a split cursor.
+    pub fn split_cursor_pos(&self, index: usize) -> Option<DVec2> {
+        if index == 0 || index >= self.boxes.len() {
+            return None
+        }
+        let (before, after) = (&self.boxes[index - 1], &self.boxes[index]);
+        if before.rtl == after.rtl
+            || (before.y - after.y).abs() >= self.line_spacing * 0.5
+            || (before.trailing_edge() - after.leading_edge()).abs() < 0.5 {
+            return None
+        }
+        Some(dvec2(before.trailing_edge(), before.y))
+    }
+    
+    // The first char index of the line the cursor at index is on, and the index at its logical
+    // end, which is before the space or newline it wraps at.
+    pub fn line_edges(&self, index: usize) -> Option<(usize, usize)> {
+        if self.boxes.is_empty() {
+            return None
+        }
+        let line = self.line_of(index);
+        let end = if line.end == self.boxes.len() {line.end} else {line.end - 1};
+        Some((line.start, end))
+    }
+    
+    // Moves the cursor at index one place left or right on screen, which in a right to left run
+    // moves it through the text backwards. Past the end of a line it goes on to the next or
+    // previous line, following the text.
+    pub fn visual_cursor_move(&self, index: usize, right: bool) -> Option<usize> {
+        if self.boxes.is_empty() {
+            return None
+        }
+        let index = index.min(self.boxes.len());
+        let line = self.line_of(index);
+        let x = self.cursor_x(index);
+        // only the stops the cursor is drawn at, a stop that is just the second half of a split
+        // cursor would put it back on the other side and the next key press back here
+        let stops: Vec<(f64, usize)> = self.cursor_stops(line.clone()).into_iter()
+            .filter( | (stop_x, index) | (self.cursor_x(*index) - stop_x).abs() < 0.5)
+            .collect();
+        let next = if right {
+            self.nearest_stop(&stops, x, | stop_x | stop_x > x + 0.5)
+        }
+        else {
+            self.nearest_stop(&stops, x, | stop_x | stop_x < x - 0.5)
+        };
+        if next.is_some() {
+            return next
+        }
+        let end = if line.end == self.boxes.len() {line.end} else {line.end - 1};
+        if index == line.start && line.start > 0 {
+            Some(index - 1)
+        }
+        else if index == end && line.end < self.boxes.len() {
+            Some(index + 1)
+        }
+        else {
+            Some(index)
+        }
+    }
+}
+
 pub struct TextGeom {
     pub eval_width: f64,
     pub eval_height: f64,
@@ -236,6 +503,8 @@ pub struct TextGeom {
 #[repr(C)]
 pub struct DrawText {
     #[rust] pub many_instances: Option<ManyInstances>,
+    // the number of chars in the text of the last draw
+    #[rust] char_count: usize,
     
     #[live] pub geometry: GeometryQuad2D,
     #[live] pub text_style: TextStyle,
@@ -255,6 +524,11 @@ pub struct DrawText {
     #[calc] pub delta: Vec2,
     #[calc] pub font_size: f32,
     #[calc] pub advance: f32,
+    #[calc] pub color_glyph: f32,
+    // the index of the first char the glyph was shaped from, and whether it is in a right to
+    // left run, for the cursor functions which work in the logical order of the text
+    #[calc] pub char_index: f32,
+    #[calc] pub rtl: f32,
 }
 
 impl LiveHook for DrawText {
@@ -269,21 +543,24 @@ impl LiveHook for DrawText {
 impl DrawText {
     
     pub fn draw(&mut self, cx: &mut Cx2d, pos: DVec2, val: &str) {
-        self.draw_inner(cx, pos, val, &mut *cx.fonts_atlas_rc.clone().0.borrow_mut());
+        self.char_count = val.chars().count();
+        self.draw_inner(cx, pos, val, 0, &mut *cx.fonts_atlas_rc.clone().0.borrow_mut());
         if self.many_instances.is_some() {
             self.end_many_instances(cx)
         }
     }
     
     pub fn draw_rel(&mut self, cx: &mut Cx2d, pos: DVec2, val: &str) {
-        self.draw_inner(cx, pos + cx.turtle().origin(), val, &mut *cx.fonts_atlas_rc.clone().0.borrow_mut());
+        self.char_count = val.chars().count();
+        self.draw_inner(cx, pos + cx.turtle().origin(), val, 0, &mut *cx.fonts_atlas_rc.clone().0.borrow_mut());
         if self.many_instances.is_some() {
             self.end_many_instances(cx)
         }
     }
     
     pub fn draw_abs(&mut self, cx: &mut Cx2d, pos: DVec2, val: &str) {
-        self.draw_inner(cx, pos, val, &mut *cx.fonts_atlas_rc.clone().0.borrow_mut());
+        self.char_count = val.chars().count();
+        self.draw_inner(cx, pos, val, 0, &mut *cx.fonts_atlas_rc.clone().0.borrow_mut());
         if self.many_instances.is_some() {
             self.end_many_instances(cx)
         }
@@ -314,29 +591,62 @@ impl DrawText {
     
     pub fn update_draw_call_vars(&mut self, font_atlas: &CxFontsAtlas) {
         self.draw_vars.texture_slots[0] = Some(font_atlas.texture_id);
+        self.draw_vars.texture_slots[1] = Some(font_atlas.color_atlas.texture_id);
+        self.draw_vars.texture_slots[2] = Some(font_atlas.sdf_atlas.texture_id);
         self.draw_vars.user_uniforms[0] = self.text_style.brightness;
         self.draw_vars.user_uniforms[1] = self.text_style.curve;
+        self.draw_vars.user_uniforms[2] = match self.text_style.glyph_atlas {
+            GlyphAtlas::Coverage => 0.0,
+            GlyphAtlas::Sdf => 1.0,
+            GlyphAtlas::Msdf => 2.0,
+        };
+        self.draw_vars.user_uniforms[3] = (2.0 * SDF_RANGE / SDF_ATLAS_SIZE) as f32;
     }
     
-    fn draw_inner(&mut self, cx: &mut Cx2d, pos: DVec2, chunk: &str, fonts_atlas: &mut CxFontsAtlas) {
+    // Draws text on one line, its bidi paragraphs one after the other. char_index is the index of
+    // its first char in the text the cursor functions work with.
+    fn draw_inner(&mut self, cx: &mut Cx2d, pos: DVec2, chunk: &str, char_index: usize, fonts_atlas: &mut CxFontsAtlas) {
+        let bidi_info = unicode_bidi::BidiInfo::new(chunk, None);
+        let mut pos = pos;
+        let mut char_index = char_index;
+        for para in &bidi_info.paragraphs {
+            pos.x = self.draw_line(cx, pos, &bidi_info, para, para.range.clone(), char_index, fonts_atlas);
+            char_index += chunk[para.range.clone()].chars().count();
+        }
+    }
+    
+    // Draws the line at line_range of the text of bidi_info, in the visual order of paragraph
+    // para that it is part of, and returns the x it ends at. line_char_index is the index of its
+    // first char in the text the cursor functions work with.
+    fn draw_line(
+        &mut self,
+        cx: &mut Cx2d,
+        pos: DVec2,
+        bidi_info: &unicode_bidi::BidiInfo,
+        para: &unicode_bidi::ParagraphInfo,
+        line_range: std::ops::Range<usize>,
+        line_char_index: usize,
+        fonts_atlas: &mut CxFontsAtlas
+    ) -> f64 {
         if !self.draw_vars.can_instance()
             || pos.x.is_nan()
             || pos.y.is_nan()
+            || line_range.is_empty()
             || self.text_style.font.font_id.is_none() {
-            return
+            return pos.x
         }
         //self.draw_clip = cx.turtle().draw_clip().into();
         //let in_many = self.many_instances.is_some();
         let font_id = self.text_style.font.font_id.unwrap();
         
         if fonts_atlas.fonts[font_id].is_none() {
-            return
+            return pos.x
         }
         
         //cx.debug.rect_r(Rect{pos:dvec2(1.0,2.0), size:dvec2(200.0,300.0)});
         let mut walk_x = pos.x;
         if walk_x.is_infinite() || walk_x.is_nan() {
-            return
+            return pos.x
         }
         //let mut char_offset = char_offset;
         
@@ -344,63 +654,181 @@ impl DrawText {
             self.begin_many_instances_internal(cx, fonts_atlas);
         }
         
-        let cxfont = fonts_atlas.fonts[font_id].as_mut().unwrap();
         let dpi_factor = cx.current_dpi_factor();
         
-        let atlas_page_id = cxfont.get_atlas_page_id(dpi_factor, self.text_style.font_size);
-        
-        let font = &mut cxfont.ttf_font;
-        let owned_font_face = &cxfont.owned_font_face;
-        
-        let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * font.units_per_em);
-        let font_size_pixels = font_size_logical * dpi_factor;
-        
-        let atlas_page = &mut cxfont.atlas_pages[atlas_page_id];
-        
-        let mi = if let Some(mi) = &mut self.many_instances {mi} else {return};
+        let mi = if let Some(mi) = &mut self.many_instances {mi} else {return pos.x};
         let zbias_step = 0.00001;
         let mut char_depth = self.draw_depth;
         
         let mut rustybuzz_buffer = rustybuzz::UnicodeBuffer::new();
         
+        // the byte offsets of the chars of the line, to find the char a glyph was shaped from
+        let char_starts: Vec<usize> = bidi_info.text[line_range.clone()]
+            .char_indices()
+            .map( | (index, _) | line_range.start + index)
+            .collect();
+        
         // This relies on the UBA ("Unicode Bidirectional Algorithm")
         // (see http://www.unicode.org/reports/tr9/#Basic_Display_Algorithm),
-        // as implemented by `unicode_bidi`, to slice the text into substrings
-        // that can be individually shaped, then assembled visually.
-        let bidi_info = unicode_bidi::BidiInfo::new(chunk, None);
+        // as implemented by `unicode_bidi`, to slice the line into substrings
+        // that can be individually shaped, then assembled visually. The levels are
+        // those of the whole paragraph, so a wrapped line keeps its paragraph direction.
+        let (adjusted_levels, runs) = bidi_info.visual_runs(para, line_range.clone());
         
-        // NOTE(eddyb) the caller of `draw_inner` has already processed the text,
-        // such that `chunk` won't contain e.g. any `\n`.
-        if bidi_info.paragraphs.len() == 1 {
-            let runs_with_level_and_range = {
-                let para = &bidi_info.paragraphs[0];
-                // Split `chunk` into "runs" (that differ in their LTR/RTL "level").
-                let (adjusted_levels, runs) = bidi_info.visual_runs(para, para.range.clone());
-                runs.into_iter().map(move | run_range | (adjusted_levels[run_range.start], run_range))
+        for run_range in runs {
+            let run_level = adjusted_levels[run_range.start];
+            // FIXME(eddyb) UBA/`unicode_bidi` only offers a LTR/RTL distinction,
+            // even if `rustybuzz` has vertical `Direction`s as well.
+            let direction = if run_level.is_rtl() {
+                rustybuzz::Direction::RightToLeft
+            } else {
+                rustybuzz::Direction::LeftToRight
             };
             
-            for (run_level, run_range) in runs_with_level_and_range {
-                // FIXME(eddyb) UBA/`unicode_bidi` only offers a LTR/RTL distinction,
-                // even if `rustybuzz` has vertical `Direction`s as well.
+            // Split the run into pieces that are each drawn by one font of the fallback
+            // chain, keeping combining marks and emoji sequences with the character they
+            // belong to so they shape together.
+            let mut pieces: Vec<(usize, std::ops::Range<usize>)> = Vec::new();
+            for (index, c) in bidi_info.text[run_range.clone()].char_indices() {
+                let start = run_range.start + index;
+                let end = start + c.len_utf8();
+                let piece_font_id = match pieces.last() {
+                    Some((last_font_id, _)) if is_cluster_continuation(c) => *last_font_id,
+                    _ => fonts_atlas.font_for_char(&self.text_style.font, c)
+                };
+                match pieces.last_mut() {
+                    Some((last_font_id, range)) if *last_font_id == piece_font_id => range.end = end,
+                    _ => pieces.push((piece_font_id, start..end))
+                }
+            }
+            // the glyphs of a right to left run come out of shaping in visual order
+            if run_level.is_rtl() {
+                pieces.reverse();
+            }
+                
+            for (piece_font_id, piece_range) in pieces {
+                // line breaks are drawn as spaces, shaping them gives the missing glyph box
+                let piece_text = &bidi_info.text[piece_range.clone()];
+                let piece_text = if piece_text.contains(['\n', '\r']) {
+                    std::borrow::Cow::Owned(piece_text.replace(['\n', '\r'], " "))
+                }
+                else {
+                    std::borrow::Cow::Borrowed(piece_text)
+                };
+                let cxfont = fonts_atlas.fonts[piece_font_id].as_mut().unwrap();
+                // distance field glyphs don't need a page for every size they are drawn at
+                let atlas_page_id = (self.text_style.glyph_atlas == GlyphAtlas::Coverage || cxfont.color_font.is_some())
+                    .then( || cxfont.get_atlas_page_id(dpi_factor, self.text_style.font_size));
+                
+                let font = &mut cxfont.ttf_font;
+                let owned_font_face = &cxfont.owned_font_face;
+                let color_font = &cxfont.color_font;
+                
+                let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * font.units_per_em);
+                let font_size_pixels = font_size_logical * dpi_factor;
+                let units_per_em = font.units_per_em;
+                let pixels_per_em = font_size_pixels * units_per_em;
+                
+                let mut atlas_page = atlas_page_id.map( | atlas_page_id | &mut cxfont.atlas_pages[atlas_page_id]);
+                
                 let (glyph_ids, new_rustybuzz_buffer) = cxfont
                     .shape_cache
                     .get_or_compute_glyph_ids(
-                    (
-                            if run_level.is_rtl() {
-                                rustybuzz::Direction::RightToLeft
-                            } else {
-                                rustybuzz::Direction::LeftToRight
-                            },
-                            &bidi_info.text[run_range]
-                        ),
-                        rustybuzz_buffer,
-                        owned_font_face
-                    );
+                    (direction, &piece_text),
+                    rustybuzz_buffer,
+                    owned_font_face
+                );
                 rustybuzz_buffer = new_rustybuzz_buffer;
-                for &glyph_id in glyph_ids {
+                for shaped_glyph in glyph_ids {
+                    let glyph_id = shaped_glyph.id;
+                    let cluster_start = piece_range.start + shaped_glyph.cluster;
+                    self.char_index = (line_char_index + char_starts.partition_point( | start | *start < cluster_start)) as f32;
+                    self.rtl = if run_level.is_rtl() {1.0} else {0.0};
+                    let advance = owned_font_face.with_ref(|face| font.get_glyph_by_id(face, glyph_id).unwrap())
+                        .horizontal_metrics.advance_width * font_size_logical * self.font_scale;
+                    
+                    // colour glyphs are rasterized once per atlas page, on first use, and a glyph
+                    // over the budget of this frame leaves its space empty until the next one
+                    let color_glyph = if let (Some(color_font), Some(atlas_page)) = (color_font, &mut atlas_page) {
+                        match atlas_page.color_glyphs.get(&glyph_id) {
+                            Some(color_glyph) => *color_glyph,
+                            None if fonts_atlas.glyph_budget.spend() => {
+                                let color_glyph = color_font.rasterize(
+                                    font,
+                                    owned_font_face,
+                                    &mut fonts_atlas.trapezoidator,
+                                    glyph_id,
+                                    pixels_per_em
+                                ).map( | image | fonts_atlas.color_atlas.add_glyph(image));
+                                atlas_page.color_glyphs.insert(glyph_id, color_glyph);
+                                color_glyph
+                            }
+                            None => {
+                                walk_x += advance;
+                                continue;
+                            }
+                        }
+                    }
+                    else {
+                        None
+                    };
+                
                     let glyph = owned_font_face.with_ref(|face| font.get_glyph_by_id(face, glyph_id).unwrap());
+                
+                    if let Some(color_glyph) = color_glyph {
+                        // the image is in pixels, and sampled top down so flip it vertically
+                        let delta_x = color_glyph.x * self.font_scale / dpi_factor;
+                        let delta_y = -color_glyph.y * self.font_scale / dpi_factor + self.text_style.font_size * self.font_scale * self.text_style.top_drop;
+                        self.font_t1 = vec2(color_glyph.tc.t1.x, color_glyph.tc.t2.y);
+                        self.font_t2 = vec2(color_glyph.tc.t2.x, color_glyph.tc.t1.y);
+                        self.rect_pos = dvec2(walk_x + delta_x, pos.y + delta_y).into();
+                        self.rect_size = dvec2(color_glyph.width * self.font_scale / dpi_factor, color_glyph.height * self.font_scale / dpi_factor).into();
+                        self.char_depth = char_depth;
+                        self.delta.x = delta_x as f32;
+                        self.delta.y = delta_y as f32;
+                        self.font_size = self.text_style.font_size as f32;
+                        self.advance = advance as f32;
+                        self.color_glyph = 1.0;
+                        char_depth += zbias_step;
+                        mi.instances.extend_from_slice(self.draw_vars.as_slice());
+                        walk_x += advance;
+                        continue;
+                    }
+                
+                    if self.text_style.glyph_atlas != GlyphAtlas::Coverage {
+                        let sdf_glyph = match cxfont.sdf_glyphs.get(&glyph_id) {
+                            Some(sdf_glyph) => *sdf_glyph,
+                            None if fonts_atlas.glyph_budget.spend() => {
+                                let sdf_glyph = rasterize_sdf_glyph(glyph, units_per_em).map( | image | fonts_atlas.sdf_atlas.add_glyph(image));
+                                cxfont.sdf_glyphs.insert(glyph_id, sdf_glyph);
+                                sdf_glyph
+                            }
+                            None => None
+                        };
+                        if let Some(sdf_glyph) = sdf_glyph {
+                            // the distance field is in pixels of an em of SDF_PIXELS_PER_EM, and sampled top down
+                            let scale = self.text_style.font_size * 96.0 / 72.0 / SDF_PIXELS_PER_EM * self.font_scale;
+                            let delta_x = sdf_glyph.x * scale;
+                            let delta_y = -sdf_glyph.y * scale + self.text_style.font_size * self.font_scale * self.text_style.top_drop;
+                            self.font_t1 = vec2(sdf_glyph.tc.t1.x, sdf_glyph.tc.t2.y);
+                            self.font_t2 = vec2(sdf_glyph.tc.t2.x, sdf_glyph.tc.t1.y);
+                            self.rect_pos = dvec2(walk_x + delta_x, pos.y + delta_y).into();
+                            self.rect_size = dvec2(sdf_glyph.width * scale, sdf_glyph.height * scale).into();
+                            self.char_depth = char_depth;
+                            self.delta.x = delta_x as f32;
+                            self.delta.y = delta_y as f32;
+                            self.font_size = self.text_style.font_size as f32;
+                            self.advance = advance as f32;
+                            self.color_glyph = 0.0;
+                            char_depth += zbias_step;
+                            mi.instances.extend_from_slice(self.draw_vars.as_slice());
+                        }
+                        walk_x += advance;
+                        continue;
+                    }
                     
-                    let advance = glyph.horizontal_metrics.advance_width * font_size_logical * self.font_scale;
+                    let atlas_page_id = atlas_page_id.unwrap();
+                    let atlas_page = atlas_page.as_mut().unwrap();
                     
                     // snap width/height to pixel granularity
                     let w = ((glyph.bounds.p_max.x - glyph.bounds.p_min.x) * font_size_pixels).ceil() + 1.0;
@@ -409,7 +837,7 @@ impl DrawText {
                     // this one needs pixel snapping
                     let min_pos_x = walk_x + font_size_logical * glyph.bounds.p_min.x;
                     let min_pos_y = pos.y - font_size_logical * glyph.bounds.p_min.y + self.text_style.font_size * self.text_style.top_drop;
-                    
+                        
                     // compute subpixel shift
                     let subpixel_x_fract = min_pos_x - (min_pos_x * dpi_factor).floor() / dpi_factor;
                     let subpixel_y_fract = min_pos_y - (min_pos_y * dpi_factor).floor() / dpi_factor;
@@ -422,7 +850,7 @@ impl DrawText {
                         ((subpixel_y_fract * dpi_factor * 7.0) as usize) << 3 |
                         (subpixel_x_fract * dpi_factor * 7.0) as usize
                     };
-                    
+                        
                     let tc = if let Some(tc) = &atlas_page.atlas_glyphs[glyph_id][subpixel_id] {
                         //println!("{} {} {} {}", tc.tx1,tc.tx2,tc.ty1,tc.ty2);
                         tc
@@ -433,7 +861,7 @@ impl DrawText {
                         fonts_atlas.alloc.todo.push(CxFontsAtlasTodo {
                             subpixel_x_fract,
                             subpixel_y_fract,
-                            font_id,
+                            font_id: piece_font_id,
                             atlas_page_id,
                             glyph_id,
                             subpixel_id
@@ -442,10 +870,10 @@ impl DrawText {
                         atlas_page.atlas_glyphs[glyph_id][subpixel_id] = Some(
                             fonts_atlas.alloc.alloc_atlas_glyph(w, h)
                         );
-                        
+                            
                         atlas_page.atlas_glyphs[glyph_id][subpixel_id].as_ref().unwrap()
                     };
-                    
+                            
                     let delta_x = font_size_logical * self.font_scale * glyph.bounds.p_min.x - subpixel_x_fract;
                     let delta_y = -font_size_logical * self.font_scale * glyph.bounds.p_min.y + self.text_style.font_size * self.font_scale * self.text_style.top_drop - subpixel_y_fract;
                     // give the callback a chance to do things
@@ -460,14 +888,16 @@ impl DrawText {
                     self.delta.y = delta_y as f32;
                     self.font_size = self.text_style.font_size as f32;
                     self.advance = advance as f32; //char_offset as f32;
+                    self.color_glyph = 0.0;
                     char_depth += zbias_step;
                     mi.instances.extend_from_slice(self.draw_vars.as_slice());
                     walk_x += advance;
                 }
             }
         }
-        
+        walk_x
     }
+        
     pub fn compute_geom(&self, cx: &Cx2d, walk: Walk, text: &str) -> Option<TextGeom> {
         self.compute_geom_inner(cx, walk, text, &mut *cx.fonts_atlas_rc.0.borrow_mut())
     }
@@ -481,6 +911,8 @@ impl DrawText {
         }
         
         let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * fonts_atlas.fonts[font_id].as_ref().unwrap().ttf_font.units_per_em);
+        // advances of characters from the fallback chain come in ems
+        let font_size_em = self.text_style.font_size * 96.0 / 72.0 * self.font_scale;
         let line_height = self.text_style.font_size * self.text_style.height_factor * self.font_scale;
         let eval_width = cx.turtle().eval_width(walk.width, walk.margin, cx.turtle().layout().flow);
         let eval_height = cx.turtle().eval_height(walk.height, walk.margin, cx.turtle().layout().flow);
@@ -501,8 +933,8 @@ impl DrawText {
                     if measured_width + ellip_width * 3.0 < eval_width {
                         ellip_pt = Some((i, measured_width, 3));
                     }
-                    if let Some(glyph) = fonts_atlas.fonts[font_id].as_mut().unwrap().get_glyph(c) {
-                        let adv = glyph.horizontal_metrics.advance_width * font_size_logical * self.font_scale;
+                    if let Some(advance) = fonts_atlas.get_char_advance(&self.text_style.font, c) {
+                        let adv = advance * font_size_em;
                         // ok so now what.
                         if measured_width + adv >= eval_width { // we have to drop back to ellip_pt
                             // if we don't have an ellip_pt, set it to 0
@@ -538,8 +970,8 @@ impl DrawText {
                 let mut measured_width = 0.0;
                 let mut measured_height = line_height;
                 
-                let mut iter = WordIterator::new(text.char_indices(), eval_width, font_size_logical * self.font_scale);
-                while let Some(word) = iter.next_word(fonts_atlas.fonts[font_id].as_mut().unwrap()) {
+                let mut iter = WordIterator::new(text.char_indices(), eval_width, font_size_em);
+                while let Some(word) = iter.next_word(fonts_atlas, &self.text_style.font) {
                     if measured_width + word.width >= eval_width {
                         measured_height += line_height * self.text_style.line_spacing;
                         measured_width = word.width;
@@ -571,9 +1003,8 @@ impl DrawText {
                     if c == '\n' {
                         measured_height += line_height * self.text_style.line_spacing;
                     }
-                    if let Some(glyph) = fonts_atlas.fonts[font_id].as_mut().unwrap().get_glyph(c) {
-                        let adv = glyph.horizontal_metrics.advance_width * font_size_logical * self.font_scale;
-                        measured_width += adv;
+                    if let Some(advance) = fonts_atlas.get_char_advance(&self.text_style.font, c) {
+                        measured_width += advance * font_size_em;
                     }
                     if measured_width > max_width {
                         max_width = measured_width;
@@ -592,14 +1023,15 @@ impl DrawText {
     
     
     pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk, align: Align, text: &str) {
-        let font_id = if let Some(font_id) = self.text_style.font.font_id{font_id}else{
+        if self.text_style.font.font_id.is_none() {
             //log!("Draw text without font");
             return
-        };
+        }
         let fonts_atlas_rc = cx.fonts_atlas_rc.clone();
         let mut fonts_atlas = fonts_atlas_rc.0.borrow_mut();
         let fonts_atlas = &mut*fonts_atlas;
         
+        self.char_count = text.chars().count();
         //let in_many = self.many_instances.is_some();
         // lets compute the geom
         if text.len() == 0 {
@@ -628,8 +1060,10 @@ impl DrawText {
                             height: Size::Fixed(height)
                         });
                         
-                        self.draw_inner(cx, rect.pos + dvec2(0.0, y_align), &text[0..ellip], fonts_atlas);
-                        self.draw_inner(cx, rect.pos + dvec2(at_x, y_align), &"..."[0..dots], fonts_atlas);
+                        // the ellipsis point is a char index
+                        let ellip_byte = text.char_indices().nth(ellip).map_or(text.len(), | (index, _) | index);
+                        self.draw_inner(cx, rect.pos + dvec2(0.0, y_align), &text[0..ellip_byte], 0, fonts_atlas);
+                        self.draw_inner(cx, rect.pos + dvec2(at_x, y_align), &"..."[0..dots], ellip, fonts_atlas);
                     }
                     else { // we might have space to h-align
                         let rect = cx.walk_turtle(Walk {
@@ -645,11 +1079,11 @@ impl DrawText {
                             )
                         });
                         let x_align = (geom.eval_width - geom.measured_width) * align.x;
-                        self.draw_inner(cx, rect.pos + dvec2(x_align, y_align), text, fonts_atlas);
+                        self.draw_inner(cx, rect.pos + dvec2(x_align, y_align), text, 0, fonts_atlas);
                     }
                 }
                 TextWrap::Word => {
-                    let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * fonts_atlas.fonts[font_id].as_ref().unwrap().ttf_font.units_per_em);
+                    let font_size_em = self.text_style.font_size * 96.0 / 72.0 * self.font_scale;
                     let line_height = self.text_style.font_size * self.text_style.height_factor * self.font_scale;
                     
                     let rect = cx.walk_turtle(Walk {
@@ -658,21 +1092,42 @@ impl DrawText {
                         width: Size::Fixed(geom.eval_width),
                         height: Size::Fixed(geom.measured_height)
                     });
-                    let mut pos = dvec2(0.0, 0.0);
                     
-                    let mut iter = WordIterator::new(text.char_indices(), geom.eval_width, font_size_logical * self.font_scale);
-                    while let Some(word) = iter.next_word(fonts_atlas.fonts[font_id].as_mut().unwrap()) {
-                        if pos.x + word.width >= geom.eval_width {
-                            pos.y += line_height * self.text_style.line_spacing;
-                            pos.x = 0.0;
+                    // break the text into lines in logical order, then draw every line in the
+                    // visual order of the bidi paragraph it is part of
+                    let mut lines = Vec::new();
+                    let mut line = 0..0;
+                    let mut line_width = 0.0;
+                    let mut iter = WordIterator::new(text.char_indices(), geom.eval_width, font_size_em);
+                    while let Some(word) = iter.next_word(fonts_atlas, &self.text_style.font) {
+                        if line_width + word.width >= geom.eval_width {
+                            lines.push(line.clone());
+                            line = word.start..word.start;
+                            line_width = 0.0;
                         }
-                        self.draw_inner(cx, rect.pos + pos, &text[word.start..word.end], fonts_atlas);
-                        pos.x += word.width;
-                        
+                        line.end = word.end;
+                        line_width += word.width;
                         if word.with_newline {
-                            pos.y += line_height * self.text_style.line_spacing;
-                            pos.x = 0.0;
+                            lines.push(line.clone());
+                            line = word.end..word.end;
+                            line_width = 0.0;
+                        }
+                    }
+                    if !line.is_empty() {
+                        lines.push(line);
+                    }
+                    
+                    let bidi_info = unicode_bidi::BidiInfo::new(text, None);
+                    let mut pos = dvec2(0.0, 0.0);
+                    let mut char_index = 0;
+                    let mut last_end = 0;
+                    for line in lines {
+                        char_index += text[last_end..line.start].chars().count();
+                        last_end = line.start;
+                        if let Some(para) = bidi_info.paragraphs.iter().find( | para | para.range.contains(&line.start)) {
+                            self.draw_line(cx, rect.pos + pos, &bidi_info, para, line.clone(), char_index, fonts_atlas);
                         }
+                        pos.y += line_height * self.text_style.line_spacing;
                     }
                 }
                 TextWrap::Line => {
@@ -686,8 +1141,10 @@ impl DrawText {
                     });
                     // lets do our y alignment
                     let mut ypos = 0.0;
+                    let mut char_index = 0;
                     for line in text.split('\n') {
-                        self.draw_inner(cx, rect.pos + dvec2(0.0, y_align + ypos), line, fonts_atlas);
+                        self.draw_inner(cx, rect.pos + dvec2(0.0, y_align + ypos), line, char_index, fonts_atlas);
+                        char_index += line.chars().count() + 1;
                         ypos += line_height * self.text_style.line_spacing;
                     }
                     
@@ -699,124 +1156,94 @@ impl DrawText {
         }
     }
     
-    pub fn closest_offset(&self, cx: &Cx, pos: DVec2) -> Option<usize> {
+    // The box of every char of the text, read back from the instances so it follows the turtle
+    // moving them. Glyphs are drawn in visual order and a glyph can be shaped from several chars,
+    // so the chars of a glyph share its advance. Reading them back isn't free, an event or a draw
+    // reads them once and asks the result all its cursor questions.
+    pub fn char_boxes(&self, cx: &Cx) -> CharBoxes {
         let area = &self.draw_vars.area;
-        
-        if !area.is_valid(cx) {
-            return None
-        }
-
         let line_spacing = self.get_line_spacing();
-        let rect_pos = area.get_read_ref(cx, live_id!(rect_pos), ShaderTy::Vec2).unwrap();
-        let delta = area.get_read_ref(cx, live_id!(delta), ShaderTy::Vec2).unwrap();
-        let advance = area.get_read_ref(cx, live_id!(advance), ShaderTy::Float).unwrap();
-
-        let mut last_y = None;
-        for i in 0..rect_pos.repeat {
-            let index = rect_pos.stride * i;
-            let x = rect_pos.buffer[index + 0] as f64 - delta.buffer[index + 0] as f64;
-            let y = rect_pos.buffer[index + 1] - delta.buffer[index + 1];
-            if last_y.is_none() {last_y = Some(y)}
-            let advance = advance.buffer[index + 0] as f64;
-            if i > 0 && y > last_y.unwrap() && pos.y < last_y.unwrap() as f64 + line_spacing as f64 {
-                return Some(i - 1)
-            }
-            if pos.x < x + advance * 0.5 && pos.y < y as f64 + line_spacing as f64 {
-                return Some(i)
-            }
-            last_y = Some(y)
-        }
-        return Some(rect_pos.repeat);
-        
-    }
-    
-    pub fn get_selection_rects(&self, cx: &Cx, start: usize, end: usize, shift: DVec2, pad: DVec2) -> Vec<Rect> {
-        let area = &self.draw_vars.area;
         
         if !area.is_valid(cx) {
-            return Vec::new();
+            return CharBoxes {boxes: Vec::new(), line_spacing, drawn: false}
         }
         
         let rect_pos = area.get_read_ref(cx, live_id!(rect_pos), ShaderTy::Vec2).unwrap();
         let delta = area.get_read_ref(cx, live_id!(delta), ShaderTy::Vec2).unwrap();
         let advance = area.get_read_ref(cx, live_id!(advance), ShaderTy::Float).unwrap();
+        let char_index = area.get_read_ref(cx, live_id!(char_index), ShaderTy::Float).unwrap();
+        let rtl = area.get_read_ref(cx, live_id!(rtl), ShaderTy::Float).unwrap();
         
-        if rect_pos.repeat == 0 || start >= rect_pos.repeat{
-            return Vec::new();
+        // the glyphs of a cluster, keyed by the index of its first char
+        let mut clusters: Vec<(usize, CharBox)> = Vec::new();
+        for i in 0..rect_pos.repeat {
+            let index = rect_pos.stride * i;
+            let left = (rect_pos.buffer[index + 0] - delta.buffer[index + 0]) as f64;
+            let glyph = CharBox {
+                left,
+                right: left + advance.buffer[index + 0] as f64,
+                y: (rect_pos.buffer[index + 1] - delta.buffer[index + 1]) as f64,
+                rtl: rtl.buffer[index + 0] > 0.5
+            };
+            let char_index = char_index.buffer[index + 0] as usize;
+            match clusters.iter_mut().rev().find( | (start, _) | *start == char_index) {
+                Some((_, cluster)) => {
+                    cluster.left = cluster.left.min(glyph.left);
+                    cluster.right = cluster.right.max(glyph.right);
+                }
+                None => clusters.push((char_index, glyph))
+            }
         }
-        // alright now we go and walk from start to end and collect our selection rects
+        clusters.sort_by_key( | (start, _) | *start);
         
-        let index = start * rect_pos.stride;
-        let start_x = rect_pos.buffer[index + 0] - delta.buffer[index + 0]; // + advance.buffer[index + 0] * pos;
-        let start_y = rect_pos.buffer[index + 1] - delta.buffer[index + 1];
-        let line_spacing = self.get_line_spacing();
-        let mut last_y = start_y;
-        let mut min_x = start_x;
-        let mut last_x = start_x;
-        let mut last_advance = advance.buffer[index + 0];
-        let mut out = Vec::new();
-        for index in start..end {
-            if index >= rect_pos.repeat{
-                break;
-            }
-            let index = index * rect_pos.stride;
-            let end_x = rect_pos.buffer[index + 0] - delta.buffer[index + 0];
-            let end_y = rect_pos.buffer[index + 1] - delta.buffer[index + 1];
-            last_advance = advance.buffer[index + 0];
-            if end_y > last_y { // emit rect
-                out.push(Rect {
-                    pos: dvec2(min_x as f64, last_y as f64) + shift,
-                    size: dvec2((last_x - min_x + last_advance) as f64, line_spacing) + pad
-                });
-                min_x = end_x;
-                last_y = end_y;
+        let mut boxes = Vec::new();
+        for (i, (start, cluster)) in clusters.iter().enumerate() {
+            let end = clusters.get(i + 1).map_or(self.char_count.max(start + 1), | (next, _) | *next);
+            let start = if i == 0 {0} else {*start};
+            let width = (cluster.right - cluster.left) / (end - start) as f64;
+            for j in 0..end - start {
+                let (left, right) = if cluster.rtl {
+                    (cluster.right - (j + 1) as f64 * width, cluster.right - j as f64 * width)
+                }
+                else {
+                    (cluster.left + j as f64 * width, cluster.left + (j + 1) as f64 * width)
+                };
+                boxes.push(CharBox {left, right, ..*cluster});
             }
-            last_x = end_x;
         }
-        out.push(Rect {
-            pos: dvec2(min_x as f64, last_y as f64) + shift,
-            size: dvec2((last_x - min_x + last_advance) as f64, line_spacing) + pad
-        });
-        out
+        CharBoxes {boxes, line_spacing, drawn: true}
+    }
+    
+    pub fn closest_offset(&self, cx: &Cx, pos: DVec2) -> Option<usize> {
+        self.char_boxes(cx).closest_offset(pos)
     }
     
+    pub fn get_selection_rects(&self, cx: &Cx, start: usize, end: usize, shift: DVec2, pad: DVec2) -> Vec<Rect> {
+        self.char_boxes(cx).selection_rects(start, end, shift, pad)
+    }
     
     pub fn get_char_count(&self, cx: &Cx) -> usize {
         let area = &self.draw_vars.area;
         if !area.is_valid(cx) {
             return 0
         }
-        let rect_pos = area.get_read_ref(cx, live_id!(rect_pos), ShaderTy::Vec2).unwrap();
-        rect_pos.repeat
+        self.char_count
     }
     
     pub fn get_cursor_pos(&self, cx: &Cx, pos: f32, index: usize) -> Option<DVec2> {
-        let area = &self.draw_vars.area;
-        
-        if !area.is_valid(cx) {
-            return None
-        }
-        
-        let rect_pos = area.get_read_ref(cx, live_id!(rect_pos), ShaderTy::Vec2).unwrap();
-        let delta = area.get_read_ref(cx, live_id!(delta), ShaderTy::Vec2).unwrap();
-        let advance = area.get_read_ref(cx, live_id!(advance), ShaderTy::Float).unwrap();
+        self.char_boxes(cx).cursor_pos(pos, index)
+    }
         
-        if rect_pos.repeat == 0 {
-            return None
-        }
-        if index >= rect_pos.repeat {
-            // lets get the last one and advance
-            let index = (rect_pos.repeat - 1) * rect_pos.stride;
-            let x = rect_pos.buffer[index + 0] - delta.buffer[index + 0] + advance.buffer[index + 0];
-            let y = rect_pos.buffer[index + 1] - delta.buffer[index + 1];
-            Some(dvec2(x as f64, y as f64))
-        }
-        else {
-            let index = index * rect_pos.stride;
-            let x = rect_pos.buffer[index + 0] - delta.buffer[index + 0] + advance.buffer[index + 0] * pos;
-            let y = rect_pos.buffer[index + 1] - delta.buffer[index + 1];
-            Some(dvec2(x as f64, y as f64))
-        }
+    pub fn get_split_cursor_pos(&self, cx: &Cx, index: usize) -> Option<DVec2> {
+        self.char_boxes(cx).split_cursor_pos(index)
+    }
+    
+    pub fn get_line_edges(&self, cx: &Cx, index: usize) -> Option<(usize, usize)> {
+        self.char_boxes(cx).line_edges(index)
+    }
+    
+    pub fn get_visual_cursor_move(&self, cx: &Cx, index: usize, right: bool) -> Option<usize> {
+        self.char_boxes(cx).visual_cursor_move(index, right)
     }
     
     pub fn get_line_spacing(&self) -> f64 {
@@ -847,3 +1274,160 @@ impl DrawText {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const LINE_SPACING: f64 = 10.0;
+    
+    // boxes of (left, right, y, rtl), in logical order
+    fn char_boxes(boxes: &[(f64, f64, f64, bool)]) -> CharBoxes {
+        CharBoxes {
+            boxes: boxes.iter().map( | &(left, right, y, rtl) | CharBox {left, right, y, rtl}).collect(),
+            line_spacing: LINE_SPACING,
+            drawn: true,
+        }
+    }
+    
+    // "ab", then the right to left run "CD", then "e", on one line
+    fn mixed_line() -> CharBoxes {
+        char_boxes(&[
+            (0.0, 10.0, 0.0, false),
+            (10.0, 20.0, 0.0, false),
+            (30.0, 40.0, 0.0, true),
+            (20.0, 30.0, 0.0, true),
+            (40.0, 50.0, 0.0, false),
+        ])
+    }
+    
+    // "ab " wrapping onto "cd"
+    fn wrapped_lines() -> CharBoxes {
+        char_boxes(&[
+            (0.0, 10.0, 0.0, false),
+            (10.0, 20.0, 0.0, false),
+            (20.0, 30.0, 0.0, false),
+            (0.0, 10.0, 10.0, false),
+            (10.0, 20.0, 10.0, false),
+        ])
+    }
+    
+    fn moves(boxes: &CharBoxes, mut index: usize, right: bool, count: usize) -> Vec<usize> {
+        let mut out = Vec::new();
+        for _ in 0..count {
+            index = boxes.visual_cursor_move(index, right).unwrap();
+            out.push(index);
+        }
+        out
+    }
+    
+    #[test]
+    fn cursor_stops_mixed_line() {
+        let boxes = mixed_line();
+        assert_eq!(boxes.cursor_stops(0..5), vec![
+            (0.0, 0), (10.0, 1),
+            (10.0, 1), (20.0, 2),
+            (40.0, 2), (30.0, 3),
+            (30.0, 3), (20.0, 4),
+            (40.0, 4), (50.0, 5),
+        ]);
+    }
+    
+    #[test]
+    fn cursor_stops_leave_out_end_of_wrapped_line() {
+        let boxes = wrapped_lines();
+        assert_eq!(boxes.line_of(1), 0..3);
+        assert_eq!(boxes.line_of(3), 3..5);
+        let first: Vec<usize> = boxes.cursor_stops(0..3).iter().map( | (_, index) | *index).collect();
+        assert_eq!(first, vec![0, 1, 1, 2, 2]);
+        let last: Vec<usize> = boxes.cursor_stops(3..5).iter().map( | (_, index) | *index).collect();
+        assert_eq!(last, vec![3, 4, 4, 5]);
+    }
+    
+    #[test]
+    fn nearest_stop_prefers_where_the_cursor_is_drawn() {
+        let boxes = mixed_line();
+        let stops = boxes.cursor_stops(0..5);
+        assert_eq!(boxes.nearest_stop(&stops, 12.0, | _ | true), Some(1));
+        assert_eq!(boxes.nearest_stop(&stops, 31.0, | _ | true), Some(3));
+        // 40 is both the start of the run and the start of "e", the cursor at 2 is drawn there
+        assert_eq!(boxes.nearest_stop(&stops, 39.0, | _ | true), Some(2));
+        // 20 is only the second half of a split cursor, so the first stop there wins
+        assert_eq!(boxes.nearest_stop(&stops, 21.0, | _ | true), Some(2));
+        assert_eq!(boxes.nearest_stop(&stops, 40.0, | x | x > 40.5), Some(5));
+        assert_eq!(boxes.nearest_stop(&stops, 0.0, | x | x < -0.5), None);
+    }
+    
+    #[test]
+    fn visual_move_through_mixed_line() {
+        let boxes = mixed_line();
+        assert_eq!(moves(&boxes, 0, true, 5), vec![1, 3, 2, 5, 5]);
+        assert_eq!(moves(&boxes, 5, false, 5), vec![2, 3, 1, 0, 0]);
+        // every move goes the way of the key
+        for index in 0..=5 {
+            let x = boxes.cursor_x(index);
+            let right = boxes.visual_cursor_move(index, true).unwrap();
+            assert!(right == index || boxes.cursor_x(right) > x);
+            let left = boxes.visual_cursor_move(index, false).unwrap();
+            assert!(left == index || boxes.cursor_x(left) < x);
+        }
+    }
+    
+    #[test]
+    fn visual_move_through_right_to_left_line() {
+        let boxes = char_boxes(&[
+            (20.0, 30.0, 0.0, true),
+            (10.0, 20.0, 0.0, true),
+            (0.0, 10.0, 0.0, true),
+        ]);
+        assert_eq!(moves(&boxes, 0, false, 4), vec![1, 2, 3, 3]);
+        assert_eq!(moves(&boxes, 3, true, 4), vec![2, 1, 0, 0]);
+    }
+    
+    #[test]
+    fn visual_move_past_end_of_wrapped_line() {
+        let boxes = wrapped_lines();
+        assert_eq!(moves(&boxes, 0, true, 6), vec![1, 2, 3, 4, 5, 5]);
+        assert_eq!(moves(&boxes, 5, false, 6), vec![4, 3, 2, 1, 0, 0]);
+        assert_eq!(boxes.line_edges(1), Some((0, 2)));
+        assert_eq!(boxes.line_edges(5), Some((3, 5)));
+    }
+    
+    #[test]
+    fn split_cursor_at_run_boundaries() {
+        let boxes = mixed_line();
+        let split: Vec<Option<DVec2>> = (0..=5).map( | index | boxes.split_cursor_pos(index)).collect();
+        assert_eq!(split, vec![None, None, Some(dvec2(20.0, 0.0)), None, Some(dvec2(20.0, 0.0)), None]);
+        assert_eq!(boxes.cursor_pos(0.0, 2), Some(dvec2(40.0, 0.0)));
+        assert_eq!(boxes.cursor_pos(0.0, 4), Some(dvec2(40.0, 0.0)));
+        
+        // a change of direction where the line wraps isn't a split
+        let boxes = char_boxes(&[
+            (0.0, 10.0, 0.0, false),
+            (0.0, 10.0, 10.0, true),
+        ]);
+        assert_eq!(boxes.split_cursor_pos(1), None);
+        // from a right to left run into a left to right one the end of the run is on its left
+        let boxes = char_boxes(&[
+            (0.0, 10.0, 0.0, true),
+            (10.0, 20.0, 0.0, false),
+        ]);
+        assert_eq!(boxes.split_cursor_pos(1), Some(dvec2(0.0, 0.0)));
+        assert_eq!(boxes.cursor_pos(0.0, 1), Some(dvec2(10.0, 0.0)));
+    }
+    
+    #[test]
+    fn empty_line() {
+        let boxes = char_boxes(&[]);
+        assert_eq!(boxes.closest_offset(dvec2(5.0, 5.0)), Some(0));
+        assert_eq!(boxes.visual_cursor_move(0, true), None);
+        assert_eq!(boxes.visual_cursor_move(0, false), None);
+        assert_eq!(boxes.split_cursor_pos(0), None);
+        assert_eq!(boxes.cursor_pos(0.0, 0), None);
+        assert_eq!(boxes.line_edges(0), None);
+        assert!(boxes.selection_rects(0, 1, dvec2(0.0, 0.0), dvec2(0.0, 0.0)).is_empty());
+        
+        let not_drawn = CharBoxes {boxes: Vec::new(), line_spacing: LINE_SPACING, drawn: false};
+        assert_eq!(not_drawn.closest_offset(dvec2(5.0, 5.0)), None);
+    }
+}
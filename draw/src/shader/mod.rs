@@ -5,3 +5,4 @@ pub mod draw_icon;
 pub mod draw_text;
 pub mod std;
 pub mod draw_trapezoid;
+pub mod draw_path;
@@ -0,0 +1,213 @@
+// Fonts installed on the system, the last resort of a fallback chain. The font directories are
+// scanned once on a thread and only fonts covering a missing character get loaded.
+
+use {
+    std::{
+        collections::{HashMap, HashSet},
+        path::{Path, PathBuf},
+    },
+    crate::makepad_platform::thread::ToUIReceiver,
+    rustybuzz::ttf_parser::{Face, Tag},
+};
+
+pub struct SystemFont {
+    pub path: PathBuf,
+    // sorted and disjoint inclusive ranges of the codepoints the font maps to glyphs
+    pub ranges: Vec<(u32, u32)>,
+    // whether the font has colour glyphs, which we prefer for emoji
+    pub color: bool,
+    pub font_id: Option<usize>,
+}
+
+impl SystemFont {
+    pub fn covers(&self, c: char) -> bool {
+        let c = c as u32;
+        self.ranges.binary_search_by( | &(start, end) | {
+            if end < c {std::cmp::Ordering::Less}
+            else if start > c {std::cmp::Ordering::Greater}
+            else {std::cmp::Ordering::Equal}
+        }).is_ok()
+    }
+}
+
+pub enum SystemFontsLoad {
+    Scanned(Vec<SystemFont>),
+    Loaded(usize, Vec<u8>),
+    Failed(usize),
+}
+
+#[derive(Default)]
+pub struct CxSystemFonts {
+    // None until the scan of the font directories is done
+    pub fonts: Option<Vec<SystemFont >>,
+    pub char_to_font: HashMap<char, Option<usize >>,
+    scanning: bool,
+    // the fonts requested from the loader thread
+    loading: HashSet<usize>,
+    pub loads: ToUIReceiver<SystemFontsLoad>,
+}
+
+impl CxSystemFonts {
+    pub fn start_scan(&mut self) {
+        if self.scanning {
+            return
+        }
+        self.scanning = true;
+        let sender = self.loads.sender();
+        std::thread::spawn(move || {
+            let _ = sender.send(SystemFontsLoad::Scanned(scan_system_fonts()));
+        });
+    }
+    
+    // reads the file of a font on the loader thread, the data comes back through loads
+    pub fn request_load(&mut self, index: usize) {
+        let Some(font) = self.fonts.as_ref().and_then( | fonts | fonts.get(index)) else {
+            return
+        };
+        if !self.loading.insert(index) {
+            return
+        }
+        let path = font.path.clone();
+        let sender = self.loads.sender();
+        std::thread::spawn(move || {
+            let load = match std::fs::read(&path) {
+                Ok(data) => SystemFontsLoad::Loaded(index, data),
+                Err(_) => SystemFontsLoad::Failed(index)
+            };
+            let _ = sender.send(load);
+        });
+    }
+    
+    // a font that failed to load no longer covers anything, so its characters go to the next font
+    pub fn set_failed(&mut self, index: usize) {
+        if let Some(font) = self.fonts.as_mut().and_then( | fonts | fonts.get_mut(index)) {
+            font.ranges.clear();
+        }
+        self.char_to_font.retain( | _, font | *font != Some(index));
+    }
+    
+    // returns the index of the first system font that has a glyph for c, None as well while
+    // the scan is still running
+    pub fn find_font_for_char(&mut self, c: char) -> Option<usize> {
+        if let Some(index) = self.char_to_font.get(&c) {
+            return *index
+        }
+        let fonts = self.fonts.as_ref()?;
+        let index = if is_emoji(c) {
+            fonts.iter().position( | font | font.color && font.covers(c))
+        }
+        else {
+            None
+        }.or_else( || fonts.iter().position( | font | font.covers(c)));
+        self.char_to_font.insert(c, index);
+        index
+    }
+}
+
+// the pictographic blocks, where most characters have an emoji presentation
+fn is_emoji(c: char) -> bool {
+    matches!(c, '\u{2600}'..='\u{27BF}' | '\u{2B00}'..='\u{2BFF}' | '\u{1F000}'..='\u{1FAFF}')
+}
+
+// regular faces sort before the styled faces of their family
+fn style_rank(path: &Path) -> usize {
+    let name = path.file_stem().map( | name | name.to_string_lossy().to_ascii_lowercase()).unwrap_or_default();
+    ["bold", "italic", "oblique", "light", "thin", "black", "condensed", "narrow", "mono", "math"]
+        .iter()
+        .filter( | style | name.contains(*style))
+        .count()
+}
+
+#[cfg(target_os = "linux")]
+fn font_dirs() -> Vec<PathBuf> {
+    // the directories fontconfig uses by default, following the XDG base directory spec
+    let mut dirs = Vec::new();
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    match std::env::var_os("XDG_DATA_HOME") {
+        Some(data_home) => dirs.push(PathBuf::from(data_home).join("fonts")),
+        None => if let Some(home) = &home {
+            dirs.push(home.join(".local/share/fonts"));
+        }
+    }
+    if let Some(home) = &home {
+        dirs.push(home.join(".fonts"));
+    }
+    let data_dirs = std::env::var("XDG_DATA_DIRS").unwrap_or_else( | _ | "/usr/local/share:/usr/share".to_string());
+    for dir in data_dirs.split(':').filter( | dir | !dir.is_empty()) {
+        dirs.push(Path::new(dir).join("fonts"));
+    }
+    dirs
+}
+
+#[cfg(not(target_os = "linux"))]
+fn font_dirs() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+fn collect_font_files(dir: &Path, depth: usize, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            // font directories are shallow, this guards against symlink loops
+            if depth < 8 {
+                collect_font_files(&path, depth + 1, out);
+            }
+        }
+        else if let Some(extension) = path.extension().and_then( | extension | extension.to_str()) {
+            if matches!(extension.to_ascii_lowercase().as_str(), "ttf" | "otf" | "ttc") {
+                out.push(path);
+            }
+        }
+    }
+}
+
+fn font_coverage(face: &Face<'_>) -> Vec<(u32, u32)> {
+    let Some(cmap) = face.tables().cmap else {
+        return Vec::new()
+    };
+    let mut codepoints = Vec::new();
+    for subtable in cmap.subtables {
+        if subtable.is_unicode() {
+            subtable.codepoints( | codepoint | codepoints.push(codepoint));
+        }
+    }
+    codepoints.sort_unstable();
+    codepoints.dedup();
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for codepoint in codepoints {
+        match ranges.last_mut() {
+            Some(range) if range.1 + 1 == codepoint => range.1 = codepoint,
+            _ => ranges.push((codepoint, codepoint))
+        }
+    }
+    ranges
+}
+
+pub fn scan_system_fonts() -> Vec<SystemFont> {
+    let mut paths = Vec::new();
+    for dir in font_dirs() {
+        collect_font_files(&dir, 0, &mut paths);
+    }
+    paths.sort_by( | a, b | style_rank(a).cmp(&style_rank(b)).then_with( || a.cmp(b)));
+    paths.dedup();
+    paths.into_iter().filter_map( | path | {
+        let data = std::fs::read(&path).ok() ?;
+        let face = Face::parse(&data, 0).ok() ?;
+        let ranges = font_coverage(&face);
+        if ranges.is_empty() {
+            return None
+        }
+        let color = face.tables().cbdt.is_some()
+            || face.tables().sbix.is_some()
+            || face.raw_face().table(Tag::from_bytes(b"COLR")).is_some();
+        Some(SystemFont {
+            path,
+            ranges,
+            color,
+            font_id: None
+        })
+    }).collect()
+}
@@ -42,6 +42,24 @@ impl LineSegment {
             },
         })
     }
+
+    /// Returns the parameters along `self` and `other` at which they intersect, or None if they
+    /// do not intersect or are parallel.
+    pub fn intersect_with_line_segment(self, other: LineSegment) -> Option<(f64, f64)> {
+        let r = self.p1 - self.p0;
+        let s = other.p1 - other.p0;
+        let denom = r.cross(s);
+        if denom == 0.0 {
+            return None;
+        }
+        let q = other.p0 - self.p0;
+        let t = q.cross(s) / denom;
+        let u = q.cross(r) / denom;
+        if !(0.0..=1.0).contains(&t) || !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        Some((t, u))
+    }
 }
 
 impl Transform for LineSegment {
@@ -2,5 +2,7 @@ pub mod font;
 pub mod geometry;
 pub mod internal_iter;
 pub mod path;
+pub mod stroker;
+pub mod svg;
 pub mod trapezoidator;
 pub mod ttf_parser;
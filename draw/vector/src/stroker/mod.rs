@@ -0,0 +1,433 @@
+use crate::geometry::{Point, Vector};
+use crate::path::{LinePath, LinePathCommand, LinePathIterator};
+use std::f64::consts::PI;
+
+/// The shape used to join two consecutive segments of a stroke.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum LineJoin {
+    /// The outer edges of the segments are extended until they meet.
+    #[default]
+    Miter,
+    /// The segments are joined by a circular arc.
+    Round,
+    /// The outer corners of the segments are connected by a straight line.
+    Bevel,
+}
+
+/// The shape used at the ends of an open stroke.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum LineCap {
+    /// The stroke ends exactly at the endpoint.
+    #[default]
+    Butt,
+    /// The stroke is extended by half its width with a square end.
+    Square,
+    /// The stroke is extended by half its width with a semicircular end.
+    Round,
+}
+
+/// The parameters that control how a path is stroked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f64,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// The maximum ratio of miter length to stroke width before a miter join becomes a bevel.
+    pub miter_limit: f64,
+    /// Alternating lengths of dashes and gaps. An empty pattern strokes a solid line.
+    pub dashes: Vec<f64>,
+    pub dash_offset: f64,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> StrokeStyle {
+        StrokeStyle {
+            width: 1.0,
+            join: LineJoin::default(),
+            cap: LineCap::default(),
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    /// Creates a new solid stroke style with the given width.
+    pub fn new(width: f64) -> StrokeStyle {
+        StrokeStyle {
+            width,
+            ..StrokeStyle::default()
+        }
+    }
+}
+
+/// Converts a sequence of line path commands to a set of closed contours that cover the stroke of
+/// that path.
+///
+/// The contours all wind in the same direction and may overlap, so the result should be filled
+/// with the nonzero fill rule.
+#[derive(Clone, Debug, Default)]
+pub struct Stroker {
+    polylines: Vec<(Vec<Point>, bool)>,
+    polygon: Vec<Point>,
+}
+
+impl Stroker {
+    /// Creates a new stroker.
+    pub fn new() -> Stroker {
+        Stroker::default()
+    }
+
+    /// Appends the outline of the stroke of `path` to `output`. Round joins and caps are
+    /// approximated with tolerance `epsilon`.
+    pub fn stroke<P: LinePathIterator>(
+        &mut self,
+        path: P,
+        style: &StrokeStyle,
+        epsilon: f64,
+        output: &mut LinePath,
+    ) {
+        self.polylines.clear();
+        self.collect_polylines(path);
+        if style.width <= 0.0 {
+            return;
+        }
+        let mut polylines = std::mem::take(&mut self.polylines);
+        if let Some(pattern) = dash_pattern(style) {
+            let mut dashed = Vec::new();
+            for (points, closed) in polylines.drain(..) {
+                dash_polyline(&points, closed, &pattern, style.dash_offset, &mut dashed);
+            }
+            polylines = dashed;
+        }
+        let half_width = style.width * 0.5;
+        for (points, closed) in polylines.iter() {
+            self.stroke_polyline(points, *closed, style, half_width, epsilon, output);
+        }
+        self.polylines = polylines;
+    }
+
+    fn collect_polylines<P: LinePathIterator>(&mut self, path: P) {
+        let polylines = &mut self.polylines;
+        // The points of the current contour, and whether anything has been drawn to it yet. A
+        // lone move to does not paint anything.
+        let mut current: Option<(Vec<Point>, bool)> = None;
+        let mut start = None;
+        path.for_each(&mut |command| {
+            match command {
+                LinePathCommand::MoveTo(p) => {
+                    if let Some((points, true)) = current.take() {
+                        polylines.push((points, false));
+                    }
+                    current = Some((vec![p], false));
+                    start = Some(p);
+                }
+                LinePathCommand::LineTo(p) => {
+                    let (points, drawn) =
+                        current.get_or_insert_with(|| (vec![start.unwrap_or(p)], false));
+                    if points.last() != Some(&p) {
+                        points.push(p);
+                    }
+                    *drawn = true;
+                }
+                LinePathCommand::Close => {
+                    if let Some((mut points, _)) = current.take() {
+                        if points.len() > 1 && points.first() == points.last() {
+                            points.pop();
+                        }
+                        polylines.push((points, true));
+                    }
+                }
+            }
+            true
+        });
+        if let Some((points, true)) = current {
+            polylines.push((points, false));
+        }
+    }
+
+    fn stroke_polyline(
+        &mut self,
+        points: &[Point],
+        closed: bool,
+        style: &StrokeStyle,
+        half_width: f64,
+        epsilon: f64,
+        output: &mut LinePath,
+    ) {
+        if points.len() == 1 {
+            // A zero length subpath only paints its caps.
+            if !closed {
+                match style.cap {
+                    LineCap::Butt => {}
+                    LineCap::Square => {
+                        let p = points[0];
+                        self.polygon.clear();
+                        self.polygon.extend_from_slice(&[
+                            Point::new(p.x - half_width, p.y - half_width),
+                            Point::new(p.x + half_width, p.y - half_width),
+                            Point::new(p.x + half_width, p.y + half_width),
+                            Point::new(p.x - half_width, p.y + half_width),
+                        ]);
+                        self.emit_polygon(output);
+                    }
+                    LineCap::Round => self.emit_circle(points[0], half_width, epsilon, output),
+                }
+            }
+            return;
+        }
+        let segment_count = if closed && points.len() > 2 {
+            points.len()
+        } else {
+            points.len() - 1
+        };
+        for index in 0..segment_count {
+            let p0 = points[index];
+            let p1 = points[(index + 1) % points.len()];
+            let direction = match (p1 - p0).normalize() {
+                Some(direction) => direction,
+                None => continue,
+            };
+            let normal = perpendicular(direction) * half_width;
+            self.polygon.clear();
+            self.polygon
+                .extend_from_slice(&[p0 - normal, p1 - normal, p1 + normal, p0 + normal]);
+            self.emit_polygon(output);
+        }
+        let join_range = if closed && points.len() > 2 {
+            0..points.len()
+        } else {
+            1..points.len() - 1
+        };
+        for index in join_range {
+            let previous = points[(index + points.len() - 1) % points.len()];
+            let p = points[index];
+            let next = points[(index + 1) % points.len()];
+            self.emit_join(previous, p, next, style, half_width, epsilon, output);
+        }
+        if !closed || points.len() == 2 {
+            let start_direction = (points[1] - points[0]).normalize();
+            let end_direction = (points[points.len() - 1] - points[points.len() - 2]).normalize();
+            if let (Some(start_direction), Some(end_direction)) = (start_direction, end_direction) {
+                self.emit_cap(points[0], -start_direction, style.cap, half_width, epsilon, output);
+                self.emit_cap(
+                    points[points.len() - 1],
+                    end_direction,
+                    style.cap,
+                    half_width,
+                    epsilon,
+                    output,
+                );
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn emit_join(
+        &mut self,
+        previous: Point,
+        p: Point,
+        next: Point,
+        style: &StrokeStyle,
+        half_width: f64,
+        epsilon: f64,
+        output: &mut LinePath,
+    ) {
+        let (d0, d1) = match ((p - previous).normalize(), (next - p).normalize()) {
+            (Some(d0), Some(d1)) => (d0, d1),
+            _ => return,
+        };
+        let turn = d0.cross(d1);
+        if turn.abs() < 1e-9 && d0.dot(d1) > 0.0 {
+            return;
+        }
+        // The join only has to cover the wedge on the outer side of the turn.
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let o0 = perpendicular(d0) * side;
+        let o1 = perpendicular(d1) * side;
+        match style.join {
+            LineJoin::Round => self.emit_circle(p, half_width, epsilon, output),
+            LineJoin::Bevel => self.emit_bevel(p, o0, o1, half_width, output),
+            LineJoin::Miter => {
+                let bisector = match (o0 + o1).normalize() {
+                    Some(bisector) => bisector,
+                    None => return self.emit_bevel(p, o0, o1, half_width, output),
+                };
+                let cos_half_angle = bisector.dot(o0);
+                if cos_half_angle <= 0.0 || 1.0 / cos_half_angle > style.miter_limit {
+                    return self.emit_bevel(p, o0, o1, half_width, output);
+                }
+                self.polygon.clear();
+                self.polygon.extend_from_slice(&[
+                    p,
+                    p + o0 * half_width,
+                    p + bisector * (half_width / cos_half_angle),
+                    p + o1 * half_width,
+                ]);
+                self.emit_polygon(output);
+            }
+        }
+    }
+
+    fn emit_bevel(
+        &mut self,
+        p: Point,
+        o0: Vector,
+        o1: Vector,
+        half_width: f64,
+        output: &mut LinePath,
+    ) {
+        self.polygon.clear();
+        self.polygon
+            .extend_from_slice(&[p, p + o0 * half_width, p + o1 * half_width]);
+        self.emit_polygon(output);
+    }
+
+    fn emit_cap(
+        &mut self,
+        p: Point,
+        direction: Vector,
+        cap: LineCap,
+        half_width: f64,
+        epsilon: f64,
+        output: &mut LinePath,
+    ) {
+        match cap {
+            LineCap::Butt => {}
+            LineCap::Square => {
+                let normal = perpendicular(direction) * half_width;
+                let extension = direction * half_width;
+                self.polygon.clear();
+                self.polygon.extend_from_slice(&[
+                    p - normal,
+                    p - normal + extension,
+                    p + normal + extension,
+                    p + normal,
+                ]);
+                self.emit_polygon(output);
+            }
+            LineCap::Round => self.emit_circle(p, half_width, epsilon, output),
+        }
+    }
+
+    fn emit_circle(&mut self, center: Point, radius: f64, epsilon: f64, output: &mut LinePath) {
+        let steps = circle_steps(radius, epsilon);
+        self.polygon.clear();
+        for step in 0..steps {
+            let angle = 2.0 * PI * step as f64 / steps as f64;
+            self.polygon
+                .push(center + Vector::new(angle.cos(), angle.sin()) * radius);
+        }
+        self.emit_polygon(output);
+    }
+
+    fn emit_polygon(&mut self, output: &mut LinePath) {
+        if self.polygon.len() < 3 {
+            return;
+        }
+        let mut area = 0.0;
+        for index in 0..self.polygon.len() {
+            let p0 = self.polygon[index].to_vector();
+            let p1 = self.polygon[(index + 1) % self.polygon.len()].to_vector();
+            area += p0.cross(p1);
+        }
+        if area == 0.0 {
+            return;
+        }
+        if area < 0.0 {
+            self.polygon.reverse();
+        }
+        output.move_to(self.polygon[0]);
+        for &p in &self.polygon[1..] {
+            output.line_to(p);
+        }
+        output.close();
+    }
+}
+
+fn perpendicular(v: Vector) -> Vector {
+    Vector::new(-v.y, v.x)
+}
+
+fn circle_steps(radius: f64, epsilon: f64) -> usize {
+    if radius <= epsilon {
+        return 8;
+    }
+    let angle = (1.0 - epsilon / radius).acos();
+    ((PI / angle).ceil() as usize).clamp(8, 256)
+}
+
+/// Returns the dash pattern of `style`, repeated to an even length, or None if the stroke is solid.
+fn dash_pattern(style: &StrokeStyle) -> Option<Vec<f64>> {
+    if style.dashes.is_empty() || style.dashes.iter().any(|&length| length < 0.0 || !length.is_finite()) {
+        return None;
+    }
+    if style.dashes.iter().sum::<f64>() <= 0.0 {
+        return None;
+    }
+    let mut pattern = style.dashes.clone();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_slice(&style.dashes);
+    }
+    Some(pattern)
+}
+
+fn dash_polyline(
+    points: &[Point],
+    closed: bool,
+    pattern: &[f64],
+    offset: f64,
+    output: &mut Vec<(Vec<Point>, bool)>,
+) {
+    let total: f64 = pattern.iter().sum();
+    let mut index = 0;
+    let mut remaining = offset.rem_euclid(total);
+    while remaining >= pattern[index] {
+        remaining -= pattern[index];
+        index = (index + 1) % pattern.len();
+    }
+    remaining = pattern[index] - remaining;
+    let mut current = if index % 2 == 0 {
+        Some(vec![points[0]])
+    } else {
+        None
+    };
+    let segment_count = if closed { points.len() } else { points.len() - 1 };
+    for segment_index in 0..segment_count {
+        let mut p0 = points[segment_index];
+        let p1 = points[(segment_index + 1) % points.len()];
+        let mut length = (p1 - p0).length();
+        while length > remaining {
+            let p = p0.lerp(p1, remaining / length);
+            length -= remaining;
+            p0 = p;
+            match current.take() {
+                Some(mut dash) => {
+                    push_point(&mut dash, p);
+                    if dash.len() > 1 {
+                        output.push((dash, false));
+                    }
+                }
+                None => current = Some(vec![p]),
+            }
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+        remaining -= length;
+        if let Some(dash) = current.as_mut() {
+            push_point(dash, p1);
+        }
+    }
+    if let Some(dash) = current {
+        if dash.len() > 1 {
+            output.push((dash, false));
+        }
+    }
+}
+
+fn push_point(points: &mut Vec<Point>, p: Point) {
+    if points.last() != Some(&p) {
+        points.push(p);
+    }
+}
@@ -0,0 +1,1073 @@
+//! A parser for the subset of SVG that is needed to render icons and illustrations.
+//!
+//! Documents are flattened into a list of shapes, each with its own path, transform, fill and
+//! stroke. Groups, `use` references, transforms, `viewBox` with `preserveAspectRatio`, the basic
+//! shapes, paths, solid colors and linear and radial gradients are supported. Text, filters, masks,
+//! clip paths and CSS stylesheets are not.
+
+mod path_data;
+mod xml;
+
+pub use self::path_data::{arc_to, parse_number, parse_path_data};
+pub use self::xml::{parse_xml, Element};
+
+use crate::geometry::{AffineTransformation, LinearTransformation, Point, Rectangle, Transformation, Vector};
+use crate::path::{Path, PathCommand};
+use crate::stroker::{LineCap, LineJoin, StrokeStyle};
+use crate::trapezoidator::FillRule;
+use std::collections::HashMap;
+
+/// A color with straight, non premultiplied alpha.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color { r, g, b, a }
+    }
+
+    fn with_opacity(self, opacity: f64) -> Color {
+        Color::new(self.r, self.g, self.b, self.a * opacity as f32)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    pub offset: f64,
+    pub color: Color,
+}
+
+/// How the inside of a shape or its stroke is painted. Gradient geometry is in the coordinate
+/// system of the shape.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    Color(Color),
+    LinearGradient {
+        start: Point,
+        end: Point,
+        stops: Vec<GradientStop>,
+    },
+    RadialGradient {
+        center: Point,
+        radius: f64,
+        stops: Vec<GradientStop>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shape {
+    pub path: Path,
+    /// Maps the coordinates of `path` to the coordinates of the document's `view_box`.
+    pub transform: AffineTransformation,
+    pub fill: Option<Paint>,
+    pub fill_rule: FillRule,
+    pub stroke: Option<Paint>,
+    pub stroke_style: StrokeStyle,
+}
+
+/// How the `view_box` is fitted into a viewport of another aspect ratio, from the
+/// `preserveAspectRatio` attribute.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AspectRatio {
+    /// Where the view box goes along each axis as a fraction of the space left over, 0 for `Min`,
+    /// 0.5 for `Mid` and 1 for `Max`. None stretches the view box to fill the viewport.
+    pub align: Option<(f64, f64)>,
+    /// Scales the view box up to cover the viewport instead of down to fit inside it.
+    pub slice: bool,
+}
+
+impl Default for AspectRatio {
+    fn default() -> AspectRatio {
+        AspectRatio {
+            align: Some((0.5, 0.5)),
+            slice: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    pub view_box: Rectangle,
+    pub aspect_ratio: AspectRatio,
+    /// The intrinsic size of the document, taken from the `width` and `height` attributes.
+    pub width: f64,
+    pub height: f64,
+    pub shapes: Vec<Shape>,
+}
+
+impl Document {
+    /// Parses an SVG document.
+    pub fn parse(data: &str) -> Result<Document, String> {
+        let root = parse_xml(data)?;
+        if local_name(&root.name) != "svg" {
+            return Err(format!("Expected an svg root element, got {}", root.name));
+        }
+        let mut ids = HashMap::new();
+        collect_ids(&root, &mut ids);
+
+        let width = root.attribute("width").and_then(parse_length);
+        let height = root.attribute("height").and_then(parse_length);
+        let view_box = root
+            .attribute("viewBox")
+            .map(parse_number_list)
+            .filter(|numbers| numbers.len() == 4 && numbers[2] > 0.0 && numbers[3] > 0.0)
+            .map(|numbers| {
+                Rectangle::new(
+                    Point::new(numbers[0], numbers[1]),
+                    Point::new(numbers[0] + numbers[2], numbers[1] + numbers[3]),
+                )
+            })
+            .unwrap_or_else(|| {
+                Rectangle::new(Point::origin(), Point::new(width.unwrap_or(100.0), height.unwrap_or(100.0)))
+            });
+        let view_box_size = view_box.p_max - view_box.p_min;
+        let aspect_ratio = root.attribute("preserveAspectRatio").map_or_else(AspectRatio::default, parse_aspect_ratio);
+
+        let mut builder = Builder {
+            ids: &ids,
+            view_box_size,
+            shapes: Vec::new(),
+            depth: 0,
+        };
+        builder.visit_children(&root, &Style::default(), AffineTransformation::identity());
+        Ok(Document {
+            view_box,
+            aspect_ratio,
+            width: width.unwrap_or(view_box_size.x),
+            height: height.unwrap_or(view_box_size.y),
+            shapes: builder.shapes,
+        })
+    }
+
+    /// Returns the transformation that fits the `view_box` into a viewport of the given size,
+    /// as the `aspect_ratio` says.
+    pub fn view_box_transform(&self, size: Vector) -> AffineTransformation {
+        let view_box_size = self.view_box.p_max - self.view_box.p_min;
+        let scale_x = size.x / view_box_size.x;
+        let scale_y = size.y / view_box_size.y;
+        let (scale, offset) = match self.aspect_ratio.align {
+            Some((align_x, align_y)) => {
+                let scale = if self.aspect_ratio.slice {scale_x.max(scale_y)} else {scale_x.min(scale_y)};
+                (
+                    Vector::new(scale, scale),
+                    Vector::new(
+                        (size.x - view_box_size.x * scale) * align_x,
+                        (size.y - view_box_size.y * scale) * align_y,
+                    ),
+                )
+            }
+            None => (Vector::new(scale_x, scale_y), Vector::zero()),
+        };
+        AffineTransformation::translation(offset)
+            .compose(AffineTransformation::scaling(scale))
+            .compose(AffineTransformation::translation(-self.view_box.p_min.to_vector()))
+    }
+}
+
+fn collect_ids<'a>(element: &'a Element, ids: &mut HashMap<&'a str, &'a Element>) {
+    if let Some(id) = element.attribute("id") {
+        ids.insert(id, element);
+    }
+    for child in &element.children {
+        collect_ids(child, ids);
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PaintSource {
+    None,
+    Color(Color),
+    CurrentColor,
+    Url(String, Option<Color>),
+}
+
+/// The presentation properties that are inherited from parent elements.
+#[derive(Clone, Debug)]
+struct Style {
+    fill: PaintSource,
+    fill_opacity: f64,
+    fill_rule: FillRule,
+    stroke: PaintSource,
+    stroke_opacity: f64,
+    stroke_width: f64,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f64,
+    dashes: Vec<f64>,
+    dash_offset: f64,
+    color: Color,
+    visible: bool,
+    // not inherited in SVG, but group opacity is approximated by multiplying it into the children
+    opacity: f64,
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style {
+            fill: PaintSource::Color(Color::new(0.0, 0.0, 0.0, 1.0)),
+            fill_opacity: 1.0,
+            fill_rule: FillRule::NonZero,
+            stroke: PaintSource::None,
+            stroke_opacity: 1.0,
+            stroke_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+            color: Color::new(0.0, 0.0, 0.0, 1.0),
+            visible: true,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl Style {
+    // applies the presentation attributes and the `style` attribute of an element, returning
+    // false if the element is not displayed at all
+    fn apply(&mut self, element: &Element) -> bool {
+        let mut displayed = true;
+        // declarations in the style attribute come last, so they override presentation attributes
+        let mut properties: Vec<(&str, &str)> = element
+            .attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        if let Some(style) = element.attribute("style") {
+            for declaration in style.split(';') {
+                if let Some((key, value)) = declaration.split_once(':') {
+                    properties.push((key.trim(), value.trim()));
+                }
+            }
+        }
+        // the style starts out as the parent's, so inherit only has to cancel the declarations of
+        // its property that came before it
+        let inherited = |index: usize, key: &str| {
+            properties[index..].iter().any(|(other, value)| *other == key && value.trim() == "inherit")
+        };
+        let properties: Vec<(&str, &str)> = properties
+            .iter()
+            .enumerate()
+            .filter(|(index, (key, _))| !inherited(*index, key))
+            .map(|(_, property)| *property)
+            .collect();
+        for (key, value) in properties {
+            let value = value.trim();
+            match key {
+                "fill" => self.fill = parse_paint(value),
+                "fill-opacity" => self.fill_opacity = parse_opacity(value, self.fill_opacity),
+                "fill-rule" => {
+                    self.fill_rule = if value == "evenodd" {FillRule::EvenOdd} else {FillRule::NonZero}
+                }
+                "stroke" => self.stroke = parse_paint(value),
+                "stroke-opacity" => self.stroke_opacity = parse_opacity(value, self.stroke_opacity),
+                "stroke-width" => {
+                    if let Some(width) = parse_length(value) {
+                        self.stroke_width = width;
+                    }
+                }
+                "stroke-linecap" => {
+                    self.line_cap = match value {
+                        "round" => LineCap::Round,
+                        "square" => LineCap::Square,
+                        _ => LineCap::Butt,
+                    }
+                }
+                "stroke-linejoin" => {
+                    self.line_join = match value {
+                        "round" => LineJoin::Round,
+                        "bevel" => LineJoin::Bevel,
+                        _ => LineJoin::Miter,
+                    }
+                }
+                "stroke-miterlimit" => {
+                    if let Some(limit) = parse_length(value) {
+                        self.miter_limit = limit;
+                    }
+                }
+                "stroke-dasharray" => {
+                    self.dashes = if value == "none" {Vec::new()} else {parse_number_list(value)};
+                }
+                "stroke-dashoffset" => {
+                    if let Some(offset) = parse_length(value) {
+                        self.dash_offset = offset;
+                    }
+                }
+                "color" => {
+                    if let Some(color) = parse_color(value) {
+                        self.color = color;
+                    }
+                }
+                "opacity" => self.opacity *= parse_opacity(value, 1.0),
+                "visibility" => self.visible = value == "visible",
+                "display" => displayed = value != "none",
+                _ => ()
+            }
+        }
+        displayed
+    }
+}
+
+struct Builder<'a> {
+    ids: &'a HashMap<&'a str, &'a Element>,
+    view_box_size: Vector,
+    shapes: Vec<Shape>,
+    // guards against `use` elements that reference themselves
+    depth: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn visit_children(&mut self, element: &Element, style: &Style, transform: AffineTransformation) {
+        for child in &element.children {
+            self.visit(child, style, transform);
+        }
+    }
+
+    fn visit(&mut self, element: &Element, parent_style: &Style, parent_transform: AffineTransformation) {
+        let name = local_name(&element.name);
+        if matches!(
+            name,
+            "defs" | "linearGradient" | "radialGradient" | "clipPath" | "mask" | "pattern" |
+            "symbol" | "marker" | "filter" | "style" | "title" | "desc" | "metadata" | "text"
+        ) {
+            return;
+        }
+        let mut style = parent_style.clone();
+        if !style.apply(element) {
+            return;
+        }
+        let mut transform = parent_transform;
+        if let Some(value) = element.attribute("transform") {
+            transform = transform.compose(parse_transform(value));
+        }
+        match name {
+            "svg" | "g" | "a" | "switch" => {
+                if name == "svg" {
+                    // a nested viewport only contributes its offset
+                    let x = self.length(element, "x", false).unwrap_or(0.0);
+                    let y = self.length(element, "y", true).unwrap_or(0.0);
+                    transform = transform.compose(AffineTransformation::translation(Vector::new(x, y)));
+                }
+                self.visit_children(element, &style, transform);
+            }
+            "use" => {
+                let Some(target) = element.attribute("href").and_then(|href| href.strip_prefix('#')) else {
+                    return
+                };
+                let Some(target) = self.ids.get(target).copied() else {
+                    return
+                };
+                if self.depth > 16 {
+                    return;
+                }
+                let x = self.length(element, "x", false).unwrap_or(0.0);
+                let y = self.length(element, "y", true).unwrap_or(0.0);
+                let transform = transform.compose(AffineTransformation::translation(Vector::new(x, y)));
+                self.depth += 1;
+                if local_name(&target.name) == "symbol" {
+                    self.visit_children(target, &style, transform);
+                }
+                else {
+                    self.visit(target, &style, transform);
+                }
+                self.depth -= 1;
+            }
+            _ => {
+                if let Some(path) = self.shape_path(name, element) {
+                    if style.visible {
+                        self.push_shape(path, &style, transform);
+                    }
+                }
+            }
+        }
+    }
+
+    fn length(&self, element: &Element, name: &str, vertical: bool) -> Option<f64> {
+        let value = element.attribute(name)?.trim();
+        if let Some(percent) = value.strip_suffix('%') {
+            let fraction = percent.trim().parse::<f64>().ok()? / 100.0;
+            let reference = if vertical {self.view_box_size.y} else {self.view_box_size.x};
+            return Some(fraction * reference);
+        }
+        parse_length(value)
+    }
+
+    fn shape_path(&self, name: &str, element: &Element) -> Option<Path> {
+        let mut path = Path::new();
+        match name {
+            "path" => {
+                let data = element.attribute("d")?;
+                // a malformed path is skipped rather than failing the whole document
+                for command in parse_path_data(data).ok()? {
+                    match command {
+                        PathCommand::MoveTo(p) => path.move_to(p),
+                        PathCommand::LineTo(p) => path.line_to(p),
+                        PathCommand::QuadraticTo(p1, p) => path.quadratic_to(p1, p),
+                        PathCommand::CubicTo(p1, p2, p) => path.cubic_to(p1, p2, p),
+                        PathCommand::Close => path.close(),
+                    }
+                }
+            }
+            "rect" => {
+                let x = self.length(element, "x", false).unwrap_or(0.0);
+                let y = self.length(element, "y", true).unwrap_or(0.0);
+                let width = self.length(element, "width", false)?;
+                let height = self.length(element, "height", true)?;
+                if width <= 0.0 || height <= 0.0 {
+                    return None;
+                }
+                let rx = self.length(element, "rx", false);
+                let ry = self.length(element, "ry", true);
+                let (rx, ry) = match (rx, ry) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(r), None) | (None, Some(r)) => (r, r),
+                    (None, None) => (0.0, 0.0),
+                };
+                let rx = rx.clamp(0.0, width * 0.5);
+                let ry = ry.clamp(0.0, height * 0.5);
+                if rx == 0.0 || ry == 0.0 {
+                    path.move_to(Point::new(x, y));
+                    path.line_to(Point::new(x + width, y));
+                    path.line_to(Point::new(x + width, y + height));
+                    path.line_to(Point::new(x, y + height));
+                    path.close();
+                }
+                else {
+                    let kx = rx * KAPPA;
+                    let ky = ry * KAPPA;
+                    let (x1, y1) = (x + width, y + height);
+                    path.move_to(Point::new(x + rx, y));
+                    path.line_to(Point::new(x1 - rx, y));
+                    path.cubic_to(Point::new(x1 - rx + kx, y), Point::new(x1, y + ry - ky), Point::new(x1, y + ry));
+                    path.line_to(Point::new(x1, y1 - ry));
+                    path.cubic_to(Point::new(x1, y1 - ry + ky), Point::new(x1 - rx + kx, y1), Point::new(x1 - rx, y1));
+                    path.line_to(Point::new(x + rx, y1));
+                    path.cubic_to(Point::new(x + rx - kx, y1), Point::new(x, y1 - ry + ky), Point::new(x, y1 - ry));
+                    path.line_to(Point::new(x, y + ry));
+                    path.cubic_to(Point::new(x, y + ry - ky), Point::new(x + rx - kx, y), Point::new(x + rx, y));
+                    path.close();
+                }
+            }
+            "circle" | "ellipse" => {
+                let cx = self.length(element, "cx", false).unwrap_or(0.0);
+                let cy = self.length(element, "cy", true).unwrap_or(0.0);
+                let (rx, ry) = if name == "circle" {
+                    let r = self.length(element, "r", false)?;
+                    (r, r)
+                }
+                else {
+                    (self.length(element, "rx", false)?, self.length(element, "ry", true)?)
+                };
+                if rx <= 0.0 || ry <= 0.0 {
+                    return None;
+                }
+                push_ellipse(&mut path, Point::new(cx, cy), rx, ry);
+            }
+            "line" => {
+                let x1 = self.length(element, "x1", false).unwrap_or(0.0);
+                let y1 = self.length(element, "y1", true).unwrap_or(0.0);
+                let x2 = self.length(element, "x2", false).unwrap_or(0.0);
+                let y2 = self.length(element, "y2", true).unwrap_or(0.0);
+                path.move_to(Point::new(x1, y1));
+                path.line_to(Point::new(x2, y2));
+            }
+            "polyline" | "polygon" => {
+                let numbers = parse_number_list(element.attribute("points")?);
+                let mut points = numbers.chunks_exact(2).map(|pair| Point::new(pair[0], pair[1]));
+                path.move_to(points.next()?);
+                for p in points {
+                    path.line_to(p);
+                }
+                if name == "polygon" {
+                    path.close();
+                }
+            }
+            _ => return None
+        }
+        Some(path)
+    }
+
+    fn push_shape(&mut self, path: Path, style: &Style, transform: AffineTransformation) {
+        let bounds = path_bounds(&path);
+        let fill = self.resolve_paint(&style.fill, style, style.fill_opacity * style.opacity, bounds);
+        let stroke = if style.stroke_width > 0.0 {
+            self.resolve_paint(&style.stroke, style, style.stroke_opacity * style.opacity, bounds)
+        }
+        else {
+            None
+        };
+        if fill.is_none() && stroke.is_none() {
+            return;
+        }
+        self.shapes.push(Shape {
+            path,
+            transform,
+            fill,
+            fill_rule: style.fill_rule,
+            stroke,
+            stroke_style: StrokeStyle {
+                width: style.stroke_width,
+                join: style.line_join,
+                cap: style.line_cap,
+                miter_limit: style.miter_limit,
+                dashes: style.dashes.clone(),
+                dash_offset: style.dash_offset,
+            },
+        });
+    }
+
+    fn resolve_paint(
+        &self,
+        source: &PaintSource,
+        style: &Style,
+        opacity: f64,
+        bounds: Option<Rectangle>,
+    ) -> Option<Paint> {
+        match source {
+            PaintSource::None => None,
+            PaintSource::Color(color) => Some(Paint::Color(color.with_opacity(opacity))),
+            PaintSource::CurrentColor => Some(Paint::Color(style.color.with_opacity(opacity))),
+            PaintSource::Url(id, fallback) => match self.ids.get(id.as_str()) {
+                Some(element) => self.resolve_gradient(element, style, opacity, bounds),
+                None => fallback.map(|color| Paint::Color(color.with_opacity(opacity))),
+            },
+        }
+    }
+
+    fn resolve_gradient(
+        &self,
+        element: &Element,
+        style: &Style,
+        opacity: f64,
+        bounds: Option<Rectangle>,
+    ) -> Option<Paint> {
+        let name = local_name(&element.name);
+        if name != "linearGradient" && name != "radialGradient" {
+            return None;
+        }
+        // attributes and stops can be inherited through href chains
+        let mut chain = vec![element];
+        while chain.len() < 8 {
+            let Some(next) = chain
+                .last()
+                .unwrap()
+                .attribute("href")
+                .and_then(|href| href.strip_prefix('#'))
+                .and_then(|id| self.ids.get(id).copied())
+            else {
+                break
+            };
+            chain.push(next);
+        }
+        let attribute = |key: &str| chain.iter().find_map(|element| element.attribute(key));
+        let stops_element = chain.iter().find(|element| {
+            element.children.iter().any(|child| local_name(&child.name) == "stop")
+        })?;
+        let mut stops = Vec::new();
+        for stop in stops_element.children.iter().filter(|child| local_name(&child.name) == "stop") {
+            let mut stop_style = Style {
+                color: style.color,
+                ..Style::default()
+            };
+            let mut color = Color::new(0.0, 0.0, 0.0, 1.0);
+            let mut stop_opacity = 1.0;
+            let mut properties: Vec<(&str, &str)> = stop
+                .attributes
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect();
+            if let Some(style) = stop.attribute("style") {
+                for declaration in style.split(';') {
+                    if let Some((key, value)) = declaration.split_once(':') {
+                        properties.push((key.trim(), value.trim()));
+                    }
+                }
+            }
+            for (key, value) in properties {
+                match key {
+                    "stop-color" => {
+                        if value.trim() == "currentColor" {
+                            color = stop_style.color;
+                        }
+                        else if let Some(parsed) = parse_color(value) {
+                            color = parsed;
+                        }
+                    }
+                    "stop-opacity" => stop_opacity = parse_opacity(value, 1.0),
+                    "color" => {
+                        if let Some(parsed) = parse_color(value) {
+                            stop_style.color = parsed;
+                        }
+                    }
+                    _ => ()
+                }
+            }
+            let offset = stop.attribute("offset").map_or(0.0, parse_fraction).clamp(0.0, 1.0);
+            // offsets never decrease
+            let offset = stops.last().map_or(offset, |last: &GradientStop| offset.max(last.offset));
+            stops.push(GradientStop {
+                offset,
+                color: color.with_opacity(stop_opacity * opacity),
+            });
+        }
+        if stops.is_empty() {
+            return None;
+        }
+        if stops.len() == 1 {
+            return Some(Paint::Color(stops[0].color));
+        }
+        let user_space = attribute("gradientUnits") == Some("userSpaceOnUse");
+        let mut gradient_transform = attribute("gradientTransform")
+            .map(parse_transform)
+            .unwrap_or_else(AffineTransformation::identity);
+        let coordinate = |key: &str, default: f64, vertical: bool| {
+            attribute(key).map_or(default, |value| {
+                if user_space {
+                    if let Some(percent) = value.trim().strip_suffix('%') {
+                        let reference = if vertical {self.view_box_size.y} else {self.view_box_size.x};
+                        return percent.trim().parse::<f64>().unwrap_or(0.0) / 100.0 * reference;
+                    }
+                    parse_length(value).unwrap_or(default)
+                }
+                else {
+                    parse_fraction(value)
+                }
+            })
+        };
+        if !user_space {
+            // map the unit square onto the bounding box of the shape
+            let bounds = bounds?;
+            let size = bounds.p_max - bounds.p_min;
+            if size.x <= 0.0 || size.y <= 0.0 {
+                return None;
+            }
+            gradient_transform = AffineTransformation::new(
+                LinearTransformation::scaling(size),
+                bounds.p_min.to_vector(),
+            )
+            .compose(gradient_transform);
+        }
+        if name == "linearGradient" {
+            let start = Point::new(coordinate("x1", 0.0, false), coordinate("y1", 0.0, true));
+            let end = Point::new(
+                coordinate("x2", if user_space {self.view_box_size.x} else {1.0}, false),
+                coordinate("y2", 0.0, true),
+            );
+            Some(Paint::LinearGradient {
+                start: gradient_transform.transform_point(start),
+                end: gradient_transform.transform_point(end),
+                stops,
+            })
+        }
+        else {
+            let center = Point::new(
+                coordinate("cx", if user_space {self.view_box_size.x * 0.5} else {0.5}, false),
+                coordinate("cy", if user_space {self.view_box_size.y * 0.5} else {0.5}, true),
+            );
+            let radius = coordinate("r", if user_space {self.view_box_size.x * 0.5} else {0.5}, false);
+            // a non uniform transform makes the gradient elliptical, which is approximated by
+            // a circle with the average radius
+            let x_axis = gradient_transform.xy.x * radius;
+            let y_axis = gradient_transform.xy.y * radius;
+            Some(Paint::RadialGradient {
+                center: gradient_transform.transform_point(center),
+                radius: (x_axis.length() + y_axis.length()) * 0.5,
+                stops,
+            })
+        }
+    }
+}
+
+// cubic approximation of a quarter circle
+const KAPPA: f64 = 0.552_284_749_831;
+
+fn push_ellipse(path: &mut Path, c: Point, rx: f64, ry: f64) {
+    let kx = rx * KAPPA;
+    let ky = ry * KAPPA;
+    path.move_to(Point::new(c.x + rx, c.y));
+    path.cubic_to(Point::new(c.x + rx, c.y + ky), Point::new(c.x + kx, c.y + ry), Point::new(c.x, c.y + ry));
+    path.cubic_to(Point::new(c.x - kx, c.y + ry), Point::new(c.x - rx, c.y + ky), Point::new(c.x - rx, c.y));
+    path.cubic_to(Point::new(c.x - rx, c.y - ky), Point::new(c.x - kx, c.y - ry), Point::new(c.x, c.y - ry));
+    path.cubic_to(Point::new(c.x + kx, c.y - ry), Point::new(c.x + rx, c.y - ky), Point::new(c.x + rx, c.y));
+    path.close();
+}
+
+/// Returns the bounding box of the points of `path`, including its control points.
+pub fn path_bounds(path: &Path) -> Option<Rectangle> {
+    let mut points = path.points().iter();
+    let first = *points.next()?;
+    let mut bounds = Rectangle::new(first, first);
+    for p in points {
+        bounds.p_min.x = bounds.p_min.x.min(p.x);
+        bounds.p_min.y = bounds.p_min.y.min(p.y);
+        bounds.p_max.x = bounds.p_max.x.max(p.x);
+        bounds.p_max.y = bounds.p_max.y.max(p.y);
+    }
+    Some(bounds)
+}
+
+fn parse_aspect_ratio(value: &str) -> AspectRatio {
+    let mut words = value.split_whitespace().filter(|word| *word != "defer");
+    let align = words.next().unwrap_or("");
+    let slice = words.next() == Some("slice");
+    if align == "none" {
+        return AspectRatio { align: None, slice };
+    }
+    let fraction = |name: &str| match name {
+        "Min" => Some(0.0),
+        "Mid" => Some(0.5),
+        "Max" => Some(1.0),
+        _ => None
+    };
+    let align = align
+        .strip_prefix('x')
+        .filter(|rest| rest.len() == 7 && rest.is_char_boundary(3))
+        .and_then(|rest| Some((fraction(&rest[..3])?, fraction(rest[3..].strip_prefix('Y')?)?)));
+    AspectRatio {
+        align: Some(align.unwrap_or((0.5, 0.5))),
+        slice,
+    }
+}
+
+fn parse_paint(value: &str) -> PaintSource {
+    let value = value.trim();
+    if value == "none" {
+        return PaintSource::None;
+    }
+    if value == "currentColor" {
+        return PaintSource::CurrentColor;
+    }
+    if let Some(rest) = value.strip_prefix("url(") {
+        if let Some(end) = rest.find(')') {
+            let id = rest[..end].trim().trim_matches(|c| c == '\'' || c == '"');
+            let id = id.strip_prefix('#').unwrap_or(id).to_string();
+            let fallback = parse_color(rest[end + 1..].trim());
+            return PaintSource::Url(id, fallback);
+        }
+    }
+    match parse_color(value) {
+        Some(color) => PaintSource::Color(color),
+        None => PaintSource::None,
+    }
+}
+
+/// Parses a CSS color in hex, `rgb()`/`rgba()` or named notation.
+pub fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digit = |index: usize| u8::from_str_radix(hex.get(index..index + 1)?, 16).ok();
+        let byte = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+        return match hex.len() {
+            3 | 4 => {
+                let a = if hex.len() == 4 {digit(3)? * 17} else {255};
+                Some(rgba8(digit(0)? * 17, digit(1)? * 17, digit(2)? * 17, a))
+            }
+            6 | 8 => {
+                let a = if hex.len() == 8 {byte(6)?} else {255};
+                Some(rgba8(byte(0)?, byte(2)?, byte(4)?, a))
+            }
+            _ => None
+        };
+    }
+    if let Some(rest) = value.strip_prefix("rgba(").or_else(|| value.strip_prefix("rgb(")) {
+        let rest = rest.strip_suffix(')')?;
+        let parts: Vec<&str> = rest.split(|c| c == ',' || c == ' ' || c == '/').filter(|part| !part.is_empty()).collect();
+        if parts.len() < 3 {
+            return None;
+        }
+        let channel = |part: &str| -> Option<f32> {
+            match part.strip_suffix('%') {
+                Some(percent) => Some(percent.parse::<f32>().ok()? / 100.0),
+                None => Some(part.parse::<f32>().ok()? / 255.0),
+            }
+        };
+        let a = match parts.get(3) {
+            Some(part) => parse_opacity(part, 1.0) as f32,
+            None => 1.0,
+        };
+        return Some(Color::new(
+            channel(parts[0])?.clamp(0.0, 1.0),
+            channel(parts[1])?.clamp(0.0, 1.0),
+            channel(parts[2])?.clamp(0.0, 1.0),
+            a,
+        ));
+    }
+    let (r, g, b) = match value.to_ascii_lowercase().as_str() {
+        "black" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "red" => (255, 0, 0),
+        "green" => (0, 128, 0),
+        "lime" => (0, 255, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "cyan" | "aqua" => (0, 255, 255),
+        "magenta" | "fuchsia" => (255, 0, 255),
+        "gray" | "grey" => (128, 128, 128),
+        "darkgray" | "darkgrey" => (169, 169, 169),
+        "lightgray" | "lightgrey" => (211, 211, 211),
+        "silver" => (192, 192, 192),
+        "maroon" => (128, 0, 0),
+        "olive" => (128, 128, 0),
+        "navy" => (0, 0, 128),
+        "purple" => (128, 0, 128),
+        "teal" => (0, 128, 128),
+        "orange" => (255, 165, 0),
+        "pink" => (255, 192, 203),
+        "brown" => (165, 42, 42),
+        "gold" => (255, 215, 0),
+        "transparent" => return Some(Color::new(0.0, 0.0, 0.0, 0.0)),
+        _ => return None
+    };
+    Some(rgba8(r, g, b, 255))
+}
+
+fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Color {
+    Color::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+}
+
+fn parse_opacity(value: &str, default: f64) -> f64 {
+    let value = value.trim();
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|percent| percent / 100.0),
+        None => value.parse::<f64>(),
+    };
+    opacity.map_or(default, |opacity| opacity.clamp(0.0, 1.0))
+}
+
+// a number or percentage, where percentages become fractions
+fn parse_fraction(value: &str) -> f64 {
+    let value = value.trim();
+    match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map_or(0.0, |percent| percent / 100.0),
+        None => value.parse().unwrap_or(0.0),
+    }
+}
+
+/// Parses a length, ignoring any unit. Percentages are not resolved and return None.
+pub fn parse_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.ends_with('%') {
+        return None;
+    }
+    parse_number(value.as_bytes()).map(|(number, _)| number)
+}
+
+/// Parses a list of numbers separated by whitespace and/or commas.
+pub fn parse_number_list(value: &str) -> Vec<f64> {
+    let bytes = value.as_bytes();
+    let mut numbers = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        if bytes[pos].is_ascii_whitespace() || bytes[pos] == b',' {
+            pos += 1;
+            continue;
+        }
+        match parse_number(&bytes[pos..]) {
+            Some((number, len)) => {
+                numbers.push(number);
+                pos += len;
+            }
+            None => break
+        }
+    }
+    numbers
+}
+
+/// Parses the value of a `transform` attribute.
+pub fn parse_transform(value: &str) -> AffineTransformation {
+    let mut transform = AffineTransformation::identity();
+    let mut rest = value;
+    while let Some(open) = rest.find('(') {
+        let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let Some(close) = rest[open..].find(')') else {
+            break
+        };
+        let args = parse_number_list(&rest[open + 1..open + close]);
+        rest = &rest[open + close + 1..];
+        let arg = |index: usize, default: f64| args.get(index).copied().unwrap_or(default);
+        let next = match name {
+            "matrix" if args.len() == 6 => AffineTransformation::new(
+                LinearTransformation::new(Vector::new(args[0], args[1]), Vector::new(args[2], args[3])),
+                Vector::new(args[4], args[5]),
+            ),
+            "translate" => AffineTransformation::translation(Vector::new(arg(0, 0.0), arg(1, 0.0))),
+            "scale" => {
+                let x = arg(0, 1.0);
+                AffineTransformation::scaling(Vector::new(x, arg(1, x)))
+            }
+            "rotate" => {
+                let (sin, cos) = arg(0, 0.0).to_radians().sin_cos();
+                let rotation = AffineTransformation::new(
+                    LinearTransformation::new(Vector::new(cos, sin), Vector::new(-sin, cos)),
+                    Vector::zero(),
+                );
+                let center = Vector::new(arg(1, 0.0), arg(2, 0.0));
+                AffineTransformation::translation(center)
+                    .compose(rotation)
+                    .compose(AffineTransformation::translation(-center))
+            }
+            "skewX" => AffineTransformation::new(
+                LinearTransformation::new(Vector::new(1.0, 0.0), Vector::new(arg(0, 0.0).to_radians().tan(), 1.0)),
+                Vector::zero(),
+            ),
+            "skewY" => AffineTransformation::new(
+                LinearTransformation::new(Vector::new(1.0, arg(0, 0.0).to_radians().tan()), Vector::new(0.0, 1.0)),
+                Vector::zero(),
+            ),
+            _ => continue
+        };
+        transform = transform.compose(next);
+    }
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_point(p: Point, x: f64, y: f64) {
+        assert!((p.x - x).abs() < 1e-9 && (p.y - y).abs() < 1e-9, "expected ({}, {}), got {:?}", x, y, p);
+    }
+
+    fn shapes(body: &str) -> Vec<Shape> {
+        Document::parse(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\">{}</svg>", body)).unwrap().shapes
+    }
+
+    #[test]
+    fn transform_lists() {
+        let p = Point::new(1.0, 1.0);
+        // the last transform in the list is applied first
+        assert_point(parse_transform("translate(10 20) scale(2)").transform_point(p), 12.0, 22.0);
+        assert_point(parse_transform("scale(2),translate(10,20)").transform_point(p), 22.0, 42.0);
+        assert_point(parse_transform("scale(2 3)").transform_point(p), 2.0, 3.0);
+        assert_point(parse_transform("translate(5)").transform_point(p), 6.0, 1.0);
+        assert_point(parse_transform("rotate(90)").transform_point(p), -1.0, 1.0);
+        assert_point(parse_transform("rotate(90 1 0)").transform_point(Point::new(2.0, 0.0)), 1.0, 1.0);
+        assert_point(parse_transform("matrix(1 0 0 1 5 6)").transform_point(p), 6.0, 7.0);
+        assert_point(parse_transform("matrix(0,1,-1,0,0,0)").transform_point(Point::new(1.0, 0.0)), 0.0, 1.0);
+        assert_point(parse_transform("skewX(45)").transform_point(Point::new(0.0, 1.0)), 1.0, 1.0);
+        assert_point(parse_transform("skewY(45)").transform_point(Point::new(1.0, 0.0)), 1.0, 1.0);
+        // unknown and malformed entries are skipped
+        assert_point(parse_transform("bogus(1) translate(1 1) matrix(1 2)").transform_point(p), 2.0, 2.0);
+        // transforms of groups and elements compose
+        let shapes = shapes("<g transform=\"translate(10 0)\"><rect transform=\"scale(2)\" width=\"1\" height=\"1\"/></g>");
+        assert_point(shapes[0].transform.transform_point(p), 12.0, 2.0);
+    }
+
+    #[test]
+    fn colors() {
+        let rgba = |r: u8, g: u8, b: u8, a: u8| Some(rgba8(r, g, b, a));
+        assert_eq!(parse_color("#f00"), rgba(255, 0, 0, 255));
+        assert_eq!(parse_color("#f008"), rgba(255, 0, 0, 0x88));
+        assert_eq!(parse_color("#12ab9C"), rgba(0x12, 0xab, 0x9c, 255));
+        assert_eq!(parse_color("#12ab9c80"), rgba(0x12, 0xab, 0x9c, 0x80));
+        assert_eq!(parse_color("rgb(255, 0, 128)"), rgba(255, 0, 128, 255));
+        assert_eq!(parse_color("rgb(100%,0%,50%)"), Some(Color::new(1.0, 0.0, 0.5, 1.0)));
+        assert_eq!(parse_color("rgba(0,0,255,0.5)"), Some(Color::new(0.0, 0.0, 1.0, 0.5)));
+        assert_eq!(parse_color("rgb(0 0 255 / 50%)"), Some(Color::new(0.0, 0.0, 1.0, 0.5)));
+        assert_eq!(parse_color(" Orange "), rgba(255, 165, 0, 255));
+        assert_eq!(parse_color("transparent"), Some(Color::new(0.0, 0.0, 0.0, 0.0)));
+        assert_eq!(parse_color("#ff"), None);
+        assert_eq!(parse_color("#ggg"), None);
+        assert_eq!(parse_color("rgb(1,2)"), None);
+        assert_eq!(parse_color("nocolor"), None);
+    }
+
+    #[test]
+    fn paints() {
+        assert_eq!(parse_paint("none"), PaintSource::None);
+        assert_eq!(parse_paint("currentColor"), PaintSource::CurrentColor);
+        assert_eq!(parse_paint("red"), PaintSource::Color(rgba8(255, 0, 0, 255)));
+        assert_eq!(parse_paint("url(#a)"), PaintSource::Url("a".to_string(), None));
+        assert_eq!(parse_paint("url('#a') blue"), PaintSource::Url("a".to_string(), Some(rgba8(0, 0, 255, 255))));
+
+        let red = Some(Paint::Color(rgba8(255, 0, 0, 255)));
+        // fill defaults to black and stroke to nothing
+        let shape = &shapes("<rect width=\"1\" height=\"1\"/>")[0];
+        assert_eq!(shape.fill, Some(Paint::Color(rgba8(0, 0, 0, 255))));
+        assert_eq!(shape.stroke, None);
+        let shape = &shapes("<rect fill=\"none\" stroke=\"red\" stroke-width=\"2\" width=\"1\" height=\"1\"/>")[0];
+        assert_eq!(shape.fill, None);
+        assert_eq!(shape.stroke, red);
+        assert_eq!(shape.stroke_style.width, 2.0);
+        // a shape that paints nothing is dropped
+        assert!(shapes("<rect fill=\"none\" width=\"1\" height=\"1\"/>").is_empty());
+        assert!(shapes("<g fill=\"none\"><rect width=\"1\" height=\"1\"/></g>").is_empty());
+        // the style attribute goes before presentation attributes, and inherit keeps the parent
+        let shape = &shapes("<g fill=\"red\"><rect fill=\"blue\" style=\"fill: inherit\" width=\"1\" height=\"1\"/></g>")[0];
+        assert_eq!(shape.fill, red);
+        let shape = &shapes("<rect fill=\"blue\" style=\"fill:red\" width=\"1\" height=\"1\"/>")[0];
+        assert_eq!(shape.fill, red);
+        let shape = &shapes("<g color=\"red\"><rect fill=\"currentColor\" width=\"1\" height=\"1\"/></g>")[0];
+        assert_eq!(shape.fill, red);
+        // opacities multiply into the color
+        let shape = &shapes("<g opacity=\"0.5\"><rect fill=\"red\" fill-opacity=\"50%\" width=\"1\" height=\"1\"/></g>")[0];
+        assert_eq!(shape.fill, Some(Paint::Color(Color::new(1.0, 0.0, 0.0, 0.25))));
+        // a missing gradient falls back to the color after it
+        let shape = &shapes("<rect fill=\"url(#missing) red\" width=\"1\" height=\"1\"/>")[0];
+        assert_eq!(shape.fill, red);
+        let shape = &shapes("<linearGradient id=\"g\"><stop offset=\"0\" stop-color=\"red\"/><stop offset=\"1\" stop-color=\"blue\"/></linearGradient><rect fill=\"url(#g)\" width=\"2\" height=\"1\"/>")[0];
+        let Some(Paint::LinearGradient {start, end, stops}) = &shape.fill else {
+            panic!("expected a linear gradient, got {:?}", shape.fill)
+        };
+        assert_point(*start, 0.0, 0.0);
+        assert_point(*end, 2.0, 0.0);
+        assert_eq!(stops.len(), 2);
+    }
+
+    #[test]
+    fn view_box() {
+        let document = Document::parse("<svg viewBox=\"10 20 100 50\" width=\"200\"></svg>").unwrap();
+        assert_eq!(document.view_box, Rectangle::new(Point::new(10.0, 20.0), Point::new(110.0, 70.0)));
+        assert_eq!((document.width, document.height), (200.0, 50.0));
+        // without a view box the size is used, and a broken one is ignored
+        let document = Document::parse("<svg width=\"30px\" height=\"40\"></svg>").unwrap();
+        assert_eq!(document.view_box, Rectangle::new(Point::origin(), Point::new(30.0, 40.0)));
+        let document = Document::parse("<svg viewBox=\"0 0 0 10\" width=\"30\" height=\"40\"></svg>").unwrap();
+        assert_eq!(document.view_box, Rectangle::new(Point::origin(), Point::new(30.0, 40.0)));
+        assert!(Document::parse("<g></g>").is_err());
+    }
+
+    #[test]
+    fn preserve_aspect_ratio() {
+        let fit = |attribute: &str, corner: Point| {
+            let document = Document::parse(&format!("<svg viewBox=\"10 20 100 50\" {}></svg>", attribute)).unwrap();
+            // map the view box into a square viewport
+            let transform = document.view_box_transform(Vector::new(200.0, 200.0));
+            (transform.transform_point(Point::new(10.0, 20.0)), transform.transform_point(corner))
+        };
+        let max = Point::new(110.0, 70.0);
+        // meet in the middle by default
+        let (min, _) = fit("", max);
+        assert_point(min, 0.0, 50.0);
+        let (min, _) = fit("preserveAspectRatio=\"xMidYMid meet\"", max);
+        assert_point(min, 0.0, 50.0);
+        let (min, _) = fit("preserveAspectRatio=\"xMinYMin\"", max);
+        assert_point(min, 0.0, 0.0);
+        let (min, corner) = fit("preserveAspectRatio=\"xMaxYMax meet\"", max);
+        assert_point(min, 0.0, 100.0);
+        assert_point(corner, 200.0, 200.0);
+        // slice covers the viewport and crops the rest
+        let (min, corner) = fit("preserveAspectRatio=\"xMinYMid slice\"", max);
+        assert_point(min, 0.0, 0.0);
+        assert_point(corner, 400.0, 200.0);
+        let (min, _) = fit("preserveAspectRatio=\"defer xMaxYMax slice\"", max);
+        assert_point(min, -200.0, 0.0);
+        // none stretches
+        let (min, corner) = fit("preserveAspectRatio=\"none\"", max);
+        assert_point(min, 0.0, 0.0);
+        assert_point(corner, 200.0, 200.0);
+        // a value that makes no sense is the default
+        let (min, _) = fit("preserveAspectRatio=\"xLeftYTop\"", max);
+        assert_point(min, 0.0, 50.0);
+    }
+}
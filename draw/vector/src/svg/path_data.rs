@@ -0,0 +1,453 @@
+use crate::geometry::{Point, Vector};
+use crate::path::PathCommand;
+use std::f64::consts::PI;
+
+/// Parses the contents of an SVG `d` attribute into a sequence of path commands.
+///
+/// All commands are supported. Relative commands are made absolute, horizontal and vertical lines
+/// become line segments, smooth curves get their reflected control point, and elliptical arcs are
+/// approximated with cubic curves.
+pub fn parse_path_data(data: &str) -> Result<Vec<PathCommand>, String> {
+    let mut parser = PathDataParser {
+        bytes: data.as_bytes(),
+        pos: 0,
+    };
+    let mut out = Vec::new();
+    let mut current = Point::origin();
+    let mut start = Point::origin();
+    // the second control point of the last curve, for smooth continuations
+    let mut last_cubic: Option<Point> = None;
+    let mut last_quadratic: Option<Point> = None;
+    let mut command = None;
+    loop {
+        parser.skip_separators();
+        let Some(&byte) = parser.bytes.get(parser.pos) else {
+            break
+        };
+        if byte.is_ascii_alphabetic() {
+            parser.pos += 1;
+            command = Some(byte);
+            if byte == b'Z' || byte == b'z' {
+                out.push(PathCommand::Close);
+                current = start;
+                last_cubic = None;
+                last_quadratic = None;
+                continue;
+            }
+        }
+        let Some(cmd) = command else {
+            return Err(format!("Expected a command at {}", parser.pos));
+        };
+        let relative = cmd.is_ascii_lowercase();
+        let offset = if relative {current.to_vector()} else {Vector::zero()};
+        let mut next_cubic = None;
+        let mut next_quadratic = None;
+        match cmd.to_ascii_uppercase() {
+            b'M' => {
+                current = parser.point()? + offset;
+                start = current;
+                out.push(PathCommand::MoveTo(current));
+                // coordinate pairs after a move are implicit lines
+                command = Some(if relative {b'l'} else {b'L'});
+            }
+            b'L' => {
+                current = parser.point()? + offset;
+                out.push(PathCommand::LineTo(current));
+            }
+            b'H' => {
+                let x = parser.number()?;
+                current = Point::new(if relative {current.x + x} else {x}, current.y);
+                out.push(PathCommand::LineTo(current));
+            }
+            b'V' => {
+                let y = parser.number()?;
+                current = Point::new(current.x, if relative {current.y + y} else {y});
+                out.push(PathCommand::LineTo(current));
+            }
+            b'C' => {
+                let p1 = parser.point()? + offset;
+                let p2 = parser.point()? + offset;
+                current = parser.point()? + offset;
+                out.push(PathCommand::CubicTo(p1, p2, current));
+                next_cubic = Some(p2);
+            }
+            b'S' => {
+                let p1 = reflect(last_cubic, current);
+                let p2 = parser.point()? + offset;
+                current = parser.point()? + offset;
+                out.push(PathCommand::CubicTo(p1, p2, current));
+                next_cubic = Some(p2);
+            }
+            b'Q' => {
+                let p1 = parser.point()? + offset;
+                current = parser.point()? + offset;
+                out.push(PathCommand::QuadraticTo(p1, current));
+                next_quadratic = Some(p1);
+            }
+            b'T' => {
+                let p1 = reflect(last_quadratic, current);
+                current = parser.point()? + offset;
+                out.push(PathCommand::QuadraticTo(p1, current));
+                next_quadratic = Some(p1);
+            }
+            b'A' => {
+                let rx = parser.number()?;
+                let ry = parser.number()?;
+                let rotation = parser.number()?;
+                let large_arc = parser.flag()?;
+                let sweep = parser.flag()?;
+                let p = parser.point()? + offset;
+                arc_to(&mut out, current, rx, ry, rotation, large_arc, sweep, p);
+                current = p;
+            }
+            _ => return Err(format!("Unknown path command {}", cmd as char))
+        }
+        last_cubic = next_cubic;
+        last_quadratic = next_quadratic;
+    }
+    Ok(out)
+}
+
+fn reflect(control: Option<Point>, current: Point) -> Point {
+    match control {
+        Some(control) => current + (current - control),
+        None => current
+    }
+}
+
+/// Appends cubic curves approximating an SVG elliptical arc from `p0` to `p`.
+#[allow(clippy::too_many_arguments)]
+pub fn arc_to(
+    out: &mut Vec<PathCommand>,
+    p0: Point,
+    rx: f64,
+    ry: f64,
+    rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+    p: Point,
+) {
+    if p0 == p {
+        return;
+    }
+    let mut rx = rx.abs();
+    let mut ry = ry.abs();
+    if rx == 0.0 || ry == 0.0 {
+        out.push(PathCommand::LineTo(p));
+        return;
+    }
+    // endpoint to center parameterization, see the SVG implementation notes
+    let (sin_phi, cos_phi) = rotation.to_radians().sin_cos();
+    let dx = (p0.x - p.x) * 0.5;
+    let dy = (p0.y - p.y) * 0.5;
+    let x1 = cos_phi * dx + sin_phi * dy;
+    let y1 = -sin_phi * dx + cos_phi * dy;
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        let scale = lambda.sqrt();
+        rx *= scale;
+        ry *= scale;
+    }
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut factor = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        factor = -factor;
+    }
+    let cx1 = factor * rx * y1 / ry;
+    let cy1 = -factor * ry * x1 / rx;
+    let cx = cos_phi * cx1 - sin_phi * cy1 + (p0.x + p.x) * 0.5;
+    let cy = sin_phi * cx1 + cos_phi * cy1 + (p0.y + p.y) * 0.5;
+    let angle = |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let theta1 = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut delta = angle((x1 - cx1) / rx, (y1 - cy1) / ry, (-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    }
+    else if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    }
+    // split into pieces of at most a quarter turn
+    let segments = (delta.abs() / (PI * 0.5)).ceil().max(1.0) as usize;
+    let step = delta / segments as f64;
+    let k = 4.0 / 3.0 * (step * 0.25).tan();
+    let point_at = |theta: f64| {
+        let (sin, cos) = theta.sin_cos();
+        Point::new(
+            cx + rx * cos * cos_phi - ry * sin * sin_phi,
+            cy + rx * cos * sin_phi + ry * sin * cos_phi,
+        )
+    };
+    let derivative_at = |theta: f64| {
+        let (sin, cos) = theta.sin_cos();
+        Vector::new(
+            -rx * sin * cos_phi - ry * cos * sin_phi,
+            -rx * sin * sin_phi + ry * cos * cos_phi,
+        )
+    };
+    let mut theta = theta1;
+    let mut from = p0;
+    for index in 0..segments {
+        let next_theta = theta + step;
+        let to = if index == segments - 1 {p} else {point_at(next_theta)};
+        out.push(PathCommand::CubicTo(
+            from + derivative_at(theta) * k,
+            to - derivative_at(next_theta) * k,
+            to,
+        ));
+        theta = next_theta;
+        from = to;
+    }
+}
+
+struct PathDataParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PathDataParser<'a> {
+    fn skip_separators(&mut self) {
+        while let Some(byte) = self.bytes.get(self.pos) {
+            if !(byte.is_ascii_whitespace() || *byte == b',') {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+        let (value, len) = parse_number(&self.bytes[self.pos..])
+            .ok_or_else(|| format!("Expected a number at {}", self.pos))?;
+        self.pos += len;
+        Ok(value)
+    }
+
+    fn point(&mut self) -> Result<Point, String> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Ok(Point::new(x, y))
+    }
+
+    // arc flags are a single digit and may be written without separators
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        match self.bytes.get(self.pos) {
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(format!("Expected an arc flag at {}", self.pos))
+        }
+    }
+}
+
+/// Parses a number at the start of `bytes`, returning it with the number of bytes it used.
+pub fn parse_number(bytes: &[u8]) -> Option<(f64, usize)> {
+    let mut end = 0;
+    if matches!(bytes.first(), Some(b'+') | Some(b'-')) {
+        end += 1;
+    }
+    let digits_start = end;
+    while bytes.get(end).map_or(false, |b| b.is_ascii_digit()) {
+        end += 1;
+    }
+    if bytes.get(end) == Some(&b'.') {
+        end += 1;
+        while bytes.get(end).map_or(false, |b| b.is_ascii_digit()) {
+            end += 1;
+        }
+    }
+    if end == digits_start || (end == digits_start + 1 && bytes[digits_start] == b'.') {
+        return None;
+    }
+    // only take the exponent if it is followed by digits, so that a unit like `em` is left alone
+    if matches!(bytes.get(end), Some(b'e') | Some(b'E')) {
+        let mut exp_end = end + 1;
+        if matches!(bytes.get(exp_end), Some(b'+') | Some(b'-')) {
+            exp_end += 1;
+        }
+        if bytes.get(exp_end).map_or(false, |b| b.is_ascii_digit()) {
+            while bytes.get(exp_end).map_or(false, |b| b.is_ascii_digit()) {
+                exp_end += 1;
+            }
+            end = exp_end;
+        }
+    }
+    let text = std::str::from_utf8(&bytes[..end]).ok()?;
+    text.parse().ok().map(|value| (value, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(data: &str) -> Vec<(char, Vec<(f64, f64)>)> {
+        parse_path_data(data).unwrap().into_iter().map(|command| {
+            let (verb, points) = match command {
+                PathCommand::MoveTo(p) => ('M', vec![p]),
+                PathCommand::LineTo(p) => ('L', vec![p]),
+                PathCommand::QuadraticTo(p1, p) => ('Q', vec![p1, p]),
+                PathCommand::CubicTo(p1, p2, p) => ('C', vec![p1, p2, p]),
+                PathCommand::Close => ('Z', vec![]),
+            };
+            (verb, points.into_iter().map(|p| (p.x, p.y)).collect())
+        }).collect()
+    }
+
+    fn cubic_at(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> Point {
+        let s = 1.0 - t;
+        Point::new(
+            s * s * s * p0.x + 3.0 * s * s * t * p1.x + 3.0 * s * t * t * p2.x + t * t * t * p3.x,
+            s * s * s * p0.y + 3.0 * s * s * t * p1.y + 3.0 * s * t * t * p2.y + t * t * t * p3.y,
+        )
+    }
+
+    // the curves of an arc after its move to, checked to stay on the circle around `center`
+    fn arc_curves(data: &str, center: Point, radius: f64) -> Vec<PathCommand> {
+        let commands = parse_path_data(data).unwrap();
+        let PathCommand::MoveTo(mut p0) = commands[0] else {
+            panic!("expected a move to")
+        };
+        for command in &commands[1..] {
+            let PathCommand::CubicTo(p1, p2, p3) = *command else {
+                panic!("expected a cubic, got {:?}", command)
+            };
+            for t in [0.0, 0.25, 0.5, 0.75, 1.0] {
+                let distance = (cubic_at(p0, p1, p2, p3, t) - center).length();
+                assert!((distance - radius).abs() < radius * 1e-3, "{} is off the arc at {}", data, t);
+            }
+            p0 = p3;
+        }
+        commands[1..].to_vec()
+    }
+
+    fn end_point(commands: &[PathCommand]) -> Point {
+        match commands.last() {
+            Some(PathCommand::CubicTo(_, _, p)) => *p,
+            command => panic!("expected a cubic, got {:?}", command)
+        }
+    }
+
+    #[test]
+    fn implicit_commands() {
+        // pairs after a move are lines, other commands repeat themselves
+        assert_eq!(
+            points("M0 0 10 0 10 10"),
+            [('M', vec![(0.0, 0.0)]), ('L', vec![(10.0, 0.0)]), ('L', vec![(10.0, 10.0)])]
+        );
+        assert_eq!(
+            points("m1 1 2 0 0 2z"),
+            [('M', vec![(1.0, 1.0)]), ('L', vec![(3.0, 1.0)]), ('L', vec![(3.0, 3.0)]), ('Z', vec![])]
+        );
+        assert_eq!(
+            points("M0 0H1 2V3 4"),
+            [
+                ('M', vec![(0.0, 0.0)]),
+                ('L', vec![(1.0, 0.0)]),
+                ('L', vec![(2.0, 0.0)]),
+                ('L', vec![(2.0, 3.0)]),
+                ('L', vec![(2.0, 4.0)]),
+            ]
+        );
+        assert_eq!(
+            points("M0 0Q1 1 2 0 3 -1 4 0"),
+            [('M', vec![(0.0, 0.0)]), ('Q', vec![(1.0, 1.0), (2.0, 0.0)]), ('Q', vec![(3.0, -1.0), (4.0, 0.0)])]
+        );
+        // numbers run into each other where a sign or a second dot starts the next one
+        assert_eq!(points("M-1-2.5.5.5"), [('M', vec![(-1.0, -2.5)]), ('L', vec![(0.5, 0.5)])]);
+        assert_eq!(points("M1e1,2E-1"), [('M', vec![(10.0, 0.2)])]);
+    }
+
+    #[test]
+    fn relative_and_absolute() {
+        assert_eq!(
+            points("M10 10 l5 0 L0 0 h-2 v3 z l1 1"),
+            [
+                ('M', vec![(10.0, 10.0)]),
+                ('L', vec![(15.0, 10.0)]),
+                ('L', vec![(0.0, 0.0)]),
+                ('L', vec![(-2.0, 0.0)]),
+                ('L', vec![(-2.0, 3.0)]),
+                ('Z', vec![]),
+                // after a close the current point is the start of the contour
+                ('L', vec![(11.0, 11.0)]),
+            ]
+        );
+        assert_eq!(
+            points("M1 1 c1 1 2 2 3 3 q1 0 1 1"),
+            [
+                ('M', vec![(1.0, 1.0)]),
+                ('C', vec![(2.0, 2.0), (3.0, 3.0), (4.0, 4.0)]),
+                ('Q', vec![(5.0, 4.0), (5.0, 5.0)]),
+            ]
+        );
+        // relative moves after the first one are relative too
+        assert_eq!(
+            points("m1 1 m2 2"),
+            [('M', vec![(1.0, 1.0)]), ('M', vec![(3.0, 3.0)])]
+        );
+    }
+
+    #[test]
+    fn smooth_curves() {
+        // the first control point mirrors the last one of the previous curve
+        assert_eq!(
+            points("M0 0C0 1 1 1 1 0S2 -1 2 0")[2],
+            ('C', vec![(1.0, -1.0), (2.0, -1.0), (2.0, 0.0)])
+        );
+        assert_eq!(
+            points("M0 0c0 1 1 1 1 0s1 -1 1 0")[2],
+            ('C', vec![(1.0, -1.0), (2.0, -1.0), (2.0, 0.0)])
+        );
+        assert_eq!(
+            points("M0 0Q1 1 2 0T4 0T6 0")[2..],
+            [('Q', vec![(3.0, -1.0), (4.0, 0.0)]), ('Q', vec![(5.0, 1.0), (6.0, 0.0)])]
+        );
+        // without a curve of the same kind before it, the control point is the current point
+        assert_eq!(points("M0 0S1 1 2 0")[1], ('C', vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)]));
+        assert_eq!(points("M0 0Q1 1 2 0L3 0T4 0")[3], ('Q', vec![(3.0, 0.0), (4.0, 0.0)]));
+        assert_eq!(points("M0 0C0 1 1 1 1 0T2 0")[2], ('Q', vec![(1.0, 0.0), (2.0, 0.0)]));
+    }
+
+    #[test]
+    fn arcs() {
+        // a half circle, with the sweep flag picking the side
+        let above = arc_curves("M0 0A1 1 0 0 1 2 0", Point::new(1.0, 0.0), 1.0);
+        let below = arc_curves("M0 0A1 1 0 0 0 2 0", Point::new(1.0, 0.0), 1.0);
+        assert_eq!(end_point(&above), Point::new(2.0, 0.0));
+        let PathCommand::CubicTo(_, _, mid) = above[0] else {unreachable!()};
+        assert!((mid - Point::new(1.0, -1.0)).length() < 1e-9);
+        let PathCommand::CubicTo(_, _, mid) = below[0] else {unreachable!()};
+        assert!((mid - Point::new(1.0, 1.0)).length() < 1e-9);
+        // flags without separators
+        let compact = arc_curves("M0 0a1 1 0 011 1", Point::new(0.0, 1.0), 1.0);
+        assert_eq!(compact.len(), 1);
+        assert_eq!(end_point(&compact), Point::new(1.0, 1.0));
+        assert_eq!(parse_path_data("M0 0a1,1,0,0,1,1,1").unwrap(), parse_path_data("M0 0a1 1 0 011 1").unwrap());
+        // the large arc flag picks the long way round
+        let center = Point::new(1.0, 3.0f64.sqrt());
+        let small = arc_curves("M0 0A2 2 0 0 1 2 0", center, 2.0);
+        let large = arc_curves("M0 0A2 2 0 1 0 2 0", center, 2.0);
+        assert_eq!(small.len(), 1);
+        assert_eq!(large.len(), 4);
+        // radii that are too small are scaled up until the arc fits
+        arc_curves("M0 0A0.5 0.5 0 0 1 2 0", Point::new(1.0, 0.0), 1.0);
+        // a zero radius is a line and an arc to the current point is nothing
+        assert_eq!(points("M0 0A0 1 0 0 1 2 0")[1], ('L', vec![(2.0, 0.0)]));
+        assert_eq!(points("M0 0A1 1 0 0 1 0 0").len(), 1);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_path_data("0 0").is_err());
+        assert!(parse_path_data("M0 0X1").is_err());
+        assert!(parse_path_data("M0 0L1").is_err());
+        assert!(parse_path_data("M0 0A1 1 0 2 1 1 1").is_err());
+        assert_eq!(parse_path_data("").unwrap(), []);
+    }
+}
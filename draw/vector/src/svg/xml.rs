@@ -0,0 +1,244 @@
+/// An element of a parsed XML document. Text content is dropped, as the SVG subset we render does
+/// not use it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    /// Returns the value of the attribute with the given name, ignoring any namespace prefix.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name || key.rsplit(':').next() == Some(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parses an XML document and returns its root element.
+pub fn parse_xml(data: &str) -> Result<Element, String> {
+    let bytes = data.as_bytes();
+    let mut pos = 0;
+    // the root is a synthetic element that collects the top level elements
+    let mut stack = vec![Element::default()];
+    while pos < bytes.len() {
+        if bytes[pos] != b'<' {
+            pos += 1;
+            continue;
+        }
+        let rest = &data[pos..];
+        if rest.starts_with("<!--") {
+            pos += find(rest, "-->")? + 3;
+        }
+        else if rest.starts_with("<![CDATA[") {
+            pos += find(rest, "]]>")? + 3;
+        }
+        else if rest.starts_with("<?") {
+            pos += find(rest, "?>")? + 2;
+        }
+        else if rest.starts_with("<!") {
+            pos += skip_declaration(rest)?;
+        }
+        else if rest.starts_with("</") {
+            let end = find(rest, ">")?;
+            let name = rest[2..end].trim();
+            let element = stack.pop().unwrap();
+            if element.name != name || stack.is_empty() {
+                return Err(format!("Unexpected closing tag {}", name));
+            }
+            stack.last_mut().unwrap().children.push(element);
+            pos += end + 1;
+        }
+        else {
+            let (element, self_closing, len) = parse_start_tag(rest)?;
+            pos += len;
+            if self_closing {
+                stack.last_mut().unwrap().children.push(element);
+            }
+            else {
+                stack.push(element);
+            }
+        }
+    }
+    if stack.len() != 1 {
+        return Err(format!("Unclosed tag {}", stack.last().unwrap().name));
+    }
+    stack.pop().unwrap().children.into_iter().next().ok_or_else(|| "Empty document".to_string())
+}
+
+fn find(data: &str, pattern: &str) -> Result<usize, String> {
+    data.find(pattern).ok_or_else(|| format!("Expected {}", pattern))
+}
+
+// doctype declarations can contain an internal subset in brackets
+fn skip_declaration(data: &str) -> Result<usize, String> {
+    let mut depth = 0;
+    for (index, byte) in data.bytes().enumerate() {
+        match byte {
+            b'[' => depth += 1,
+            b']' => depth -= 1,
+            b'>' if depth == 0 => return Ok(index + 1),
+            _ => ()
+        }
+    }
+    Err("Unterminated declaration".to_string())
+}
+
+fn parse_start_tag(data: &str) -> Result<(Element, bool, usize), String> {
+    let bytes = data.as_bytes();
+    let mut pos = 1;
+    let name_start = pos;
+    while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' && bytes[pos] != b'/' {
+        pos += 1;
+    }
+    let mut element = Element {
+        name: data[name_start..pos].to_string(),
+        ..Element::default()
+    };
+    loop {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        match bytes.get(pos) {
+            None => return Err(format!("Unterminated tag {}", element.name)),
+            Some(b'>') => return Ok((element, false, pos + 1)),
+            Some(b'/') => {
+                if bytes.get(pos + 1) != Some(&b'>') {
+                    return Err(format!("Expected > in tag {}", element.name));
+                }
+                return Ok((element, true, pos + 2));
+            }
+            _ => ()
+        }
+        let key_start = pos;
+        while pos < bytes.len() && bytes[pos] != b'=' && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' {
+            pos += 1;
+        }
+        let key = data[key_start..pos].to_string();
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if bytes.get(pos) != Some(&b'=') {
+            // an attribute without a value
+            element.attributes.push((key, String::new()));
+            continue;
+        }
+        pos += 1;
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let quote = match bytes.get(pos) {
+            Some(&quote) if quote == b'"' || quote == b'\'' => quote,
+            _ => return Err(format!("Expected a quoted value for {}", key))
+        };
+        pos += 1;
+        let value_start = pos;
+        while pos < bytes.len() && bytes[pos] != quote {
+            pos += 1;
+        }
+        if pos == bytes.len() {
+            return Err(format!("Unterminated value for {}", key));
+        }
+        element.attributes.push((key, decode_entities(&data[value_start..pos])));
+        pos += 1;
+    }
+}
+
+// the predefined entities and character references, anything else is kept as it is
+fn decode_entities(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "amp" => '&',
+                name => {
+                    let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => name.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elements_and_attributes() {
+        let root = parse_xml("<svg a=\"1\" b='2' c = \"3\"><g><path d=\"M0 0\"/></g><rect/></svg>").unwrap();
+        assert_eq!(root.name, "svg");
+        assert_eq!(root.attributes.len(), 3);
+        assert_eq!((root.attribute("a"), root.attribute("b"), root.attribute("c")), (Some("1"), Some("2"), Some("3")));
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].children[0].attribute("d"), Some("M0 0"));
+        // prefixes are ignored when looking an attribute up
+        let root = parse_xml("<svg><use xlink:href=\"#a\"/></svg>").unwrap();
+        assert_eq!(root.children[0].attribute("href"), Some("#a"));
+    }
+
+    #[test]
+    fn entities() {
+        let root = parse_xml("<a v=\"&lt;&gt;&amp;&quot;&apos;\" w=\"&#65;&#x42;&#X43;\" x=\"&amp;lt; &bogus; & &#xZZ;\"/>").unwrap();
+        assert_eq!(root.attribute("v"), Some("<>&\"'"));
+        assert_eq!(root.attribute("w"), Some("ABC"));
+        // entities are decoded once, and anything unknown stays as it is
+        assert_eq!(root.attribute("x"), Some("&lt; &bogus; & &#xZZ;"));
+    }
+
+    #[test]
+    fn comments_cdata_and_declarations() {
+        let root = parse_xml(
+            "<?xml version=\"1.0\"?>\n\
+             <!DOCTYPE svg [ <!ENTITY e \"x\"> ]>\n\
+             <!-- <notme/> -->\n\
+             <svg><!-- <a> --><style><![CDATA[ a > b { } <c> ]]></style><g/></svg>",
+        )
+        .unwrap();
+        assert_eq!(root.name, "svg");
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.children[0].name, "style");
+        assert!(root.children[0].children.is_empty());
+        assert_eq!(root.children[1].name, "g");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse_xml("").is_err());
+        assert!(parse_xml("<!-- only a comment -->").is_err());
+        assert!(parse_xml("<svg>").is_err());
+        assert!(parse_xml("<svg></g>").is_err());
+        assert!(parse_xml("<svg a=1/>").is_err());
+        assert!(parse_xml("<svg a=\"1/>").is_err());
+        assert!(parse_xml("<svg><!-- </svg>").is_err());
+        assert!(parse_xml("<svg><![CDATA[ </svg>").is_err());
+    }
+}
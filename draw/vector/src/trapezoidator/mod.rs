@@ -6,12 +6,34 @@ use std::collections::BinaryHeap;
 use std::mem;
 use std::ops::Range;
 
+/// The rule that decides which regions of a set of contours are inside.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum FillRule {
+    /// A region is inside if the contours wind around it a nonzero number of times.
+    #[default]
+    NonZero,
+    /// A region is inside if a ray from it crosses the contours an odd number of times.
+    EvenOdd,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
 /// Converts a sequence of line path commands to a sequence of trapezoids. The line path commands
 /// should define a set of closed contours.
 #[derive(Clone, Debug, Default)]
 pub struct Trapezoidator {
     event_queue: BinaryHeap<Event>,
     active_segments: Vec<ActiveSegment>,
+    segments: Vec<LineSegment>,
+    fill_rule: FillRule,
+    split_intersections: bool,
 }
 
 impl Trapezoidator {
@@ -20,36 +42,67 @@ impl Trapezoidator {
         Trapezoidator::default()
     }
 
+    /// Sets the fill rule used by subsequent calls to `trapezoidate`.
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) {
+        self.fill_rule = fill_rule;
+    }
+
+    /// Sets whether subsequent calls to `trapezoidate` split segments at their intersections
+    /// first. Paths with crossing contours need this, font glyphs don't and skip the cost.
+    pub fn set_split_intersections(&mut self, split_intersections: bool) {
+        self.split_intersections = split_intersections;
+    }
+
     /// Returns an iterator over trapezoids corresponding to the given iterator over line path
     /// commands.
+    ///
+    /// Contours that are left open are closed implicitly. Contours may only intersect each other
+    /// or themselves when `set_split_intersections` is on.
     pub fn trapezoidate<P: LinePathIterator>(&mut self, path: P)->Option<Trapezoidate>{
-        let mut initial_point = None;
-        let mut current_point = None;
-        if !path.for_each(&mut |command| {
+        let mut segments = mem::take(&mut self.segments);
+        segments.clear();
+        let mut initial_point: Option<Point> = None;
+        let mut current_point: Option<Point> = None;
+        path.for_each(&mut |command| {
             match command {
                 LinePathCommand::MoveTo(p) => {
-                    //assert!(initial_point == current_point);
+                    if let (Some(p0), Some(p1)) = (current_point, initial_point) {
+                        segments.push(LineSegment::new(p0, p1));
+                    }
                     initial_point = Some(p);
                     current_point = Some(p);
                 }
                 LinePathCommand::LineTo(p) => {
-                    let p0 = current_point.replace(p).unwrap();
-                    if self.push_events_for_segment(LineSegment::new(p0, p)){
-                        return false
+                    if initial_point.is_none() {
+                        initial_point = current_point.or(Some(p));
+                    }
+                    if let Some(p0) = current_point.replace(p) {
+                        segments.push(LineSegment::new(p0, p));
                     }
                 }
                 LinePathCommand::Close => {
-                    let p = initial_point.take().unwrap();
-                    let p0 = current_point.replace(p).unwrap();
-                    if self.push_events_for_segment(LineSegment::new(p0, p)){
-                        return false
+                    if let (Some(p0), Some(p1)) = (current_point, initial_point.take()) {
+                        segments.push(LineSegment::new(p0, p1));
+                        current_point = Some(p1);
                     }
                 }
             }
             true
-        }){
-            return None
-        };
+        });
+        if let (Some(p0), Some(p1)) = (current_point, initial_point) {
+            segments.push(LineSegment::new(p0, p1));
+        }
+        if self.split_intersections {
+            split_at_intersections(&mut segments);
+        }
+        for segment in segments.iter() {
+            if self.push_events_for_segment(*segment) {
+                self.event_queue.clear();
+                self.segments = segments;
+                return None
+            }
+        }
+        self.segments = segments;
         Some(Trapezoidate {
             trapezoidator: self,
         })
@@ -220,13 +273,14 @@ impl Trapezoidator {
         } else {
             self.active_segments[incident_segment_range.end - 1].upper_region
         };
+        let fill_rule = self.fill_rule;
         self.active_segments.splice(
             incident_segment_range.end..incident_segment_range.end,
             Iterator::map(right_segments.iter(), |right_segment| {
                 let upper_region = {
                     let winding = lower_region.winding + right_segment.winding;
                     Region {
-                        is_inside: winding != 0,
+                        is_inside: fill_rule.is_inside(winding),
                         winding,
                     }
                 };
@@ -295,6 +349,87 @@ impl Trapezoidator {
     }
 }
 
+/// Splits the given segments at the points where they intersect each other, so that the remaining
+/// segments only touch at their endpoints.
+fn split_at_intersections(segments: &mut Vec<LineSegment>) {
+    let mut order: Vec<usize> = Iterator::collect(0..segments.len());
+    order.sort_by(|&i, &j| {
+        let x_i = segments[i].p0.x.min(segments[i].p1.x);
+        let x_j = segments[j].p0.x.min(segments[j].p1.x);
+        x_i.partial_cmp(&x_j).unwrap_or(Ordering::Equal)
+    });
+    let mut splits: Vec<(usize, f64, Point)> = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+    for &i in order.iter() {
+        let segment_i = segments[i];
+        let min_x = segment_i.p0.x.min(segment_i.p1.x);
+        active.retain(|&j| segments[j].p0.x.max(segments[j].p1.x) >= min_x);
+        let min_y = segment_i.p0.y.min(segment_i.p1.y);
+        let max_y = segment_i.p0.y.max(segment_i.p1.y);
+        for &j in active.iter() {
+            let segment_j = segments[j];
+            if segment_j.p0.y.max(segment_j.p1.y) < min_y
+                || segment_j.p0.y.min(segment_j.p1.y) > max_y
+            {
+                continue;
+            }
+            if let Some((t, u)) = segment_i.intersect_with_line_segment(segment_j) {
+                let is_interior_i = t > 0.0 && t < 1.0;
+                let is_interior_j = u > 0.0 && u < 1.0;
+                if !is_interior_i && !is_interior_j {
+                    continue;
+                }
+                // Snap to an existing endpoint where possible, so that both segments are split at
+                // exactly the same point.
+                let p = if !is_interior_i {
+                    if t == 0.0 { segment_i.p0 } else { segment_i.p1 }
+                } else if !is_interior_j {
+                    if u == 0.0 { segment_j.p0 } else { segment_j.p1 }
+                } else {
+                    segment_i.p0.lerp(segment_i.p1, t)
+                };
+                if is_interior_i {
+                    splits.push((i, t, p));
+                }
+                if is_interior_j {
+                    splits.push((j, u, p));
+                }
+            }
+        }
+        active.push(i);
+    }
+    if splits.is_empty() {
+        return;
+    }
+    splits.sort_by(|a, b| {
+        a.0.cmp(&b.0).then(a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+    });
+    let mut split_index = 0;
+    let count = segments.len();
+    for i in 0..count {
+        let mut p0 = segments[i].p0;
+        let p1 = segments[i].p1;
+        let mut first = true;
+        while split_index < splits.len() && splits[split_index].0 == i {
+            let p = splits[split_index].2;
+            split_index += 1;
+            if p == p0 || p == p1 {
+                continue;
+            }
+            if first {
+                segments[i].p1 = p;
+                first = false;
+            } else {
+                segments.push(LineSegment::new(p0, p));
+            }
+            p0 = p;
+        }
+        if !first {
+            segments.push(LineSegment::new(p0, p1));
+        }
+    }
+}
+
 /// An iterator over trapezoids corresponding to the given iterator over line path commands.
 #[derive(Debug)]
 pub struct Trapezoidate<'a> {
@@ -489,3 +624,118 @@ mod tests {
         );
     }
 }*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::LinePath;
+
+    fn trapezoidate(contours: &[&[(f64, f64)]], fill_rule: FillRule) -> Vec<Trapezoid> {
+        let mut path = LinePath::new();
+        for contour in contours {
+            path.move_to(Point::new(contour[0].0, contour[0].1));
+            for &(x, y) in &contour[1..] {
+                path.line_to(Point::new(x, y));
+            }
+            path.close();
+        }
+        let mut trapezoidator = Trapezoidator::new();
+        trapezoidator.set_fill_rule(fill_rule);
+        trapezoidator.set_split_intersections(true);
+        let mut trapezoids = Vec::new();
+        trapezoidator.trapezoidate(path.commands()).unwrap().for_each(&mut |trapezoid| {
+            trapezoids.push(trapezoid);
+            true
+        });
+        trapezoids
+    }
+
+    fn area(trapezoids: &[Trapezoid]) -> f32 {
+        Iterator::map(trapezoids.iter(), |t| {
+            (t.xs[1] - t.xs[0]) * ((t.ys[2] - t.ys[0]) + (t.ys[3] - t.ys[1])) / 2.0
+        }).sum()
+    }
+
+    // the number of trapezoids covering a point, which is at most one
+    fn coverage(trapezoids: &[Trapezoid], x: f32, y: f32) -> usize {
+        trapezoids.iter().filter(|t| {
+            let f = (x - t.xs[0]) / (t.xs[1] - t.xs[0]);
+            let bottom = t.ys[0] + (t.ys[1] - t.ys[0]) * f;
+            let top = t.ys[2] + (t.ys[3] - t.ys[2]) * f;
+            x > t.xs[0] && x < t.xs[1] && y > bottom && y < top
+        }).count()
+    }
+
+    #[test]
+    fn self_intersecting_bowtie() {
+        // two triangles of area 1 that touch at (1, 1)
+        let bowtie: &[(f64, f64)] = &[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)];
+        for fill_rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            let trapezoids = trapezoidate(&[bowtie], fill_rule);
+            assert!((area(&trapezoids) - 2.0).abs() < 1e-5);
+            assert_eq!(coverage(&trapezoids, 0.5, 1.0), 1);
+            assert_eq!(coverage(&trapezoids, 1.5, 1.0), 1);
+            assert_eq!(coverage(&trapezoids, 1.0, 0.5), 0);
+            assert_eq!(coverage(&trapezoids, 1.0, 1.5), 0);
+        }
+    }
+
+    #[test]
+    fn self_intersecting_star() {
+        // a pentagram, whose center is wound around twice
+        let star: Vec<(f64, f64)> = Iterator::collect(Iterator::map(0..5, |i| {
+            let angle = std::f64::consts::PI * (0.5 + 0.8 * i as f64);
+            (angle.cos(), angle.sin())
+        }));
+        let non_zero = trapezoidate(&[&star], FillRule::NonZero);
+        let even_odd = trapezoidate(&[&star], FillRule::EvenOdd);
+        assert_eq!(coverage(&non_zero, 0.0, 0.0), 1);
+        assert_eq!(coverage(&even_odd, 0.0, 0.0), 0);
+        // the tips are inside once for both rules
+        assert_eq!(coverage(&non_zero, 0.0, 0.9), 1);
+        assert_eq!(coverage(&even_odd, 0.0, 0.9), 1);
+        assert!(area(&even_odd) < area(&non_zero));
+    }
+
+    #[test]
+    fn overlapping_contours() {
+        let a: &[(f64, f64)] = &[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let b: &[(f64, f64)] = &[(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)];
+        let reversed: Vec<(f64, f64)> = Iterator::collect(b.iter().rev().copied());
+
+        // the overlap is counted once for nonzero and left out for even-odd
+        let non_zero = trapezoidate(&[a, b], FillRule::NonZero);
+        assert!((area(&non_zero) - 7.0).abs() < 1e-5);
+        assert_eq!(coverage(&non_zero, 1.5, 1.5), 1);
+        let even_odd = trapezoidate(&[a, b], FillRule::EvenOdd);
+        assert!((area(&even_odd) - 6.0).abs() < 1e-5);
+        assert_eq!(coverage(&even_odd, 1.5, 1.5), 0);
+
+        // with opposite windings the overlap cancels out for nonzero too
+        let opposite = trapezoidate(&[a, &reversed], FillRule::NonZero);
+        assert!((area(&opposite) - 6.0).abs() < 1e-5);
+        assert_eq!(coverage(&opposite, 1.5, 1.5), 0);
+
+        // a diamond whose slanted edges cross the square, overlapping it in a triangle of area 1
+        let diamond: &[(f64, f64)] = &[(1.0, 1.0), (2.0, 0.0), (3.0, 1.0), (2.0, 2.0)];
+        let non_zero = trapezoidate(&[a, diamond], FillRule::NonZero);
+        assert!((area(&non_zero) - 5.0).abs() < 1e-5);
+        let even_odd = trapezoidate(&[a, diamond], FillRule::EvenOdd);
+        assert!((area(&even_odd) - 4.0).abs() < 1e-5);
+        assert_eq!(coverage(&even_odd, 1.8, 1.0), 0);
+        assert_eq!(coverage(&even_odd, 2.2, 1.0), 1);
+    }
+
+    #[test]
+    fn shared_edges_and_corners() {
+        // two squares side by side and a third touching a corner only
+        let left: &[(f64, f64)] = &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let right: &[(f64, f64)] = &[(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)];
+        let corner: &[(f64, f64)] = &[(2.0, 1.0), (3.0, 1.0), (3.0, 2.0), (2.0, 2.0)];
+        let trapezoids = trapezoidate(&[left, right, corner], FillRule::NonZero);
+        assert!((area(&trapezoids) - 3.0).abs() < 1e-5);
+        assert_eq!(coverage(&trapezoids, 1.0, 0.5), 0);
+        assert_eq!(coverage(&trapezoids, 0.99, 0.5), 1);
+        assert_eq!(coverage(&trapezoids, 1.01, 0.5), 1);
+    }
+}
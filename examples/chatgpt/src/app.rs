@@ -28,12 +28,9 @@ live_design!{
                 }
             }
             
-            message_label = <Label> {
+            message_label = <Markdown> {
                 width: 300,
                 height: Fit
-                draw_text: {
-                    color: #f
-                },
                 text: "hi! how may I assist you today?",
             }
             message_input = <TextInput> {
@@ -96,7 +93,7 @@ impl AppMain for App {
         for event in event.network_responses() {
             match &event.response {
                 NetworkResponse::HttpResponse(response) => {
-                    let label = self.ui.label(id!(message_label));
+                    let label = self.ui.markdown(id!(message_label));
                     match event.request_id {
                         live_id!(SendChatMessage) => {
                             if response.status_code == 200 {
@@ -111,7 +108,7 @@ impl AppMain for App {
                     }
                 }
                 NetworkResponse::HttpRequestError(error) => {
-                    let label = self.ui.label(id!(message_label));
+                    let label = self.ui.markdown(id!(message_label));
                     label.set_text_and_redraw(cx, &format!("Failed to connect with OpenAI {:?}", error));
                 }
                 _ => ()
@@ -238,6 +238,44 @@ pub struct IronFishSettings {
     #[live(0.0)] portamento: f32a,
 }
 
+impl IronFishSettings {
+    // the settings exposed as automatable parameters, with their defaults and smoothing times in seconds
+    const PARAMS: [(LiveId, f64, f64); 10] = [
+        (live_id!(filter_cutoff), 0.5, 0.01),
+        (live_id!(filter_resonance), 0.05, 0.02),
+        (live_id!(osc_balance), 0.5, 0.02),
+        (live_id!(sub_osc), 0.5, 0.02),
+        (live_id!(noise), 0.0, 0.02),
+        (live_id!(portamento), 0.0, 0.05),
+        (live_id!(delay_send), 0.15, 0.02),
+        (live_id!(delay_feedback), 0.8, 0.05),
+        (live_id!(chorus_mix), 0.5, 0.05),
+        (live_id!(reverb_mix), 0.05, 0.05),
+    ];
+    
+    fn param_infos() -> Vec<ParamInfo> {
+        Self::PARAMS.iter().map( | (id, default, smoothing) | {
+            ParamInfo::new(*id, 0.0, 1.0, *default).with_unit(ParamUnit::Percent).with_smoothing(*smoothing)
+        }).collect()
+    }
+    
+    fn param(&self, id: LiveId) -> Option<&f32a> {
+        match id {
+            live_id!(filter_cutoff) => Some(&self.filter1.cutoff),
+            live_id!(filter_resonance) => Some(&self.filter1.resonance),
+            live_id!(osc_balance) => Some(&self.osc_balance),
+            live_id!(sub_osc) => Some(&self.sub_osc),
+            live_id!(noise) => Some(&self.noise),
+            live_id!(portamento) => Some(&self.portamento),
+            live_id!(delay_send) => Some(&self.delay.delaysend),
+            live_id!(delay_feedback) => Some(&self.delay.delayfeedback),
+            live_id!(chorus_mix) => Some(&self.chorus.mix),
+            live_id!(reverb_mix) => Some(&self.reverb.mix),
+            _ => None
+        }
+    }
+}
+
 
 #[derive(Copy, Clone)]
 pub struct SequencerState
@@ -860,38 +898,9 @@ pub struct ReverbSettings {
     #[live(0.04)] feedback: f32a
 }
 
-#[derive(Copy, Clone)]
-pub struct SmoothVal {
-    current: f32,
-    target: f32,
-    rate: f32
-}
-
-impl SmoothVal {
-    pub fn get(&mut self, target: f32) -> f32 {
-        self.target = target;
-        self.current += (self.target - self.current) * self.rate;
-        return self.current;
-    }
-    
-    pub fn ratebased(rate: f32) -> Self {
-        Self {
-            current: 0.0,
-            target: 0.0,
-            rate: rate
-        }
-    }
-}
-
-impl Default for SmoothVal {
-    fn default() -> Self {
-        Self {
-            current: 0.0,
-            target: 0.0,
-            rate: 0.1
-        }
-    }
-}
+// the chorus smooths its settings per block, the delay depths slower so the lines dont zip
+const CHORUS_SMOOTHING: f64 = 0.05;
+const CHORUS_DEPTH_SMOOTHING: f64 = 0.5;
 
 #[derive(Clone)]
 pub struct ChorusState {
@@ -899,11 +908,12 @@ pub struct ChorusState {
     phase: f32,
     linephase: [f32; 6],
     dphase: f32,
-    feedbacksmooth: SmoothVal,
-    mixsmooth: SmoothVal,
-    phasediffsmooth: SmoothVal,
-    mindepthsmooth: SmoothVal,
-    moddepthsmooth: SmoothVal
+    feedbacksmooth: SmoothedParam,
+    mixsmooth: SmoothedParam,
+    phasediffsmooth: SmoothedParam,
+    mindepthsmooth: SmoothedParam,
+    moddepthsmooth: SmoothedParam,
+    sample_rate: f32
 }
 
 
@@ -917,20 +927,35 @@ impl Default for ChorusState {
             feedbacksmooth: Default::default(),
             mixsmooth: Default::default(),
             phasediffsmooth: Default::default(),
-            mindepthsmooth: SmoothVal::ratebased(0.01),
-            moddepthsmooth: SmoothVal::ratebased(0.01),
+            mindepthsmooth: Default::default(),
+            moddepthsmooth: Default::default(),
+            sample_rate: 0.0,
         }
     }
 }
 
 impl ChorusState {
-    pub fn apply_chorus(&mut self, buffer: &mut AudioBuffer, settings: &ChorusSettings, sample_rate: f32) {
+    fn smooth(value: &mut SmoothedParam, target: f32, frame_count: usize) -> f32 {
+        value.set(target);
+        value.skip(frame_count)
+    }
+    
+    pub fn apply_chorus(&mut self, buffer: &mut AudioBuffer, startidx: usize, frame_count: usize, settings: &ChorusSettings, sample_rate: f32) {
         if settings.mix.get() == 0.0
         {
             return;
         }
         
-        let frame_count = buffer.frame_count();
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            let rate = sample_rate as f64;
+            self.feedbacksmooth.set_time(CHORUS_SMOOTHING, rate);
+            self.mixsmooth.set_time(CHORUS_SMOOTHING, rate);
+            self.phasediffsmooth.set_time(CHORUS_SMOOTHING, rate);
+            self.mindepthsmooth.set_time(CHORUS_DEPTH_SMOOTHING, rate);
+            self.moddepthsmooth.set_time(CHORUS_DEPTH_SMOOTHING, rate);
+        }
+        
         let (left, right) = buffer.stereo_mut();
         
         let lfofreq = 0.5 * (2.0).powf(settings.rate.get() * 8.0 - 4.0);
@@ -942,17 +967,17 @@ impl ChorusState {
         {
             self.phase = self.phase - 6.283;
         };
-        let mindelay = self.mindepthsmooth.get(sample_rate * 0.030 * settings.mindelay.get()) + 1.0;
-        let moddepth = self.moddepthsmooth.get(settings.moddepth.get() * sample_rate * 0.050);
-        let phasediff = self.phasediffsmooth.get(settings.phasediff.get());
+        let mindelay = Self::smooth(&mut self.mindepthsmooth, sample_rate * 0.030 * settings.mindelay.get(), frame_count) + 1.0;
+        let moddepth = Self::smooth(&mut self.moddepthsmooth, settings.moddepth.get() * sample_rate * 0.050, frame_count);
+        let phasediff = Self::smooth(&mut self.phasediffsmooth, settings.phasediff.get(), frame_count);
         for i in 0..6
         {
             self.linephase[i] = mindelay + ((self.phase + (i as f32) * phasediff).sin() + 1.0) * moddepth;
         }
-        let fb = self.feedbacksmooth.get(settings.feedback.get()) * 0.86;
-        let mix = self.mixsmooth.get(settings.mix.get());
+        let fb = Self::smooth(&mut self.feedbacksmooth, settings.feedback.get(), frame_count) * 0.86;
+        let mix = Self::smooth(&mut self.mixsmooth, settings.mix.get(), frame_count);
         let imix = 1.0 - mix;
-        for i in 0..frame_count {
+        for i in startidx..startidx + frame_count {
             let l1 = self.lines[0].feed(left[i], fb, self.linephase[0]);
             let l2 = self.lines[1].feed(right[i], fb, self.linephase[1]);
             let l3 = self.lines[2].feed(left[i], fb, self.linephase[2]);
@@ -1403,6 +1428,9 @@ pub struct IronFishGlobalVoiceState {
     hypersaw2: HyperSawGlobalState,
 }
 
+// frames rendered between updates while an automated setting ramps
+const PARAM_RAMP_STEP: usize = 32;
+
 pub struct IronFishState {
     //from_ui: FromUIReceiver<FromUI>,
     //to_ui: ToUISender<ToUI>,
@@ -1430,8 +1458,10 @@ pub struct IronFishState {
     sps_detune_tab: [f32; 1024], // FIXME: move to IronFishGlobalVoiceState
     g: IronFishGlobalVoiceState,
     chorus: ChorusState,
-    reverb: ReverbState
-    
+    reverb: ReverbState,
+    params: ParamSet,
+    // the values last written to the settings, only changes are written back
+    param_values: [f32; IronFishSettings::PARAMS.len()]
 }
 
 impl IronFishState {
@@ -1541,7 +1571,7 @@ impl IronFishState {
         return output; // * 1000.0;
     }
     */
-    pub fn apply_bitcrush(&mut self, buffer: &mut AudioBuffer) {
+    pub fn apply_bitcrush(&mut self, buffer: &mut AudioBuffer, startidx: usize, frame_count: usize) {
         
         if !self.settings.bitcrush.enable.get() {return;};
         
@@ -1549,9 +1579,8 @@ impl IronFishState {
         let crushbits = (amount * 22.0) as i32;
         let precrushmult = 65536.0 * 8.0;
         let postcrushmult = (1 << crushbits) as f32 / precrushmult;
-        let frame_count = buffer.frame_count();
         let (left, right) = buffer.stereo_mut();
-        for i in 0..frame_count {
+        for i in startidx..startidx + frame_count {
             let intermediate_left = (left[i] * precrushmult) as i32;
             let crushed_left = intermediate_left >> crushbits;
             
@@ -1563,11 +1592,10 @@ impl IronFishState {
         }
         
     }
-    pub fn apply_reverb(&mut self, buffer: &mut AudioBuffer ){
+    pub fn apply_reverb(&mut self, buffer: &mut AudioBuffer, startidx: usize, frame_count: usize){
         if self.settings.reverb.mix.get() == 0.0 {return;};
-        let frame_count = buffer.frame_count();
         let (left, right) = buffer.stereo_mut();
-        for i in 0..frame_count {
+        for i in startidx..startidx + frame_count {
               
        // let verb = self.reverb.toy.test_delay(left[i], right[i], self.settings.reverb.mix.get(), self.settings.reverb.feedback.get());
         let verb = self.reverb.toy.griesinger_reverb(left[i], right[i], self.settings.reverb.mix.get(), self.settings.reverb.feedback.get());
@@ -1576,8 +1604,7 @@ impl IronFishState {
     }        
     }
 
-    pub fn apply_delay(&mut self, buffer: &mut AudioBuffer) {
-        let frame_count = buffer.frame_count();
+    pub fn apply_delay(&mut self, buffer: &mut AudioBuffer, startidx: usize, frame_count: usize) {
         let (left, right) = buffer.stereo_mut();
         
         let icross = self.settings.delay.cross.get();
@@ -1593,7 +1620,7 @@ impl IronFishState {
         let fb = self.settings.delay.delayfeedback.get() * 0.98;
         let send = self.settings.delay.delaysend.get();
         
-        for i in 0..frame_count {
+        for i in startidx..startidx + frame_count {
             let rr = self.delaylineright[delayreadposr];
             let ll = self.delaylineleft[delayreadposl];
             
@@ -1720,15 +1747,14 @@ impl IronFishState {
             }
         }
         
-        let mut remaining = buffer.frame_count();
-        let mut bufferidx = 0;
+        let frame_count = buffer.frame_count();
         
         
         let lfofreq = 0.5 * (2.0).powf(self.settings.lfo.rate.get() * 8.0 - 4.0);
         let lfodphase = 1.0 / (self.settings.sample_rate.get() / lfofreq);
         
         
-        self.lfo.phase += remaining as f32 * lfodphase;
+        self.lfo.phase += frame_count as f32 * lfodphase;
         
         
         while self.lfo.phase > 1.0 {
@@ -1737,6 +1763,45 @@ impl IronFishState {
         self.lfovalue = (self.lfo.phase * 6.283).sin();
         //   log!("s{}", remaining);
         
+        // automated settings change at their frame, the block is rendered in pieces between the changes
+        self.params.set_sample_rate(self.settings.sample_rate.get() as f64);
+        let mut startidx = 0;
+        while startidx < frame_count {
+            self.params.apply_due();
+            self.write_params();
+            let mut endidx = self.params.next_change().map_or(frame_count, | frame | frame.min(frame_count));
+            if !self.params.is_settled() {
+                endidx = endidx.min(startidx + PARAM_RAMP_STEP);
+            }
+            self.fill_range(buffer, startidx, endidx - startidx);
+            self.params.skip(endidx - startidx);
+            startidx = endidx;
+        }
+        self.params.end_block();
+        self.write_params();
+        
+        for (i, dp) in self.display_buffers.iter_mut().enumerate() {
+            if let Some(dp) = dp.take() {
+                display.send_buffer(self.voices[i].active()>-1, i, dp);
+            }
+        }
+    }
+    
+    fn write_params(&mut self) {
+        for (index, (id, _, _)) in IronFishSettings::PARAMS.iter().enumerate() {
+            let value = self.params.value(index);
+            if value != self.param_values[index] {
+                self.param_values[index] = value;
+                if let Some(param) = self.settings.param(*id) {
+                    param.set(value);
+                }
+            }
+        }
+    }
+    
+    fn fill_range(&mut self, buffer: &mut AudioBuffer, startidx: usize, frame_count: usize) {
+        let mut remaining = frame_count;
+        let mut bufferidx = startidx;
         while remaining > 0 {
             //log!("b{}", remaining);
             let mut toprocess = remaining;
@@ -1845,15 +1910,10 @@ impl IronFishState {
             remaining -= toprocess;
         }
         
-        for (i, dp) in self.display_buffers.iter_mut().enumerate() {
-            if let Some(dp) = dp.take() {
-                display.send_buffer(self.voices[i].active()>-1, i, dp);
-            }
-        }
-        self.apply_bitcrush(buffer);
-        self.chorus.apply_chorus(buffer, &self.settings.chorus, self.settings.sample_rate.get());        
-        self.apply_delay(buffer);
-        self.apply_reverb(buffer);
+        self.apply_bitcrush(buffer, startidx, frame_count);
+        self.chorus.apply_chorus(buffer, startidx, frame_count, &self.settings.chorus, self.settings.sample_rate.get());        
+        self.apply_delay(buffer, startidx, frame_count);
+        self.apply_reverb(buffer, startidx, frame_count);
 
     }
 }
@@ -1947,6 +2007,10 @@ impl AudioGraphNode for IronFishState {
     fn render_to_audio_buffer(&mut self, _info: AudioInfo, outputs: &mut [&mut AudioBuffer], _inputs: &[&AudioBuffer], display: &mut DisplayAudioGraph) {
         self.fill_buffer(outputs[0], display)
     }
+    
+    fn set_param(&mut self, change: ParamChange) {
+        self.params.push(change);
+    }
 }
 
 impl AudioComponent for IronFish {
@@ -1968,6 +2032,16 @@ impl AudioComponent for IronFish {
             (24.1878824391 * pow(detune, 2.0)) + (0.6717417634 * detune) + 0.0030115596;
         }
         
+        // the automation starts from the settings as the UI left them
+        let mut params = ParamSet::new(IronFishSettings::param_infos());
+        let mut param_values = [0.0; IronFishSettings::PARAMS.len()];
+        for (index, (id, _, _)) in IronFishSettings::PARAMS.iter().enumerate() {
+            if let Some(param) = self.settings.param(*id) {
+                param_values[index] = param.get();
+                params.jump_index(index, param.get() as f64);
+            }
+        }
+        
         Box::new(IronFishState {
             display_buffers: buffers,
             settings: self.settings.clone(),
@@ -1995,16 +2069,34 @@ impl AudioComponent for IronFish {
             sps_detune_tab,
             g: Default::default(),
             chorus: Default::default(),
-            reverb: Default::default()
+            reverb: Default::default(),
+            params,
+            param_values
         })
     }
     
+    // the node has no channel, it reads the settings the UI has set so an offline copy can share them
+    fn get_offline_graph_node(&mut self, cx: &mut Cx) -> Box<dyn AudioGraphNode + Send> {
+        self.get_graph_node(cx)
+    }
+    
     fn handle_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, AudioComponentAction)) {
     }
     // we dont have inputs
     fn audio_query(&mut self, _query: &AudioQuery, _callback: &mut Option<AudioQueryCb>) -> AudioResult {
         AudioResult::not_found()
     }
+    
+    fn params(&self) -> Vec<ParamInfo> {
+        IronFishSettings::param_infos()
+    }
+    
+    // the whole patch, applying it goes through the atomics like the UI does
+    fn preset_read_to(&self, id: LiveId, out: &mut Vec<LiveNode>) {
+        out.open_object(id);
+        self.settings.live_read_to(live_id!(settings), out);
+        out.close();
+    }
 }
 
 
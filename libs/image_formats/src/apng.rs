@@ -0,0 +1,156 @@
+use {
+    makepad_zune_png::{
+        PngDecoder,
+        BlendOp,
+        DisposeOp,
+        makepad_zune_core::{
+            bit_depth::{BitDepth, ByteEndian},
+            options::DecoderOptions,
+        },
+    },
+    crate::{Canvas, Dispose, FrameRect, DecodedImage, ImageFrame, argb},
+};
+
+// Animated PNG on top of the png decoder, which hands out the frames one by one
+// as rectangles that still need compositing. Plain PNG files come out as a
+// single frame.
+
+pub fn decode_apng(data: &[u8]) -> Result<DecodedImage, String> {
+    let options = DecoderOptions::default().png_set_add_alpha_channel(true);
+    let mut decoder = PngDecoder::new_with_options(data, options);
+    decoder.decode_headers().map_err( | err | format!("Error decoding PNG: {:?}", err))?;
+    let (width, height) = decoder.get_dimensions().ok_or("PNG has no size")?;
+    let components = decoder.get_colorspace().ok_or("PNG has no colorspace")?.num_components();
+    let is_sixteen = decoder.get_depth() == Some(BitDepth::Sixteen);
+    // the high byte of 16 bit samples
+    let high = if is_sixteen && decoder.byte_endian() == ByteEndian::LE {1} else {0};
+    let bytes = if is_sixteen {2} else {1};
+    let (num_frames, plays) = decoder.actl_info().map_or((1, 1), | actl | (actl.num_frames as usize, actl.num_plays));
+
+    let mut buffer = vec![0u8; decoder.output_buffer_size().ok_or("PNG has no size")?];
+    let mut decoded = Vec::new();
+    while decoder.decode_into(&mut buffer).is_ok() {
+        let Some(info) = decoder.frame_info() else {
+            break
+        };
+        let pixels: Vec<u32> = (0..info.width * info.height).map( | i | {
+            let sample = | c: usize | buffer[(i * components + c) * bytes + high];
+            if components == 4 {
+                argb(sample(0), sample(1), sample(2), sample(3))
+            }
+            else {
+                argb(sample(0), sample(0), sample(0), sample(1))
+            }
+        }).collect();
+        decoded.push((info, pixels));
+    }
+    // a default image without frame control is there for decoders that don't
+    // know APNG and is not part of the animation
+    if decoded.len() == num_frames + 1 {
+        decoded.remove(0);
+    }
+
+    let mut image = DecodedImage {width, height, frames: Vec::new(), plays};
+    let mut canvas = Canvas::new(width, height);
+    for (info, pixels) in decoded {
+        let rect = FrameRect {x: info.x_offset, y: info.y_offset, width: info.width, height: info.height};
+        let dispose = match info.dispose_op {
+            DisposeOp::None => Dispose::None,
+            DisposeOp::Background => Dispose::Background,
+            DisposeOp::Previous => Dispose::Previous,
+        };
+        if dispose == Dispose::Previous {
+            canvas.save();
+        }
+        canvas.draw(rect, &pixels, matches!(info.blend_op, BlendOp::Over));
+        let denom = if info.delay_denom == 0 {100} else {info.delay_denom};
+        image.frames.push(ImageFrame {
+            data: canvas.data.clone(),
+            duration: info.delay_num as f64 / denom as f64
+        });
+        canvas.dispose(rect, dispose);
+    }
+    if image.frames.is_empty() {
+        return Err("PNG has no frames".to_string())
+    }
+    if image.frames.len() == 1 {
+        image.frames[0].duration = 0.0;
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::decode_apng,
+        crate::{argb, blend_over},
+    };
+
+    const ANIM: &[u8] = include_bytes!("../tests/data/anim.png");
+    const STILL: &[u8] = include_bytes!("../tests/data/still.png");
+
+    // both files are 4x3, anim.png has a white default image outside the
+    // animation, a full frame and a blue 2x2 frame blended over it whose left
+    // column is half transparent
+    fn base(x: usize, y: usize) -> u32 {
+        argb(x as u8 * 60, y as u8 * 100, 50, 255)
+    }
+
+    #[test]
+    fn decodes_a_still_png_as_one_frame() {
+        let image = decode_apng(STILL).unwrap();
+        assert_eq!((image.width, image.height, image.plays), (4, 3, 1));
+        assert_eq!(image.frames.len(), 1);
+        assert_eq!(image.frames[0].duration, 0.0);
+        let expected: Vec<u32> = (0..12).map( | i | base(i % 4, i / 4)).collect();
+        assert!(image.frames[0].data == expected);
+    }
+
+    #[test]
+    fn composites_animation_frames() {
+        let image = decode_apng(ANIM).unwrap();
+        assert_eq!((image.width, image.height, image.plays), (4, 3, 3));
+        assert_eq!(image.frames.len(), 2);
+        assert_eq!(image.frames[0].duration, 0.25);
+        assert_eq!(image.frames[1].duration, 0.3);
+
+        let mut expected: Vec<u32> = (0..12).map( | i | base(i % 4, i / 4)).collect();
+        assert!(image.frames[0].data == expected);
+        for y in 1..3 {
+            for x in 1..3 {
+                let over = argb(0, 0, 255, if x == 1 {128} else {255});
+                expected[y * 4 + x] = blend_over(over, expected[y * 4 + x]);
+            }
+        }
+        assert!(image.frames[1].data == expected);
+    }
+
+    #[test]
+    fn broken_input_is_an_error() {
+        assert!(decode_apng(&[]).is_err());
+        assert!(decode_apng(&STILL[..8]).is_err());
+        // ends inside the header
+        assert!(decode_apng(&STILL[..20]).is_err());
+        // ends before any image data
+        assert!(decode_apng(&STILL[..33]).is_err());
+        let mut data = STILL.to_vec();
+        data[1] = b'Q';
+        assert!(decode_apng(&data).is_err());
+    }
+
+    #[test]
+    fn cut_or_corrupted_input_does_not_panic() {
+        for file in [ANIM, STILL] {
+            for len in 0..file.len() {
+                let _ = decode_apng(&file[..len]);
+            }
+            for pos in 0..file.len() {
+                for value in [0x00, 0x01, 0x80, 0xff] {
+                    let mut data = file.to_vec();
+                    data[pos] = value;
+                    let _ = decode_apng(&data);
+                }
+            }
+        }
+    }
+}
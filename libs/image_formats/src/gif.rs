@@ -0,0 +1,380 @@
+use crate::{Canvas, Dispose, FrameRect, DecodedImage, ImageFrame, argb, check_size};
+
+// GIF87a and GIF89a. Frames are composited like browsers do: the canvas starts
+// transparent, disposal to background clears to transparent and delays of 0 or
+// 10ms play at 100ms since that is what files out there are made for.
+
+const EXTENSION: u8 = 0x21;
+const IMAGE: u8 = 0x2c;
+const TRAILER: u8 = 0x3b;
+const GRAPHIC_CONTROL: u8 = 0xf9;
+const APPLICATION: u8 = 0xff;
+
+const MAX_CODES: usize = 4096;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn u8(&mut self) -> Result<u8, String> {
+        let value = *self.data.get(self.pos).ok_or_else(truncated)?;
+        self.pos += 1;
+        Ok(value)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(self.u8()? as u16 | (self.u8()? as u16) << 8)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or_else(truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    // a sub block, empty at the terminator
+    fn sub_block(&mut self) -> Result<&'a [u8], String> {
+        let len = self.u8()? as usize;
+        self.bytes(len)
+    }
+
+    fn sub_blocks(&mut self) -> Result<Vec<u8>, String> {
+        let mut out = Vec::new();
+        loop {
+            let block = self.sub_block()?;
+            if block.is_empty() {
+                return Ok(out)
+            }
+            out.extend_from_slice(block);
+        }
+    }
+
+    fn palette(&mut self, flags: u8) -> Result<Vec<u32>, String> {
+        let size = 2 << (flags & 7);
+        let bytes = self.bytes(size * 3)?;
+        Ok(bytes.chunks(3).map( | c | argb(c[0], c[1], c[2], 255)).collect())
+    }
+}
+
+fn truncated() -> String {
+    "GIF data ends early".to_string()
+}
+
+#[derive(Clone, Copy)]
+struct GraphicControl {
+    dispose: Dispose,
+    // in hundredths of a second
+    delay: u16,
+    transparent: Option<u8>,
+}
+
+impl Default for GraphicControl {
+    fn default() -> Self {
+        Self {dispose: Dispose::None, delay: 0, transparent: None}
+    }
+}
+
+pub fn decode_gif(data: &[u8]) -> Result<DecodedImage, String> {
+    let mut reader = Reader {data, pos: 0};
+    let signature = reader.bytes(6).map_err( | _ | "Not a GIF file".to_string())?;
+    if signature != b"GIF87a" && signature != b"GIF89a" {
+        return Err("Not a GIF file".to_string())
+    }
+    let width = reader.u16()? as usize;
+    let height = reader.u16()? as usize;
+    let flags = reader.u8()?;
+    let _background = reader.u8()?;
+    let _aspect = reader.u8()?;
+    if width == 0 || height == 0 {
+        return Err("GIF has no size".to_string())
+    }
+    check_size(width, height)?;
+    let global_palette = if flags & 0x80 != 0 {reader.palette(flags)?} else {Vec::new()};
+
+    let mut image = DecodedImage {width, height, frames: Vec::new(), plays: 1};
+    let mut canvas = Canvas::new(width, height);
+    let mut control = GraphicControl::default();
+    // a truncated file still shows the frames that made it
+    let result = (|| -> Result<(), String> {
+        loop {
+            match reader.u8()? {
+                EXTENSION => match reader.u8()? {
+                    GRAPHIC_CONTROL => {
+                        let block = reader.sub_blocks()?;
+                        if block.len() >= 4 {
+                            control.dispose = match (block[0] >> 2) & 7 {
+                                2 => Dispose::Background,
+                                3 => Dispose::Previous,
+                                _ => Dispose::None
+                            };
+                            control.delay = block[1] as u16 | (block[2] as u16) << 8;
+                            control.transparent = if block[0] & 1 != 0 {Some(block[3])} else {None};
+                        }
+                    }
+                    APPLICATION => {
+                        let id = reader.sub_block()?;
+                        if id == b"NETSCAPE2.0" || id == b"ANIMEXTS1.0" {
+                            let block = reader.sub_blocks()?;
+                            if block.len() >= 3 && block[0] == 1 {
+                                let loops = block[1] as u32 | (block[2] as u32) << 8;
+                                image.plays = if loops == 0 {0} else {loops + 1};
+                            }
+                        }
+                        else {
+                            reader.sub_blocks()?;
+                        }
+                    }
+                    _ => {
+                        reader.sub_blocks()?;
+                    }
+                },
+                IMAGE => {
+                    let rect = FrameRect {
+                        x: reader.u16()? as usize,
+                        y: reader.u16()? as usize,
+                        width: reader.u16()? as usize,
+                        height: reader.u16()? as usize,
+                    };
+                    let flags = reader.u8()?;
+                    let local_palette = if flags & 0x80 != 0 {Some(reader.palette(flags)?)} else {None};
+                    let palette = local_palette.as_ref().unwrap_or(&global_palette);
+                    let min_code_size = reader.u8()?;
+                    let lzw = reader.sub_blocks()?;
+
+                    check_size(rect.width, rect.height)?;
+                    let count = rect.width * rect.height;
+                    let mut indices = lzw_decode(min_code_size, &lzw, count)?;
+                    if flags & 0x40 != 0 {
+                        indices = deinterlace(&indices, rect.width, rect.height);
+                    }
+                    let mut pixels = vec![0u32; count];
+                    for (pixel, index) in pixels.iter_mut().zip(indices.iter()) {
+                        if Some(*index) != control.transparent {
+                            *pixel = palette.get(*index as usize).copied().unwrap_or(0);
+                        }
+                    }
+                    if control.dispose == Dispose::Previous {
+                        canvas.save();
+                    }
+                    // transparent pixels are 0 so blending keeps what is below
+                    canvas.draw(rect, &pixels, true);
+                    let delay = if control.delay <= 1 {10} else {control.delay};
+                    image.frames.push(ImageFrame {
+                        data: canvas.data.clone(),
+                        duration: delay as f64 / 100.0
+                    });
+                    canvas.dispose(rect, control.dispose);
+                    control = GraphicControl::default();
+                }
+                TRAILER => return Ok(()),
+                _ => return Err("Unknown block in GIF".to_string())
+            }
+        }
+    })();
+    if let Err(err) = result {
+        if image.frames.is_empty() {
+            return Err(err)
+        }
+    }
+    if image.frames.is_empty() {
+        return Err("GIF has no frames".to_string())
+    }
+    Ok(image)
+}
+
+// Decodes up to count palette indices, fewer when the data ends early.
+fn lzw_decode(min_code_size: u8, data: &[u8], count: usize) -> Result<Vec<u8>, String> {
+    if min_code_size == 0 || min_code_size > 11 {
+        return Err("Invalid GIF code size".to_string())
+    }
+    let clear = 1usize << min_code_size;
+    let end = clear + 1;
+    // every code is a prefix code plus one byte, strings are written back to front
+    let mut prefix = vec![0u16; MAX_CODES];
+    let mut suffix = vec![0u8; MAX_CODES];
+    let mut first = vec![0u8; MAX_CODES];
+    let mut lengths = vec![0u16; MAX_CODES];
+    for code in 0..clear {
+        suffix[code] = code as u8;
+        first[code] = code as u8;
+        lengths[code] = 1;
+    }
+    let mut next = clear + 2;
+    let mut size = min_code_size as usize + 1;
+    let mut prev: Option<usize> = None;
+
+    let mut out = Vec::with_capacity(count);
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+    let mut pos = 0;
+    while out.len() < count {
+        while bit_count < size {
+            if pos >= data.len() {
+                return Ok(out)
+            }
+            bits |= (data[pos] as u32) << bit_count;
+            bit_count += 8;
+            pos += 1;
+        }
+        let code = (bits & ((1 << size) - 1)) as usize;
+        bits >>= size;
+        bit_count -= size;
+
+        if code == clear {
+            next = clear + 2;
+            size = min_code_size as usize + 1;
+            prev = None;
+            continue
+        }
+        if code == end {
+            break
+        }
+        if let Some(prev) = prev {
+            if code > next || (code == next && next == MAX_CODES) {
+                break
+            }
+            if next < MAX_CODES {
+                // the new string is the previous one plus the first byte of this one,
+                // which for a code that is being defined right now is its own first byte
+                prefix[next] = prev as u16;
+                suffix[next] = if code < next {first[code]} else {first[prev]};
+                first[next] = first[prev];
+                lengths[next] = lengths[prev] + 1;
+                next += 1;
+                if next == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+        }
+        else if code >= clear {
+            break
+        }
+        let len = lengths[code] as usize;
+        let start = out.len();
+        out.resize(start + len, 0);
+        let mut c = code;
+        for i in (0..len).rev() {
+            out[start + i] = suffix[c];
+            c = prefix[c] as usize;
+        }
+        prev = Some(code);
+    }
+    out.truncate(count);
+    Ok(out)
+}
+
+// interlaced rows come in four passes, every 8th row from 0, every 8th from 4,
+// every 4th from 2 and every 2nd from 1
+fn deinterlace(indices: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut out = vec![0u8; width * height];
+    let mut src_row = 0;
+    for (start, step) in [(0, 8), (4, 8), (2, 4), (1, 2)] {
+        for y in (start..height).step_by(step) {
+            let src = src_row * width;
+            if src >= indices.len() {
+                return out
+            }
+            let len = width.min(indices.len() - src);
+            out[y * width..y * width + len].copy_from_slice(&indices[src..src + len]);
+            src_row += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::decode_gif,
+        crate::argb,
+    };
+
+    const ANIM: &[u8] = include_bytes!("../tests/data/anim.gif");
+
+    // anim.gif is 16x12 with a black, red, green, blue palette: a full frame,
+    // a transparent sub rect that is cleared afterwards, and an interlaced
+    // strip with its own white and grey palette
+    fn palette(index: usize) -> u32 {
+        [argb(0, 0, 0, 255), argb(255, 0, 0, 255), argb(0, 255, 0, 255), argb(0, 0, 255, 255)][index]
+    }
+
+    #[test]
+    fn decodes_frames_onto_the_canvas() {
+        let image = decode_gif(ANIM).unwrap();
+        assert_eq!((image.width, image.height), (16, 12));
+        assert_eq!(image.plays, 0);
+        assert_eq!(image.frames.len(), 3);
+        let durations: Vec<f64> = image.frames.iter().map( | frame | frame.duration).collect();
+        assert_eq!(durations, [0.2, 0.1, 0.1]);
+
+        let mut expected: Vec<u32> = (0..12 * 16).map( | i | palette((i % 16 / 3 + i / 16) % 4)).collect();
+        assert!(image.frames[0].data == expected);
+
+        let in_rect = | x: usize, y: usize | (4..10).contains(&x) && (3..8).contains(&y);
+        let mut second = expected.clone();
+        for y in 3..8 {
+            for x in 4..10 {
+                let index = (x - 4) * (y - 3) % 4;
+                if index != 0 {
+                    second[y * 16 + x] = palette(index);
+                }
+            }
+        }
+        assert!(image.frames[1].data == second);
+
+        for y in 0..12 {
+            for x in 0..16 {
+                if x < 4 {
+                    expected[y * 16 + x] = if (x + y) % 2 == 0 {argb(255, 255, 255, 255)} else {argb(128, 128, 128, 255)};
+                }
+                else if in_rect(x, y) {
+                    expected[y * 16 + x] = 0;
+                }
+            }
+        }
+        assert!(image.frames[2].data == expected);
+    }
+
+    #[test]
+    fn truncated_file_keeps_complete_frames() {
+        // ends in the middle of the second frame
+        let image = decode_gif(&ANIM[..ANIM.len() - 40]).unwrap();
+        assert!(!image.frames.is_empty() && image.frames.len() < 3);
+        assert!(image.frames[0].data == decode_gif(ANIM).unwrap().frames[0].data);
+        // ends before the first image descriptor
+        assert!(decode_gif(&ANIM[..40]).is_err());
+    }
+
+    #[test]
+    fn broken_input_is_an_error() {
+        assert!(decode_gif(&[]).is_err());
+        assert!(decode_gif(b"GIF89").is_err());
+        assert!(decode_gif(b"GIF90a\x10\x00\x0c\x00\x00\x00\x00;").is_err());
+        // zero size
+        assert!(decode_gif(b"GIF89a\x00\x00\x0c\x00\x00\x00\x00;").is_err());
+        // too large to allocate
+        assert!(decode_gif(b"GIF89a\xff\xff\xff\xff\x00\x00\x00;").is_err());
+        // no frames
+        assert!(decode_gif(b"GIF89a\x10\x00\x0c\x00\x00\x00\x00;").is_err());
+        // an unknown block before any frame
+        let mut data = ANIM.to_vec();
+        data[13 + 12] = 0x42;
+        assert!(decode_gif(&data).is_err());
+    }
+
+    #[test]
+    fn cut_or_corrupted_input_does_not_panic() {
+        for len in 0..ANIM.len() {
+            let _ = decode_gif(&ANIM[..len]);
+        }
+        for pos in 0..ANIM.len() {
+            for value in [0x00, 0x01, 0x0b, 0x80, 0xff] {
+                let mut data = ANIM.to_vec();
+                data[pos] = value;
+                let _ = decode_gif(&data);
+            }
+        }
+    }
+}
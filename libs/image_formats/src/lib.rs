@@ -0,0 +1,173 @@
+// Decoders for the animated image formats, GIF, WebP and APNG. Every format
+// decodes to the same shape: a list of full canvas frames in 0xAARRGGBB with
+// the time each one shows, so still images are just animations of one frame.
+//
+// let image = decode_gif(&data)?;
+// for frame in &image.frames {
+//     upload(image.width, image.height, &frame.data);
+// }
+
+pub mod gif;
+pub mod webp;
+pub mod apng;
+
+pub use crate::{
+    gif::decode_gif,
+    webp::decode_webp,
+    apng::decode_apng,
+};
+
+#[derive(Clone, Default)]
+pub struct ImageFrame {
+    // the whole canvas with this frame composited on top of the previous ones
+    pub data: Vec<u32>,
+    // in seconds
+    pub duration: f64,
+}
+
+#[derive(Clone, Default)]
+pub struct DecodedImage {
+    pub width: usize,
+    pub height: usize,
+    pub frames: Vec<ImageFrame>,
+    // how many times the animation plays, 0 repeats forever
+    pub plays: u32,
+}
+
+impl DecodedImage {
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    pub fn total_duration(&self) -> f64 {
+        self.frames.iter().map( | frame | frame.duration).sum()
+    }
+
+    // the frame showing at a time since the animation started, holding
+    // the last frame once all plays are done
+    pub fn frame_at(&self, time: f64) -> usize {
+        let total = self.total_duration();
+        if self.frames.len() < 2 || total <= 0.0 {
+            return 0
+        }
+        if self.plays != 0 && time >= total * self.plays as f64 {
+            return self.frames.len() - 1
+        }
+        let mut time = time.max(0.0) % total;
+        for (index, frame) in self.frames.iter().enumerate() {
+            if time < frame.duration {
+                return index
+            }
+            time -= frame.duration;
+        }
+        self.frames.len() - 1
+    }
+}
+
+// the largest canvas or frame a decoder accepts, which is also the 16384 by
+// 16384 limit of VP8 and VP8L. larger sizes in a header are a broken file
+pub(crate) const MAX_PIXELS: usize = 1 << 28;
+
+pub(crate) fn check_size(width: usize, height: usize) -> Result<(), String> {
+    if width * height > MAX_PIXELS {
+        return Err(format!("Image size {}x{} is too large", width, height))
+    }
+    Ok(())
+}
+
+pub(crate) fn argb(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32)
+}
+
+// The canvas animation frames draw into. GIF, WebP and APNG share the model of
+// a frame rectangle that is either blended over or replaces the canvas, and is
+// afterwards left alone, cleared, or restored to what was there before.
+pub(crate) struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u32>,
+    saved: Option<Vec<u32>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Dispose {
+    None,
+    Background,
+    Previous,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct FrameRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width * height],
+            saved: None,
+        }
+    }
+
+    // call before drawing a frame that is disposed with Dispose::Previous
+    pub fn save(&mut self) {
+        self.saved = Some(self.data.clone());
+    }
+
+    // draws frame pixels of the rect size, clipped to the canvas
+    pub fn draw(&mut self, rect: FrameRect, pixels: &[u32], blend: bool) {
+        let width = rect.width.min(self.width.saturating_sub(rect.x));
+        if width == 0 {
+            return
+        }
+        for y in 0..rect.height.min(self.height.saturating_sub(rect.y)) {
+            let src = &pixels[y * rect.width..];
+            let dst = &mut self.data[(rect.y + y) * self.width + rect.x..];
+            for x in 0..width {
+                dst[x] = if blend {blend_over(src[x], dst[x])} else {src[x]};
+            }
+        }
+    }
+
+    pub fn dispose(&mut self, rect: FrameRect, dispose: Dispose) {
+        match dispose {
+            Dispose::None => (),
+            Dispose::Background => {
+                for y in rect.y..(rect.y + rect.height).min(self.height) {
+                    for x in rect.x..(rect.x + rect.width).min(self.width) {
+                        self.data[y * self.width + x] = 0;
+                    }
+                }
+            }
+            Dispose::Previous => if let Some(saved) = self.saved.take() {
+                self.data = saved;
+            }
+        }
+    }
+}
+
+// src over dst on non premultiplied colors
+pub(crate) fn blend_over(src: u32, dst: u32) -> u32 {
+    let src_a = src >> 24;
+    if src_a == 255 {
+        return src
+    }
+    if src_a == 0 {
+        return dst
+    }
+    let dst_a = dst >> 24;
+    // the alpha of the result, scaled by 255
+    let dst_weight = dst_a * (255 - src_a);
+    let out_a = src_a * 255 + dst_weight;
+    let channel = | shift: u32 | {
+        let s = (src >> shift) & 0xff;
+        let d = (dst >> shift) & 0xff;
+        (s * src_a * 255 + d * dst_weight + out_a / 2) / out_a
+    };
+    ((out_a + 127) / 255) << 24 | channel(16) << 16 | channel(8) << 8 | channel(0)
+}
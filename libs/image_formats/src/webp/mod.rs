@@ -0,0 +1,274 @@
+use crate::{Canvas, Dispose, FrameRect, DecodedImage, ImageFrame, check_size};
+
+// WebP in its three shapes: a lone lossy VP8 or lossless VP8L chunk, or the
+// extended VP8X layout that adds a separate alpha plane and animation frames.
+
+mod vp8;
+mod vp8l;
+mod vp8_tables;
+
+const ANIMATION_FLAG: u8 = 0x02;
+
+struct Chunk<'a> {
+    id: &'a [u8],
+    data: &'a [u8],
+}
+
+// splits RIFF chunks, which are padded to an even size
+fn read_chunks(mut data: &[u8]) -> Result<Vec<Chunk<'_>>, String> {
+    let mut out = Vec::new();
+    while data.len() >= 8 {
+        let size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let chunk = data.get(8..8 + size).ok_or("WebP chunk ends early")?;
+        out.push(Chunk {id: &data[0..4], data: chunk});
+        data = data.get(8 + size + (size & 1)..).unwrap_or(&[]);
+    }
+    Ok(out)
+}
+
+fn u24(data: &[u8]) -> usize {
+    data[0] as usize | (data[1] as usize) << 8 | (data[2] as usize) << 16
+}
+
+pub fn decode_webp(data: &[u8]) -> Result<DecodedImage, String> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err("Not a WebP file".to_string())
+    }
+    let riff_size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let body = &data[12..data.len().min(8 + riff_size).max(12)];
+    let chunks = read_chunks(body)?;
+    let first = chunks.first().ok_or("WebP file has no chunks")?;
+
+    if first.id != b"VP8X" {
+        let (width, height, pixels) = decode_still(&chunks)?;
+        return Ok(DecodedImage {
+            width,
+            height,
+            frames: vec![ImageFrame {data: pixels, duration: 0.0}],
+            plays: 1,
+        })
+    }
+    if first.data.len() < 10 {
+        return Err("WebP extended header ends early".to_string())
+    }
+    let width = u24(&first.data[4..]) + 1;
+    let height = u24(&first.data[7..]) + 1;
+    check_size(width, height)?;
+    if first.data[0] & ANIMATION_FLAG == 0 {
+        let (w, h, pixels) = decode_still(&chunks[1..])?;
+        if w != width || h != height {
+            return Err("WebP image size differs from the canvas".to_string())
+        }
+        return Ok(DecodedImage {
+            width,
+            height,
+            frames: vec![ImageFrame {data: pixels, duration: 0.0}],
+            plays: 1,
+        })
+    }
+
+    // the background color in ANIM is only a hint, like browsers the canvas
+    // starts out transparent
+    let mut image = DecodedImage {width, height, frames: Vec::new(), plays: 0};
+    let mut canvas = Canvas::new(width, height);
+    for chunk in &chunks[1..] {
+        match chunk.id {
+            b"ANIM" if chunk.data.len() >= 6 => {
+                image.plays = u16::from_le_bytes([chunk.data[4], chunk.data[5]]) as u32;
+            }
+            b"ANMF" if chunk.data.len() >= 16 => {
+                let d = chunk.data;
+                let rect = FrameRect {
+                    x: u24(&d[0..]) * 2,
+                    y: u24(&d[3..]) * 2,
+                    width: u24(&d[6..]) + 1,
+                    height: u24(&d[9..]) + 1,
+                };
+                let duration = u24(&d[12..]);
+                let flags = d[15];
+                let (w, h, pixels) = match read_chunks(&d[16..]).and_then( | frame | decode_still(&frame)) {
+                    Ok(frame) => frame,
+                    // keep what decoded when a later frame is broken
+                    Err(err) if image.frames.is_empty() => return Err(err),
+                    Err(_) => break
+                };
+                if w != rect.width || h != rect.height {
+                    return Err("WebP frame size differs from its rect".to_string())
+                }
+                canvas.draw(rect, &pixels, flags & 0x02 == 0);
+                let duration = if duration <= 10 {100} else {duration};
+                image.frames.push(ImageFrame {
+                    data: canvas.data.clone(),
+                    duration: duration as f64 / 1000.0
+                });
+                canvas.dispose(rect, if flags & 0x01 != 0 {Dispose::Background} else {Dispose::None});
+            }
+            _ => ()
+        }
+    }
+    if image.frames.is_empty() {
+        return Err("WebP animation has no frames".to_string())
+    }
+    Ok(image)
+}
+
+// decodes the image chunks of a still image or a frame, with the optional
+// alpha chunk in front of lossy data
+fn decode_still(chunks: &[Chunk]) -> Result<(usize, usize, Vec<u32>), String> {
+    let mut alpha = None;
+    for chunk in chunks {
+        match chunk.id {
+            b"ALPH" => alpha = Some(chunk.data),
+            b"VP8L" => return vp8l::decode_vp8l(chunk.data),
+            b"VP8 " => {
+                let (width, height, mut pixels) = vp8::decode_vp8(chunk.data)?;
+                if let Some(alpha) = alpha {
+                    let alpha = decode_alpha(alpha, width, height)?;
+                    for (pixel, a) in pixels.iter_mut().zip(alpha) {
+                        *pixel = (*pixel & 0x00ffffff) | (a as u32) << 24;
+                    }
+                }
+                return Ok((width, height, pixels))
+            }
+            _ => ()
+        }
+    }
+    Err("WebP file has no image data".to_string())
+}
+
+fn decode_alpha(data: &[u8], width: usize, height: usize) -> Result<Vec<u8>, String> {
+    let header = *data.first().ok_or("WebP alpha chunk is empty")?;
+    let mut alpha = match header & 3 {
+        0 => {
+            let raw = data.get(1..1 + width * height).ok_or("WebP alpha data ends early")?;
+            raw.to_vec()
+        }
+        1 => vp8l::decode_vp8l_stream(&data[1..], width, height)?
+            .iter()
+            .map( | p | (p >> 8) as u8)
+            .collect(),
+        _ => return Err("Unknown WebP alpha compression".to_string())
+    };
+    unfilter_alpha(&mut alpha, width, (header >> 2) & 3);
+    Ok(alpha)
+}
+
+// undoes the horizontal, vertical or gradient prediction of the alpha plane
+fn unfilter_alpha(alpha: &mut [u8], width: usize, filter: u8) {
+    if filter == 0 {
+        return
+    }
+    for y in 0..alpha.len() / width {
+        let row = y * width;
+        for x in 0..width {
+            let i = row + x;
+            let pred = match (filter, x, y) {
+                (_, 0, 0) => 0,
+                // the first row is always predicted from the left
+                (_, _, 0) => alpha[i - 1],
+                // and the first column from above
+                (1 | 3, 0, _) => alpha[i - width],
+                (1, _, _) => alpha[i - 1],
+                (2, _, _) => alpha[i - width],
+                _ => {
+                    let gradient = alpha[i - 1] as i32 + alpha[i - width] as i32 - alpha[i - width - 1] as i32;
+                    gradient.clamp(0, 255) as u8
+                }
+            };
+            alpha[i] = alpha[i].wrapping_add(pred);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::decode_webp,
+        crate::argb,
+    };
+
+    // all three are 13x9 with red rising along x, green along y and alpha
+    // falling along x, the .rgba files are what libwebp decodes the lossy ones to
+    const LOSSY: &[u8] = include_bytes!("../../tests/data/lossy.webp");
+    const LOSSY_RGBA: &[u8] = include_bytes!("../../tests/data/lossy.rgba");
+    const ALPHA: &[u8] = include_bytes!("../../tests/data/alpha.webp");
+    const ALPHA_RGBA: &[u8] = include_bytes!("../../tests/data/alpha.rgba");
+    const LOSSLESS: &[u8] = include_bytes!("../../tests/data/lossless.webp");
+
+    fn pattern(x: usize, y: usize) -> u32 {
+        argb((x * 19) as u8, (y * 28) as u8, (x * y * 7) as u8, (255 - x * 10) as u8)
+    }
+
+    // the largest difference of any channel against the reference decode
+    fn max_difference(pixels: &[u32], rgba: &[u8]) -> i32 {
+        let mut max = 0;
+        for (pixel, rgba) in pixels.iter().zip(rgba.chunks(4)) {
+            for (shift, value) in [16, 8, 0, 24].into_iter().zip(rgba) {
+                max = max.max((((pixel >> shift) & 0xff) as i32 - *value as i32).abs());
+            }
+        }
+        max
+    }
+
+    #[test]
+    fn decodes_lossless() {
+        let image = decode_webp(LOSSLESS).unwrap();
+        assert_eq!((image.width, image.height, image.plays), (13, 9, 1));
+        assert_eq!(image.frames.len(), 1);
+        let expected: Vec<u32> = (0..13 * 9).map( | i | pattern(i % 13, i / 13)).collect();
+        assert!(image.frames[0].data == expected);
+    }
+
+    #[test]
+    fn decodes_lossy_close_to_libwebp() {
+        let image = decode_webp(LOSSY).unwrap();
+        assert_eq!((image.width, image.height), (13, 9));
+        assert!(max_difference(&image.frames[0].data, LOSSY_RGBA) <= 2);
+        assert!(image.frames[0].data.iter().all( | pixel | pixel >> 24 == 255));
+    }
+
+    #[test]
+    fn decodes_lossy_with_alpha_close_to_libwebp() {
+        let image = decode_webp(ALPHA).unwrap();
+        assert_eq!((image.width, image.height), (13, 9));
+        assert!(max_difference(&image.frames[0].data, ALPHA_RGBA) <= 2);
+    }
+
+    #[test]
+    fn broken_input_is_an_error() {
+        assert!(decode_webp(&[]).is_err());
+        assert!(decode_webp(b"RIFF\x04\x00\x00\x00WEBP").is_err());
+        assert!(decode_webp(b"RIFF\x04\x00\x00\x00WAVE").is_err());
+        for file in [LOSSY, ALPHA, LOSSLESS] {
+            // cut inside the first chunk header and inside the image data
+            assert!(decode_webp(&file[..16]).is_err());
+            assert!(decode_webp(&file[..file.len() / 2]).is_err());
+        }
+        // an extended header claiming a canvas of 2^24 by 2^24
+        assert!(decode_webp(b"RIFF\x16\x00\x00\x00WEBPVP8X\x0a\x00\x00\x00\x02\x00\x00\x00\xff\xff\xff\xff\xff\xff").is_err());
+        // a VP8 frame without its start code
+        let mut data = LOSSY.to_vec();
+        data[23] = 0;
+        assert!(decode_webp(&data).is_err());
+        // a VP8L stream without its signature byte
+        let mut data = LOSSLESS.to_vec();
+        data[20] = 0;
+        assert!(decode_webp(&data).is_err());
+    }
+
+    #[test]
+    fn cut_or_corrupted_input_does_not_panic() {
+        for file in [LOSSY, ALPHA, LOSSLESS] {
+            for len in 0..file.len() {
+                let _ = decode_webp(&file[..len]);
+            }
+            for pos in 0..file.len() {
+                for value in [0x00, 0x01, 0x80, 0xff] {
+                    let mut data = file.to_vec();
+                    data[pos] = value;
+                    let _ = decode_webp(&data);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1156 @@
+use super::vp8_tables::{AC_QUANT, COEFF_PROBS, COEFF_UPDATE_PROBS, DC_QUANT, KF_BMODE_PROBS};
+
+// The lossy WebP bitstream, a VP8 key frame. Macroblocks of 16x16 luma and two
+// 8x8 chroma blocks are intra predicted from their decoded neighbours, residuals
+// are added back through the inverse DCT and the loop filter smooths the block
+// edges once the whole frame is reconstructed. Rounding follows libwebp so the
+// output matches other decoders to the bit.
+
+const ZIGZAG: [usize; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
+const BANDS: [usize; 17] = [0, 1, 2, 3, 6, 4, 5, 6, 6, 6, 6, 6, 6, 6, 6, 7, 0];
+
+const CAT3: [u8; 3] = [173, 148, 140];
+const CAT4: [u8; 4] = [176, 155, 140, 135];
+const CAT5: [u8; 5] = [180, 157, 141, 134, 130];
+const CAT6: [u8; 11] = [254, 254, 243, 230, 196, 177, 153, 140, 133, 130, 129];
+
+// 16x16 and chroma modes, the first four 4x4 modes share the numbering
+const DC_PRED: u8 = 0;
+const TM_PRED: u8 = 1;
+const V_PRED: u8 = 2;
+const H_PRED: u8 = 3;
+
+const B_DC_PRED: u8 = 0;
+const B_TM_PRED: u8 = 1;
+const B_VE_PRED: u8 = 2;
+const B_HE_PRED: u8 = 3;
+const B_RD_PRED: u8 = 4;
+const B_VR_PRED: u8 = 5;
+const B_LD_PRED: u8 = 6;
+const B_VL_PRED: u8 = 7;
+const B_HD_PRED: u8 = 8;
+const B_HU_PRED: u8 = 9;
+
+// the scratch area a macroblock is predicted in, with a row above and a column
+// left of each plane and 4 extra top right samples for luma
+const BPS: usize = 32;
+const Y_OFF: usize = BPS + 1;
+const U_OFF: usize = Y_OFF + 17 * BPS;
+const V_OFF: usize = U_OFF + 16;
+const SCRATCH_SIZE: usize = U_OFF + 9 * BPS;
+
+struct BoolDecoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    value: u32,
+    range: u32,
+    bit_count: u32,
+}
+
+impl<'a> BoolDecoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        let mut decoder = Self {data, pos: 0, value: 0, range: 255, bit_count: 0};
+        decoder.value = decoder.next_byte() << 8 | decoder.next_byte();
+        decoder
+    }
+
+    // reads past the end as zeros, like the reference decoder
+    fn next_byte(&mut self) -> u32 {
+        let byte = self.data.get(self.pos).copied().unwrap_or(0);
+        self.pos += 1;
+        byte as u32
+    }
+
+    fn read_bool(&mut self, prob: u8) -> bool {
+        let split = 1 + (((self.range - 1) * prob as u32) >> 8);
+        let big_split = split << 8;
+        let bit = if self.value >= big_split {
+            self.range -= split;
+            self.value -= big_split;
+            true
+        }
+        else {
+            self.range = split;
+            false
+        };
+        while self.range < 128 {
+            self.value <<= 1;
+            self.range <<= 1;
+            self.bit_count += 1;
+            if self.bit_count == 8 {
+                self.bit_count = 0;
+                self.value |= self.next_byte();
+            }
+        }
+        bit
+    }
+
+    fn flag(&mut self) -> bool {
+        self.read_bool(128)
+    }
+
+    fn literal(&mut self, bits: u32) -> u32 {
+        (0..bits).fold(0, | value, _ | value << 1 | self.flag() as u32)
+    }
+
+    fn signed(&mut self, bits: u32) -> i32 {
+        let value = self.literal(bits) as i32;
+        if self.flag() {-value} else {value}
+    }
+
+    fn optional_signed(&mut self, bits: u32) -> i32 {
+        if self.flag() {self.signed(bits)} else {0}
+    }
+
+    fn is_past_end(&self) -> bool {
+        self.pos > self.data.len() + 2
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Quant {
+    y1: [i32; 2],
+    y2: [i32; 2],
+    uv: [i32; 2],
+}
+
+#[derive(Clone, Copy, Default)]
+struct FilterInfo {
+    limit: i32,
+    inner_limit: i32,
+    hev_thresh: i32,
+    inner: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct MacroBlock {
+    filter: FilterInfo,
+}
+
+struct Header {
+    mb_width: usize,
+    mb_height: usize,
+    use_segment: bool,
+    update_map: bool,
+    // segment quantizers, deltas from the base unless absolute
+    segment_quant: [i32; 4],
+    absolute_delta: bool,
+    segment_probs: [u8; 3],
+    quants: [Quant; 4],
+    filter_type: u8,
+    filters: [[FilterInfo; 2]; 4],
+    probs: [[[[u8; 11]; 3]; 8]; 4],
+    skip_prob: Option<u8>,
+}
+
+// Decodes a VP8 chunk to opaque 0xAARRGGBB pixels.
+pub fn decode_vp8(data: &[u8]) -> Result<(usize, usize, Vec<u32>), String> {
+    if data.len() < 10 {
+        return Err("WebP lossy data ends early".to_string())
+    }
+    let tag = data[0] as u32 | (data[1] as u32) << 8 | (data[2] as u32) << 16;
+    if tag & 1 != 0 {
+        return Err("WebP lossy image is not a key frame".to_string())
+    }
+    let first_size = (tag >> 5) as usize;
+    if data[3..6] != [0x9d, 0x01, 0x2a] {
+        return Err("Invalid WebP lossy start code".to_string())
+    }
+    let width = (u16::from_le_bytes([data[6], data[7]]) & 0x3fff) as usize;
+    let height = (u16::from_le_bytes([data[8], data[9]]) & 0x3fff) as usize;
+    if width == 0 || height == 0 {
+        return Err("WebP lossy image has no size".to_string())
+    }
+    let first = data.get(10..10 + first_size).ok_or("WebP lossy data ends early")?;
+    let mut br = BoolDecoder::new(first);
+    let mut header = read_header(&mut br, width, height);
+
+    let rest = &data[10 + first_size..];
+    let num_parts = 1usize << br.literal(2);
+    let sizes_len = 3 * (num_parts - 1);
+    if rest.len() < sizes_len {
+        return Err("WebP lossy partitions end early".to_string())
+    }
+    let mut parts = Vec::with_capacity(num_parts);
+    let mut start = sizes_len;
+    for p in 0..num_parts {
+        let size = if p + 1 < num_parts {
+            let s = &rest[3 * p..];
+            (s[0] as usize | (s[1] as usize) << 8 | (s[2] as usize) << 16).min(rest.len() - start)
+        } else {
+            rest.len() - start
+        };
+        parts.push(BoolDecoder::new(&rest[start..start + size]));
+        start += size;
+    }
+
+    read_quant(&mut br, &mut header);
+    // refresh entropy probs, meaningless for a single frame
+    br.flag();
+    for t in 0..4 {
+        for b in 0..8 {
+            for c in 0..3 {
+                for p in 0..11 {
+                    if br.read_bool(COEFF_UPDATE_PROBS[t][b][c][p]) {
+                        header.probs[t][b][c][p] = br.literal(8) as u8;
+                    }
+                }
+            }
+        }
+    }
+    header.skip_prob = if br.flag() {Some(br.literal(8) as u8)} else {None};
+
+    let mut frame = Frame::new(&header);
+    frame.decode(&header, &mut br, &mut parts)?;
+    if header.filter_type > 0 {
+        frame.filter(&header);
+    }
+    Ok((width, height, frame.to_argb(width, height)))
+}
+
+fn read_header(br: &mut BoolDecoder, width: usize, height: usize) -> Header {
+    let _color_space = br.flag();
+    let _clamping = br.flag();
+
+    let mut segment_quant = [0i32; 4];
+    let mut segment_filter = [0i32; 4];
+    let mut absolute = false;
+    let mut segment_probs = [255u8; 3];
+    let use_segment = br.flag();
+    let mut update_map = false;
+    if use_segment {
+        update_map = br.flag();
+        if br.flag() {
+            absolute = br.flag();
+            for q in &mut segment_quant {
+                *q = br.optional_signed(7);
+            }
+            for f in &mut segment_filter {
+                *f = br.optional_signed(6);
+            }
+        }
+        if update_map {
+            for p in &mut segment_probs {
+                *p = if br.flag() {br.literal(8) as u8} else {255};
+            }
+        }
+    }
+
+    let simple = br.flag();
+    let level = br.literal(6) as i32;
+    let sharpness = br.literal(3) as i32;
+    let mut ref_delta = 0;
+    let mut mode_delta = 0;
+    let use_lf_delta = br.flag();
+    if use_lf_delta && br.flag() {
+        // only the intra frame and B_PRED deltas apply to key frames
+        for i in 0..4 {
+            let delta = br.optional_signed(6);
+            if i == 0 {
+                ref_delta = delta;
+            }
+        }
+        for i in 0..4 {
+            let delta = br.optional_signed(6);
+            if i == 0 {
+                mode_delta = delta;
+            }
+        }
+    }
+    let filter_type = if level == 0 {0} else if simple {1} else {2};
+
+    let mut filters = [[FilterInfo::default(); 2]; 4];
+    for s in 0..4 {
+        let base = if use_segment {
+            segment_filter[s] + if absolute {0} else {level}
+        }
+        else {
+            level
+        };
+        for (i4x4, info) in filters[s].iter_mut().enumerate() {
+            let mut level = base;
+            if use_lf_delta {
+                level += ref_delta;
+                if i4x4 == 1 {
+                    level += mode_delta;
+                }
+            }
+            let level = level.clamp(0, 63);
+            if level > 0 {
+                let mut inner_limit = level;
+                if sharpness > 0 {
+                    inner_limit >>= if sharpness > 4 {2} else {1};
+                    inner_limit = inner_limit.min(9 - sharpness);
+                }
+                let inner_limit = inner_limit.max(1);
+                info.inner_limit = inner_limit;
+                info.limit = 2 * level + inner_limit;
+                info.hev_thresh = if level >= 40 {2} else if level >= 15 {1} else {0};
+            }
+            info.inner = i4x4 == 1;
+        }
+    }
+
+    Header {
+        mb_width: (width + 15) / 16,
+        mb_height: (height + 15) / 16,
+        use_segment,
+        update_map,
+        segment_probs,
+        segment_quant,
+        absolute_delta: absolute,
+        quants: [Quant::default(); 4],
+        filter_type,
+        filters,
+        probs: COEFF_PROBS,
+        skip_prob: None,
+    }
+}
+
+fn read_quant(br: &mut BoolDecoder, header: &mut Header) {
+    let base = br.literal(7) as i32;
+    let y1_dc = br.optional_signed(4);
+    let y2_dc = br.optional_signed(4);
+    let y2_ac = br.optional_signed(4);
+    let uv_dc = br.optional_signed(4);
+    let uv_ac = br.optional_signed(4);
+    let dc = | q: i32, max: i32 | DC_QUANT[q.clamp(0, max) as usize] as i32;
+    let ac = | q: i32 | AC_QUANT[q.clamp(0, 127) as usize] as i32;
+    for (s, quant) in header.quants.iter_mut().enumerate() {
+        let q = if header.use_segment {
+            header.segment_quant[s] + if header.absolute_delta {0} else {base}
+        }
+        else {
+            base
+        };
+        *quant = Quant {
+            y1: [dc(q + y1_dc, 127), ac(q)],
+            y2: [dc(q + y2_dc, 127) * 2, ((ac(q + y2_ac) * 101581) >> 16).max(8)],
+            uv: [dc(q + uv_dc, 117), ac(q + uv_ac)],
+        };
+    }
+}
+
+struct Frame {
+    mb_width: usize,
+    y_stride: usize,
+    uv_stride: usize,
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+    blocks: Vec<MacroBlock>,
+}
+
+struct BlockModes {
+    is_i4x4: bool,
+    y_mode: u8,
+    sub_modes: [u8; 16],
+    uv_mode: u8,
+}
+
+impl Frame {
+    fn new(header: &Header) -> Self {
+        let y_stride = header.mb_width * 16;
+        let uv_stride = header.mb_width * 8;
+        Self {
+            mb_width: header.mb_width,
+            y_stride,
+            uv_stride,
+            y: vec![0; y_stride * header.mb_height * 16],
+            u: vec![0; uv_stride * header.mb_height * 8],
+            v: vec![0; uv_stride * header.mb_height * 8],
+            blocks: vec![MacroBlock::default(); header.mb_width * header.mb_height],
+        }
+    }
+
+    fn decode(&mut self, header: &Header, br: &mut BoolDecoder, parts: &mut [BoolDecoder]) -> Result<(), String> {
+        // the 4x4 modes above and left, for the mode probabilities
+        let mut top_modes = vec![B_DC_PRED; header.mb_width * 4];
+        // non zero flags of the blocks above and left: 4 luma, 2 u, 2 v and the
+        // luma dc block
+        let mut top_nz = vec![[0u8; 9]; header.mb_width];
+        let mut scratch = [0u8; SCRATCH_SIZE];
+        let mut coeffs = [0i32; 384];
+        for mb_y in 0..header.mb_height {
+            let mut left_modes = [B_DC_PRED; 4];
+            let mut left_nz = [0u8; 9];
+            let part = &mut parts[mb_y & (parts.len() - 1)];
+            for mb_x in 0..header.mb_width {
+                let segment = if header.update_map {
+                    if !br.read_bool(header.segment_probs[0]) {
+                        br.read_bool(header.segment_probs[1]) as usize
+                    }
+                    else {
+                        2 + br.read_bool(header.segment_probs[2]) as usize
+                    }
+                }
+                else {
+                    0
+                };
+                let skip = header.skip_prob.map_or(false, | p | br.read_bool(p));
+                let modes = read_modes(br, &mut top_modes[mb_x * 4..mb_x * 4 + 4], &mut left_modes);
+
+                coeffs.fill(0);
+                let has_coeffs = if skip {
+                    for i in 0..8 {
+                        top_nz[mb_x][i] = 0;
+                        left_nz[i] = 0;
+                    }
+                    if !modes.is_i4x4 {
+                        top_nz[mb_x][8] = 0;
+                        left_nz[8] = 0;
+                    }
+                    false
+                }
+                else {
+                    read_residuals(part, header, &header.quants[segment], modes.is_i4x4, &mut top_nz[mb_x], &mut left_nz, &mut coeffs)
+                };
+                if header.filter_type > 0 {
+                    let mut filter = header.filters[segment][modes.is_i4x4 as usize];
+                    filter.inner |= has_coeffs;
+                    self.blocks[mb_y * self.mb_width + mb_x].filter = filter;
+                }
+                self.reconstruct(header, mb_x, mb_y, &modes, &coeffs, &mut scratch);
+            }
+            if br.is_past_end() || part.is_past_end() {
+                return Err("WebP lossy data ends early".to_string())
+            }
+        }
+        Ok(())
+    }
+
+    // fills the scratch borders from the unfiltered frame, predicts and adds the
+    // residuals, then stores the macroblock back
+    fn reconstruct(&mut self, header: &Header, mb_x: usize, mb_y: usize, modes: &BlockModes, coeffs: &[i32; 384], ws: &mut [u8; SCRATCH_SIZE]) {
+        let (x0, y0) = (mb_x * 16, mb_y * 16);
+        let ys = self.y_stride;
+        // luma borders, 127 above the frame and 129 left of it
+        for i in 0..21 {
+            ws[Y_OFF - BPS - 1 + i] = if mb_y == 0 {
+                127
+            }
+            else if i == 0 {
+                if mb_x == 0 {129} else {self.y[(y0 - 1) * ys + x0 - 1]}
+            }
+            else if i <= 16 {
+                self.y[(y0 - 1) * ys + x0 + i - 1]
+            }
+            else if mb_x + 1 < header.mb_width {
+                self.y[(y0 - 1) * ys + x0 + i - 1]
+            }
+            else {
+                self.y[(y0 - 1) * ys + x0 + 15]
+            };
+        }
+        for j in 0..16 {
+            ws[Y_OFF + j * BPS - 1] = if mb_x == 0 {129} else {self.y[(y0 + j) * ys + x0 - 1]};
+        }
+        // the top right of the right column subblocks repeats the one above the macroblock
+        for j in [3, 7, 11] {
+            for i in 16..20 {
+                ws[Y_OFF + j * BPS + i] = ws[Y_OFF - BPS + i];
+            }
+        }
+        let (cx0, cy0) = (mb_x * 8, mb_y * 8);
+        let cs = self.uv_stride;
+        for (off, plane) in [(U_OFF, &self.u), (V_OFF, &self.v)] {
+            for i in 0..9 {
+                ws[off - BPS - 1 + i] = if mb_y == 0 {
+                    127
+                }
+                else if i == 0 {
+                    if mb_x == 0 {129} else {plane[(cy0 - 1) * cs + cx0 - 1]}
+                }
+                else {
+                    plane[(cy0 - 1) * cs + cx0 + i - 1]
+                };
+            }
+            for j in 0..8 {
+                ws[off + j * BPS - 1] = if mb_x == 0 {129} else {plane[(cy0 + j) * cs + cx0 - 1]};
+            }
+        }
+
+        if modes.is_i4x4 {
+            for n in 0..16 {
+                let off = Y_OFF + (n >> 2) * 4 * BPS + (n & 3) * 4;
+                predict4(ws, off, modes.sub_modes[n]);
+                inverse_dct(&coeffs[n * 16..n * 16 + 16], ws, off);
+            }
+        }
+        else {
+            predict_block(ws, Y_OFF, 16, check_mode(modes.y_mode, mb_x, mb_y));
+            for n in 0..16 {
+                inverse_dct(&coeffs[n * 16..n * 16 + 16], ws, Y_OFF + (n >> 2) * 4 * BPS + (n & 3) * 4);
+            }
+        }
+        let uv_mode = check_mode(modes.uv_mode, mb_x, mb_y);
+        for (plane, off) in [(0, U_OFF), (1, V_OFF)] {
+            predict_block(ws, off, 8, uv_mode);
+            for n in 0..4 {
+                let block = 256 + plane * 64 + n * 16;
+                inverse_dct(&coeffs[block..block + 16], ws, off + (n >> 1) * 4 * BPS + (n & 1) * 4);
+            }
+        }
+
+        for j in 0..16 {
+            self.y[(y0 + j) * ys + x0..(y0 + j) * ys + x0 + 16].copy_from_slice(&ws[Y_OFF + j * BPS..Y_OFF + j * BPS + 16]);
+        }
+        for j in 0..8 {
+            let row = (cy0 + j) * cs + cx0;
+            self.u[row..row + 8].copy_from_slice(&ws[U_OFF + j * BPS..U_OFF + j * BPS + 8]);
+            self.v[row..row + 8].copy_from_slice(&ws[V_OFF + j * BPS..V_OFF + j * BPS + 8]);
+        }
+    }
+
+    fn filter(&mut self, header: &Header) {
+        let (ys, cs) = (self.y_stride, self.uv_stride);
+        for mb_y in 0..header.mb_height {
+            for mb_x in 0..header.mb_width {
+                let info = self.blocks[mb_y * self.mb_width + mb_x].filter;
+                if info.limit == 0 {
+                    continue
+                }
+                let y_off = mb_y * 16 * ys + mb_x * 16;
+                let uv_off = mb_y * 8 * cs + mb_x * 8;
+                let (limit, inner, hev) = (info.limit, info.inner_limit, info.hev_thresh);
+                if header.filter_type == 1 {
+                    let y = &mut self.y;
+                    if mb_x > 0 {
+                        simple_filter(y, y_off, 1, ys, limit + 4);
+                    }
+                    if info.inner {
+                        for i in [4, 8, 12] {
+                            simple_filter(y, y_off + i, 1, ys, limit);
+                        }
+                    }
+                    if mb_y > 0 {
+                        simple_filter(y, y_off, ys, 1, limit + 4);
+                    }
+                    if info.inner {
+                        for i in [4, 8, 12] {
+                            simple_filter(y, y_off + i * ys, ys, 1, limit);
+                        }
+                    }
+                }
+                else {
+                    if mb_x > 0 {
+                        edge_filter(&mut self.y, y_off, 1, ys, 16, limit + 4, inner, hev);
+                        edge_filter(&mut self.u, uv_off, 1, cs, 8, limit + 4, inner, hev);
+                        edge_filter(&mut self.v, uv_off, 1, cs, 8, limit + 4, inner, hev);
+                    }
+                    if info.inner {
+                        for i in [4, 8, 12] {
+                            inner_filter(&mut self.y, y_off + i, 1, ys, 16, limit, inner, hev);
+                        }
+                        inner_filter(&mut self.u, uv_off + 4, 1, cs, 8, limit, inner, hev);
+                        inner_filter(&mut self.v, uv_off + 4, 1, cs, 8, limit, inner, hev);
+                    }
+                    if mb_y > 0 {
+                        edge_filter(&mut self.y, y_off, ys, 1, 16, limit + 4, inner, hev);
+                        edge_filter(&mut self.u, uv_off, cs, 1, 8, limit + 4, inner, hev);
+                        edge_filter(&mut self.v, uv_off, cs, 1, 8, limit + 4, inner, hev);
+                    }
+                    if info.inner {
+                        for i in [4, 8, 12] {
+                            inner_filter(&mut self.y, y_off + i * ys, ys, 1, 16, limit, inner, hev);
+                        }
+                        inner_filter(&mut self.u, uv_off + 4 * cs, cs, 1, 8, limit, inner, hev);
+                        inner_filter(&mut self.v, uv_off + 4 * cs, cs, 1, 8, limit, inner, hev);
+                    }
+                }
+            }
+        }
+    }
+
+    // converts to rgb, upsampling chroma with the 9-3-3-1 filter between the
+    // nearest samples
+    fn to_argb(&self, width: usize, height: usize) -> Vec<u32> {
+        let uv_width = (width + 1) / 2;
+        let uv_height = (height + 1) / 2;
+        let mut out = vec![0u32; width * height];
+        let mut row_u = vec![0u8; width];
+        let mut row_v = vec![0u8; width];
+        for y in 0..height {
+            let (near, far) = if y == 0 {
+                (0, 0)
+            }
+            else if y & 1 == 1 {
+                ((y - 1) / 2, ((y + 1) / 2).min(uv_height - 1))
+            }
+            else {
+                (y / 2, y / 2 - 1)
+            };
+            for (plane, row) in [(&self.u, &mut row_u), (&self.v, &mut row_v)] {
+                let near = &plane[near * self.uv_stride..near * self.uv_stride + uv_width];
+                let far = &plane[far * self.uv_stride..far * self.uv_stride + uv_width];
+                upsample_row(near, far, row, width);
+            }
+            let luma = &self.y[y * self.y_stride..];
+            for x in 0..width {
+                out[y * width + x] = yuv_to_argb(luma[x], row_u[x], row_v[x]);
+            }
+        }
+        out
+    }
+}
+
+fn read_modes(br: &mut BoolDecoder, top: &mut [u8], left: &mut [u8; 4]) -> BlockModes {
+    let mut modes = BlockModes {is_i4x4: false, y_mode: DC_PRED, sub_modes: [B_DC_PRED; 16], uv_mode: DC_PRED};
+    if br.read_bool(145) {
+        let y_mode = if br.read_bool(156) {
+            if br.read_bool(128) {TM_PRED} else {H_PRED}
+        }
+        else if br.read_bool(163) {
+            V_PRED
+        }
+        else {
+            DC_PRED
+        };
+        modes.y_mode = y_mode;
+        top.fill(y_mode);
+        left.fill(y_mode);
+    }
+    else {
+        modes.is_i4x4 = true;
+        for y in 0..4 {
+            let mut mode = left[y];
+            for x in 0..4 {
+                let p = &KF_BMODE_PROBS[top[x] as usize][mode as usize];
+                mode = if !br.read_bool(p[0]) {
+                    B_DC_PRED
+                }
+                else if !br.read_bool(p[1]) {
+                    B_TM_PRED
+                }
+                else if !br.read_bool(p[2]) {
+                    B_VE_PRED
+                }
+                else if !br.read_bool(p[3]) {
+                    if !br.read_bool(p[4]) {
+                        B_HE_PRED
+                    }
+                    else if !br.read_bool(p[5]) {
+                        B_RD_PRED
+                    }
+                    else {
+                        B_VR_PRED
+                    }
+                }
+                else if !br.read_bool(p[6]) {
+                    B_LD_PRED
+                }
+                else if !br.read_bool(p[7]) {
+                    B_VL_PRED
+                }
+                else if !br.read_bool(p[8]) {
+                    B_HD_PRED
+                }
+                else {
+                    B_HU_PRED
+                };
+                top[x] = mode;
+                modes.sub_modes[y * 4 + x] = mode;
+            }
+            left[y] = mode;
+        }
+    }
+    modes.uv_mode = if !br.read_bool(142) {
+        DC_PRED
+    }
+    else if !br.read_bool(114) {
+        V_PRED
+    }
+    else if br.read_bool(183) {
+        TM_PRED
+    }
+    else {
+        H_PRED
+    };
+    modes
+}
+
+// Reads the coefficients of a macroblock into 16 luma, 4 u and 4 v blocks of 16,
+// returning whether any of them is non zero.
+fn read_residuals(
+    br: &mut BoolDecoder,
+    header: &Header,
+    quant: &Quant,
+    is_i4x4: bool,
+    top: &mut [u8; 9],
+    left: &mut [u8; 9],
+    coeffs: &mut [i32; 384]
+) -> bool {
+    let probs = &header.probs;
+    let mut any = false;
+    let (first, y_type) = if is_i4x4 {
+        (0, 3)
+    }
+    else {
+        let mut dc = [0i32; 16];
+        let ctx = (top[8] + left[8]) as usize;
+        let nz = read_coeffs(br, &probs[1], ctx, &quant.y2, 0, &mut dc);
+        top[8] = (nz > 0) as u8;
+        left[8] = top[8];
+        inverse_wht(&dc, coeffs);
+        any |= (0..16).any( | n | coeffs[n * 16] != 0);
+        (1, 0)
+    };
+    for y in 0..4 {
+        let mut l = left[y];
+        for x in 0..4 {
+            let ctx = (l + top[x]) as usize;
+            let block = &mut coeffs[(y * 4 + x) * 16..(y * 4 + x) * 16 + 16];
+            let nz = read_coeffs(br, &probs[y_type], ctx, &quant.y1, first, block);
+            l = (nz > first) as u8;
+            top[x] = l;
+            any |= nz > first;
+        }
+        left[y] = l;
+    }
+    for plane in 0..2 {
+        let base = 4 + plane * 2;
+        for y in 0..2 {
+            let mut l = left[base + y];
+            for x in 0..2 {
+                let ctx = (l + top[base + x]) as usize;
+                let block = 256 + plane * 64 + (y * 2 + x) * 16;
+                let nz = read_coeffs(br, &probs[2], ctx, &quant.uv, 0, &mut coeffs[block..block + 16]);
+                l = (nz > 0) as u8;
+                top[base + x] = l;
+                any |= nz > 0;
+            }
+            left[base + y] = l;
+        }
+    }
+    any
+}
+
+// Reads the tokens of one block from position n, returning the position after
+// the last non zero coefficient.
+fn read_coeffs(br: &mut BoolDecoder, probs: &[[[u8; 11]; 3]; 8], ctx: usize, dq: &[i32; 2], mut n: usize, out: &mut [i32]) -> usize {
+    let mut p = &probs[BANDS[n]][ctx];
+    while n < 16 {
+        if !br.read_bool(p[0]) {
+            return n
+        }
+        while !br.read_bool(p[1]) {
+            n += 1;
+            if n == 16 {
+                return 16
+            }
+            p = &probs[BANDS[n]][0];
+        }
+        let next = &probs[BANDS[n + 1]];
+        let value = if !br.read_bool(p[2]) {
+            p = &next[1];
+            1
+        }
+        else {
+            let value = read_large_value(br, p);
+            p = &next[2];
+            value
+        };
+        let value = if br.flag() {-value} else {value};
+        // libwebp keeps coefficients in 16 bits, which also bounds the transforms
+        out[ZIGZAG[n]] = (value * dq[(n > 0) as usize]) as i16 as i32;
+        n += 1;
+    }
+    16
+}
+
+fn read_large_value(br: &mut BoolDecoder, p: &[u8; 11]) -> i32 {
+    if !br.read_bool(p[3]) {
+        if !br.read_bool(p[4]) {
+            2
+        }
+        else {
+            3 + br.read_bool(p[5]) as i32
+        }
+    }
+    else if !br.read_bool(p[6]) {
+        if !br.read_bool(p[7]) {
+            5 + br.read_bool(159) as i32
+        }
+        else {
+            7 + 2 * br.read_bool(165) as i32 + br.read_bool(145) as i32
+        }
+    }
+    else {
+        let bit1 = br.read_bool(p[8]) as usize;
+        let bit0 = br.read_bool(p[9 + bit1]) as usize;
+        let cat = 2 * bit1 + bit0;
+        let table: &[u8] = match cat {
+            0 => &CAT3,
+            1 => &CAT4,
+            2 => &CAT5,
+            _ => &CAT6
+        };
+        let extra = table.iter().fold(0, | v, prob | v + v + br.read_bool(*prob) as i32);
+        extra + 3 + (8 << cat)
+    }
+}
+
+// the luma dc values come through a Walsh-Hadamard transform
+fn inverse_wht(input: &[i32; 16], out: &mut [i32; 384]) {
+    let mut tmp = [0i32; 16];
+    for i in 0..4 {
+        let a0 = input[i] + input[12 + i];
+        let a1 = input[4 + i] + input[8 + i];
+        let a2 = input[4 + i] - input[8 + i];
+        let a3 = input[i] - input[12 + i];
+        tmp[i] = a0 + a1;
+        tmp[8 + i] = a0 - a1;
+        tmp[4 + i] = a3 + a2;
+        tmp[12 + i] = a3 - a2;
+    }
+    for i in 0..4 {
+        let dc = tmp[i * 4] + 3;
+        let a0 = dc + tmp[3 + i * 4];
+        let a1 = tmp[1 + i * 4] + tmp[2 + i * 4];
+        let a2 = tmp[1 + i * 4] - tmp[2 + i * 4];
+        let a3 = dc - tmp[3 + i * 4];
+        out[i * 64] = ((a0 + a1) >> 3) as i16 as i32;
+        out[i * 64 + 16] = ((a3 + a2) >> 3) as i16 as i32;
+        out[i * 64 + 32] = ((a0 - a1) >> 3) as i16 as i32;
+        out[i * 64 + 48] = ((a3 - a2) >> 3) as i16 as i32;
+    }
+}
+
+fn mul1(a: i32) -> i32 {
+    (a.wrapping_mul(20091) >> 16).wrapping_add(a)
+}
+
+fn mul2(a: i32) -> i32 {
+    a.wrapping_mul(35468) >> 16
+}
+
+fn clip_pixel(v: i32) -> u8 {
+    v.clamp(0, 255) as u8
+}
+
+// adds the inverse transform of a 4x4 block to the prediction at off
+fn inverse_dct(input: &[i32], ws: &mut [u8], off: usize) {
+    if input.iter().all( | c | *c == 0) {
+        return
+    }
+    let mut tmp = [0i32; 16];
+    for i in 0..4 {
+        let a = input[i] + input[8 + i];
+        let b = input[i] - input[8 + i];
+        let c = mul2(input[4 + i]) - mul1(input[12 + i]);
+        let d = mul1(input[4 + i]) + mul2(input[12 + i]);
+        tmp[i * 4] = a + d;
+        tmp[i * 4 + 1] = b + c;
+        tmp[i * 4 + 2] = b - c;
+        tmp[i * 4 + 3] = a - d;
+    }
+    for i in 0..4 {
+        let dc = tmp[i] + 4;
+        let a = dc + tmp[8 + i];
+        let b = dc - tmp[8 + i];
+        let c = mul2(tmp[4 + i]) - mul1(tmp[12 + i]);
+        let d = mul1(tmp[4 + i]) + mul2(tmp[12 + i]);
+        let row = off + i * BPS;
+        for (x, v) in [a + d, b + c, b - c, a - d].into_iter().enumerate() {
+            ws[row + x] = clip_pixel(ws[row + x] as i32 + (v >> 3));
+        }
+    }
+}
+
+// DC prediction leaves out the edges outside the frame
+const DC_PRED_NO_TOP: u8 = 4;
+const DC_PRED_NO_LEFT: u8 = 5;
+const DC_PRED_NO_TOP_LEFT: u8 = 6;
+
+fn check_mode(mode: u8, mb_x: usize, mb_y: usize) -> u8 {
+    if mode != DC_PRED {
+        return mode
+    }
+    match (mb_x == 0, mb_y == 0) {
+        (true, true) => DC_PRED_NO_TOP_LEFT,
+        (true, false) => DC_PRED_NO_LEFT,
+        (false, true) => DC_PRED_NO_TOP,
+        (false, false) => DC_PRED
+    }
+}
+
+// the 16x16 luma and 8x8 chroma predictors
+fn predict_block(ws: &mut [u8], off: usize, size: usize, mode: u8) {
+    let shift = if size == 16 {4} else {3};
+    let top = | ws: &[u8] | (0..size).map( | i | ws[off - BPS + i] as u32).sum::<u32>();
+    let left = | ws: &[u8] | (0..size).map( | j | ws[off + j * BPS - 1] as u32).sum::<u32>();
+    let fill = | ws: &mut [u8], value: u8 | {
+        for j in 0..size {
+            ws[off + j * BPS..off + j * BPS + size].fill(value);
+        }
+    };
+    match mode {
+        DC_PRED => {
+            let dc = (top(ws) + left(ws) + size as u32) >> (shift + 1);
+            fill(ws, dc as u8);
+        }
+        DC_PRED_NO_TOP => {
+            let dc = (left(ws) + (size as u32 >> 1)) >> shift;
+            fill(ws, dc as u8);
+        }
+        DC_PRED_NO_LEFT => {
+            let dc = (top(ws) + (size as u32 >> 1)) >> shift;
+            fill(ws, dc as u8);
+        }
+        DC_PRED_NO_TOP_LEFT => fill(ws, 0x80),
+        TM_PRED => {
+            let top_left = ws[off - BPS - 1] as i32;
+            for j in 0..size {
+                let left = ws[off + j * BPS - 1] as i32;
+                for i in 0..size {
+                    ws[off + j * BPS + i] = clip_pixel(ws[off - BPS + i] as i32 + left - top_left);
+                }
+            }
+        }
+        V_PRED => {
+            for j in 0..size {
+                ws.copy_within(off - BPS..off - BPS + size, off + j * BPS);
+            }
+        }
+        _ => {
+            for j in 0..size {
+                let left = ws[off + j * BPS - 1];
+                ws[off + j * BPS..off + j * BPS + size].fill(left);
+            }
+        }
+    }
+}
+
+fn avg2(a: u8, b: u8) -> u8 {
+    ((a as u32 + b as u32 + 1) >> 1) as u8
+}
+
+fn avg3(a: u8, b: u8, c: u8) -> u8 {
+    ((a as u32 + 2 * b as u32 + c as u32 + 2) >> 2) as u8
+}
+
+fn predict4(ws: &mut [u8], off: usize, mode: u8) {
+    // the top row with the top right, the left column and the corner
+    let t: [u8; 8] = std::array::from_fn( | i | ws[off - BPS + i]);
+    let l: [u8; 4] = std::array::from_fn( | j | ws[off + j * BPS - 1]);
+    let x = ws[off - BPS - 1];
+    let mut dst = [[0u8; 4]; 4];
+    match mode {
+        B_DC_PRED => {
+            let sum = t[..4].iter().chain(l.iter()).map( | v | *v as u32).sum::<u32>();
+            dst = [[((sum + 4) >> 3) as u8; 4]; 4];
+        }
+        B_TM_PRED => {
+            for j in 0..4 {
+                for i in 0..4 {
+                    dst[j][i] = clip_pixel(t[i] as i32 + l[j] as i32 - x as i32);
+                }
+            }
+        }
+        B_VE_PRED => {
+            let row = [avg3(x, t[0], t[1]), avg3(t[0], t[1], t[2]), avg3(t[1], t[2], t[3]), avg3(t[2], t[3], t[4])];
+            dst = [row; 4];
+        }
+        B_HE_PRED => {
+            let col = [avg3(x, l[0], l[1]), avg3(l[0], l[1], l[2]), avg3(l[1], l[2], l[3]), avg3(l[2], l[3], l[3])];
+            for j in 0..4 {
+                dst[j] = [col[j]; 4];
+            }
+        }
+        B_RD_PRED => {
+            // the edge from bottom left to top right through the corner
+            let e = [l[3], l[2], l[1], l[0], x, t[0], t[1], t[2], t[3]];
+            for j in 0..4 {
+                for i in 0..4 {
+                    let k = 3 + i - j;
+                    dst[j][i] = avg3(e[k], e[k + 1], e[k + 2]);
+                }
+            }
+        }
+        B_LD_PRED => {
+            for j in 0..4 {
+                for i in 0..4 {
+                    let k = i + j;
+                    dst[j][i] = avg3(t[k], t[k + 1], t[(k + 2).min(7)]);
+                }
+            }
+        }
+        B_VR_PRED => {
+            dst[0] = [avg2(x, t[0]), avg2(t[0], t[1]), avg2(t[1], t[2]), avg2(t[2], t[3])];
+            dst[1] = [avg3(l[0], x, t[0]), avg3(x, t[0], t[1]), avg3(t[0], t[1], t[2]), avg3(t[1], t[2], t[3])];
+            dst[2] = [avg3(l[1], l[0], x), dst[0][0], dst[0][1], dst[0][2]];
+            dst[3] = [avg3(l[2], l[1], l[0]), dst[1][0], dst[1][1], dst[1][2]];
+        }
+        B_VL_PRED => {
+            dst[0] = [avg2(t[0], t[1]), avg2(t[1], t[2]), avg2(t[2], t[3]), avg2(t[3], t[4])];
+            dst[1] = [avg3(t[0], t[1], t[2]), avg3(t[1], t[2], t[3]), avg3(t[2], t[3], t[4]), avg3(t[3], t[4], t[5])];
+            dst[2] = [dst[0][1], dst[0][2], dst[0][3], avg3(t[4], t[5], t[6])];
+            dst[3] = [dst[1][1], dst[1][2], dst[1][3], avg3(t[5], t[6], t[7])];
+        }
+        B_HD_PRED => {
+            dst[0] = [avg2(l[0], x), avg3(l[0], x, t[0]), avg3(x, t[0], t[1]), avg3(t[0], t[1], t[2])];
+            dst[1] = [avg2(l[1], l[0]), avg3(l[1], l[0], x), dst[0][0], dst[0][1]];
+            dst[2] = [avg2(l[2], l[1]), avg3(l[2], l[1], l[0]), dst[1][0], dst[1][1]];
+            dst[3] = [avg2(l[3], l[2]), avg3(l[3], l[2], l[1]), dst[2][0], dst[2][1]];
+        }
+        _ => {
+            dst[0] = [avg2(l[0], l[1]), avg3(l[0], l[1], l[2]), avg2(l[1], l[2]), avg3(l[1], l[2], l[3])];
+            dst[1] = [dst[0][2], dst[0][3], avg2(l[2], l[3]), avg3(l[2], l[3], l[3])];
+            dst[2] = [dst[1][2], dst[1][3], l[3], l[3]];
+            dst[3] = [l[3]; 4];
+        }
+    }
+    for j in 0..4 {
+        ws[off + j * BPS..off + j * BPS + 4].copy_from_slice(&dst[j]);
+    }
+}
+
+fn sclip1(v: i32) -> i32 {
+    v.clamp(-128, 127)
+}
+
+fn sclip2(v: i32) -> i32 {
+    v.clamp(-16, 15)
+}
+
+fn clip1(v: i32) -> u8 {
+    v.clamp(0, 255) as u8
+}
+
+// pixels p[-4 * step] to p[3 * step] straddle the edge between p[-step] and p[0]
+fn pixel(plane: &[u8], pos: usize, offset: isize) -> i32 {
+    plane[(pos as isize + offset) as usize] as i32
+}
+
+fn needs_filter(plane: &[u8], pos: usize, step: isize, thresh: i32) -> bool {
+    let (p1, p0, q0, q1) = (pixel(plane, pos, -2 * step), pixel(plane, pos, -step), pixel(plane, pos, 0), pixel(plane, pos, step));
+    4 * (p0 - q0).abs() + (p1 - q1).abs() <= thresh
+}
+
+fn needs_filter2(plane: &[u8], pos: usize, step: isize, thresh: i32, inner: i32) -> bool {
+    let p: [i32; 8] = std::array::from_fn( | i | pixel(plane, pos, (i as isize - 4) * step));
+    let (p3, p2, p1, p0, q0, q1, q2, q3) = (p[0], p[1], p[2], p[3], p[4], p[5], p[6], p[7]);
+    if 4 * (p0 - q0).abs() + (p1 - q1).abs() > thresh {
+        return false
+    }
+    (p3 - p2).abs() <= inner && (p2 - p1).abs() <= inner && (p1 - p0).abs() <= inner
+        && (q3 - q2).abs() <= inner && (q2 - q1).abs() <= inner && (q1 - q0).abs() <= inner
+}
+
+fn high_edge_variance(plane: &[u8], pos: usize, step: isize, thresh: i32) -> bool {
+    let (p1, p0, q0, q1) = (pixel(plane, pos, -2 * step), pixel(plane, pos, -step), pixel(plane, pos, 0), pixel(plane, pos, step));
+    (p1 - p0).abs() > thresh || (q1 - q0).abs() > thresh
+}
+
+fn set(plane: &mut [u8], pos: usize, offset: isize, value: u8) {
+    plane[(pos as isize + offset) as usize] = value;
+}
+
+fn do_filter2(plane: &mut [u8], pos: usize, step: isize) {
+    let (p1, p0, q0, q1) = (pixel(plane, pos, -2 * step), pixel(plane, pos, -step), pixel(plane, pos, 0), pixel(plane, pos, step));
+    let a = 3 * (q0 - p0) + sclip1(p1 - q1);
+    let a1 = sclip2((a + 4) >> 3);
+    let a2 = sclip2((a + 3) >> 3);
+    set(plane, pos, -step, clip1(p0 + a2));
+    set(plane, pos, 0, clip1(q0 - a1));
+}
+
+fn do_filter4(plane: &mut [u8], pos: usize, step: isize) {
+    let (p1, p0, q0, q1) = (pixel(plane, pos, -2 * step), pixel(plane, pos, -step), pixel(plane, pos, 0), pixel(plane, pos, step));
+    let a = 3 * (q0 - p0);
+    let a1 = sclip2((a + 4) >> 3);
+    let a2 = sclip2((a + 3) >> 3);
+    let a3 = (a1 + 1) >> 1;
+    set(plane, pos, -2 * step, clip1(p1 + a3));
+    set(plane, pos, -step, clip1(p0 + a2));
+    set(plane, pos, 0, clip1(q0 - a1));
+    set(plane, pos, step, clip1(q1 - a3));
+}
+
+fn do_filter6(plane: &mut [u8], pos: usize, step: isize) {
+    let (p2, p1, p0) = (pixel(plane, pos, -3 * step), pixel(plane, pos, -2 * step), pixel(plane, pos, -step));
+    let (q0, q1, q2) = (pixel(plane, pos, 0), pixel(plane, pos, step), pixel(plane, pos, 2 * step));
+    let a = sclip1(3 * (q0 - p0) + sclip1(p1 - q1));
+    let a1 = (27 * a + 63) >> 7;
+    let a2 = (18 * a + 63) >> 7;
+    let a3 = (9 * a + 63) >> 7;
+    set(plane, pos, -3 * step, clip1(p2 + a3));
+    set(plane, pos, -2 * step, clip1(p1 + a2));
+    set(plane, pos, -step, clip1(p0 + a1));
+    set(plane, pos, 0, clip1(q0 - a1));
+    set(plane, pos, step, clip1(q1 - a2));
+    set(plane, pos, 2 * step, clip1(q2 - a3));
+}
+
+// filters 16 pixels along an edge, step crosses the edge and stride walks it
+fn simple_filter(plane: &mut [u8], pos: usize, step: usize, stride: usize, thresh: i32) {
+    let thresh2 = 2 * thresh + 1;
+    for i in 0..16 {
+        let p = pos + i * stride;
+        if needs_filter(plane, p, step as isize, thresh2) {
+            do_filter2(plane, p, step as isize);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn edge_filter(plane: &mut [u8], pos: usize, step: usize, stride: usize, len: usize, thresh: i32, inner: i32, hev: i32) {
+    let thresh2 = 2 * thresh + 1;
+    let step = step as isize;
+    for i in 0..len {
+        let p = pos + i * stride;
+        if needs_filter2(plane, p, step, thresh2, inner) {
+            if high_edge_variance(plane, p, step, hev) {
+                do_filter2(plane, p, step);
+            }
+            else {
+                do_filter6(plane, p, step);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn inner_filter(plane: &mut [u8], pos: usize, step: usize, stride: usize, len: usize, thresh: i32, inner: i32, hev: i32) {
+    let thresh2 = 2 * thresh + 1;
+    let step = step as isize;
+    for i in 0..len {
+        let p = pos + i * stride;
+        if needs_filter2(plane, p, step, thresh2, inner) {
+            if high_edge_variance(plane, p, step, hev) {
+                do_filter2(plane, p, step);
+            }
+            else {
+                do_filter4(plane, p, step);
+            }
+        }
+    }
+}
+
+// one row of chroma at full width, weighting the nearest sample by 9, the
+// horizontal and vertical neighbours by 3 and the diagonal by 1
+fn upsample_row(near: &[u8], far: &[u8], out: &mut [u8], width: usize) {
+    let edge = | i: usize | ((3 * near[i] as u32 + far[i] as u32 + 2) >> 2) as u8;
+    out[0] = edge(0);
+    for x in 1..=(width - 1) / 2 {
+        let (a, b, c, d) = (near[x - 1] as u32, near[x] as u32, far[x - 1] as u32, far[x] as u32);
+        let avg = a + b + c + d + 8;
+        let diag_bc = (avg + 2 * (b + c)) >> 3;
+        let diag_ad = (avg + 2 * (a + d)) >> 3;
+        out[2 * x - 1] = ((diag_bc + a) >> 1) as u8;
+        out[2 * x] = ((diag_ad + b) >> 1) as u8;
+    }
+    if width & 1 == 0 {
+        out[width - 1] = edge((width - 1) / 2);
+    }
+}
+
+fn yuv_to_argb(y: u8, u: u8, v: u8) -> u32 {
+    let mult_hi = | v: u8, c: i32 | (v as i32 * c) >> 8;
+    let clip8 = | v: i32 | (v >> 6).clamp(0, 255) as u32;
+    let luma = mult_hi(y, 19077);
+    let r = clip8(luma + mult_hi(v, 26149) - 14234);
+    let g = clip8(luma - mult_hi(u, 6419) - mult_hi(v, 13320) + 8708);
+    let b = clip8(luma + mult_hi(u, 33050) - 17685);
+    0xff000000 | r << 16 | g << 8 | b
+}
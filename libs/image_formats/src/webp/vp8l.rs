@@ -0,0 +1,568 @@
+// The lossless WebP bitstream. Pixels are entropy coded ARGB with LZ77 style
+// back references and a small cache of recent colors, followed by up to four
+// inverse transforms that undo the encoder's decorrelation.
+
+const NUM_LITERALS: usize = 256;
+const NUM_LENGTH_CODES: usize = 24;
+const NUM_DISTANCE_CODES: usize = 40;
+const NUM_CODE_LENGTH_CODES: usize = 19;
+const CODE_LENGTH_ORDER: [usize; NUM_CODE_LENGTH_CODES] = [17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+const MAX_CODE_LENGTH: usize = 15;
+const TABLE_BITS: u32 = 8;
+
+// distances below 120 are offsets to nearby pixels, stored as y << 4 | (8 - x)
+const CODE_TO_PLANE: [u8; 120] = [
+    0x18, 0x07, 0x17, 0x19, 0x28, 0x06, 0x27, 0x29, 0x16, 0x1a,
+    0x26, 0x2a, 0x38, 0x05, 0x37, 0x39, 0x15, 0x1b, 0x36, 0x3a,
+    0x25, 0x2b, 0x48, 0x04, 0x47, 0x49, 0x14, 0x1c, 0x35, 0x3b,
+    0x46, 0x4a, 0x24, 0x2c, 0x58, 0x45, 0x4b, 0x34, 0x3c, 0x03,
+    0x57, 0x59, 0x13, 0x1d, 0x56, 0x5a, 0x23, 0x2d, 0x44, 0x4c,
+    0x55, 0x5b, 0x33, 0x3d, 0x68, 0x02, 0x67, 0x69, 0x12, 0x1e,
+    0x66, 0x6a, 0x22, 0x2e, 0x54, 0x5c, 0x43, 0x4d, 0x65, 0x6b,
+    0x32, 0x3e, 0x78, 0x01, 0x77, 0x79, 0x53, 0x5d, 0x11, 0x1f,
+    0x64, 0x6c, 0x42, 0x4e, 0x76, 0x7a, 0x21, 0x2f, 0x75, 0x7b,
+    0x31, 0x3f, 0x63, 0x6d, 0x52, 0x5e, 0x00, 0x74, 0x7c, 0x41,
+    0x4f, 0x10, 0x20, 0x62, 0x6e, 0x30, 0x73, 0x7d, 0x51, 0x5f,
+    0x40, 0x72, 0x7e, 0x61, 0x6f, 0x50, 0x71, 0x7f, 0x60, 0x70,
+];
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u64,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {data, pos: 0, bits: 0, count: 0}
+    }
+
+    fn fill(&mut self) {
+        while self.count <= 56 {
+            let byte = self.data.get(self.pos).copied().unwrap_or(0);
+            self.bits |= (byte as u64) << self.count;
+            self.count += 8;
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self, n: u32) -> u32 {
+        if self.count < n {
+            self.fill();
+        }
+        (self.bits & ((1u64 << n) - 1)) as u32
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.bits >>= n;
+        self.count -= n;
+    }
+
+    fn read(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0
+        }
+        let value = self.peek(n);
+        self.consume(n);
+        value
+    }
+
+    // true once bits beyond the data have been read
+    fn is_past_end(&self) -> bool {
+        self.pos * 8 - self.count as usize > self.data.len() * 8
+    }
+}
+
+fn truncated() -> String {
+    "WebP lossless data ends early".to_string()
+}
+
+// A canonical prefix code. Codes up to TABLE_BITS long are looked up directly,
+// longer ones are walked bit by bit.
+struct Huffman {
+    // symbol and length by the next TABLE_BITS bits, length 0 for longer codes
+    table: Vec<(u16, u8)>,
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    symbols: Vec<u16>,
+    // a code with one symbol takes no bits
+    single: Option<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, String> {
+        let mut counts = [0u16; MAX_CODE_LENGTH + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let used: Vec<usize> = (0..lengths.len()).filter( | s | lengths[*s] != 0).collect();
+        if used.is_empty() {
+            return Err("WebP prefix code has no symbols".to_string())
+        }
+        if used.len() == 1 {
+            return Ok(Self {table: Vec::new(), counts, symbols: Vec::new(), single: Some(used[0] as u16)})
+        }
+        // reject codes that use more than the whole code space
+        let mut left = 1i32;
+        for len in 1..=MAX_CODE_LENGTH {
+            left = (left << 1) - counts[len] as i32;
+            if left < 0 {
+                return Err("WebP prefix code is oversubscribed".to_string())
+            }
+        }
+        let mut offsets = [0u16; MAX_CODE_LENGTH + 2];
+        for len in 1..=MAX_CODE_LENGTH {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; used.len()];
+        for &symbol in &used {
+            let len = lengths[symbol] as usize;
+            symbols[offsets[len] as usize] = symbol as u16;
+            offsets[len] += 1;
+        }
+        // canonical codes are assigned in order of length then symbol, the bitstream
+        // sends them most significant bit first so the table is indexed reversed
+        let mut table = vec![(0u16, 0u8); 1 << TABLE_BITS];
+        let mut code = 0u32;
+        let mut index = 0;
+        for len in 1..=MAX_CODE_LENGTH {
+            for _ in 0..counts[len] {
+                if len as u32 <= TABLE_BITS {
+                    let reversed = code.reverse_bits() >> (32 - len);
+                    let mut i = reversed as usize;
+                    while i < table.len() {
+                        table[i] = (symbols[index], len as u8);
+                        i += 1 << len;
+                    }
+                }
+                code += 1;
+                index += 1;
+            }
+            code <<= 1;
+        }
+        Ok(Self {table, counts, symbols, single: None})
+    }
+
+    fn read(&self, reader: &mut BitReader) -> u16 {
+        if let Some(symbol) = self.single {
+            return symbol
+        }
+        let (symbol, len) = self.table[reader.peek(TABLE_BITS) as usize];
+        if len > 0 {
+            reader.consume(len as u32);
+            return symbol
+        }
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for len in 1..=MAX_CODE_LENGTH {
+            code |= reader.read(1) as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return self.symbols[(index + code - first) as usize]
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        // only reachable on incomplete codes
+        0
+    }
+}
+
+fn read_huffman(reader: &mut BitReader, alphabet_size: usize) -> Result<Huffman, String> {
+    let mut lengths = vec![0u8; alphabet_size];
+    if reader.read(1) == 1 {
+        let num_symbols = reader.read(1) + 1;
+        let first_bits = if reader.read(1) == 0 {1} else {8};
+        let symbol = reader.read(first_bits) as usize;
+        *lengths.get_mut(symbol).ok_or("WebP symbol out of range")? = 1;
+        if num_symbols == 2 {
+            let symbol = reader.read(8) as usize;
+            *lengths.get_mut(symbol).ok_or("WebP symbol out of range")? = 1;
+        }
+    }
+    else {
+        let mut code_length_lengths = [0u8; NUM_CODE_LENGTH_CODES];
+        let num_codes = reader.read(4) as usize + 4;
+        for i in 0..num_codes {
+            code_length_lengths[CODE_LENGTH_ORDER[i]] = reader.read(3) as u8;
+        }
+        let code_lengths = Huffman::new(&code_length_lengths)?;
+        let mut max_symbol = if reader.read(1) == 1 {
+            let length_bits = 2 + 2 * reader.read(3);
+            let max_symbol = 2 + reader.read(length_bits) as usize;
+            if max_symbol > alphabet_size {
+                return Err("WebP code length count out of range".to_string())
+            }
+            max_symbol
+        }
+        else {
+            alphabet_size
+        };
+        let mut prev_len = 8;
+        let mut symbol = 0;
+        while symbol < alphabet_size {
+            if max_symbol == 0 {
+                break
+            }
+            max_symbol -= 1;
+            let len = code_lengths.read(reader) as u8;
+            if len < 16 {
+                lengths[symbol] = len;
+                symbol += 1;
+                if len != 0 {
+                    prev_len = len;
+                }
+            }
+            else {
+                let (extra_bits, offset, value) = match len {
+                    16 => (2, 3, prev_len),
+                    17 => (3, 3, 0),
+                    _ => (7, 11, 0)
+                };
+                let repeat = reader.read(extra_bits) as usize + offset;
+                if symbol + repeat > alphabet_size {
+                    return Err("WebP code lengths overflow the alphabet".to_string())
+                }
+                lengths[symbol..symbol + repeat].fill(value);
+                symbol += repeat;
+            }
+        }
+    }
+    if reader.is_past_end() {
+        return Err(truncated())
+    }
+    Huffman::new(&lengths)
+}
+
+// green and length codes, red, blue, alpha, distance
+type HuffmanGroup = [Huffman; 5];
+
+fn read_huffman_group(reader: &mut BitReader, cache_size: usize) -> Result<HuffmanGroup, String> {
+    Ok([
+        read_huffman(reader, NUM_LITERALS + NUM_LENGTH_CODES + cache_size)?,
+        read_huffman(reader, NUM_LITERALS)?,
+        read_huffman(reader, NUM_LITERALS)?,
+        read_huffman(reader, NUM_LITERALS)?,
+        read_huffman(reader, NUM_DISTANCE_CODES)?,
+    ])
+}
+
+fn subsample_size(size: usize, bits: u32) -> usize {
+    (size + (1 << bits) - 1) >> bits
+}
+
+enum Transform {
+    Predictor {bits: u32, width: usize, data: Vec<u32>},
+    Color {bits: u32, width: usize, data: Vec<u32>},
+    SubtractGreen,
+    ColorIndexing {bits: u32, width: usize, table: Vec<u32>},
+}
+
+// Decodes a full VP8L chunk with its header, returning the size and pixels.
+pub fn decode_vp8l(data: &[u8]) -> Result<(usize, usize, Vec<u32>), String> {
+    if data.len() < 5 || data[0] != 0x2f {
+        return Err("Not a WebP lossless image".to_string())
+    }
+    let mut reader = BitReader::new(&data[1..]);
+    let width = reader.read(14) as usize + 1;
+    let height = reader.read(14) as usize + 1;
+    let _alpha_is_used = reader.read(1);
+    if reader.read(3) != 0 {
+        return Err("Unknown WebP lossless version".to_string())
+    }
+    let pixels = decode_image_stream(&mut reader, width, height, true)?;
+    Ok((width, height, pixels))
+}
+
+// Decodes headerless image data of a known size, like compressed alpha planes.
+pub fn decode_vp8l_stream(data: &[u8], width: usize, height: usize) -> Result<Vec<u32>, String> {
+    let mut reader = BitReader::new(data);
+    decode_image_stream(&mut reader, width, height, true)
+}
+
+// The main image may have transforms and several prefix code groups, the
+// sub images that carry transform and group data only have one group.
+fn decode_image_stream(reader: &mut BitReader, width: usize, height: usize, is_main: bool) -> Result<Vec<u32>, String> {
+    let mut transforms = Vec::new();
+    let mut coded_width = width;
+    if is_main {
+        let mut seen = [false; 4];
+        while reader.read(1) == 1 {
+            let kind = reader.read(2) as usize;
+            if seen[kind] {
+                return Err("WebP transform used twice".to_string())
+            }
+            seen[kind] = true;
+            match kind {
+                0 | 1 => {
+                    let bits = reader.read(3) + 2;
+                    let data = decode_image_stream(
+                        reader,
+                        subsample_size(coded_width, bits),
+                        subsample_size(height, bits),
+                        false
+                    )?;
+                    transforms.push(if kind == 0 {
+                        Transform::Predictor {bits, width: coded_width, data}
+                    } else {
+                        Transform::Color {bits, width: coded_width, data}
+                    });
+                }
+                2 => transforms.push(Transform::SubtractGreen),
+                _ => {
+                    let size = reader.read(8) as usize + 1;
+                    let mut table = decode_image_stream(reader, size, 1, false)?;
+                    for i in 1..table.len() {
+                        table[i] = add_pixels(table[i], table[i - 1]);
+                    }
+                    let bits = if size <= 2 {3} else if size <= 4 {2} else if size <= 16 {1} else {0};
+                    transforms.push(Transform::ColorIndexing {bits, width: coded_width, table});
+                    coded_width = subsample_size(coded_width, bits);
+                }
+            }
+        }
+    }
+
+    let cache_bits = if reader.read(1) == 1 {
+        let bits = reader.read(4);
+        if !(1..=11).contains(&bits) {
+            return Err("Invalid WebP color cache size".to_string())
+        }
+        bits
+    }
+    else {
+        0
+    };
+    let cache_size = if cache_bits > 0 {1 << cache_bits} else {0};
+
+    let mut meta = None;
+    let mut num_groups = 1;
+    if is_main && reader.read(1) == 1 {
+        let bits = reader.read(3) + 2;
+        let meta_width = subsample_size(coded_width, bits);
+        let image = decode_image_stream(reader, meta_width, subsample_size(height, bits), false)?;
+        let groups: Vec<usize> = image.iter().map( | p | ((p >> 8) & 0xffff) as usize).collect();
+        num_groups = groups.iter().max().map_or(1, | max | max + 1);
+        meta = Some((bits, meta_width, groups));
+    }
+    let mut groups = Vec::with_capacity(num_groups);
+    for _ in 0..num_groups {
+        groups.push(read_huffman_group(reader, cache_size)?);
+    }
+
+    let mut pixels = decode_pixels(reader, coded_width, height, &groups, &meta, cache_bits)?;
+    for transform in transforms.iter().rev() {
+        pixels = inverse_transform(transform, pixels, height);
+    }
+    Ok(pixels)
+}
+
+fn decode_pixels(
+    reader: &mut BitReader,
+    width: usize,
+    height: usize,
+    groups: &[HuffmanGroup],
+    meta: &Option<(u32, usize, Vec<usize>)>,
+    cache_bits: u32
+) -> Result<Vec<u32>, String> {
+    let total = width * height;
+    let mut pixels = vec![0u32; total];
+    let mut cache = vec![0u32; if cache_bits > 0 {1 << cache_bits} else {0}];
+    let mut cached = 0;
+    let mut pos = 0;
+    while pos < total {
+        let group = match meta {
+            Some((bits, meta_width, map)) => {
+                let (x, y) = (pos % width, pos / width);
+                &groups[map[(y >> bits) * meta_width + (x >> bits)]]
+            }
+            None => &groups[0]
+        };
+        let green = group[0].read(reader) as usize;
+        if green < NUM_LITERALS {
+            let red = group[1].read(reader) as u32;
+            let blue = group[2].read(reader) as u32;
+            let alpha = group[3].read(reader) as u32;
+            pixels[pos] = alpha << 24 | red << 16 | (green as u32) << 8 | blue;
+            pos += 1;
+        }
+        else if green < NUM_LITERALS + NUM_LENGTH_CODES {
+            let length = prefix_value(reader, green - NUM_LITERALS);
+            let dist_symbol = group[4].read(reader) as usize;
+            let dist = plane_distance(width, prefix_value(reader, dist_symbol));
+            if dist > pos || length > total - pos {
+                return Err("WebP back reference out of range".to_string())
+            }
+            for i in pos..pos + length {
+                pixels[i] = pixels[i - dist];
+            }
+            pos += length;
+        }
+        else {
+            let index = green - NUM_LITERALS - NUM_LENGTH_CODES;
+            if index >= cache.len() {
+                return Err("WebP color cache index out of range".to_string())
+            }
+            // every pixel so far enters the cache in order before it is read
+            while cached < pos {
+                let color = pixels[cached];
+                cache[(0x1e35a7bdu32.wrapping_mul(color) >> (32 - cache_bits)) as usize] = color;
+                cached += 1;
+            }
+            pixels[pos] = cache[index];
+            pos += 1;
+        }
+        if reader.is_past_end() {
+            return Err(truncated())
+        }
+    }
+    Ok(pixels)
+}
+
+fn prefix_value(reader: &mut BitReader, prefix: usize) -> usize {
+    if prefix < 4 {
+        return prefix + 1
+    }
+    let extra_bits = (prefix - 2) >> 1;
+    let offset = (2 + (prefix & 1)) << extra_bits;
+    offset + reader.read(extra_bits as u32) as usize + 1
+}
+
+fn plane_distance(width: usize, code: usize) -> usize {
+    if code > CODE_TO_PLANE.len() {
+        return code - CODE_TO_PLANE.len()
+    }
+    let plane = CODE_TO_PLANE[code - 1] as isize;
+    let dist = (plane >> 4) * width as isize + 8 - (plane & 0xf);
+    dist.max(1) as usize
+}
+
+fn add_pixels(a: u32, b: u32) -> u32 {
+    let alpha_green = (a & 0xff00ff00).wrapping_add(b & 0xff00ff00) & 0xff00ff00;
+    let red_blue = (a & 0x00ff00ff).wrapping_add(b & 0x00ff00ff) & 0x00ff00ff;
+    alpha_green | red_blue
+}
+
+fn channels(p: u32) -> [i32; 4] {
+    [(p >> 24) as i32, ((p >> 16) & 0xff) as i32, ((p >> 8) & 0xff) as i32, (p & 0xff) as i32]
+}
+
+fn from_channels(c: [i32; 4]) -> u32 {
+    (c[0] as u32) << 24 | (c[1] as u32) << 16 | (c[2] as u32) << 8 | c[3] as u32
+}
+
+fn average2(a: u32, b: u32) -> u32 {
+    (((a ^ b) & 0xfefefefe) >> 1) + (a & b)
+}
+
+fn select(left: u32, top: u32, top_left: u32) -> u32 {
+    let (l, t, tl) = (channels(left), channels(top), channels(top_left));
+    let mut to_left = 0;
+    let mut to_top = 0;
+    for i in 0..4 {
+        to_left += (t[i] - tl[i]).abs();
+        to_top += (l[i] - tl[i]).abs();
+    }
+    if to_left < to_top {left} else {top}
+}
+
+fn clamp_add_subtract_full(a: u32, b: u32, c: u32) -> u32 {
+    let (a, b, c) = (channels(a), channels(b), channels(c));
+    from_channels([0, 1, 2, 3].map( | i | (a[i] + b[i] - c[i]).clamp(0, 255)))
+}
+
+fn clamp_add_subtract_half(a: u32, b: u32) -> u32 {
+    let (a, b) = (channels(a), channels(b));
+    from_channels([0, 1, 2, 3].map( | i | (a[i] + (a[i] - b[i]) / 2).clamp(0, 255)))
+}
+
+fn predict(mode: u32, left: u32, top: u32, top_left: u32, top_right: u32) -> u32 {
+    match mode {
+        1 => left,
+        2 => top,
+        3 => top_right,
+        4 => top_left,
+        5 => average2(average2(left, top_right), top),
+        6 => average2(left, top_left),
+        7 => average2(left, top),
+        8 => average2(top_left, top),
+        9 => average2(top, top_right),
+        10 => average2(average2(left, top_left), average2(top, top_right)),
+        11 => select(left, top, top_left),
+        12 => clamp_add_subtract_full(left, top, top_left),
+        13 => clamp_add_subtract_half(average2(left, top), top_left),
+        _ => 0xff000000
+    }
+}
+
+fn inverse_transform(transform: &Transform, mut pixels: Vec<u32>, height: usize) -> Vec<u32> {
+    match transform {
+        Transform::Predictor {bits, width, data} => {
+            let width = *width;
+            let blocks_width = subsample_size(width, *bits);
+            for y in 0..height {
+                for x in 0..width {
+                    let i = y * width + x;
+                    let pred = if y == 0 {
+                        if x == 0 {0xff000000} else {pixels[i - 1]}
+                    }
+                    else if x == 0 {
+                        pixels[i - width]
+                    }
+                    else {
+                        let mode = (data[(y >> bits) * blocks_width + (x >> bits)] >> 8) & 0xf;
+                        // the top right of the last column wraps to the start of this row
+                        predict(mode, pixels[i - 1], pixels[i - width], pixels[i - width - 1], pixels[i - width + 1])
+                    };
+                    pixels[i] = add_pixels(pixels[i], pred);
+                }
+            }
+            pixels
+        }
+        Transform::Color {bits, width, data} => {
+            let width = *width;
+            let blocks_width = subsample_size(width, *bits);
+            fn delta(t: u8, c: u8) -> i32 {
+                (t as i8 as i32 * c as i8 as i32) >> 5
+            }
+            for y in 0..height {
+                for x in 0..width {
+                    let element = data[(y >> bits) * blocks_width + (x >> bits)];
+                    let (green_to_red, green_to_blue, red_to_blue) = (element as u8, (element >> 8) as u8, (element >> 16) as u8);
+                    let p = &mut pixels[y * width + x];
+                    let green = (*p >> 8) as u8;
+                    let red = (((*p >> 16) & 0xff) as i32 + delta(green_to_red, green)) as u8;
+                    let blue = ((*p & 0xff) as i32 + delta(green_to_blue, green) + delta(red_to_blue, red)) as u8;
+                    *p = (*p & 0xff00ff00) | (red as u32) << 16 | blue as u32;
+                }
+            }
+            pixels
+        }
+        Transform::SubtractGreen => {
+            for p in &mut pixels {
+                let green = (*p >> 8) & 0xff;
+                *p = add_pixels(*p, green << 16 | green);
+            }
+            pixels
+        }
+        Transform::ColorIndexing {bits, width, table} => {
+            let width = *width;
+            let packed_width = subsample_size(width, *bits);
+            let bits_per_pixel = 8 >> bits;
+            let mask = (1 << bits_per_pixel) - 1;
+            let mut out = vec![0u32; width * height];
+            for y in 0..height {
+                for x in 0..width {
+                    let packed = (pixels[y * packed_width + (x >> bits)] >> 8) & 0xff;
+                    let index = (packed >> ((x & ((1 << bits) - 1)) as u32 * bits_per_pixel)) & mask;
+                    out[y * width + x] = table.get(index as usize).copied().unwrap_or(0);
+                }
+            }
+            out
+        }
+    }
+}
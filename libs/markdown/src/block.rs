@@ -0,0 +1,609 @@
+use {
+    std::collections::HashMap,
+    crate::{
+        inline::{parse_inline, normalize_label},
+        Block,
+        MarkdownDoc,
+    }
+};
+
+// link reference definitions by normalized label
+pub type LinkRefs = HashMap<String, String>;
+
+pub fn parse_markdown(source: &str) -> MarkdownDoc {
+    let lines: Vec<String> = source.lines().map(expand_tabs).collect();
+    let mut links = Vec::new();
+    let mut refs = LinkRefs::new();
+    let mut blocks = parse_blocks(&lines, &mut links, &mut refs);
+    // a definition can come after the links that use it, so with any definitions
+    // the document is parsed again knowing all of them
+    if !refs.is_empty() {
+        links.clear();
+        blocks = parse_blocks(&lines, &mut links, &mut refs);
+    }
+    MarkdownDoc {blocks, links}
+}
+
+fn expand_tabs(line: &str) -> String {
+    if !line.contains('\t') {
+        return line.to_string()
+    }
+    let mut out = String::new();
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let n = 4 - column % 4;
+            for _ in 0..n {
+                out.push(' ');
+            }
+            column += n;
+        }
+        else {
+            out.push(c);
+            column += 1;
+        }
+    }
+    out
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn atx_heading(t: &str) -> Option<(usize, &str)> {
+    let level = t.len() - t.trim_start_matches('#').len();
+    if level == 0 || level > 6 {
+        return None
+    }
+    let rest = &t[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None
+    }
+    let text = rest.trim();
+    // the optional closing #'s have to be separated by a space
+    let stripped = text.trim_end_matches('#');
+    if stripped.is_empty() {
+        return Some((level, ""))
+    }
+    if stripped.ends_with(' ') {
+        return Some((level, stripped.trim_end()))
+    }
+    Some((level, text))
+}
+
+fn is_thematic_break(t: &str) -> bool {
+    let mut marker = None;
+    let mut count = 0;
+    for c in t.chars() {
+        match c {
+            ' ' => (),
+            '*' | '-' | '_' => {
+                if marker.is_some() && marker != Some(c) {
+                    return false
+                }
+                marker = Some(c);
+                count += 1;
+            }
+            _ => return false
+        }
+    }
+    count >= 3
+}
+
+fn setext_level(line: &str) -> Option<usize> {
+    if indent_of(line) >= 4 {
+        return None
+    }
+    let t = line.trim();
+    if t.is_empty() {
+        return None
+    }
+    if t.chars().all( | c | c == '=') {
+        return Some(1)
+    }
+    if t.chars().all( | c | c == '-') {
+        return Some(2)
+    }
+    None
+}
+
+struct Fence {
+    ch: char,
+    len: usize,
+    indent: usize,
+    info: String,
+}
+
+fn fence_open(line: &str) -> Option<Fence> {
+    let indent = indent_of(line);
+    if indent >= 4 {
+        return None
+    }
+    let t = &line[indent..];
+    let ch = t.chars().next()?;
+    if ch != '`' && ch != '~' {
+        return None
+    }
+    let len = t.len() - t.trim_start_matches(ch).len();
+    if len < 3 {
+        return None
+    }
+    let info = t[len..].trim();
+    if ch == '`' && info.contains('`') {
+        return None
+    }
+    Some(Fence {
+        ch,
+        len,
+        indent,
+        info: info.split_whitespace().next().unwrap_or("").to_string()
+    })
+}
+
+fn is_fence_close(line: &str, fence: &Fence) -> bool {
+    let indent = indent_of(line);
+    if indent >= 4 {
+        return false
+    }
+    let t = line[indent..].trim_end();
+    let len = t.len() - t.trim_start_matches(fence.ch).len();
+    len >= fence.len && len == t.len()
+}
+
+#[derive(Clone, Copy)]
+struct ListMarker {
+    // the number an ordered item starts with
+    ordered: Option<u64>,
+    // the bullet, or the '.' or ')' after the number
+    ch: char,
+    // where the content of the item starts
+    content: usize,
+    empty: bool,
+}
+
+fn list_marker(line: &str) -> Option<ListMarker> {
+    let indent = indent_of(line);
+    if indent >= 4 {
+        return None
+    }
+    let t = &line[indent..];
+    let (ordered, ch, marker_len) = match t.chars().next() ? {
+        c @ ('-' | '+' | '*') => (None, c, 1),
+        c if c.is_ascii_digit() => {
+            let digits = t.len() - t.trim_start_matches( | c: char | c.is_ascii_digit()).len();
+            if digits > 9 {
+                return None
+            }
+            let delim = t[digits..].chars().next() ?;
+            if delim != '.' && delim != ')' {
+                return None
+            }
+            (Some(t[..digits].parse().ok() ?), delim, digits + 1)
+        }
+        _ => return None
+    };
+    let rest = &t[marker_len..];
+    if rest.trim().is_empty() {
+        return Some(ListMarker {ordered, ch, content: indent + marker_len + 1, empty: true})
+    }
+    let spaces = indent_of(rest);
+    if spaces == 0 {
+        return None
+    }
+    // with five or more spaces the content is an indented code block one space in
+    let spaces = if spaces > 4 {1} else {spaces};
+    Some(ListMarker {ordered, ch, content: indent + marker_len + spaces, empty: false})
+}
+
+// `[label]: destination "title"` on one line, the title isnt kept
+fn link_definition(line: &str) -> Option<(String, String)> {
+    let t = line.trim();
+    let rest = t.strip_prefix('[')?;
+    let mut label_end = None;
+    let mut escaped = false;
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => return None,
+            ']' => {
+                label_end = Some(i);
+                break
+            }
+            _ => ()
+        }
+    }
+    let label_end = label_end?;
+    let label = normalize_label(&rest[..label_end]);
+    if label.is_empty() || label_end > 999 {
+        return None
+    }
+    let rest = rest[label_end + 1..].strip_prefix(':')?.trim_start();
+    let (url, rest) = if let Some(rest) = rest.strip_prefix('<') {
+        let end = rest.find('>')?;
+        (&rest[..end], &rest[end + 1..])
+    }
+    else {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            return None
+        }
+        (&rest[..end], &rest[end..])
+    };
+    let title = rest.trim();
+    if !title.is_empty() {
+        // only a title may follow, and it has to be separated from the destination
+        if !rest.starts_with(char::is_whitespace) {
+            return None
+        }
+        let closed = match title.chars().next() {
+            Some('"') => title.len() >= 2 && title.ends_with('"'),
+            Some('\'') => title.len() >= 2 && title.ends_with('\''),
+            Some('(') => title.ends_with(')'),
+            _ => false
+        };
+        if !closed {
+            return None
+        }
+    }
+    Some((label, url.to_string()))
+}
+
+fn interrupts_paragraph(line: &str) -> bool {
+    if indent_of(line) >= 4 {
+        return false
+    }
+    let t = line.trim_start();
+    atx_heading(t).is_some()
+        || fence_open(line).is_some()
+        || is_thematic_break(t)
+        || t.starts_with('>')
+        // only lists that start with something, and ordered ones only at 1
+        || list_marker(line).map_or(false, | m | !m.empty && m.ordered.map_or(true, | n | n == 1))
+}
+
+// whether a line can be the text of a paragraph, so the next one may continue it lazily
+fn is_paragraph_text(line: &str) -> bool {
+    let t = line.trim_start();
+    !t.is_empty()
+        && atx_heading(t).is_none()
+        && !is_thematic_break(t)
+        && fence_open(line).is_none()
+        && setext_level(line).is_none()
+}
+
+fn parse_blocks(lines: &[String], links: &mut Vec<String>, refs: &mut LinkRefs) -> Vec<Block> {
+    parse_blocks_spaced(lines, links, refs).0
+}
+
+// also returns whether a blank line separates any two of the blocks, which makes a list loose
+fn parse_blocks_spaced(lines: &[String], links: &mut Vec<String>, refs: &mut LinkRefs) -> (Vec<Block>, bool) {
+    let mut blocks = Vec::new();
+    let mut blank = false;
+    let mut spaced = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = &lines[i];
+        if is_blank(line) {
+            blank = true;
+            i += 1;
+            continue
+        }
+        if blank && !blocks.is_empty() {
+            spaced = true;
+        }
+        blank = false;
+        let indent = indent_of(line);
+        if indent >= 4 {
+            let mut code = Vec::new();
+            while i < lines.len() && (is_blank(&lines[i]) || indent_of(&lines[i]) >= 4) {
+                code.push(lines[i].get(4..).unwrap_or(""));
+                i += 1;
+            }
+            // trailing blank lines come after the block
+            while code.last().map_or(false, | l | l.trim().is_empty()) {
+                code.pop();
+                i -= 1;
+            }
+            blocks.push(Block::CodeBlock {info: String::new(), code: code.join("\n")});
+            continue
+        }
+        if let Some(fence) = fence_open(line) {
+            i += 1;
+            let mut code = Vec::new();
+            while i < lines.len() && !is_fence_close(&lines[i], &fence) {
+                let l = &lines[i];
+                code.push(&l[indent_of(l).min(fence.indent)..]);
+                i += 1;
+            }
+            // skip the closing fence, an unclosed one runs to the end
+            i += 1;
+            blocks.push(Block::CodeBlock {info: fence.info, code: code.join("\n")});
+            continue
+        }
+        let t = &line[indent..];
+        if let Some((level, text)) = atx_heading(t) {
+            blocks.push(Block::Heading {level, spans: parse_inline(text, refs, links)});
+            i += 1;
+            continue
+        }
+        if is_thematic_break(t) {
+            blocks.push(Block::Rule);
+            i += 1;
+            continue
+        }
+        if t.starts_with('>') {
+            let mut quote: Vec<String> = Vec::new();
+            let mut fence: Option<Fence> = None;
+            while i < lines.len() {
+                let l = &lines[i];
+                let li = indent_of(l);
+                if li < 4 && l[li..].starts_with('>') {
+                    let rest = &l[li + 1..];
+                    let rest = rest.strip_prefix(' ').unwrap_or(rest);
+                    fence = match fence {
+                        Some(f) if is_fence_close(rest, &f) => None,
+                        Some(f) => Some(f),
+                        None => fence_open(rest)
+                    };
+                    quote.push(rest.to_string());
+                }
+                // a paragraph in a quote can continue on lines without the marker
+                else if !is_blank(l) && fence.is_none() && quote.last().map_or(false, | q | is_paragraph_text(q)) && !interrupts_paragraph(l) {
+                    quote.push(l.to_string());
+                }
+                else {
+                    break
+                }
+                i += 1;
+            }
+            blocks.push(Block::Quote(parse_blocks(&quote, links, refs)));
+            continue
+        }
+        if let Some(marker) = list_marker(line) {
+            let (list, next) = parse_list(lines, i, marker, links, refs);
+            blocks.push(list);
+            i = next;
+            continue
+        }
+        
+        let mut para = vec![line.trim_start()];
+        let mut heading = None;
+        i += 1;
+        while i < lines.len() {
+            let l = &lines[i];
+            if is_blank(l) {
+                break
+            }
+            if let Some(level) = setext_level(l) {
+                heading = Some(level);
+                i += 1;
+                break
+            }
+            if interrupts_paragraph(l) {
+                break
+            }
+            para.push(l.trim_start());
+            i += 1;
+        }
+        // definitions at the start of a paragraph arent part of its text
+        let definitions = para.iter().take_while( | l | link_definition(l).is_some()).count();
+        for l in &para[..definitions] {
+            let (label, url) = link_definition(l).unwrap();
+            // the first definition of a label wins
+            refs.entry(label).or_insert(url);
+        }
+        let para = &para[definitions..];
+        if para.is_empty() {
+            continue
+        }
+        let text = para.join("\n");
+        let spans = parse_inline(text.trim_end(), refs, links);
+        blocks.push(match heading {
+            Some(level) => Block::Heading {level, spans},
+            None => Block::Paragraph(spans)
+        });
+    }
+    (blocks, spaced)
+}
+
+fn parse_list(lines: &[String], mut i: usize, first: ListMarker, links: &mut Vec<String>, refs: &mut LinkRefs) -> (Block, usize) {
+    let mut items = Vec::new();
+    let mut tight = true;
+    let mut marker = first;
+    loop {
+        let mut item = vec![lines[i].get(marker.content..).unwrap_or("").to_string()];
+        i += 1;
+        while i < lines.len() {
+            let l = &lines[i];
+            if is_blank(l) {
+                item.push(String::new());
+                i += 1;
+                continue
+            }
+            if indent_of(l) >= marker.content {
+                item.push(l[marker.content..].to_string());
+                i += 1;
+                continue
+            }
+            let after_blank = item.last().map_or(false, | l | l.is_empty());
+            if after_blank || list_marker(l).is_some() || interrupts_paragraph(l) {
+                break
+            }
+            // lazy continuation of the last paragraph
+            item.push(l.trim_start().to_string());
+            i += 1;
+        }
+        let mut trailing_blank = 0;
+        while item.last().map_or(false, | l | is_blank(l)) {
+            item.pop();
+            trailing_blank += 1;
+        }
+        let (blocks, spaced) = parse_blocks_spaced(&item, links, refs);
+        // blank lines between the blocks of an item make the list loose, ones inside
+        // a nested list or a code block dont
+        if spaced {
+            tight = false;
+        }
+        items.push(blocks);
+        
+        if i < lines.len() && !is_thematic_break(lines[i].trim()) {
+            if let Some(next) = list_marker(&lines[i]) {
+                if next.ordered.is_some() == first.ordered.is_some() && next.ch == first.ch {
+                    if trailing_blank > 0 {
+                        tight = false;
+                    }
+                    marker = next;
+                    continue
+                }
+            }
+        }
+        // blank lines after the list are left to whoever holds it
+        i -= trailing_blank;
+        break
+    }
+    (Block::List {start: first.ordered, tight, items}, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::Span,
+    };
+    
+    fn text(spans: &[Span], links: &[String]) -> String {
+        spans.iter().map( | span | match span.style.link {
+            Some(link) => format!("[{}]({})", span.text, links[link]),
+            None => span.text.clone()
+        }).collect()
+    }
+    
+    fn outline_blocks(blocks: &[Block], links: &[String]) -> String {
+        blocks.iter().map( | block | match block {
+            Block::Heading {level, spans} => format!("h{}({})", level, text(spans, links)),
+            Block::Paragraph(spans) => format!("p({})", text(spans, links)),
+            Block::CodeBlock {info, code} => format!("code[{}]({})", info, code),
+            Block::List {start, tight, items} => format!(
+                "{}{}[{}]",
+                start.map_or("ul".to_string(), | n | format!("ol{}", n)),
+                if *tight {""} else {" loose"},
+                items.iter().map( | item | outline_blocks(item, links)).collect::<Vec<_ >> ().join(", ")
+            ),
+            Block::Quote(blocks) => format!("quote[{}]", outline_blocks(blocks, links)),
+            Block::Rule => "hr".to_string(),
+        }).collect::<Vec<_ >> ().join(" ")
+    }
+    
+    // a compact rendering of the block tree, `ul[p(a), p(b)]` is a tight list of two items
+    fn outline(source: &str) -> String {
+        let doc = parse_markdown(source);
+        outline_blocks(&doc.blocks, &doc.links)
+    }
+    
+    #[test]
+    fn headings_and_rules() {
+        assert_eq!(outline("# a\n## b ##\nc\n===\nd\n---\n***"), "h1(a) h2(b) h1(c) h2(d) hr");
+        assert_eq!(outline("#a\n####### b"), "p(#a ####### b)");
+        assert_eq!(outline("- - -\n_ _ _"), "hr hr");
+    }
+    
+    #[test]
+    fn fenced_and_indented_code() {
+        assert_eq!(outline("```rust extra\nfn a() {}\n\n```\nb"), "code[rust](fn a() {}\n) p(b)");
+        assert_eq!(outline("~~~\n```\n~~~"), "code[](```)");
+        // a shorter run doesnt close the fence
+        assert_eq!(outline("````\na\n```\nb\n````"), "code[](a\n```\nb)");
+        assert_eq!(outline("```\nunclosed\n\nstill code"), "code[](unclosed\n\nstill code)");
+        // the fence's own indent is taken off its lines
+        assert_eq!(outline(" ```\n  a\nb\n ```"), "code[]( a\nb)");
+        assert_eq!(outline("    a\n\n      b\n\nc"), "code[](a\n\n  b) p(c)");
+        // indented code cant interrupt a paragraph
+        assert_eq!(outline("a\n    b"), "p(a b)");
+        assert_eq!(outline("a\n```\nb\n```"), "p(a) code[](b)");
+        // markdown inside code stays as it is
+        assert_eq!(outline("```\n# *a*\n```"), "code[](# *a*)");
+    }
+    
+    #[test]
+    fn tight_and_loose_lists() {
+        assert_eq!(outline("- a\n- b"), "ul[p(a), p(b)]");
+        assert_eq!(outline("- a\n\n- b"), "ul loose[p(a), p(b)]");
+        assert_eq!(outline("- a\n\n  b\n- c"), "ul loose[p(a) p(b), p(c)]");
+        assert_eq!(outline("- a\n  - b\n- c"), "ul[p(a) ul[p(b)], p(c)]");
+        // a blank line inside a nested list doesnt loosen the outer one
+        assert_eq!(outline("- a\n  - b\n\n  - c\n- d"), "ul[p(a) ul loose[p(b), p(c)], p(d)]");
+        // another bullet starts another list
+        assert_eq!(outline("- a\n* b"), "ul[p(a)] ul[p(b)]");
+        assert_eq!(outline("- a\nb"), "ul[p(a b)]");
+        assert_eq!(outline("-a"), "p(-a)");
+        assert_eq!(outline("- a\n\n\nb"), "ul[p(a)] p(b)");
+        assert_eq!(outline("- a\n  - b\n\n  c"), "ul loose[p(a) ul[p(b)] p(c)]");
+        assert_eq!(outline("- ```\n  a\n\n  b\n  ```"), "ul[code[](a\n\nb)]");
+    }
+    
+    #[test]
+    fn ordered_starts() {
+        assert_eq!(outline("1. a\n2. b"), "ol1[p(a), p(b)]");
+        assert_eq!(outline("3. a\n7. b"), "ol3[p(a), p(b)]");
+        assert_eq!(outline("0) a"), "ol0[p(a)]");
+        // '.' and ')' are different lists
+        assert_eq!(outline("1) a\n2. b"), "ol1[p(a)] ol2[p(b)]");
+        // only a list starting at 1 interrupts a paragraph
+        assert_eq!(outline("a\n2. b"), "p(a 2. b)");
+        assert_eq!(outline("a\n1. b"), "p(a) ol1[p(b)]");
+        assert_eq!(outline("1234567890. a"), "p(1234567890. a)");
+    }
+    
+    #[test]
+    fn block_quotes() {
+        assert_eq!(outline("> a\n> b"), "quote[p(a b)]");
+        assert_eq!(outline("> a\n>\n> b"), "quote[p(a) p(b)]");
+        assert_eq!(outline("> # h\n> - a\n> - b"), "quote[h1(h) ul[p(a), p(b)]]");
+        assert_eq!(outline("> > a\n> b"), "quote[quote[p(a b)]]");
+        assert_eq!(outline("> a\n\n> b"), "quote[p(a)] quote[p(b)]");
+    }
+    
+    #[test]
+    fn lazy_continuation() {
+        assert_eq!(outline("> a\nb\nc"), "quote[p(a b c)]");
+        assert_eq!(outline("> > a\nb"), "quote[quote[p(a b)]]");
+        assert_eq!(outline("> a\n\nb"), "quote[p(a)] p(b)");
+        // only paragraph text continues lazily
+        assert_eq!(outline("> a\n---"), "quote[p(a)] hr");
+        assert_eq!(outline("> # h\nb"), "quote[h1(h)] p(b)");
+        assert_eq!(outline("> ```\n> a\nb"), "quote[code[](a)] p(b)");
+        assert_eq!(outline("> a\n- b"), "quote[p(a)] ul[p(b)]");
+    }
+    
+    #[test]
+    fn reference_links() {
+        let doc = parse_markdown("[a][x], [x][], [x] and [y]\n\n[X]: http://u\n[y]: <v w> \"title\"");
+        assert_eq!(outline_blocks(&doc.blocks, &doc.links), "p([a](http://u), [x](http://u), [x](http://u) and [y](v w))");
+        assert_eq!(doc.links.len(), 4);
+        // labels match ignoring case and runs of whitespace
+        assert_eq!(outline("[Foo  Bar]\n\n[foo bar]: u"), "p([Foo  Bar](u))");
+        assert_eq!(outline("[x]\n\n[x]: a\n[x]: b"), "p([x](a))");
+        assert_eq!(outline("[*x*][]\n\n[*x*]: u 'title'"), "p([x](u))");
+        assert_eq!(outline("[a][nope] [nope]\n\n[x]: u"), "p([a][nope] [nope])");
+        // an inline destination goes before a reference
+        assert_eq!(outline("[x](v)\n\n[x]: u"), "p([x](v))");
+    }
+    
+    #[test]
+    fn reference_definitions() {
+        assert_eq!(outline("[x]: u"), "");
+        assert_eq!(outline("[x]: u\ntext [x]"), "p(text [x](u))");
+        // a definition cant interrupt a paragraph
+        assert_eq!(outline("a\n[x]: u\n\n[x]"), "p(a [x]: u) p([x])");
+        assert_eq!(outline("[x]: u junk\n\n[x]"), "p([x]: u junk) p([x])");
+        assert_eq!(outline("[]: u\n\n[]"), "p([]: u) p([])");
+        assert_eq!(outline("    [x]: u\n\n[x]"), "code[]([x]: u) p([x])");
+        // definitions in other blocks count for the whole document
+        assert_eq!(outline("> [x]: u\n\n[x]"), "quote[] p([x](u))");
+    }
+}
@@ -0,0 +1,652 @@
+use crate::{
+    block::LinkRefs,
+    Span,
+    SpanStyle,
+};
+
+enum Node {
+    Text(String),
+    Code(String),
+    Break,
+    Emphasis(Vec<Node>),
+    Strong(Vec<Node>),
+    Strike(Vec<Node>),
+    Link(String, Vec<Node>),
+    Delim(Delim),
+}
+
+// a run of *, _ or ~ that may open or close emphasis, resolved after the whole paragraph is scanned
+struct Delim {
+    ch: char,
+    count: usize,
+    orig: usize,
+    can_open: bool,
+    can_close: bool,
+}
+
+struct Bracket {
+    node: usize,
+    // the char after the opening bracket, for using the text as a reference label
+    start: usize,
+    image: bool,
+}
+
+pub fn parse_inline(text: &str, refs: &LinkRefs, links: &mut Vec<String>) -> Vec<Span> {
+    let nodes = process_emphasis(InlineParser::new(text, refs).parse());
+    let mut spans = Vec::new();
+    flatten(nodes, SpanStyle::default(), links, &mut spans);
+    spans
+}
+
+fn is_punct(c: char) -> bool {
+    c.is_ascii_punctuation() || (!c.is_alphanumeric() && !c.is_whitespace() && !c.is_control())
+}
+
+// labels match case insensitively and with any run of whitespace as one space
+pub fn normalize_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<_ >> ().join(" ").to_lowercase()
+}
+
+struct InlineParser<'a> {
+    refs: &'a LinkRefs,
+    chars: Vec<char>,
+    pos: usize,
+    text: String,
+    nodes: Vec<Node>,
+    brackets: Vec<Bracket>,
+}
+
+impl<'a> InlineParser<'a> {
+    fn new(text: &str, refs: &'a LinkRefs) -> Self {
+        Self {
+            refs,
+            chars: text.chars().collect(),
+            pos: 0,
+            text: String::new(),
+            nodes: Vec::new(),
+            brackets: Vec::new(),
+        }
+    }
+    
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).cloned()
+    }
+    
+    fn run_len(&self, at: usize, ch: char) -> usize {
+        self.chars[at..].iter().take_while( | c | **c == ch).count()
+    }
+    
+    fn flush(&mut self) {
+        if !self.text.is_empty() {
+            self.nodes.push(Node::Text(std::mem::take(&mut self.text)));
+        }
+    }
+    
+    fn skip_spaces(&mut self) {
+        while self.peek(0) == Some(' ') {
+            self.pos += 1;
+        }
+    }
+    
+    fn parse(mut self) -> Vec<Node> {
+        while let Some(c) = self.peek(0) {
+            match c {
+                '\\' => self.backslash(),
+                '`' => self.code_span(),
+                '*' | '_' | '~' => self.delim_run(c),
+                '!' if self.peek(1) == Some('[') => self.open_bracket(true),
+                '[' => self.open_bracket(false),
+                ']' => self.close_bracket(),
+                '<' if self.autolink() => (),
+                '&' => self.entity(),
+                '\n' => self.line_break(),
+                'h' | 'H' | 'w' | 'W' if self.bare_link() => (),
+                _ => {
+                    self.text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        self.flush();
+        self.nodes
+    }
+    
+    fn backslash(&mut self) {
+        match self.peek(1) {
+            Some('\n') => {
+                self.flush();
+                self.nodes.push(Node::Break);
+                self.pos += 2;
+                self.skip_spaces();
+            }
+            Some(c) if c.is_ascii_punctuation() => {
+                self.text.push(c);
+                self.pos += 2;
+            }
+            _ => {
+                self.text.push('\\');
+                self.pos += 1;
+            }
+        }
+    }
+    
+    fn code_span(&mut self) {
+        let n = self.run_len(self.pos, '`');
+        let start = self.pos + n;
+        let mut i = start;
+        while i < self.chars.len() {
+            if self.chars[i] != '`' {
+                i += 1;
+                continue
+            }
+            let m = self.run_len(i, '`');
+            if m == n {
+                let mut code: String = self.chars[start..i].iter().map( | c | if *c == '\n' {' '} else {*c}).collect();
+                if code.len() >= 2 && code.starts_with(' ') && code.ends_with(' ') && !code.trim().is_empty() {
+                    code = code[1..code.len() - 1].to_string();
+                }
+                self.flush();
+                self.nodes.push(Node::Code(code));
+                self.pos = i + m;
+                return
+            }
+            i += m;
+        }
+        // without a closing run of the same length the backticks are literal
+        for _ in 0..n {
+            self.text.push('`');
+        }
+        self.pos += n;
+    }
+    
+    fn delim_run(&mut self, ch: char) {
+        let n = self.run_len(self.pos, ch);
+        let before = if self.pos == 0 {' '} else {self.chars[self.pos - 1]};
+        let after = self.chars.get(self.pos + n).cloned().unwrap_or(' ');
+        let left = !after.is_whitespace() && (!is_punct(after) || before.is_whitespace() || is_punct(before));
+        let right = !before.is_whitespace() && (!is_punct(before) || after.is_whitespace() || is_punct(after));
+        // underscores inside words dont count, snake_case stays as it is
+        let (can_open, can_close) = if ch == '_' {
+            (left && (!right || is_punct(before)), right && (!left || is_punct(after)))
+        }
+        else {
+            (left, right)
+        };
+        self.flush();
+        self.nodes.push(Node::Delim(Delim {ch, count: n, orig: n, can_open, can_close}));
+        self.pos += n;
+    }
+    
+    fn open_bracket(&mut self, image: bool) {
+        self.flush();
+        self.pos += if image {2} else {1};
+        self.brackets.push(Bracket {node: self.nodes.len(), start: self.pos, image});
+        self.nodes.push(Node::Text(if image {"![".to_string()} else {"[".to_string()}));
+    }
+    
+    fn close_bracket(&mut self) {
+        let bracket = if let Some(bracket) = self.brackets.pop() {bracket} else {
+            self.text.push(']');
+            self.pos += 1;
+            return
+        };
+        let target = self.link_destination(self.pos + 1).or_else( || self.link_reference(bracket.start, self.pos));
+        if let Some((url, end)) = target {
+            self.flush();
+            let children = self.nodes.split_off(bracket.node + 1);
+            self.nodes.pop();
+            self.nodes.push(Node::Link(url, process_emphasis(children)));
+            self.pos = end;
+            // links dont nest, the brackets before this one are plain text now
+            if !bracket.image {
+                self.brackets.clear();
+            }
+        }
+        else {
+            self.text.push(']');
+            self.pos += 1;
+        }
+    }
+    
+    // a full `[text][label]`, collapsed `[label][]` or shortcut `[label]` reference to a
+    // definition, close is the position of the bracket that ends the text
+    fn link_reference(&self, start: usize, close: usize) -> Option<(String, usize)> {
+        let text: String = self.chars[start..close].iter().collect();
+        if self.chars.get(close + 1) == Some(&'[') {
+            let label_end = self.chars[close + 2..].iter().position( | c | *c == ']' || *c == '[')? + close + 2;
+            if self.chars[label_end] != ']' {
+                return None
+            }
+            let label: String = self.chars[close + 2..label_end].iter().collect();
+            let label = if label.trim().is_empty() {&text} else {&label};
+            return self.refs.get(&normalize_label(label)).map( | url | (url.clone(), label_end + 1))
+        }
+        self.refs.get(&normalize_label(&text)).map( | url | (url.clone(), close + 1))
+    }
+    
+    fn skip_whitespace_at(&self, mut i: usize) -> usize {
+        while self.chars.get(i).map_or(false, | c | c.is_whitespace()) {
+            i += 1;
+        }
+        i
+    }
+    
+    // parses `(url "title")` and returns the url and the position after it
+    fn link_destination(&self, at: usize) -> Option<(String, usize)> {
+        let c = &self.chars;
+        if c.get(at) != Some(&'(') {
+            return None
+        }
+        let mut i = self.skip_whitespace_at(at + 1);
+        let mut url = String::new();
+        if c.get(i) == Some(&'<') {
+            i += 1;
+            loop {
+                match c.get(i) {
+                    Some('>') => {
+                        i += 1;
+                        break
+                    }
+                    Some('\n') | Some('<') | None => return None,
+                    Some(ch) => {
+                        url.push(*ch);
+                        i += 1;
+                    }
+                }
+            }
+        }
+        else {
+            let mut depth = 0;
+            while let Some(&ch) = c.get(i) {
+                if ch.is_whitespace() || ch.is_control() {
+                    break
+                }
+                if ch == '\\' && c.get(i + 1).map_or(false, | n | n.is_ascii_punctuation()) {
+                    url.push(c[i + 1]);
+                    i += 2;
+                    continue
+                }
+                if ch == '(' {
+                    depth += 1;
+                }
+                if ch == ')' {
+                    if depth == 0 {
+                        break
+                    }
+                    depth -= 1;
+                }
+                url.push(ch);
+                i += 1;
+            }
+            if depth != 0 {
+                return None
+            }
+        }
+        i = self.skip_whitespace_at(i);
+        // the title isnt shown but it has to be skipped
+        if let Some(&quote) = c.get(i) {
+            if quote == '"' || quote == '\'' || quote == '(' {
+                let close = if quote == '(' {')'} else {quote};
+                i += 1;
+                while let Some(&ch) = c.get(i) {
+                    if ch == close {
+                        break
+                    }
+                    if ch == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                if c.get(i) != Some(&close) {
+                    return None
+                }
+                i = self.skip_whitespace_at(i + 1);
+            }
+        }
+        if c.get(i) != Some(&')') {
+            return None
+        }
+        Some((url, i + 1))
+    }
+    
+    fn autolink(&mut self) -> bool {
+        let rest = &self.chars[self.pos + 1..];
+        let end = if let Some(end) = rest.iter().position( | c | *c == '>') {end} else {
+            return false
+        };
+        let inner: String = rest[..end].iter().collect();
+        if inner.is_empty() || inner.chars().any( | c | c.is_whitespace() || c == '<') {
+            return false
+        }
+        let url = if is_uri(&inner) {
+            inner.clone()
+        }
+        else if is_email(&inner) {
+            format!("mailto:{}", inner)
+        }
+        else {
+            return false
+        };
+        self.flush();
+        self.nodes.push(Node::Link(url, vec![Node::Text(inner)]));
+        self.pos += end + 2;
+        true
+    }
+    
+    // the GFM extension that turns plain http(s):// and www. text into links
+    fn bare_link(&mut self) -> bool {
+        if !self.brackets.iter().all( | b | b.image) {
+            return false
+        }
+        let before = if self.pos == 0 {' '} else {self.chars[self.pos - 1]};
+        if !before.is_whitespace() && !matches!(before, '(' | '*' | '_' | '~') {
+            return false
+        }
+        let head: String = self.chars[self.pos..(self.pos + 8).min(self.chars.len())].iter().collect::<String>().to_ascii_lowercase();
+        let prefix = if let Some(prefix) = ["https://", "http://", "www."].iter().find( | p | head.starts_with(*p)) {prefix} else {
+            return false
+        };
+        let mut end = self.pos;
+        while end < self.chars.len() && !self.chars[end].is_whitespace() && self.chars[end] != '<' {
+            end += 1;
+        }
+        // trailing punctuation belongs to the sentence, a closing paren only when it isnt balanced
+        while end > self.pos {
+            let c = self.chars[end - 1];
+            if matches!(c, '?' | '!' | '.' | ',' | ':' | ';' | '*' | '_' | '~' | '\'' | '"') {
+                end -= 1;
+                continue
+            }
+            if c == ')' {
+                let open = self.chars[self.pos..end].iter().filter( | c | **c == '(').count();
+                let close = self.chars[self.pos..end].iter().filter( | c | **c == ')').count();
+                if close > open {
+                    end -= 1;
+                    continue
+                }
+            }
+            break
+        }
+        if end - self.pos <= prefix.len() {
+            return false
+        }
+        let text: String = self.chars[self.pos..end].iter().collect();
+        let url = if *prefix == "www." {format!("http://{}", text)} else {text.clone()};
+        self.flush();
+        self.nodes.push(Node::Link(url, vec![Node::Text(text)]));
+        self.pos = end;
+        true
+    }
+    
+    fn entity(&mut self) {
+        let rest: String = self.chars[self.pos..(self.pos + 12).min(self.chars.len())].iter().collect();
+        if let Some(end) = rest.find(';') {
+            let name = &rest[1..end];
+            let decoded = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                "copy" => Some('\u{a9}'),
+                _ if name.starts_with("#x") || name.starts_with("#X") => u32::from_str_radix(&name[2..], 16).ok().and_then(char::from_u32),
+                _ if name.starts_with('#') => name[1..].parse().ok().and_then(char::from_u32),
+                _ => None
+            };
+            if let Some(c) = decoded {
+                self.text.push(c);
+                self.pos += rest[..=end].chars().count();
+                return
+            }
+        }
+        self.text.push('&');
+        self.pos += 1;
+    }
+    
+    fn line_break(&mut self) {
+        // two or more spaces at the end of a line make a hard break, otherwise its a space
+        let trailing = self.text.len() - self.text.trim_end_matches(' ').len();
+        self.text.truncate(self.text.len() - trailing);
+        if trailing >= 2 {
+            self.flush();
+            self.nodes.push(Node::Break);
+        }
+        else {
+            self.text.push(' ');
+        }
+        self.pos += 1;
+        self.skip_spaces();
+    }
+}
+
+fn is_uri(s: &str) -> bool {
+    let scheme_len = if let Some(colon) = s.find(':') {colon} else {
+        return false
+    };
+    let scheme = &s[..scheme_len];
+    scheme.len() >= 2 && scheme.len() <= 32
+        && scheme.starts_with( | c: char | c.is_ascii_alphabetic())
+        && scheme.chars().all( | c | c.is_ascii_alphanumeric() || c == '+' || c == '.' || c == '-')
+}
+
+fn is_email(s: &str) -> bool {
+    let mut parts = s.split('@');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(user), Some(domain), None) => !user.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.'),
+        _ => false
+    }
+}
+
+fn delims_to_text(nodes: Vec<Node>) -> Vec<Node> {
+    nodes.into_iter().map( | node | match node {
+        Node::Delim(d) => Node::Text(std::iter::repeat(d.ch).take(d.count).collect()),
+        node => node
+    }).collect()
+}
+
+fn delim_at(nodes: &[Node], i: usize) -> Option<&Delim> {
+    if let Some(Node::Delim(d)) = nodes.get(i) {Some(d)} else {None}
+}
+
+fn delim_at_mut(nodes: &mut [Node], i: usize) -> &mut Delim {
+    if let Node::Delim(d) = &mut nodes[i] {d} else {unreachable!()}
+}
+
+// pairs up the delimiter runs the way CommonMark does: every closer looks back for the
+// nearest opener of the same kind and the nodes in between get wrapped
+fn process_emphasis(mut nodes: Vec<Node>) -> Vec<Node> {
+    let mut i = 0;
+    while i < nodes.len() {
+        let (ch, closer_count, closer_orig, closer_open) = match delim_at(&nodes, i) {
+            Some(d) if d.can_close => (d.ch, d.count, d.orig, d.can_open),
+            _ => {
+                i += 1;
+                continue
+            }
+        };
+        let mut opener = None;
+        for j in (0..i).rev() {
+            if let Some(o) = delim_at(&nodes, j) {
+                if o.ch != ch || !o.can_open {
+                    continue
+                }
+                if ch == '~' {
+                    if o.count >= 2 && closer_count >= 2 {
+                        opener = Some(j);
+                        break
+                    }
+                    continue
+                }
+                // the rule of three, so *foo**bar* doesnt pair the middle run
+                if (o.can_close || closer_open) && (o.orig + closer_orig) % 3 == 0 && !(o.orig % 3 == 0 && closer_orig % 3 == 0) {
+                    continue
+                }
+                opener = Some(j);
+                break
+            }
+        }
+        let j = if let Some(j) = opener {j} else {
+            i += 1;
+            continue
+        };
+        let opener_count = delim_at(&nodes, j).unwrap().count;
+        let used = if ch == '~' || (opener_count >= 2 && closer_count >= 2) {2} else {1};
+        let inner = delims_to_text(nodes.drain(j + 1..i).collect());
+        nodes.insert(j + 1, match (ch, used) {
+            ('~', _) => Node::Strike(inner),
+            (_, 2) => Node::Strong(inner),
+            _ => Node::Emphasis(inner)
+        });
+        let mut closer = j + 2;
+        delim_at_mut(&mut nodes, j).count -= used;
+        delim_at_mut(&mut nodes, closer).count -= used;
+        if delim_at(&nodes, closer).unwrap().count == 0 {
+            nodes.remove(closer);
+        }
+        if delim_at(&nodes, j).unwrap().count == 0 {
+            nodes.remove(j);
+            closer -= 1;
+        }
+        // a closer with some of its run left tries again
+        i = closer;
+    }
+    delims_to_text(nodes)
+}
+
+fn push_span(spans: &mut Vec<Span>, text: String, style: SpanStyle) {
+    if text.is_empty() {
+        return
+    }
+    if let Some(last) = spans.last_mut() {
+        if last.style == style && !last.is_break() {
+            last.text.push_str(&text);
+            return
+        }
+    }
+    spans.push(Span {text, style});
+}
+
+fn flatten(nodes: Vec<Node>, style: SpanStyle, links: &mut Vec<String>, spans: &mut Vec<Span>) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => push_span(spans, text, style),
+            Node::Code(code) => push_span(spans, code, SpanStyle {code: true, ..style}),
+            Node::Break => spans.push(Span {text: "\n".to_string(), style}),
+            Node::Emphasis(children) => flatten(children, SpanStyle {emphasis: true, ..style}, links, spans),
+            Node::Strong(children) => flatten(children, SpanStyle {strong: true, ..style}, links, spans),
+            Node::Strike(children) => flatten(children, SpanStyle {strike: true, ..style}, links, spans),
+            Node::Link(url, children) => {
+                links.push(url);
+                let link = Some(links.len() - 1);
+                flatten(children, SpanStyle {link, ..style}, links, spans)
+            }
+            Node::Delim(d) => push_span(spans, std::iter::repeat(d.ch).take(d.count).collect(), style),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // the spans with their style flags in front, `{se:a}` is strong emphasis and links
+    // are written as `[text](url)` around that
+    fn render(text: &str) -> String {
+        let mut links = Vec::new();
+        let spans = parse_inline(text, &LinkRefs::new(), &mut links);
+        let mut out = String::new();
+        for span in spans {
+            let mut flags = String::new();
+            for (on, flag) in [(span.style.strong, 's'), (span.style.emphasis, 'e'), (span.style.strike, '~'), (span.style.code, 'c')] {
+                if on {
+                    flags.push(flag);
+                }
+            }
+            let styled = if flags.is_empty() {span.text} else {format!("{{{}:{}}}", flags, span.text)};
+            match span.style.link {
+                Some(link) => out.push_str(&format!("[{}]({})", styled, links[link])),
+                None => out.push_str(&styled)
+            }
+        }
+        out
+    }
+    
+    #[test]
+    fn emphasis_and_strong() {
+        assert_eq!(render("*a* _b_ **c** __d__"), "{e:a} {e:b} {s:c} {s:d}");
+        assert_eq!(render("***a***"), "{se:a}");
+        assert_eq!(render("*a **b** c*"), "{e:a }{se:b}{e: c}");
+        assert_eq!(render("**a *b* c**"), "{s:a }{se:b}{s: c}");
+        assert_eq!(render("_a __b__ c_"), "{e:a }{se:b}{e: c}");
+        // leftover delimiters stay as text on the outside
+        assert_eq!(render("**a*"), "*{e:a}");
+        assert_eq!(render("*a**"), "{e:a}*");
+        assert_eq!(render("~~a~~ ~b~"), "{~:a} ~b~");
+    }
+    
+    #[test]
+    fn delimiter_rules() {
+        assert_eq!(render("snake_case_name"), "snake_case_name");
+        assert_eq!(render("_a_b"), "_a_b");
+        assert_eq!(render("a*b*c"), "a{e:b}c");
+        // runs surrounded by whitespace cant open or close
+        assert_eq!(render("a * b *"), "a * b *");
+        assert_eq!(render("*a"), "*a");
+        // the rule of three
+        assert_eq!(render("*foo**bar*"), "{e:foo**bar}");
+        assert_eq!(render("\\*a\\*"), "*a*");
+        // punctuation flanking
+        assert_eq!(render("*(a)*"), "{e:(a)}");
+        assert_eq!(render("a**\"b\"**"), "a**\"b\"**");
+    }
+    
+    #[test]
+    fn code_spans() {
+        assert_eq!(render("`a`"), "{c:a}");
+        assert_eq!(render("`` a`b ``"), "{c:a`b}");
+        assert_eq!(render("``` `` ```"), "{c:``}");
+        // a run of another length doesnt close
+        assert_eq!(render("`a``b`"), "{c:a``b}");
+        assert_eq!(render("``a`"), "``a`");
+        assert_eq!(render("`a\nb`"), "{c:a b}");
+        assert_eq!(render("`*a*` `\\`"), "{c:*a*} {c:\\}");
+        assert_eq!(render("*a `*` b*"), "{e:a }{ec:*}{e: b}");
+    }
+    
+    #[test]
+    fn links() {
+        assert_eq!(render("[a](http://x.y)"), "[a](http://x.y)");
+        assert_eq!(render("[a](<b c> \"title\")"), "[a](b c)");
+        assert_eq!(render("[a]( u 'title' )"), "[a](u)");
+        assert_eq!(render("[a](u(1))"), "[a](u(1))");
+        assert_eq!(render("[*a*](u) [b](v)"), "[{e:a}](u) [b](v)");
+        assert_eq!(render("![alt](img.png)"), "[alt](img.png)");
+        assert_eq!(render("[[a](u)]"), "[[a](u)]");
+        // links dont nest, the inner one wins
+        assert_eq!(render("[a [b](u)](v)"), "[a [b](u)](v)");
+        assert_eq!(render("[a]"), "[a]");
+        assert_eq!(render("[a] (u)"), "[a] (u)");
+        assert_eq!(render("[a](u"), "[a](u");
+    }
+    
+    #[test]
+    fn autolinks() {
+        assert_eq!(render("<https://a.b/c>"), "[https://a.b/c](https://a.b/c)");
+        assert_eq!(render("<me@x.org>"), "[me@x.org](mailto:me@x.org)");
+        assert_eq!(render("<not a link>"), "<not a link>");
+        assert_eq!(render("see https://a.b/c."), "see [https://a.b/c](https://a.b/c).");
+        assert_eq!(render("(www.a.b)"), "([www.a.b](http://www.a.b))");
+        assert_eq!(render("http://a.b/(c)"), "[http://a.b/(c)](http://a.b/(c))");
+        // no bare links inside link text
+        assert_eq!(render("[https://a.b](u)"), "[https://a.b](u)");
+    }
+    
+    #[test]
+    fn breaks_and_entities() {
+        assert_eq!(render("a\nb"), "a b");
+        assert_eq!(render("a  \nb"), "a\nb");
+        assert_eq!(render("a\\\nb"), "a\nb");
+        assert_eq!(render("&amp; &#65; &#x42; &bogus;"), "& A B &bogus;");
+    }
+}
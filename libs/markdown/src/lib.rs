@@ -0,0 +1,88 @@
+// A CommonMark parser that produces a block tree ready for layout. Inline
+// content is flattened into spans that each carry one style, so a renderer
+// only has to pick a font per span and flow the words.
+//
+// let doc = parse_markdown("# Title\n\nSome *emphasis* and a [link](https://makepad.nl)");
+// for block in &doc.blocks {
+//     draw_block(block, &doc.links);
+// }
+//
+// Covered are headings (ATX and setext), paragraphs, emphasis, strong, inline code,
+// fenced and indented code blocks, bullet and ordered lists, block quotes,
+// thematic breaks, inline and reference links, images (as links to the image),
+// autolinks and hard line breaks. From GFM we take strikethrough and bare http(s)
+// links since model output uses both a lot. Raw HTML is left as text.
+
+mod block;
+mod inline;
+
+pub use crate::block::parse_markdown;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarkdownDoc {
+    pub blocks: Vec<Block>,
+    // link targets, spans point into this by index
+    pub links: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Heading {level: usize, spans: Vec<Span>},
+    Paragraph(Vec<Span>),
+    CodeBlock {info: String, code: String},
+    List {start: Option<u64>, tight: bool, items: Vec<Vec<Block>>},
+    Quote(Vec<Block>),
+    Rule,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpanStyle {
+    pub strong: bool,
+    pub emphasis: bool,
+    pub strike: bool,
+    pub code: bool,
+    pub link: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    // a hard line break is a span of just "\n"
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+impl Span {
+    pub fn is_break(&self) -> bool {
+        self.text == "\n"
+    }
+}
+
+impl Block {
+    // the plain text of a block without markup, handy for copying and accessibility
+    pub fn plain_text(&self, out: &mut String) {
+        match self {
+            Block::Heading {spans, ..} | Block::Paragraph(spans) => {
+                for span in spans {
+                    out.push_str(&span.text);
+                }
+                out.push('\n');
+            }
+            Block::CodeBlock {code, ..} => {
+                out.push_str(code);
+            }
+            Block::List {items, ..} => {
+                for item in items {
+                    for block in item {
+                        block.plain_text(out);
+                    }
+                }
+            }
+            Block::Quote(blocks) => {
+                for block in blocks {
+                    block.plain_text(out);
+                }
+            }
+            Block::Rule => (),
+        }
+    }
+}
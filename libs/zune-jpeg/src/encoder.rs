@@ -0,0 +1,550 @@
+/*
+ * Copyright (c) 2023.
+ *
+ * This software is free software;
+ *
+ * You can redistribute it or modify it under terms of the MIT, Apache License or Zlib license
+ */
+
+//! A baseline JPEG encoder
+//!
+//! Writes sequential huffman coded JFIF files with the example quantization
+//! and huffman tables of the spec scaled by the quality setting. Color images
+//! are stored as YCbCr, with chroma subsampled 2x2 below quality 90.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use makepad_zune_core::bit_depth::BitDepth;
+use makepad_zune_core::colorspace::ColorSpace;
+use makepad_zune_core::options::EncoderOptions;
+
+use crate::errors::EncodeErrors;
+use crate::misc::{
+    AC_CHROMA_BITS, AC_CHROMA_VALUES, AC_LUMA_BITS, AC_LUMA_VALUES, DC_CHROMA_BITS,
+    DC_CHROMA_VALUES, DC_LUMA_BITS, DC_LUMA_VALUES, UN_ZIGZAG
+};
+
+/// Table K.1, luminance quantization in natural order
+#[rustfmt::skip]
+const LUMA_QUANT: [u16; 64] = [
+    16, 11, 10, 16,  24,  40,  51,  61,
+    12, 12, 14, 19,  26,  58,  60,  55,
+    14, 13, 16, 24,  40,  57,  69,  56,
+    14, 17, 22, 29,  51,  87,  80,  62,
+    18, 22, 37, 56,  68, 109, 103,  77,
+    24, 35, 55, 64,  81, 104, 113,  92,
+    49, 64, 78, 87, 103, 121, 120, 101,
+    72, 92, 95, 98, 112, 100, 103,  99
+];
+
+/// Table K.2, chrominance quantization in natural order
+#[rustfmt::skip]
+const CHROMA_QUANT: [u16; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99,
+    18, 21, 26, 66, 99, 99, 99, 99,
+    24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99
+];
+
+/// The orthonormal DCT-II basis, row `u` holds `C(u)/2 * cos((2x+1)u*pi/16)`
+#[rustfmt::skip]
+const DCT_BASIS: [f32; 64] = [
+     0.353_553_4,  0.353_553_4,  0.353_553_4,  0.353_553_4,  0.353_553_4,  0.353_553_4,  0.353_553_4,  0.353_553_4,
+     0.490_392_6,  0.415_734_8,  0.277_785_1,  0.097_545_2, -0.097_545_2, -0.277_785_1, -0.415_734_8, -0.490_392_6,
+     0.461_939_8,  0.191_341_7, -0.191_341_7, -0.461_939_8, -0.461_939_8, -0.191_341_7,  0.191_341_7,  0.461_939_8,
+     0.415_734_8, -0.097_545_2, -0.490_392_6, -0.277_785_1,  0.277_785_1,  0.490_392_6,  0.097_545_2, -0.415_734_8,
+     0.353_553_4, -0.353_553_4, -0.353_553_4,  0.353_553_4,  0.353_553_4, -0.353_553_4, -0.353_553_4,  0.353_553_4,
+     0.277_785_1, -0.490_392_6,  0.097_545_2,  0.415_734_8, -0.415_734_8, -0.097_545_2,  0.490_392_6, -0.277_785_1,
+     0.191_341_7, -0.461_939_8,  0.461_939_8, -0.191_341_7, -0.191_341_7,  0.461_939_8, -0.461_939_8,  0.191_341_7,
+     0.097_545_2, -0.277_785_1,  0.415_734_8, -0.490_392_6,  0.490_392_6, -0.415_734_8,  0.277_785_1, -0.097_545_2
+];
+
+/// Quality from which chroma is kept at full resolution
+const FULL_CHROMA_QUALITY: u8 = 90;
+
+/// A baseline JPEG encoder
+///
+/// Takes 8 bit interleaved `RGB`, `RGBA`, `BGR`, `BGRA`, `Luma` or `LumaA`
+/// pixels, alpha is dropped since JPEG has no place for it.
+///
+/// # Example
+/// ```no_run
+/// use makepad_zune_core::colorspace::ColorSpace;
+/// use makepad_zune_core::options::EncoderOptions;
+/// use makepad_zune_jpeg::JpegEncoder;
+///
+/// let pixels = vec![255_u8; 16 * 16 * 3];
+/// let options = EncoderOptions::default()
+///     .set_width(16)
+///     .set_height(16)
+///     .set_colorspace(ColorSpace::RGB)
+///     .set_quality(85);
+/// let jpeg = JpegEncoder::new(&pixels, options).encode().unwrap();
+/// ```
+pub struct JpegEncoder<'a> {
+    data:    &'a [u8],
+    options: EncoderOptions
+}
+
+impl<'a> JpegEncoder<'a> {
+    /// Create a new encoder for the pixels in `data`, laid out as described
+    /// by the width, height, colorspace and quality of `options`
+    #[must_use]
+    pub fn new(data: &'a [u8], options: EncoderOptions) -> JpegEncoder<'a> {
+        JpegEncoder { data, options }
+    }
+
+    /// Encode the image into a complete JPEG file
+    ///
+    /// # Errors
+    /// When the size is zero or too large for JPEG, the colorspace or depth is
+    /// not supported or the data does not match the size.
+    pub fn encode(&self) -> Result<Vec<u8>, EncodeErrors> {
+        let width = self.options.get_width();
+        let height = self.options.get_height();
+        if width == 0 || height == 0 {
+            return Err(EncodeErrors::ZeroDimensions);
+        }
+        if width > usize::from(u16::MAX) || height > usize::from(u16::MAX) {
+            return Err(EncodeErrors::LargeDimensions(width.max(height)));
+        }
+        if self.options.get_depth() != BitDepth::Eight {
+            return Err(EncodeErrors::UnsupportedDepth(self.options.get_depth()));
+        }
+        let colorspace = self.options.get_colorspace();
+        let is_gray = match colorspace {
+            ColorSpace::Luma | ColorSpace::LumaA => true,
+            ColorSpace::RGB | ColorSpace::RGBA | ColorSpace::BGR | ColorSpace::BGRA => false,
+            _ => return Err(EncodeErrors::UnsupportedColorspace(colorspace))
+        };
+        let expected = width * height * colorspace.num_components();
+        if self.data.len() != expected {
+            return Err(EncodeErrors::WrongDataSize(expected, self.data.len()));
+        }
+
+        let quality = self.options.get_quality().max(1);
+        let subsample = !is_gray && quality < FULL_CHROMA_QUALITY;
+        let luma_quant = scale_quant(&LUMA_QUANT, quality);
+        let chroma_quant = scale_quant(&CHROMA_QUANT, quality);
+
+        let mut out = Vec::with_capacity(expected / 4 + 1024);
+        write_headers(&mut out, width, height, is_gray, subsample, &luma_quant, &chroma_quant);
+
+        // the planes are padded to whole MCUs by repeating the edge pixels
+        let mcu = if subsample { 16 } else { 8 };
+        let padded_width = (width + mcu - 1) / mcu * mcu;
+        let padded_height = (height + mcu - 1) / mcu * mcu;
+        let planes = self.to_planes(padded_width, padded_height, is_gray);
+
+        let luma_tables = (
+            HuffmanCodes::new(&DC_LUMA_BITS, &DC_LUMA_VALUES),
+            HuffmanCodes::new(&AC_LUMA_BITS, &AC_LUMA_VALUES)
+        );
+        let chroma_tables = (
+            HuffmanCodes::new(&DC_CHROMA_BITS, &DC_CHROMA_VALUES),
+            HuffmanCodes::new(&AC_CHROMA_BITS, &AC_CHROMA_VALUES)
+        );
+
+        let mut writer = BitWriter::new(&mut out);
+        let mut predictions = [0_i32; 3];
+        let mut block = [0.0_f32; 64];
+        for mcu_y in (0..padded_height).step_by(mcu) {
+            for mcu_x in (0..padded_width).step_by(mcu) {
+                // all luma blocks of the MCU, left to right, top to bottom
+                for y in (mcu_y..mcu_y + mcu).step_by(8) {
+                    for x in (mcu_x..mcu_x + mcu).step_by(8) {
+                        read_block(&planes[0], padded_width, x, y, &mut block);
+                        encode_block(
+                            &mut writer, &block, &luma_quant, &luma_tables, &mut predictions[0]
+                        );
+                    }
+                }
+                if is_gray {
+                    continue;
+                }
+                for c in 1..3 {
+                    if subsample {
+                        read_block_subsampled(&planes[c], padded_width, mcu_x, mcu_y, &mut block);
+                    } else {
+                        read_block(&planes[c], padded_width, mcu_x, mcu_y, &mut block);
+                    }
+                    encode_block(
+                        &mut writer, &block, &chroma_quant, &chroma_tables, &mut predictions[c]
+                    );
+                }
+            }
+        }
+        writer.flush();
+
+        // EOI
+        out.extend_from_slice(&[0xFF, 0xD9]);
+        Ok(out)
+    }
+
+    /// Splits the pixels into level shifted Y, Cb and Cr planes of the padded size
+    fn to_planes(&self, padded_width: usize, padded_height: usize, is_gray: bool) -> Vec<Vec<f32>> {
+        let width = self.options.get_width();
+        let height = self.options.get_height();
+        let colorspace = self.options.get_colorspace();
+        let components = colorspace.num_components();
+        let (r, b) = match colorspace {
+            ColorSpace::BGR | ColorSpace::BGRA => (2, 0),
+            _ => (0, 2)
+        };
+        let plane_count = if is_gray { 1 } else { 3 };
+        let mut planes = vec![vec![0.0_f32; padded_width * padded_height]; plane_count];
+        for y in 0..padded_height {
+            let row = y.min(height - 1) * width;
+            for x in 0..padded_width {
+                let pixel = &self.data[(row + x.min(width - 1)) * components..];
+                let i = y * padded_width + x;
+                if is_gray {
+                    planes[0][i] = f32::from(pixel[0]) - 128.0;
+                    continue;
+                }
+                let (red, green, blue) = (
+                    f32::from(pixel[r]),
+                    f32::from(pixel[1]),
+                    f32::from(pixel[b])
+                );
+                planes[0][i] = 0.299 * red + 0.587 * green + 0.114 * blue - 128.0;
+                planes[1][i] = -0.168_736 * red - 0.331_264 * green + 0.5 * blue;
+                planes[2][i] = 0.5 * red - 0.418_688 * green - 0.081_312 * blue;
+            }
+        }
+        planes
+    }
+}
+
+/// Scales a quantization table the way libjpeg does, so quality settings
+/// carry over between encoders
+fn scale_quant(base: &[u16; 64], quality: u8) -> [u16; 64] {
+    let quality = u32::from(quality.clamp(1, 100));
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - quality * 2
+    };
+    let mut out = [0; 64];
+    for (out, base) in out.iter_mut().zip(base) {
+        *out = ((u32::from(*base) * scale + 50) / 100).clamp(1, 255) as u16;
+    }
+    out
+}
+
+fn write_marker(out: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+    out.extend_from_slice(payload);
+}
+
+fn write_headers(
+    out: &mut Vec<u8>, width: usize, height: usize, is_gray: bool, subsample: bool,
+    luma_quant: &[u16; 64], chroma_quant: &[u16; 64]
+) {
+    // SOI
+    out.extend_from_slice(&[0xFF, 0xD8]);
+    // APP0, JFIF 1.1 without a density or thumbnail
+    write_marker(out, 0xE0, b"JFIF\0\x01\x01\x00\x00\x01\x00\x01\x00\x00");
+
+    // DQT, the tables go out in zigzag order
+    let mut dqt = Vec::with_capacity(130);
+    for (id, table) in [luma_quant, chroma_quant].iter().enumerate().take(if is_gray { 1 } else { 2 }) {
+        dqt.push(id as u8);
+        dqt.extend(UN_ZIGZAG[..64].iter().map(|&i| table[i] as u8));
+    }
+    write_marker(out, 0xDB, &dqt);
+
+    // SOF0
+    let mut sof = vec![8];
+    sof.extend_from_slice(&(height as u16).to_be_bytes());
+    sof.extend_from_slice(&(width as u16).to_be_bytes());
+    if is_gray {
+        sof.extend_from_slice(&[1, 1, 0x11, 0]);
+    } else {
+        let luma_sampling = if subsample { 0x22 } else { 0x11 };
+        sof.extend_from_slice(&[3, 1, luma_sampling, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    }
+    write_marker(out, 0xC0, &sof);
+
+    // DHT
+    let mut dht = Vec::with_capacity(420);
+    let mut add_table = |class_id: u8, bits: &[u8; 17], values: &[u8]| {
+        dht.push(class_id);
+        dht.extend_from_slice(&bits[1..]);
+        dht.extend_from_slice(values);
+    };
+    add_table(0x00, &DC_LUMA_BITS, &DC_LUMA_VALUES);
+    add_table(0x10, &AC_LUMA_BITS, &AC_LUMA_VALUES);
+    if !is_gray {
+        add_table(0x01, &DC_CHROMA_BITS, &DC_CHROMA_VALUES);
+        add_table(0x11, &AC_CHROMA_BITS, &AC_CHROMA_VALUES);
+    }
+    write_marker(out, 0xC4, &dht);
+
+    // SOS, one interleaved scan over all coefficients
+    if is_gray {
+        write_marker(out, 0xDA, &[1, 1, 0x00, 0, 63, 0]);
+    } else {
+        write_marker(out, 0xDA, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+    }
+}
+
+fn read_block(plane: &[f32], stride: usize, x: usize, y: usize, block: &mut [f32; 64]) {
+    for row in 0..8 {
+        let start = (y + row) * stride + x;
+        block[row * 8..row * 8 + 8].copy_from_slice(&plane[start..start + 8]);
+    }
+}
+
+/// Averages 2x2 pixels of a 16x16 area into one 8x8 block
+fn read_block_subsampled(plane: &[f32], stride: usize, x: usize, y: usize, block: &mut [f32; 64]) {
+    for row in 0..8 {
+        for col in 0..8 {
+            let i = (y + row * 2) * stride + x + col * 2;
+            block[row * 8 + col] =
+                (plane[i] + plane[i + 1] + plane[i + stride] + plane[i + stride + 1]) * 0.25;
+        }
+    }
+}
+
+/// Separable forward DCT, rows first then columns
+fn forward_dct(block: &[f32; 64]) -> [f32; 64] {
+    let mut rows = [0.0_f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            let basis = &DCT_BASIS[u * 8..u * 8 + 8];
+            rows[y * 8 + u] = (0..8).map(|x| block[y * 8 + x] * basis[x]).sum();
+        }
+    }
+    let mut out = [0.0_f32; 64];
+    for u in 0..8 {
+        for v in 0..8 {
+            let basis = &DCT_BASIS[v * 8..v * 8 + 8];
+            out[v * 8 + u] = (0..8).map(|y| rows[y * 8 + u] * basis[y]).sum();
+        }
+    }
+    out
+}
+
+fn encode_block(
+    writer: &mut BitWriter, block: &[f32; 64], quant: &[u16; 64],
+    tables: &(HuffmanCodes, HuffmanCodes), prediction: &mut i32
+) {
+    let coefficients = forward_dct(block);
+    let mut zigzag = [0_i32; 64];
+    for (out, &i) in zigzag.iter_mut().zip(&UN_ZIGZAG[..64]) {
+        let q = coefficients[i] / f32::from(quant[i]);
+        // round half away from zero without needing std
+        *out = if q >= 0.0 { (q + 0.5) as i32 } else { (q - 0.5) as i32 };
+    }
+    let (dc_codes, ac_codes) = tables;
+
+    let diff = zigzag[0] - *prediction;
+    *prediction = zigzag[0];
+    let category = magnitude_category(diff);
+    dc_codes.write(writer, category as u8);
+    writer.write_bits(magnitude_bits(diff, category), category);
+
+    let mut run = 0;
+    for &coefficient in &zigzag[1..] {
+        if coefficient == 0 {
+            run += 1;
+            continue;
+        }
+        while run > 15 {
+            // ZRL, sixteen zeros
+            ac_codes.write(writer, 0xF0);
+            run -= 16;
+        }
+        let category = magnitude_category(coefficient);
+        ac_codes.write(writer, (run << 4) | category as u8);
+        writer.write_bits(magnitude_bits(coefficient, category), category);
+        run = 0;
+    }
+    if run > 0 {
+        // EOB
+        ac_codes.write(writer, 0x00);
+    }
+}
+
+/// The number of bits needed for the magnitude of `value`
+fn magnitude_category(value: i32) -> u32 {
+    32 - value.unsigned_abs().leading_zeros()
+}
+
+/// Negative values are stored as their ones complement
+fn magnitude_bits(value: i32, category: u32) -> u32 {
+    if value < 0 {
+        (value - 1) as u32 & ((1 << category) - 1)
+    } else {
+        value as u32
+    }
+}
+
+/// Canonical huffman codes built from a table as it is stored in DHT
+struct HuffmanCodes {
+    codes:   [u16; 256],
+    lengths: [u8; 256]
+}
+
+impl HuffmanCodes {
+    fn new(bits: &[u8; 17], values: &[u8]) -> HuffmanCodes {
+        let mut codes = [0; 256];
+        let mut lengths = [0; 256];
+        let mut code = 0_u16;
+        let mut values = values.iter();
+        for (length, &count) in bits.iter().enumerate().skip(1) {
+            for _ in 0..count {
+                if let Some(&value) = values.next() {
+                    codes[usize::from(value)] = code;
+                    lengths[usize::from(value)] = length as u8;
+                }
+                code += 1;
+            }
+            code <<= 1;
+        }
+        HuffmanCodes { codes, lengths }
+    }
+
+    fn write(&self, writer: &mut BitWriter, symbol: u8) {
+        let symbol = usize::from(symbol);
+        writer.write_bits(u32::from(self.codes[symbol]), u32::from(self.lengths[symbol]));
+    }
+}
+
+/// Packs bits msb first into entropy coded data, stuffing a zero after every 0xFF
+struct BitWriter<'a> {
+    out:   &'a mut Vec<u8>,
+    bits:  u32,
+    count: u32
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(out: &'a mut Vec<u8>) -> BitWriter<'a> {
+        BitWriter { out, bits: 0, count: 0 }
+    }
+
+    fn write_bits(&mut self, value: u32, length: u32) {
+        if length == 0 {
+            return;
+        }
+        self.bits = (self.bits << length) | (value & ((1 << length) - 1));
+        self.count += length;
+        while self.count >= 8 {
+            self.count -= 8;
+            let byte = (self.bits >> self.count) as u8;
+            self.out.push(byte);
+            if byte == 0xFF {
+                self.out.push(0);
+            }
+        }
+    }
+
+    /// Pads the last byte with one bits
+    fn flush(&mut self) {
+        if self.count > 0 {
+            let pad = 8 - self.count;
+            self.write_bits((1 << pad) - 1, pad);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use makepad_zune_core::colorspace::ColorSpace;
+    use makepad_zune_core::options::{DecoderOptions, EncoderOptions};
+
+    use crate::{JpegDecoder, JpegEncoder};
+
+    /// A gradient with a bright square on it, sized so the MCUs need padding
+    fn test_image(width: usize, height: usize, components: usize) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(width * height * components);
+        for y in 0..height {
+            for x in 0..width {
+                let square = (10..20).contains(&x) && (6..14).contains(&y);
+                let rgb = if square {
+                    [240, 240, 220]
+                } else {
+                    [(x * 6) as u8, (y * 10) as u8, (128 + x * 2 - y * 3) as u8]
+                };
+                match components {
+                    1 => pixels.push(rgb[0]),
+                    _ => pixels.extend_from_slice(&rgb[..components.min(3)])
+                }
+                if components == 4 {
+                    pixels.push(255);
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Encodes and decodes, returning the mean and largest channel difference
+    fn round_trip(colorspace: ColorSpace, quality: u8) -> (f64, u8) {
+        let (width, height) = (37, 21);
+        let pixels = test_image(width, height, colorspace.num_components());
+        let options = EncoderOptions::default()
+            .set_width(width)
+            .set_height(height)
+            .set_colorspace(colorspace)
+            .set_quality(quality);
+        let jpeg = JpegEncoder::new(&pixels, options).encode().unwrap();
+
+        let out_colorspace = match colorspace {
+            ColorSpace::Luma => ColorSpace::Luma,
+            _ => ColorSpace::RGB
+        };
+        let options = DecoderOptions::default().jpeg_set_out_colorspace(out_colorspace);
+        let mut decoder = JpegDecoder::new_with_options(jpeg.as_slice(), options);
+        let decoded = decoder.decode().unwrap();
+        assert_eq!(decoder.dimensions(), Some((width as u16, height as u16)));
+
+        // compare color channels only, alpha does not survive
+        let components = colorspace.num_components();
+        let channels = out_colorspace.num_components();
+        assert_eq!(decoded.len(), width * height * channels);
+        let mut total = 0_u64;
+        let mut largest = 0_u8;
+        for (original, decoded) in pixels.chunks(components).zip(decoded.chunks(channels)) {
+            for (a, b) in original.iter().zip(decoded) {
+                let difference = a.abs_diff(*b);
+                total += u64::from(difference);
+                largest = largest.max(difference);
+            }
+        }
+        (total as f64 / (width * height * channels) as f64, largest)
+    }
+
+    #[test]
+    fn rgb_round_trip_full_chroma() {
+        let (mean, largest) = round_trip(ColorSpace::RGB, 95);
+        assert!(mean < 2.5 && largest < 24, "mean {mean} largest {largest}");
+    }
+
+    #[test]
+    fn rgb_round_trip_subsampled() {
+        // the chroma of the square edges smears across the 2x2 blocks
+        let (mean, largest) = round_trip(ColorSpace::RGB, 75);
+        assert!(mean < 6.0 && largest < 80, "mean {mean} largest {largest}");
+    }
+
+    #[test]
+    fn rgba_round_trip_drops_alpha() {
+        let (mean, largest) = round_trip(ColorSpace::RGBA, 85);
+        assert!(mean < 6.0 && largest < 80, "mean {mean} largest {largest}");
+    }
+
+    #[test]
+    fn luma_round_trip() {
+        let (mean, largest) = round_trip(ColorSpace::Luma, 85);
+        assert!(mean < 3.0 && largest < 24, "mean {mean} largest {largest}");
+    }
+}
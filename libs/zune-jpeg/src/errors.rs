@@ -12,6 +12,9 @@
 use alloc::string::String;
 use core::fmt::{Debug, Display, Formatter};
 
+use makepad_zune_core::bit_depth::BitDepth;
+use makepad_zune_core::colorspace::ColorSpace;
+
 use crate::misc::{
     START_OF_FRAME_EXT_AR, START_OF_FRAME_EXT_SEQ, START_OF_FRAME_LOS_SEQ,
     START_OF_FRAME_LOS_SEQ_AR, START_OF_FRAME_PROG_DCT_AR
@@ -156,3 +159,43 @@ impl UnsupportedSchemes {
         }
     }
 }
+
+/// Errors the encoder can run into, all of them caused by the options or the
+/// input not describing an image baseline JPEG can hold
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone)]
+pub enum EncodeErrors {
+    /// Image has zero width or height
+    ZeroDimensions,
+    /// Width or height does not fit in the 16 bits of the frame header
+    LargeDimensions(usize),
+    /// Only 8 bit samples can be encoded
+    UnsupportedDepth(BitDepth),
+    /// The colorspace has no mapping to JPEG
+    UnsupportedColorspace(ColorSpace),
+    /// The pixel data does not match the size, expected and found length
+    WrongDataSize(usize, usize)
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeErrors {}
+
+impl Debug for EncodeErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ZeroDimensions => write!(f, "Image width or height is set to zero, cannot encode"),
+            Self::LargeDimensions(dimensions) => {
+                write!(f, "Too large dimensions {dimensions}, JPEG supports up to {}", u16::MAX)
+            }
+            Self::UnsupportedDepth(depth) => write!(f, "Cannot encode {depth:?} samples, only 8 bit is supported"),
+            Self::UnsupportedColorspace(colorspace) => write!(f, "Cannot encode the {colorspace:?} colorspace"),
+            Self::WrongDataSize(expected, found) => write!(f, "Expected {expected} bytes of pixel data but got {found}")
+        }
+    }
+}
+
+impl Display for EncodeErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
@@ -122,11 +122,13 @@ macro_rules!debug {
 pub use makepad_zune_core;
 
 pub use crate::decoder::{ImageInfo, JpegDecoder};
+pub use crate::encoder::JpegEncoder;
 
 mod bitstream;
 mod color_convert;
 mod components;
 mod decoder;
+mod encoder;
 pub mod errors;
 mod headers;
 mod huffman;
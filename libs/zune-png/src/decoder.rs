@@ -317,6 +317,24 @@ impl<T: ZReaderTrait> PngDecoder<T> {
         self.frames.len() > self.current_frame
     }
 
+    /// Return the animation control chunk of an APNG
+    ///
+    /// This contains the number of frames and how often the animation plays,
+    /// where zero means it repeats indefinitely
+    pub fn actl_info(&self) -> Option<&ActlChunk> {
+        self.actl_info.as_ref()
+    }
+
+    /// Return the frame control information of the frame decoded last
+    ///
+    /// This gives the region, delay, dispose and blend operations needed
+    /// to composite the frame onto the previous ones
+    pub fn frame_info(&self) -> Option<FrameInfo> {
+        self.frames
+            .get(self.current_frame.checked_sub(1)?)
+            .and_then(|frame| frame.fctl_info)
+    }
+
     pub(crate) fn read_chunk_header(&mut self) -> Result<PngChunk, PngDecodeErrors> {
         // Format is length - chunk type - [data] -  crc chunk, load crc chunk now
         let chunk_length = self.stream.get_u32_be_err()? as usize;
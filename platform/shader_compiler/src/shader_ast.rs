@@ -81,6 +81,8 @@ pub struct DrawShaderDef {
     pub fields: Vec<DrawShaderFieldDef>,
     pub methods: Vec<FnPtr>,
     pub enums: Vec<LiveType>,
+    // the sampler presets the shader selects for its textures, with `sampler: {image: linear}`
+    pub samplers: Vec<(Ident, Ident)>,
     // analysis results:
     //pub all_const_refs: RefCell<BTreeSet<ConstPtr>>,
     pub all_live_refs: RefCell<BTreeMap<ValuePtr, Ty >>,
@@ -666,6 +666,23 @@ impl ShaderRegistry {
                                 }
                             };
                         }
+                        LiveValue::Object if prop.id == live_id!(sampler) => {
+                            let mut child_iter = doc.nodes.first_child(node_index);
+                            while let Some(child_index) = child_iter {
+                                let child = &doc.nodes[child_index];
+                                if let LiveValue::Id(preset) = child.value {
+                                    draw_shader_def.samplers.push((Ident(child.id), Ident(preset)));
+                                }
+                                else {
+                                    return Err(LiveError {
+                                        origin: live_error_origin!(),
+                                        span: child.origin.token_id().unwrap().into(),
+                                        message: format!("Sampler for texture {} should be a preset name like linear or nearest_repeat", child.id)
+                                    })
+                                }
+                                child_iter = doc.nodes.next_child(child_index);
+                            }
+                        }
                         LiveValue::Class {live_type, ..} => {
                             if prop.id == live_id!(geometry) {
                                 ext_self(
@@ -747,6 +764,16 @@ impl ShaderRegistry {
                     }
                     node_iter = doc.nodes.next_child(node_index);
                 }
+                // a sampler has to belong to one of the textures
+                for (texture, _) in &draw_shader_def.samplers {
+                    if !draw_shader_def.fields.iter().any( | field | field.ident == *texture && matches!(field.kind, DrawShaderFieldKind::Texture {..})) {
+                        return Err(LiveError {
+                            origin: live_error_origin!(),
+                            span: class_node.origin.token_id().unwrap().into(),
+                            message: format!("Sampler given for {} which is not a texture of the shader", texture)
+                        })
+                    }
+                }
                 // lets check for duplicate fields
                 for i in 0..draw_shader_def.fields.len() {
                     for j in (i + 1)..draw_shader_def.fields.len() {
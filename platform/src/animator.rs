@@ -16,6 +16,7 @@ use {
             LiveId,
             LiveNode,
             LiveIdAsProp,
+            LiveProp,
             LiveNodeSliceApi,
             LiveNodeVecApi
          },
@@ -36,6 +37,7 @@ pub trait AnimatorImpl {
     
     fn animator_cut(&mut self, cx: &mut Cx, state: &[LiveId; 2]);
     fn animator_play(&mut self, cx: &mut Cx, state: &[LiveId; 2]);
+    fn animator_play_delayed(&mut self, cx: &mut Cx, state: &[LiveId; 2], delay: f64);
     fn animator_toggle(&mut self, cx: &mut Cx, is_state_1: bool, animate: Animate, state1: &[LiveId; 2], state2: &[LiveId; 2]) {
         if is_state_1 {
             if let Animate::Yes = animate {
@@ -97,6 +99,9 @@ pub enum Play {
     
     #[live {duration: 1.0, end: 1.0}]
     BounceLoop {duration: f64, end: f64},
+    
+    #[live {stiffness: 180.0, damping: 24.0, mass: 1.0}]
+    Spring {stiffness: f64, damping: f64, mass: f64},
 }
 //pub type StatePair = [LiveId; 2];
 
@@ -139,8 +144,57 @@ impl Play {
                 };
                 (false, local_time)
             },
+            Self::Spring {..} => {
+                let (ended, pos, _) = self.spring_state(time, 0.0).unwrap();
+                (ended, pos)
+            }
         }
     }
+    
+    // a damped harmonic oscillator pulling from 0 to 1, velocity is in units per second.
+    // returns ended, position and velocity at time, or None if we aren't a spring
+    pub fn spring_state(&self, time: f64, velocity: f64) -> Option<(bool, f64, f64)> {
+        if let Self::Spring {stiffness, damping, mass} = self {
+            let mass = mass.max(0.0001);
+            let stiffness = stiffness.max(0.0001);
+            let omega = (stiffness / mass).sqrt();
+            let zeta = damping.max(0.0) / (2.0 * (stiffness * mass).sqrt());
+            // solve for the displacement from the target, which starts at -1
+            let x0 = -1.0;
+            let (x, v) = if zeta < 1.0 {
+                let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+                let b = (velocity + zeta * omega * x0) / omega_d;
+                let decay = (-zeta * omega * time).exp();
+                let (sin, cos) = (omega_d * time).sin_cos();
+                (
+                    decay * (x0 * cos + b * sin),
+                    decay * ((omega_d * b - zeta * omega * x0) * cos - (omega_d * x0 + zeta * omega * b) * sin)
+                )
+            }
+            else if zeta == 1.0 {
+                let b = velocity + omega * x0;
+                let decay = (-omega * time).exp();
+                (
+                    decay * (x0 + b * time),
+                    decay * (b - omega * (x0 + b * time))
+                )
+            }
+            else {
+                let root = omega * (zeta * zeta - 1.0).sqrt();
+                let r1 = -zeta * omega + root;
+                let r2 = -zeta * omega - root;
+                let c2 = (velocity - r1 * x0) / (r2 - r1);
+                let c1 = x0 - c2;
+                let (e1, e2) = ((r1 * time).exp(), (r2 * time).exp());
+                (c1 * e1 + c2 * e2, r1 * c1 * e1 + r2 * c2 * e2)
+            };
+            if x.abs() < 0.0005 && v.abs() < 0.005 {
+                return Some((true, 1.0, 0.0))
+            }
+            return Some((false, 1.0 + x, v))
+        }
+        None
+    }
 }
 
 
@@ -523,6 +577,7 @@ pub struct Animator {
     pub live_ptr: LiveRef,
     pub state: Option<Vec<LiveNode >>,
     pub next_frame: NextFrame,
+    pub last_frame_time: f64,
 }
 
 #[derive(Copy, Clone)]
@@ -612,6 +667,7 @@ impl Animator {
             if self.state.is_none() {
                 return AnimatorAction::None
             }
+            self.last_frame_time = nf.time;
             let state_nodes = self.state.as_mut().unwrap();
             
             let mut state_index = state_nodes.child_by_name(0, live_id!(state).as_field()).unwrap();
@@ -695,7 +751,34 @@ impl Animator {
                     };
                     node_iter = nodes.next_child(id_index);
                     
-                    let (ended, time) = play.get_ended_time(ext_time - start_time);
+                    // a spring carries over the velocity of the animation it interrupted
+                    let mut velocity = 0.0;
+                    if let Some(velocity_index) = node_iter {
+                        if nodes[velocity_index].id == live_id!(velocity) {
+                            if let LiveValue::Float64(v) = nodes[velocity_index].value {
+                                velocity = v;
+                            }
+                            node_iter = nodes.next_child(velocity_index);
+                        }
+                    }
+                    
+                    // delayed tracks hold their start value until the delay passed
+                    let delay = match nodes.child_value_by_path(track_index, &[live_id!(delay).as_field()]) {
+                        Some(LiveValue::Float64(v)) => *v,
+                        Some(LiveValue::Int64(v)) => *v as f64,
+                        _ => 0.0
+                    };
+                    let local_time = ext_time - start_time - delay;
+                    if local_time < 0.0 {
+                        return (false, false)
+                    }
+                    
+                    let (ended, time) = if let Some((ended, pos, _)) = play.spring_state(local_time, velocity) {
+                        (ended, pos)
+                    }
+                    else {
+                        play.get_ended_time(local_time)
+                    };
                     
                     if ended { // mark ended step 1
                         if let Some(index) = nodes.child_by_name(track_index, live_id!(ended).as_field()) {
@@ -709,6 +792,11 @@ impl Animator {
                         }else {false}
                     }else {false};
                     
+                    if let Play::Spring {..} = play {
+                        // springs ignore intermediate keyframes, they go from the first to the last value
+                        return Self::update_spring_value(cx, index, nodes, node_iter, time, ended, redraw)
+                    }
+                    
                     (ended, time, redraw, track_id)
                 }
                 else {panic!()}
@@ -766,51 +854,7 @@ impl Animator {
                         let a = &prev_kf.value;
                         let b = &next_kf.value;
                         
-                        let new_val = match a {
-                            LiveValue::Int64(va) => match b {
-                                LiveValue::Int64(vb) => {
-                                    LiveValue::Float64(((vb - va) as f64) * mix + *va as f64)
-                                }
-                                LiveValue::Float64(vb) => {
-                                    LiveValue::Float64(((vb - *va as f64) as f64) * mix + *va as f64)
-                                }
-                                _ => LiveValue::None
-                            }
-                            LiveValue::Float64(va) => match b {
-                                LiveValue::Int64(vb) => {
-                                    LiveValue::Float64(((*vb as f64 - va) as f64) * mix + *va as f64)
-                                }
-                                LiveValue::Float64(vb) => {
-                                    LiveValue::Float64(((vb - va)) * mix + *va)
-                                }
-                                _ => LiveValue::None
-                            }
-                            LiveValue::Color(va) => match b {
-                                LiveValue::Color(vb) => {
-                                    LiveValue::Color(Vec4::from_lerp(Vec4::from_u32(*va), Vec4::from_u32(*vb), mix as f32).to_u32())
-                                }
-                                _ => LiveValue::None
-                            }
-                            LiveValue::Vec2(va) => match b {
-                                LiveValue::Vec2(vb) => {
-                                    LiveValue::Vec2(Vec2::from_lerp(*va, *vb, mix as f32))
-                                }
-                                _ => LiveValue::None
-                            }
-                            LiveValue::Vec3(va) => match b {
-                                LiveValue::Vec3(vb) => {
-                                    LiveValue::Vec3(Vec3::from_lerp(*va, *vb, mix as f32))
-                                }
-                                _ => LiveValue::None
-                            }
-                            LiveValue::Id(_) => match b {
-                                LiveValue::Id(vb) => {
-                                    LiveValue::Id(*vb)
-                                }
-                                _ => LiveValue::None
-                            }
-                            _ => LiveValue::None
-                        };
+                        let new_val = Self::mix_values(a, b, mix);
                         if let LiveValue::None = &new_val {
                             cx.apply_key_frame_cannot_be_interpolated(live_error_origin!(), index, nodes, a, b);
                             return (ended, redraw)
@@ -833,6 +877,99 @@ impl Animator {
     }
     
     
+    fn mix_values(a: &LiveValue, b: &LiveValue, mix: f64) -> LiveValue {
+        match a {
+            LiveValue::Int64(va) => match b {
+                LiveValue::Int64(vb) => {
+                    LiveValue::Float64(((vb - va) as f64) * mix + *va as f64)
+                }
+                LiveValue::Float64(vb) => {
+                    LiveValue::Float64(((vb - *va as f64) as f64) * mix + *va as f64)
+                }
+                _ => LiveValue::None
+            }
+            LiveValue::Float64(va) => match b {
+                LiveValue::Int64(vb) => {
+                    LiveValue::Float64(((*vb as f64 - va) as f64) * mix + *va as f64)
+                }
+                LiveValue::Float64(vb) => {
+                    LiveValue::Float64(((vb - va)) * mix + *va)
+                }
+                _ => LiveValue::None
+            }
+            LiveValue::Color(va) => match b {
+                LiveValue::Color(vb) => {
+                    LiveValue::Color(Vec4::from_lerp(Vec4::from_u32(*va), Vec4::from_u32(*vb), mix as f32).to_u32())
+                }
+                _ => LiveValue::None
+            }
+            LiveValue::Vec2(va) => match b {
+                LiveValue::Vec2(vb) => {
+                    LiveValue::Vec2(Vec2::from_lerp(*va, *vb, mix as f32))
+                }
+                _ => LiveValue::None
+            }
+            LiveValue::Vec3(va) => match b {
+                LiveValue::Vec3(vb) => {
+                    LiveValue::Vec3(Vec3::from_lerp(*va, *vb, mix as f32))
+                }
+                _ => LiveValue::None
+            }
+            LiveValue::Id(_) => match b {
+                LiveValue::Id(vb) => {
+                    LiveValue::Id(*vb)
+                }
+                _ => LiveValue::None
+            }
+            _ => LiveValue::None
+        }
+    }
+    
+    fn keyframe_value(index: usize, nodes: &[LiveNode]) -> Option<LiveValue> {
+        if nodes[index].is_value_type() {
+            return Some(nodes[index].value.clone())
+        }
+        nodes.child_by_name(index, live_id!(value).as_field()).map( | index | nodes[index].value.clone())
+    }
+    
+    fn update_spring_value(cx: &mut Cx, index: usize, nodes: &mut [LiveNode], node_iter: Option<usize>, mix: f64, ended: bool, redraw: bool) -> (bool, bool) {
+        let mut first_value: Option<LiveValue> = None;
+        let mut last_value: Option<LiveValue> = None;
+        let mut node_iter = node_iter;
+        while let Some(node_index) = node_iter {
+            if nodes[node_index + 1].is_close() { // at last slot
+                if let (Some(a), Some(b)) = (&first_value, &last_value) {
+                    let new_val = if ended {b.clone()} else {Self::mix_values(a, b, mix)};
+                    if let LiveValue::None = &new_val {
+                        cx.apply_key_frame_cannot_be_interpolated(live_error_origin!(), index, nodes, a, b);
+                        return (ended, redraw)
+                    }
+                    nodes[node_index].value = new_val;
+                }
+                break;
+            }
+            let value = Self::keyframe_value(node_index, nodes);
+            if first_value.is_none() {
+                first_value = value.clone();
+            }
+            last_value = value;
+            node_iter = nodes.next_child(node_index);
+        }
+        (ended, redraw)
+    }
+    
+    // the values we can interpolate as vectors, used to carry velocity between animations
+    fn value_to_vec4(value: &LiveValue) -> Option<Vec4> {
+        match value {
+            LiveValue::Int64(v) => Some(vec4(*v as f32, 0.0, 0.0, 0.0)),
+            LiveValue::Float64(v) => Some(vec4(*v as f32, 0.0, 0.0, 0.0)),
+            LiveValue::Color(v) => Some(Vec4::from_u32(*v)),
+            LiveValue::Vec2(v) => Some(vec4(v.x, v.y, 0.0, 0.0)),
+            LiveValue::Vec3(v) => Some(vec4(v.x, v.y, v.z, 0.0)),
+            _ => None
+        }
+    }
+    
     pub fn last_keyframe_value_from_array(index: usize, nodes: &[LiveNode]) -> Option<usize> {
         if let Some(index) = nodes.last_child(index) {
             if nodes[index].value.is_object() {
@@ -1060,6 +1197,24 @@ impl Animator {
         
         let mut path = Vec::new();
         
+        // springs pick up the velocity of whatever was playing on the track, so keep a copy to measure it
+        let play_index = nodes.child_by_name(index, live_id!(from).as_field()).and_then( | index | {
+            nodes.child_by_name(index, from_id.as_field()).or_else( || nodes.child_by_name(index, live_id!(all).as_field()))
+        });
+        let is_spring = if let Some(play_index) = play_index {
+            matches!(Play::new_apply(cx, ApplyFrom::New, play_index, nodes), Play::Spring {..})
+        }
+        else {
+            false
+        };
+        let mut probe = if is_spring {
+            if let Some(LiveValue::Float64(_)) = state.child_value_by_path(0, &[live_id!(tracks).as_field(), track.as_field(), live_id!(time).as_field()]) {
+                Some(state.clone())
+            }
+            else {None}
+        }
+        else {None};
+        
         state.replace_or_insert_last_node_by_path(0, &[live_id!(tracks).as_field(), track.as_field()], live_object!{
             [track]: {state_id: (state_pair[1]), ended: 0, time: void},
         });
@@ -1091,6 +1246,10 @@ impl Animator {
             state.replace_or_insert_last_node_by_path(0, &[live_id!(tracks).as_field(), track.as_field(), live_id!(redraw).as_field()], nodes.node_slice(index));
         }
         
+        if let Some(index) = nodes.child_by_name(index, live_id!(delay).as_field()) {
+            state.replace_or_insert_last_node_by_path(0, &[live_id!(tracks).as_field(), track.as_field(), live_id!(delay).as_field()], nodes.node_slice(index));
+        }
+        
         path.push(live_id!(state).as_field());
         
         let mut reader = if let Some(reader) = LiveNodeReader::new(index, nodes).child_by_name(live_id!(apply).as_field()) {
@@ -1134,6 +1293,11 @@ impl Animator {
                 let mut timeline = Vec::new();
                 timeline.open_array(live_id!(0));
                 timeline.push_id(live_id!(0), track);
+                if is_spring {
+                    let target = Self::last_keyframe_value_from_array(reader.index(), reader.nodes()).map( | index | reader.nodes()[index].value.clone());
+                    let velocity = self.probe_velocity(cx, probe.as_mut(), &path, &state[last_index].value, target.as_ref());
+                    timeline.push_float64(live_id!(velocity), velocity);
+                }
                 if first_time != 0.0 { // insert first key from the last value
                     timeline.push_live(state.node_slice(last_index));
                 }
@@ -1191,6 +1355,10 @@ impl Animator {
                     let mut timeline = Vec::new();
                     timeline.open_array(LiveId(0));
                     timeline.push_live(live_array!{(track)});
+                    if is_spring {
+                        let velocity = self.probe_velocity(cx, probe.as_mut(), &path, &state[last_index].value, Some(&reader.value));
+                        timeline.push_float64(live_id!(velocity), velocity);
+                    }
                     timeline.push_live(state.node_slice(last_index));
                     timeline.push_live(reader.node_slice());
                     //timeline.last_mut().unwrap().id = live_id!(0); // clean up property id
@@ -1208,4 +1376,46 @@ impl Animator {
         self.next_frame = cx.new_next_frame();
     }
     
+    // measures how fast a value was moving in the interrupted timeline, and expresses that
+    // as the starting velocity of a spring going from current to target
+    fn probe_velocity(&self, cx: &mut Cx, probe: Option<&mut Vec<LiveNode>>, path: &[LiveProp], current: &LiveValue, target: Option<&LiveValue>) -> f64 {
+        let probe = if let Some(probe) = probe {probe} else {return 0.0};
+        let target = if let Some(target) = target {target} else {return 0.0};
+        let index = if let Some(index) = probe.child_by_path(0, path) {index} else {return 0.0};
+        if !probe[index].is_array() {
+            return 0.0
+        }
+        let dt = 1.0 / 60.0;
+        let mut sample = | time: f64 | {
+            Self::update_timeline_value(cx, index, probe, time);
+            probe.last_child(index).and_then( | last | Self::value_to_vec4(&probe[last].value))
+        };
+        let before = sample(self.last_frame_time - dt);
+        let after = sample(self.last_frame_time);
+        if let (Some(before), Some(after), Some(current), Some(target)) = (before, after, Self::value_to_vec4(current), Self::value_to_vec4(target)) {
+            let velocity = (after - before) * (1.0 / dt as f32);
+            let distance = target - current;
+            let len_sq = distance.dot(distance);
+            if len_sq > 1e-12 {
+                return (velocity.dot(distance) / len_sq) as f64
+            }
+        }
+        0.0
+    }
+    
+    pub fn animate_to_live_delayed(&mut self, cx: &mut Cx, state_pair: &[LiveId; 2], delay: f64) {
+        self.animate_to_live(cx, state_pair);
+        if let Some(state) = self.state.as_mut() {
+            let path = &[live_id!(tracks).as_field(), state_pair[0].as_field(), live_id!(delay).as_field()];
+            let delay = delay + match state.child_value_by_path(0, path) {
+                Some(LiveValue::Float64(v)) => *v,
+                Some(LiveValue::Int64(v)) => *v as f64,
+                _ => 0.0
+            };
+            let mut node = Vec::new();
+            node.push_float64(live_id!(delay), delay);
+            state.replace_or_insert_last_node_by_path(0, path, &node);
+        }
+    }
+    
 }
@@ -1,3 +1,7 @@
+pub mod wav;
+
+pub use wav::*;
+
 use {
     crate::{
         makepad_live_id::{LiveId, FromLiveId},
@@ -15,6 +19,7 @@ pub struct AudioDeviceId(pub LiveId);
 #[derive(Clone, Copy, Debug)]
 pub struct AudioInfo{
     pub device_id: AudioDeviceId,
+    pub sample_rate: f64,
     pub time: Option<AudioTime>
 }
 
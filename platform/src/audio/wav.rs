@@ -0,0 +1,286 @@
+use {
+    std::io::{Cursor, Read, Seek, SeekFrom, Write},
+    std::path::Path,
+    super::AudioBuffer,
+};
+
+// RIFF wave files. The reader streams the data chunk in blocks and trusts chunk sizes
+// only as far as the stream goes, the writer writes integer or float pcm.
+//
+// let (buffer, sample_rate) = decode_wav(&std::fs::read("in.wav")?)?;
+// write_wav(Path::new("out.wav"), &buffer, sample_rate as u32, WavFormat::Pcm24)?;
+
+#[derive(Debug)]
+pub enum WavError {
+    NotWav,
+    Unsupported(String),
+    Corrupt(String),
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for WavError {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == std::io::ErrorKind::UnexpectedEof {
+            Self::Corrupt("unexpected end of stream".to_string())
+        }
+        else {
+            Self::Io(err)
+        }
+    }
+}
+
+impl From<WavError> for std::io::Error {
+    fn from(err: WavError) -> Self {
+        match err {
+            WavError::Io(err) => err,
+            WavError::Unsupported(what) => Self::new(std::io::ErrorKind::Unsupported, what),
+            err => Self::new(std::io::ErrorKind::InvalidData, err.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for WavError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::NotWav => write!(f, "not a wav file"),
+            Self::Unsupported(what) => write!(f, "unsupported wav: {}", what),
+            Self::Corrupt(what) => write!(f, "corrupt wav: {}", what),
+            Self::Io(err) => write!(f, "io error: {}", err),
+        }
+    }
+}
+
+// the sample formats the writer produces
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WavFormat {
+    Pcm16,
+    Pcm24,
+    Float32,
+}
+
+impl WavFormat {
+    fn bytes_per_sample(&self) -> usize {
+        match self {
+            Self::Pcm16 => 2,
+            Self::Pcm24 => 3,
+            Self::Float32 => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SampleFormat {
+    Int,
+    Float
+}
+
+pub struct WavReader<R> {
+    reader: R,
+    format: SampleFormat,
+    bits_per_sample: usize,
+    channel_count: usize,
+    sample_rate: f64,
+    data_start: u64,
+    frame_count: usize,
+    frames_read: usize,
+    bytes: Vec<u8>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+impl<R: Read + Seek> WavReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, WavError> {
+        // chunk sizes are only trusted as far as the stream goes
+        let stream_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(WavError::NotWav)
+        }
+        let mut fmt = None;
+        // walk the chunks until we find the data, fmt has to come before it
+        loop {
+            let mut chunk = [0u8; 8];
+            reader.read_exact(&mut chunk)?;
+            let size = read_u32(&chunk, 4) as usize;
+            let available = (stream_len - reader.stream_position()?.min(stream_len)) as usize;
+            match &chunk[0..4] {
+                b"fmt " => {
+                    if size < 16 {
+                        return Err(WavError::Corrupt("fmt chunk too small".to_string()))
+                    }
+                    if size > available {
+                        return Err(WavError::Corrupt("fmt chunk past the end of the stream".to_string()))
+                    }
+                    let mut data = vec![0u8; size];
+                    reader.read_exact(&mut data)?;
+                    if size & 1 != 0 {
+                        reader.seek(SeekFrom::Current(1))?;
+                    }
+                    let mut tag = read_u16(&data, 0);
+                    // extensible carries the real format in the first bytes of the subformat guid
+                    if tag == 0xfffe && size >= 26 {
+                        tag = read_u16(&data, 24);
+                    }
+                    let format = match tag {
+                        1 => SampleFormat::Int,
+                        3 => SampleFormat::Float,
+                        _ => return Err(WavError::Unsupported(format!("format tag {}", tag)))
+                    };
+                    let channel_count = read_u16(&data, 2) as usize;
+                    let sample_rate = read_u32(&data, 4) as f64;
+                    let bits_per_sample = read_u16(&data, 14) as usize;
+                    match (format, bits_per_sample) {
+                        (SampleFormat::Int, 8) | (SampleFormat::Int, 16) | (SampleFormat::Int, 24) | (SampleFormat::Int, 32) |
+                        (SampleFormat::Float, 32) | (SampleFormat::Float, 64) => (),
+                        _ => return Err(WavError::Unsupported(format!("{} bits per sample", bits_per_sample)))
+                    }
+                    if channel_count == 0 {
+                        return Err(WavError::Corrupt("no channels".to_string()))
+                    }
+                    fmt = Some((format, channel_count, sample_rate, bits_per_sample));
+                }
+                b"data" => {
+                    let (format, channel_count, sample_rate, bits_per_sample) = if let Some(fmt) = fmt {fmt} else {
+                        return Err(WavError::Corrupt("data before fmt".to_string()))
+                    };
+                    let data_start = reader.stream_position()?;
+                    // streamed files leave the size at 0xffffffff and truncated ones stop early,
+                    // either way we read what is there
+                    let frame_count = size.min(available) / (channel_count * bits_per_sample / 8);
+                    return Ok(Self {
+                        reader,
+                        format,
+                        bits_per_sample,
+                        channel_count,
+                        sample_rate,
+                        data_start,
+                        frame_count,
+                        frames_read: 0,
+                        bytes: Vec::new(),
+                    })
+                }
+                _ => {
+                    // chunks are padded to even sizes
+                    reader.seek(SeekFrom::Current((size + (size & 1)) as i64))?;
+                }
+            }
+        }
+    }
+
+    pub fn sample_rate(&self) -> f64 {self.sample_rate}
+
+    pub fn channel_count(&self) -> usize {self.channel_count}
+
+    pub fn frame_count(&self) -> usize {self.frame_count}
+
+    // the next block of at most max_frames, None at the end of the data
+    pub fn read_block(&mut self, max_frames: usize) -> Result<Option<AudioBuffer>, WavError> {
+        let frames = max_frames.min(self.frame_count - self.frames_read);
+        if frames == 0 {
+            return Ok(None)
+        }
+        let sample_bytes = self.bits_per_sample / 8;
+        let frame_bytes = sample_bytes * self.channel_count;
+        let mut bytes = std::mem::take(&mut self.bytes);
+        bytes.resize(frames * frame_bytes, 0);
+        self.reader.read_exact(&mut bytes)?;
+        let mut buffer = AudioBuffer::new_with_size(frames, self.channel_count);
+        for c in 0..self.channel_count {
+            let channel = buffer.channel_mut(c);
+            for i in 0..frames {
+                let offset = i * frame_bytes + c * sample_bytes;
+                channel[i] = self.decode_sample(&bytes[offset..offset + sample_bytes]);
+            }
+        }
+        self.bytes = bytes;
+        self.frames_read += frames;
+        Ok(Some(buffer))
+    }
+
+    pub fn rewind(&mut self) -> Result<(), WavError> {
+        self.reader.seek(SeekFrom::Start(self.data_start))?;
+        self.frames_read = 0;
+        Ok(())
+    }
+
+    fn decode_sample(&self, bytes: &[u8]) -> f32 {
+        match (self.format, self.bits_per_sample) {
+            (SampleFormat::Int, 8) => (bytes[0] as f32 - 128.0) / 128.0,
+            (SampleFormat::Int, 16) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            (SampleFormat::Int, 24) => (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8388608.0,
+            (SampleFormat::Int, 32) => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2147483648.0,
+            (SampleFormat::Float, 32) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            (SampleFormat::Float, 64) => f64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]) as f32,
+            _ => 0.0
+        }
+    }
+}
+
+// a whole file at once, returns the samples and the sample rate
+pub fn decode_wav(data: &[u8]) -> Result<(AudioBuffer, f64), WavError> {
+    let mut reader = WavReader::new(Cursor::new(data))?;
+    let buffer = match reader.read_block(reader.frame_count())? {
+        Some(buffer) => buffer,
+        None => AudioBuffer::new_with_size(0, reader.channel_count())
+    };
+    Ok((buffer, reader.sample_rate()))
+}
+
+pub fn encode_wav(buffer: &AudioBuffer, sample_rate: u32, format: WavFormat) -> Vec<u8> {
+    let channel_count = buffer.channel_count();
+    let frame_count = buffer.frame_count();
+    let bytes_per_sample = format.bytes_per_sample();
+    let data_size = frame_count * channel_count * bytes_per_sample;
+    let block_align = channel_count * bytes_per_sample;
+
+    let mut out = Vec::with_capacity(44 + data_size);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&((36 + data_size) as u32).to_le_bytes());
+    out.extend_from_slice(b"WAVE");
+
+    out.extend_from_slice(b"fmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    // 1 is integer pcm, 3 is ieee float
+    let format_tag: u16 = if let WavFormat::Float32 = format {3} else {1};
+    out.extend_from_slice(&format_tag.to_le_bytes());
+    out.extend_from_slice(&(channel_count as u16).to_le_bytes());
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    out.extend_from_slice(&(block_align as u16).to_le_bytes());
+    out.extend_from_slice(&((bytes_per_sample * 8) as u16).to_le_bytes());
+
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&(data_size as u32).to_le_bytes());
+    for i in 0..frame_count {
+        for c in 0..channel_count {
+            let sample = buffer.channel(c)[i];
+            match format {
+                WavFormat::Pcm16 => {
+                    let v = (sample.max(-1.0).min(1.0) * 32767.0).round() as i16;
+                    out.extend_from_slice(&v.to_le_bytes());
+                }
+                WavFormat::Pcm24 => {
+                    let v = (sample.max(-1.0).min(1.0) as f64 * 8388607.0).round() as i32;
+                    out.extend_from_slice(&v.to_le_bytes()[0..3]);
+                }
+                WavFormat::Float32 => {
+                    out.extend_from_slice(&sample.to_le_bytes());
+                }
+            }
+        }
+    }
+    out
+}
+
+pub fn write_wav(path: &Path, buffer: &AudioBuffer, sample_rate: u32, format: WavFormat) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(&encode_wav(buffer, sample_rate, format))
+}
@@ -28,10 +28,12 @@ use {
             Trigger,
             CxKeyboard,
             NextFrame,
+            ReadbackEvent,
         },
         menu::{
             CxCommandSetting,
-            MenuCommand
+            MenuCommand,
+            Menu
         },
         cx_api::CxOsOp,
         area::Area,
@@ -39,7 +41,7 @@ use {
         window::CxWindowPool,
         draw_list::CxDrawListPool,
         pass::CxPassPool,
-        texture::CxTexturePool,
+        texture::{CxTexturePool, TextureId},
         geometry::{
             Geometry,
             CxGeometryPool,
@@ -85,6 +87,9 @@ pub struct Cx {
     
     pub (crate) new_next_frames: HashSet<NextFrame>,
     
+    pub (crate) texture_readbacks: Vec<TextureId>,
+    pub (crate) readbacks: Vec<ReadbackEvent>,
+    
     pub (crate) dependencies: HashMap<String, CxDependency>,
     
     pub (crate) triggers: HashMap<Area, Vec<Trigger >>,
@@ -93,8 +98,9 @@ pub struct Cx {
     pub live_file_changes: Option<std::sync::mpsc::Receiver<Vec<LiveFileChange>>>,
     pub shader_registry: ShaderRegistry,
     
-    #[allow(dead_code)]
     pub (crate) command_settings: HashMap<MenuCommand, CxCommandSetting>,
+    pub (crate) window_menu: Option<Menu>,
+    pub (crate) menu_commands: Vec<MenuCommand>,
     
     pub os: CxOs,
     // (cratethis cuts the compiletime of an end-user application in half
@@ -239,6 +245,9 @@ impl Cx {
             
             new_next_frames: Default::default(),
             
+            texture_readbacks: Vec::new(),
+            readbacks: Vec::new(),
+            
             dependencies: Default::default(),
             
             triggers: Default::default(),
@@ -248,6 +257,8 @@ impl Cx {
             shader_registry: ShaderRegistry::new(),
             
             command_settings: HashMap::new(),
+            window_menu: None,
+            menu_commands: Vec::new(),
             
             os: CxOs::default(),
             
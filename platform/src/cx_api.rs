@@ -31,6 +31,7 @@ use {
         },
         menu::{
             Menu,
+            MenuCommand,
         },
         pass::{
             PassId,
@@ -110,6 +111,12 @@ impl Cx {
         self.platform_ops.push(CxOsOp::UpdateMenu(menu));
     }
     
+    pub fn window_menu(&self) -> Option<&Menu> {self.window_menu.as_ref()}
+    
+    pub fn send_menu_command(&mut self, command: MenuCommand) {
+        self.menu_commands.push(command);
+    }
+    
     pub fn push_unique_platform_op(&mut self, op: CxOsOp) {
         if self.platform_ops.iter().find( | o | **o == op).is_none() {
             self.platform_ops.push(op);
@@ -347,6 +354,10 @@ impl Cx {
         self.keyboard.has_key_focus(focus_area)
     }
     
+    pub fn key_focus(&self) -> Area {
+        self.keyboard.key_focus
+    }
+    
     pub fn new_next_frame(&mut self) -> NextFrame {
         let res = NextFrame(self.next_frame_id);
         self.next_frame_id += 1;
@@ -11,8 +11,10 @@ use {
         makepad_live_compiler::{LiveValue, LiveFieldKind, LiveNode, LivePtr, LiveNodeSliceApi},
         makepad_shader_compiler::*,
         makepad_live_id::*,
+        makepad_error_log::*,
         live_traits::*,
         draw_vars::DrawVars,
+        texture::{TextureSampler, TextureDesc},
         os::CxOsDrawShader,
         cx::Cx
     }
@@ -264,7 +266,9 @@ impl DrawShaderInputs {
 #[derive(Clone)]
 pub struct DrawShaderTextureInput {
     pub id: LiveId,
-    pub ty: ShaderTy
+    pub ty: ShaderTy,
+    // the sampler the shader selected for the texture, otherwise the one of the texture itself is used
+    pub sampler: Option<TextureSampler>
 }
 
 #[derive(Clone)]
@@ -353,9 +357,24 @@ impl CxDrawShaderMapping {
                     }
                 }
                 DrawShaderFieldKind::Texture {..} => {
+                    let sampler = draw_shader_def.samplers.iter()
+                        .find( | (texture, _) | *texture == field.ident)
+                        .and_then( | (_, preset) | {
+                        let sampler = TextureSampler::from_preset(preset.0);
+                        if sampler.is_none() {
+                            error!("Unknown sampler preset {} for texture {}", preset, field.ident);
+                        }
+                        let desc = TextureDesc {sampler, ..Default::default()};
+                        if let Err(err) = desc.check_supported() {
+                            error!("{}, texture {} keeps the sampler of the shader", err, field.ident);
+                            return None
+                        }
+                        sampler
+                    });
                     textures.push(DrawShaderTextureInput {
                         ty:ty,
                         id: field.ident.0,
+                        sampler
                     });
                 }
                 _ => ()
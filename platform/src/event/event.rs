@@ -1,5 +1,6 @@
 use {
     std::{
+        rc::Rc,
         collections::{HashSet, HashMap}
     },
     crate::{
@@ -21,6 +22,8 @@ use {
         video::VideoInputsEvent,
         draw_list::DrawListId,
         menu::MenuCommand,
+        texture::{Texture, TextureId},
+        pass::{Pass, PassId},
     },
 };
 
@@ -63,6 +66,7 @@ pub enum Event {
     KeyDown(KeyEvent),
     KeyUp(KeyEvent),
     TextInput(TextInputEvent),
+    TextComposition(TextCompositionEvent),
     TextCopy(TextClipboardEvent),
     TextCut(TextClipboardEvent),
     
@@ -73,6 +77,7 @@ pub enum Event {
     AudioDevices(AudioDevicesEvent),
     MidiPorts(MidiPortsEvent),
     VideoInputs(VideoInputsEvent),
+    Readback(ReadbackEvent),
     NetworkResponses(Vec<NetworkResponseEvent>),
 
     #[cfg(target_arch = "wasm32")]
@@ -86,6 +91,7 @@ pub enum Hit{
     KeyUp(KeyEvent),
     Trigger(TriggerHitEvent),
     TextInput(TextInputEvent),
+    TextComposition(TextCompositionEvent),
     TextCopy(TextClipboardEvent),
     TextCut(TextClipboardEvent),
     
@@ -181,6 +187,44 @@ pub struct NextFrameEvent {
     pub set: HashSet<NextFrame>
 }
 
+/// Where the pixels of a readback came from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReadbackSource {
+    Texture(TextureId),
+    Pass(PassId),
+}
+
+/// The pixels asked for with `Texture::read_back` or `Pass::read_back`, top row
+/// first and packed as 0xAARRGGBB like image textures. Rendered content keeps
+/// the premultiplied alpha it was blended with. Backends that cant read the
+/// pixels back answer with an error and a size of zero.
+#[derive(Clone, Debug)]
+pub struct ReadbackEvent {
+    pub source: ReadbackSource,
+    pub width: usize,
+    pub height: usize,
+    pub data: Result<Rc<Vec<u32>>, String>,
+}
+
+impl ReadbackEvent {
+    pub fn error(source: ReadbackSource, err: String) -> Self {
+        Self {
+            source,
+            width: 0,
+            height: 0,
+            data: Err(err)
+        }
+    }
+    
+    pub fn is_texture(&self, texture: &Texture) -> bool {
+        self.source == ReadbackSource::Texture(texture.texture_id())
+    }
+    
+    pub fn is_pass(&self, pass: &Pass) -> bool {
+        self.source == ReadbackSource::Pass(pass.pass_id())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct TimerEvent {
     pub timer_id: u64
@@ -116,6 +116,15 @@ pub struct TextInputEvent {
     pub was_paste: bool
 }
 
+// the pre-edit text of an input method while the user is composing.
+// cursor is a char offset into text, an empty text ends the composition.
+// the composed result arrives as a normal TextInput event
+#[derive(Clone, Debug, Default)]
+pub struct TextCompositionEvent {
+    pub text: String,
+    pub cursor: usize,
+}
+
 #[derive(Clone, Debug)]
 pub struct TextClipboardEvent {
     pub response: Rc<RefCell<Option<String>>>
@@ -132,7 +132,10 @@ pub use {
             RectArea,
             InstanceArea
         },
-        menu::MenuCommand,
+        menu::{
+            MenuCommand,
+            CxCommandSetting
+        },
         thread::Signal,
         event::{
             HttpRequest,
@@ -164,10 +167,13 @@ pub use {
             WindowGeomChangeEvent,
             WindowMovedEvent,
             NextFrameEvent,
+            ReadbackEvent,
+            ReadbackSource,
             TimerEvent,
             KeyEvent,
             KeyFocusEvent,
             TextInputEvent,
+            TextCompositionEvent,
             TextClipboardEvent,
             WindowCloseRequestedEvent,
             WindowClosedEvent,
@@ -201,7 +207,11 @@ pub use {
             Texture,
             TextureId,
             TextureFormat,
-            TextureDesc
+            TextureDesc,
+            TextureRect,
+            TextureSampler,
+            TextureFilter,
+            TextureWrap
         },
         live_prims::{
             LiveDependency,
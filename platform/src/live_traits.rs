@@ -154,6 +154,16 @@ impl<T, const N:usize> LiveRead for [T;N]  where T: LiveRead {
     }
 } 
 
+impl<T> LiveRead for Vec<T> where T: LiveRead {
+    fn live_read_to(&self, id:LiveId, out:&mut Vec<LiveNode>){
+        out.open_array(id);
+        for (i, item) in self.iter().enumerate(){
+            item.live_read_to(LiveId(i as u64), out);
+        }
+        out.close();
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum ApplyFrom {
     NewFromDoc {file_id: LiveFileId}, // newed from DSL
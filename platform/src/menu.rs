@@ -1,12 +1,13 @@
 use {
     crate::{
         makepad_live_id::LiveId,
+        cx::Cx,
         event::KeyCode
     },
 };
 
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct CxCommandSetting {
     pub shift: bool,
     pub key_code: KeyCode,
@@ -22,8 +23,10 @@ impl From<LiveId> for MenuCommand {
 }
 
 impl MenuCommand{
-    //pub fn from_id(id:LiveId)->Self{Self(id.0)}
-    /*
+    pub fn setting(&self, cx:&Cx)->CxCommandSetting{
+        if let Some(s) = cx.command_settings.get(self){*s}else{CxCommandSetting::default()}
+    }
+    
     pub fn set_enabled(&self, cx:&mut Cx, enabled:bool)->Self{
         let mut s = if let Some(s) = cx.command_settings.get(self){*s}else{CxCommandSetting::default()};
         s.enabled = enabled;
@@ -45,7 +48,7 @@ impl MenuCommand{
         s.key_code = key_code;
         cx.command_settings.insert(*self, s);
         *self
-    }*/
+    }
 }
 
 #[derive(PartialEq, Clone)]
@@ -212,6 +212,7 @@ impl AudioUnitAccess {
                             if let Some(audio_input_cb) = &mut *audio_input_cb.lock().unwrap() {
                                 return audio_input_cb(AudioInfo{
                                     device_id, 
+                                    sample_rate: 48000.0,
                                     time: Some(time)
                                 }, output)
                             }
@@ -280,6 +281,7 @@ impl AudioUnitAccess {
                             if let Some(audio_output_cb) = &mut *audio_output_cb.lock().unwrap() {
                                 audio_output_cb(AudioInfo{
                                     device_id, 
+                                    sample_rate: 48000.0,
                                     time:Some(time)
                                 }, output)
                             }
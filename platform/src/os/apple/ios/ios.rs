@@ -87,6 +87,9 @@ impl Cx {
                 }
             }
         }
+        self.read_back_image_textures();
+        self.fail_gpu_readbacks("Metal");
+        self.call_readback_events();
     }
 
     pub(crate) fn handle_networking_events(&mut self) {
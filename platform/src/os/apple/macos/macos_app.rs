@@ -191,13 +191,13 @@ impl MacosApp {
                 },
                 Menu::Sub {name, items} => {
                     let sub_menu: ObjcId = msg_send![class!(NSMenu), new];
-                    let () = msg_send![sub_menu, setTitle: str_to_nsstring(name)];
+                    let () = msg_send![sub_menu, setTitle: str_to_nsstring(&name.replacen('&', "", 1))];
                     let () = msg_send![sub_menu, setAutoenablesItems: NO];
                     let () = msg_send![sub_menu, setDelegate: delegate];
                     // append item to parebt
                     let sub_item: ObjcId = msg_send![
                         parent_menu,
-                        addItemWithTitle: str_to_nsstring(name)
+                        addItemWithTitle: str_to_nsstring(&name.replacen('&', "", 1))
                         action: nil
                         keyEquivalent: str_to_nsstring("")
                     ];
@@ -216,7 +216,7 @@ impl MacosApp {
                     };
                     let sub_item: ObjcId = msg_send![
                         parent_menu,
-                        addItemWithTitle: str_to_nsstring(name)
+                        addItemWithTitle: str_to_nsstring(&name.replacen('&', "", 1))
                         action: sel!(menuAction:)
                         keyEquivalent: str_to_nsstring(keycode_to_menu_key(settings.key_code, settings.shift))
                     ];
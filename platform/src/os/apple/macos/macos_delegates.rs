@@ -510,7 +510,7 @@ pub fn define_cocoa_view_class() -> *const Class {
         }
     }
     
-    extern fn set_marked_text(this: &mut Object, _sel: Sel, string: ObjcId, _selected_range: NSRange, _replacement_range: NSRange) {
+    extern fn set_marked_text(this: &mut Object, _sel: Sel, string: ObjcId, selected_range: NSRange, _replacement_range: NSRange) {
         unsafe {
             let marked_text_ref: &mut ObjcId = this.get_mut_ivar("markedText");
             let _: () = msg_send![(*marked_text_ref), release];
@@ -522,12 +522,18 @@ pub fn define_cocoa_view_class() -> *const Class {
                 marked_text.init_with_string(string);
             };
             *marked_text_ref = marked_text;
+            // the selected range is in utf16 units, for composition text thats close enough to chars
+            let text = nsstring_to_string(marked_text.mutable_string());
+            get_cocoa_window(this).send_text_composition(text, selected_range.location as usize);
         }
     }
     
     extern fn unmark_text(this: &Object, _sel: Sel) {
         unsafe {
             let marked_text: ObjcId = *this.get_ivar("markedText");
+            if marked_text.length() > 0 {
+                get_cocoa_window(this).send_text_composition(String::new(), 0);
+            }
             let mutable_string = marked_text.mutable_string();
             let _: () = msg_send![mutable_string, setString: get_apple_class_global().const_empty_string.as_id()];
             let input_context: ObjcId = msg_send![this, inputContext];
@@ -12,6 +12,7 @@ use {
             WindowCloseRequestedEvent,
             WindowClosedEvent,
             TextInputEvent,
+            TextCompositionEvent,
             KeyEvent,
             DragEvent,
             DropEvent,
@@ -39,6 +40,7 @@ pub enum MacosEvent {
     WindowDragQuery(WindowDragQueryEvent),
     WindowCloseRequested(WindowCloseRequestedEvent),
     TextInput(TextInputEvent),
+    TextComposition(TextCompositionEvent),
     Drag(DragEvent),
     Drop(DropEvent),
     DragEnd,
@@ -36,6 +36,7 @@ use {
             WindowCloseRequestedEvent,
             WindowClosedEvent,
             TextInputEvent,
+            TextCompositionEvent,
             DragItem,
         },
     }
@@ -421,6 +422,13 @@ impl MacosWindow {
         }))
     }
     
+    pub fn send_text_composition(&mut self, text: String, cursor: usize) {
+        self.do_callback(MacosEvent::TextComposition(TextCompositionEvent {
+            text,
+            cursor
+        }))
+    }
+    
     #[cfg(target_os = "macos")]
     pub fn start_dragging(&mut self, ns_event: ObjcId, items: Vec<DragItem>) {
         let mut dragged_files = Vec::new();
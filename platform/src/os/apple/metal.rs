@@ -28,6 +28,7 @@ use {
         texture::{
             TextureFormat,
             TextureDesc,
+            TextureRect,
         },
     },
     std::sync::{
@@ -207,12 +208,15 @@ impl Cx {
                             &cxtexture.desc,
                         );
                     }
-                    else if cxtexture.update_image {
+                    else if cxtexture.update_image || cxtexture.update_rect.is_some() {
+                        let rect = if cxtexture.update_image {None} else {cxtexture.update_rect};
                         cxtexture.update_image = false;
+                        cxtexture.update_rect = None;
                         cxtexture.os.update_normal_texture(
                             metal_cx,
                             &cxtexture.desc,
-                            &cxtexture.image_u32
+                            &cxtexture.image_u32,
+                            rect
                         );
                     }
                     
@@ -768,12 +772,34 @@ pub struct CxOsTexture {
 
 impl CxOsTexture {
     
+    // bgra images and the render formats are uploaded here, the shaders fix their sampler
+    pub fn check_desc(desc: &TextureDesc) -> Result<(), String> {
+        match desc.format {
+            TextureFormat::ImageR8 |
+            TextureFormat::ImageRG8 |
+            TextureFormat::ImageRf16 |
+            TextureFormat::ImageRGBAf16 |
+            TextureFormat::ImageRGBAf32 |
+            TextureFormat::DepthTexture32 => {
+                return Err(format!("Texture format {:?} is not supported by the metal backend", desc.format))
+            }
+            _ => ()
+        }
+        if desc.sampler.is_some() {
+            return Err("Texture samplers are not supported by the metal backend".to_string())
+        }
+        if desc.mipmaps {
+            return Err("Texture mipmaps are not supported by the metal backend".to_string())
+        }
+        Ok(())
+    }
     
     fn update_normal_texture(
         &mut self,
         metal_cx: &MetalCx,
         desc: &TextureDesc,
         data: &[u32],
+        rect: Option<TextureRect>,
     ) {
         // we need a width/height for this one.
         if desc.width.is_none() || desc.height.is_none() {
@@ -793,7 +819,10 @@ impl CxOsTexture {
                     return
                 }
             }
-            _ => panic!(),
+            _ => {
+                error!("Texture format {:?} is not supported by the metal backend yet", desc.format);
+                return
+            }
         }
         
         let need_alloc = if let Some(inner) = &self.inner {
@@ -856,17 +885,30 @@ impl CxOsTexture {
         
         let inner = self.inner.as_ref().unwrap();
         
+        // a new texture needs all of it, otherwise only the changed rect
+        let (x, y, w, h) = match rect {
+            Some(rect) if !need_alloc => {
+                let x = (rect.x as u64).min(width);
+                let y = (rect.y as u64).min(height);
+                (x, y, (rect.width as u64).min(width - x), (rect.height as u64).min(height - y))
+            }
+            _ => (0, 0, width, height)
+        };
+        if w == 0 || h == 0 {
+            return
+        }
+        
         // ok now update the texture
         let region = MTLRegion {
-            origin: MTLOrigin {x: 0, y: 0, z: 0},
-            size: MTLSize {width: width as u64, height: height as u64, depth: 1}
+            origin: MTLOrigin {x, y, z: 0},
+            size: MTLSize {width: w, height: h, depth: 1}
         };
         
         let () = unsafe {msg_send![
             inner.texture.as_id(),
             replaceRegion: region
             mipmapLevel: 0
-            withBytes: data.as_ptr() as *const std::ffi::c_void
+            withBytes: data[(y * width + x) as usize..].as_ptr() as *const std::ffi::c_void
             bytesPerRow: (width * std::mem::size_of::<u32>() as u64)
         ]};
     }
@@ -1,9 +1,13 @@
 
 use {
-    std::collections::{HashSet, HashMap},
+    std::{
+        rc::Rc,
+        collections::{HashSet, HashMap},
+    },
     crate::{
         makepad_error_log::*,
         cx::Cx,
+        texture::TextureFormat,
         pass::{
             PassId,
             CxPassParent
@@ -14,6 +18,8 @@ use {
             Event,
             KeyFocusEvent,
             NextFrameEvent,
+            ReadbackEvent,
+            ReadbackSource,
         },
     }
 };
@@ -125,16 +131,22 @@ impl Cx {
     }
     
     pub fn handle_triggers(&mut self) {
-        // post op events like signals, triggers and key-focus
+        // post op events like signals, triggers, menu commands and key-focus
         let mut counter = 0;
-        while self.triggers.len() != 0 {
+        while self.triggers.len() != 0 || self.menu_commands.len() != 0 {
             counter += 1;
-            let mut triggers = HashMap::new();
-            std::mem::swap(&mut self.triggers, &mut triggers);
-            self.inner_call_event_handler(&Event::Trigger(TriggerEvent {
-                triggers: triggers,
-            }));
-            self.inner_key_focus_change();
+            for command in std::mem::take(&mut self.menu_commands) {
+                self.inner_call_event_handler(&Event::MenuCommand(command));
+                self.inner_key_focus_change();
+            }
+            if self.triggers.len() != 0 {
+                let mut triggers = HashMap::new();
+                std::mem::swap(&mut self.triggers, &mut triggers);
+                self.inner_call_event_handler(&Event::Trigger(TriggerEvent {
+                    triggers: triggers,
+                }));
+                self.inner_key_focus_change();
+            }
             if counter > 100 {
                 error!("Trigger feedback loop detected");
                 break
@@ -169,4 +181,48 @@ impl Cx {
         std::mem::swap(&mut set, &mut self.new_next_frames);
         self.call_event_handler(&Event::NextFrame(NextFrameEvent {set, time: time, frame: self.repaint_id}));
     }
+    
+    // image textures hand out the pixels they were given on every backend,
+    // the backend reads the render targets that remain from the gpu
+    pub (crate) fn read_back_image_textures(&mut self) {
+        let textures = &self.textures;
+        let readbacks = &mut self.readbacks;
+        self.texture_readbacks.retain( | texture_id | {
+            let cxtexture = &textures[*texture_id];
+            match (cxtexture.desc.format, cxtexture.desc.width, cxtexture.desc.height) {
+                (TextureFormat::Default | TextureFormat::ImageBGRA, Some(width), Some(height)) if cxtexture.image_u32.len() != 0 => {
+                    readbacks.push(ReadbackEvent {
+                        source: ReadbackSource::Texture(*texture_id),
+                        width,
+                        height,
+                        data: Ok(Rc::new(cxtexture.image_u32.clone()))
+                    });
+                    false
+                }
+                _ => true
+            }
+        });
+    }
+    
+    // for backends that cant read from the gpu, the requests left after a repaint get an error
+    #[allow(dead_code)]
+    pub (crate) fn fail_gpu_readbacks(&mut self, backend: &str) {
+        for texture_id in std::mem::take(&mut self.texture_readbacks) {
+            let err = format!("read_back of render targets is not supported on {}", backend);
+            self.readbacks.push(ReadbackEvent::error(ReadbackSource::Texture(texture_id), err));
+        }
+        for pass_id in self.passes.id_iter() {
+            if std::mem::take(&mut self.passes[pass_id].read_back) {
+                let err = format!("read_back of passes is not supported on {}", backend);
+                self.readbacks.push(ReadbackEvent::error(ReadbackSource::Pass(pass_id), err));
+            }
+        }
+    }
+    
+    #[allow(dead_code)]
+    pub (crate) fn call_readback_events(&mut self) {
+        for readback in std::mem::take(&mut self.readbacks) {
+            self.call_event_handler(&Event::Readback(readback));
+        }
+    }
 }
@@ -21,6 +21,7 @@ struct AlsaAudioDevice {
     device_handle: *mut snd_pcm_t,
     channel_count: usize,
     frame_count: usize,
+    sample_rate: f64,
     interleaved: Vec<f32>,
     _buffer_size: usize,
 }
@@ -215,6 +216,7 @@ impl AlsaAudioAccess {
                             fbox(
                                 AudioInfo {
                                     device_id,
+                                    sample_rate: device.sample_rate,
                                     time: None,
                                 },
                                 &audio_buffer
@@ -274,6 +276,7 @@ impl AlsaAudioAccess {
                             fbox(
                                 AudioInfo {
                                     device_id,
+                                    sample_rate: device.sample_rate,
                                     time: None,
                                 },
                                 &mut audio_buffer
@@ -336,6 +339,7 @@ impl AlsaAudioDevice {
                 device_handle: handle,
                 channel_count: channel_count as usize,
                 frame_count: frame_count as usize,
+                sample_rate: rate as f64,
                 _buffer_size: buffer_size as usize,
             }, AlsaAudioDeviceRef {
                 device_id,
@@ -8,6 +8,7 @@ use {
     super::{
         alsa_sys::*,
         alsa_audio::AlsaError,
+        virtual_media::VirtualMediaAccess,
     },
     crate::{
         makepad_live_id::*,
@@ -18,7 +19,7 @@ use {
  
 
 #[derive(Clone)]
-pub struct OsMidiOutput(pub (crate) Arc<Mutex<AlsaMidiAccess >>);
+pub struct OsMidiOutput(pub (crate) Arc<Mutex<AlsaMidiAccess >>, pub (crate) Arc<Mutex<VirtualMediaAccess >>);
 
 pub struct OsMidiInput(mpsc::Receiver<(MidiPortId, MidiData) >);
 
@@ -27,6 +28,7 @@ impl OsMidiOutput {
         // alright lets send some midi.
         // send some midi here
         let _ = self.0.lock().unwrap().send_midi(port_id, d);
+        self.1.lock().unwrap().send_midi(port_id, d);
     }
 }
 
@@ -39,7 +41,7 @@ impl OsMidiInput {
     }
 }
 
-type InputSenders = Arc<Mutex<Vec<mpsc::Sender<(MidiPortId, MidiData) >> >>;
+pub (crate) type InputSenders = Arc<Mutex<Vec<mpsc::Sender<(MidiPortId, MidiData) >> >>;
 
 #[derive(Clone)]
 pub struct AlsaMidiOutput {
@@ -407,6 +409,10 @@ impl AlsaMidiAccess {
         None
     }
     
+    pub fn input_senders(&self) -> InputSenders {
+        self.input_senders.clone()
+    }
+    
     pub fn create_midi_input(&self) -> MidiInput {
         let senders = self.input_senders.clone();
         let (send, recv) = mpsc::channel();
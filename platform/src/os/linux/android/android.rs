@@ -452,6 +452,8 @@ impl Cx {
             zbias_step,
         );
         
+        self.opengl_read_back_pass(pass_id, self.os.display_size.x as usize, self.os.display_size.y as usize);
+        
         //to_java.swap_buffers();
         //unsafe {
         //direct_app.drm.swap_buffers_and_wait(&direct_app.egl);
@@ -484,8 +486,8 @@ impl Cx {
                 }
             }
         }
-        
-        
+        self.opengl_read_back_textures();
+        self.call_readback_events();
     }
     
     fn panning_adjust_for_text_ime(&mut self, android_ime_height: u32) {
@@ -210,6 +210,7 @@ impl AndroidAudioOutput {
             data.audio_buffer.resize(frame_count as usize, data.channel_count);
             output_fn(AudioInfo {
                 device_id: data.device_id,
+                sample_rate: 48000.0,
                 time: None
             }, &mut data.audio_buffer);
             let output = std::slice::from_raw_parts_mut(audio_data as *mut f32, frame_count as usize * data.actual_channel_count);
@@ -281,6 +282,7 @@ impl AndroidAudioInput {
             data.audio_buffer.copy_from_interleaved(data.channel_count, &input_data);
             input_fn(AudioInfo {
                 device_id: data.device_id,
+                sample_rate: 48000.0,
                 time: None
             }, &data.audio_buffer);
         }
@@ -240,6 +240,8 @@ impl Cx {
             zbias_step,
         );
         
+        self.opengl_read_back_pass(pass_id, direct_app.drm.width as usize, direct_app.drm.height as usize);
+        
         unsafe {
             direct_app.drm.swap_buffers_and_wait(&direct_app.egl);
         }
@@ -263,6 +265,8 @@ impl Cx {
                 }
             }
         }
+        self.opengl_read_back_textures();
+        self.call_readback_events();
     }
     
     fn handle_platform_ops(&mut self, direct_app: &mut DirectApp) -> EventFlow {
@@ -5,6 +5,7 @@ use {
         alsa_audio::AlsaAudioAccess,
         pulse_audio::PulseAudioAccess,
         alsa_midi::*,
+        virtual_media::*,
     },
     crate::{
         cx::Cx,
@@ -22,7 +23,14 @@ impl Cx {
         if self.os.media.audio_change.check_and_clear() {
             // alright so. if we 'failed' opening a device here
             // what do we do. we could flag our device as 'failed' on the desc
-            let mut descs = self.os.media.alsa_audio().lock().unwrap().get_updated_descs();
+            // virtual devices go first so their defaults win
+            let mut descs = if let Some(virtual_media) = &self.os.media.virtual_media {
+                virtual_media.lock().unwrap().get_updated_audio_descs()
+            }
+            else {
+                Vec::new()
+            };
+            descs.extend(self.os.media.alsa_audio().lock().unwrap().get_updated_descs());
             let descs2 = self.os.media.pulse_audio().lock().unwrap().get_updated_descs();
             descs.extend(descs2);
             self.call_event_handler(&Event::AudioDevices(AudioDevicesEvent {
@@ -30,7 +38,13 @@ impl Cx {
             }));
         }
         if self.os.media.alsa_midi_change.check_and_clear() {
-            let descs = self.os.media.alsa_midi().lock().unwrap().get_updated_descs();
+            let mut descs = if let Some(virtual_media) = &self.os.media.virtual_media {
+                virtual_media.lock().unwrap().get_updated_midi_descs()
+            }
+            else {
+                Vec::new()
+            };
+            descs.extend(self.os.media.alsa_midi().lock().unwrap().get_updated_descs());
             self.call_event_handler(&Event::MidiPorts(MidiPortsEvent {
                 descs,
             }));
@@ -45,6 +59,7 @@ pub struct CxLinuxMedia {
     pub (crate) audio_change: Signal,
     pub (crate) alsa_midi: Option<Arc<Mutex<AlsaMidiAccess >> >,
     pub (crate) alsa_midi_change: Signal,
+    pub (crate) virtual_media: Option<Arc<Mutex<VirtualMediaAccess >> >,
 }
 
 impl CxLinuxMedia {
@@ -68,8 +83,47 @@ impl CxLinuxMedia {
         }
         self.alsa_midi.as_ref().unwrap().clone()
     }
+    
+    pub fn virtual_media(&mut self) -> Arc<Mutex<VirtualMediaAccess >> {
+        if self.virtual_media.is_none() {
+            let input_senders = self.alsa_midi().lock().unwrap().input_senders();
+            self.virtual_media = Some(VirtualMediaAccess::new(
+                self.audio_change.clone(),
+                self.alsa_midi_change.clone(),
+                &self.alsa_audio().lock().unwrap(),
+                input_senders
+            ));
+        }
+        self.virtual_media.as_ref().unwrap().clone()
+    }
 
+}
 
+// Virtual devices, see virtual_media.rs
+impl Cx {
+    pub fn add_virtual_audio_output(&mut self, output: VirtualAudioOutput) -> (AudioDeviceId, VirtualAudioRecording) {
+        self.os.media.virtual_media().lock().unwrap().add_audio_output(output)
+    }
+    
+    pub fn add_virtual_audio_input(&mut self, input: VirtualAudioInput) -> AudioDeviceId {
+        self.os.media.virtual_media().lock().unwrap().add_audio_input(input)
+    }
+    
+    pub fn remove_virtual_audio_device(&mut self, device_id: AudioDeviceId) {
+        self.os.media.virtual_media().lock().unwrap().remove_audio_device(device_id)
+    }
+    
+    pub fn add_virtual_midi_input(&mut self, input: VirtualMidiInput) -> (MidiPortId, VirtualMidiSender) {
+        self.os.media.virtual_media().lock().unwrap().add_midi_input(input)
+    }
+    
+    pub fn add_virtual_midi_output(&mut self, name: &str) -> (MidiPortId, VirtualMidiRecording) {
+        self.os.media.virtual_media().lock().unwrap().add_midi_output(name)
+    }
+    
+    pub fn remove_virtual_midi_port(&mut self, port_id: MidiPortId) {
+        self.os.media.virtual_media().lock().unwrap().remove_midi_port(port_id)
+    }
 }
 
 impl CxMediaApi for Cx { 
@@ -79,7 +133,7 @@ impl CxMediaApi for Cx {
     }
     
     fn midi_output(&mut self) -> MidiOutput {
-        MidiOutput(Some(OsMidiOutput(self.os.media.alsa_midi())))
+        MidiOutput(Some(OsMidiOutput(self.os.media.alsa_midi(), self.os.media.virtual_media())))
     }
     
     fn midi_reset(&mut self) {
@@ -87,20 +141,32 @@ impl CxMediaApi for Cx {
     
     fn use_midi_inputs(&mut self, ports: &[MidiPortId]) {
         self.os.media.alsa_midi().lock().unwrap().use_midi_inputs(ports);
+        if let Some(virtual_media) = &self.os.media.virtual_media {
+            virtual_media.lock().unwrap().use_midi_inputs(ports);
+        }
     }
     
     fn use_midi_outputs(&mut self, ports: &[MidiPortId]) {
         self.os.media.alsa_midi().lock().unwrap().use_midi_outputs(ports);
+        if let Some(virtual_media) = &self.os.media.virtual_media {
+            virtual_media.lock().unwrap().use_midi_outputs(ports);
+        }
     }
     
     fn use_audio_inputs(&mut self, devices: &[AudioDeviceId]) {
         self.os.media.alsa_audio().lock().unwrap().use_audio_inputs(devices);
         self.os.media.pulse_audio().lock().unwrap().use_audio_inputs(devices);
+        if let Some(virtual_media) = &self.os.media.virtual_media {
+            virtual_media.lock().unwrap().use_audio_inputs(devices);
+        }
     }
     
     fn use_audio_outputs(&mut self, devices: &[AudioDeviceId]) {
         self.os.media.alsa_audio().lock().unwrap().use_audio_outputs(devices);
         self.os.media.pulse_audio().lock().unwrap().use_audio_outputs(devices);
+        if let Some(virtual_media) = &self.os.media.virtual_media {
+            virtual_media.lock().unwrap().use_audio_outputs(devices);
+        }
     }
     
     fn audio_output_box(&mut self, index: usize, f: AudioOutputFn){
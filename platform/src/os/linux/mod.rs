@@ -17,6 +17,8 @@ pub mod alsa_audio;
 #[cfg(not(target_os="android"))]
 pub mod alsa_midi;
 #[cfg(not(target_os="android"))]
+pub mod virtual_media;
+#[cfg(not(target_os="android"))]
 pub mod select_timer;
 #[cfg(not(target_os="android"))] 
 pub mod pulse_audio; 
@@ -5,6 +5,7 @@ use {
         mem,
         ptr,
         ffi::CStr,
+        rc::Rc,
     },
     self::super::gl_sys,
     crate::{
@@ -12,7 +13,8 @@ use {
         makepad_error_log::*,
         makepad_shader_compiler::generate_glsl,
         cx::Cx,
-        texture::{TextureDesc, TextureFormat},
+        event::{ReadbackEvent, ReadbackSource},
+        texture::{TextureDesc, TextureFormat, TextureRect, TextureSampler, TextureFilter, TextureWrap},
         makepad_math::{Mat4, DVec2, Vec4},
         pass::{PassClearColor, PassClearDepth, PassId},
         draw_list::DrawListId,
@@ -191,14 +193,21 @@ impl Cx {
                             continue;
                         };
                         let cxtexture = &mut self.textures[texture_id];
-                        if cxtexture.update_image || cxtexture.image_u32.len() != 0 && cxtexture.os.gl_texture.is_none(){
+                        if cxtexture.update_image || cxtexture.has_image() && cxtexture.os.gl_texture.is_none(){
                             cxtexture.update_image = false;
-                            cxtexture.os.update_platform_texture_image2d(
-                                cxtexture.desc.width.unwrap() as u32,
-                                cxtexture.desc.height.unwrap() as u32,
-                                &cxtexture.image_u32
-                            );
-                        }  
+                            cxtexture.update_rect = None;
+                            let desc = cxtexture.desc;
+                            match desc.format {
+                                TextureFormat::ImageR8 | TextureFormat::ImageRG8 => cxtexture.os.update_platform_texture_image2d(&desc, &cxtexture.image_u8),
+                                TextureFormat::ImageRf16 | TextureFormat::ImageRGBAf16 => cxtexture.os.update_platform_texture_image2d(&desc, &cxtexture.image_f16),
+                                TextureFormat::ImageRGBAf32 => cxtexture.os.update_platform_texture_image2d(&desc, &cxtexture.image_f32),
+                                _ => cxtexture.os.update_platform_texture_image2d(&desc, &cxtexture.image_u32)
+                            }
+                        }
+                        else if let Some(rect) = cxtexture.update_rect.take() {
+                            let desc = cxtexture.desc;
+                            cxtexture.os.update_platform_texture_rect(&desc, &cxtexture.image_u32, rect);
+                        }
                     }
                     for i in 0..sh.mapping.textures.len() {
                         let texture_id = if let Some(texture_id) = draw_call.texture_slots[i] {
@@ -209,12 +218,7 @@ impl Cx {
                         let cxtexture = &mut self.textures[texture_id];
                         // get the loc
                         gl_sys::ActiveTexture(gl_sys::TEXTURE0 + i as u32);
-                        if let Some(texture) = cxtexture.os.gl_texture {
-                            gl_sys::BindTexture(gl_sys::TEXTURE_2D, texture);
-                        }
-                        else {
-                            gl_sys::BindTexture(gl_sys::TEXTURE_2D, 0);
-                        }
+                        cxtexture.os.bind_with_sampler(sh.mapping.textures[i].sampler);
                         gl_sys::Uniform1i(shgl.textures[i].loc, i as i32);
                     }
                     
@@ -335,6 +339,13 @@ impl Cx {
                     clear_flags |= gl_sys::DEPTH_BUFFER_BIT;
                 }
             }
+            if self.textures[depth_texture_id].desc.format == TextureFormat::DepthTexture32 {
+                if let Some(gl_texture) = self.textures[depth_texture_id].os.gl_texture {
+                    unsafe {
+                        gl_sys::FramebufferTexture2D(gl_sys::FRAMEBUFFER, gl_sys::DEPTH_ATTACHMENT, gl_sys::TEXTURE_2D, gl_texture, 0);
+                    }
+                }
+            }
         }
         else {
             /* unsafe { // BUGFIX. we have to create a depthbuffer for rtt without depthbuffer use otherwise it fails if there is another pass with depth
@@ -384,12 +395,82 @@ impl Cx {
             zbias_step,
         );
         
+        if self.passes[pass_id].color_textures.len() > 0 {
+            self.opengl_read_back_pass(pass_id, (pass_size.x * dpi_factor) as usize, (pass_size.y * dpi_factor) as usize);
+        }
+        
         unsafe {
             gl_sys::BindFramebuffer(gl_sys::FRAMEBUFFER, 0);
             //gl_sys::Finish();
         }
     }
     
+    // reads the bound framebuffer, render targets hold RGBA with the bottom row first
+    unsafe fn gl_read_pixels(width: usize, height: usize) -> Vec<u32> {
+        let mut pixels = vec![0u32; width * height];
+        gl_sys::ReadPixels(0, 0, width as i32, height as i32, gl_sys::RGBA, gl_sys::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut _);
+        let mut out = Vec::with_capacity(pixels.len());
+        for row in pixels.chunks(width.max(1)).rev() {
+            out.extend(row.iter().map( | p | (p & 0xff00ff00) | (p & 0xff) << 16 | (p >> 16) & 0xff));
+        }
+        out
+    }
+    
+    // call with the framebuffer of the pass still bound, after rendering it
+    pub (crate) fn opengl_read_back_pass(&mut self, pass_id: PassId, width: usize, height: usize) {
+        if !self.passes[pass_id].read_back {
+            return
+        }
+        self.passes[pass_id].read_back = false;
+        let data = unsafe {Self::gl_read_pixels(width, height)};
+        self.readbacks.push(ReadbackEvent {
+            source: ReadbackSource::Pass(pass_id),
+            width,
+            height,
+            data: Ok(Rc::new(data))
+        });
+    }
+    
+    // render targets are attached to a scratch framebuffer and read from the gpu
+    pub (crate) fn opengl_read_back_textures(&mut self) {
+        self.read_back_image_textures();
+        for texture_id in std::mem::take(&mut self.texture_readbacks) {
+            let cxtexture = &self.textures[texture_id];
+            let result = match cxtexture.desc.format {
+                TextureFormat::Default | TextureFormat::RenderBGRA | TextureFormat::RenderBGRAf16 | TextureFormat::RenderBGRAf32 => {
+                    if let Some(gl_texture) = cxtexture.os.gl_texture {
+                        let (width, height) = (cxtexture.os.width as usize, cxtexture.os.height as usize);
+                        let data = unsafe {
+                            let mut gl_framebuffer = std::mem::MaybeUninit::uninit();
+                            gl_sys::GenFramebuffers(1, gl_framebuffer.as_mut_ptr());
+                            let gl_framebuffer = gl_framebuffer.assume_init();
+                            gl_sys::BindFramebuffer(gl_sys::FRAMEBUFFER, gl_framebuffer);
+                            gl_sys::FramebufferTexture2D(gl_sys::FRAMEBUFFER, gl_sys::COLOR_ATTACHMENT0, gl_sys::TEXTURE_2D, gl_texture, 0);
+                            let data = Self::gl_read_pixels(width, height);
+                            gl_sys::BindFramebuffer(gl_sys::FRAMEBUFFER, 0);
+                            gl_sys::DeleteFramebuffers(1, &gl_framebuffer);
+                            data
+                        };
+                        Ok((width, height, data))
+                    }
+                    else {
+                        Err("read_back on a texture that was never rendered to".to_string())
+                    }
+                }
+                format => Err(format!("read_back not supported for texture format {:?}", format))
+            };
+            self.readbacks.push(match result {
+                Ok((width, height, data)) => ReadbackEvent {
+                    source: ReadbackSource::Texture(texture_id),
+                    width,
+                    height,
+                    data: Ok(Rc::new(data))
+                },
+                Err(err) => ReadbackEvent::error(ReadbackSource::Texture(texture_id), err)
+            });
+        }
+    }
+    
     pub fn opengl_compile_shaders(&mut self) {
         //let p = profile_start();
         for draw_shader_ptr in &self.draw_shaders.compile_set {
@@ -788,21 +869,155 @@ impl CxOsDrawCall {
     }    
 }
 
+// the optional texture features of the gl context, queried when the first texture is made
+#[derive(Clone, Copy)]
+struct GlTextureCaps {
+    anisotropic_filter: bool,
+    float_linear: bool,
+    half_float_linear: bool,
+}
+
+impl GlTextureCaps {
+    fn get() -> Self {
+        static CAPS: std::sync::OnceLock<GlTextureCaps> = std::sync::OnceLock::new();
+        *CAPS.get_or_init( || unsafe {
+            let get_string = | name | {
+                let string = gl_sys::GetString(name);
+                if string.is_null() {String::new()} else {CStr::from_ptr(string as *const _).to_string_lossy().to_string()}
+            };
+            let extensions = get_string(gl_sys::EXTENSIONS);
+            let has = | name | extensions.split(' ').any( | ext | ext == name);
+            // half floats are filterable in gles 3
+            let is_gles3 = get_string(gl_sys::VERSION).starts_with("OpenGL ES 3");
+            GlTextureCaps {
+                anisotropic_filter: has("GL_EXT_texture_filter_anisotropic"),
+                float_linear: has("GL_OES_texture_float_linear"),
+                half_float_linear: is_gles3 || has("GL_OES_texture_half_float_linear"),
+            }
+        })
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct CxOsTexture {
     pub alloc_desc: TextureDesc,
     pub width: u64,
     pub height: u64,
+    // the sampler of the texture itself, and the state last set on the gl texture as shaders
+    // can select another one
+    pub own_sampler: TextureSampler,
+    pub sampler: Option<TextureSampler>,
+    pub has_mipmaps: bool,
     pub gl_texture: Option<u32>,
     pub gl_renderbuffer: Option<u32>
 }
 
 impl CxOsTexture {
     
-    pub fn update_platform_texture_image2d(&mut self, width: u32, height: u32, image_u32: &Vec<u32>) {
-        
-        if image_u32.len() != (width * height) as usize {
-            log!("update_platform_texture_image2d with wrong buffer_u32 size! {} {} {}", image_u32.len(), width, height);
+    // every format, sampler and mip chain is available, float ones fall back where they cant be filtered
+    pub fn check_desc(_desc: &TextureDesc) -> Result<(), String> {
+        Ok(())
+    }
+    
+    fn gl_image_format(format: TextureFormat) -> (u32, u32, u32) {
+        // internal format, format, type
+        match format {
+            TextureFormat::ImageR8 => (gl_sys::R8, gl_sys::RED, gl_sys::UNSIGNED_BYTE),
+            TextureFormat::ImageRG8 => (gl_sys::RG8, gl_sys::RG, gl_sys::UNSIGNED_BYTE),
+            TextureFormat::ImageRf16 => (gl_sys::R16F, gl_sys::RED, gl_sys::HALF_FLOAT),
+            TextureFormat::ImageRGBAf16 | TextureFormat::RenderBGRAf16 => (gl_sys::RGBA16F, gl_sys::RGBA, gl_sys::HALF_FLOAT),
+            TextureFormat::ImageRGBAf32 | TextureFormat::RenderBGRAf32 => (gl_sys::RGBA32F, gl_sys::RGBA, gl_sys::FLOAT),
+            TextureFormat::DepthTexture32 => (gl_sys::DEPTH_COMPONENT32F, gl_sys::DEPTH_COMPONENT, gl_sys::FLOAT),
+            _ => (gl_sys::RGBA, gl_sys::RGBA, gl_sys::UNSIGNED_BYTE)
+        }
+    }
+    
+    fn gl_filter(filter: TextureFilter) -> u32 {
+        match filter {
+            TextureFilter::Nearest => gl_sys::NEAREST,
+            TextureFilter::Linear => gl_sys::LINEAR,
+        }
+    }
+    
+    // the sampler a texture gets when neither the texture nor the shader selects one
+    fn default_sampler(desc: &TextureDesc, is_render_target: bool) -> TextureSampler {
+        if let Some(sampler) = desc.sampler {
+            sampler
+        }
+        else if is_render_target || desc.format.is_float() {
+            TextureSampler {
+                min_filter: TextureFilter::Nearest,
+                mag_filter: TextureFilter::Nearest,
+                mip_filter: TextureFilter::Nearest,
+                ..Default::default()
+            }
+        }
+        else {
+            TextureSampler::default()
+        }
+    }
+    
+    // float textures can only be filtered linearly with an extension on gles
+    fn is_filterable(format: TextureFormat) -> bool {
+        let caps = GlTextureCaps::get();
+        match format {
+            TextureFormat::ImageRf16 | TextureFormat::ImageRGBAf16 | TextureFormat::RenderBGRAf16 => caps.half_float_linear,
+            TextureFormat::ImageRGBAf32 | TextureFormat::RenderBGRAf32 => caps.float_linear,
+            _ => true
+        }
+    }
+    
+    // the currently bound texture gets the sampler state
+    unsafe fn set_sampler_parameters(sampler: &TextureSampler, format: TextureFormat, has_mipmaps: bool) {
+        let filter = | filter | if Self::is_filterable(format) {Self::gl_filter(filter)} else {gl_sys::NEAREST};
+        let min_filter = if has_mipmaps {
+            match (filter(sampler.min_filter), sampler.mip_filter) {
+                (gl_sys::NEAREST, TextureFilter::Nearest) => gl_sys::NEAREST_MIPMAP_NEAREST,
+                (_, TextureFilter::Nearest) => gl_sys::LINEAR_MIPMAP_NEAREST,
+                (gl_sys::NEAREST, TextureFilter::Linear) => gl_sys::NEAREST_MIPMAP_LINEAR,
+                (_, TextureFilter::Linear) => gl_sys::LINEAR_MIPMAP_LINEAR,
+            }
+        }
+        else {
+            filter(sampler.min_filter)
+        };
+        let wrap = match sampler.wrap {
+            TextureWrap::ClampToEdge => gl_sys::CLAMP_TO_EDGE,
+            TextureWrap::Repeat => gl_sys::REPEAT,
+            TextureWrap::MirroredRepeat => gl_sys::MIRRORED_REPEAT,
+        };
+        gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MIN_FILTER, min_filter as i32);
+        gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MAG_FILTER, filter(sampler.mag_filter) as i32);
+        gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_WRAP_S, wrap as i32);
+        gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_WRAP_T, wrap as i32);
+        if GlTextureCaps::get().anisotropic_filter {
+            gl_sys::TexParameterf(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MAX_ANISOTROPY_EXT, sampler.anisotropy.max(1.0));
+        }
+    }
+    
+    // binds the texture with the sampler the shader selected, or its own
+    pub unsafe fn bind_with_sampler(&mut self, shader_sampler: Option<TextureSampler>) {
+        let gl_texture = if let Some(gl_texture) = self.gl_texture {gl_texture} else {
+            gl_sys::BindTexture(gl_sys::TEXTURE_2D, 0);
+            return
+        };
+        gl_sys::BindTexture(gl_sys::TEXTURE_2D, gl_texture);
+        let sampler = shader_sampler.unwrap_or(self.own_sampler);
+        if self.sampler != Some(sampler) {
+            Self::set_sampler_parameters(&sampler, self.alloc_desc.format, self.has_mipmaps);
+            self.sampler = Some(sampler);
+        }
+    }
+    
+    pub fn update_platform_texture_image2d<T>(&mut self, desc: &TextureDesc, image: &Vec<T>) {
+        if desc.width.is_none() || desc.height.is_none() {
+            log!("update_platform_texture_image2d without width/height");
+            return;
+        }
+        let width = desc.width.unwrap();
+        let height = desc.height.unwrap();
+        if image.len() != width * height * desc.format.image_elements_per_pixel() {
+            log!("update_platform_texture_image2d with wrong buffer size! {} {} {}", image.len(), width, height);
             return;
         }
         
@@ -813,25 +1028,77 @@ impl CxOsTexture {
                 self.gl_texture = Some(gl_texture.assume_init());
             }
         }
+        // float textures only get mipmaps when asked for with a sampler and they can be filtered
+        let has_mipmaps = desc.mipmaps && (!desc.format.is_float() || desc.sampler.is_some() && Self::is_filterable(desc.format));
+        let sampler = Self::default_sampler(desc, false);
+        let (internal_format, format, data_type) = Self::gl_image_format(desc.format);
         unsafe {
             gl_sys::BindTexture(gl_sys::TEXTURE_2D, self.gl_texture.unwrap());
-            gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MIN_FILTER, gl_sys::LINEAR_MIPMAP_LINEAR as i32);            gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MAG_FILTER, gl_sys::NEAREST as i32);
-            gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_WRAP_S, gl_sys::CLAMP_TO_EDGE as i32);
-            gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_WRAP_T, gl_sys::CLAMP_TO_EDGE as i32);
+            Self::set_sampler_parameters(&sampler, desc.format, has_mipmaps);
+            // single and dual channel u8 rows are not 4 byte aligned
+            gl_sys::PixelStorei(gl_sys::UNPACK_ALIGNMENT, 1);
             gl_sys::TexImage2D(
                 gl_sys::TEXTURE_2D,
                 0,
-                gl_sys::RGBA as i32,
+                internal_format as i32,
                 width as i32,
                 height as i32,
                 0,
-                gl_sys::RGBA,
-                gl_sys::UNSIGNED_BYTE,
-                image_u32.as_ptr() as *const _
+                format,
+                data_type,
+                image.as_ptr() as *const _
             );
+            gl_sys::PixelStorei(gl_sys::UNPACK_ALIGNMENT, 4);
             gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_BASE_LEVEL, 0);
-            gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MAX_LEVEL, 3);
-            gl_sys::GenerateMipmap(gl_sys::TEXTURE_2D);  
+            if has_mipmaps {
+                // the full chain down to 1x1
+                let max_level = usize::BITS - 1 - width.max(height).max(1).leading_zeros();
+                gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MAX_LEVEL, max_level as i32);
+                gl_sys::GenerateMipmap(gl_sys::TEXTURE_2D);
+            }
+            else {
+                gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MAX_LEVEL, 0);
+            }
+            gl_sys::BindTexture(gl_sys::TEXTURE_2D, 0);
+        }
+        self.alloc_desc = desc.clone();
+        self.has_mipmaps = has_mipmaps;
+        self.own_sampler = sampler;
+        self.sampler = Some(sampler);
+        self.width = width as u64;
+        self.height = height as u64;
+    }
+    
+    // uploads the rows of a u32 image that rect covers, GLES2 can't upload
+    // part of a row without unpack row length
+    pub fn update_platform_texture_rect(&mut self, desc: &TextureDesc, image: &Vec<u32>, rect: TextureRect) {
+        if self.gl_texture.is_none() || self.alloc_desc != *desc {
+            return self.update_platform_texture_image2d(desc, image)
+        }
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let top = rect.y.min(height);
+        let bottom = (rect.y + rect.height).min(height);
+        if bottom <= top || image.len() != width * height {
+            return
+        }
+        let (_, format, data_type) = Self::gl_image_format(desc.format);
+        unsafe {
+            gl_sys::BindTexture(gl_sys::TEXTURE_2D, self.gl_texture.unwrap());
+            gl_sys::TexSubImage2D(
+                gl_sys::TEXTURE_2D,
+                0,
+                0,
+                top as i32,
+                width as i32,
+                (bottom - top) as i32,
+                format,
+                data_type,
+                image[top * width..].as_ptr() as *const _
+            );
+            if self.has_mipmaps {
+                gl_sys::GenerateMipmap(gl_sys::TEXTURE_2D);
+            }
             gl_sys::BindTexture(gl_sys::TEXTURE_2D, 0);
         }
     }
@@ -847,12 +1114,16 @@ impl CxOsTexture {
         unsafe {
             
             self.alloc_desc = desc.clone();
+            // render targets have no mipmaps and get their sampler set again on the next bind
+            self.has_mipmaps = false;
+            self.own_sampler = Self::default_sampler(desc, true);
+            self.sampler = None;
             self.width = width;
             self.height = height;
             
             if !is_depth {
                 match desc.format {
-                    TextureFormat::Default | TextureFormat::RenderBGRA => {
+                    TextureFormat::Default | TextureFormat::RenderBGRA | TextureFormat::RenderBGRAf16 | TextureFormat::RenderBGRAf32 => {
                         if self.gl_texture.is_none() {
                             let mut gl_texture = std::mem::MaybeUninit::uninit();
                             gl_sys::GenTextures(1, gl_texture.as_mut_ptr());
@@ -861,19 +1132,17 @@ impl CxOsTexture {
                         
                         gl_sys::BindTexture(gl_sys::TEXTURE_2D, self.gl_texture.unwrap());
                         
-                        //self.gl_texture = Some(gl_texture);
-                        
-                        gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MIN_FILTER, gl_sys::NEAREST as i32);
-                        gl_sys::TexParameteri(gl_sys::TEXTURE_2D, gl_sys::TEXTURE_MAG_FILTER, gl_sys::NEAREST as i32);
+                        let (internal_format, format, data_type) = Self::gl_image_format(desc.format);
+                        Self::set_sampler_parameters(&Self::default_sampler(desc, true), desc.format, false);
                         gl_sys::TexImage2D(
                             gl_sys::TEXTURE_2D,
                             0,
-                            gl_sys::RGBA as i32,
+                            internal_format as i32,
                             width as i32,
                             height as i32,
                             0,
-                            gl_sys::RGBA,
-                            gl_sys::UNSIGNED_BYTE,
+                            format,
+                            data_type,
                             ptr::null()
                         );
                         gl_sys::BindTexture(gl_sys::TEXTURE_2D, 0);
@@ -904,6 +1173,29 @@ impl CxOsTexture {
                         );
                         gl_sys::BindRenderbuffer(gl_sys::RENDERBUFFER, 0);
                     },
+                    TextureFormat::DepthTexture32 => {
+                        // a depth buffer that can be sampled by a later pass
+                        if self.gl_texture.is_none() {
+                            let mut gl_texture = std::mem::MaybeUninit::uninit();
+                            gl_sys::GenTextures(1, gl_texture.as_mut_ptr());
+                            self.gl_texture = Some(gl_texture.assume_init());
+                        }
+                        gl_sys::BindTexture(gl_sys::TEXTURE_2D, self.gl_texture.unwrap());
+                        let (internal_format, format, data_type) = Self::gl_image_format(desc.format);
+                        Self::set_sampler_parameters(&Self::default_sampler(desc, true), desc.format, false);
+                        gl_sys::TexImage2D(
+                            gl_sys::TEXTURE_2D,
+                            0,
+                            internal_format as i32,
+                            width as i32,
+                            height as i32,
+                            0,
+                            format,
+                            data_type,
+                            ptr::null()
+                        );
+                        gl_sys::BindTexture(gl_sys::TEXTURE_2D, 0);
+                    },
                     _ => {
                         println!("update_platform_render_targete unsupported texture format");
                         return false;
@@ -138,6 +138,7 @@ impl PulseInputStream {
             input.audio_buffer.copy_from_interleaved(2, interleaved);
             input_fn(AudioInfo {
                 device_id: input.device_id,
+                sample_rate: 48000.0,
                 time: None
             }, &input.audio_buffer);
         }        
@@ -285,6 +286,7 @@ impl PulseOutputStream {
             if let Some(output_fn) = &mut *output_fn {
                 output_fn(AudioInfo {
                     device_id: output.device_id,
+                    sample_rate: 48000.0,
                     time: None
                 }, &mut output.audio_buffer);
                 // lets copy it to interleaved format
@@ -0,0 +1,771 @@
+use {
+    std::path::{Path, PathBuf},
+    std::sync::{Arc, Mutex},
+    std::sync::atomic::{AtomicU64, Ordering},
+    std::time::{Duration, Instant},
+    self::super::{
+        alsa_audio::AlsaAudioAccess,
+        alsa_midi::InputSenders,
+    },
+    crate::{
+        makepad_live_id::*,
+        makepad_error_log::*,
+        thread::Signal,
+        audio::*,
+        midi::*,
+    }
+};
+
+// Devices that only exist in software, so audio and midi code can run end to end
+// without hardware, in headless CI for instance. They are listed in AudioDevicesEvent
+// and MidiPortsEvent before the ALSA and PulseAudio ones and are selected the same way.
+//
+// let (output, recording) = cx.add_virtual_audio_output(VirtualAudioOutput::new("render").with_max_frames(48000));
+// cx.add_virtual_midi_input(VirtualMidiInput::from_script("keys", "0.0 90 3c 64\n0.5 80 3c 00")?);
+// ...
+// recording.wait_for_frames(48000, Duration::from_secs(5));
+
+pub struct VirtualAudioOutput {
+    pub name: String,
+    pub sample_rate: f64,
+    pub channel_count: usize,
+    // frames per callback
+    pub frame_count: usize,
+    // pace the callbacks to the wall clock, otherwise render as fast as possible
+    pub realtime: bool,
+    // stop after rendering this many frames
+    pub max_frames: Option<usize>,
+    // written when the device stops
+    pub wav_path: Option<PathBuf>,
+    pub is_default: bool,
+}
+
+impl VirtualAudioOutput {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            sample_rate: 48000.0,
+            channel_count: 2,
+            frame_count: 512,
+            realtime: true,
+            max_frames: None,
+            wav_path: None,
+            is_default: true,
+        }
+    }
+
+    pub fn with_format(mut self, sample_rate: f64, channel_count: usize, frame_count: usize) -> Self {
+        self.sample_rate = sample_rate;
+        self.channel_count = channel_count.max(1);
+        self.frame_count = frame_count.max(1);
+        self
+    }
+
+    pub fn with_realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    pub fn with_max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = Some(max_frames);
+        self
+    }
+
+    pub fn with_wav(mut self, path: impl AsRef<Path>) -> Self {
+        self.wav_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_default(mut self, is_default: bool) -> Self {
+        self.is_default = is_default;
+        self
+    }
+}
+
+pub struct VirtualAudioInput {
+    pub name: String,
+    // played from the start each time the device is used, silence after the end
+    pub buffer: AudioBuffer,
+    pub sample_rate: f64,
+    pub frame_count: usize,
+    pub looping: bool,
+    pub realtime: bool,
+    pub is_default: bool,
+}
+
+impl VirtualAudioInput {
+    pub fn from_buffer(name: &str, buffer: AudioBuffer, sample_rate: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            buffer,
+            sample_rate,
+            frame_count: 512,
+            looping: false,
+            realtime: true,
+            is_default: true,
+        }
+    }
+
+    pub fn from_wav(name: &str, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let (buffer, sample_rate) = decode_wav(&data)?;
+        Ok(Self::from_buffer(name, buffer, sample_rate))
+    }
+
+    pub fn silence(name: &str, sample_rate: f64, channel_count: usize) -> Self {
+        Self::from_buffer(name, AudioBuffer::new_with_size(0, channel_count.max(1)), sample_rate)
+    }
+
+    pub fn with_frame_count(mut self, frame_count: usize) -> Self {
+        self.frame_count = frame_count.max(1);
+        self
+    }
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn with_realtime(mut self, realtime: bool) -> Self {
+        self.realtime = realtime;
+        self
+    }
+
+    pub fn with_default(mut self, is_default: bool) -> Self {
+        self.is_default = is_default;
+        self
+    }
+}
+
+// Everything a virtual output rendered, shared with the device thread.
+#[derive(Clone, Default)]
+pub struct VirtualAudioRecording(Arc<Mutex<RecordedAudio >>);
+
+#[derive(Default)]
+struct RecordedAudio {
+    interleaved: Vec<f32>,
+    channel_count: usize,
+    sample_rate: f64,
+    is_finished: bool,
+}
+
+impl VirtualAudioRecording {
+    pub fn frame_count(&self) -> usize {
+        let rec = self.0.lock().unwrap();
+        rec.interleaved.len() / rec.channel_count.max(1)
+    }
+
+    // true once the device reached max_frames or was stopped
+    pub fn is_finished(&self) -> bool {
+        self.0.lock().unwrap().is_finished
+    }
+
+    pub fn snapshot(&self) -> AudioBuffer {
+        let rec = self.0.lock().unwrap();
+        let mut buffer = AudioBuffer::default();
+        buffer.copy_from_interleaved(rec.channel_count.max(1), &rec.interleaved);
+        buffer
+    }
+
+    pub fn take(&self) -> AudioBuffer {
+        let mut rec = self.0.lock().unwrap();
+        let mut buffer = AudioBuffer::default();
+        buffer.copy_from_interleaved(rec.channel_count.max(1), &rec.interleaved);
+        rec.interleaved.clear();
+        buffer
+    }
+
+    pub fn write_wav(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let rec = self.0.lock().unwrap();
+        let mut buffer = AudioBuffer::default();
+        buffer.copy_from_interleaved(rec.channel_count.max(1), &rec.interleaved);
+        write_wav(path.as_ref(), &buffer, rec.sample_rate as u32, WavFormat::Float32)
+    }
+
+    // blocks until at least frame_count frames are recorded or the device finished
+    pub fn wait_for_frames(&self, frame_count: usize, timeout: Duration) -> bool {
+        let start = Instant::now();
+        loop {
+            if self.frame_count() >= frame_count {
+                return true
+            }
+            if self.is_finished() || start.elapsed() > timeout {
+                return false
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn begin(&self, channel_count: usize, sample_rate: f64) {
+        let mut rec = self.0.lock().unwrap();
+        rec.interleaved.clear();
+        rec.channel_count = channel_count;
+        rec.sample_rate = sample_rate;
+        rec.is_finished = false;
+    }
+
+    fn append(&self, buffer: &AudioBuffer) {
+        let mut rec = self.0.lock().unwrap();
+        let start = rec.interleaved.len();
+        rec.interleaved.resize(start + buffer.data.len(), 0.0);
+        buffer.copy_to_interleaved(&mut rec.interleaved[start..]);
+    }
+
+    fn finish(&self) {
+        self.0.lock().unwrap().is_finished = true;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VirtualMidiEvent {
+    // seconds after the port was selected
+    pub time: f64,
+    pub data: MidiData,
+}
+
+pub struct VirtualMidiInput {
+    pub name: String,
+    pub events: Vec<VirtualMidiEvent>,
+}
+
+impl VirtualMidiInput {
+    pub fn new(name: &str) -> Self {
+        Self {name: name.to_string(), events: Vec::new()}
+    }
+
+    pub fn with_event(mut self, time: f64, data: MidiData) -> Self {
+        self.events.push(VirtualMidiEvent {time, data});
+        self
+    }
+
+    // One event per line, the time in seconds followed by the message in hex:
+    //
+    // # middle c for half a second
+    // 0.0 90 3c 64
+    // 0.5 80 3c 00
+    pub fn from_script(name: &str, script: &str) -> Result<Self, String> {
+        let mut input = Self::new(name);
+        for (line_nr, line) in script.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let time = parts.next().unwrap().parse::<f64>().map_err( | _ | format!("line {}: invalid time", line_nr + 1)) ?;
+            let mut data = [0u8; 3];
+            for (i, part) in parts.enumerate() {
+                if i >= 3 {
+                    return Err(format!("line {}: more than 3 bytes", line_nr + 1))
+                }
+                data[i] = u8::from_str_radix(part, 16).map_err( | _ | format!("line {}: invalid byte {}", line_nr + 1, part)) ?;
+            }
+            input.events.push(VirtualMidiEvent {time, data: MidiData {data}});
+        }
+        Ok(input)
+    }
+}
+
+// Sends midi from a virtual input port as if a device played it.
+#[derive(Clone)]
+pub struct VirtualMidiSender {
+    port_id: MidiPortId,
+    generation: Arc<AtomicU64>,
+    input_senders: InputSenders,
+}
+
+impl VirtualMidiSender {
+    pub fn send(&self, data: MidiData) {
+        // unused ports are silent, like an unsubscribed device
+        if self.generation.load(Ordering::SeqCst) & 1 == 1 {
+            send_to_inputs(&self.input_senders, self.port_id, data);
+        }
+    }
+}
+
+// Everything sent to a virtual output port, timed from when the port was selected.
+#[derive(Clone, Default)]
+pub struct VirtualMidiRecording(Arc<Mutex<Vec<VirtualMidiEvent >> >);
+
+impl VirtualMidiRecording {
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn snapshot(&self) -> Vec<VirtualMidiEvent> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn take(&self) -> Vec<VirtualMidiEvent> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+fn send_to_inputs(input_senders: &InputSenders, port_id: MidiPortId, data: MidiData) {
+    let mut senders = input_senders.lock().unwrap();
+    senders.retain( | s | {
+        s.send((port_id, data)).is_ok()
+    });
+    if senders.len()>0 {
+        Signal::set_ui_signal();
+    }
+}
+
+struct VirtualDeviceRef {
+    device_id: AudioDeviceId,
+    is_terminated: bool,
+}
+
+enum VirtualAudioDevice {
+    Input(Arc<VirtualAudioInput>),
+    Output(Arc<VirtualAudioOutput>, VirtualAudioRecording),
+}
+
+struct VirtualAudioDesc {
+    desc: AudioDeviceDesc,
+    device: VirtualAudioDevice,
+}
+
+struct VirtualMidiInputPort {
+    desc: MidiPortDesc,
+    events: Arc<Vec<VirtualMidiEvent >>,
+    // odd while the port is in use, bumped on every change so stale script threads stop
+    generation: Arc<AtomicU64>,
+}
+
+struct VirtualMidiOutputPort {
+    desc: MidiPortDesc,
+    recording: VirtualMidiRecording,
+    started: Option<Instant>,
+}
+
+pub struct VirtualMediaAccess {
+    pub audio_input_cb: [Arc<Mutex<Option<AudioInputFn> > >; MAX_AUDIO_DEVICE_INDEX],
+    pub audio_output_cb: [Arc<Mutex<Option<AudioOutputFn> > >; MAX_AUDIO_DEVICE_INDEX],
+    audio_descs: Vec<VirtualAudioDesc>,
+    audio_running: Arc<Mutex<Vec<VirtualDeviceRef >> >,
+    midi_inputs: Vec<VirtualMidiInputPort>,
+    midi_outputs: Vec<VirtualMidiOutputPort>,
+    input_senders: InputSenders,
+    audio_change: Signal,
+    midi_change: Signal,
+}
+
+impl VirtualMediaAccess {
+    pub fn new(audio_change: Signal, midi_change: Signal, alsa_audio: &AlsaAudioAccess, input_senders: InputSenders) -> Arc<Mutex<Self >> {
+        Arc::new(Mutex::new(Self {
+            audio_input_cb: alsa_audio.audio_input_cb.clone(),
+            audio_output_cb: alsa_audio.audio_output_cb.clone(),
+            audio_descs: Vec::new(),
+            audio_running: Default::default(),
+            midi_inputs: Vec::new(),
+            midi_outputs: Vec::new(),
+            input_senders,
+            audio_change,
+            midi_change,
+        }))
+    }
+
+    pub fn add_audio_output(&mut self, output: VirtualAudioOutput) -> (AudioDeviceId, VirtualAudioRecording) {
+        let device_id = AudioDeviceId(LiveId::from_str(&format!("virtual {} output", output.name)));
+        self.remove_audio_device(device_id);
+        let recording = VirtualAudioRecording::default();
+        self.audio_descs.push(VirtualAudioDesc {
+            desc: AudioDeviceDesc {
+                device_id,
+                device_type: AudioDeviceType::Output,
+                is_default: output.is_default,
+                has_failed: false,
+                channel_count: output.channel_count,
+                name: format!("[Virtual] {}", output.name),
+            },
+            device: VirtualAudioDevice::Output(Arc::new(output), recording.clone())
+        });
+        self.audio_change.set();
+        (device_id, recording)
+    }
+
+    pub fn add_audio_input(&mut self, input: VirtualAudioInput) -> AudioDeviceId {
+        let device_id = AudioDeviceId(LiveId::from_str(&format!("virtual {} input", input.name)));
+        self.remove_audio_device(device_id);
+        self.audio_descs.push(VirtualAudioDesc {
+            desc: AudioDeviceDesc {
+                device_id,
+                device_type: AudioDeviceType::Input,
+                is_default: input.is_default,
+                has_failed: false,
+                channel_count: input.buffer.channel_count(),
+                name: format!("[Virtual] {}", input.name),
+            },
+            device: VirtualAudioDevice::Input(Arc::new(input))
+        });
+        self.audio_change.set();
+        device_id
+    }
+
+    pub fn remove_audio_device(&mut self, device_id: AudioDeviceId) {
+        let len = self.audio_descs.len();
+        self.audio_descs.retain( | v | v.desc.device_id != device_id);
+        if len != self.audio_descs.len() {
+            self.audio_running.lock().unwrap().iter_mut().for_each( | v | {
+                if v.device_id == device_id {
+                    v.is_terminated = true;
+                }
+            });
+            self.audio_change.set();
+        }
+    }
+
+    pub fn add_midi_input(&mut self, mut input: VirtualMidiInput) -> (MidiPortId, VirtualMidiSender) {
+        input.events.sort_by( | a, b | a.time.total_cmp(&b.time));
+        let port_id = MidiPortId(LiveId::from_str(&format!("virtual {} input", input.name)));
+        self.remove_midi_port(port_id);
+        let generation = Arc::new(AtomicU64::new(0));
+        self.midi_inputs.push(VirtualMidiInputPort {
+            desc: MidiPortDesc {
+                name: format!("[Virtual] {}", input.name),
+                port_id,
+                port_type: MidiPortType::Input,
+            },
+            events: Arc::new(input.events),
+            generation: generation.clone(),
+        });
+        self.midi_change.set();
+        (port_id, VirtualMidiSender {
+            port_id,
+            generation,
+            input_senders: self.input_senders.clone()
+        })
+    }
+
+    pub fn add_midi_output(&mut self, name: &str) -> (MidiPortId, VirtualMidiRecording) {
+        let port_id = MidiPortId(LiveId::from_str(&format!("virtual {} output", name)));
+        self.remove_midi_port(port_id);
+        let recording = VirtualMidiRecording::default();
+        self.midi_outputs.push(VirtualMidiOutputPort {
+            desc: MidiPortDesc {
+                name: format!("[Virtual] {}", name),
+                port_id,
+                port_type: MidiPortType::Output,
+            },
+            recording: recording.clone(),
+            started: None,
+        });
+        self.midi_change.set();
+        (port_id, recording)
+    }
+
+    pub fn remove_midi_port(&mut self, port_id: MidiPortId) {
+        let len = self.midi_inputs.len() + self.midi_outputs.len();
+        self.midi_inputs.retain( | p | {
+            if p.desc.port_id == port_id {
+                // back to even stops the script and the sender
+                if p.generation.load(Ordering::SeqCst) & 1 == 1 {
+                    p.generation.fetch_add(1, Ordering::SeqCst);
+                }
+                return false
+            }
+            true
+        });
+        self.midi_outputs.retain( | p | p.desc.port_id != port_id);
+        if len != self.midi_inputs.len() + self.midi_outputs.len() {
+            self.midi_change.set();
+        }
+    }
+
+    pub fn get_updated_audio_descs(&self) -> Vec<AudioDeviceDesc> {
+        self.audio_descs.iter().map( | v | v.desc.clone()).collect()
+    }
+
+    pub fn get_updated_midi_descs(&self) -> Vec<MidiPortDesc> {
+        let inputs = self.midi_inputs.iter().map( | p | p.desc.clone());
+        inputs.chain(self.midi_outputs.iter().map( | p | p.desc.clone())).collect()
+    }
+
+    pub fn use_audio_inputs(&mut self, devices: &[AudioDeviceId]) {
+        for (index, input) in self.start_devices(devices, AudioDeviceType::Input) {
+            let VirtualAudioDevice::Input(input) = input else {continue};
+            let device_id = devices[index];
+            let audio_input_cb = self.audio_input_cb[index].clone();
+            let audio_running = self.audio_running.clone();
+            std::thread::spawn(move || {
+                let frame_count = input.frame_count;
+                let source = &input.buffer;
+                let mut audio_buffer = AudioBuffer::new_with_size(frame_count, source.channel_count());
+                let start = Instant::now();
+                let mut sample_time = 0;
+                loop {
+                    if is_terminated(&audio_running, device_id) {
+                        break;
+                    }
+                    for i in 0..frame_count {
+                        let mut pos = sample_time + i;
+                        if input.looping && source.frame_count() > 0 {
+                            pos %= source.frame_count();
+                        }
+                        for c in 0..source.channel_count() {
+                            audio_buffer.channel_mut(c)[i] = if pos < source.frame_count() {source.channel(c)[pos]} else {0.0};
+                        }
+                    }
+                    if let Some(fbox) = &mut *audio_input_cb.lock().unwrap() {
+                        fbox(audio_info(device_id, input.sample_rate, sample_time), &audio_buffer);
+                    }
+                    sample_time += frame_count;
+                    if input.realtime {
+                        pace(start, sample_time, input.sample_rate);
+                    }
+                }
+                audio_running.lock().unwrap().retain( | v | v.device_id != device_id);
+            });
+        }
+    }
+
+    pub fn use_audio_outputs(&mut self, devices: &[AudioDeviceId]) {
+        for (index, output) in self.start_devices(devices, AudioDeviceType::Output) {
+            let VirtualAudioDevice::Output(output, recording) = output else {continue};
+            let device_id = devices[index];
+            let audio_output_cb = self.audio_output_cb[index].clone();
+            let audio_running = self.audio_running.clone();
+            std::thread::spawn(move || {
+                let mut audio_buffer = AudioBuffer::new_with_size(output.frame_count, output.channel_count);
+                recording.begin(output.channel_count, output.sample_rate);
+                let start = Instant::now();
+                let mut sample_time = 0;
+                loop {
+                    if is_terminated(&audio_running, device_id) {
+                        break;
+                    }
+                    if let Some(max_frames) = output.max_frames {
+                        if sample_time >= max_frames {
+                            break;
+                        }
+                        // the last block is cut to end exactly on max_frames
+                        let frame_count = output.frame_count.min(max_frames - sample_time);
+                        audio_buffer.resize(frame_count, output.channel_count);
+                    }
+                    audio_buffer.zero();
+                    if let Some(fbox) = &mut *audio_output_cb.lock().unwrap() {
+                        fbox(audio_info(device_id, output.sample_rate, sample_time), &mut audio_buffer);
+                    }
+                    recording.append(&audio_buffer);
+                    sample_time += audio_buffer.frame_count();
+                    if output.realtime {
+                        pace(start, sample_time, output.sample_rate);
+                    }
+                }
+                if let Some(path) = &output.wav_path {
+                    if let Err(e) = recording.write_wav(path) {
+                        error!("Virtual audio output could not write {:?} {}", path, e);
+                    }
+                }
+                recording.finish();
+                audio_running.lock().unwrap().retain( | v | v.device_id != device_id);
+            });
+        }
+    }
+
+    // stops the running devices of this type not in the list and returns the ones to start
+    fn start_devices(&mut self, devices: &[AudioDeviceId], device_type: AudioDeviceType) -> Vec<(usize, VirtualAudioDevice)> {
+        let mut audio_running = self.audio_running.lock().unwrap();
+        audio_running.iter_mut().for_each( | v | {
+            let is_type = self.audio_descs.iter().any( | d | d.desc.device_id == v.device_id && d.desc.device_type == device_type);
+            if is_type && !devices.contains(&v.device_id) {
+                v.is_terminated = true;
+            }
+        });
+        let mut new = Vec::new();
+        for (index, device_id) in devices.iter().enumerate() {
+            if audio_running.iter().any( | v | v.device_id == *device_id && !v.is_terminated) {
+                continue;
+            }
+            if let Some(v) = self.audio_descs.iter().find( | v | v.desc.device_id == *device_id && v.desc.device_type == device_type) {
+                audio_running.push(VirtualDeviceRef {device_id: *device_id, is_terminated: false});
+                new.push((index, match &v.device {
+                    VirtualAudioDevice::Input(input) => VirtualAudioDevice::Input(input.clone()),
+                    VirtualAudioDevice::Output(output, recording) => VirtualAudioDevice::Output(output.clone(), recording.clone()),
+                }));
+            }
+        }
+        new
+    }
+
+    pub fn use_midi_inputs(&mut self, ports: &[MidiPortId]) {
+        for port in &self.midi_inputs {
+            let in_use = port.generation.load(Ordering::SeqCst) & 1 == 1;
+            let used = ports.contains(&port.desc.port_id);
+            if in_use == used {
+                continue;
+            }
+            let generation = port.generation.fetch_add(1, Ordering::SeqCst) + 1;
+            if !used {
+                continue;
+            }
+            // play the script from the moment the port is selected
+            let port_id = port.desc.port_id;
+            let events = port.events.clone();
+            let current = port.generation.clone();
+            let input_senders = self.input_senders.clone();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                for event in events.iter() {
+                    let at = start + Duration::from_secs_f64(event.time.max(0.0));
+                    let now = Instant::now();
+                    if at > now {
+                        std::thread::sleep(at - now);
+                    }
+                    if current.load(Ordering::SeqCst) != generation {
+                        return
+                    }
+                    send_to_inputs(&input_senders, port_id, event.data);
+                }
+            });
+        }
+    }
+
+    pub fn use_midi_outputs(&mut self, ports: &[MidiPortId]) {
+        for port in &mut self.midi_outputs {
+            if !ports.contains(&port.desc.port_id) {
+                port.started = None;
+            }
+            else if port.started.is_none() {
+                port.started = Some(Instant::now());
+            }
+        }
+    }
+
+    pub fn send_midi(&mut self, port_id: Option<MidiPortId>, data: MidiData) {
+        for port in &self.midi_outputs {
+            if port_id.is_none() || Some(port.desc.port_id) == port_id {
+                if let Some(started) = port.started {
+                    port.recording.0.lock().unwrap().push(VirtualMidiEvent {
+                        time: started.elapsed().as_secs_f64(),
+                        data
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn is_terminated(audio_running: &Arc<Mutex<Vec<VirtualDeviceRef >> >, device_id: AudioDeviceId) -> bool {
+    audio_running.lock().unwrap().iter().any( | v | v.device_id == device_id && v.is_terminated)
+}
+
+// virtual devices count their own time so runs are repeatable
+fn audio_info(device_id: AudioDeviceId, sample_rate: f64, sample_time: usize) -> AudioInfo {
+    AudioInfo {
+        device_id,
+        sample_rate,
+        time: Some(AudioTime {
+            sample_time: sample_time as f64,
+            host_time: (sample_time as f64 / sample_rate * 1e9) as u64,
+            rate_scalar: 1.0,
+        })
+    }
+}
+
+fn pace(start: Instant, sample_time: usize, sample_rate: f64) {
+    let at = start + Duration::from_secs_f64(sample_time as f64 / sample_rate);
+    let now = Instant::now();
+    if at > now {
+        std::thread::sleep(at - now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::sync::mpsc,
+    };
+
+    // a media access with its own callback slots, without starting alsa
+    fn access() -> VirtualMediaAccess {
+        VirtualMediaAccess {
+            audio_input_cb: std::array::from_fn( | _ | Default::default()),
+            audio_output_cb: std::array::from_fn( | _ | Default::default()),
+            audio_descs: Vec::new(),
+            audio_running: Default::default(),
+            midi_inputs: Vec::new(),
+            midi_outputs: Vec::new(),
+            input_senders: Default::default(),
+            audio_change: Signal::new(),
+            midi_change: Signal::new(),
+        }
+    }
+
+    #[test]
+    fn input_output_and_midi_script_run_end_to_end() {
+        let mut access = access();
+        let wav_path = std::env::temp_dir().join(format!("makepad_virtual_output_{}.wav", std::process::id()));
+
+        // the input plays a ramp, the output renders a channel dependent ramp and writes a wav
+        let source = AudioBuffer::from_data((0..300).map( | i | i as f32 / 300.0).collect(), 1);
+        let input_id = access.add_audio_input(VirtualAudioInput::from_buffer("mic", source.clone(), 8000.0)
+            .with_frame_count(64).with_realtime(false));
+        let (output_id, recording) = access.add_audio_output(VirtualAudioOutput::new("render")
+            .with_format(8000.0, 2, 100).with_realtime(false).with_max_frames(1000).with_wav(&wav_path));
+        let (port_id, _sender) = access.add_midi_input(VirtualMidiInput::from_script("keys", "
+            # a note and its release
+            0.0 90 3c 64
+            0.02 80 3c 00
+        ").unwrap());
+        assert_eq!(access.get_updated_audio_descs().len(), 2);
+        assert_eq!(access.get_updated_midi_descs().len(), 1);
+
+        let (captured_send, captured) = mpsc::channel();
+        *access.audio_input_cb[0].lock().unwrap() = Some(Box::new(move | info, buffer | {
+            let _ = captured_send.send((info.time.unwrap().sample_time as usize, buffer.channel(0).to_vec()));
+        }));
+        *access.audio_output_cb[0].lock().unwrap() = Some(Box::new( | info, buffer | {
+            let start = info.time.unwrap().sample_time as usize;
+            for c in 0..buffer.channel_count() {
+                for (i, sample) in buffer.channel_mut(c).iter_mut().enumerate() {
+                    *sample = ((start + i) % 100) as f32 / 100.0 * if c == 0 {1.0} else {-1.0};
+                }
+            }
+        }));
+        let (midi_send, midi) = mpsc::channel();
+        access.input_senders.lock().unwrap().push(midi_send);
+
+        access.use_audio_inputs(&[input_id]);
+        access.use_audio_outputs(&[output_id]);
+        access.use_midi_inputs(&[port_id]);
+
+        // the input repeats the ramp's frames in order and goes silent after it
+        let mut frames = Vec::new();
+        while frames.len() < 640 {
+            let (sample_time, block) = captured.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(sample_time, frames.len());
+            frames.extend(block);
+        }
+        access.use_audio_inputs(&[]);
+        assert_eq!(&frames[0..300], source.channel(0));
+        assert!(frames[300..].iter().all( | s | *s == 0.0));
+
+        // the output stops on max_frames and its recording matches the file it wrote
+        assert!(recording.wait_for_frames(1000, Duration::from_secs(5)));
+        let start = Instant::now();
+        while !recording.is_finished() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(recording.is_finished());
+        let rendered = recording.snapshot();
+        assert_eq!(rendered.frame_count(), 1000);
+        assert_eq!(rendered.channel(0)[250], 0.5);
+        assert_eq!(rendered.channel(1)[250], -0.5);
+        let from_file = VirtualAudioInput::from_wav("file", &wav_path).unwrap();
+        let _ = std::fs::remove_file(&wav_path);
+        assert_eq!(from_file.sample_rate, 8000.0);
+        assert_eq!(from_file.buffer.data, rendered.data);
+
+        // the script arrives in order on the port it was added as
+        let on = midi.recv_timeout(Duration::from_secs(5)).unwrap();
+        let off = midi.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(on, (port_id, MidiData {data: [0x90, 0x3c, 0x64]}));
+        assert_eq!(off, (port_id, MidiData {data: [0x80, 0x3c, 0x00]}));
+    }
+}
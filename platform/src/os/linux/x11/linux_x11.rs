@@ -165,6 +165,9 @@ impl Cx {
             XlibEvent::TextInput(e) => {
                 self.call_event_handler(&Event::TextInput(e))
             }
+            XlibEvent::TextComposition(e) => {
+                self.call_event_handler(&Event::TextComposition(e))
+            }
             XlibEvent::Drag(e) => {
                 self.call_event_handler(&Event::Drag(e))
             }
@@ -236,6 +239,8 @@ impl Cx {
                 }
             }
         }
+        self.opengl_read_back_textures();
+        self.call_readback_events();
     }
     
     fn handle_platform_ops(&mut self, opengl_windows: &mut Vec<OpenglWindow>, opengl_cx: &OpenglCx, xlib_app: &mut XlibApp) -> EventFlow {
@@ -314,7 +319,10 @@ impl Cx {
                 },
                 CxOsOp::StartDragging(_dragged_item) => {
                 },
-                CxOsOp::UpdateMenu(_menu) => {
+                CxOsOp::UpdateMenu(menu) => {
+                    // no native menubar here, the DesktopWindow draws it
+                    self.window_menu = Some(menu);
+                    self.redraw_all();
                 },
                 CxOsOp::HttpRequest{request_id:_, request:_} => {
                     todo!()
@@ -79,6 +79,8 @@ impl Cx {
             zbias_step,
         );
         
+        self.opengl_read_back_pass(pass_id, pix_width.floor() as usize, pix_height.floor() as usize);
+        
         unsafe {
             glx_sys::glXSwapBuffers(opengl_cx.display, window);
         }
@@ -9,6 +9,7 @@ use std::os::raw::{
     c_void,
     c_char,
     c_uchar,
+    c_ushort,
 };
 
 
@@ -34,6 +35,8 @@ pub type XrmDatabase = *mut _XrmHashBucketRec;
 pub type XKeyPressedEvent = XKeyEvent;
 pub type XComposeStatus = _XComposeStatus;
 pub type GC = *mut _XGC;
+pub type XIMFeedback = c_ulong;
+pub type XICProc = Option<unsafe extern "C" fn(XIC, XPointer, XPointer) -> c_int>;
 
 pub const None: u32 = 0;
 pub const True: u32 = 1;
@@ -57,6 +60,8 @@ pub const KeyRelease: u32 = 3;
 pub const ButtonPress: u32 = 4;
 pub const ButtonRelease: u32 = 5;
 pub const Expose: u32 = 12;
+pub const FocusIn: u32 = 9;
+pub const FocusOut: u32 = 10;
 
 pub const CWBorderPixel: u32 = 8;
 pub const CWColormap: u32 = 8192;
@@ -80,12 +85,23 @@ pub const EnterWindowMask: u32 = 16;
 pub const LeaveWindowMask: u32 = 32;
 pub const XBufferOverflow: i32 = -1;
 
+pub const XIMPreeditCallbacks: u32 = 2;
+pub const XIMForwardChar: c_int = 0;
+pub const XIMBackwardChar: c_int = 1;
+pub const XIMLineStart: c_int = 8;
+pub const XIMLineEnd: c_int = 9;
+pub const XIMAbsolutePosition: c_int = 10;
 pub const XIMPreeditNothing: u32 = 8;
 pub const XIMStatusNothing: u32 = 1024;
 
 pub const XNInputStyle: &'static [u8; 11usize] = b"inputStyle\0";
 pub const XNClientWindow: &'static [u8; 13usize] = b"clientWindow\0";
 pub const XNFocusWindow: &'static [u8; 12usize] = b"focusWindow\0";
+pub const XNPreeditAttributes: &'static [u8; 18usize] = b"preeditAttributes\0";
+pub const XNPreeditStartCallback: &'static [u8; 21usize] = b"preeditStartCallback\0";
+pub const XNPreeditDoneCallback: &'static [u8; 20usize] = b"preeditDoneCallback\0";
+pub const XNPreeditDrawCallback: &'static [u8; 20usize] = b"preeditDrawCallback\0";
+pub const XNPreeditCaretCallback: &'static [u8; 21usize] = b"preeditCaretCallback\0";
 
 pub const Mod1Mask: u32 = 8;
 pub const ShiftMask: u32 = 1;
@@ -257,6 +273,16 @@ extern "C" {
     
     pub fn XCloseIM(arg1: XIM) -> c_int;
     
+    pub fn XSetLocaleModifiers(arg1: *const c_char) -> *mut c_char;
+    
+    pub fn XVaCreateNestedList(unused: c_int, ...) -> *mut c_void;
+    
+    pub fn XSetICFocus(arg1: XIC);
+    
+    pub fn XUnsetICFocus(arg1: XIC);
+    
+    pub fn XFilterEvent(arg1: *mut XEvent, arg2: Window) -> c_int;
+    
     pub fn XCloseDisplay(arg1: *mut Display) -> c_int;
     
     pub fn XPending(arg1: *mut Display) -> c_int;
@@ -439,6 +465,46 @@ pub struct _XIC {
     _unused: [u8; 0],
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XICCallback {
+    pub client_data: XPointer,
+    pub callback: XICProc,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union XIMTextString {
+    pub multi_byte: *mut c_char,
+    pub wide_char: *mut u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct XIMText {
+    pub length: c_ushort,
+    pub feedback: *mut XIMFeedback,
+    pub encoding_is_wchar: c_int,
+    pub string: XIMTextString,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XIMPreeditDrawCallbackStruct {
+    pub caret: c_int,
+    pub chg_first: c_int,
+    pub chg_length: c_int,
+    pub text: *mut XIMText,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct XIMPreeditCaretCallbackStruct {
+    pub position: c_int,
+    pub direction: c_int,
+    pub style: c_int,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct _XComposeStatus {
@@ -12,6 +12,7 @@ use {
         xlib_event::XlibEvent,
         xlib_window::*,
         super::select_timer::SelectTimers,
+        super::libc_sys,
     },
     crate::{
         makepad_math::DVec2,
@@ -61,9 +62,16 @@ pub struct XlibApp {
 impl XlibApp {
     pub fn new(event_callback: Box<dyn FnMut(&mut XlibApp, XlibEvent) -> EventFlow>) -> XlibApp {
         unsafe {
+            // the input method follows the locale and XMODIFIERS, without a running input method we fall back to the builtin compose
+            libc_sys::setlocale(libc_sys::LC_CTYPE, "\0".as_ptr() as *const _);
+            x11_sys::XSetLocaleModifiers("\0".as_ptr() as *const _);
             let display = x11_sys::XOpenDisplay(ptr::null());
             let display_fd = x11_sys::XConnectionNumber(display);
-            let xim = x11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+            let mut xim = x11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+            if xim.is_null() {
+                x11_sys::XSetLocaleModifiers("@im=none\0".as_ptr() as *const _);
+                xim = x11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+            }
             //let mut signal_fds = [0, 0];
             //libc_sys::pipe(signal_fds.as_mut_ptr());
             x11_sys::XrmInitialize();
@@ -96,6 +104,10 @@ impl XlibApp {
             let mut event = mem::MaybeUninit::uninit();
             x11_sys::XNextEvent(self.display, event.as_mut_ptr());
             let mut event = event.assume_init();
+            // keys the input method consumes for a composition dont reach us
+            if x11_sys::XFilterEvent(&mut event, 0) != 0 {
+                continue;
+            }
             match event.type_ as u32 {
                 x11_sys::SelectionNotify => {
                     let selection = event.xselection;
@@ -218,6 +230,18 @@ impl XlibApp {
                         }
                     }
                 },
+                x11_sys::FocusIn => {
+                    if let Some(window_ptr) = self.window_map.get(&event.xfocus.window) {
+                        let window = &mut (**window_ptr);
+                        window.set_ime_focus(true);
+                    }
+                },
+                x11_sys::FocusOut => {
+                    if let Some(window_ptr) = self.window_map.get(&event.xfocus.window) {
+                        let window = &mut (**window_ptr);
+                        window.set_ime_focus(false);
+                    }
+                },
                 x11_sys::EnterNotify => {},
                 x11_sys::LeaveNotify => {
                     let crossing = event.xcrossing;
@@ -477,11 +501,11 @@ impl XlibApp {
                         }else {false};
                         
                         if !block_text {
-                            // decode the character
-                            let mut buffer = [0u8; 32];
+                            // decode the character, or the text an input method committed
+                            let mut buffer = vec![0u8; 32];
                             let mut keysym = mem::MaybeUninit::uninit();
                             let mut status = mem::MaybeUninit::uninit();
-                            let count = x11_sys::Xutf8LookupString(
+                            let mut count = x11_sys::Xutf8LookupString(
                                 window.xic.unwrap(),
                                 &mut event.xkey,
                                 buffer.as_mut_ptr() as *mut c_char,
@@ -489,6 +513,17 @@ impl XlibApp {
                                 keysym.as_mut_ptr(),
                                 status.as_mut_ptr(),
                             );
+                            if status.assume_init() == x11_sys::XBufferOverflow {
+                                buffer.resize(count as usize, 0);
+                                count = x11_sys::Xutf8LookupString(
+                                    window.xic.unwrap(),
+                                    &mut event.xkey,
+                                    buffer.as_mut_ptr() as *mut c_char,
+                                    buffer.len() as c_int,
+                                    keysym.as_mut_ptr(),
+                                    status.as_mut_ptr(),
+                                );
+                            }
                             //let keysym = keysym.assume_init();
                             let status = status.assume_init();
                             if status != x11_sys::XBufferOverflow {
@@ -10,6 +10,7 @@ use {
             WindowCloseRequestedEvent,
             WindowClosedEvent,
             TextInputEvent,
+            TextCompositionEvent,
             KeyEvent,
             DragEvent,
             DropEvent,
@@ -35,6 +36,7 @@ pub enum XlibEvent {
     WindowDragQuery(WindowDragQueryEvent),
     WindowCloseRequested(WindowCloseRequestedEvent),
     TextInput(TextInputEvent),
+    TextComposition(TextCompositionEvent),
     Drag(DragEvent),
     Drop(DropEvent),
     DragEnd,
@@ -3,7 +3,7 @@ use {
         mem,
         cell::Cell,
         rc::Rc,
-        os::raw::{c_ulong, c_long, c_void, c_char},
+        os::raw::{c_ulong, c_long, c_void, c_char, c_int},
         ptr,
         ffi::{CStr,CString}, 
     },
@@ -34,6 +34,8 @@ pub struct XlibWindow {
     pub last_window_geom: WindowGeom,
     
     pub ime_spot: DVec2,
+    pub ime_preedit: Vec<char>,
+    pub ime_caret: usize,
     pub current_cursor: MouseCursor,
     pub last_mouse_pos: DVec2,
 }
@@ -62,6 +64,8 @@ impl XlibWindow {
             last_window_geom: WindowGeom::default(),
             last_nc_mode: None,
             ime_spot: DVec2::default(),
+            ime_preedit: Vec::new(),
+            ime_caret: 0,
             current_cursor: MouseCursor::Default,
             last_mouse_pos: DVec2::default(),
         }
@@ -150,16 +154,50 @@ impl XlibWindow {
             let title_bytes = format!("{}\0", title);
             x11_sys::XStoreName(display, window, title_bytes.as_bytes().as_ptr() as *const c_char);
             
-            let xic = x11_sys::XCreateIC(
+            // we draw the pre-edit text ourselves when the input method supports it,
+            // otherwise the input method shows it in a window of its own
+            let client_data = self as *mut _ as x11_sys::XPointer;
+            let start = x11_sys::XICCallback {client_data, callback: Some(xim_preedit_start)};
+            let done = x11_sys::XICCallback {client_data, callback: Some(xim_preedit_done)};
+            let draw = x11_sys::XICCallback {client_data, callback: Some(xim_preedit_draw)};
+            let caret = x11_sys::XICCallback {client_data, callback: Some(xim_preedit_caret)};
+            let preedit_attributes = x11_sys::XVaCreateNestedList(
+                0,
+                x11_sys::XNPreeditStartCallback.as_ptr(),
+                &start,
+                x11_sys::XNPreeditDoneCallback.as_ptr(),
+                &done,
+                x11_sys::XNPreeditDrawCallback.as_ptr(),
+                &draw,
+                x11_sys::XNPreeditCaretCallback.as_ptr(),
+                &caret,
+                ptr::null_mut() as *mut c_void
+            );
+            let mut xic = x11_sys::XCreateIC(
                 get_xlib_app_global().xim,
                 x11_sys::XNInputStyle.as_ptr(),
-                (x11_sys::XIMPreeditNothing | x11_sys::XIMStatusNothing) as i32,
+                (x11_sys::XIMPreeditCallbacks | x11_sys::XIMStatusNothing) as i32,
+                x11_sys::XNPreeditAttributes.as_ptr(),
+                preedit_attributes,
                 x11_sys::XNClientWindow.as_ptr(),
                 window,
                 x11_sys::XNFocusWindow.as_ptr(),
                 window,
                 ptr::null_mut() as *mut c_void
             );
+            x11_sys::XFree(preedit_attributes);
+            if xic.is_null() {
+                xic = x11_sys::XCreateIC(
+                    get_xlib_app_global().xim,
+                    x11_sys::XNInputStyle.as_ptr(),
+                    (x11_sys::XIMPreeditNothing | x11_sys::XIMStatusNothing) as i32,
+                    x11_sys::XNClientWindow.as_ptr(),
+                    window,
+                    x11_sys::XNFocusWindow.as_ptr(),
+                    window,
+                    ptr::null_mut() as *mut c_void
+                );
+            }
             
             // Create a window
             get_xlib_app_global().window_map.insert(window, self);
@@ -301,6 +339,19 @@ impl XlibWindow {
         self.ime_spot = spot;
     }
     
+    pub fn set_ime_focus(&mut self, focus: bool) {
+        if let Some(xic) = self.xic {
+            unsafe {
+                if focus {
+                    x11_sys::XSetICFocus(xic);
+                }
+                else {
+                    x11_sys::XUnsetICFocus(xic);
+                }
+            }
+        }
+    }
+    
     pub fn get_position(&self) -> DVec2 {
         unsafe {
             let mut xwa = mem::MaybeUninit::uninit();
@@ -470,6 +521,72 @@ impl XlibWindow {
         }))
     }
     
+    pub fn send_text_composition(&mut self) {
+        self.do_callback(XlibEvent::TextComposition(TextCompositionEvent {
+            text: self.ime_preedit.iter().collect(),
+            cursor: self.ime_caret.min(self.ime_preedit.len())
+        }))
+    }
+}
+
+// the on-the-spot pre-edit callbacks of the input method, client_data is our XlibWindow
+
+unsafe extern "C" fn xim_preedit_start(_xic: x11_sys::XIC, client_data: x11_sys::XPointer, _call_data: x11_sys::XPointer) -> c_int {
+    let window = &mut *(client_data as *mut XlibWindow);
+    window.ime_preedit.clear();
+    window.ime_caret = 0;
+    // no limit on the pre-edit length
+    -1
+}
+
+unsafe extern "C" fn xim_preedit_done(_xic: x11_sys::XIC, client_data: x11_sys::XPointer, _call_data: x11_sys::XPointer) -> c_int {
+    let window = &mut *(client_data as *mut XlibWindow);
+    window.ime_preedit.clear();
+    window.ime_caret = 0;
+    window.send_text_composition();
+    0
+}
+
+unsafe extern "C" fn xim_preedit_draw(_xic: x11_sys::XIC, client_data: x11_sys::XPointer, call_data: x11_sys::XPointer) -> c_int {
+    let window = &mut *(client_data as *mut XlibWindow);
+    let draw = &*(call_data as *const x11_sys::XIMPreeditDrawCallbackStruct);
+    // the input method replaces chg_length chars at chg_first with the new text
+    let mut text = Vec::new();
+    if !draw.text.is_null() {
+        let xim_text = &*draw.text;
+        if xim_text.encoding_is_wchar != 0 {
+            if !xim_text.string.wide_char.is_null() {
+                let wide = std::slice::from_raw_parts(xim_text.string.wide_char, xim_text.length as usize);
+                text.extend(wide.iter().filter_map( | c | char::from_u32(*c)));
+            }
+        }
+        else if !xim_text.string.multi_byte.is_null() {
+            text.extend(CStr::from_ptr(xim_text.string.multi_byte).to_string_lossy().chars());
+        }
+    }
+    let len = window.ime_preedit.len();
+    let first = (draw.chg_first.max(0) as usize).min(len);
+    let end = (first + draw.chg_length.max(0) as usize).min(len);
+    window.ime_preedit.splice(first..end, text);
+    window.ime_caret = draw.caret.max(0) as usize;
+    window.send_text_composition();
+    0
+}
+
+unsafe extern "C" fn xim_preedit_caret(_xic: x11_sys::XIC, client_data: x11_sys::XPointer, call_data: x11_sys::XPointer) -> c_int {
+    let window = &mut *(client_data as *mut XlibWindow);
+    let caret = &mut *(call_data as *mut x11_sys::XIMPreeditCaretCallbackStruct);
+    window.ime_caret = match caret.direction {
+        x11_sys::XIMForwardChar => window.ime_caret + 1,
+        x11_sys::XIMBackwardChar => window.ime_caret.saturating_sub(1),
+        x11_sys::XIMLineStart => 0,
+        x11_sys::XIMLineEnd => window.ime_preedit.len(),
+        x11_sys::XIMAbsolutePosition => caret.position.max(0) as usize,
+        _ => window.ime_caret
+    }.min(window.ime_preedit.len());
+    caret.position = window.ime_caret as c_int;
+    window.send_text_composition();
+    0
 }
 
 
@@ -43,6 +43,9 @@ pub use crate::os::linux::*;
 #[cfg(target_os = "linux")]
 pub use crate::os::linux::linux_media::*;
 
+#[cfg(target_os = "linux")]
+pub use crate::os::linux::virtual_media::*;
+
 
 #[cfg(target_arch = "wasm32")]
 pub mod web;
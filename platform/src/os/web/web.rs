@@ -361,7 +361,10 @@ impl Cx {
                     self.draw_pass_to_texture(*pass_id);
                 }
             }
-        }    
+        }
+        self.read_back_image_textures();
+        self.fail_gpu_readbacks("WebGL");
+        self.call_readback_events();
     }
     
     
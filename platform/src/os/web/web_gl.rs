@@ -15,6 +15,7 @@ use {
         cx::Cx,
         draw_list::DrawListId,
         pass::{PassId, PassClearColor, PassClearDepth},
+        texture::{TextureFormat, TextureDesc},
     },
 };
 
@@ -80,8 +81,10 @@ impl Cx {
                     };
                     
                     let cxtexture = &mut self.textures[texture_id];
-                    if cxtexture.update_image {
+                    // a changed rect goes up as the whole image here
+                    if cxtexture.update_image || cxtexture.update_rect.is_some() {
                         cxtexture.update_image = false;
+                        cxtexture.update_rect = None;
                         self.os.from_wasm(FromWasmAllocTextureImage2D {
                             texture_id: texture_id.0,
                             width: cxtexture.desc.width.unwrap(),
@@ -430,6 +433,30 @@ pub struct CxOsDrawShader {
 pub struct CxOsTexture {
 }
 
+impl CxOsTexture {
+    // bgra images and the render formats are uploaded here, the shaders fix their sampler
+    pub fn check_desc(desc: &TextureDesc) -> Result<(), String> {
+        match desc.format {
+            TextureFormat::ImageR8 |
+            TextureFormat::ImageRG8 |
+            TextureFormat::ImageRf16 |
+            TextureFormat::ImageRGBAf16 |
+            TextureFormat::ImageRGBAf32 |
+            TextureFormat::DepthTexture32 => {
+                return Err(format!("Texture format {:?} is not supported by the webgl backend", desc.format))
+            }
+            _ => ()
+        }
+        if desc.sampler.is_some() {
+            return Err("Texture samplers are not supported by the webgl backend".to_string())
+        }
+        if desc.mipmaps {
+            return Err("Texture mipmaps are not supported by the webgl backend".to_string())
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct CxOsGeometry {
     pub vb_id: Option<usize>,
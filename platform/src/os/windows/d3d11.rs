@@ -16,6 +16,7 @@ use crate::{
     texture::{ 
         TextureFormat,
         TextureDesc,
+        TextureRect,
     },  
     windows::{
         core::{
@@ -37,6 +38,7 @@ use crate::{
                     D3D11_BIND_CONSTANT_BUFFER,
                     D3D11_RESOURCE_MISC_FLAG,
                     D3D11_SUBRESOURCE_DATA,
+                    D3D11_BOX,
                     D3D11_CREATE_DEVICE_FLAG,
                     D3D11_SDK_VERSION,
                     D3D11_BIND_FLAG,
@@ -258,6 +260,7 @@ impl Cx {
                         TextureFormat::Default | TextureFormat::ImageBGRA => {
                             if cxtexture.update_image {
                                 cxtexture.update_image = false;
+                                cxtexture.update_rect = None;
                                 cxtexture.os.update_platform_texture_image_bgra(
                                     d3d11_cx,
                                     cxtexture.desc.width.unwrap() as u32,
@@ -265,6 +268,15 @@ impl Cx {
                                     &cxtexture.image_u32,
                                 );
                             }
+                            else if let Some(rect) = cxtexture.update_rect.take() {
+                                cxtexture.os.update_platform_texture_rect_bgra(
+                                    d3d11_cx,
+                                    cxtexture.desc.width.unwrap() as u32,
+                                    cxtexture.desc.height.unwrap() as u32,
+                                    &cxtexture.image_u32,
+                                    rect
+                                );
+                            }
                         },
                         TextureFormat::SharedBGRA(_) => {
                             cxtexture.os.update_shared_texture(
@@ -751,6 +763,28 @@ pub struct CxOsTexture {
 
 impl CxOsTexture {
 
+    // bgra images and the render formats are uploaded here, the shaders fix their sampler
+    pub fn check_desc(desc: &TextureDesc) -> Result<(), String> {
+        match desc.format {
+            TextureFormat::ImageR8 |
+            TextureFormat::ImageRG8 |
+            TextureFormat::ImageRf16 |
+            TextureFormat::ImageRGBAf16 |
+            TextureFormat::ImageRGBAf32 |
+            TextureFormat::DepthTexture32 => {
+                return Err(format!("Texture format {:?} is not supported by the d3d11 backend", desc.format))
+            }
+            _ => ()
+        }
+        if desc.sampler.is_some() {
+            return Err("Texture samplers are not supported by the d3d11 backend".to_string())
+        }
+        if desc.mipmaps {
+            return Err("Texture mipmaps are not supported by the d3d11 backend".to_string())
+        }
+        Ok(())
+    }
+
     pub fn update_render_target(
         &mut self,
         d3d11_cx: &D3d11Cx,
@@ -923,6 +957,41 @@ impl CxOsTexture {
         self.shader_resource_view = shader_resource_view;
     }
 
+    // uploads the pixels in rect into the texture, which keeps its size
+    pub fn update_platform_texture_rect_bgra(
+        &mut self,
+        d3d11_cx: &D3d11Cx,
+        width: u32,
+        height: u32,
+        image_u32: &Vec<u32>,
+        rect: TextureRect
+    ) {
+        if self.texture.is_none() || width != self.width || height != self.height {
+            return self.update_platform_texture_image_bgra(d3d11_cx, width, height, image_u32)
+        }
+        if image_u32.len() != (width * height) as usize {
+            error!("update_platform_texture_rect_bgra with wrong buffer_u32 size!");
+            return
+        }
+        let left = (rect.x as u32).min(width);
+        let top = (rect.y as u32).min(height);
+        let right = ((rect.x + rect.width) as u32).min(width);
+        let bottom = ((rect.y + rect.height) as u32).min(height);
+        if right <= left || bottom <= top {
+            return
+        }
+        let dst_box = D3D11_BOX {left, top, front: 0, right, bottom, back: 1};
+        let resource: ID3D11Resource = self.texture.clone().unwrap().cast().unwrap();
+        unsafe {d3d11_cx.context.UpdateSubresource(
+            &resource,
+            0,
+            Some(&dst_box as *const _),
+            image_u32[(top * width + left) as usize..].as_ptr() as *const _,
+            width * 4,
+            0
+        )};
+    }
+
     pub fn update_shared_texture(
         &mut self,
         d3d11_cx: &D3d11Cx,
@@ -152,6 +152,7 @@ impl WasapiAccess {
                             fbox(
                                 AudioInfo {
                                     device_id,
+                                    sample_rate: 48000.0,
                                     time: None
                                 },
                                 &buffer
@@ -210,6 +211,7 @@ impl WasapiAccess {
                             fbox(
                                 AudioInfo {
                                     device_id,
+                                    sample_rate: 48000.0,
                                     time: None,
                                 },
                                 &mut buffer.audio_buffer
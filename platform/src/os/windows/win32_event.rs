@@ -11,6 +11,7 @@ use {
             WindowCloseRequestedEvent,
             WindowClosedEvent,
             TextInputEvent,
+            TextCompositionEvent,
             KeyEvent,
             DragEvent,
             DropEvent,
@@ -38,6 +39,7 @@ pub enum Win32Event {
     WindowDragQuery(WindowDragQueryEvent),
     WindowCloseRequested(WindowCloseRequestedEvent),
     TextInput(TextInputEvent),
+    TextComposition(TextCompositionEvent),
     Drag(DragEvent),
     Drop(DropEvent),
     DragEnd,
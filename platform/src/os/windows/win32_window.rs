@@ -90,6 +90,9 @@ use {
                 WM_KEYUP,
                 WM_SYSKEYUP,
                 WM_CHAR,
+                WM_IME_STARTCOMPOSITION,
+                WM_IME_COMPOSITION,
+                WM_IME_ENDCOMPOSITION,
                 WM_ENTERSIZEMOVE,
                 WM_EXITSIZEMOVE,
                 WM_SIZE,
@@ -112,6 +115,16 @@ use {
                 MARGINS,
                 WM_MOUSELEAVE
             },
+            Win32::Globalization::HIMC,
+            Win32::UI::Input::Ime::{
+                ImmGetContext,
+                ImmReleaseContext,
+                ImmGetCompositionStringW,
+                IME_COMPOSITION_STRING,
+                GCS_COMPSTR,
+                GCS_CURSORPOS,
+                GCS_RESULTSTR,
+            },
             Win32::UI::Input::KeyboardAndMouse::{
                 VIRTUAL_KEY,
                 ReleaseCapture,
@@ -561,6 +574,38 @@ impl Win32Window {
                     }
                 }
             },
+            WM_IME_STARTCOMPOSITION => {
+                // we draw the composition in the text input, so the default composition window stays hidden
+            },
+            WM_IME_COMPOSITION => {
+                let himc = ImmGetContext(hwnd);
+                let flags = lparam.0 as u32;
+                if flags & GCS_RESULTSTR.0 != 0 {
+                    let result = String::from_utf16_lossy(&Self::get_composition_string(himc, GCS_RESULTSTR));
+                    window.send_text_composition(String::new(), 0);
+                    if !result.is_empty() {
+                        window.send_text_input(result, false);
+                    }
+                }
+                if flags & GCS_COMPSTR.0 != 0 {
+                    let text = Self::get_composition_string(himc, GCS_COMPSTR);
+                    // the cursor is in utf16 units
+                    let cursor = if flags & GCS_CURSORPOS.0 != 0 {
+                        let pos = ImmGetCompositionStringW(himc, GCS_CURSORPOS, None, 0).max(0) as usize;
+                        String::from_utf16_lossy(&text[..pos.min(text.len())]).chars().count()
+                    }
+                    else {
+                        text.len()
+                    };
+                    let text = String::from_utf16_lossy(&text);
+                    let cursor = cursor.min(text.chars().count());
+                    window.send_text_composition(text, cursor);
+                }
+                ImmReleaseContext(hwnd, himc);
+            },
+            WM_IME_ENDCOMPOSITION => {
+                window.send_text_composition(String::new(), 0);
+            },
             WM_ENTERSIZEMOVE => {
                 get_win32_app_global().start_resize();
                 window.do_callback(Win32Event::WindowResizeLoopStart(window.window_id));
@@ -954,6 +999,23 @@ impl Win32Window {
         }))
     }
     
+    pub fn send_text_composition(&mut self, text: String, cursor: usize) {
+        self.do_callback(Win32Event::TextComposition(TextCompositionEvent {
+            text,
+            cursor
+        }))
+    }
+    
+    unsafe fn get_composition_string(himc: HIMC, kind: IME_COMPOSITION_STRING) -> Vec<u16> {
+        let bytes = ImmGetCompositionStringW(himc, kind, None, 0);
+        if bytes <= 0 {
+            return Vec::new()
+        }
+        let mut buffer = vec![0u16; bytes as usize / 2];
+        ImmGetCompositionStringW(himc, kind, Some(buffer.as_mut_ptr() as *mut _), bytes as u32);
+        buffer
+    }
+    
     pub fn virtual_key_to_key_code(wparam: WPARAM) -> KeyCode {
         match VIRTUAL_KEY(wparam.0 as u16) {
             VK_ESCAPE => KeyCode::Escape,
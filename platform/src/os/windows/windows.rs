@@ -164,6 +164,9 @@ impl Cx {
             Win32Event::TextInput(e) => {
                 self.call_event_handler(&Event::TextInput(e))
             }
+            Win32Event::TextComposition(e) => {
+                self.call_event_handler(&Event::TextComposition(e))
+            }
             Win32Event::Drag(e) => {
                 self.call_event_handler(&Event::Drag(e))
             }
@@ -228,6 +231,9 @@ impl Cx {
                 }
             }
         }
+        self.read_back_image_textures();
+        self.fail_gpu_readbacks("D3D11");
+        self.call_readback_events();
     }
     
     pub(crate) fn handle_networking_events(&mut self) {
@@ -310,7 +316,10 @@ impl Cx {
                 },
                 CxOsOp::StartDragging(_dragged_item) => {
                 },
-                CxOsOp::UpdateMenu(_menu) => {
+                CxOsOp::UpdateMenu(menu) => {
+                    // no native menubar here, the DesktopWindow draws it
+                    self.window_menu = Some(menu);
+                    self.redraw_all();
                 },
                 CxOsOp::HttpRequest{request_id:_, request:_} => {
                     //todo!()
@@ -192,6 +192,14 @@ impl Pass {
         cxpass.matrix_mode = pmm;
     }
     
+    /// Asks for the pixels of the first color texture, or of the window for a
+    /// window pass, which arrive as `Event::Readback` once the pass is painted
+    pub fn read_back(&self, cx: &mut Cx) {
+        let cxpass = &mut cx.passes[self.pass_id()];
+        cxpass.read_back = true;
+        cxpass.paint_dirty = true;
+    }
+    
     pub fn set_debug(&mut self, cx: &mut Cx, debug: bool) {
         let cxpass = &mut cx.passes[self.pass_id()];
         cxpass.debug = debug;
@@ -269,6 +277,7 @@ pub struct CxPass {
     pub main_draw_list_id: Option<DrawListId>,
     pub parent: CxPassParent,
     pub paint_dirty: bool,
+    pub read_back: bool,
     pub pass_rect: Option<CxPassRect>,
     pub pass_uniforms: PassUniforms,
     pub zbias_step: f32,
@@ -293,6 +302,7 @@ impl Default for CxPass {
             main_draw_list_id: None,
             parent: CxPassParent::None,
             paint_dirty: false,
+            read_back: false,
             pass_rect: None,
             os: CxOsPass::default()
         }
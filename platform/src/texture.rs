@@ -64,14 +64,17 @@ impl std::ops::IndexMut<TextureId> for CxTexturePool {
 pub enum TextureFormat {
     Default,
     ImageBGRA,
+    ImageR8,
+    ImageRG8,
+    ImageRf16,
+    ImageRGBAf16,
+    ImageRGBAf32,
     Depth32Stencil8,
+    DepthTexture32,
     RenderBGRA,
     RenderBGRAf16,
     RenderBGRAf32,
     SharedBGRA(u64),
-    //    ImageBGRAf32,
-    //    ImageRf32,
-    //    ImageRGf32,
     //    MappedBGRA,
     //    MappedBGRAf32,
     //    MappedRf32,
@@ -84,12 +87,142 @@ impl TextureFormat{
              _=>false
          }
     }
+    
+    pub fn is_image(&self)->bool{
+        match self{
+            Self::Default |
+            Self::ImageBGRA |
+            Self::ImageR8 |
+            Self::ImageRG8 |
+            Self::ImageRf16 |
+            Self::ImageRGBAf16 |
+            Self::ImageRGBAf32 => true,
+            _=>false
+        }
+    }
+    
+    pub fn is_depth(&self)->bool{
+        match self{
+            Self::Depth32Stencil8 | Self::DepthTexture32 => true,
+            _=>false
+        }
+    }
+    
+    pub fn is_float(&self)->bool{
+        match self{
+            Self::ImageRf16 |
+            Self::ImageRGBAf16 |
+            Self::ImageRGBAf32 |
+            Self::RenderBGRAf16 |
+            Self::RenderBGRAf32 => true,
+            _=>false
+        }
+    }
+    
+    /// The number of elements one pixel takes up in the image buffer belonging to this format
+    pub fn image_elements_per_pixel(&self)->usize{
+        match self{
+            Self::ImageRG8 => 2,
+            Self::ImageRGBAf16 | Self::ImageRGBAf32 => 4,
+            _ => 1
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureWrap {
+    ClampToEdge,
+    Repeat,
+    MirroredRepeat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureSampler {
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub mip_filter: TextureFilter,
+    pub wrap: TextureWrap,
+    pub anisotropy: f32,
+}
+
+impl Default for TextureSampler {
+    fn default() -> Self {
+        TextureSampler {
+            min_filter: TextureFilter::Linear,
+            mag_filter: TextureFilter::Nearest,
+            mip_filter: TextureFilter::Linear,
+            wrap: TextureWrap::ClampToEdge,
+            anisotropy: 1.0,
+        }
+    }
 }
+
+impl TextureSampler {
+    /// The sampler a draw shader selects for a texture by name, with `sampler: {image: linear_repeat}`
+    pub fn from_preset(preset: LiveId) -> Option<Self> {
+        let (filter, wrap) = match preset {
+            live_id!(nearest) => (TextureFilter::Nearest, TextureWrap::ClampToEdge),
+            live_id!(linear) => (TextureFilter::Linear, TextureWrap::ClampToEdge),
+            live_id!(nearest_repeat) => (TextureFilter::Nearest, TextureWrap::Repeat),
+            live_id!(linear_repeat) => (TextureFilter::Linear, TextureWrap::Repeat),
+            live_id!(nearest_mirror) => (TextureFilter::Nearest, TextureWrap::MirroredRepeat),
+            live_id!(linear_mirror) => (TextureFilter::Linear, TextureWrap::MirroredRepeat),
+            live_id!(anisotropic) => return Some(TextureSampler {
+                min_filter: TextureFilter::Linear,
+                mag_filter: TextureFilter::Linear,
+                mip_filter: TextureFilter::Linear,
+                wrap: TextureWrap::Repeat,
+                anisotropy: 16.0,
+            }),
+            _ => return None
+        };
+        Some(TextureSampler {
+            min_filter: filter,
+            mag_filter: filter,
+            mip_filter: filter,
+            wrap,
+            anisotropy: 1.0,
+        })
+    }
+}
+
+// a rectangle of pixels in a texture image
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextureRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl TextureRect {
+    pub fn union(self, other: TextureRect) -> TextureRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        TextureRect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub struct TextureDesc {
     pub format: TextureFormat,
     pub width: Option<usize>,
     pub height: Option<usize>,
+    // a mip chain generated on upload, float formats only get one where the platform can filter them
+    pub mipmaps: bool,
+    // None keeps the default sampler of the platform layer for the texture format
+    pub sampler: Option<TextureSampler>,
 }
 
 impl Default for TextureDesc {
@@ -98,10 +231,19 @@ impl Default for TextureDesc {
             format: TextureFormat::Default,
             width: None,
             height: None,
+            mipmaps: false,
+            sampler: None,
         }
     }
 }
 
+impl TextureDesc {
+    /// Whether the platform layer can create a texture with this format, sampler and mip chain
+    pub fn check_supported(&self) -> Result<(), String> {
+        CxOsTexture::check_desc(self)
+    }
+}
+
 impl LiveHook for Texture {}
 impl LiveNew for Texture {
     fn live_design_with(_cx:&mut Cx){}
@@ -148,7 +290,12 @@ impl LiveApply for Texture {
 
 
 impl Texture {
+    // a desc the platform layer cant create is rejected here, instead of drawing nothing later
     pub fn set_desc(&self, cx: &mut Cx, desc: TextureDesc) {
+        if let Err(err) = desc.check_supported() {
+            error!("{}", err);
+            return
+        }
         let cxtexture = &mut cx.textures[self.texture_id()];
         cxtexture.desc = desc;
     }
@@ -157,11 +304,66 @@ impl Texture {
         cx.textures[self.texture_id()].desc.clone()
     }
     
+    pub fn set_sampler(&self, cx: &mut Cx, sampler: TextureSampler) {
+        let desc = TextureDesc {sampler: Some(sampler), ..cx.textures[self.texture_id()].desc};
+        if let Err(err) = desc.check_supported() {
+            error!("{}", err);
+            return
+        }
+        let cxtexture = &mut cx.textures[self.texture_id()];
+        cxtexture.desc.sampler = Some(sampler);
+        if cxtexture.has_image() {
+            cxtexture.update_image = true;
+        }
+    }
+    
     pub fn swap_image_u32(&self, cx: &mut Cx, image_u32: &mut Vec<u32>) {
         let cxtexture = &mut cx.textures[self.texture_id()];
         std::mem::swap(&mut cxtexture.image_u32, image_u32);
         cxtexture.update_image = true;
     }
+    
+    // changes the u32 image in place, update returns the rect it touched and only
+    // those pixels are uploaded again instead of the whole image
+    pub fn update_image_u32_rect(&self, cx: &mut Cx, update: impl FnOnce(&mut Vec<u32>) -> TextureRect) {
+        let cxtexture = &mut cx.textures[self.texture_id()];
+        let rect = update(&mut cxtexture.image_u32);
+        if !cxtexture.update_image {
+            cxtexture.update_rect = Some(cxtexture.update_rect.map_or(rect, | old | old.union(rect)));
+        }
+    }
+    
+    // used by ImageR8 and ImageRG8
+    pub fn swap_image_u8(&self, cx: &mut Cx, image_u8: &mut Vec<u8>) {
+        let cxtexture = &mut cx.textures[self.texture_id()];
+        std::mem::swap(&mut cxtexture.image_u8, image_u8);
+        cxtexture.update_image = true;
+    }
+    
+    // used by ImageRf16 and ImageRGBAf16, the values are raw IEEE half floats
+    pub fn swap_image_f16(&self, cx: &mut Cx, image_f16: &mut Vec<u16>) {
+        let cxtexture = &mut cx.textures[self.texture_id()];
+        std::mem::swap(&mut cxtexture.image_f16, image_f16);
+        cxtexture.update_image = true;
+    }
+    
+    // used by ImageRGBAf32
+    pub fn swap_image_f32(&self, cx: &mut Cx, image_f32: &mut Vec<f32>) {
+        let cxtexture = &mut cx.textures[self.texture_id()];
+        std::mem::swap(&mut cxtexture.image_f32, image_f32);
+        cxtexture.update_image = true;
+    }
+    
+    /// Asks for a copy of the pixels, which arrives as `Event::Readback` after
+    /// the next repaint. Image textures are answered on every backend, render
+    /// targets only on OpenGL and with an error elsewhere.
+    pub fn read_back(&self, cx: &mut Cx) {
+        let texture_id = self.texture_id();
+        if !cx.texture_readbacks.contains(&texture_id) {
+            cx.texture_readbacks.push(texture_id);
+        }
+        cx.repaint_windows();
+    }
 }
 
 
@@ -169,7 +371,17 @@ impl Texture {
 pub struct CxTexture {
     pub (crate) desc: TextureDesc,
     pub (crate) image_u32: Vec<u32>,
-    //pub(crate) _image_f32: Vec<f32>,
+    pub (crate) image_u8: Vec<u8>,
+    pub (crate) image_f16: Vec<u16>,
+    pub (crate) image_f32: Vec<f32>,
     pub (crate) update_image: bool,
+    // the part of image_u32 changed since the last upload, when the image itself stays
+    pub (crate) update_rect: Option<TextureRect>,
     pub os: CxOsTexture
 }
+
+impl CxTexture {
+    pub fn has_image(&self) -> bool {
+        self.image_u32.len() != 0 || self.image_u8.len() != 0 || self.image_f16.len() != 0 || self.image_f32.len() != 0
+    }
+}
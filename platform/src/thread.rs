@@ -159,6 +159,13 @@ impl<T> FromUISender<T> {
             receiver: self.receiver.take().unwrap()
         }
     }
+    
+    // a receiver nothing sends on, for a second consumer that must not take over this channel
+    pub fn detached_receiver(&self) -> FromUIReceiver<T> {
+        FromUIReceiver {
+            receiver: channel().1
+        }
+    }
 }
 
 impl<T> FromUIReceiver<T> {
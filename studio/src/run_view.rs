@@ -189,6 +189,7 @@ impl RunView {
                         format: TextureFormat::SharedBGRA(0),
                         width: Some(new_size.0.max(1)),
                         height: Some(new_size.1.max(1)),
+                        ..Default::default()
                     });
 
                     manager.send_host_to_stdin(Some(process.cmd_id), HostToStdin::WindowSize(StdinWindowSize {
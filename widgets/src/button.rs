@@ -29,6 +29,7 @@ pub struct Button {
     #[walk] walk: Walk,
     
     #[layout] layout: Layout,
+    #[live] nav_order: NavOrder,
 
     #[live] pub text: RcStringMut,
 }
@@ -78,6 +79,9 @@ impl Button {
     
     pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, ButtonAction)) {
         self.animator_handle_event(cx, event);
+        if let Some(AccessAction::Activate) = Cx2d::access_action_hit(event, self.draw_bg.area()) {
+            dispatch_action(cx, ButtonAction::Clicked);
+        }
         match event.hits(cx, self.draw_bg.area()) {
             Hit::FingerDown(_fe) => {
                 dispatch_action(cx, ButtonAction::Pressed);
@@ -103,9 +107,24 @@ impl Button {
                 dispatch_action(cx, ButtonAction::Released);
                 self.animator_play(cx, id!(hover.off));
             }
+            Hit::KeyDown(ke) if !ke.is_repeat && Self::is_activate_key(ke.key_code) => {
+                dispatch_action(cx, ButtonAction::Pressed);
+                self.animator_play(cx, id!(hover.pressed));
+            }
+            Hit::KeyUp(ke) if Self::is_activate_key(ke.key_code) => {
+                dispatch_action(cx, ButtonAction::Clicked);
+                self.animator_play(cx, id!(hover.off));
+            }
+            Hit::KeyFocusLost(_) => {
+                self.animator_play(cx, id!(hover.off));
+            }
             _ => ()
         };
     }
+    
+    fn is_activate_key(key_code: KeyCode) -> bool {
+        matches!(key_code, KeyCode::Space | KeyCode::ReturnKey | KeyCode::NumpadEnter)
+    }
     /*
     pub fn draw_text(&mut self, cx: &mut Cx2d, label: &str) {
         self.draw_bg.begin(cx, self.walk, self.layout);
@@ -118,6 +137,13 @@ impl Button {
         self.draw_text.draw_walk(cx, self.label_walk, Align::default(), self.text.as_ref());
         self.draw_icon.draw_walk(cx, self.icon_walk);
         self.draw_bg.end(cx);
+        cx.add_nav_stop_with_access(
+            self.draw_bg.area(),
+            NavRole::Button,
+            self.nav_order,
+            Margin::default(),
+            AccessInfo::named(self.text.as_ref())
+        );
     }
 }
 
@@ -0,0 +1,836 @@
+use crate::{
+    makepad_derive_widget::*,
+    makepad_draw::*,
+    widget::*,
+};
+
+live_design!{
+    DrawChartLine = {{DrawChartLine}} {}
+    DrawChartArea = {{DrawChartArea}} {}
+    DrawChartBar = {{DrawChartBar}} {}
+    DrawChartPoint = {{DrawChartPoint}} {}
+    ChartBase = {{Chart}} {}
+}
+
+// one segment of a polyline, the quad covers the bounding box and the pixel shader
+// measures the distance to the segment so joins come out round
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawChartLine {
+    #[deref] draw_super: DrawQuad,
+    #[live] color: Vec4,
+    #[live] p1: Vec2,
+    #[live] p2: Vec2,
+    #[live(1.5)] line_width: f32,
+}
+
+// the fill between one segment and the baseline
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawChartArea {
+    #[deref] draw_super: DrawQuad,
+    #[live] color: Vec4,
+    #[live] p1: Vec2,
+    #[live] p2: Vec2,
+    #[live] base: f32,
+}
+
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawChartBar {
+    #[deref] draw_super: DrawQuad,
+    #[live] color: Vec4,
+}
+
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawChartPoint {
+    #[deref] draw_super: DrawQuad,
+    #[live] color: Vec4,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ChartKind {
+    #[default] Line,
+    Area,
+    Bar,
+    Scatter,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ChartSeries {
+    pub name: String,
+    pub kind: ChartKind,
+    // without a color the series gets one from the palette
+    pub color: Option<Vec4>,
+    // line, area and bar series are expected to be sorted by x
+    pub points: Vec<DVec2>,
+}
+
+impl ChartSeries {
+    pub fn new(name: &str, kind: ChartKind, points: Vec<DVec2>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            color: None,
+            points
+        }
+    }
+    
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+const PALETTE: [u32; 8] = [
+    0x4C9BE8FF,
+    0xE8A04CFF,
+    0x6CC46CFF,
+    0xE0605CFF,
+    0xA77FE0FF,
+    0x5CC8C8FF,
+    0xD8D060FF,
+    0xE07FB8FF,
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChartRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ChartRange {
+    pub fn new(min: f64, max: f64) -> Self {
+        Self {min, max}
+    }
+    
+    pub fn span(&self) -> f64 {
+        self.max - self.min
+    }
+    
+    fn zoom(&self, around: f64, factor: f64) -> Self {
+        Self {
+            min: around - (around - self.min) * factor,
+            max: around + (self.max - around) * factor
+        }
+    }
+    
+    fn shift(&self, d: f64) -> Self {
+        Self {min: self.min + d, max: self.max + d}
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ChartPan {
+    x: ChartRange,
+    y: ChartRange,
+}
+
+// maps between data and screen coordinates for one frame
+#[derive(Clone, Copy)]
+struct ChartMap {
+    x: ChartRange,
+    y: ChartRange,
+    rect: Rect,
+}
+
+impl ChartMap {
+    fn map(&self, p: DVec2) -> DVec2 {
+        dvec2(
+            self.rect.pos.x + (p.x - self.x.min) / self.x.span() * self.rect.size.x,
+            self.rect.pos.y + self.rect.size.y - (p.y - self.y.min) / self.y.span() * self.rect.size.y
+        )
+    }
+    
+    fn unmap(&self, p: DVec2) -> DVec2 {
+        dvec2(
+            self.x.min + (p.x - self.rect.pos.x) / self.rect.size.x.max(1.0) * self.x.span(),
+            self.y.min + (self.rect.pos.y + self.rect.size.y - p.y) / self.rect.size.y.max(1.0) * self.y.span()
+        )
+    }
+}
+
+// ticks land on multiples of 1, 2 or 5 times a power of ten
+fn tick_step(range: ChartRange, target: f64) -> f64 {
+    let raw = range.span() / target.max(1.0);
+    if !(raw > 0.0) || !raw.is_finite() {
+        return 1.0
+    }
+    let mag = 10f64.powf(raw.log10().floor());
+    let norm = raw / mag;
+    let nice = if norm < 1.5 {1.0} else if norm < 3.0 {2.0} else if norm < 7.0 {5.0} else {10.0};
+    nice * mag
+}
+
+// every tick is computed from its index, adding up the step would let the error grow along
+// the axis. a step below one divides by its inverse, which is whole for the nice steps, so
+// 0.3 comes out as 0.3 and not as 3 * 0.1
+fn ticks(range: ChartRange, step: f64) -> Vec<f64> {
+    let count = range.span() / step;
+    if step <= 0.0 || !(0.0..=1000.0).contains(&count) {
+        return Vec::new()
+    }
+    let first = (range.min / step).ceil();
+    let last = (range.max / step + 1e-9).floor();
+    // past 2^53 the indices themselves aren't exact any more
+    if first.abs() > 9e15 || last.abs() > 9e15 {
+        return Vec::new()
+    }
+    let per_unit = (1.0 / step).round();
+    let mut out = Vec::new();
+    let mut k = first;
+    while k <= last {
+        out.push(if step < 1.0 {k / per_unit} else {k * step});
+        k += 1.0;
+    }
+    out
+}
+
+fn format_value(v: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).clamp(0.0, 10.0) as usize;
+    let s = format!("{:.*}", decimals, v);
+    // rounding can leave a -0 behind
+    if s.trim_start_matches('-').chars().all( | c | c == '0' || c == '.') {
+        return s.trim_start_matches('-').to_string()
+    }
+    s
+}
+
+fn series_color(series: &ChartSeries, index: usize) -> Vec4 {
+    series.color.unwrap_or_else( || Vec4::from_u32(PALETTE[index % PALETTE.len()]))
+}
+
+// line, area, bar and scatter series over shared axes. scrolling zooms around the pointer,
+// dragging pans, a double tap goes back to fitting the data and hovering shows a crosshair readout
+#[derive(Live)]
+pub struct Chart {
+    #[walk] walk: Walk,
+    #[live] draw_bg: DrawColor,
+    #[live] draw_grid: DrawColor,
+    #[live] draw_axis: DrawColor,
+    #[live] draw_crosshair: DrawColor,
+    #[live] draw_readout: DrawColor,
+    #[live] draw_label: DrawText,
+    #[live] draw_readout_text: DrawText,
+    #[live] draw_line: DrawChartLine,
+    #[live] draw_area: DrawChartArea,
+    #[live] draw_bar: DrawChartBar,
+    #[live] draw_point: DrawChartPoint,
+    
+    #[live] plot_padding: Padding,
+    #[live(48.0)] y_axis_width: f64,
+    #[live(22.0)] x_axis_height: f64,
+    #[live(80.0)] x_tick_spacing: f64,
+    #[live(40.0)] y_tick_spacing: f64,
+    #[live(0.25)] area_alpha: f64,
+    #[live(0.8)] bar_fill: f64,
+    #[live(3.0)] point_radius: f64,
+    #[live(0.002)] zoom_speed: f64,
+    #[live(true)] zoom_x: bool,
+    #[live(true)] zoom_y: bool,
+    #[live(true)] include_zero: bool,
+    
+    #[rust] series: Vec<ChartSeries>,
+    #[rust] view: Option<(ChartRange, ChartRange)>,
+    #[rust] plot_rect: Rect,
+    #[rust] pan: Option<ChartPan>,
+    #[rust] hover: Option<DVec2>,
+    #[rust] area: Area,
+}
+
+impl LiveHook for Chart {
+    fn before_live_design(cx: &mut Cx) {
+        register_widget!(cx, Chart)
+    }
+}
+
+#[derive(Clone, WidgetAction)]
+pub enum ChartAction {
+    ViewChanged(ChartRange, ChartRange),
+    None
+}
+
+impl Widget for Chart {
+    fn handle_widget_event_with(
+        &mut self,
+        cx: &mut Cx,
+        event: &Event,
+        dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)
+    ) {
+        let uid = self.widget_uid();
+        self.handle_event_with(cx, event, &mut | cx, action | {
+            dispatch_action(cx, WidgetActionItem::new(action.into(), uid));
+        });
+    }
+    
+    fn walk(&mut self, _cx: &mut Cx) -> Walk {
+        self.walk
+    }
+    
+    fn redraw(&mut self, cx: &mut Cx) {
+        self.area.redraw(cx)
+    }
+    
+    fn draw_walk_widget(&mut self, cx: &mut Cx2d, walk: Walk) -> WidgetDraw {
+        self.draw_walk(cx, walk);
+        WidgetDraw::done()
+    }
+}
+
+impl Chart {
+    pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, ChartAction)) {
+        let plot = self.plot_rect;
+        match event.hits(cx, self.area) {
+            Hit::FingerScroll(se) if plot.contains(se.abs) => {
+                let (x, y) = self.view_ranges();
+                let pos = self.mapping().unmap(se.abs);
+                let factor = (se.scroll.y * self.zoom_speed).exp();
+                // shift keeps the y axis where it is, alt the x axis
+                let zoom_x = self.zoom_x && !se.modifiers.alt;
+                let zoom_y = self.zoom_y && !se.modifiers.shift;
+                let mut x = if zoom_x {x.zoom(pos.x, factor)} else {x};
+                let y = if zoom_y {y.zoom(pos.y, factor)} else {y};
+                if se.scroll.x != 0.0 {
+                    x = x.shift(se.scroll.x / plot.size.x.max(1.0) * x.span());
+                }
+                self.set_view(cx, x, y);
+                dispatch_action(cx, ChartAction::ViewChanged(x, y));
+            }
+            Hit::FingerDown(fe) => {
+                if fe.tap_count == 2 {
+                    self.reset_view(cx);
+                    let (x, y) = self.view_ranges();
+                    dispatch_action(cx, ChartAction::ViewChanged(x, y));
+                    return
+                }
+                let (x, y) = self.view_ranges();
+                self.pan = Some(ChartPan {x, y});
+            }
+            Hit::FingerMove(fe) => if let Some(pan) = self.pan {
+                cx.set_cursor(MouseCursor::Move);
+                let d = fe.abs - fe.abs_start;
+                let x = pan.x.shift(-d.x / plot.size.x.max(1.0) * pan.x.span());
+                let y = pan.y.shift(d.y / plot.size.y.max(1.0) * pan.y.span());
+                self.hover = None;
+                self.set_view(cx, x, y);
+                dispatch_action(cx, ChartAction::ViewChanged(x, y));
+            }
+            Hit::FingerUp(_) => {
+                self.pan = None;
+            }
+            Hit::FingerHoverIn(fe) | Hit::FingerHoverOver(fe) => {
+                cx.set_cursor(MouseCursor::Crosshair);
+                self.hover = if plot.contains(fe.abs) {Some(fe.abs)} else {None};
+                self.area.redraw(cx);
+            }
+            Hit::FingerHoverOut(_) => {
+                self.hover = None;
+                self.area.redraw(cx);
+            }
+            _ => ()
+        }
+    }
+    
+    pub fn set_series(&mut self, cx: &mut Cx, series: Vec<ChartSeries>) {
+        self.series = series;
+        self.redraw(cx);
+    }
+    
+    pub fn push_series(&mut self, cx: &mut Cx, series: ChartSeries) {
+        self.series.push(series);
+        self.redraw(cx);
+    }
+    
+    pub fn series_mut(&mut self) -> &mut Vec<ChartSeries> {
+        &mut self.series
+    }
+    
+    pub fn clear(&mut self, cx: &mut Cx) {
+        self.series.clear();
+        self.redraw(cx);
+    }
+    
+    // pins the view, zooming and panning move it from there
+    pub fn set_view(&mut self, cx: &mut Cx, x: ChartRange, y: ChartRange) {
+        if x.span() > 0.0 && y.span() > 0.0 && x.span().is_finite() && y.span().is_finite() {
+            self.view = Some((x, y));
+            self.redraw(cx);
+        }
+    }
+    
+    // goes back to fitting whatever data there is
+    pub fn reset_view(&mut self, cx: &mut Cx) {
+        self.view = None;
+        self.redraw(cx);
+    }
+    
+    pub fn view_ranges(&self) -> (ChartRange, ChartRange) {
+        self.view.unwrap_or_else( || self.fit_ranges())
+    }
+    
+    fn fit_ranges(&self) -> (ChartRange, ChartRange) {
+        let mut x = ChartRange::new(f64::INFINITY, f64::NEG_INFINITY);
+        let mut y = x;
+        let mut has_bars = false;
+        for series in &self.series {
+            has_bars |= series.kind == ChartKind::Bar;
+            for p in &series.points {
+                x.min = x.min.min(p.x);
+                x.max = x.max.max(p.x);
+                y.min = y.min.min(p.y);
+                y.max = y.max.max(p.y);
+            }
+        }
+        if x.min > x.max {
+            return (ChartRange::new(0.0, 1.0), ChartRange::new(0.0, 1.0))
+        }
+        if self.include_zero || has_bars {
+            y.min = y.min.min(0.0);
+            y.max = y.max.max(0.0);
+        }
+        if x.span() <= 0.0 {
+            x = ChartRange::new(x.min - 1.0, x.max + 1.0);
+        }
+        if y.span() <= 0.0 {
+            y = ChartRange::new(y.min - 1.0, y.max + 1.0);
+        }
+        // bars are centered on their x, so half a bar of room is needed on both ends
+        if has_bars {
+            let pad = 0.5 * Self::min_bar_spacing(&self.series).unwrap_or(x.span());
+            x = ChartRange::new(x.min - pad, x.max + pad);
+        }
+        let pad = y.span() * 0.05;
+        (x, ChartRange::new(if y.min == 0.0 {0.0} else {y.min - pad}, if y.max == 0.0 {0.0} else {y.max + pad}))
+    }
+    
+    fn min_bar_spacing(series: &[ChartSeries]) -> Option<f64> {
+        let mut spacing: Option<f64> = None;
+        for series in series.iter().filter( | s | s.kind == ChartKind::Bar) {
+            for w in series.points.windows(2) {
+                let d = w[1].x - w[0].x;
+                if d > 0.0 {
+                    spacing = Some(spacing.map_or(d, | s | s.min(d)));
+                }
+            }
+        }
+        spacing
+    }
+    
+    fn mapping(&self) -> ChartMap {
+        let (x, y) = self.view_ranges();
+        ChartMap {x, y, rect: self.plot_rect}
+    }
+    
+    // the points of a sorted series that fall inside the x range, plus one on each side
+    // so lines run on to the edge of the plot
+    fn visible_range(points: &[DVec2], x: ChartRange) -> std::ops::Range<usize> {
+        let start = points.partition_point( | p | p.x < x.min).saturating_sub(1);
+        let end = (points.partition_point( | p | p.x <= x.max) + 1).min(points.len());
+        start..end.max(start)
+    }
+    
+    // with more points than pixels only the first, lowest, highest and last point of every
+    // pixel column are kept, which draws the same picture with a fraction of the segments
+    fn decimate(m: &ChartMap, points: &[DVec2]) -> Vec<DVec2> {
+        let mut out = Vec::new();
+        let width = m.rect.size.x;
+        if (points.len() as f64) < width * 4.0 {
+            out.extend(points.iter().map( | p | m.map(*p)));
+            return out
+        }
+        let mut column = None;
+        let mut bucket: Vec<(usize, DVec2)> = Vec::new();
+        let flush = | bucket: &mut Vec<(usize, DVec2)>, out: &mut Vec<DVec2> | {
+            if bucket.is_empty() {
+                return
+            }
+            let first = bucket[0];
+            let last = bucket[bucket.len() - 1];
+            let min = *bucket.iter().min_by( | a, b | a.1.y.total_cmp(&b.1.y)).unwrap();
+            let max = *bucket.iter().max_by( | a, b | a.1.y.total_cmp(&b.1.y)).unwrap();
+            let mut keep = [first, min, max, last];
+            keep.sort_by_key( | k | k.0);
+            let mut prev = usize::MAX;
+            for (i, p) in keep {
+                if i != prev {
+                    out.push(p);
+                    prev = i;
+                }
+            }
+            bucket.clear();
+        };
+        for (i, p) in points.iter().enumerate() {
+            let sp = m.map(*p);
+            let col = sp.x.floor() as i64;
+            if column != Some(col) {
+                flush(&mut bucket, &mut out);
+                column = Some(col);
+            }
+            bucket.push((i, sp));
+        }
+        flush(&mut bucket, &mut out);
+        out
+    }
+    
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
+        let rect = cx.walk_turtle_with_area(&mut self.area, walk);
+        self.draw_bg.draw_abs(cx, rect);
+        let pad = self.plot_padding;
+        self.plot_rect = Rect {
+            pos: rect.pos + dvec2(pad.left + self.y_axis_width, pad.top),
+            size: dvec2(
+                (rect.size.x - pad.left - pad.right - self.y_axis_width).max(0.0),
+                (rect.size.y - pad.top - pad.bottom - self.x_axis_height).max(0.0)
+            )
+        };
+        let plot = self.plot_rect;
+        if plot.size.x <= 0.0 || plot.size.y <= 0.0 {
+            return
+        }
+        let m = self.mapping();
+        let (x_range, y_range) = (m.x, m.y);
+        
+        // grid and tick labels
+        let x_step = tick_step(x_range, plot.size.x / self.x_tick_spacing);
+        let y_step = tick_step(y_range, plot.size.y / self.y_tick_spacing);
+        let label_height = self.draw_label.text_style.font_size * self.draw_label.text_style.height_factor * self.draw_label.font_scale;
+        for v in ticks(x_range, x_step) {
+            let sx = m.map(dvec2(v, 0.0)).x.round();
+            self.draw_grid.draw_abs(cx, Rect {pos: dvec2(sx, plot.pos.y), size: dvec2(1.0, plot.size.y)});
+            let text = format_value(v, x_step);
+            let width = self.draw_label.compute_geom(cx, Walk::fit(), &text).map_or(0.0, | g | g.measured_width);
+            self.draw_label.draw_abs(cx, dvec2(sx - 0.5 * width, plot.pos.y + plot.size.y + 4.0), &text);
+        }
+        for v in ticks(y_range, y_step) {
+            let sy = m.map(dvec2(0.0, v)).y.round();
+            self.draw_grid.draw_abs(cx, Rect {pos: dvec2(plot.pos.x, sy), size: dvec2(plot.size.x, 1.0)});
+            let text = format_value(v, y_step);
+            let width = self.draw_label.compute_geom(cx, Walk::fit(), &text).map_or(0.0, | g | g.measured_width);
+            self.draw_label.draw_abs(cx, dvec2(plot.pos.x - width - 6.0, sy - 0.5 * label_height), &text);
+        }
+        
+        // the series are clipped to the plot
+        cx.begin_turtle(Walk::fixed_size(plot.size).with_abs_pos(plot.pos), Layout::default());
+        let series = std::mem::take(&mut self.series);
+        let bar_series: Vec<usize> = (0..series.len()).filter( | i | series[*i].kind == ChartKind::Bar).collect();
+        let bar_spacing = Self::min_bar_spacing(&series).unwrap_or(x_range.span());
+        for (index, series) in series.iter().enumerate() {
+            let color = series_color(series, index);
+            let points = &series.points;
+            match series.kind {
+                ChartKind::Line | ChartKind::Area => {
+                    let range = Self::visible_range(points, x_range);
+                    let screen = Self::decimate(&m, &points[range]);
+                    if series.kind == ChartKind::Area {
+                        let base = m.map(dvec2(0.0, 0.0.max(y_range.min).min(y_range.max))).y;
+                        self.draw_area.color = vec4(color.x, color.y, color.z, color.w * self.area_alpha as f32);
+                        self.draw_area.base = base as f32;
+                        self.draw_area.begin_many_instances(cx);
+                        for w in screen.windows(2) {
+                            self.draw_area.p1 = w[0].into();
+                            self.draw_area.p2 = w[1].into();
+                            let top = w[0].y.min(w[1].y).min(base);
+                            let bottom = w[0].y.max(w[1].y).max(base);
+                            self.draw_area.draw_abs(cx, Rect {
+                                pos: dvec2(w[0].x, top),
+                                size: dvec2(w[1].x - w[0].x, bottom - top)
+                            });
+                        }
+                        self.draw_area.end_many_instances(cx);
+                    }
+                    self.draw_line.color = color;
+                    let half = self.draw_line.line_width as f64 * 0.5 + 1.0;
+                    self.draw_line.begin_many_instances(cx);
+                    for w in screen.windows(2) {
+                        self.draw_line.p1 = w[0].into();
+                        self.draw_line.p2 = w[1].into();
+                        let min = dvec2(w[0].x.min(w[1].x), w[0].y.min(w[1].y)) - dvec2(half, half);
+                        let max = dvec2(w[0].x.max(w[1].x), w[0].y.max(w[1].y)) + dvec2(half, half);
+                        self.draw_line.draw_abs(cx, Rect {pos: min, size: max - min});
+                    }
+                    self.draw_line.end_many_instances(cx);
+                }
+                ChartKind::Bar => {
+                    let slot = bar_series.iter().position( | i | *i == index).unwrap_or(0);
+                    let group = bar_spacing / x_range.span() * plot.size.x * self.bar_fill;
+                    let width = (group / bar_series.len() as f64).max(1.0);
+                    let base = m.map(dvec2(0.0, 0.0)).y;
+                    self.draw_bar.color = color;
+                    self.draw_bar.begin_many_instances(cx);
+                    for p in &points[Self::visible_range(points, x_range)] {
+                        let sp = m.map(*p);
+                        let left = sp.x - 0.5 * group + slot as f64 * width;
+                        self.draw_bar.draw_abs(cx, Rect {
+                            pos: dvec2(left, sp.y.min(base)),
+                            size: dvec2(width, (sp.y - base).abs())
+                        });
+                    }
+                    self.draw_bar.end_many_instances(cx);
+                }
+                ChartKind::Scatter => {
+                    let r = self.point_radius;
+                    self.draw_point.color = color;
+                    self.draw_point.begin_many_instances(cx);
+                    for p in points {
+                        let sp = m.map(*p);
+                        if sp.x < plot.pos.x - r || sp.x > plot.pos.x + plot.size.x + r || sp.y < plot.pos.y - r || sp.y > plot.pos.y + plot.size.y + r {
+                            continue
+                        }
+                        self.draw_point.draw_abs(cx, Rect {pos: sp - dvec2(r, r), size: dvec2(2.0 * r, 2.0 * r)});
+                    }
+                    self.draw_point.end_many_instances(cx);
+                }
+            }
+        }
+        
+        self.series = series;
+        
+        if let Some(hover) = self.hover {
+            self.draw_hover(cx, &m, hover, x_step, y_step);
+        }
+        cx.end_turtle();
+        
+        self.draw_axis.draw_abs(cx, Rect {pos: dvec2(plot.pos.x, plot.pos.y), size: dvec2(1.0, plot.size.y)});
+        self.draw_axis.draw_abs(cx, Rect {pos: dvec2(plot.pos.x, plot.pos.y + plot.size.y), size: dvec2(plot.size.x, 1.0)});
+    }
+    
+    // the point of every series closest to the pointer, by x for sorted series and by distance for scatter
+    fn nearest_points(&self, m: &ChartMap, hover: DVec2) -> Vec<(usize, DVec2)> {
+        let data = m.unmap(hover);
+        let mut found = Vec::new();
+        for (index, series) in self.series.iter().enumerate() {
+            let points = &series.points;
+            if points.is_empty() {
+                continue
+            }
+            let nearest = if series.kind == ChartKind::Scatter {
+                points.iter().min_by( | a, b | {
+                    (m.map(**a) - hover).length().total_cmp(&(m.map(**b) - hover).length())
+                }).cloned()
+            }
+            else {
+                let i = points.partition_point( | p | p.x < data.x);
+                let candidates = [i.checked_sub(1), Some(i)];
+                candidates.iter().flatten().filter_map( | i | points.get(*i)).min_by( | a, b | {
+                    (a.x - data.x).abs().total_cmp(&(b.x - data.x).abs())
+                }).cloned()
+            };
+            if let Some(p) = nearest {
+                found.push((index, p));
+            }
+        }
+        found
+    }
+    
+    fn draw_hover(&mut self, cx: &mut Cx2d, m: &ChartMap, hover: DVec2, x_step: f64, y_step: f64) {
+        let plot = self.plot_rect;
+        let found = self.nearest_points(m, hover);
+        // the vertical line snaps to the closest data x so the readout matches what is drawn
+        let snap_x = found.iter().map( | (_, p) | m.map(*p).x).min_by( | a, b | (a - hover.x).abs().total_cmp(&(b - hover.x).abs())).unwrap_or(hover.x);
+        self.draw_crosshair.draw_abs(cx, Rect {pos: dvec2(snap_x.round(), plot.pos.y), size: dvec2(1.0, plot.size.y)});
+        self.draw_crosshair.draw_abs(cx, Rect {pos: dvec2(plot.pos.x, hover.y.round()), size: dvec2(plot.size.x, 1.0)});
+        
+        let r = self.point_radius + 1.0;
+        let mut lines = vec![(None, format!("x: {}", format_value(m.unmap(dvec2(snap_x, 0.0)).x, x_step * 0.1)))];
+        for (index, p) in &found {
+            let sp = m.map(*p);
+            if (sp.x - snap_x).abs() > 0.5 && self.series[*index].kind != ChartKind::Scatter {
+                continue
+            }
+            self.draw_point.color = series_color(&self.series[*index], *index);
+            self.draw_point.draw_abs(cx, Rect {pos: sp - dvec2(r, r), size: dvec2(2.0 * r, 2.0 * r)});
+            let name = &self.series[*index].name;
+            lines.push((Some(*index), format!("{}: {}", name, format_value(p.y, y_step * 0.1))));
+        }
+        
+        // size the readout first so it can flip to the other side of the pointer near the edges
+        let line_height = self.draw_readout_text.get_line_spacing();
+        let swatch = 10.0;
+        let pad = 6.0;
+        let mut width: f64 = 0.0;
+        for (_, text) in &lines {
+            let w = self.draw_readout_text.compute_geom(cx, Walk::fit(), text).map_or(0.0, | g | g.measured_width);
+            width = width.max(w + swatch);
+        }
+        let size = dvec2(width + 2.0 * pad, lines.len() as f64 * line_height + 2.0 * pad);
+        let mut pos = hover + dvec2(12.0, 12.0);
+        if pos.x + size.x > plot.pos.x + plot.size.x {
+            pos.x = hover.x - 12.0 - size.x;
+        }
+        if pos.y + size.y > plot.pos.y + plot.size.y {
+            pos.y = hover.y - 12.0 - size.y;
+        }
+        self.draw_readout.draw_abs(cx, Rect {pos, size});
+        for (i, (series, text)) in lines.iter().enumerate() {
+            let line_pos = pos + dvec2(pad, pad + i as f64 * line_height);
+            if let Some(series) = series {
+                self.draw_point.color = series_color(&self.series[*series], *series);
+                self.draw_point.draw_abs(cx, Rect {pos: line_pos + dvec2(0.0, 0.5 * line_height - 4.0), size: dvec2(6.0, 6.0)});
+            }
+            self.draw_readout_text.draw_abs(cx, line_pos + dvec2(swatch, 0.0), text);
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, WidgetRef)]
+pub struct ChartRef(WidgetRef);
+
+impl ChartRef {
+    pub fn set_series(&self, cx: &mut Cx, series: Vec<ChartSeries>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_series(cx, series);
+        }
+    }
+    
+    pub fn push_series(&self, cx: &mut Cx, series: ChartSeries) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.push_series(cx, series);
+        }
+    }
+    
+    pub fn clear(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.clear(cx);
+        }
+    }
+    
+    pub fn set_view(&self, cx: &mut Cx, x: ChartRange, y: ChartRange) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_view(cx, x, y);
+        }
+    }
+    
+    pub fn reset_view(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.reset_view(cx);
+        }
+    }
+    
+    pub fn view_changed(&self, actions: &WidgetActions) -> Option<(ChartRange, ChartRange)> {
+        if let Some(item) = actions.find_single_action(self.widget_uid()) {
+            if let ChartAction::ViewChanged(x, y) = item.action() {
+                return Some((x, y))
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Default, WidgetSet)]
+pub struct ChartSet(WidgetSet);
+
+impl ChartSet {
+    pub fn set_view(&self, cx: &mut Cx, x: ChartRange, y: ChartRange) {
+        for item in self.iter() {
+            item.set_view(cx, x, y);
+        }
+    }
+    
+    pub fn reset_view(&self, cx: &mut Cx) {
+        for item in self.iter() {
+            item.reset_view(cx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn tick_step_is_nice() {
+        assert_eq!(tick_step(ChartRange::new(0.0, 10.0), 10.0), 1.0);
+        assert_eq!(tick_step(ChartRange::new(0.0, 23.0), 10.0), 2.0);
+        assert_eq!(tick_step(ChartRange::new(-40.0, 10.0), 10.0), 5.0);
+        assert_eq!(tick_step(ChartRange::new(0.0, 0.8), 10.0), 0.1);
+        assert_eq!(tick_step(ChartRange::new(0.0, 1e12), 5.0), 2e11);
+        // a target below one tick is one tick
+        assert_eq!(tick_step(ChartRange::new(0.0, 10.0), 0.0), 10.0);
+    }
+    
+    #[test]
+    fn tick_step_of_degenerate_ranges() {
+        assert_eq!(tick_step(ChartRange::new(3.0, 3.0), 10.0), 1.0);
+        assert_eq!(tick_step(ChartRange::new(10.0, 0.0), 10.0), 1.0);
+        assert_eq!(tick_step(ChartRange::new(0.0, f64::NAN), 10.0), 1.0);
+        assert_eq!(tick_step(ChartRange::new(-f64::MAX, f64::MAX), 10.0), 1.0);
+        let huge = tick_step(ChartRange::new(0.0, 1e300), 10.0);
+        assert!(huge.is_finite() && (huge / 1e299 - 1.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn ticks_land_on_multiples_of_the_step() {
+        assert_eq!(ticks(ChartRange::new(-0.5, 3.2), 1.0), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(ticks(ChartRange::new(0.0, 1.0), 0.1), vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0]);
+        assert_eq!(ticks(ChartRange::new(-0.13, 0.13), 0.05), vec![-0.1, -0.05, 0.0, 0.05, 0.1]);
+        assert_eq!(ticks(ChartRange::new(2e11, 1e12), 2e11), vec![2e11, 4e11, 6e11, 8e11, 1e12]);
+    }
+    
+    #[test]
+    fn ticks_do_not_drift() {
+        // far from zero and a thousand ticks along, every tick is still the decimal it stands for
+        let ticks = ticks(ChartRange::new(1e6, 1e6 + 100.0), 0.1);
+        assert_eq!(ticks.len(), 1001);
+        for (i, v) in ticks.iter().enumerate() {
+            let expected: f64 = format!("{}.{}", 1_000_000 + i / 10, i % 10).parse().unwrap();
+            assert_eq!(*v, expected);
+        }
+    }
+    
+    #[test]
+    fn ticks_of_degenerate_ranges() {
+        assert_eq!(ticks(ChartRange::new(3.0, 3.0), 1.0), vec![3.0]);
+        assert!(ticks(ChartRange::new(10.0, 0.0), 1.0).is_empty());
+        assert!(ticks(ChartRange::new(0.0, 10.0), 0.0).is_empty());
+        assert!(ticks(ChartRange::new(0.0, 1e6), 1.0).is_empty());
+        assert!(ticks(ChartRange::new(-f64::MAX, f64::MAX), 1.0).is_empty());
+        assert!(ticks(ChartRange::new(1e300, 1e300 + 1e285), 1e284).is_empty());
+    }
+    
+    fn map(width: f64) -> ChartMap {
+        ChartMap {
+            x: ChartRange::new(0.0, 1.0),
+            y: ChartRange::new(-1.0, 1.0),
+            rect: Rect {pos: dvec2(0.0, 0.0), size: dvec2(width, 100.0)}
+        }
+    }
+    
+    #[test]
+    fn decimate_keeps_short_series() {
+        let points: Vec<DVec2> = (0..30).map( | i | dvec2(i as f64 / 30.0, 0.0)).collect();
+        assert_eq!(Chart::decimate(&map(10.0), &points).len(), 30);
+    }
+    
+    #[test]
+    fn decimate_keeps_extremes_of_every_column() {
+        let m = map(10.0);
+        let count = 1000;
+        let points: Vec<DVec2> = (0..count).map( | i | {
+            let t = i as f64 / (count - 1) as f64;
+            dvec2(t, (t * 37.0).sin() * 0.9)
+        }).collect();
+        let out = Chart::decimate(&m, &points);
+        assert!(out.len() <= 11 * 4);
+        assert_eq!(out[0], m.map(points[0]));
+        assert_eq!(out[out.len() - 1], m.map(points[count - 1]));
+        // every column has the lowest and highest point of its points, and nothing else
+        for column in 0..=10 {
+            let in_column = | p: &DVec2 | p.x.floor() as i64 == column;
+            let mapped: Vec<DVec2> = points.iter().map( | p | m.map(*p)).filter(in_column).collect();
+            let kept: Vec<DVec2> = out.iter().cloned().filter(in_column).collect();
+            if mapped.is_empty() {
+                assert!(kept.is_empty());
+                continue;
+            }
+            let lowest = mapped.iter().map( | p | p.y).fold(f64::INFINITY, f64::min);
+            let highest = mapped.iter().map( | p | p.y).fold(f64::NEG_INFINITY, f64::max);
+            assert!(kept.iter().any( | p | p.y == lowest));
+            assert!(kept.iter().any( | p | p.y == highest));
+            assert!(kept.len() <= 4);
+            assert!(kept.iter().all( | p | mapped.contains(p)));
+        }
+        // points stay in order
+        assert!(out.windows(2).all( | w | w[0].x <= w[1].x));
+    }
+}
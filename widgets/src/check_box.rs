@@ -49,6 +49,7 @@ pub struct CheckBox {
     #[live] text: RcStringMut,
     
     #[live] bind: String,
+    #[live] nav_order: NavOrder,
 }
 
 impl LiveHook for CheckBox{
@@ -75,6 +76,10 @@ impl CheckBox {
     pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, CheckBoxAction)) {
         self.animator_handle_event(cx, event);
         
+        if let Some(AccessAction::Activate) = Cx2d::access_action_hit(event, self.draw_check.area()) {
+            self.toggle(cx, dispatch_action);
+        }
+        
         match event.hits(cx, self.draw_check.area()) {
             Hit::FingerHoverIn(_) => {
                 cx.set_cursor(MouseCursor::Arrow);
@@ -84,30 +89,54 @@ impl CheckBox {
                 self.animator_play(cx, id!(hover.off));
             },
             Hit::FingerDown(_fe) => {
-                if self.animator_in_state(cx, id!(selected.on)) {
-                    self.animator_play(cx, id!(selected.off));
-                    dispatch_action(cx, CheckBoxAction::Change(false));
-                }
-                else {
-                    self.animator_play(cx, id!(selected.on));
-                    dispatch_action(cx, CheckBoxAction::Change(true));
-                }
+                self.toggle(cx, dispatch_action);
             },
             Hit::FingerUp(_fe) => {
                 
             }
             Hit::FingerMove(_fe) => {
                 
+            }
+            Hit::KeyFocus(_) => {
+                self.animator_play(cx, id!(focus.on));
+            }
+            Hit::KeyFocusLost(_) => {
+                self.animator_play(cx, id!(focus.off));
+            }
+            Hit::KeyDown(ke) if !ke.is_repeat => match ke.key_code {
+                KeyCode::Space | KeyCode::ReturnKey | KeyCode::NumpadEnter => {
+                    self.toggle(cx, dispatch_action);
+                }
+                _ => ()
             }
             _ => ()
         }
     }
     
+    fn toggle(&mut self, cx: &mut Cx, dispatch_action: &mut dyn FnMut(&mut Cx, CheckBoxAction)) {
+        if self.animator_in_state(cx, id!(selected.on)) {
+            self.animator_play(cx, id!(selected.off));
+            dispatch_action(cx, CheckBoxAction::Change(false));
+        }
+        else {
+            self.animator_play(cx, id!(selected.on));
+            dispatch_action(cx, CheckBoxAction::Change(true));
+        }
+    }
+    
     pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
         self.draw_check.begin(cx, walk, self.layout);
         self.draw_text.draw_walk(cx, self.label_walk, self.label_align, self.text.as_ref());
         self.draw_icon.draw_walk(cx, self.icon_walk);
         self.draw_check.end(cx);
+        let checked = self.animator_in_state(cx, id!(selected.on));
+        cx.add_nav_stop_with_access(
+            self.draw_check.area(),
+            NavRole::CheckBox,
+            self.nav_order,
+            Margin::default(),
+            AccessInfo::named(self.text.as_ref()).with_checked(checked)
+        );
     }
 }
 
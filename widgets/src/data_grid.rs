@@ -0,0 +1,746 @@
+use {
+    std::ops::Range,
+    crate::{
+        widget::*,
+        makepad_derive_widget::*,
+        makepad_draw::*,
+        scroll_bar::{ScrollBar, ScrollBarAction}
+    }
+};
+
+live_design!{
+    DrawGridCell = {{DrawGridCell}} {}
+    DrawGridHeader = {{DrawGridHeader}} {}
+    DataGridBase = {{DataGrid}} {}
+}
+
+// a table that only draws the rows and columns in view, cells are pulled in with next_visible_cell
+
+#[derive(Live, LiveHook)]#[repr(C)]
+struct DrawGridCell {
+    #[deref] draw_super: DrawQuad,
+    #[live] selected: f32,
+    #[live] cursor: f32,
+    #[live] odd: f32,
+    #[live] focus: f32,
+}
+
+#[derive(Live, LiveHook)]#[repr(C)]
+struct DrawGridHeader {
+    #[deref] draw_super: DrawQuad,
+    #[live] hover: f32,
+    #[live] sort: f32,
+    #[live] dragging: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortOrder {
+    None,
+    Ascending,
+    Descending
+}
+
+#[derive(Clone, Debug)]
+pub struct DataGridColumn {
+    pub id: LiveId,
+    pub label: String,
+    pub width: f64,
+}
+
+impl DataGridColumn {
+    pub fn new(id: LiveId, label: &str, width: f64) -> Self {
+        Self {id, label: label.to_string(), width}
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DataGridCell {
+    pub row: u64,
+    pub column: LiveId,
+}
+
+#[derive(Clone, WidgetAction)]
+pub enum DataGridAction {
+    Sort {column: LiveId, order: SortOrder},
+    CursorMoved(DataGridCell),
+    Activate(DataGridCell),
+    ColumnsChanged,
+    None
+}
+
+#[derive(Clone)]
+enum DataGridDrawState {
+    Cells {next: usize},
+    End
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum HeaderDrag {
+    None,
+    Resize {column: usize, start_width: f64},
+    Move {visual: usize, dragging: bool},
+}
+
+#[derive(Live)]
+pub struct DataGrid {
+    #[walk] walk: Walk,
+    #[layout] layout: Layout,
+    
+    #[live] draw_bg: DrawQuad,
+    #[live] draw_cell: DrawGridCell,
+    #[live] draw_header: DrawGridHeader,
+    #[live] draw_text: DrawText,
+    #[live] draw_header_text: DrawText,
+    #[live] draw_drop: DrawQuad,
+    
+    #[live] cell_layout: Layout,
+    #[live] header_layout: Layout,
+    #[live(24.0)] row_height: f64,
+    #[live(26.0)] header_height: f64,
+    #[live] frozen_columns: usize,
+    #[live(30.0)] min_column_width: f64,
+    #[live(4.0)] resize_handle: f64,
+    #[live(false)] select_cells: bool,
+    
+    #[live] scroll_bar_x: ScrollBar,
+    #[live] scroll_bar_y: ScrollBar,
+    
+    #[rust] area: Area,
+    #[rust] columns: Vec<DataGridColumn>,
+    // visual position to index in columns
+    #[rust] order: Vec<usize>,
+    #[rust] row_count: u64,
+    #[rust] scroll_pos: DVec2,
+    #[rust] rect: Rect,
+    #[rust] col_x: Vec<f64>,
+    #[rust] total_width: f64,
+    #[rust] frozen_width: f64,
+    
+    #[rust] sort_column: Option<LiveId>,
+    #[rust(SortOrder::None)] sort_order: SortOrder,
+    #[rust] hover_header: Option<usize>,
+    #[rust(HeaderDrag::None)] header_drag: HeaderDrag,
+    #[rust] drop_visual: Option<usize>,
+    
+    // the cursor and selection anchor as (row, visual column)
+    #[rust] cursor: Option<(u64, usize)>,
+    #[rust] anchor: Option<(u64, usize)>,
+    #[rust] selecting: bool,
+    
+    #[rust] visible_cells: Vec<(u64, usize)>,
+    #[rust] draw_state: DrawStateWrap<DataGridDrawState>,
+}
+
+impl LiveHook for DataGrid {
+    fn before_live_design(cx: &mut Cx) {
+        register_widget!(cx, DataGrid)
+    }
+}
+
+impl DataGrid {
+    
+    // replaces the columns, when the ids are unchanged the user resized widths and order are kept
+    pub fn set_columns(&mut self, cx: &mut Cx, columns: Vec<DataGridColumn>) {
+        let same = columns.len() == self.columns.len() && columns.iter().zip(self.columns.iter()).all( | (a, b) | a.id == b.id);
+        if same {
+            for (new, old) in columns.into_iter().zip(self.columns.iter_mut()) {
+                old.label = new.label;
+            }
+        }
+        else {
+            self.order = (0..columns.len()).collect();
+            self.columns = columns;
+            self.cursor = None;
+            self.anchor = None;
+        }
+        self.area.redraw(cx);
+    }
+    
+    pub fn columns(&self) -> impl Iterator<Item = &DataGridColumn> {
+        self.order.iter().map(move | index | &self.columns[*index])
+    }
+    
+    pub fn set_row_count(&mut self, cx: &mut Cx, row_count: u64) {
+        if self.row_count != row_count {
+            self.row_count = row_count;
+            if row_count == 0 {
+                self.cursor = None;
+                self.anchor = None;
+            }
+            else {
+                for (row, _) in self.cursor.iter_mut().chain(self.anchor.iter_mut()) {
+                    *row = (*row).min(row_count - 1);
+                }
+            }
+            self.area.redraw(cx);
+        }
+    }
+    
+    pub fn set_sort(&mut self, cx: &mut Cx, column: LiveId, order: SortOrder) {
+        self.sort_column = Some(column);
+        self.sort_order = order;
+        self.area.redraw(cx);
+    }
+    
+    pub fn cursor(&self) -> Option<DataGridCell> {
+        self.cursor.map( | (row, visual) | self.cell(row, visual))
+    }
+    
+    pub fn selected_rows(&self) -> Option<Range<u64>> {
+        let (cursor, anchor) = (self.cursor?, self.anchor.or(self.cursor)?);
+        Some(cursor.0.min(anchor.0)..cursor.0.max(anchor.0) + 1)
+    }
+    
+    // the selected columns in visual order, in row selection mode thats all of them
+    pub fn selected_columns(&self) -> Vec<LiveId> {
+        let (cursor, anchor) = match (self.cursor, self.anchor.or(self.cursor)) {
+            (Some(cursor), Some(anchor)) => (cursor, anchor),
+            _ => return Vec::new()
+        };
+        let visual = if self.select_cells {cursor.1.min(anchor.1)..cursor.1.max(anchor.1) + 1} else {0..self.order.len()};
+        visual.map( | v | self.columns[self.order[v]].id).collect()
+    }
+    
+    pub fn is_selected(&self, row: u64, column: LiveId) -> bool {
+        match self.order.iter().position( | index | self.columns[*index].id == column) {
+            Some(visual) => self.is_selected_visual(row, visual),
+            None => false
+        }
+    }
+    
+    fn is_selected_visual(&self, row: u64, visual: usize) -> bool {
+        let (cursor, anchor) = match (self.cursor, self.anchor.or(self.cursor)) {
+            (Some(cursor), Some(anchor)) => (cursor, anchor),
+            _ => return false
+        };
+        if row < cursor.0.min(anchor.0) || row > cursor.0.max(anchor.0) {
+            return false
+        }
+        !self.select_cells || (visual >= cursor.1.min(anchor.1) && visual <= cursor.1.max(anchor.1))
+    }
+    
+    fn cell(&self, row: u64, visual: usize) -> DataGridCell {
+        DataGridCell {row, column: self.columns[self.order[visual]].id}
+    }
+    
+    fn frozen(&self) -> usize {
+        self.frozen_columns.min(self.order.len())
+    }
+    
+    fn layout_columns(&mut self) {
+        self.col_x.clear();
+        let mut x = 0.0;
+        for index in &self.order {
+            self.col_x.push(x);
+            x += self.columns[*index].width;
+        }
+        self.total_width = x;
+        self.frozen_width = self.col_x.get(self.frozen()).cloned().unwrap_or(x);
+    }
+    
+    fn column_width(&self, visual: usize) -> f64 {
+        self.columns[self.order[visual]].width
+    }
+    
+    fn body_rect(&self) -> Rect {
+        Rect {
+            pos: self.rect.pos + dvec2(0.0, self.header_height),
+            size: dvec2(self.rect.size.x, (self.rect.size.y - self.header_height).max(0.0))
+        }
+    }
+    
+    // where a visual column is on screen, frozen columns dont scroll horizontally
+    fn column_screen_x(&self, visual: usize) -> f64 {
+        if visual < self.frozen() {
+            self.rect.pos.x + self.col_x[visual]
+        }
+        else {
+            self.rect.pos.x + self.col_x[visual] - self.scroll_pos.x
+        }
+    }
+    
+    fn column_at(&self, abs_x: f64) -> Option<usize> {
+        (0..self.order.len()).find( | v | {
+            let x = self.column_screen_x(*v);
+            if *v >= self.frozen() && x + self.column_width(*v) <= self.rect.pos.x + self.frozen_width {
+                return false
+            }
+            abs_x >= x && abs_x < x + self.column_width(*v)
+        })
+    }
+    
+    fn cell_at(&self, abs: DVec2) -> Option<(u64, usize)> {
+        let body = self.body_rect();
+        if !body.contains(abs) || self.row_count == 0 {
+            return None
+        }
+        let row = ((abs.y - body.pos.y + self.scroll_pos.y) / self.row_height).floor() as u64;
+        let visual = self.column_at(abs.x)?;
+        Some((row.min(self.row_count - 1), visual))
+    }
+    
+    // the header column whose right edge is under the finger, for resizing
+    fn resize_edge_at(&self, abs_x: f64) -> Option<usize> {
+        (0..self.order.len()).rev().find( | v | {
+            (self.column_screen_x(*v) + self.column_width(*v) - abs_x).abs() <= self.resize_handle
+        })
+    }
+    
+    fn drop_visual_at(&self, abs_x: f64) -> usize {
+        (0..self.order.len()).find( | v | {
+            abs_x < self.column_screen_x(*v) + self.column_width(*v) * 0.5
+        }).unwrap_or(self.order.len())
+    }
+    
+    fn scroll_to_cursor(&mut self, cx: &mut Cx) {
+        let (row, visual) = if let Some(cursor) = self.cursor {cursor} else {return};
+        let body = self.body_rect();
+        let mut scroll = self.scroll_pos;
+        let y = row as f64 * self.row_height;
+        if y < scroll.y {
+            scroll.y = y;
+        }
+        else if y + self.row_height > scroll.y + body.size.y {
+            scroll.y = y + self.row_height - body.size.y;
+        }
+        if visual >= self.frozen() {
+            let x = self.col_x[visual] - self.frozen_width;
+            let view = body.size.x - self.frozen_width;
+            if x < scroll.x {
+                scroll.x = x;
+            }
+            else if x + self.column_width(visual) > scroll.x + view {
+                scroll.x = x + self.column_width(visual) - view;
+            }
+        }
+        if scroll != self.scroll_pos {
+            self.scroll_bar_x.set_scroll_pos(cx, scroll.x);
+            self.scroll_bar_y.set_scroll_pos(cx, scroll.y);
+            self.scroll_pos = dvec2(self.scroll_bar_x.get_scroll_pos(), self.scroll_bar_y.get_scroll_pos());
+        }
+    }
+    
+    fn move_cursor(&mut self, cx: &mut Cx, row: i64, visual: i64, extend: bool, dispatch_action: &mut dyn FnMut(&mut Cx, DataGridAction)) {
+        if self.row_count == 0 || self.order.len() == 0 {
+            return
+        }
+        let row = row.max(0).min(self.row_count as i64 - 1) as u64;
+        let visual = visual.max(0).min(self.order.len() as i64 - 1) as usize;
+        if !extend || self.anchor.is_none() {
+            self.anchor = Some((row, visual));
+        }
+        if self.cursor != Some((row, visual)) {
+            self.cursor = Some((row, visual));
+            dispatch_action(cx, DataGridAction::CursorMoved(self.cell(row, visual)));
+        }
+        self.scroll_to_cursor(cx);
+        self.area.redraw(cx);
+    }
+    
+    fn begin(&mut self, cx: &mut Cx2d, walk: Walk) {
+        cx.begin_turtle(walk, self.layout);
+        self.rect = cx.turtle().rect();
+        self.layout_columns();
+        
+        // collect the cells in view, the frozen columns first so each region is drawn in one go
+        self.visible_cells.clear();
+        let body = self.body_rect();
+        if self.row_count > 0 && self.row_height > 0.0 {
+            let first_row = (self.scroll_pos.y / self.row_height).floor().max(0.0) as u64;
+            let last_row = (((self.scroll_pos.y + body.size.y) / self.row_height).ceil() as u64).min(self.row_count);
+            let frozen = self.frozen();
+            let scroll_view = body.size.x - self.frozen_width;
+            for (start, end) in [(0, frozen), (frozen, self.order.len())] {
+                for row in first_row..last_row {
+                    for visual in start..end {
+                        if visual >= frozen {
+                            let x = self.col_x[visual] - self.frozen_width - self.scroll_pos.x;
+                            if x + self.column_width(visual) <= 0.0 || x >= scroll_view {
+                                continue
+                            }
+                        }
+                        self.visible_cells.push((row, visual));
+                    }
+                }
+            }
+        }
+        self.draw_bg.draw_abs(cx, self.rect);
+    }
+    
+    fn begin_region(&mut self, cx: &mut Cx2d, frozen: bool) {
+        let body = self.body_rect();
+        let rect = if frozen {
+            Rect {pos: body.pos, size: dvec2(self.frozen_width.min(body.size.x), body.size.y)}
+        }
+        else {
+            Rect {pos: body.pos + dvec2(self.frozen_width, 0.0), size: dvec2((body.size.x - self.frozen_width).max(0.0), body.size.y)}
+        };
+        cx.begin_turtle(Walk {
+            abs_pos: Some(rect.pos),
+            margin: Default::default(),
+            width: Size::Fixed(rect.size.x),
+            height: Size::Fixed(rect.size.y)
+        }, Layout {flow: Flow::Overlay, ..Layout::default()});
+    }
+    
+    pub fn next_visible_cell(&mut self, cx: &mut Cx2d) -> Option<DataGridCell> {
+        let next = match self.draw_state.get() {
+            Some(DataGridDrawState::Cells {next}) => next,
+            _ => return None
+        };
+        let frozen = self.frozen();
+        if next > 0 {
+            cx.end_turtle();
+        }
+        let prev_frozen = next > 0 && self.visible_cells[next - 1].1 < frozen;
+        let (row, visual) = if let Some(cell) = self.visible_cells.get(next) {*cell} else {
+            if next > 0 {
+                cx.end_turtle();
+            }
+            self.draw_state.set(DataGridDrawState::End);
+            return None
+        };
+        let is_frozen = visual < frozen;
+        if next == 0 || prev_frozen != is_frozen {
+            if next > 0 {
+                cx.end_turtle();
+            }
+            self.begin_region(cx, is_frozen);
+        }
+        
+        let rect = Rect {
+            pos: dvec2(self.column_screen_x(visual), self.body_rect().pos.y + row as f64 * self.row_height - self.scroll_pos.y),
+            size: dvec2(self.column_width(visual), self.row_height)
+        };
+        self.draw_cell.selected = if self.is_selected_visual(row, visual) {1.0} else {0.0};
+        self.draw_cell.cursor = if self.select_cells && self.cursor == Some((row, visual)) {1.0} else {0.0};
+        self.draw_cell.odd = (row & 1) as f32;
+        self.draw_cell.focus = if cx.has_key_focus(self.area) {1.0} else {0.0};
+        self.draw_cell.draw_abs(cx, rect);
+        cx.begin_turtle(Walk {
+            abs_pos: Some(rect.pos),
+            margin: Default::default(),
+            width: Size::Fixed(rect.size.x),
+            height: Size::Fixed(rect.size.y)
+        }, self.cell_layout);
+        
+        self.draw_state.set(DataGridDrawState::Cells {next: next + 1});
+        Some(self.cell(row, visual))
+    }
+    
+    // draws text in the current cell, call this between next_visible_cell calls
+    pub fn draw_cell_text(&mut self, cx: &mut Cx2d, text: &str) {
+        self.draw_text.draw_walk(cx, Walk::fill_fit(), Align::default(), text);
+    }
+    
+    fn draw_header_cell(&mut self, cx: &mut Cx2d, visual: usize) {
+        let column = &self.columns[self.order[visual]];
+        let rect = Rect {
+            pos: dvec2(self.column_screen_x(visual), self.rect.pos.y),
+            size: dvec2(column.width, self.header_height)
+        };
+        self.draw_header.hover = if self.hover_header == Some(visual) {1.0} else {0.0};
+        self.draw_header.sort = match (self.sort_column == Some(column.id), self.sort_order) {
+            (true, SortOrder::Ascending) => 1.0,
+            (true, SortOrder::Descending) => -1.0,
+            _ => 0.0
+        };
+        self.draw_header.dragging = if let HeaderDrag::Move {visual: v, dragging: true} = self.header_drag {
+            if v == visual {1.0} else {0.0}
+        } else {0.0};
+        self.draw_header.begin(cx, Walk {
+            abs_pos: Some(rect.pos),
+            margin: Default::default(),
+            width: Size::Fixed(rect.size.x),
+            height: Size::Fixed(rect.size.y)
+        }, self.header_layout);
+        let label = self.columns[self.order[visual]].label.clone();
+        self.draw_header_text.draw_walk(cx, Walk::fill_fit(), Align::default(), &label);
+        self.draw_header.end(cx);
+    }
+    
+    fn end(&mut self, cx: &mut Cx2d) {
+        let frozen = self.frozen();
+        
+        // the header scrolls horizontally with the body, except above the frozen columns
+        cx.begin_turtle(Walk {
+            abs_pos: Some(self.rect.pos + dvec2(self.frozen_width, 0.0)),
+            margin: Default::default(),
+            width: Size::Fixed((self.rect.size.x - self.frozen_width).max(0.0)),
+            height: Size::Fixed(self.header_height)
+        }, Layout {flow: Flow::Overlay, ..Layout::default()});
+        for visual in frozen..self.order.len() {
+            let x = self.col_x[visual] - self.frozen_width - self.scroll_pos.x;
+            if x + self.column_width(visual) > 0.0 && x < self.rect.size.x - self.frozen_width {
+                self.draw_header_cell(cx, visual);
+            }
+        }
+        cx.end_turtle();
+        if frozen > 0 {
+            cx.begin_turtle(Walk {
+                abs_pos: Some(self.rect.pos),
+                margin: Default::default(),
+                width: Size::Fixed(self.frozen_width.min(self.rect.size.x)),
+                height: Size::Fixed(self.header_height)
+            }, Layout {flow: Flow::Overlay, ..Layout::default()});
+            for visual in 0..frozen {
+                self.draw_header_cell(cx, visual);
+            }
+            cx.end_turtle();
+        }
+        
+        if let Some(drop_visual) = self.drop_visual {
+            let x = if drop_visual < self.order.len() {
+                self.column_screen_x(drop_visual)
+            }
+            else {
+                self.column_screen_x(self.order.len() - 1) + self.column_width(self.order.len() - 1)
+            };
+            self.draw_drop.draw_abs(cx, Rect {
+                pos: dvec2(x - 1.0, self.rect.pos.y),
+                size: dvec2(2.0, self.rect.size.y)
+            });
+        }
+        
+        let view_total = dvec2(self.total_width, self.header_height + self.row_count as f64 * self.row_height);
+        self.scroll_pos.x = self.scroll_bar_x.draw_scroll_bar(cx, Axis::Horizontal, self.rect, view_total);
+        self.scroll_pos.y = self.scroll_bar_y.draw_scroll_bar(cx, Axis::Vertical, self.rect, view_total);
+        
+        cx.end_turtle_with_area(&mut self.area);
+    }
+    
+    pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, DataGridAction)) {
+        let mut scrolled = false;
+        for scroll_bar in [&mut self.scroll_bar_x, &mut self.scroll_bar_y] {
+            let mut on_scroll = | _cx: &mut Cx, action | {
+                if let ScrollBarAction::Scroll {..} = action {
+                    scrolled = true;
+                }
+            };
+            scroll_bar.handle_event_with(cx, event, &mut on_scroll);
+            scroll_bar.handle_scroll_event(cx, event, self.area, &mut on_scroll);
+        }
+        if scrolled {
+            self.scroll_pos = dvec2(self.scroll_bar_x.get_scroll_pos(), self.scroll_bar_y.get_scroll_pos());
+            self.area.redraw(cx);
+        }
+        if self.scroll_bar_x.is_area_captured(cx) || self.scroll_bar_y.is_area_captured(cx) {
+            return
+        }
+        
+        match event.hits(cx, self.area) {
+            Hit::KeyFocus(_) | Hit::KeyFocusLost(_) => {
+                self.area.redraw(cx);
+            }
+            Hit::KeyDown(ke) => {
+                let (row, visual) = self.cursor.map( | (r, v) | (r as i64, v as i64)).unwrap_or((0, 0));
+                let page = (self.body_rect().size.y / self.row_height).floor().max(1.0) as i64;
+                let extend = ke.modifiers.shift;
+                match ke.key_code {
+                    KeyCode::ArrowUp => self.move_cursor(cx, row - 1, visual, extend, dispatch_action),
+                    KeyCode::ArrowDown => self.move_cursor(cx, row + 1, visual, extend, dispatch_action),
+                    KeyCode::ArrowLeft => self.move_cursor(cx, row, visual - 1, extend, dispatch_action),
+                    KeyCode::ArrowRight => self.move_cursor(cx, row, visual + 1, extend, dispatch_action),
+                    KeyCode::PageUp => self.move_cursor(cx, row - page, visual, extend, dispatch_action),
+                    KeyCode::PageDown => self.move_cursor(cx, row + page, visual, extend, dispatch_action),
+                    KeyCode::Home if ke.modifiers.control => self.move_cursor(cx, 0, visual, extend, dispatch_action),
+                    KeyCode::End if ke.modifiers.control => self.move_cursor(cx, i64::MAX, visual, extend, dispatch_action),
+                    KeyCode::Home => self.move_cursor(cx, row, 0, extend, dispatch_action),
+                    KeyCode::End => self.move_cursor(cx, row, i64::MAX, extend, dispatch_action),
+                    KeyCode::KeyA if ke.modifiers.control || ke.modifiers.logo => {
+                        if self.row_count > 0 && self.order.len() > 0 {
+                            self.anchor = Some((0, 0));
+                            self.cursor = Some((self.row_count - 1, self.order.len() - 1));
+                            self.area.redraw(cx);
+                        }
+                    }
+                    KeyCode::ReturnKey | KeyCode::NumpadEnter => if let Some(cell) = self.cursor() {
+                        dispatch_action(cx, DataGridAction::Activate(cell));
+                    }
+                    _ => ()
+                }
+            }
+            Hit::FingerHoverIn(fe) | Hit::FingerHoverOver(fe) => {
+                let in_header = fe.abs.y < self.rect.pos.y + self.header_height;
+                let hover = if in_header {self.column_at(fe.abs.x)} else {None};
+                if hover != self.hover_header {
+                    self.hover_header = hover;
+                    self.area.redraw(cx);
+                }
+                if in_header && self.resize_edge_at(fe.abs.x).is_some() {
+                    cx.set_cursor(MouseCursor::ColResize);
+                }
+            }
+            Hit::FingerHoverOut(_) => {
+                if self.hover_header.is_some() {
+                    self.hover_header = None;
+                    self.area.redraw(cx);
+                }
+            }
+            Hit::FingerDown(fe) => {
+                cx.set_key_focus(self.area);
+                if fe.abs.y < self.rect.pos.y + self.header_height {
+                    if let Some(visual) = self.resize_edge_at(fe.abs.x) {
+                        self.header_drag = HeaderDrag::Resize {column: self.order[visual], start_width: self.column_width(visual)};
+                    }
+                    else if let Some(visual) = self.column_at(fe.abs.x) {
+                        self.header_drag = HeaderDrag::Move {visual, dragging: false};
+                    }
+                }
+                else if let Some((row, visual)) = self.cell_at(fe.abs) {
+                    self.selecting = true;
+                    self.move_cursor(cx, row as i64, visual as i64, fe.modifiers.shift, dispatch_action);
+                    if fe.tap_count == 2 {
+                        dispatch_action(cx, DataGridAction::Activate(self.cell(row, visual)));
+                    }
+                }
+            }
+            Hit::FingerMove(fe) => match self.header_drag {
+                HeaderDrag::Resize {column, start_width} => {
+                    cx.set_cursor(MouseCursor::ColResize);
+                    self.columns[column].width = (start_width + fe.abs.x - fe.abs_start.x).max(self.min_column_width);
+                    self.area.redraw(cx);
+                }
+                HeaderDrag::Move {visual, dragging} => {
+                    if dragging || (fe.abs.x - fe.abs_start.x).abs() > 5.0 {
+                        self.header_drag = HeaderDrag::Move {visual, dragging: true};
+                        self.drop_visual = Some(self.drop_visual_at(fe.abs.x));
+                        self.area.redraw(cx);
+                    }
+                }
+                HeaderDrag::None => if self.selecting {
+                    // dragging across the body extends the selection, clamped to the view
+                    let body = self.body_rect();
+                    let abs = dvec2(
+                        fe.abs.x.max(body.pos.x).min(body.pos.x + body.size.x - 1.0),
+                        fe.abs.y.max(body.pos.y).min(body.pos.y + body.size.y - 1.0)
+                    );
+                    if let Some((row, visual)) = self.cell_at(abs) {
+                        self.move_cursor(cx, row as i64, visual as i64, true, dispatch_action);
+                    }
+                }
+            }
+            Hit::FingerUp(fe) => {
+                match self.header_drag {
+                    HeaderDrag::Resize {..} => {
+                        dispatch_action(cx, DataGridAction::ColumnsChanged);
+                    }
+                    HeaderDrag::Move {visual, dragging: true} => {
+                        let drop = self.drop_visual_at(fe.abs.x);
+                        let target = if drop > visual {drop - 1} else {drop};
+                        if target != visual {
+                            let moved = self.order.remove(visual);
+                            self.order.insert(target, moved);
+                            self.cursor = None;
+                            self.anchor = None;
+                            dispatch_action(cx, DataGridAction::ColumnsChanged);
+                        }
+                    }
+                    HeaderDrag::Move {visual, dragging: false} => if fe.is_over {
+                        // clicking a header cycles its sort order
+                        let column = self.columns[self.order[visual]].id;
+                        let order = match (self.sort_column == Some(column), self.sort_order) {
+                            (true, SortOrder::Ascending) => SortOrder::Descending,
+                            (true, SortOrder::Descending) => SortOrder::None,
+                            _ => SortOrder::Ascending
+                        };
+                        self.sort_column = Some(column);
+                        self.sort_order = order;
+                        dispatch_action(cx, DataGridAction::Sort {column, order});
+                    }
+                    HeaderDrag::None => ()
+                }
+                self.header_drag = HeaderDrag::None;
+                self.drop_visual = None;
+                self.selecting = false;
+                self.area.redraw(cx);
+            }
+            _ => ()
+        }
+    }
+}
+
+impl Widget for DataGrid {
+    fn redraw(&mut self, cx: &mut Cx) {
+        self.area.redraw(cx);
+    }
+    
+    fn handle_widget_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)) {
+        let uid = self.widget_uid();
+        self.handle_event_with(cx, event, &mut | cx, action | {
+            dispatch_action(cx, WidgetActionItem::new(action.into(), uid))
+        });
+    }
+    
+    fn walk(&mut self, _cx:&mut Cx) -> Walk {self.walk}
+    
+    fn draw_walk_widget(&mut self, cx: &mut Cx2d, walk: Walk) -> WidgetDraw {
+        if self.draw_state.begin(cx, DataGridDrawState::Cells {next: 0}) {
+            self.begin(cx, walk);
+            return WidgetDraw::hook_above()
+        }
+        if self.draw_state.get().is_some() {
+            // cells the caller didnt pull still need their turtles closed
+            while self.next_visible_cell(cx).is_some() {}
+            self.end(cx);
+            self.draw_state.end();
+        }
+        WidgetDraw::done()
+    }
+}
+
+#[derive(Clone, Default, PartialEq, WidgetRef)]
+pub struct DataGridRef(WidgetRef);
+
+impl DataGridRef {
+    pub fn set_columns(&self, cx: &mut Cx, columns: Vec<DataGridColumn>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_columns(cx, columns);
+        }
+    }
+    
+    pub fn set_sort(&self, cx: &mut Cx, column: LiveId, order: SortOrder) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_sort(cx, column, order);
+        }
+    }
+    
+    pub fn cursor(&self) -> Option<DataGridCell> {
+        if let Some(inner) = self.borrow() {
+            inner.cursor()
+        }
+        else {
+            None
+        }
+    }
+    
+    pub fn selected_rows(&self) -> Option<Range<u64>> {
+        if let Some(inner) = self.borrow() {
+            inner.selected_rows()
+        }
+        else {
+            None
+        }
+    }
+    
+    pub fn sorted(&self, actions: &WidgetActions) -> Option<(LiveId, SortOrder)> {
+        if let Some(item) = actions.find_single_action(self.widget_uid()) {
+            if let DataGridAction::Sort {column, order} = item.action() {
+                return Some((column, order))
+            }
+        }
+        None
+    }
+    
+    pub fn activated(&self, actions: &WidgetActions) -> Option<DataGridCell> {
+        if let Some(item) = actions.find_single_action(self.widget_uid()) {
+            if let DataGridAction::Activate(cell) = item.action() {
+                return Some(cell)
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Default, WidgetSet)]
+pub struct DataGridSet(WidgetSet);
@@ -3,7 +3,6 @@ use crate::{
     debug_view::DebugView,
     makepad_draw::*,
     nav_control::NavControl,
-    // window_menu::*,
     button::*,
     widget::*,
     view::*,
@@ -30,7 +29,6 @@ pub struct DesktopWindow {
     #[live] depth_texture: Texture,
     #[live] hide_caption_on_fullscreen: bool, 
     #[deref] view: View,
-    // #[rust(WindowMenu::new(cx))] _window_menu: WindowMenu,
     /*#[rust(Menu::main(vec![
         Menu::sub("App", vec![
             //Menu::item("Quit App", Cx::command_quit()),
@@ -96,6 +94,13 @@ pub enum DesktopWindowAction {
 }
 
 impl DesktopWindow {
+    
+    // the accessibility tree of what was drawn in this window during the last frame
+    pub fn access_tree(&self, cx: &mut Cx) -> AccessTree {
+        let title = cx.windows[self.window.window_id()].create_title.clone();
+        Cx2d::build_access_tree(cx, self.main_draw_list.draw_list_id(), &title)
+    }
+    
     pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, DesktopWindowAction)) {
         
         self.debug_view.handle_event(cx, event);
@@ -186,6 +191,9 @@ impl DesktopWindow {
             Cx2d::reset_fonts_atlas(cx);
             Cx2d::reset_icon_atlas(cx);
         }
+        if let Event::Signal = event {
+            Cx2d::poll_system_fonts(cx);
+        }
         if let Event::MouseMove(ev) = event {
             if let OsType::LinuxDirect = cx.os_type() {
                 // ok move our mouse cursor
@@ -218,6 +226,7 @@ impl DesktopWindow {
     pub fn end(&mut self, cx: &mut Cx2d) {
         //while self.frame.draw_widget_continue(cx).is_not_done() {}
         self.debug_view.draw(cx);
+        self.nav_control.draw(cx);
         
         // lets draw our cursor
         if let OsType::LinuxDirect = cx.os_type() {
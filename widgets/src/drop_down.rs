@@ -34,10 +34,12 @@ pub struct DropDown {
     #[live] popup_shift: DVec2,
     
     #[rust] is_open: bool,
+    #[rust] highlighted_item: usize,
     
     #[live] selected_item: usize,
     
     #[layout] layout: Layout,
+    #[live] nav_order: NavOrder,
 }
 
 #[derive(Default, Clone)]
@@ -92,9 +94,18 @@ impl DropDown {
         let lb = map.get_mut(&self.popup_menu.unwrap()).unwrap();
         let node_id = LiveId(self.selected_item as u64).into();
         lb.init_select_item(node_id);
+        self.highlighted_item = self.selected_item;
         cx.sweep_lock(self.draw_bg.area());
     }
     
+    fn highlight_item(&mut self, cx: &mut Cx, item: usize) {
+        self.highlighted_item = item;
+        let global = cx.global::<PopupMenuGlobal>().clone();
+        let mut map = global.map.borrow_mut();
+        let menu = map.get_mut(&self.popup_menu.unwrap()).unwrap();
+        menu.highlight_item(cx, LiveId(item as u64).into());
+    }
+    
     pub fn set_closed(&mut self, cx: &mut Cx) {
         self.is_open = false;
         self.draw_bg.redraw(cx);
@@ -104,6 +115,12 @@ impl DropDown {
     pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, DropDownAction)) {
         self.animator_handle_event(cx, event);
         
+        if let Some(AccessAction::Activate) = Cx2d::access_action_hit(event, self.draw_bg.area()) {
+            if !self.is_open {
+                self.set_open(cx);
+            }
+        }
+        
         if self.is_open && self.popup_menu.is_some() {
             // ok so how will we solve this one
             let global = cx.global::<PopupMenuGlobal>().clone();
@@ -148,7 +165,28 @@ impl DropDown {
             Hit::KeyFocus(_) => {
                 self.animator_play(cx, id!(focus.on));
             }
+            // while open the arrows move the highlight in the menu, enter picks it
+            Hit::KeyDown(ke) if self.is_open => match ke.key_code {
+                KeyCode::ArrowUp if self.highlighted_item > 0 => {
+                    self.highlight_item(cx, self.highlighted_item - 1);
+                }
+                KeyCode::ArrowDown if self.highlighted_item + 1 < self.labels.len() => {
+                    self.highlight_item(cx, self.highlighted_item + 1);
+                }
+                KeyCode::ReturnKey | KeyCode::NumpadEnter | KeyCode::Space => {
+                    self.selected_item = self.highlighted_item;
+                    dispatch_action(cx, DropDownAction::Select(self.selected_item, self.values.get(self.selected_item).cloned().unwrap_or(LiveValue::None)));
+                    self.set_closed(cx);
+                }
+                KeyCode::Escape => {
+                    self.set_closed(cx);
+                }
+                _ => ()
+            }
             Hit::KeyDown(ke) => match ke.key_code {
+                KeyCode::ReturnKey | KeyCode::NumpadEnter | KeyCode::Space => {
+                    self.set_open(cx);
+                }
                 KeyCode::ArrowUp => {
                     if self.selected_item > 0 {
                         self.selected_item -= 1;
@@ -213,7 +251,14 @@ impl DropDown {
         }
         self.draw_bg.end(cx);
         
-        cx.add_nav_stop(self.draw_bg.area(), NavRole::DropDown, Margin::default());
+        let access_value = self.labels.get(self.selected_item).cloned().unwrap_or_default();
+        cx.add_nav_stop_with_access(
+            self.draw_bg.area(),
+            NavRole::DropDown,
+            self.nav_order,
+            Margin::default(),
+            AccessInfo::default().with_value(&access_value).with_expanded(self.is_open)
+        );
         
         if self.is_open && self.popup_menu.is_some() {
             //cx.set_sweep_lock(self.draw_bg.area());
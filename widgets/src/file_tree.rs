@@ -90,6 +90,7 @@ pub struct FileTree {
     #[live] node_height: f64,
     
     #[live] draw_scroll_shadow: DrawScrollShadow,
+    #[live] nav_order: NavOrder,
     
     #[rust] draw_state: DrawStateWrap<()>,
     
@@ -101,6 +102,9 @@ pub struct FileTree {
     
     #[rust] count: usize,
     #[rust] stack: Vec<f64>,
+    // the nodes of the last draw in order, with their depth. keyboard navigation walks these
+    #[rust] nav_nodes: Vec<(FileNodeId, usize)>,
+    #[rust] selected_name: String,
 }
 
 impl LiveHook for FileTree {
@@ -242,6 +246,7 @@ impl FileTree {
     pub fn begin(&mut self, cx: &mut Cx2d, walk: Walk) {
         self.scroll_bars.begin(cx, walk, self.layout);
         self.count = 0;
+        self.nav_nodes.clear();
     }
     
     pub fn end(&mut self, cx: &mut Cx2d) {
@@ -258,6 +263,20 @@ impl FileTree {
         self.draw_scroll_shadow.draw(cx, dvec2(0., 0.));
         self.scroll_bars.end(cx);
         
+        let mut access = AccessInfo::named(&self.selected_name).with_selected(self.selected_node_id.is_some());
+        if let Some(node_id) = self.selected_node_id {
+            if self.is_folder(node_id) {
+                access = access.with_expanded(self.open_nodes.contains(&node_id));
+            }
+        }
+        cx.add_nav_stop_with_access(
+            self.scroll_bars.area(),
+            NavRole::TreeItem,
+            self.nav_order,
+            Margin::default(),
+            access
+        );
+        
         let selected_node_id = self.selected_node_id;
         self.tree_nodes.retain_visible_and( | node_id, _ | Some(*node_id) == selected_node_id);
     }
@@ -290,16 +309,21 @@ impl FileTree {
         if scale > 0.2 {
             self.count += 1;
         }
+        self.push_nav_node(node_id, name);
         
         let is_open = self.open_nodes.contains(&node_id);
         
         if self.should_node_draw(cx) {
             let folder_node = self.folder_node;
+            let is_selected = self.selected_node_id == Some(node_id);
             let (tree_node, _) = self.tree_nodes.get_or_insert(cx, node_id, | cx | {
                 let mut tree_node = FileTreeNode::new_from_ptr(cx, folder_node);
                 if is_open {
                     tree_node.set_folder_is_open(cx, true, Animate::No)
                 }
+                if is_selected {
+                    tree_node.set_is_selected(cx, true, Animate::No)
+                }
                 (tree_node, live_id!(folder_node))
             });
             
@@ -331,15 +355,123 @@ impl FileTree {
         if scale > 0.2 {
             self.count += 1;
         }
+        self.push_nav_node(node_id, name);
         if self.should_node_draw(cx) {
             let file_node = self.file_node;
+            let is_selected = self.selected_node_id == Some(node_id);
             let (tree_node, _) = self.tree_nodes.get_or_insert(cx, node_id, | cx | {
-                (FileTreeNode::new_from_ptr(cx, file_node), live_id!(file_node))
+                let mut tree_node = FileTreeNode::new_from_ptr(cx, file_node);
+                if is_selected {
+                    tree_node.set_is_selected(cx, true, Animate::No)
+                }
+                (tree_node, live_id!(file_node))
             });
             tree_node.draw_file(cx, name, Self::is_even(self.count), self.node_height, self.stack.len(), scale);
         }
     }
     
+    fn push_nav_node(&mut self, node_id: FileNodeId, name: &str) {
+        // nodes of a closing folder are still drawn while they animate, but arent reachable
+        if self.stack.last().cloned().unwrap_or(1.0) < 1.0 {
+            return
+        }
+        if self.selected_node_id == Some(node_id) {
+            self.selected_name.clear();
+            self.selected_name.push_str(name);
+        }
+        self.nav_nodes.push((node_id, self.stack.len()));
+    }
+    
+    fn select_node(&mut self, cx: &mut Cx, node_id: FileNodeId) {
+        if let Some(last_selected) = self.selected_node_id {
+            if last_selected != node_id {
+                if let Some((node, _)) = self.tree_nodes.get_mut(&last_selected) {
+                    node.set_is_selected(cx, false, Animate::Yes);
+                    node.set_is_focussed(cx, false, Animate::Yes);
+                }
+            }
+        }
+        self.selected_node_id = Some(node_id);
+        let has_focus = cx.has_key_focus(self.scroll_bars.area());
+        if let Some((node, _)) = self.tree_nodes.get_mut(&node_id) {
+            node.set_is_selected(cx, true, Animate::Yes);
+            node.set_is_focussed(cx, has_focus, Animate::Yes);
+        }
+        if let Some(index) = self.nav_nodes.iter().position( | (id, _) | *id == node_id) {
+            self.scroll_bars.scroll_into_view(cx, Rect {
+                pos: dvec2(0.0, index as f64 * self.node_height),
+                size: dvec2(0.0, self.node_height)
+            });
+        }
+        self.scroll_bars.redraw(cx);
+    }
+    
+    fn activate_node(&mut self, cx: &mut Cx, node_id: FileNodeId, dispatch_action: &mut dyn FnMut(&mut Cx, FileTreeAction)) {
+        if self.is_folder(node_id) {
+            let is_open = self.open_nodes.contains(&node_id);
+            self.set_folder_is_open(cx, node_id, !is_open, Animate::Yes);
+            self.scroll_bars.redraw(cx);
+            dispatch_action(cx, FileTreeAction::FolderClicked(node_id));
+        }
+        else {
+            dispatch_action(cx, FileTreeAction::FileClicked(node_id));
+        }
+    }
+    
+    fn handle_key_down(&mut self, cx: &mut Cx, ke: &KeyEvent, dispatch_action: &mut dyn FnMut(&mut Cx, FileTreeAction)) {
+        if self.nav_nodes.len() == 0 {
+            return
+        }
+        let current = self.selected_node_id.and_then( | selected | {
+            self.nav_nodes.iter().position( | (id, _) | *id == selected)
+        });
+        let index = if let Some(index) = current {index} else {
+            if let KeyCode::ArrowUp | KeyCode::ArrowDown | KeyCode::Home | KeyCode::End = ke.key_code {
+                self.select_node(cx, self.nav_nodes[0].0);
+            }
+            return
+        };
+        let (node_id, depth) = self.nav_nodes[index];
+        match ke.key_code {
+            KeyCode::ArrowUp if index > 0 => {
+                self.select_node(cx, self.nav_nodes[index - 1].0);
+            }
+            KeyCode::ArrowDown if index + 1 < self.nav_nodes.len() => {
+                self.select_node(cx, self.nav_nodes[index + 1].0);
+            }
+            KeyCode::Home => {
+                self.select_node(cx, self.nav_nodes[0].0);
+            }
+            KeyCode::End => {
+                self.select_node(cx, self.nav_nodes[self.nav_nodes.len() - 1].0);
+            }
+            KeyCode::ArrowRight if self.is_folder(node_id) => {
+                if !self.open_nodes.contains(&node_id) {
+                    self.set_folder_is_open(cx, node_id, true, Animate::Yes);
+                    self.scroll_bars.redraw(cx);
+                }
+                else if let Some((child_id, child_depth)) = self.nav_nodes.get(index + 1) {
+                    if *child_depth > depth {
+                        self.select_node(cx, *child_id);
+                    }
+                }
+            }
+            KeyCode::ArrowLeft => {
+                if self.is_folder(node_id) && self.open_nodes.contains(&node_id) {
+                    self.set_folder_is_open(cx, node_id, false, Animate::Yes);
+                    self.scroll_bars.redraw(cx);
+                }
+                else if let Some((parent_id, _)) = self.nav_nodes[0..index].iter().rev().find( | (_, d) | *d < depth) {
+                    self.select_node(cx, *parent_id);
+                }
+            }
+            KeyCode::ReturnKey | KeyCode::NumpadEnter | KeyCode::Space => {
+                self.activate_node(cx, node_id, dispatch_action);
+            }
+            _ => ()
+        }
+    }
+    
     pub fn forget(&mut self) {
         self.tree_nodes.clear();
     }
@@ -441,15 +573,28 @@ impl FileTree {
             }
         }
         
+        if let Some(AccessAction::Activate) = Cx2d::access_action_hit(event, self.scroll_bars.area()) {
+            if let Some(node_id) = self.selected_node_id {
+                self.activate_node(cx, node_id, dispatch_action);
+            }
+        }
+        
         match event.hits(cx, self.scroll_bars.area()) {
+            Hit::KeyDown(ke) => {
+                self.handle_key_down(cx, &ke, dispatch_action);
+            }
             Hit::KeyFocus(_) => {
                 if let Some(node_id) = self.selected_node_id {
-                    self.tree_nodes.get_mut(&node_id).unwrap().0.set_is_focussed(cx, true, Animate::Yes);
+                    if let Some((node, _)) = self.tree_nodes.get_mut(&node_id) {
+                        node.set_is_focussed(cx, true, Animate::Yes);
+                    }
                 }
             }
             Hit::KeyFocusLost(_) => {
                 if let Some(node_id) = self.selected_node_id {
-                    self.tree_nodes.get_mut(&node_id).unwrap().0.set_is_focussed(cx, false, Animate::Yes);
+                    if let Some((node, _)) = self.tree_nodes.get_mut(&node_id) {
+                        node.set_is_focussed(cx, false, Animate::Yes);
+                    }
                 }
             }
             _ => ()
@@ -4,6 +4,7 @@ use crate::{
     makepad_draw::*,
     widget::*
 };
+use std::rc::Rc;
 
 live_design!{
     ImageBase = {{Image}} {}
@@ -19,6 +20,13 @@ pub struct Image {
     #[live] fit: ImageFit,
     #[live] source: LiveDependency,
     #[live] texture: Option<Texture>,
+    // plays animated gif, webp and apng sources, otherwise they show their first frame
+    #[live(true)] animate: bool,
+    
+    #[rust] animation: Option<Rc<DecodedImage>>,
+    #[rust] animation_start: Option<f64>,
+    #[rust] animation_frame: usize,
+    #[rust] next_frame: NextFrame,
 }
 
 impl ImageCacheImpl for Image {
@@ -29,6 +37,15 @@ impl ImageCacheImpl for Image {
     fn set_texture(&mut self, texture: Option<Texture>) {
         self.texture = texture;
     }
+    
+    fn set_animation(&mut self, cx: &mut Cx, animation: Option<Rc<DecodedImage>>) {
+        self.animation = animation;
+        self.animation_start = None;
+        self.animation_frame = 0;
+        if self.animate && self.animation.is_some() {
+            self.next_frame = cx.new_next_frame();
+        }
+    }
 }
 
 impl LiveHook for Image {
@@ -54,6 +71,12 @@ impl Widget for Image {
         self.walk
     }
     
+    fn handle_widget_event_with(&mut self, cx: &mut Cx, event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)) {
+        if let Some(ne) = self.next_frame.is_event(event) {
+            self.animate_frame(cx, ne.time);
+        }
+    }
+    
     fn draw_walk_widget(&mut self, cx: &mut Cx2d, walk: Walk) -> WidgetDraw {
         self.draw_walk(cx, walk)
     }
@@ -61,6 +84,29 @@ impl Widget for Image {
 
 impl Image {
     
+    fn animate_frame(&mut self, cx: &mut Cx, time: f64) {
+        let Some(animation) = &self.animation else {
+            return
+        };
+        if !self.animate {
+            return
+        }
+        let start = *self.animation_start.get_or_insert(time);
+        let elapsed = time - start;
+        let frame = animation.frame_at(elapsed);
+        if frame != self.animation_frame {
+            self.animation_frame = frame;
+            if let Some(texture) = &self.texture {
+                ImageBuffer::from_decoded(animation, frame).into_texture(cx, texture);
+                self.draw_bg.redraw(cx);
+            }
+        }
+        // stop asking for frames once the last play ended
+        if animation.plays == 0 || elapsed < animation.total_duration() * animation.plays as f64 {
+            self.next_frame = cx.new_next_frame();
+        }
+    }
+    
     pub fn draw_walk(&mut self, cx: &mut Cx2d, mut walk: Walk) -> WidgetDraw {
         // alright we get a walk. depending on our aspect ratio
         // we change either nothing, or width or height
@@ -134,6 +180,35 @@ impl ImageRef {
         }
     }
     
+    pub fn load_gif_from_data(&self, cx: &mut Cx, data: &[u8]) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_gif_from_data(cx, data)
+        }
+    }
+    
+    pub fn load_webp_from_data(&self, cx: &mut Cx, data: &[u8]) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_webp_from_data(cx, data)
+        }
+    }
+    
+    pub fn load_apng_from_data(&self, cx: &mut Cx, data: &[u8]) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_apng_from_data(cx, data)
+        }
+    }
+    
+    /// Plays an animated image from its first frame, or stops it on the current one
+    pub fn set_animate(&self, cx: &mut Cx, animate: bool) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.animate = animate;
+            inner.animation_start = None;
+            if animate && inner.animation.is_some() {
+                inner.next_frame = cx.new_next_frame();
+            }
+        }
+    }
+    
     pub fn set_texture(&self, texture: Option<Texture>) {
         if let Some(mut inner) = self.borrow_mut() {
             inner.texture = texture
@@ -1,7 +1,13 @@
 use crate::{makepad_draw::*};
-use std::collections::HashMap;
-use makepad_zune_jpeg::JpegDecoder;
-use makepad_zune_png::PngDecoder;
+use std::{collections::HashMap, rc::Rc};
+use makepad_zune_jpeg::{JpegDecoder, JpegEncoder};
+use makepad_zune_png::{
+    PngDecoder,
+    PngEncoder,
+    makepad_zune_core::{bit_depth::BitDepth, colorspace::ColorSpace, options::EncoderOptions},
+};
+use makepad_image_formats::{decode_apng, decode_gif, decode_webp};
+pub use makepad_image_formats::{DecodedImage, ImageFrame};
 
 
 #[derive(Live, LiveHook)]
@@ -68,6 +74,7 @@ impl ImageBuffer {
                 format: TextureFormat::ImageBGRA,
                 width: Some(self.width),
                 height: Some(self.height),
+                ..Default::default()
             },
         );
         texture.swap_image_u32(cx, &mut self.data);
@@ -109,16 +116,115 @@ impl ImageBuffer {
             }
         }
     }
+    
+    pub fn from_decoded(image: &DecodedImage, frame: usize) -> Self {
+        ImageBuffer {
+            width: image.width,
+            height: image.height,
+            data: image.frames[frame].data.clone()
+        }
+    }
+    
+    pub fn from_readback(readback: &ReadbackEvent) -> Result<Self, String> {
+        match &readback.data {
+            Ok(data) => Ok(ImageBuffer {
+                width: readback.width,
+                height: readback.height,
+                data: data.as_ref().clone()
+            }),
+            Err(err) => Err(format!("Error reading back pixels: {}", err))
+        }
+    }
+    
+    /// The pixels as interleaved RGBA bytes, the layout both encoders take
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.data.len() * 4);
+        for pixel in &self.data {
+            out.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8, (pixel >> 24) as u8]);
+        }
+        out
+    }
+    
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        if self.width == 0 || self.height == 0 || self.data.len() != self.width * self.height {
+            return Err("Error encoding PNG: image size does not match its data".to_string())
+        }
+        let rgba = self.to_rgba8();
+        let options = EncoderOptions::new(self.width, self.height, ColorSpace::RGBA, BitDepth::Eight);
+        Ok(PngEncoder::new(&rgba, options).encode())
+    }
+    
+    /// Encodes a baseline JPEG with quality 1 to 100, alpha is dropped
+    pub fn to_jpeg(&self, quality: u8) -> Result<Vec<u8>, String> {
+        let rgba = self.to_rgba8();
+        let options = EncoderOptions::new(self.width, self.height, ColorSpace::RGBA, BitDepth::Eight)
+            .set_quality(quality);
+        JpegEncoder::new(&rgba, options).encode().map_err( | err | format!("Error encoding JPEG: {:?}", err))
+    }
+}
+
+/// The formats that can hold more than one frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AnimatedFormat {
+    Gif,
+    WebP,
+    Apng,
+}
+
+impl AnimatedFormat {
+    /// The format by file extension, for .png only when the file has an
+    /// animation control chunk
+    pub fn detect(path: &str, data: &[u8]) -> Option<Self> {
+        if path.ends_with(".gif") {
+            Some(Self::Gif)
+        }
+        else if path.ends_with(".webp") {
+            Some(Self::WebP)
+        }
+        else if path.ends_with(".apng") {
+            Some(Self::Apng)
+        }
+        else if path.ends_with(".png") && Self::has_actl(data) {
+            Some(Self::Apng)
+        }
+        else {
+            None
+        }
+    }
+    
+    // walks the png chunks up to the image data, acTL has to come before it
+    fn has_actl(data: &[u8]) -> bool {
+        let mut pos = 8;
+        while let Some(header) = data.get(pos..pos + 8) {
+            match &header[4..8] {
+                b"acTL" => return true,
+                b"IDAT" | b"IEND" => return false,
+                _ => pos += 12 + u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize
+            }
+        }
+        false
+    }
+    
+    pub fn decode(&self, data: &[u8]) -> Result<DecodedImage, String> {
+        match self {
+            Self::Gif => decode_gif(data),
+            Self::WebP => decode_webp(data),
+            Self::Apng => decode_apng(data),
+        }
+    }
 }
 
 pub struct ImageCache {
     map: HashMap<String, Texture>,
+    // animations are decoded once, but every user plays them on its own texture
+    animations: HashMap<String, Rc<DecodedImage>>,
 }
 
 impl ImageCache {
     pub fn new() -> Self {
         Self {
             map: HashMap::new(),
+            animations: HashMap::new(),
         }
     }
 }
@@ -126,6 +232,11 @@ impl ImageCache {
 pub trait ImageCacheImpl {
     fn get_texture(&self) -> &Option<Texture>;
     fn set_texture(&mut self, texture: Option<Texture>);
+    
+    /// Called with the frames of animated images, implementors that don't play
+    /// them keep showing the first frame
+    fn set_animation(&mut self, _cx: &mut Cx, _animation: Option<Rc<DecodedImage>>) {
+    }
 
     fn lazy_create_image_cache(&mut self,cx: &mut Cx) {
         if !cx.has_global::<ImageCache>() {
@@ -166,6 +277,38 @@ pub trait ImageCacheImpl {
         }
     }
 
+    fn load_decoded_image(&mut self, cx: &mut Cx, image: Rc<DecodedImage>) {
+        let data = ImageBuffer::from_decoded(&image, 0);
+        if let Some(texture) = self.get_texture() {
+            data.into_texture(cx, texture);
+        }
+        else {
+            self.set_texture(Some(data.into_new_texture(cx)));
+        }
+        self.set_animation(cx, if image.is_animated() {Some(image)} else {None});
+    }
+    
+    fn load_animated_from_data(&mut self, cx: &mut Cx, format: AnimatedFormat, data: &[u8]) {
+        match format.decode(data) {
+            Ok(image) => self.load_decoded_image(cx, Rc::new(image)),
+            Err(err) => {
+                error!("load_animated_from_data: Cannot load {:?} image from data {}", format, err);
+            }
+        }
+    }
+    
+    fn load_gif_from_data(&mut self, cx: &mut Cx, data: &[u8]) {
+        self.load_animated_from_data(cx, AnimatedFormat::Gif, data)
+    }
+    
+    fn load_webp_from_data(&mut self, cx: &mut Cx, data: &[u8]) {
+        self.load_animated_from_data(cx, AnimatedFormat::WebP, data)
+    }
+    
+    fn load_apng_from_data(&mut self, cx: &mut Cx, data: &[u8]) {
+        self.load_animated_from_data(cx, AnimatedFormat::Apng, data)
+    }
+
     fn load_image_dep_by_path(
         &mut self,
         cx: &mut Cx,
@@ -173,11 +316,29 @@ pub trait ImageCacheImpl {
     ) {
         if let Some(texture) = cx.get_global::<ImageCache>().map.get(image_path){
             self.set_texture(Some(texture.clone()));
+            self.set_animation(cx, None);
+        }
+        else if let Some(image) = cx.get_global::<ImageCache>().animations.get(image_path).cloned(){
+            // a texture of our own, a shared one would be animated by every user
+            self.set_texture(None);
+            self.load_decoded_image(cx, image);
         }
         else{
             match cx.get_dependency(image_path) {
                 Ok(data) => {
-                    if image_path.ends_with(".jpg") {
+                    if let Some(format) = AnimatedFormat::detect(image_path, &data) {
+                        match format.decode(&data){
+                            Ok(image)=>{
+                                let image = Rc::new(image);
+                                cx.get_global::<ImageCache>().animations.insert(image_path.to_string(), image.clone());
+                                self.set_texture(None);
+                                self.load_decoded_image(cx, image);
+                            }
+                            Err(err)=>{
+                                error!("load_image_dep_by_path: Cannot load {:?} image from path: {} {}", format, image_path, err);
+                            }
+                        }
+                    } else if image_path.ends_with(".jpg") {
                         match ImageBuffer::from_jpg(&*data){
                             Ok(data)=>{
                                 let texture = data.into_new_texture(cx);
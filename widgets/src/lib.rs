@@ -10,7 +10,14 @@ pub mod label;
 pub mod image;
 pub mod link_label;
 pub mod drop_down;
+pub mod data_grid;
 pub mod popup_menu;
+pub mod modal;
+pub mod tooltip;
+pub mod toast;
+pub mod markdown;
+pub mod chart;
+pub mod svg;
 pub mod check_box;
 pub mod radio_button;
 pub mod text_input;
@@ -67,6 +74,13 @@ pub use crate::{
     text_input::*,
     link_label::*,
     list_view::*,
+    data_grid::*,
+    modal::*,
+    tooltip::*,
+    toast::*,
+    markdown::*,
+    chart::*,
+    svg::*,
     page_flip::*,
     slide_panel::*,
     desktop_window::*,
@@ -122,6 +136,13 @@ pub fn live_design(cx: &mut Cx) {
     crate::radio_button::live_design(cx);
     crate::popup_menu::live_design(cx);
     crate::drop_down::live_design(cx);
+    crate::data_grid::live_design(cx);
+    crate::modal::live_design(cx);
+    crate::tooltip::live_design(cx);
+    crate::toast::live_design(cx);
+    crate::markdown::live_design(cx);
+    crate::chart::live_design(cx);
+    crate::svg::live_design(cx);
     crate::multi_window::live_design(cx);
     crate::designer::live_design(cx);
     crate::hook_widget::live_design(cx);
@@ -0,0 +1,457 @@
+use {
+    crate::{
+        makepad_derive_widget::*,
+        makepad_draw::*,
+        widget::*,
+    },
+    makepad_markdown::*,
+};
+
+live_design!{
+    DrawMarkdownLinkText = {{DrawMarkdownLinkText}} {}
+    DrawMarkdownLinkLine = {{DrawMarkdownLinkLine}} {}
+    MarkdownBase = {{Markdown}} {}
+}
+
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawMarkdownLinkText {
+    #[deref] draw_super: DrawText,
+    #[live] hover: f32,
+}
+
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawMarkdownLinkLine {
+    #[deref] draw_super: DrawQuad,
+    #[live] hover: f32,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TextKind {
+    Normal,
+    Italic,
+    Bold,
+    BoldItalic,
+    Fixed,
+    Link,
+}
+
+struct Piece {
+    text: String,
+    style: SpanStyle,
+    space: bool,
+    hidden: bool,
+    line: usize,
+    x: f64,
+    width: f64,
+    // room around inline code so its background doesnt hug the glyphs
+    pad_left: f64,
+    pad_right: f64,
+}
+
+// renders CommonMark with a style per run of text. blocks are laid out with nested turtles,
+// paragraphs are measured word by word and wrapped to the width the turtle gives them
+#[derive(Live)]
+pub struct Markdown {
+    #[walk] walk: Walk,
+    #[layout] layout: Layout,
+    #[live] text: RcStringMut,
+    
+    #[live] draw_normal: DrawText,
+    #[live] draw_italic: DrawText,
+    #[live] draw_bold: DrawText,
+    #[live] draw_bold_italic: DrawText,
+    #[live] draw_fixed: DrawText,
+    #[live] draw_link: DrawMarkdownLinkText,
+    
+    #[live] draw_code_bg: DrawColor,
+    #[live] draw_quote_bar: DrawColor,
+    #[live] draw_rule: DrawColor,
+    #[live] draw_strike: DrawColor,
+    #[live] draw_link_line: DrawMarkdownLinkLine,
+    
+    #[live(2.0)] heading_scale: f64,
+    #[live(20.0)] list_indent: f64,
+    #[live(12.0)] quote_indent: f64,
+    #[live(3.0)] quote_bar_width: f64,
+    #[live(3.0)] inline_code_padding: f64,
+    #[live] code_layout: Layout,
+    
+    #[rust] doc: MarkdownDoc,
+    #[rust] parsed: Option<String>,
+    #[rust] link_areas: Vec<(usize, Area)>,
+    #[rust] hovered_link: Option<usize>,
+    #[rust] area: Area,
+}
+
+impl LiveHook for Markdown {
+    fn before_live_design(cx: &mut Cx) {
+        register_widget!(cx, Markdown)
+    }
+}
+
+#[derive(Clone, WidgetAction)]
+pub enum MarkdownAction {
+    LinkClicked(String),
+    None
+}
+
+impl Widget for Markdown {
+    fn handle_widget_event_with(
+        &mut self,
+        cx: &mut Cx,
+        event: &Event,
+        dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)
+    ) {
+        let uid = self.widget_uid();
+        self.handle_event_with(cx, event, &mut | cx, action | {
+            dispatch_action(cx, WidgetActionItem::new(action.into(), uid));
+        });
+    }
+    
+    fn walk(&mut self, _cx: &mut Cx) -> Walk {
+        self.walk
+    }
+    
+    fn redraw(&mut self, cx: &mut Cx) {
+        self.area.redraw(cx)
+    }
+    
+    fn draw_walk_widget(&mut self, cx: &mut Cx2d, walk: Walk) -> WidgetDraw {
+        self.draw_walk(cx, walk);
+        WidgetDraw::done()
+    }
+    
+    fn text(&self) -> String {
+        self.text.as_ref().to_string()
+    }
+    
+    fn set_text(&mut self, v: &str) {
+        self.text.as_mut_empty().push_str(v);
+    }
+}
+
+impl Markdown {
+    pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, MarkdownAction)) {
+        for i in 0..self.link_areas.len() {
+            let (link, area) = self.link_areas[i];
+            match event.hits(cx, area) {
+                Hit::FingerHoverIn(_) => {
+                    cx.set_cursor(MouseCursor::Hand);
+                    if self.hovered_link != Some(link) {
+                        self.hovered_link = Some(link);
+                        self.area.redraw(cx);
+                    }
+                }
+                Hit::FingerHoverOut(fe) => {
+                    // a link that wrapped is made of several pieces, moving between them keeps the hover
+                    let still_over = self.link_areas.iter().any( | (l, a) | *l == link && a.get_clipped_rect(cx).contains(fe.abs));
+                    if !still_over && self.hovered_link == Some(link) {
+                        self.hovered_link = None;
+                        self.area.redraw(cx);
+                    }
+                }
+                Hit::FingerUp(fe) => if fe.is_over {
+                    if let Some(url) = self.doc.links.get(link) {
+                        dispatch_action(cx, MarkdownAction::LinkClicked(url.clone()));
+                    }
+                }
+                _ => ()
+            }
+        }
+    }
+    
+    pub fn set_markdown(&mut self, cx: &mut Cx, text: &str) {
+        self.text.as_mut_empty().push_str(text);
+        self.redraw(cx);
+    }
+    
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
+        if self.parsed.as_deref() != Some(self.text.as_ref()) {
+            self.doc = parse_markdown(self.text.as_ref());
+            self.parsed = Some(self.text.as_ref().to_string());
+            self.hovered_link = None;
+        }
+        self.link_areas.clear();
+        
+        // the backgrounds get their draw calls before any text so they end up underneath it
+        self.draw_code_bg.append_to_draw_call(cx);
+        self.draw_quote_bar.append_to_draw_call(cx);
+        self.draw_rule.append_to_draw_call(cx);
+        self.draw_link_line.append_to_draw_call(cx);
+        for kind in [TextKind::Normal, TextKind::Italic, TextKind::Bold, TextKind::BoldItalic, TextKind::Fixed, TextKind::Link] {
+            self.draw_text(kind).new_draw_call(cx);
+        }
+        self.draw_strike.append_to_draw_call(cx);
+        
+        cx.begin_turtle(walk, self.layout);
+        let blocks = std::mem::take(&mut self.doc.blocks);
+        self.draw_blocks(cx, &blocks, self.layout.spacing);
+        self.doc.blocks = blocks;
+        cx.end_turtle_with_area(&mut self.area);
+    }
+    
+    fn draw_blocks(&mut self, cx: &mut Cx2d, blocks: &[Block], spacing: f64) {
+        for block in blocks {
+            match block {
+                Block::Heading {level, spans} => {
+                    // h1 gets the full heading scale, going down to the text size at h6
+                    let scale = 1.0 + (self.heading_scale - 1.0) * (6 - (*level).min(6)) as f64 / 5.0;
+                    self.draw_spans(cx, spans, Some(scale));
+                }
+                Block::Paragraph(spans) => {
+                    self.draw_spans(cx, spans, None);
+                }
+                Block::CodeBlock {code, ..} => {
+                    self.draw_code_bg.begin(cx, Walk::fill_fit(), self.code_layout);
+                    self.draw_fixed.draw_walk(cx, Walk::fit(), Align::default(), code);
+                    self.draw_code_bg.end(cx);
+                }
+                Block::List {start, tight, items} => {
+                    let item_spacing = if *tight {0.0} else {spacing};
+                    cx.begin_turtle(Walk::fill_fit(), Layout {flow: Flow::Down, spacing: item_spacing, ..Layout::default()});
+                    for (i, item) in items.iter().enumerate() {
+                        cx.begin_turtle(Walk::fill_fit(), Layout::flow_right());
+                        let marker = match start {
+                            Some(n) => format!("{}.", n + i as u64),
+                            None => "•".to_string()
+                        };
+                        let marker_walk = Walk {width: Size::Fixed(self.list_indent), ..Walk::fit()};
+                        self.draw_normal.draw_walk(cx, marker_walk, Align::default(), &marker);
+                        cx.begin_turtle(Walk::fill_fit(), Layout {flow: Flow::Down, spacing: item_spacing, ..Layout::default()});
+                        self.draw_blocks(cx, item, spacing);
+                        cx.end_turtle();
+                        cx.end_turtle();
+                    }
+                    cx.end_turtle();
+                }
+                Block::Quote(blocks) => {
+                    let layout = Layout {flow: Flow::Down, spacing, ..Layout::default()};
+                    cx.begin_turtle(Walk::fill_fit(), layout.with_padding_left(self.quote_indent));
+                    self.draw_blocks(cx, blocks, spacing);
+                    let rect = cx.end_turtle();
+                    self.draw_quote_bar.draw_abs(cx, Rect {
+                        pos: rect.pos,
+                        size: dvec2(self.quote_bar_width, rect.size.y)
+                    });
+                }
+                Block::Rule => {
+                    self.draw_rule.draw_walk(cx, Walk {height: Size::Fixed(1.0), ..Walk::fill_fit()});
+                }
+            }
+        }
+    }
+    
+    fn draw_text(&mut self, kind: TextKind) -> &mut DrawText {
+        match kind {
+            TextKind::Normal => &mut self.draw_normal,
+            TextKind::Italic => &mut self.draw_italic,
+            TextKind::Bold => &mut self.draw_bold,
+            TextKind::BoldItalic => &mut self.draw_bold_italic,
+            TextKind::Fixed => &mut self.draw_fixed,
+            TextKind::Link => &mut self.draw_link,
+        }
+    }
+    
+    fn text_kind(style: &SpanStyle, heading: bool) -> TextKind {
+        if style.code {
+            return TextKind::Fixed
+        }
+        if style.link.is_some() {
+            return TextKind::Link
+        }
+        match (style.strong || heading, style.emphasis) {
+            (true, true) => TextKind::BoldItalic,
+            (true, false) => TextKind::Bold,
+            (false, true) => TextKind::Italic,
+            (false, false) => TextKind::Normal,
+        }
+    }
+    
+    fn set_font_scale(&mut self, scale: f64) {
+        for kind in [TextKind::Normal, TextKind::Italic, TextKind::Bold, TextKind::BoldItalic, TextKind::Fixed, TextKind::Link] {
+            self.draw_text(kind).font_scale *= scale;
+        }
+    }
+    
+    fn measure(&mut self, cx: &Cx2d, kind: TextKind, text: &str) -> f64 {
+        self.draw_text(kind).compute_geom(cx, Walk::fit(), text).map_or(0.0, | geom | geom.measured_width)
+    }
+    
+    fn split_pieces(&mut self, cx: &Cx2d, spans: &[Span], heading: bool) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+        for span in spans {
+            if span.is_break() {
+                pieces.push(Piece {text: span.text.clone(), style: span.style, space: false, hidden: true, line: 0, x: 0.0, width: 0.0, pad_left: 0.0, pad_right: 0.0});
+                continue
+            }
+            let kind = Self::text_kind(&span.style, heading);
+            let first = pieces.len();
+            let mut word = String::new();
+            let mut chars = span.text.chars().peekable();
+            while let Some(c) = chars.next() {
+                word.push(c);
+                let space = c.is_whitespace();
+                if chars.peek().map_or(true, | next | next.is_whitespace() != space) {
+                    let width = self.measure(cx, kind, &word);
+                    pieces.push(Piece {text: std::mem::take(&mut word), style: span.style, space, hidden: false, line: 0, x: 0.0, width, pad_left: 0.0, pad_right: 0.0});
+                }
+            }
+            if span.style.code && pieces.len() > first {
+                pieces[first].pad_left = self.inline_code_padding;
+                pieces.last_mut().unwrap().pad_right = self.inline_code_padding;
+            }
+        }
+        pieces
+    }
+    
+    // cuts a word that is wider than a whole line into parts that fit
+    fn split_long_word(&mut self, cx: &Cx2d, piece: Piece, max_width: f64, heading: bool) -> Vec<Piece> {
+        let kind = Self::text_kind(&piece.style, heading);
+        let mut parts = Vec::new();
+        let mut part = String::new();
+        let mut width = 0.0;
+        for c in piece.text.chars() {
+            let mut buf = [0u8; 4];
+            let adv = self.measure(cx, kind, c.encode_utf8(&mut buf));
+            if !part.is_empty() && width + adv > max_width {
+                parts.push(Piece {text: std::mem::take(&mut part), width, pad_left: 0.0, pad_right: 0.0, ..piece});
+                width = 0.0;
+            }
+            part.push(c);
+            width += adv;
+        }
+        parts.push(Piece {text: part, width, pad_left: 0.0, pad_right: 0.0, ..piece});
+        parts
+    }
+    
+    fn draw_spans(&mut self, cx: &mut Cx2d, spans: &[Span], heading_scale: Option<f64>) {
+        let heading = heading_scale.is_some();
+        let scale = heading_scale.unwrap_or(1.0);
+        self.set_font_scale(scale);
+        
+        let avail = cx.turtle().eval_width(Size::Fill, Margin::default(), cx.turtle().layout().flow);
+        let mut pieces = Vec::new();
+        for piece in self.split_pieces(cx, spans, heading) {
+            if !piece.space && !avail.is_nan() && piece.width > avail {
+                pieces.extend(self.split_long_word(cx, piece, avail, heading));
+            }
+            else {
+                pieces.push(piece);
+            }
+        }
+        
+        // break the pieces into lines, spaces at the start or end of a line arent drawn
+        let mut line = 0;
+        let mut x = 0.0;
+        let mut max_x: f64 = 0.0;
+        for i in 0..pieces.len() {
+            let piece = &pieces[i];
+            if piece.is_break() {
+                line += 1;
+                x = 0.0;
+                continue
+            }
+            let width = piece.pad_left + piece.width + piece.pad_right;
+            if piece.space {
+                if x == 0.0 {
+                    pieces[i].hidden = true;
+                    continue
+                }
+            }
+            else if x > 0.0 && !avail.is_nan() && x + width > avail {
+                for prev in pieces[..i].iter_mut().rev() {
+                    if !prev.space || prev.line != line {
+                        break
+                    }
+                    prev.hidden = true;
+                }
+                line += 1;
+                x = 0.0;
+            }
+            let piece = &mut pieces[i];
+            piece.line = line;
+            piece.x = x;
+            x += width;
+            max_x = max_x.max(x);
+        }
+        
+        let line_height = self.draw_normal.text_style.font_size * self.draw_normal.text_style.height_factor * self.draw_normal.font_scale;
+        let line_advance = self.draw_normal.get_line_spacing();
+        let height = line_height + line as f64 * line_advance;
+        let rect = cx.walk_turtle(Walk {
+            width: if avail.is_nan() {Size::Fixed(max_x)} else {Size::Fill},
+            height: Size::Fixed(height),
+            ..Walk::default()
+        });
+        
+        for piece in &pieces {
+            if piece.hidden {
+                continue
+            }
+            let pos = rect.pos + dvec2(piece.x, piece.line as f64 * line_advance);
+            let width = piece.pad_left + piece.width + piece.pad_right;
+            if piece.style.code {
+                self.draw_code_bg.draw_abs(cx, Rect {pos, size: dvec2(width, line_height)});
+            }
+            if let Some(link) = piece.style.link {
+                let hover = if self.hovered_link == Some(link) {1.0} else {0.0};
+                self.draw_link.hover = hover;
+                self.draw_link_line.hover = hover;
+                // every piece keeps its own area, so the instance must not be remapped to the last one drawn
+                self.draw_link_line.draw_vars.area = Area::Empty;
+                self.draw_link_line.draw_abs(cx, Rect {pos, size: dvec2(width, line_height)});
+                self.link_areas.push((link, self.draw_link_line.area()));
+            }
+            if !piece.space {
+                let kind = Self::text_kind(&piece.style, heading);
+                self.draw_text(kind).draw_abs(cx, pos + dvec2(piece.pad_left, 0.0), &piece.text);
+            }
+            if piece.style.strike {
+                self.draw_strike.draw_abs(cx, Rect {
+                    pos: pos + dvec2(0.0, (0.5 * line_height).floor()),
+                    size: dvec2(width, 1.0)
+                });
+            }
+        }
+        self.set_font_scale(1.0 / scale);
+    }
+}
+
+impl Piece {
+    fn is_break(&self) -> bool {
+        self.text == "\n"
+    }
+}
+
+#[derive(Clone, Default, PartialEq, WidgetRef)]
+pub struct MarkdownRef(WidgetRef);
+
+impl MarkdownRef {
+    pub fn set_markdown(&self, cx: &mut Cx, text: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_markdown(cx, text);
+        }
+    }
+    
+    pub fn link_clicked(&self, actions: &WidgetActions) -> Option<String> {
+        if let Some(item) = actions.find_single_action(self.widget_uid()) {
+            if let MarkdownAction::LinkClicked(url) = item.action() {
+                return Some(url)
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Default, WidgetSet)]
+pub struct MarkdownSet(WidgetSet);
+
+impl MarkdownSet {
+    pub fn set_markdown(&self, cx: &mut Cx, text: &str) {
+        for item in self.iter() {
+            item.set_markdown(cx, text);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    makepad_derive_widget::*,
+    makepad_draw::*,
+    nav_control::NavControl,
+    view::*,
+    widget::*,
+};
+
+live_design!{
+    ModalBase = {{Modal}} {}
+}
+
+// a dialog drawn on top of everything else in the window. while it is open the backdrop
+// holds the sweep lock so nothing underneath can be clicked, and tab navigation stays inside
+#[derive(Live)]
+pub struct Modal {
+    #[deref] view: View,
+    #[live] draw_list: DrawList2d,
+    #[live] draw_backdrop: DrawColor,
+    #[live] placement: Align,
+    #[live(true)] dismiss_on_escape: bool,
+    #[live(true)] dismiss_on_backdrop: bool,
+    #[rust] area: Area,
+    #[rust] opened: bool,
+    #[rust] focus_pending: bool,
+    #[rust] focus_before: Area,
+    #[rust] draw_state: DrawStateWrap<DrawState>,
+}
+
+#[derive(Clone)]
+enum DrawState {
+    Drawing,
+}
+
+impl LiveHook for Modal {
+    fn before_live_design(cx: &mut Cx) {
+        register_widget!(cx, Modal)
+    }
+}
+
+#[derive(Clone, WidgetAction)]
+pub enum ModalAction {
+    Dismissed,
+    None
+}
+
+impl Widget for Modal {
+    fn handle_widget_event_with(
+        &mut self,
+        cx: &mut Cx,
+        event: &Event,
+        dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)
+    ) {
+        if !self.opened {
+            return
+        }
+        // the content has to be reachable through our own lock
+        let backdrop = self.draw_backdrop.area();
+        cx.sweep_unlock(backdrop);
+        self.view.handle_widget_event_with(cx, event, dispatch_action);
+        if !backdrop.is_empty() {
+            cx.sweep_lock(backdrop);
+        }
+        
+        let uid = self.widget_uid();
+        self.handle_event_with(cx, event, &mut | cx, action | {
+            dispatch_action(cx, WidgetActionItem::new(action.into(), uid));
+        });
+    }
+    
+    fn walk(&mut self, cx: &mut Cx) -> Walk {
+        self.view.walk(cx)
+    }
+    
+    fn redraw(&mut self, cx: &mut Cx) {
+        self.area.redraw(cx);
+        self.draw_list.redraw(cx);
+    }
+    
+    fn find_widgets(&mut self, path: &[LiveId], cached: WidgetCache, results: &mut WidgetSet) {
+        self.view.find_widgets(path, cached, results);
+    }
+    
+    fn draw_walk_widget(&mut self, cx: &mut Cx2d, _walk: Walk) -> WidgetDraw {
+        if self.draw_state.begin(cx, DrawState::Drawing) {
+            // the modal takes no room where it is declared, the area only ties it to the redraws of its parent
+            cx.walk_turtle_with_area(&mut self.area, Walk::fixed(0.0, 0.0).with_abs_pos(dvec2(0.0, 0.0)));
+            if !self.opened {
+                self.draw_state.end();
+                return WidgetDraw::done()
+            }
+            self.draw_list.begin_overlay_last(cx);
+            cx.begin_pass_sized_turtle(Layout::flow_down());
+            self.draw_backdrop.begin(cx, Walk::fill(), Layout {align: self.placement, ..Layout::default()});
+        }
+        
+        if let Some(DrawState::Drawing) = self.draw_state.get() {
+            let walk = self.view.walk(cx);
+            self.view.draw_walk_widget(cx, walk) ?;
+            self.draw_state.end();
+            
+            self.draw_backdrop.end(cx);
+            cx.end_pass_sized_turtle();
+            self.draw_list.end(cx);
+            
+            cx.sweep_lock(self.draw_backdrop.area());
+            if self.focus_pending {
+                self.focus_pending = false;
+                self.focus_first_stop(cx);
+            }
+        }
+        WidgetDraw::done()
+    }
+}
+
+impl Modal {
+    pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, ModalAction)) {
+        if !self.opened {
+            return
+        }
+        if let Event::KeyDown(ke) = event {
+            if ke.key_code == KeyCode::Escape && self.dismiss_on_escape {
+                self.close(cx);
+                dispatch_action(cx, ModalAction::Dismissed);
+                return
+            }
+        }
+        let backdrop = self.draw_backdrop.area();
+        match event.hits_with_sweep_area(cx, backdrop, backdrop) {
+            // a press that lands on the dialog itself but isnt handled by its content shouldnt dismiss it
+            Hit::FingerUp(fe) if fe.was_tap() && self.dismiss_on_backdrop => {
+                let rect = self.view.area().get_clipped_rect(cx);
+                if !rect.contains(fe.abs_start) && !rect.contains(fe.abs) {
+                    self.close(cx);
+                    dispatch_action(cx, ModalAction::Dismissed);
+                }
+            }
+            _ => ()
+        }
+    }
+    
+    fn focus_first_stop(&mut self, cx: &mut Cx) {
+        let stops = Cx2d::collect_nav_stops(cx, self.draw_list.draw_list_id());
+        if let Some((stop, _)) = stops.iter().find( | (stop, _) | stop.role != NavRole::ListItem) {
+            cx.set_key_focus(stop.area);
+        }
+        else {
+            cx.set_key_focus(self.draw_backdrop.area());
+        }
+    }
+    
+    pub fn open(&mut self, cx: &mut Cx) {
+        if self.opened {
+            return
+        }
+        self.opened = true;
+        self.focus_pending = true;
+        self.focus_before = cx.key_focus();
+        NavControl::push_focus_trap(cx, self.draw_list.draw_list_id());
+        self.redraw(cx);
+    }
+    
+    pub fn close(&mut self, cx: &mut Cx) {
+        if !self.opened {
+            return
+        }
+        self.opened = false;
+        self.focus_pending = false;
+        cx.sweep_unlock(self.draw_backdrop.area());
+        NavControl::pop_focus_trap(cx, self.draw_list.draw_list_id());
+        cx.set_key_focus(self.focus_before);
+        self.redraw(cx);
+    }
+    
+    pub fn is_open(&self) -> bool {
+        self.opened
+    }
+}
+
+#[derive(Clone, Default, PartialEq, WidgetRef)]
+pub struct ModalRef(WidgetRef);
+
+impl ModalRef {
+    pub fn open(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.open(cx);
+        }
+    }
+    
+    pub fn close(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.close(cx);
+        }
+    }
+    
+    pub fn is_open(&self) -> bool {
+        if let Some(inner) = self.borrow() {
+            return inner.is_open()
+        }
+        false
+    }
+    
+    pub fn dismissed(&self, actions: &WidgetActions) -> bool {
+        if let Some(item) = actions.find_single_action(self.widget_uid()) {
+            if let ModalAction::Dismissed = item.action() {
+                return true
+            }
+        }
+        false
+    }
+}
+
+#[derive(Clone, Default, WidgetSet)]
+pub struct ModalSet(WidgetSet);
+
+impl ModalSet {
+    pub fn open(&self, cx: &mut Cx) {
+        for item in self.iter() {
+            item.open(cx);
+        }
+    }
+    
+    pub fn close(&self, cx: &mut Cx) {
+        for item in self.iter() {
+            item.close(cx);
+        }
+    }
+}
@@ -7,16 +7,31 @@ live_design!{
     NavControlBase = {{NavControl}} {}
 }
 
+// the draw lists tab navigation is confined to, modal layers push theirs while they are open
+#[derive(Default)]
+pub struct NavFocusTrap(Vec<DrawListId>);
+
 #[derive(Live, LiveHook)]
 pub struct NavControl {
     #[live] draw_list: DrawList2d,
     #[live] draw_focus: DrawQuad,
     #[live] draw_text: DrawText,
-    #[rust] _recent_focus: Area,
+    #[live(2.0)] focus_margin: f64,
+    #[rust] recent_focus: Area,
 }
 
 impl NavControl {
     
+    pub fn push_focus_trap(cx: &mut Cx, draw_list_id: DrawListId) {
+        let trap = cx.global::<NavFocusTrap>();
+        trap.0.retain( | id | *id != draw_list_id);
+        trap.0.push(draw_list_id);
+    }
+    
+    pub fn pop_focus_trap(cx: &mut Cx, draw_list_id: DrawListId) {
+        cx.global::<NavFocusTrap>().0.retain( | id | *id != draw_list_id);
+    }
+    
     pub fn send_trigger_to_scroll_stack(cx: &mut Cx, stack:Vec<Area>){
         let mut prev_area = None;
         for next_area in stack{
@@ -34,50 +49,50 @@ impl NavControl {
         match event {
             Event::KeyDown(ke) => match ke.key_code {
                 KeyCode::Tab => {
-                    if ke.modifiers.shift {
-                        let mut prev_area = Area::Empty;
-                        if let Some((prev_area, scroll_stack)) = Cx2d::iterate_nav_stops(cx, root, | cx, stop | {
-                            if cx.has_key_focus(stop.area) {
-                                return Some(prev_area);
-                            }
-                            prev_area = stop.area;
-                            None
-                        }) {
-                            if !prev_area.is_empty() {
-                                Self::send_trigger_to_scroll_stack(cx, scroll_stack);
-                                cx.set_key_focus(prev_area);
-                            }
-                        }
-                    }
-                    else {
-                        let mut next_stop = false;
-                        if let Some((next_area, scroll_stack)) = Cx2d::iterate_nav_stops(cx, root, | cx, stop | {
-                            if next_stop {
-                                return Some(stop.area)
-                            }
-                            if cx.has_key_focus(stop.area) {
-                                next_stop = true;
-                            }
-                            None
-                        }) {
-                            Self::send_trigger_to_scroll_stack(cx, scroll_stack);
-                            cx.set_key_focus(next_area);
-                        }
+                    let root = cx.global::<NavFocusTrap>().0.last().cloned().unwrap_or(root);
+                    let mut stops = Cx2d::collect_nav_stops(cx, root);
+                    // list items are reached with the arrow keys of the list that owns them
+                    stops.retain( | (stop, _) | stop.role != NavRole::ListItem);
+                    if stops.len() == 0 {
+                        return
                     }
+                    // tabbing wraps around, and without focus we start at either end.
+                    // focus can also sit inside a stop (the text field of a slider), then we continue from that stop
+                    let current = stops.iter().position( | (stop, _) | cx.has_key_focus(stop.area))
+                        .or_else( | | stops.iter().position( | (stop, _) | stop.area == self.recent_focus));
+                    let next = match (current, ke.modifiers.shift) {
+                        (Some(index), false) => (index + 1) % stops.len(),
+                        (Some(index), true) => (index + stops.len() - 1) % stops.len(),
+                        (None, false) => 0,
+                        (None, true) => stops.len() - 1,
+                    };
+                    let (stop, scroll_stack) = stops.swap_remove(next);
+                    Self::send_trigger_to_scroll_stack(cx, scroll_stack);
+                    cx.set_key_focus(stop.area);
+                    self.recent_focus = stop.area;
+                    self.draw_list.redraw(cx);
                 }
                 _ => ()
             },
+            Event::KeyFocus(_) | Event::KeyFocusLost(_) => {
+                self.draw_list.redraw(cx);
+            }
             _ => ()
         }
     }
     
     pub fn draw(&mut self, cx: &mut Cx2d) {
-        if !self.draw_list.begin(cx, Walk::default()).is_redrawing() {
-            return
+        self.draw_list.begin_overlay_last(cx);
+        // the ring only shows for keyboard navigation, clicking into a widget doesnt draw it
+        if !self.recent_focus.is_empty() && cx.has_key_focus(self.recent_focus) {
+            let rect = self.recent_focus.get_clipped_rect(cx);
+            if rect.size.x > 0.0 && rect.size.y > 0.0 {
+                self.draw_focus.draw_abs(cx, rect.add_margin(dvec2(self.focus_margin, self.focus_margin)));
+            }
+        }
+        else {
+            self.recent_focus = Area::Empty;
         }
-        
         self.draw_list.end(cx);
     }
 }
-
-
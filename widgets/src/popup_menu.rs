@@ -17,6 +17,7 @@ pub struct PopupMenuItem {
     
     #[live] draw_bg: DrawQuad,
     #[live] draw_name: DrawText,
+    #[live] draw_shortcut: DrawText,
     
     #[layout] layout: Layout,
     #[animator] animator: Animator,
@@ -36,6 +37,8 @@ pub struct PopupMenu {
     #[live] menu_item: Option<LivePtr>,
     
     #[live] draw_bg: DrawQuad,
+    #[live] draw_line: DrawQuad,
+    #[live] line_walk: Walk,
     #[layout] layout: Layout,
     #[walk] walk: Walk,
     #[live] items: Vec<String>,
@@ -58,6 +61,7 @@ impl LiveHook for PopupMenu {
 }
 
 pub enum PopupMenuItemAction {
+    WasHovered,
     WasSweeped,
     WasSelected,
     MightBeSelected,
@@ -66,6 +70,7 @@ pub enum PopupMenuItemAction {
 
 #[derive(Clone, WidgetAction)]
 pub enum PopupMenuAction {
+    WasHovered(PopupMenuItemId),
     WasSweeped(PopupMenuItemId),
     WasSelected(PopupMenuItemId),
     None,
@@ -84,6 +89,39 @@ impl PopupMenuItem {
         self.draw_bg.begin(cx, self.walk, self.layout);
         self.draw_name.draw_walk(cx, Walk::fit(), Align::default(), label);
         self.draw_bg.end(cx);
+        let selected = self.animator_in_state(cx, id!(select.on));
+        cx.add_nav_stop_with_access(
+            self.draw_bg.area(),
+            NavRole::ListItem,
+            NavOrder::Default,
+            Margin::default(),
+            AccessInfo::named(label).with_selected(selected)
+        );
+    }
+    
+    // draws the label with a right aligned shortcut (or submenu marker), disabled items are dimmed
+    pub fn draw_item_with_shortcut(
+        &mut self,
+        cx: &mut Cx2d,
+        label: &str,
+        shortcut: &str,
+        enabled: bool,
+    ) {
+        let disabled = if enabled {0.0} else {1.0};
+        self.draw_name.apply_over(cx, live!{disabled: (disabled)});
+        self.draw_shortcut.apply_over(cx, live!{disabled: (disabled)});
+        self.draw_bg.begin(cx, self.walk, self.layout);
+        self.draw_name.draw_walk(cx, Walk::fit(), Align::default(), label);
+        self.draw_shortcut.draw_walk(cx, Walk::fill_fit(), Align {x: 1.0, y: 0.0}, shortcut);
+        self.draw_bg.end(cx);
+        let selected = self.animator_in_state(cx, id!(select.on));
+        cx.add_nav_stop_with_access(
+            self.draw_bg.area(),
+            NavRole::ListItem,
+            NavOrder::Default,
+            Margin::default(),
+            AccessInfo::named(label).with_selected(selected)
+        );
     }
     
     pub fn handle_event_with(
@@ -97,6 +135,10 @@ impl PopupMenuItem {
             self.draw_bg.area().redraw(cx);
         }
         
+        if let Some(AccessAction::Activate) = Cx2d::access_action_hit(event, self.draw_bg.area()) {
+            dispatch_action(cx, PopupMenuItemAction::WasSelected);
+        }
+        
         match event.hits_with_options(
             cx,
             self.draw_bg.area(),
@@ -104,6 +146,7 @@ impl PopupMenuItem {
         ) {
             Hit::FingerHoverIn(_) => {
                 self.animator_play(cx, id!(hover.on));
+                dispatch_action(cx, PopupMenuItemAction::WasHovered);
             }
             Hit::FingerHoverOut(_) => {
                 self.animator_play(cx, id!(hover.off));
@@ -188,11 +231,48 @@ impl PopupMenu {
         menu_item.draw_item(cx, label);
     }
     
+    pub fn draw_item_with_shortcut(
+        &mut self,
+        cx: &mut Cx2d,
+        item_id: PopupMenuItemId,
+        label: &str,
+        shortcut: &str,
+        enabled: bool,
+    ) {
+        self.count += 1;
+        
+        let menu_item = self.menu_item;
+        let menu_item = self.menu_items.get_or_insert(cx, item_id, | cx | {
+            PopupMenuItem::new_from_ptr(cx, menu_item)
+        });
+        menu_item.draw_item_with_shortcut(cx, label, shortcut, enabled);
+    }
+    
+    pub fn draw_separator(&mut self, cx: &mut Cx2d) {
+        self.draw_line.draw_walk(cx, self.line_walk);
+    }
+    
+    // the area of a drawn item, used to anchor cascading submenus next to it
+    pub fn item_area(&self, item_id: PopupMenuItemId) -> Area {
+        if let Some(item) = self.menu_items.get(&item_id) {
+            item.draw_bg.area()
+        }
+        else {
+            Area::Empty
+        }
+    }
+    
     pub fn init_select_item(&mut self, which_id: PopupMenuItemId) {
         self.init_select_item = Some(which_id);
         self.first_tap = true;
     }
     
+    // moves the highlight without selecting, used for keyboard navigation of an open menu
+    pub fn highlight_item(&mut self, cx: &mut Cx, which_id: PopupMenuItemId) {
+        self.select_item_state(cx, which_id);
+        self.draw_list.redraw(cx);
+    }
+    
     fn select_item_state(&mut self, cx: &mut Cx, which_id: PopupMenuItemId) {
         for (id, item) in &mut *self.menu_items {
             if *id == which_id {
@@ -229,6 +309,9 @@ impl PopupMenu {
                         dispatch_action(cx, PopupMenuAction::WasSelected(node_id));
                     }
                 }
+                PopupMenuItemAction::WasHovered => {
+                    dispatch_action(cx, PopupMenuAction::WasHovered(node_id));
+                }
                 PopupMenuItemAction::WasSweeped => {
                     self.select_item_state(cx, node_id);
                     dispatch_action(cx, PopupMenuAction::WasSweeped(node_id));
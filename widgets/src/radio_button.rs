@@ -49,6 +49,7 @@ pub struct RadioButton {
     #[live] label: String,
     
     #[live] bind: String,
+    #[live] nav_order: NavOrder,
 }
 
 impl LiveHook for RadioButton{
@@ -69,6 +70,10 @@ impl RadioButton {
     pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, RadioButtonAction)) {
         self.animator_handle_event(cx, event);
         
+        if let Some(AccessAction::Activate) = Cx2d::access_action_hit(event, self.draw_radio.area()) {
+            self.select(cx, dispatch_action);
+        }
+        
         match event.hits(cx, self.draw_radio.area()) {
             Hit::FingerHoverIn(_) => {
                 cx.set_cursor(MouseCursor::Hand);
@@ -79,26 +84,50 @@ impl RadioButton {
                 self.animator_play(cx, id!(hover.off));
             },
             Hit::FingerDown(_fe) => {
-                if self.animator_in_state(cx, id!(selected.off)) {
-                    self.animator_play(cx, id!(selected.on));
-                    dispatch_action(cx, RadioButtonAction::Clicked);
-                }
+                self.select(cx, dispatch_action);
             },
             Hit::FingerUp(_fe) => {
                 
             }
             Hit::FingerMove(_fe) => {
                 
+            }
+            Hit::KeyFocus(_) => {
+                self.animator_play(cx, id!(focus.on));
+            }
+            Hit::KeyFocusLost(_) => {
+                self.animator_play(cx, id!(focus.off));
+            }
+            Hit::KeyDown(ke) if !ke.is_repeat => match ke.key_code {
+                KeyCode::Space | KeyCode::ReturnKey | KeyCode::NumpadEnter => {
+                    self.select(cx, dispatch_action);
+                }
+                _ => ()
             }
             _ => ()
         }
     }
     
+    fn select(&mut self, cx: &mut Cx, dispatch_action: &mut dyn FnMut(&mut Cx, RadioButtonAction)) {
+        if self.animator_in_state(cx, id!(selected.off)) {
+            self.animator_play(cx, id!(selected.on));
+            dispatch_action(cx, RadioButtonAction::Clicked);
+        }
+    }
+    
     pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
         self.draw_radio.begin(cx, walk, self.layout);
         self.draw_icon.draw_walk(cx, self.icon_walk);
         self.draw_text.draw_walk(cx, self.label_walk, self.label_align, &self.label);
         self.draw_radio.end(cx);
+        let selected = self.animator_in_state(cx, id!(selected.on));
+        cx.add_nav_stop_with_access(
+            self.draw_radio.area(),
+            NavRole::RadioButton,
+            self.nav_order,
+            Margin::default(),
+            AccessInfo::named(&self.label).with_checked(selected)
+        );
     }
 }
 
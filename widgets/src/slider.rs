@@ -53,6 +53,7 @@ pub struct Slider {
     #[live] step: f64,
     
     #[live] bind: String,
+    #[live] nav_order: NavOrder,
     
     #[rust] pub value: f64,
     #[rust] pub dragging: Option<f64>,
@@ -79,7 +80,8 @@ impl Slider {
     fn to_external(&self) -> f64 {
         let val = self.value * (self.max - self.min) + self.min;
         if self.step != 0.0{
-            return (val * self.step).floor() / self.step
+            // the epsilon keeps values that were set exactly on a step from flooring to the one below
+            return (val * self.step + 1e-9).floor() / self.step
         }
         else{
             val
@@ -92,6 +94,25 @@ impl Slider {
         old != self.value
     }
     
+    // keyboard steps are a percent of the range, rounded up to whole steps when stepped
+    fn key_step(&self) -> f64 {
+        let step = (self.max - self.min) / 100.0;
+        if self.step != 0.0 {
+            (step * self.step).ceil().max(1.0) / self.step
+        }
+        else {
+            step
+        }
+    }
+    
+    fn set_external_from_key(&mut self, cx: &mut Cx, external: f64, dispatch_action: &mut dyn FnMut(&mut Cx, SliderAction)) {
+        if self.set_internal(external.max(self.min).min(self.max)) {
+            self.draw_slider.redraw(cx);
+            self.update_text_input(cx);
+            dispatch_action(cx, SliderAction::Slide(self.to_external()));
+        }
+    }
+    
     pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, SliderAction)) {
         self.animator_handle_event(cx, event);
         for action in self.text_input.handle_event(cx, event) {
@@ -115,6 +136,15 @@ impl Slider {
                 _ => ()
             }
         };
+        match Cx2d::access_action_hit(event, self.draw_slider.area()) {
+            Some(AccessAction::Increment) => {
+                self.set_external_from_key(cx, self.to_external() + self.key_step(), dispatch_action);
+            }
+            Some(AccessAction::Decrement) => {
+                self.set_external_from_key(cx, self.to_external() - self.key_step(), dispatch_action);
+            }
+            _ => ()
+        }
         match event.hits(cx, self.draw_slider.area()) {
             Hit::FingerHoverIn(_) => {
                 cx.set_cursor(MouseCursor::Arrow);
@@ -158,6 +188,43 @@ impl Slider {
                     dispatch_action(cx, SliderAction::Slide(self.to_external()));
                 }
             }
+            Hit::KeyFocus(_) => {
+                self.animator_play(cx, id!(focus.on));
+            }
+            Hit::KeyFocusLost(_) => {
+                self.animator_play(cx, id!(focus.off));
+            }
+            Hit::KeyDown(ke) => {
+                let external = self.to_external();
+                let step = if ke.modifiers.shift {self.key_step() * 10.0} else {self.key_step()};
+                match ke.key_code {
+                    KeyCode::ArrowRight | KeyCode::ArrowUp => {
+                        self.set_external_from_key(cx, external + step, dispatch_action);
+                    }
+                    KeyCode::ArrowLeft | KeyCode::ArrowDown => {
+                        self.set_external_from_key(cx, external - step, dispatch_action);
+                    }
+                    KeyCode::PageUp => {
+                        self.set_external_from_key(cx, external + self.key_step() * 10.0, dispatch_action);
+                    }
+                    KeyCode::PageDown => {
+                        self.set_external_from_key(cx, external - self.key_step() * 10.0, dispatch_action);
+                    }
+                    KeyCode::Home => {
+                        self.set_external_from_key(cx, self.min, dispatch_action);
+                    }
+                    KeyCode::End => {
+                        self.set_external_from_key(cx, self.max, dispatch_action);
+                    }
+                    // enter moves into the value field to type a number
+                    KeyCode::ReturnKey | KeyCode::NumpadEnter => {
+                        self.text_input.set_key_focus(cx);
+                        self.text_input.select_all();
+                        self.text_input.redraw(cx);
+                    }
+                    _ => ()
+                }
+            }
             _ => ()
         }
     }
@@ -191,6 +258,13 @@ impl Slider {
         }
         
         self.draw_slider.end(cx);
+        cx.add_nav_stop_with_access(
+            self.draw_slider.area(),
+            NavRole::Slider,
+            self.nav_order,
+            Margin::default(),
+            AccessInfo::named(&self.text).with_value(&self.text_input.text)
+        );
     }
 }
 
@@ -0,0 +1,115 @@
+use crate::{
+    makepad_derive_widget::*,
+    makepad_draw::*,
+    makepad_draw::makepad_vector::svg::Document,
+    widget::*,
+};
+use std::rc::Rc;
+
+live_design!{
+    SvgBase = {{Svg}} {}
+}
+
+// Renders a multi color SVG document with its groups, transforms, fills, strokes and gradients,
+// scaled to fit the rect while keeping its aspect ratio. A Fit width or height uses the size
+// the document declares.
+#[derive(Live)]
+pub struct Svg {
+    #[walk] walk: Walk,
+    #[live] draw_svg: DrawPath,
+    #[live] source: LiveDependency,
+    #[rust] document: Option<Rc<Document>>,
+    #[rust] loaded_source: String,
+}
+
+impl LiveHook for Svg {
+    fn before_live_design(cx: &mut Cx) {
+        register_widget!(cx, Svg)
+    }
+    
+    fn after_apply(&mut self, cx: &mut Cx, _from: ApplyFrom, _index: usize, _nodes: &[LiveNode]) {
+        let source = self.source.clone();
+        if source.as_str().len() > 0 && source.as_str() != self.loaded_source {
+            self.load_svg_dep_by_path(cx, source.as_str());
+        }
+    }
+}
+
+impl Widget for Svg {
+    fn redraw(&mut self, cx: &mut Cx) {
+        self.draw_svg.redraw(cx)
+    }
+    
+    fn walk(&mut self, _cx: &mut Cx) -> Walk {
+        self.walk
+    }
+    
+    fn handle_widget_event_with(&mut self, _cx: &mut Cx, _event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)) {
+    }
+    
+    fn draw_walk_widget(&mut self, cx: &mut Cx2d, walk: Walk) -> WidgetDraw {
+        self.draw_walk(cx, walk);
+        WidgetDraw::done()
+    }
+}
+
+impl Svg {
+    pub fn load_svg_dep_by_path(&mut self, cx: &mut Cx, path: &str) {
+        self.loaded_source = path.to_string();
+        match cx.get_dependency(path) {
+            Ok(data) => match std::str::from_utf8(&data) {
+                Ok(data) => self.load_svg_from_str(cx, data),
+                Err(err) => error!("load_svg_dep_by_path: {} is not utf8 {}", path, err),
+            },
+            Err(err) => error!("load_svg_dep_by_path: Cannot load {} {}", path, err),
+        }
+    }
+    
+    pub fn load_svg_from_str(&mut self, cx: &mut Cx, data: &str) {
+        match Document::parse(data) {
+            Ok(document) => {
+                self.document = Some(Rc::new(document));
+                self.draw_svg.redraw(cx);
+            }
+            Err(err) => error!("load_svg_from_str: Cannot parse svg {}", err),
+        }
+    }
+    
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, mut walk: Walk) {
+        let Some(document) = self.document.clone() else {
+            cx.walk_turtle(walk);
+            return
+        };
+        if walk.width.is_fit() {
+            walk.width = Size::Fixed(document.width);
+        }
+        if walk.height.is_fit() {
+            walk.height = Size::Fixed(document.height);
+        }
+        let rect = cx.walk_turtle(walk);
+        self.draw_svg.draw_svg(cx, &document, rect);
+    }
+}
+
+#[derive(Clone, Default, PartialEq, WidgetRef)]
+pub struct SvgRef(WidgetRef);
+
+impl SvgRef {
+    pub fn load_svg_dep_by_path(&self, cx: &mut Cx, path: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_svg_dep_by_path(cx, path)
+        }
+    }
+    
+    pub fn load_svg_from_str(&self, cx: &mut Cx, data: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.load_svg_from_str(cx, data)
+        }
+    }
+}
+
+#[derive(Clone, Default, WidgetSet)]
+pub struct SvgSet(WidgetSet);
+
+impl SvgSet {
+}
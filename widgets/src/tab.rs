@@ -31,6 +31,7 @@ pub struct Tab {
     
     #[walk] walk: Walk,
     #[layout] layout: Layout,
+    #[live] nav_order: NavOrder,
     
 }
 
@@ -38,7 +39,8 @@ pub enum TabAction {
     WasPressed,
     CloseWasPressed,
     ShouldTabStartDrag,
-    ShouldTabStopDrag
+    ShouldTabStopDrag,
+    ShouldFocusSibling(isize),
     //DragHit(DragHit)
 }
 
@@ -62,6 +64,13 @@ impl Tab {
         self.draw_name.draw_walk(cx, Walk::fit(), Align::default(), name);
         //cx.turtle_align_y();
         self.draw_bg.end(cx);
+        cx.add_nav_stop_with_access(
+            self.draw_bg.area(),
+            NavRole::Tab,
+            self.nav_order,
+            Margin::default(),
+            AccessInfo::named(name).with_selected(self.is_selected)
+        );
         
         //if self.is_dragged {
         //    self.draw_drag.draw_abs(cx, self.draw_bg.area().get_clipped_rect(cx));
@@ -88,6 +97,10 @@ impl Tab {
             _ => ()
         };
         
+        if let Some(AccessAction::Activate) = Cx2d::access_action_hit(event, self.draw_bg.area()) {
+            dispatch_action(cx, TabAction::WasPressed);
+        }
+        
         match event.hits(cx, self.draw_bg.area()) {
             Hit::FingerHoverIn(_) => {
                 self.animator_play(cx, id!(hover.on));
@@ -110,6 +123,27 @@ impl Tab {
             Hit::FingerDown(_) => {
                 dispatch_action(cx, TabAction::WasPressed);
             }
+            Hit::KeyFocus(_) => {
+                self.animator_play(cx, id!(hover.on));
+            }
+            Hit::KeyFocusLost(_) => {
+                self.animator_play(cx, id!(hover.off));
+            }
+            Hit::KeyDown(ke) => match ke.key_code {
+                KeyCode::Space | KeyCode::ReturnKey | KeyCode::NumpadEnter => {
+                    dispatch_action(cx, TabAction::WasPressed);
+                }
+                KeyCode::Delete => {
+                    dispatch_action(cx, TabAction::CloseWasPressed);
+                }
+                KeyCode::ArrowLeft => {
+                    dispatch_action(cx, TabAction::ShouldFocusSibling(-1));
+                }
+                KeyCode::ArrowRight => {
+                    dispatch_action(cx, TabAction::ShouldFocusSibling(1));
+                }
+                _ => ()
+            }
             _ => {}
         }
         /*
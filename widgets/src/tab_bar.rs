@@ -186,6 +186,7 @@ impl TabBar {
         if let Some(tab_id) = self.next_selected_tab_id.take() {
             dispatch_action(cx, TabBarAction::TabWasPressed(tab_id));
         }
+        let mut focus_sibling = None;
         for (tab_id, tab) in self.tabs.iter_mut() {
             tab.handle_event_with(cx, event, &mut | cx, action | match action {
                 TabAction::WasPressed => {
@@ -198,12 +199,26 @@ impl TabBar {
                     dispatch_action(cx, TabBarAction::ShouldTabStartDrag(*tab_id));
                 }
                 TabAction::ShouldTabStopDrag=>{
+                }
+                TabAction::ShouldFocusSibling(delta)=>{
+                    focus_sibling = Some((*tab_id, delta));
                 }/*
                 TabAction::DragHit(hit)=>{
                     dispatch_action(cx, TabBarAction::DragHitTab(hit, *tab_id));
                 }*/
             });
         }
+        // arrow keys move the focus along the tabs and select the tab that receives it
+        if let Some((tab_id, delta)) = focus_sibling {
+            if let Some(index) = self.tab_order.iter().position( | id | *id == tab_id) {
+                let len = self.tab_order.len() as isize;
+                let next_id = self.tab_order[(index as isize + delta).rem_euclid(len) as usize];
+                if let Some(tab) = self.tabs.get(&next_id) {
+                    cx.set_key_focus(tab.area());
+                }
+                dispatch_action(cx, TabBarAction::TabWasPressed(next_id));
+            }
+        }
         /*
         match event.drag_hits(cx, self.scroll_bars.area()) {
             DragHit::NoHit=>(),
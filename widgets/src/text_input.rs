@@ -44,10 +44,14 @@ pub struct TextInput {
     #[live] draw_bg: DrawColor,
     #[live] draw_select: DrawQuad,
     #[live] draw_cursor: DrawQuad,
+    #[live] draw_composition: DrawColor,
     #[live] draw_text: DrawLabel,
     
     #[walk] walk: Walk,
     #[layout] layout: Layout,
+    #[live] nav_order: NavOrder,
+    // widgets that embed a text input (like the slider) can take over its nav stop
+    #[live(true)] nav_stop: bool,
     
     #[live] label_align: Align,
     
@@ -60,6 +64,9 @@ pub struct TextInput {
     #[live] secret: bool,
     #[live] on_focus_select_all: bool,
     #[live] pub read_only: bool,
+    #[live] pub multiline: bool,
+    // in multiline mode return inserts a newline and ctrl/cmd+return submits, this flips it (for chat boxes)
+    #[live] submit_on_return: bool,
     
     //#[live] label_walk: Walk,
     
@@ -72,7 +79,14 @@ pub struct TextInput {
     #[rust] undo_stack: Vec<UndoItem>,
     #[rust] redo_stack: Vec<UndoItem>,
     #[rust] cursor_tail: usize,
-    #[rust] cursor_head: usize
+    #[rust] cursor_head: usize,
+    // remembered x position for moving up and down through lines of different length
+    #[rust] cursor_x: Option<f64>,
+    #[rust] cursor_into_view: bool,
+    #[rust] scroll_y: f64,
+    // the uncommitted IME pre-edit text, shown at the cursor
+    #[rust] composition: String,
+    #[rust] composition_cursor: usize,
 }
 
 impl LiveHook for TextInput {
@@ -314,6 +328,88 @@ impl TextInput {
         }
     }
     
+    fn char_count(&self) -> usize {
+        self.text.chars().count()
+    }
+    
+    // cursor position of a char index, the empty line after a trailing newline has no glyph so we construct it
+    fn cursor_pos(&self, boxes: &CharBoxes, index: usize) -> Option<DVec2> {
+        let char_count = self.char_count();
+        if self.multiline && index > 0 && index >= char_count && self.text.ends_with('\n') {
+            let first = boxes.cursor_pos(0.0, 0)?;
+            let last = boxes.cursor_pos(0.0, char_count - 1)?;
+            return Some(dvec2(first.x, last.y + self.draw_text.get_line_spacing()))
+        }
+        boxes.cursor_pos(0.0, index)
+    }
+    
+    fn move_cursor_to(&mut self, cx: &mut Cx, pos: usize, select: bool) {
+        self.undo_id += 1;
+        self.cursor_head = pos.min(self.char_count());
+        if !select {
+            self.cursor_tail = self.cursor_head;
+        }
+        self.cursor_into_view = true;
+        self.draw_bg.redraw(cx);
+    }
+    
+    // moves the cursor a number of visual lines up or down, past the first or last line it goes to the start or end
+    fn move_cursor_lines(&mut self, cx: &mut Cx, lines: f64, select: bool) {
+        let line_spacing = self.draw_text.get_line_spacing();
+        let boxes = self.draw_text.char_boxes(cx);
+        let (head, first) = match (self.cursor_pos(&boxes, self.cursor_head), self.cursor_pos(&boxes, 0)) {
+            (Some(head), Some(first)) => (head, first),
+            _ => return
+        };
+        let x = *self.cursor_x.get_or_insert(head.x);
+        let y = head.y + line_spacing * (lines + 0.5);
+        let pos = if y < first.y {
+            0
+        }
+        else if let Some(pos) = boxes.closest_offset(dvec2(x, y)) {
+            pos
+        }
+        else {
+            return
+        };
+        let cursor_x = self.cursor_x;
+        self.move_cursor_to(cx, pos, select);
+        self.cursor_x = cursor_x;
+    }
+    
+    // start or end of the visual line the cursor is on, in logical order so in right to left text
+    // the start is on the right
+    fn move_cursor_line_edge(&mut self, cx: &mut Cx, to_end: bool, select: bool) {
+        let char_count = self.char_count();
+        let pos = if self.multiline && self.cursor_head >= char_count && self.text.ends_with('\n') {
+            char_count
+        }
+        else if let Some((start, end)) = self.draw_text.get_line_edges(cx, self.cursor_head) {
+            if to_end {end} else {start}
+        }
+        else {
+            0
+        };
+        self.move_cursor_to(cx, pos, select);
+    }
+    
+    fn return_inserts_newline(&self, modifiers: &KeyModifiers) -> bool {
+        if !self.multiline {
+            return false
+        }
+        if self.submit_on_return {
+            modifiers.shift
+        }
+        else {
+            !modifiers.control && !modifiers.logo
+        }
+    }
+    
+    fn scroll_by(&mut self, cx: &mut Cx, delta: f64) {
+        self.scroll_y += delta;
+        self.draw_bg.redraw(cx);
+    }
+    
     pub fn handle_event(&mut self, cx: &mut Cx, event: &Event) -> Vec<TextInputAction> {
         let mut actions = Vec::new();
         self.handle_event_with(cx, event, &mut | _, a | actions.push(a));
@@ -325,6 +421,7 @@ impl TextInput {
         match event.hits(cx, self.draw_bg.area()) {
             Hit::KeyFocusLost(_) => {
                 self.animator_play(cx, id!(focus.off));
+                self.composition.clear();
                 cx.hide_text_ime();
                 dispatch_action(cx, TextInputAction::Return(self.text.clone()));
                 dispatch_action(cx, TextInputAction::KeyFocusLost);
@@ -339,7 +436,25 @@ impl TextInput {
                 self.draw_bg.redraw(cx);
                 dispatch_action(cx, TextInputAction::KeyFocus);
             }
+            Hit::TextComposition(tc) => {
+                if self.read_only || self.secret {
+                    return
+                }
+                // composing replaces the selection, like typing does
+                if tc.text.len() > 0 && self.cursor_head != self.cursor_tail {
+                    self.undo_id += 1;
+                    self.create_undo(UndoGroup::TextInput(self.undo_id));
+                    self.change(cx, "", dispatch_action);
+                }
+                self.composition_cursor = tc.cursor.min(tc.text.chars().count());
+                self.composition = tc.text;
+                self.cursor_into_view = true;
+                self.draw_bg.redraw(cx);
+            }
             Hit::TextInput(te) => {
+                self.composition.clear();
+                self.cursor_x = None;
+                self.cursor_into_view = true;
                 let mut input = String::new();
                 self.filter_input(&te.input, Some(&mut input));
                 if input.len() == 0 {
@@ -374,11 +489,20 @@ impl TextInput {
                     self.change(cx, "", dispatch_action);
                 }
             }
+            // the input method owns the keyboard while composing
+            Hit::KeyDown(_) if self.composition.len() > 0 => {}
             Hit::KeyDown(ke) => match ke.key_code {
                 
                 KeyCode::Tab => {
                     // dispatch_action(cx, self, TextInputAction::Tab(key.mod_shift));
                 }
+                KeyCode::ReturnKey if self.return_inserts_newline(&ke.modifiers) => {
+                    self.undo_id += 1;
+                    self.create_undo(UndoGroup::TextInput(self.undo_id));
+                    self.cursor_x = None;
+                    self.cursor_into_view = true;
+                    self.change(cx, "\n", dispatch_action);
+                }
                 KeyCode::ReturnKey => {
                     cx.hide_text_ime();
                     dispatch_action(cx, TextInputAction::Return(self.text.clone()));
@@ -407,70 +531,49 @@ impl TextInput {
                     self.draw_bg.redraw(cx);
                 }
                 KeyCode::ArrowLeft => if !ke.modifiers.logo {
-                    
-                    self.undo_id += 1;
-                    if self.cursor_head>0 {
-                        self.cursor_head -= 1;
-                    }
-                    if !ke.modifiers.shift {
-                        self.cursor_tail = self.cursor_head;
-                    }
-                    self.draw_bg.redraw(cx);
+                    self.cursor_x = None;
+                    let pos = self.draw_text.get_visual_cursor_move(cx, self.cursor_head, false)
+                        .unwrap_or(if self.cursor_head > 0 {self.cursor_head - 1} else {0});
+                    self.move_cursor_to(cx, pos, ke.modifiers.shift);
                 },
                 KeyCode::ArrowRight => if !ke.modifiers.logo {
-                    self.undo_id += 1;
-                    if self.cursor_head < self.text.chars().count() {
-                        self.cursor_head += 1;
-                    }
-                    if !ke.modifiers.shift {
-                        self.cursor_tail = self.cursor_head;
-                    }
-                    self.draw_bg.redraw(cx);
+                    self.cursor_x = None;
+                    let pos = self.draw_text.get_visual_cursor_move(cx, self.cursor_head, true)
+                        .unwrap_or(self.cursor_head + 1);
+                    self.move_cursor_to(cx, pos, ke.modifiers.shift);
                 }
                 KeyCode::ArrowDown => if !ke.modifiers.logo {
-                    self.undo_id += 1;
-                    // we need to figure out what is below our current cursor
-                    if let Some(pos) = self.draw_text.get_cursor_pos(cx, 0.0, self.cursor_head) {
-                        if let Some(pos) = self.draw_text.closest_offset(cx, dvec2(pos.x, pos.y + self.draw_text.get_line_spacing() * 1.5)) {
-                            self.cursor_head = pos;
-                            if !ke.modifiers.shift {
-                                self.cursor_tail = self.cursor_head;
-                            }
-                            self.draw_bg.redraw(cx);
-                        }
-                    }
+                    self.move_cursor_lines(cx, 1.0, ke.modifiers.shift);
                 },
                 KeyCode::ArrowUp => if !ke.modifiers.logo {
-                    self.undo_id += 1;
-                    // we need to figure out what is below our current cursor
-                    if let Some(pos) = self.draw_text.get_cursor_pos(cx, 0.0, self.cursor_head) {
-                        if let Some(pos) = self.draw_text.closest_offset(cx, dvec2(pos.x, pos.y - self.draw_text.get_line_spacing() * 0.5)) {
-                            self.cursor_head = pos;
-                            if !ke.modifiers.shift {
-                                self.cursor_tail = self.cursor_head;
-                            }
-                            self.draw_bg.redraw(cx);
-                        }
-                    }
+                    self.move_cursor_lines(cx, -1.0, ke.modifiers.shift);
                 },
+                KeyCode::PageDown | KeyCode::PageUp if self.multiline => {
+                    let lines = (self.draw_bg.area().get_rect(cx).size.y / self.draw_text.get_line_spacing()).floor().max(1.0);
+                    let lines = if ke.key_code == KeyCode::PageUp {-lines} else {lines};
+                    self.move_cursor_lines(cx, lines, ke.modifiers.shift);
+                }
                 KeyCode::Home => if !ke.modifiers.logo {
-                    self.undo_id += 1;
-                    self.cursor_head = 0;
-                    if !ke.modifiers.shift {
-                        self.cursor_tail = self.cursor_head;
+                    self.cursor_x = None;
+                    if self.multiline && !ke.modifiers.control {
+                        self.move_cursor_line_edge(cx, false, ke.modifiers.shift);
+                    }
+                    else {
+                        self.move_cursor_to(cx, 0, ke.modifiers.shift);
                     }
-                    self.draw_bg.redraw(cx);
                 }
                 KeyCode::End => if !ke.modifiers.logo {
-                    self.undo_id += 1;
-                    self.cursor_head = self.text.chars().count();
-                    
-                    if !ke.modifiers.shift {
-                        self.cursor_tail = self.cursor_head;
+                    self.cursor_x = None;
+                    if self.multiline && !ke.modifiers.control {
+                        self.move_cursor_line_edge(cx, true, ke.modifiers.shift);
+                    }
+                    else {
+                        self.move_cursor_to(cx, self.char_count(), ke.modifiers.shift);
                     }
-                    self.draw_bg.redraw(cx);
                 }
                 KeyCode::Backspace => {
+                    self.cursor_x = None;
+                    self.cursor_into_view = true;
                     self.create_undo(UndoGroup::Backspace(self.undo_id));
                     if self.cursor_head == self.cursor_tail {
                         if self.cursor_tail > 0 {
@@ -480,6 +583,8 @@ impl TextInput {
                     self.change(cx, "", dispatch_action);
                 }
                 KeyCode::Delete => {
+                    self.cursor_x = None;
+                    self.cursor_into_view = true;
                     self.create_undo(UndoGroup::Delete(self.undo_id));
                     if self.cursor_head == self.cursor_tail {
                         if self.cursor_head < self.text.chars().count() {
@@ -490,6 +595,9 @@ impl TextInput {
                 }
                 _ => ()
             }
+            Hit::FingerScroll(fs) if self.multiline => {
+                self.scroll_by(cx, fs.scroll.y);
+            }
             Hit::FingerHoverIn(_) => {
                 cx.set_cursor(MouseCursor::Text);
                 self.animator_play(cx, id!(hover.on));
@@ -500,6 +608,7 @@ impl TextInput {
             Hit::FingerDown(fe) => {
                 cx.set_cursor(MouseCursor::Text);
                 self.set_key_focus(cx);
+                self.cursor_x = None;
                 // ok so we need to calculate where we put the cursor down.
                 //elf.
                 if let Some(pos) = self.draw_text.closest_offset(cx, fe.abs) {
@@ -546,7 +655,8 @@ impl TextInput {
                 }
             }
             Hit::FingerMove(fe) => {
-                if let Some(pos) = self.draw_text.closest_offset(cx, fe.abs) {
+                let boxes = self.draw_text.char_boxes(cx);
+                if let Some(pos) = boxes.closest_offset(fe.abs) {
                     let pos = pos.min(self.text.chars().count());
                     if fe.tap_count == 2 {
                         let (head, tail) = self.double_tap_start.unwrap();
@@ -561,7 +671,7 @@ impl TextInput {
                         self.draw_bg.redraw(cx);
                     }
                     else if fe.tap_count == 1 {
-                        if let Some(pos_start) = self.draw_text.closest_offset(cx, fe.abs_start) {
+                        if let Some(pos_start) = boxes.closest_offset(fe.abs_start) {
                             let pos_start = pos_start.min(self.text.chars().count());
                             
                             self.cursor_head = pos_start;
@@ -580,29 +690,38 @@ impl TextInput {
     
     pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
         
-        self.draw_bg.begin(cx, walk, self.layout);
-        let turtle_rect = cx.turtle().rect();
+        let layout = if self.multiline {self.layout.with_scroll(dvec2(0.0, self.scroll_y))} else {self.layout};
+        self.draw_bg.begin(cx, walk, layout);
+        let turtle_rect = cx.turtle().unscrolled_rect();
         
         // this makes sure selection goes behind the text
         self.draw_select.append_to_draw_call(cx);
         
-        if self.text.len() == 0 {
+        let text_walk = if self.multiline {
+            self.draw_text.wrap = TextWrap::Word;
+            Walk::size(if walk.width.is_fit() {Size::Fit} else {Size::Fill}, Size::Fit)
+        }
+        else {
+            Walk::size(self.walk.width, self.walk.height)
+        };
+        
+        if self.text.len() == 0 && self.composition.len() == 0 {
             self.draw_text.is_empty = 1.0;
-            self.draw_text.draw_walk(cx, Walk::size(self.walk.width, self.walk.height), self.label_align, &self.empty_message);
+            self.draw_text.draw_walk(cx, text_walk, self.label_align, &self.empty_message);
         }
         else {
             self.draw_text.is_empty = 0.0;
             if self.secret {
-                self.draw_text.draw_walk(cx, Walk::size(
-                    self.walk.width,
-                    self.walk.height
-                ), self.label_align, &"*".repeat(self.text.len()));
+                self.draw_text.draw_walk(cx, text_walk, self.label_align, &"*".repeat(self.text.len()));
+            }
+            else if self.composition.len() > 0 {
+                let mut text = self.text.clone();
+                let byte_index = text.char_indices().nth(self.cursor_head).map(|(i, _)| i).unwrap_or(text.len());
+                text.insert_str(byte_index, &self.composition);
+                self.draw_text.draw_walk(cx, text_walk, self.label_align, &text);
             }
             else {
-                self.draw_text.draw_walk(cx, Walk::size(
-                    self.walk.width,
-                    self.walk.height
-                ), self.label_align, &self.text);
+                self.draw_text.draw_walk(cx, text_walk, self.label_align, &self.text);
             }
         }
         
@@ -612,14 +731,51 @@ impl TextInput {
         // move the IME
         let line_spacing = self.draw_text.get_line_spacing();
         let top_drop = self.draw_text.get_font_size() * 0.2;
-        let head = self.draw_text.get_cursor_pos(cx, 0.0, self.cursor_head)
-            .unwrap_or(dvec2(turtle.pos.x, 0.0));
+        let boxes = self.draw_text.char_boxes(cx);
+        let head = if self.composition.len() > 0 {
+            boxes.cursor_pos(0.0, self.cursor_head + self.composition_cursor)
+        }
+        else {
+            self.cursor_pos(&boxes, self.cursor_head)
+        }.unwrap_or(dvec2(turtle.pos.x, 0.0));
         
         if !self.read_only && self.cursor_head == self.cursor_tail {
-            self.draw_cursor.draw_abs(cx, Rect {
-                pos: dvec2(head.x - 0.5 * self.cursor_size, head.y - top_drop),
-                size: dvec2(self.cursor_size, line_spacing)
-            });
+            // between runs of different direction the cursor is split, the top half is where
+            // typed text of the run after it goes and the bottom half marks the end of the run before
+            let split = if self.composition.len() > 0 {
+                None
+            }
+            else {
+                boxes.split_cursor_pos(self.cursor_head)
+            };
+            if let Some(split) = split {
+                self.draw_cursor.draw_abs(cx, Rect {
+                    pos: dvec2(head.x - 0.5 * self.cursor_size, head.y - top_drop),
+                    size: dvec2(self.cursor_size, line_spacing * 0.5)
+                });
+                self.draw_cursor.draw_abs(cx, Rect {
+                    pos: dvec2(split.x - 0.5 * self.cursor_size, split.y - top_drop + line_spacing * 0.5),
+                    size: dvec2(self.cursor_size, line_spacing * 0.5)
+                });
+            }
+            else {
+                self.draw_cursor.draw_abs(cx, Rect {
+                    pos: dvec2(head.x - 0.5 * self.cursor_size, head.y - top_drop),
+                    size: dvec2(self.cursor_size, line_spacing)
+                });
+            }
+        }
+        
+        // underline the text that is still being composed
+        if self.composition.len() > 0 {
+            let start = self.cursor_head;
+            let end = start + self.composition.chars().count();
+            for rect in boxes.selection_rects(start, end, dvec2(0.0, 0.0), dvec2(0.0, 0.0)) {
+                self.draw_composition.draw_abs(cx, Rect {
+                    pos: dvec2(rect.pos.x, rect.pos.y - top_drop + line_spacing - 2.0),
+                    size: dvec2(rect.size.x, 1.0)
+                });
+            }
         }
         
         // draw selection rects
@@ -629,28 +785,65 @@ impl TextInput {
             let bottom_drop = self.draw_text.get_font_size() * 0.1;
             
             let (start, end) = self.sorted_cursor();
-            let rects = self.draw_text.get_selection_rects(cx, start, end, dvec2(0.0, -top_drop), dvec2(0.0, bottom_drop));
+            let rects = boxes.selection_rects(start, end, dvec2(0.0, -top_drop), dvec2(0.0, bottom_drop));
             for rect in rects {
                 self.draw_select.draw_abs(cx, rect);
             }
         }
         self.draw_bg.end(cx);
         
+        // keep the cursor in view and the scroll within the text, this lands on the next frame
+        let cursor_into_view = std::mem::take(&mut self.cursor_into_view);
+        if self.multiline {
+            let rect = self.draw_bg.area().get_rect(cx);
+            let visible_top = rect.pos.y + self.layout.padding.top;
+            let visible_height = rect.size.y - self.layout.padding.top - self.layout.padding.bottom;
+            let mut scroll_y = self.scroll_y;
+            if cursor_into_view {
+                let cursor_top = head.y - top_drop - visible_top;
+                if cursor_top < 0.0 {
+                    scroll_y += cursor_top;
+                }
+                else if cursor_top + line_spacing > visible_height {
+                    scroll_y += cursor_top + line_spacing - visible_height;
+                }
+            }
+            let content_height = match (self.cursor_pos(&boxes, 0), self.cursor_pos(&boxes, self.char_count())) {
+                (Some(first), Some(last)) => last.y - first.y + line_spacing,
+                _ => 0.0
+            };
+            let scroll_y = scroll_y.min(content_height - visible_height).max(0.0);
+            if scroll_y != self.scroll_y {
+                self.scroll_y = scroll_y;
+                self.draw_bg.redraw(cx);
+            }
+        }
+        
         if  cx.has_key_focus(self.draw_bg.area()) {
             // ok so. if we have the IME we should inject a tracking point
-            let ime_x = self.draw_text.get_cursor_pos(cx, 0.5, self.cursor_head)
+            let ime_x = boxes.cursor_pos(0.5, self.cursor_head)
                 .unwrap_or(dvec2(turtle.pos.x, 0.0)).x;
             
             if self.numeric_only {
                 cx.hide_text_ime();
             }
             else {
-                let ime_abs = dvec2(ime_x, turtle.pos.y);
-                cx.show_text_ime(self.draw_bg.area(), ime_abs - turtle_rect.pos);
+                let ime_y = if self.multiline {head.y} else {turtle.pos.y};
+                cx.show_text_ime(self.draw_bg.area(), dvec2(ime_x, ime_y) - turtle_rect.pos);
             }
         }
         
-        cx.add_nav_stop(self.draw_bg.area(), NavRole::TextInput, Margin::default())
+        if !self.nav_stop {
+            return
+        }
+        let access_value = if self.secret {"*".repeat(self.text.chars().count())} else {self.text.clone()};
+        cx.add_nav_stop_with_access(
+            self.draw_bg.area(),
+            NavRole::TextInput,
+            self.nav_order,
+            Margin::default(),
+            AccessInfo::named(&self.empty_message).with_value(&access_value).with_read_only(self.read_only)
+        )
     }
 }
 
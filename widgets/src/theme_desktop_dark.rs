@@ -661,9 +661,20 @@ live_design!{
     }
     
     NavControl = <NavControlBase> {
+        focus_margin: 2.0
         draw_focus: {
+            draw_depth: 10.0
             fn pixel(self) -> vec4 {
-                return #000f
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(
+                    1.,
+                    1.,
+                    self.rect_size.x - 2.0,
+                    self.rect_size.y - 2.0,
+                    3.
+                )
+                sdf.stroke(THEME_COLOR_MID, 1.5)
+                return sdf.result
             }
         }
         draw_text: {
@@ -674,6 +685,211 @@ live_design!{
         }
     }
     
+    PopupMenuItem = <PopupMenuItemBase> {
+        
+        align: {y: 0.5}
+        padding: {left: 15, top: 5, bottom: 5},
+        width: Fill,
+        height: Fit
+        
+        draw_name: {
+            text_style: <THEME_FONT_LABEL> {}
+            instance selected: 0.0
+            instance hover: 0.0
+            instance disabled: 0.0
+            fn get_color(self) -> vec4 {
+                return mix(
+                    mix(
+                        mix(
+                            THEME_COLOR_TEXT_DEFAULT,
+                            THEME_COLOR_TEXT_SELECTED,
+                            self.selected
+                        ),
+                        THEME_COLOR_TEXT_HOVER,
+                        self.hover
+                    ),
+                    THEME_COLOR_TEXT_META,
+                    self.disabled
+                )
+            }
+        }
+        
+        draw_shortcut: {
+            text_style: <THEME_FONT_LABEL> {}
+            wrap: Ellipsis
+            instance disabled: 0.0
+            fn get_color(self) -> vec4 {
+                return mix(THEME_COLOR_TEXT_DEFAULT, THEME_COLOR_TEXT_META, self.disabled)
+            }
+        }
+        
+        draw_bg: {
+            instance selected: 0.0
+            instance hover: 0.0
+            instance color: #0
+            instance color_selected: #4
+            
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                
+                sdf.clear(mix(
+                    self.color,
+                    self.color_selected,
+                    // THEME_COLOR_BG_EDITOR,
+                    // THEME_COLOR_BG_SELECTED,
+                    self.hover
+                ))
+                
+                //
+                // we have 3 points, and need to rotate around its center
+                let sz = 3.;
+                let dx = 2.0;
+                let c = vec2(8.0, 0.5 * self.rect_size.y);
+                sdf.move_to(c.x - sz + dx * 0.5, c.y - sz + dx);
+                sdf.line_to(c.x, c.y + sz);
+                sdf.line_to(c.x + sz, c.y - sz);
+                sdf.stroke(mix(#fff0, #f, self.selected), 1.0);
+                
+                return sdf.result;
+            }
+        }
+        
+        animator: {
+            hover = {
+                default: off
+                off = {
+                    from: {all: Snap}
+                    apply: {
+                        draw_bg: {hover: 0.0}
+                        draw_name: {hover: 0.0}
+                    }
+                }
+                on = {
+                    cursor: Hand
+                    from: {all: Snap}
+                    apply: {
+                        draw_bg: {hover: 1.0}
+                        draw_name: {hover: 1.0}
+                    }
+                }
+            }
+            
+            select = {
+                default: off
+                off = {
+                    from: {all: Snap}
+                    apply: {
+                        draw_bg: {selected: 0.0,}
+                        draw_name: {selected: 0.0,}
+                    }
+                }
+                on = {
+                    from: {all: Snap}
+                    apply: {
+                        draw_bg: {selected: 1.0,}
+                        draw_name: {selected: 1.0,}
+                    }
+                }
+            }
+        }
+        indent_width: 10.0
+    }
+    
+    PopupMenu = <PopupMenuBase> {
+        menu_item: <PopupMenuItem> {}
+        
+        flow: Down,
+        padding: 5
+        
+        
+        width: 100,
+        height: Fit
+        
+        line_walk: {width: Fill, height: 1, margin: {top: 3, bottom: 3, left: 5, right: 5}}
+        draw_line: {
+            fn pixel(self) -> vec4 {
+                return THEME_COLOR_UP_10
+            }
+        }
+        
+        draw_bg: {
+            instance color: #0
+            instance border_width: 0.0,
+            instance border_color: #0000,
+            instance inset: vec4(0.0, 0.0, 0.0, 0.0),
+            instance radius: 4.0
+            
+            fn get_color(self) -> vec4 {
+                return self.color
+            }
+            
+            fn get_border_color(self) -> vec4 {
+                return self.border_color
+            }
+            
+            fn pixel(self) -> vec4 {
+                
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size)
+                sdf.blur = 20.0;
+                sdf.box(
+                    self.inset.x + self.border_width,
+                    self.inset.y + self.border_width,
+                    self.rect_size.x - (self.inset.x + self.inset.z + self.border_width * 2.0),
+                    self.rect_size.y - (self.inset.y + self.inset.w + self.border_width * 2.0),
+                    max(1.0, self.radius)
+                )
+                sdf.fill_keep(self.get_color())
+                return sdf.result;
+            }
+        }
+    }
+    
+    WindowMenu = <WindowMenuBase> {
+        width: Fill,
+        height: Fit,
+        flow: Right,
+        padding: {left: 5}
+        
+        draw_bg: {
+            fn pixel(self) -> vec4 {
+                return THEME_COLOR_BG_HEADER
+            }
+        }
+        
+        item_walk: {width: Fit, height: Fit}
+        item_layout: {padding: {left: 8, right: 8, top: 5, bottom: 5}}
+        
+        draw_item: {
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(1., 1., self.rect_size.x - 2.0, self.rect_size.y - 2.0, 2.0);
+                sdf.fill(mix(
+                    mix(#0000, THEME_COLOR_CONTROL_HOVER, self.hover),
+                    THEME_COLOR_BG_SELECTED,
+                    self.active
+                ));
+                return sdf.result
+            }
+        }
+        
+        draw_text: {
+            text_style: <THEME_FONT_LABEL> {}
+            color: (THEME_COLOR_TEXT_DEFAULT)
+        }
+        
+        popup_menu: <PopupMenu> {
+            width: 220,
+            menu_item: <PopupMenuItem> {
+                padding: {left: 10, right: 10, top: 5, bottom: 5}
+                draw_bg: {
+                    fn pixel(self) -> vec4 {
+                        return mix(self.color, self.color_selected, max(self.hover, self.selected))
+                    }
+                }
+            }
+        }
+    }
+    
     DesktopWindow = <DesktopWindowBase> {
         pass: {clear_color: (THEME_COLOR_CLEAR)}
         flow: Down
@@ -712,6 +928,7 @@ live_design!{
                 xr_on = <DesktopButton> {draw_bg: {button_type: XRMode}}
             }
         }
+        window_menu = <WindowMenu> {}
         cursor: Default
         mouse_cursor_size: vec2(20, 20),
         draw_cursor: {
@@ -1045,148 +1262,6 @@ live_design!{
     }
     
     
-    
-    
-    PopupMenuItem = <PopupMenuItemBase> {
-        
-        align: {y: 0.5}
-        padding: {left: 15, top: 5, bottom: 5},
-        width: Fill,
-        height: Fit
-        
-        draw_name: {
-            text_style: <THEME_FONT_LABEL> {}
-            instance selected: 0.0
-            instance hover: 0.0
-            fn get_color(self) -> vec4 {
-                return mix(
-                    mix(
-                        THEME_COLOR_TEXT_DEFAULT,
-                        THEME_COLOR_TEXT_SELECTED,
-                        self.selected
-                    ),
-                    THEME_COLOR_TEXT_HOVER,
-                    self.hover
-                )
-            }
-        }
-        
-        draw_bg: {
-            instance selected: 0.0
-            instance hover: 0.0
-            instance color: #0
-            instance color_selected: #4
-            
-            fn pixel(self) -> vec4 {
-                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
-                
-                sdf.clear(mix(
-                    self.color,
-                    self.color_selected,
-                    // THEME_COLOR_BG_EDITOR,
-                    // THEME_COLOR_BG_SELECTED,
-                    self.hover
-                ))
-                
-                //
-                // we have 3 points, and need to rotate around its center
-                let sz = 3.;
-                let dx = 2.0;
-                let c = vec2(8.0, 0.5 * self.rect_size.y);
-                sdf.move_to(c.x - sz + dx * 0.5, c.y - sz + dx);
-                sdf.line_to(c.x, c.y + sz);
-                sdf.line_to(c.x + sz, c.y - sz);
-                sdf.stroke(mix(#fff0, #f, self.selected), 1.0);
-                
-                return sdf.result;
-            }
-        }
-        
-        animator: {
-            hover = {
-                default: off
-                off = {
-                    from: {all: Snap}
-                    apply: {
-                        draw_bg: {hover: 0.0}
-                        draw_name: {hover: 0.0}
-                    }
-                }
-                on = {
-                    cursor: Hand
-                    from: {all: Snap}
-                    apply: {
-                        draw_bg: {hover: 1.0}
-                        draw_name: {hover: 1.0}
-                    }
-                }
-            }
-            
-            select = {
-                default: off
-                off = {
-                    from: {all: Snap}
-                    apply: {
-                        draw_bg: {selected: 0.0,}
-                        draw_name: {selected: 0.0,}
-                    }
-                }
-                on = {
-                    from: {all: Snap}
-                    apply: {
-                        draw_bg: {selected: 1.0,}
-                        draw_name: {selected: 1.0,}
-                    }
-                }
-            }
-        }
-        indent_width: 10.0
-    }
-    
-    PopupMenu = <PopupMenuBase> {
-        menu_item: <PopupMenuItem> {}
-        
-        flow: Down,
-        padding: 5
-        
-        
-        width: 100,
-        height: Fit
-        
-        draw_bg: {
-            instance color: #0
-            instance border_width: 0.0,
-            instance border_color: #0000,
-            instance inset: vec4(0.0, 0.0, 0.0, 0.0),
-            instance radius: 4.0
-            
-            fn get_color(self) -> vec4 {
-                return self.color
-            }
-            
-            fn get_border_color(self) -> vec4 {
-                return self.border_color
-            }
-            
-            fn pixel(self) -> vec4 {
-                
-                let sdf = Sdf2d::viewport(self.pos * self.rect_size)
-                sdf.blur = 20.0;
-                sdf.box(
-                    self.inset.x + self.border_width,
-                    self.inset.y + self.border_width,
-                    self.rect_size.x - (self.inset.x + self.inset.z + self.border_width * 2.0),
-                    self.rect_size.y - (self.inset.y + self.inset.w + self.border_width * 2.0),
-                    max(1.0, self.radius)
-                )
-                sdf.fill_keep(self.get_color())
-                return sdf.result;
-            }
-        }
-    }
-    
-    
-    
     DropDown = <DropDownBase> {
         draw_text: {
             fn get_color(self) -> vec4 {
@@ -1883,6 +1958,397 @@ live_design!{
         flow: Down
     }
     
+    DataGrid = <DataGridBase> {
+        width: Fill
+        height: Fill
+        row_height: 24.0
+        header_height: 26.0
+        
+        scroll_bar_x: <ScrollBar> {}
+        scroll_bar_y: <ScrollBar> {}
+        
+        cell_layout: {padding: {left: 6, right: 6}, align: {y: 0.5}}
+        header_layout: {padding: {left: 6, right: 16}, align: {y: 0.5}}
+        
+        draw_bg: {
+            fn pixel(self) -> vec4 {
+                return THEME_COLOR_BG_EDITOR
+            }
+        }
+        
+        draw_cell: {
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.clear(mix(
+                    mix(THEME_COLOR_BG_EDITOR, THEME_COLOR_BG_ODD, self.odd),
+                    mix(THEME_COLOR_BG_UNFOCUSSED, THEME_COLOR_BG_SELECTED, self.focus),
+                    self.selected
+                ));
+                // grid lines on the right and bottom edge
+                sdf.rect(self.rect_size.x - 1.0, 0.0, 1.0, self.rect_size.y);
+                sdf.fill(THEME_COLOR_DOWN_10);
+                sdf.rect(0.0, self.rect_size.y - 1.0, self.rect_size.x, 1.0);
+                sdf.fill(THEME_COLOR_DOWN_10);
+                if self.cursor > 0.5 {
+                    sdf.rect(1.0, 1.0, self.rect_size.x - 2.0, self.rect_size.y - 2.0);
+                    sdf.stroke(THEME_COLOR_TEXT_SELECTED, 1.0);
+                }
+                return sdf.result
+            }
+        }
+        
+        draw_header: {
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.clear(mix(
+                    mix(THEME_COLOR_BG_HEADER, THEME_COLOR_CONTROL_HOVER, self.hover),
+                    THEME_COLOR_CONTROL_PRESSED,
+                    self.dragging
+                ));
+                sdf.rect(self.rect_size.x - 1.0, 3.0, 1.0, self.rect_size.y - 6.0);
+                sdf.fill(THEME_COLOR_DOWN_20);
+                // the sort arrow points up when ascending
+                if abs(self.sort) > 0.5 {
+                    let c = vec2(self.rect_size.x - 9.0, self.rect_size.y * 0.5);
+                    let sz = 3.0;
+                    sdf.move_to(c.x - sz, c.y + sz * 0.5 * self.sort);
+                    sdf.line_to(c.x + sz, c.y + sz * 0.5 * self.sort);
+                    sdf.line_to(c.x, c.y - sz * 0.5 * self.sort);
+                    sdf.close_path();
+                    sdf.fill(THEME_COLOR_TEXT_DEFAULT);
+                }
+                return sdf.result
+            }
+        }
+        
+        draw_text: {
+            wrap: Ellipsis
+            text_style: <THEME_FONT_LABEL> {}
+            color: (THEME_COLOR_TEXT_DEFAULT)
+        }
+        
+        draw_header_text: {
+            wrap: Ellipsis
+            text_style: <THEME_FONT_LABEL> {}
+            color: (THEME_COLOR_TEXT_HOVER)
+        }
+        
+        draw_drop: {
+            fn pixel(self) -> vec4 {
+                return THEME_COLOR_UP_50
+            }
+        }
+    }
+    
+    Modal = <ModalBase> {
+        width: 400
+        height: Fit
+        flow: Down
+        spacing: 10
+        padding: 20
+        placement: {x: 0.5, y: 0.5}
+        
+        show_bg: true
+        draw_bg: {
+            instance radius: 4.0
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(1.0, 1.0, self.rect_size.x - 2.0, self.rect_size.y - 2.0, self.radius);
+                sdf.fill_keep(THEME_COLOR_BG_HEADER);
+                sdf.stroke(THEME_COLOR_UP_10, 1.0);
+                return sdf.result
+            }
+        }
+        
+        draw_backdrop: {
+            fn pixel(self) -> vec4 {
+                return THEME_COLOR_DOWN_50
+            }
+        }
+    }
+    
+    Tooltip = <TooltipBase> {
+        width: Fit
+        height: Fit
+        padding: {left: 8, right: 8, top: 5, bottom: 5}
+        delay: 0.5
+        offset: 4.0
+        screen_margin: 4.0
+        
+        draw_bg: {
+            instance radius: 3.0
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(1.0, 1.0, self.rect_size.x - 2.0, self.rect_size.y - 2.0, self.radius);
+                sdf.fill_keep(THEME_COLOR_BG_EDITOR);
+                sdf.stroke(THEME_COLOR_UP_15, 1.0);
+                return sdf.result
+            }
+        }
+        
+        draw_text: {
+            wrap: Line
+            text_style: <THEME_FONT_LABEL> {}
+            color: (THEME_COLOR_TEXT_HOVER)
+        }
+    }
+    
+    Toast = <ToastBase> {
+        flow: Down
+        spacing: 8
+        padding: 16
+        align: {x: 1.0, y: 1.0}
+        duration: 4.0
+        fade_duration: 0.25
+        max_toasts: 5
+        
+        item_walk: {width: 280, height: Fit}
+        item_layout: {padding: {left: 14, right: 10, top: 10, bottom: 10}}
+        
+        draw_bg: {
+            instance radius: 4.0
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(1.0, 1.0, self.rect_size.x - 2.0, self.rect_size.y - 2.0, self.radius);
+                sdf.fill_keep(mix(THEME_COLOR_BG_HEADER, THEME_COLOR_CONTROL_HOVER, self.hover));
+                sdf.stroke(THEME_COLOR_UP_10, 1.0);
+                // a stripe on the left tells info, success, warning and error apart
+                let accent = THEME_COLOR_UP_50;
+                if self.kind > 2.5 {
+                    accent = THEME_COLOR_HIGH;
+                }
+                else if self.kind > 1.5 {
+                    accent = THEME_COLOR_MID;
+                }
+                else if self.kind > 0.5 {
+                    accent = THEME_COLOR_LOW;
+                }
+                sdf.box(1.0, 1.0, 4.0, self.rect_size.y - 2.0, 1.0);
+                sdf.fill(accent);
+                return sdf.result * (1.0 - self.fade)
+            }
+        }
+        
+        draw_text: {
+            wrap: Word
+            text_style: <THEME_FONT_LABEL> {}
+            color: (THEME_COLOR_TEXT_HOVER)
+        }
+    }    
+    
+    Markdown = <MarkdownBase> {
+        width: Fill
+        height: Fit
+        flow: Down
+        spacing: 10
+        heading_scale: 1.8
+        list_indent: 20
+        quote_indent: 14
+        quote_bar_width: 3
+        inline_code_padding: 3
+        code_layout: {padding: {left: 10, right: 10, top: 8, bottom: 8}}
+        
+        draw_normal: {
+            text_style: <THEME_FONT_LABEL> {}
+            color: (THEME_COLOR_TEXT_DEFAULT)
+        }
+        
+        // there is no italic face, so the glyphs are slanted around the baseline instead
+        draw_italic: {
+            text_style: <THEME_FONT_LABEL> {}
+            color: (THEME_COLOR_TEXT_DEFAULT)
+            fn vertex(self) -> vec4 {
+                let min_pos = vec2(self.rect_pos.x, self.rect_pos.y)
+                let max_pos = vec2(self.rect_pos.x + self.rect_size.x, self.rect_pos.y - self.rect_size.y)
+                
+                self.clipped = clamp(
+                    mix(min_pos, max_pos, self.geom_pos),
+                    self.draw_clip.xy,
+                    self.draw_clip.zw
+                )
+                
+                let normalized: vec2 = (self.clipped - min_pos) / vec2(self.rect_size.x, -self.rect_size.y)
+                self.tex_coord1 = mix(self.font_t1.xy, self.font_t2.xy, normalized.xy)
+                self.tex_coord2 = mix(self.font_t1.xy, self.font_t1.xy + (self.font_t2.xy - self.font_t1.xy) * 0.75, normalized.xy)
+                self.tex_coord3 = mix(self.font_t1.xy, self.font_t1.xy + (self.font_t2.xy - self.font_t1.xy) * 0.6, normalized.xy)
+                
+                let baseline = self.rect_pos.y - self.delta.y + self.font_size
+                return self.camera_projection * (self.camera_view * (self.view_transform * vec4(
+                    self.clipped.x + (baseline - self.clipped.y) * 0.2,
+                    self.clipped.y,
+                    self.char_depth + self.draw_zbias,
+                    1.
+                )))
+            }
+        }
+        
+        draw_bold: {
+            text_style: <THEME_FONT_LABEL> {
+                font: {path: dep("crate://self/resources/IBMPlexSans-SemiBold.ttf")}
+            }
+            color: (THEME_COLOR_TEXT_HOVER)
+        }
+        
+        draw_bold_italic: {
+            text_style: <THEME_FONT_LABEL> {
+                font: {path: dep("crate://self/resources/IBMPlexSans-SemiBold.ttf")}
+            }
+            color: (THEME_COLOR_TEXT_HOVER)
+            fn vertex(self) -> vec4 {
+                let min_pos = vec2(self.rect_pos.x, self.rect_pos.y)
+                let max_pos = vec2(self.rect_pos.x + self.rect_size.x, self.rect_pos.y - self.rect_size.y)
+                
+                self.clipped = clamp(
+                    mix(min_pos, max_pos, self.geom_pos),
+                    self.draw_clip.xy,
+                    self.draw_clip.zw
+                )
+                
+                let normalized: vec2 = (self.clipped - min_pos) / vec2(self.rect_size.x, -self.rect_size.y)
+                self.tex_coord1 = mix(self.font_t1.xy, self.font_t2.xy, normalized.xy)
+                self.tex_coord2 = mix(self.font_t1.xy, self.font_t1.xy + (self.font_t2.xy - self.font_t1.xy) * 0.75, normalized.xy)
+                self.tex_coord3 = mix(self.font_t1.xy, self.font_t1.xy + (self.font_t2.xy - self.font_t1.xy) * 0.6, normalized.xy)
+                
+                let baseline = self.rect_pos.y - self.delta.y + self.font_size
+                return self.camera_projection * (self.camera_view * (self.view_transform * vec4(
+                    self.clipped.x + (baseline - self.clipped.y) * 0.2,
+                    self.clipped.y,
+                    self.char_depth + self.draw_zbias,
+                    1.
+                )))
+            }
+        }
+        
+        draw_fixed: {
+            text_style: <THEME_FONT_CODE> {line_spacing: 1.4}
+            color: (THEME_COLOR_TEXT_HOVER)
+        }
+        
+        draw_link: {
+            text_style: <THEME_FONT_LABEL> {}
+            fn get_color(self) -> vec4 {
+                return mix(#x6CB4EE, #x9CD0FF, self.hover)
+            }
+        }
+        
+        draw_link_line: {
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.move_to(0., self.rect_size.y - 1.0);
+                sdf.line_to(self.rect_size.x, self.rect_size.y - 1.0);
+                return sdf.stroke(#x9CD0FF, mix(0.0, 0.8, self.hover));
+            }
+        }
+        
+        draw_code_bg: {
+            color: (THEME_COLOR_BG_EDITOR)
+        }
+        
+        draw_quote_bar: {
+            color: (THEME_COLOR_UP_25)
+        }
+        
+        draw_rule: {
+            color: (THEME_COLOR_UP_15)
+        }
+        
+        draw_strike: {
+            color: (THEME_COLOR_TEXT_DEFAULT)
+        }
+    }
+    
+    Chart = <ChartBase> {
+        width: Fill
+        height: 240
+        plot_padding: {left: 4, top: 8, right: 12, bottom: 4}
+        y_axis_width: 48
+        x_axis_height: 22
+        
+        draw_bg: {
+            color: (THEME_COLOR_BG_EDITOR)
+        }
+        
+        draw_grid: {
+            color: (THEME_COLOR_UP_4)
+        }
+        
+        draw_axis: {
+            color: (THEME_COLOR_UP_25)
+        }
+        
+        draw_crosshair: {
+            color: (THEME_COLOR_UP_50)
+        }
+        
+        draw_readout: {
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(1.0, 1.0, self.rect_size.x - 2.0, self.rect_size.y - 2.0, 3.0);
+                sdf.fill_keep(THEME_COLOR_BG_HEADER);
+                sdf.stroke(THEME_COLOR_UP_10, 1.0);
+                return sdf.result
+            }
+        }
+        
+        draw_label: {
+            text_style: <THEME_FONT_DATA> {font_size: 8.0}
+            color: (THEME_COLOR_TEXT_META)
+        }
+        
+        draw_readout_text: {
+            text_style: <THEME_FONT_DATA> {font_size: 8.5}
+            color: (THEME_COLOR_TEXT_DEFAULT)
+        }
+        
+        draw_line: {
+            line_width: 1.5
+            fn pixel(self) -> vec4 {
+                let p = self.pos * self.rect_size + self.rect_pos;
+                let pa = p - self.p1;
+                let ba = self.p2 - self.p1;
+                let h = clamp(dot(pa, ba) / max(dot(ba, ba), 0.0001), 0.0, 1.0);
+                let d = length(pa - ba * h);
+                let alpha = clamp(self.line_width * 0.5 - d + 0.5, 0.0, 1.0) * self.color.w;
+                return vec4(self.color.xyz * alpha, alpha);
+            }
+        }
+        
+        draw_area: {
+            fn pixel(self) -> vec4 {
+                let p = self.pos * self.rect_size + self.rect_pos;
+                let t = clamp((p.x - self.p1.x) / max(self.p2.x - self.p1.x, 0.0001), 0.0, 1.0);
+                let y = mix(self.p1.y, self.p2.y, t);
+                let top = min(y, self.base);
+                let bottom = max(y, self.base);
+                let alpha = clamp(p.y - top + 0.5, 0.0, 1.0) * clamp(bottom - p.y + 0.5, 0.0, 1.0) * self.color.w;
+                return vec4(self.color.xyz * alpha, alpha);
+            }
+        }
+        
+        draw_bar: {
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(0.5, 0.0, self.rect_size.x - 1.0, self.rect_size.y, min(2.0, 0.5 * self.rect_size.x));
+                sdf.fill(self.color);
+                return sdf.result
+            }
+        }
+        
+        draw_point: {
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                let r = 0.5 * min(self.rect_size.x, self.rect_size.y);
+                sdf.circle(0.5 * self.rect_size.x, 0.5 * self.rect_size.y, r - 0.5);
+                sdf.fill(self.color);
+                return sdf.result
+            }
+        }
+    }
+    
+    Svg = <SvgBase> {
+        width: Fit
+        height: Fit
+    }
     
     CachedScrollXY = <CachedView> {
         scroll_bars: <ScrollBars> {show_scroll_x: true, show_scroll_y: true}
@@ -1959,6 +2425,10 @@ live_design!{
             }
         }
         
+        draw_composition: {
+            color: #xFFFFFFCC
+        }
+        
         cursor_margin_bottom: 3.0,
         cursor_margin_top: 4.0,
         select_pad_edges: 3.0
@@ -2124,6 +2594,7 @@ live_design!{
             cursor_size: 2.0,
             empty_message: "0",
             numeric_only: true,
+            nav_stop: false,
             draw_bg: {
                 shape: None
                 color: #5
@@ -0,0 +1,312 @@
+use crate::{
+    makepad_derive_widget::*,
+    makepad_draw::*,
+    widget::*,
+};
+
+live_design!{
+    DrawToast = {{DrawToast}} {}
+    ToastBase = {{Toast}} {}
+}
+
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawToast {
+    #[deref] draw_super: DrawQuad,
+    #[live] kind: f32,
+    #[live] hover: f32,
+    #[live] fade: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToastKind {
+    #[default] Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl ToastKind {
+    fn shader_value(&self) -> f32 {
+        match self {
+            Self::Info => 0.0,
+            Self::Success => 1.0,
+            Self::Warning => 2.0,
+            Self::Error => 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ToastId(pub u64);
+
+struct ToastItem {
+    id: ToastId,
+    kind: ToastKind,
+    text: String,
+    duration: f64,
+    timer: Timer,
+    hover: bool,
+    closing: bool,
+    fade_start: Option<f64>,
+    fade: f64,
+    area: Area,
+}
+
+// a stack of notifications in a corner of the window, each one goes away by itself after a while.
+// hovering a toast keeps it up, clicking it dismisses it right away
+#[derive(Live)]
+pub struct Toast {
+    #[live] draw_list: DrawList2d,
+    #[live] draw_bg: DrawToast,
+    #[live] draw_text: DrawText,
+    #[live] item_walk: Walk,
+    #[live] item_layout: Layout,
+    #[layout] layout: Layout,
+    #[live(4.0)] duration: f64,
+    #[live(0.25)] fade_duration: f64,
+    #[live] max_toasts: usize,
+    #[rust] area: Area,
+    #[rust] items: Vec<ToastItem>,
+    #[rust] last_id: u64,
+    #[rust] next_frame: NextFrame,
+}
+
+impl LiveHook for Toast {
+    fn before_live_design(cx: &mut Cx) {
+        register_widget!(cx, Toast)
+    }
+}
+
+#[derive(Clone, WidgetAction)]
+pub enum ToastAction {
+    Clicked(ToastId),
+    Dismissed(ToastId),
+    None
+}
+
+impl Widget for Toast {
+    fn handle_widget_event_with(
+        &mut self,
+        cx: &mut Cx,
+        event: &Event,
+        dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)
+    ) {
+        let uid = self.widget_uid();
+        self.handle_event_with(cx, event, &mut | cx, action | {
+            dispatch_action(cx, WidgetActionItem::new(action.into(), uid));
+        });
+    }
+    
+    fn walk(&mut self, _cx: &mut Cx) -> Walk {
+        Walk::default()
+    }
+    
+    fn redraw(&mut self, cx: &mut Cx) {
+        self.area.redraw(cx);
+        self.draw_list.redraw(cx);
+    }
+    
+    fn draw_walk_widget(&mut self, cx: &mut Cx2d, walk: Walk) -> WidgetDraw {
+        self.draw_walk(cx, walk);
+        WidgetDraw::done()
+    }
+}
+
+impl Toast {
+    pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, ToastAction)) {
+        if self.items.is_empty() {
+            return
+        }
+        if let Some(ne) = self.next_frame.is_event(event) {
+            let fade_duration = self.fade_duration.max(0.001);
+            let mut animating = false;
+            for item in &mut self.items {
+                if item.closing {
+                    let start = *item.fade_start.get_or_insert(ne.time);
+                    item.fade = ((ne.time - start) / fade_duration).min(1.0);
+                    animating = true;
+                }
+            }
+            self.items.retain( | item | {
+                if item.fade >= 1.0 {
+                    dispatch_action(cx, ToastAction::Dismissed(item.id));
+                    return false
+                }
+                true
+            });
+            if animating {
+                self.next_frame = cx.new_next_frame();
+            }
+            self.redraw(cx);
+        }
+        
+        let mut start_closing = false;
+        for item in &mut self.items {
+            if item.timer.is_event(event) {
+                item.timer = Timer::empty();
+                item.closing = true;
+                start_closing = true;
+                continue
+            }
+            if item.closing {
+                continue
+            }
+            match event.hits(cx, item.area) {
+                Hit::FingerHoverIn(_) => {
+                    cx.set_cursor(MouseCursor::Hand);
+                    cx.stop_timer(item.timer);
+                    item.timer = Timer::empty();
+                    item.hover = true;
+                    item.area.redraw(cx);
+                }
+                Hit::FingerHoverOut(_) => {
+                    if item.duration > 0.0 {
+                        item.timer = cx.start_timeout(item.duration);
+                    }
+                    item.hover = false;
+                    item.area.redraw(cx);
+                }
+                Hit::FingerUp(fe) if fe.is_over && fe.was_tap() => {
+                    dispatch_action(cx, ToastAction::Clicked(item.id));
+                    cx.stop_timer(item.timer);
+                    item.timer = Timer::empty();
+                    item.closing = true;
+                    start_closing = true;
+                }
+                _ => ()
+            }
+        }
+        if start_closing {
+            self.next_frame = cx.new_next_frame();
+        }
+    }
+    
+    pub fn show(&mut self, cx: &mut Cx, kind: ToastKind, text: &str) -> ToastId {
+        self.show_with_duration(cx, kind, text, self.duration)
+    }
+    
+    // a duration of zero keeps the toast up until it is clicked or dismissed from code
+    pub fn show_with_duration(&mut self, cx: &mut Cx, kind: ToastKind, text: &str, duration: f64) -> ToastId {
+        self.last_id += 1;
+        let id = ToastId(self.last_id);
+        let timer = if duration > 0.0 {cx.start_timeout(duration)} else {Timer::empty()};
+        self.items.push(ToastItem {
+            id,
+            kind,
+            text: text.to_string(),
+            duration,
+            timer,
+            hover: false,
+            closing: false,
+            fade_start: None,
+            fade: 0.0,
+            area: Area::Empty,
+        });
+        // the oldest toasts make room when the stack is full
+        if self.max_toasts > 0 {
+            let open = self.items.iter().filter( | item | !item.closing).count();
+            for item in self.items.iter_mut().filter( | item | !item.closing).take(open.saturating_sub(self.max_toasts)) {
+                cx.stop_timer(item.timer);
+                item.timer = Timer::empty();
+                item.closing = true;
+            }
+            self.next_frame = cx.new_next_frame();
+        }
+        self.redraw(cx);
+        id
+    }
+    
+    pub fn dismiss(&mut self, cx: &mut Cx, id: ToastId) {
+        if let Some(item) = self.items.iter_mut().find( | item | item.id == id && !item.closing) {
+            cx.stop_timer(item.timer);
+            item.timer = Timer::empty();
+            item.closing = true;
+            self.next_frame = cx.new_next_frame();
+        }
+    }
+    
+    pub fn dismiss_all(&mut self, cx: &mut Cx) {
+        for item in &mut self.items {
+            cx.stop_timer(item.timer);
+            item.timer = Timer::empty();
+            item.closing = true;
+        }
+        if !self.items.is_empty() {
+            self.next_frame = cx.new_next_frame();
+        }
+    }
+    
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, _walk: Walk) {
+        cx.walk_turtle_with_area(&mut self.area, Walk::fixed(0.0, 0.0).with_abs_pos(dvec2(0.0, 0.0)));
+        if self.items.is_empty() {
+            return
+        }
+        self.draw_list.begin_overlay_last(cx);
+        cx.begin_pass_sized_turtle(Layout::flow_down());
+        cx.begin_turtle(Walk::fill(), self.layout);
+        for item in &mut self.items {
+            self.draw_bg.kind = item.kind.shader_value();
+            self.draw_bg.hover = if item.hover {1.0} else {0.0};
+            self.draw_bg.fade = item.fade as f32;
+            self.draw_bg.begin(cx, self.item_walk, self.item_layout);
+            self.draw_text.draw_walk(cx, Walk::fill_fit(), Align::default(), &item.text);
+            self.draw_bg.end(cx);
+            item.area = self.draw_bg.area();
+        }
+        cx.end_turtle();
+        cx.end_pass_sized_turtle();
+        self.draw_list.end(cx);
+    }
+}
+
+#[derive(Clone, Default, PartialEq, WidgetRef)]
+pub struct ToastRef(WidgetRef);
+
+impl ToastRef {
+    pub fn show(&self, cx: &mut Cx, kind: ToastKind, text: &str) -> Option<ToastId> {
+        if let Some(mut inner) = self.borrow_mut() {
+            return Some(inner.show(cx, kind, text))
+        }
+        None
+    }
+    
+    pub fn show_with_duration(&self, cx: &mut Cx, kind: ToastKind, text: &str, duration: f64) -> Option<ToastId> {
+        if let Some(mut inner) = self.borrow_mut() {
+            return Some(inner.show_with_duration(cx, kind, text, duration))
+        }
+        None
+    }
+    
+    pub fn dismiss(&self, cx: &mut Cx, id: ToastId) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.dismiss(cx, id);
+        }
+    }
+    
+    pub fn dismiss_all(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.dismiss_all(cx);
+        }
+    }
+    
+    pub fn clicked(&self, actions: &WidgetActions) -> Option<ToastId> {
+        if let Some(item) = actions.find_single_action(self.widget_uid()) {
+            if let ToastAction::Clicked(id) = item.action() {
+                return Some(id)
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Default, WidgetSet)]
+pub struct ToastSet(WidgetSet);
+
+impl ToastSet {
+    pub fn dismiss_all(&self, cx: &mut Cx) {
+        for item in self.iter() {
+            item.dismiss_all(cx);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    makepad_derive_widget::*,
+    makepad_draw::*,
+    widget::*,
+};
+
+live_design!{
+    TooltipBase = {{Tooltip}} {}
+}
+
+// a small text bubble that shows up after the pointer rested on an anchor area for a while.
+// it is placed below the anchor, or above it when there is no room, and kept inside the window
+#[derive(Live)]
+pub struct Tooltip {
+    #[live] draw_list: DrawList2d,
+    #[live] draw_bg: DrawQuad,
+    #[live] draw_text: DrawText,
+    #[walk] walk: Walk,
+    #[layout] layout: Layout,
+    #[live(0.5)] delay: f64,
+    #[live(4.0)] offset: f64,
+    #[live(4.0)] screen_margin: f64,
+    #[rust] area: Area,
+    #[rust] text: String,
+    #[rust] anchor: Area,
+    #[rust] anchor_rect: Rect,
+    #[rust] timer: Timer,
+    #[rust] visible: bool,
+}
+
+impl LiveHook for Tooltip {
+    fn before_live_design(cx: &mut Cx) {
+        register_widget!(cx, Tooltip)
+    }
+}
+
+#[derive(Clone, WidgetAction)]
+pub enum TooltipAction {
+    None
+}
+
+impl Widget for Tooltip {
+    fn handle_widget_event_with(
+        &mut self,
+        cx: &mut Cx,
+        event: &Event,
+        _dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)
+    ) {
+        self.handle_event(cx, event);
+    }
+    
+    fn walk(&mut self, _cx: &mut Cx) -> Walk {
+        self.walk
+    }
+    
+    fn redraw(&mut self, cx: &mut Cx) {
+        self.area.redraw(cx);
+        self.draw_list.redraw(cx);
+    }
+    
+    fn draw_walk_widget(&mut self, cx: &mut Cx2d, walk: Walk) -> WidgetDraw {
+        self.draw_walk(cx, walk);
+        WidgetDraw::done()
+    }
+}
+
+impl Tooltip {
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &Event) {
+        if self.anchor.is_empty() {
+            return
+        }
+        if self.timer.is_event(event) {
+            self.timer = Timer::empty();
+            self.visible = true;
+            self.redraw(cx);
+            return
+        }
+        match event {
+            Event::MouseMove(e) if !self.anchor_rect.contains(e.abs) => self.hide(cx),
+            Event::MouseDown(_) | Event::TouchUpdate(_) | Event::Scroll(_) | Event::KeyDown(_) => self.hide(cx),
+            _ => ()
+        }
+    }
+    
+    // shows the tooltip for an area while the mouse is over it, call this from handle_event
+    pub fn handle_hover(&mut self, cx: &mut Cx, event: &Event, area: Area, text: &str) {
+        if let Event::MouseMove(e) = event {
+            if self.anchor != area && area.get_clipped_rect(cx).contains(e.abs) {
+                self.show(cx, area, text);
+            }
+        }
+    }
+    
+    pub fn show(&mut self, cx: &mut Cx, anchor: Area, text: &str) {
+        self.text.clear();
+        self.text.push_str(text);
+        self.anchor = anchor;
+        self.anchor_rect = anchor.get_clipped_rect(cx);
+        cx.stop_timer(self.timer);
+        // moving from one tooltip straight to the next skips the delay
+        if self.visible || self.delay <= 0.0 {
+            self.timer = Timer::empty();
+            self.visible = true;
+            self.redraw(cx);
+        }
+        else {
+            self.timer = cx.start_timeout(self.delay);
+        }
+    }
+    
+    pub fn hide(&mut self, cx: &mut Cx) {
+        cx.stop_timer(self.timer);
+        self.timer = Timer::empty();
+        self.anchor = Area::Empty;
+        if self.visible {
+            self.visible = false;
+            self.redraw(cx);
+        }
+    }
+    
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    
+    fn place(&self, pass_size: DVec2, size: DVec2) -> DVec2 {
+        let anchor = self.anchor_rect;
+        let margin = self.screen_margin;
+        let mut pos = dvec2(
+            anchor.pos.x + 0.5 * (anchor.size.x - size.x),
+            anchor.pos.y + anchor.size.y + self.offset
+        );
+        if pos.y + size.y > pass_size.y - margin {
+            pos.y = anchor.pos.y - self.offset - size.y;
+        }
+        pos.x = pos.x.min(pass_size.x - margin - size.x).max(margin);
+        pos.y = pos.y.min(pass_size.y - margin - size.y).max(margin);
+        pos
+    }
+    
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
+        cx.walk_turtle_with_area(&mut self.area, Walk::fixed(0.0, 0.0).with_abs_pos(dvec2(0.0, 0.0)));
+        if !self.visible {
+            return
+        }
+        self.draw_list.begin_overlay_last(cx);
+        cx.begin_pass_sized_turtle(Layout::flow_down());
+        
+        self.draw_bg.begin(cx, walk, self.layout);
+        self.draw_text.draw_walk(cx, Walk::fit(), Align::default(), &self.text);
+        self.draw_bg.end(cx);
+        
+        // the size is only known after drawing, so the whole layer is shifted into place
+        let size = self.draw_bg.area().get_rect(cx).size;
+        let pos = self.place(cx.current_pass_size(), size);
+        cx.end_pass_sized_turtle_with_shift(Area::Empty, pos);
+        self.draw_list.end(cx);
+    }
+}
+
+#[derive(Clone, Default, PartialEq, WidgetRef)]
+pub struct TooltipRef(WidgetRef);
+
+impl TooltipRef {
+    pub fn show(&self, cx: &mut Cx, anchor: Area, text: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.show(cx, anchor, text);
+        }
+    }
+    
+    pub fn hide(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.hide(cx);
+        }
+    }
+    
+    pub fn handle_hover(&self, cx: &mut Cx, event: &Event, area: Area, text: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.handle_hover(cx, event, area, text);
+        }
+    }
+    
+    pub fn is_visible(&self) -> bool {
+        if let Some(inner) = self.borrow() {
+            return inner.is_visible()
+        }
+        false
+    }
+}
+
+#[derive(Clone, Default, WidgetSet)]
+pub struct TooltipSet(WidgetSet);
+
+impl TooltipSet {
+    pub fn hide(&self, cx: &mut Cx) {
+        for item in self.iter() {
+            item.hide(cx);
+        }
+    }
+}
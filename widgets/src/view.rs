@@ -239,6 +239,12 @@ impl ViewRef {
         }
     }
     
+    pub fn animator_play_delayed(&self, cx: &mut Cx, state: &[LiveId; 2], delay: f64) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.animator_play_delayed(cx, state, delay);
+        }
+    }
+    
     pub fn toggle_state(&self, cx: &mut Cx, is_state_1: bool, animate: Animate, state1: &[LiveId; 2], state2: &[LiveId; 2]) {
         if let Some(mut inner) = self.borrow_mut() {
             inner.animator_toggle(cx, is_state_1, animate, state1, state2);
@@ -319,6 +325,13 @@ impl ViewSet {
         }
     }
     
+    // plays the state on each view in turn, every next one starting stagger seconds later
+    pub fn animator_play_staggered(&mut self, cx: &mut Cx, state: &[LiveId; 2], stagger: f64) {
+        for (i, item) in self.iter().enumerate() {
+            item.animator_play_delayed(cx, state, i as f64 * stagger);
+        }
+    }
+    
     pub fn toggle_state(&mut self, cx: &mut Cx, is_state_1: bool, animate: Animate, state1: &[LiveId; 2], state2: &[LiveId; 2]) {
         for item in self.iter() {
             item.toggle_state(cx, is_state_1, animate, state1, state2);
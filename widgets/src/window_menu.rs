@@ -1,30 +1,537 @@
-// a window menu implementation
-use crate::makepad_platform::*;
+// the in-window menubar for platforms without a native one (Linux and Windows)
+use {
+    crate::{
+        makepad_derive_widget::*,
+        popup_menu::{PopupMenu, PopupMenuAction},
+        makepad_draw::*,
+        widget::*,
+    }
+};
 
 live_design!{
+    DrawWindowMenuItem = {{DrawWindowMenuItem}} {}
+    WindowMenuBase = {{WindowMenu}} {}
+}
+
+#[derive(Live, LiveHook)]#[repr(C)]
+struct DrawWindowMenuItem {
+    #[deref] draw_super: DrawQuad,
+    #[live] hover: f32,
+    #[live] active: f32,
 }
 
-#[derive(Clone)]
+#[derive(Live)]
 pub struct WindowMenu {
+    #[live] draw_bg: DrawQuad,
+    #[live] draw_item: DrawWindowMenuItem,
+    #[live] draw_text: DrawText,
+    
+    #[walk] walk: Walk,
+    #[layout] layout: Layout,
+    #[live] item_walk: Walk,
+    #[live] item_layout: Layout,
+    
+    #[live] popup_menu: Option<LivePtr>,
+    
+    #[rust] menu: Option<Menu>,
+    #[rust] bar_rects: Vec<(usize, Rect)>,
+    #[rust] popups: ComponentMap<usize, PopupMenu>,
+    
+    // the open bar item, the submenus opened below it and the highlight in the deepest one
+    #[rust] open_bar: Option<usize>,
+    #[rust] open_subs: Vec<usize>,
+    #[rust] highlight: Option<usize>,
+    #[rust] hover_bar: Option<usize>,
+    #[rust] alt_tap: bool,
+}
+
+impl LiveHook for WindowMenu {
+    fn before_live_design(cx: &mut Cx) {
+        register_widget!(cx, WindowMenu)
+    }
+    
+    fn after_apply(&mut self, cx: &mut Cx, from: ApplyFrom, _index: usize, _nodes: &[LiveNode]) {
+        // when live styling drop the popups so they get rebuilt from the new style
+        if !from.is_from_doc() {
+            return
+        }
+        self.popups.clear();
+        self.draw_bg.redraw(cx);
+    }
+}
+
+#[derive(Clone, WidgetAction)]
+pub enum WindowMenuAction {
+    None
 }
 
-#[derive(Clone)]
-pub enum WindowMenuEvent {
-    SelectItem {
-    },
-    None,
+// the character after '&' is the mnemonic, otherwise the first one
+fn mnemonic(name: &str) -> Option<char> {
+    name.find('&')
+        .and_then( | i | name[i + 1..].chars().next())
+        .or_else( || name.chars().next())
+        .map( | c | c.to_ascii_lowercase())
+}
+
+fn display_name(name: &str) -> String {
+    name.replacen('&', "", 1)
+}
+
+fn shortcut_label(setting: &CxCommandSetting) -> String {
+    let key = match setting.key_code {
+        KeyCode::Unknown => return String::new(),
+        key_code => if let Some(c) = key_code.to_char(true) {
+            c.to_string()
+        }
+        else {
+            format!("{:?}", key_code)
+        }
+    };
+    if setting.shift {
+        format!("Ctrl+Shift+{}", key)
+    }
+    else {
+        format!("Ctrl+{}", key)
+    }
+}
+
+// the editing keys a focused widget such as a text input handles itself, with
+// another widget focused these don't run the menu command with the same key
+fn is_editing_key(key_code: KeyCode, shift: bool) -> bool {
+    match key_code {
+        KeyCode::KeyA | KeyCode::KeyC | KeyCode::KeyX | KeyCode::KeyV | KeyCode::KeyY => !shift,
+        KeyCode::KeyZ => true,
+        _ => false
+    }
+}
+
+fn is_selectable(item: &Menu) -> bool {
+    matches!(item, Menu::Item {..} | Menu::Sub {..})
 }
 
 impl WindowMenu {
-    pub fn new(_cx: &mut Cx) -> Self {
-        Self {
+    
+    fn bar_items(&self) -> &[Menu] {
+        match &self.menu {
+            Some(Menu::Main {items}) => items,
+            _ => &[]
         }
     }
     
-    pub fn handle_window_menu(&mut self, _cx: &mut Cx, _event: &Event, _menu: &Menu) -> WindowMenuEvent {
-        WindowMenuEvent::None
+    // the items shown in the popup at this cascade level
+    fn level_items(&self, level: usize) -> &[Menu] {
+        let mut items = match self.open_bar.and_then( | bar | self.bar_items().get(bar)) {
+            Some(Menu::Sub {items, ..}) => items.as_slice(),
+            _ => return &[]
+        };
+        for sub in &self.open_subs[0..level.min(self.open_subs.len())] {
+            items = match items.get(*sub) {
+                Some(Menu::Sub {items, ..}) => items,
+                _ => return &[]
+            };
+        }
+        items
     }
     
-    pub fn draw_window_menu(&mut self, _cx: &mut Cx, _menu: &Menu) {
+    fn find_command(items: &[Menu], cx: &Cx, key_code: KeyCode, shift: bool) -> Option<MenuCommand> {
+        for item in items {
+            match item {
+                Menu::Item {command, ..} => {
+                    let setting = command.setting(cx);
+                    if setting.enabled && setting.key_code == key_code && setting.shift == shift {
+                        return Some(*command)
+                    }
+                }
+                Menu::Sub {items, ..} | Menu::Main {items} => {
+                    if let Some(command) = Self::find_command(items, cx, key_code, shift) {
+                        return Some(command)
+                    }
+                }
+                Menu::Line => ()
+            }
+        }
+        None
+    }
+    
+    fn redraw_all(&mut self, cx: &mut Cx) {
+        self.draw_bg.redraw(cx);
+        for (_, popup) in self.popups.iter_mut() {
+            popup.redraw(cx);
+        }
+    }
+    
+    fn open_bar(&mut self, cx: &mut Cx, index: usize, highlight_first: bool) {
+        match self.bar_items().get(index) {
+            Some(Menu::Item {command, ..}) => {
+                let command = *command;
+                self.close(cx);
+                if command.setting(cx).enabled {
+                    cx.send_menu_command(command);
+                }
+                return
+            }
+            Some(Menu::Sub {..}) => (),
+            _ => return
+        }
+        if self.open_bar.is_none() {
+            cx.sweep_lock(self.draw_bg.area());
+            cx.set_key_focus(self.draw_bg.area());
+        }
+        self.open_bar = Some(index);
+        self.open_subs.clear();
+        self.highlight = if highlight_first {self.level_items(0).iter().position(is_selectable)} else {None};
+        self.redraw_all(cx);
+    }
+    
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.open_bar.is_none() {
+            return
+        }
+        self.open_bar = None;
+        self.open_subs.clear();
+        self.highlight = None;
+        cx.sweep_unlock(self.draw_bg.area());
+        if cx.has_key_focus(self.draw_bg.area()) {
+            cx.revert_key_focus();
+        }
+        self.redraw_all(cx);
+    }
+    
+    // moves to the next Sub on the bar, skipping top level commands
+    fn step_bar(&mut self, cx: &mut Cx, forward: bool) {
+        let len = self.bar_items().len();
+        let mut index = self.open_bar.unwrap_or(0);
+        for _ in 0..len {
+            index = if forward {(index + 1) % len} else {(index + len - 1) % len};
+            if let Some(Menu::Sub {..}) = self.bar_items().get(index) {
+                self.open_bar(cx, index, true);
+                return
+            }
+        }
+    }
+    
+    fn step_highlight(&mut self, cx: &mut Cx, forward: bool) {
+        let items = self.level_items(self.open_subs.len());
+        let len = items.len();
+        let mut index = match self.highlight {
+            Some(index) => index,
+            None if forward => len.saturating_sub(1),
+            None => 0
+        };
+        for _ in 0..len {
+            index = if forward {(index + 1) % len} else {(index + len - 1) % len};
+            if is_selectable(&items[index]) {
+                self.highlight = Some(index);
+                self.redraw_all(cx);
+                return
+            }
+        }
+    }
+    
+    // runs the highlighted item of the deepest popup, or opens it when its a submenu
+    fn activate(&mut self, cx: &mut Cx, from_keyboard: bool) {
+        let index = if let Some(index) = self.highlight {index} else {return};
+        match self.level_items(self.open_subs.len()).get(index) {
+            Some(Menu::Item {command, ..}) => {
+                let command = *command;
+                if command.setting(cx).enabled {
+                    self.close(cx);
+                    cx.send_menu_command(command);
+                }
+            }
+            Some(Menu::Sub {..}) => {
+                self.open_subs.push(index);
+                self.highlight = if from_keyboard {
+                    self.level_items(self.open_subs.len()).iter().position(is_selectable)
+                }
+                else {
+                    None
+                };
+                self.redraw_all(cx);
+            }
+            _ => ()
+        }
+    }
+    
+    fn hover_item(&mut self, cx: &mut Cx, level: usize, index: usize) {
+        // hovering the submenu thats already open keeps it open
+        if self.open_subs.get(level) == Some(&index) {
+            return
+        }
+        self.open_subs.truncate(level);
+        self.highlight = Some(index);
+        if let Some(Menu::Sub {..}) = self.level_items(level).get(index) {
+            self.activate(cx, false);
+        }
+        self.redraw_all(cx);
+    }
+    
+    fn bar_index_at(&self, abs: DVec2) -> Option<usize> {
+        self.bar_rects.iter().find( | (_, rect) | rect.contains(abs)).map( | (index, _) | *index)
+    }
+    
+    pub fn handle_event_with(&mut self, cx: &mut Cx, event: &Event, _dispatch_action: &mut dyn FnMut(&mut Cx, WindowMenuAction)) {
+        if self.menu.is_none() {
+            return
+        }
+        
+        // accelerators, alt-mnemonics and the alt tap work regardless of key focus,
+        // except for editing keys the focused widget handles
+        match event {
+            Event::KeyDown(ke) if ke.key_code == KeyCode::Alt => {
+                if !ke.is_repeat {
+                    self.alt_tap = true;
+                }
+            }
+            Event::KeyDown(ke) => {
+                self.alt_tap = false;
+                if ke.modifiers.control && !ke.modifiers.alt {
+                    let focus = cx.key_focus();
+                    let focus_handles_key = !focus.is_empty() && focus != self.draw_bg.area()
+                        && is_editing_key(ke.key_code, ke.modifiers.shift);
+                    if !focus_handles_key {
+                        if let Some(command) = Self::find_command(self.bar_items(), cx, ke.key_code, ke.modifiers.shift) {
+                            self.close(cx);
+                            cx.send_menu_command(command);
+                        }
+                    }
+                }
+                else if ke.modifiers.alt && !ke.modifiers.control {
+                    if let Some(c) = ke.key_code.to_char(false) {
+                        if let Some(index) = self.bar_items().iter().position( | item | match item {
+                            Menu::Sub {name, ..} | Menu::Item {name, ..} => mnemonic(name) == Some(c),
+                            _ => false
+                        }) {
+                            self.open_bar(cx, index, true);
+                        }
+                    }
+                }
+            }
+            Event::KeyUp(ke) if ke.key_code == KeyCode::Alt && self.alt_tap => {
+                self.alt_tap = false;
+                if self.open_bar.is_some() {
+                    self.close(cx);
+                }
+                else if let Some(index) = self.bar_items().iter().position( | item | matches!(item, Menu::Sub {..})) {
+                    self.open_bar(cx, index, true);
+                }
+            }
+            Event::MouseDown(_) => {
+                self.alt_tap = false;
+            }
+            _ => ()
+        }
+        
+        if self.open_bar.is_some() {
+            let mut actions = Vec::new();
+            for level in 0..=self.open_subs.len() {
+                if let Some(popup) = self.popups.get_mut(&level) {
+                    popup.handle_event_with(cx, event, self.draw_bg.area(), &mut | _, action | actions.push((level, action)));
+                }
+            }
+            for (level, action) in actions {
+                match action {
+                    PopupMenuAction::WasHovered(item_id) => {
+                        self.hover_item(cx, level, item_id.0.0 as usize);
+                    }
+                    PopupMenuAction::WasSelected(item_id) => {
+                        self.open_subs.truncate(level);
+                        self.highlight = Some(item_id.0.0 as usize);
+                        self.activate(cx, false);
+                    }
+                    _ => ()
+                }
+            }
+            
+            // check if we clicked outside of the bar and the popups
+            if let Event::MouseDown(e) = event {
+                let in_popup = (0..=self.open_subs.len()).any( | level | {
+                    self.popups.get(&level).map_or(false, | popup | popup.menu_contains_pos(cx, e.abs))
+                });
+                if !in_popup && !self.draw_bg.area().get_clipped_rect(cx).contains(e.abs) {
+                    self.close(cx);
+                }
+            }
+        }
+        
+        match event.hits_with_sweep_area(cx, self.draw_bg.area(), self.draw_bg.area()) {
+            Hit::KeyFocusLost(_) => {
+                self.close(cx);
+            }
+            Hit::KeyDown(ke) if self.open_bar.is_some() => match ke.key_code {
+                KeyCode::ArrowDown => self.step_highlight(cx, true),
+                KeyCode::ArrowUp => self.step_highlight(cx, false),
+                KeyCode::ArrowRight => {
+                    let deepest = self.level_items(self.open_subs.len());
+                    if matches!(self.highlight.and_then( | index | deepest.get(index)), Some(Menu::Sub {..})) {
+                        self.activate(cx, true);
+                    }
+                    else {
+                        self.step_bar(cx, true);
+                    }
+                }
+                KeyCode::ArrowLeft => {
+                    if let Some(index) = self.open_subs.pop() {
+                        self.highlight = Some(index);
+                        self.redraw_all(cx);
+                    }
+                    else {
+                        self.step_bar(cx, false);
+                    }
+                }
+                KeyCode::ReturnKey | KeyCode::NumpadEnter | KeyCode::Space => {
+                    self.activate(cx, true);
+                }
+                KeyCode::Escape => {
+                    if let Some(index) = self.open_subs.pop() {
+                        self.highlight = Some(index);
+                        self.redraw_all(cx);
+                    }
+                    else {
+                        self.close(cx);
+                    }
+                }
+                key_code if !ke.modifiers.alt && !ke.modifiers.control && !ke.modifiers.logo => {
+                    // a plain letter picks the item with that mnemonic in the deepest popup
+                    if let Some(c) = key_code.to_char(false) {
+                        if let Some(index) = self.level_items(self.open_subs.len()).iter().position( | item | match item {
+                            Menu::Sub {name, ..} | Menu::Item {name, ..} => mnemonic(name) == Some(c),
+                            _ => false
+                        }) {
+                            self.highlight = Some(index);
+                            self.activate(cx, true);
+                        }
+                    }
+                }
+                _ => ()
+            }
+            Hit::FingerDown(fe) => {
+                if let Some(index) = self.bar_index_at(fe.abs) {
+                    if self.open_bar == Some(index) {
+                        self.close(cx);
+                    }
+                    else {
+                        self.open_bar(cx, index, false);
+                    }
+                }
+            }
+            Hit::FingerHoverIn(fe) | Hit::FingerHoverOver(fe) => {
+                let index = self.bar_index_at(fe.abs);
+                if index != self.hover_bar {
+                    self.hover_bar = index;
+                    self.draw_bg.redraw(cx);
+                }
+                // while a menu is open hovering the bar switches between menus
+                if let Some(index) = index {
+                    if self.open_bar.is_some() && self.open_bar != Some(index) {
+                        if let Some(Menu::Sub {..}) = self.bar_items().get(index) {
+                            self.open_bar(cx, index, false);
+                        }
+                    }
+                    cx.set_cursor(MouseCursor::Hand);
+                }
+            }
+            Hit::FingerHoverOut(_) => {
+                if self.hover_bar.is_some() {
+                    self.hover_bar = None;
+                    self.draw_bg.redraw(cx);
+                }
+            }
+            _ => ()
+        }
+    }
+    
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
+        // the menu comes from Cx::update_menu, on macOS it goes to the native menubar instead
+        if self.menu.as_ref() != cx.window_menu() {
+            self.close(cx);
+            self.menu = cx.window_menu().cloned();
+        }
+        let bar_items = match &self.menu {
+            Some(Menu::Main {items}) => items.clone(),
+            _ => return
+        };
+        
+        self.draw_bg.begin(cx, walk, self.layout);
+        self.bar_rects.clear();
+        let mut open_area = Area::Empty;
+        for (index, item) in bar_items.iter().enumerate() {
+            let name = match item {
+                Menu::Sub {name, ..} | Menu::Item {name, ..} => name,
+                _ => continue
+            };
+            self.draw_item.hover = if self.hover_bar == Some(index) {1.0} else {0.0};
+            self.draw_item.active = if self.open_bar == Some(index) {1.0} else {0.0};
+            self.draw_item.begin(cx, self.item_walk, self.item_layout);
+            self.draw_text.draw_walk(cx, Walk::fit(), Align::default(), &display_name(name));
+            self.draw_item.end(cx);
+            if self.open_bar == Some(index) {
+                open_area = self.draw_item.area();
+            }
+            self.bar_rects.push((index, self.draw_item.area().get_rect(cx)));
+        }
+        self.draw_bg.end(cx);
+        
+        if self.open_bar.is_none() || self.popup_menu.is_none() {
+            return
+        }
+        
+        // the popups cascade, each one anchored to the opened item of the one before it
+        let mut anchor = open_area;
+        let mut shift = dvec2(0.0, open_area.get_rect(cx).size.y);
+        let popup_menu = self.popup_menu;
+        for level in 0..=self.open_subs.len() {
+            let items = self.level_items(level).to_vec();
+            let highlight = if level < self.open_subs.len() {Some(self.open_subs[level])} else {self.highlight};
+            let popup = self.popups.get_or_insert(cx, level, | cx | {
+                PopupMenu::new_from_ptr(cx, popup_menu)
+            });
+            if let Some(index) = highlight {
+                popup.init_select_item(LiveId(index as u64).into());
+            }
+            popup.begin(cx);
+            for (index, item) in items.iter().enumerate() {
+                let item_id = LiveId(index as u64).into();
+                match item {
+                    Menu::Item {name, command} => {
+                        let setting = command.setting(cx);
+                        popup.draw_item_with_shortcut(cx, item_id, &display_name(name), &shortcut_label(&setting), setting.enabled);
+                    }
+                    Menu::Sub {name, ..} => {
+                        popup.draw_item_with_shortcut(cx, item_id, &display_name(name), ">", true);
+                    }
+                    Menu::Line => {
+                        popup.draw_separator(cx);
+                    }
+                    Menu::Main {..} => ()
+                }
+            }
+            popup.end(cx, anchor, shift);
+            if let Some(index) = self.open_subs.get(level) {
+                anchor = popup.item_area(LiveId(*index as u64).into());
+                shift = dvec2(anchor.get_rect(cx).size.x, 0.0);
+            }
+        }
+        self.popups.retain_visible();
+    }
+}
+
+impl Widget for WindowMenu {
+    fn redraw(&mut self, cx: &mut Cx) {
+        self.draw_bg.redraw(cx);
+    }
+    
+    fn handle_widget_event_with(&mut self, cx: &mut Cx, event: &Event, dispatch_action: &mut dyn FnMut(&mut Cx, WidgetActionItem)) {
+        let uid = self.widget_uid();
+        self.handle_event_with(cx, event, &mut | cx, action | {
+            dispatch_action(cx, WidgetActionItem::new(action.into(), uid))
+        });
+    }
+    
+    fn walk(&mut self, _cx:&mut Cx) -> Walk {self.walk}
+    
+    fn draw_walk_widget(&mut self, cx: &mut Cx2d, walk: Walk) -> WidgetDraw {
+        self.draw_walk(cx, walk);
+        WidgetDraw::done()
     }
 }